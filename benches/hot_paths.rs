@@ -0,0 +1,128 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Benchmarks of the hot paths of a Replica: the validation, registration and propagation
+//! of a transfer, which verify the signatures of the sender and of the groups, the replay
+//! of a million events, of credits alone, and of credits and debits as loaded from a
+//! history, and the queries of a wallet with a long history.
+//!
+//! Run with `cargo bench --features bench-utils`. Regressions are reported against a
+//! baseline saved with `-- --save-baseline <name>` and compared with `-- --baseline <name>`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use safe_nd::Money;
+use safe_transfers::{
+    BenchNetwork, ReplicaEvent, TransferReplica, VerificationCache, MAX_PAGE_SIZE,
+};
+
+/// The seed of the keys, so that runs verify the same signatures.
+const SEED: u64 = 0;
+/// The Replicas of the group, as many as the Elders of a section.
+const REPLICAS: usize = 7;
+/// The events replayed.
+const REPLAY_EVENTS: usize = 1_000_000;
+/// The wallets credited by the events replayed.
+const REPLAY_WALLETS: usize = 1_000;
+/// The credits to the wallet queried.
+const LARGE_WALLET_CREDITS: usize = 100_000;
+
+fn transfers(c: &mut Criterion) {
+    let network = BenchNetwork::new(REPLICAS, 1, SEED);
+    // no outcome is cached, so that every iteration verifies the signatures
+    let mut replica = network
+        .replica(0)
+        .with_verification_cache(VerificationCache::new(0));
+    let credit = network.credit(0, 0, Money::from_nano(1_000));
+    let debit = network.debit(0, 0, Money::from_nano(10));
+    let debit_proof = network.agree(debit.clone());
+
+    c.bench_function("receive_propagated", |b| {
+        b.iter(|| replica.receive_propagated(black_box(&credit)).unwrap())
+    });
+    let propagated = replica.receive_propagated(&credit).unwrap();
+    replica.apply(ReplicaEvent::TransferPropagated(propagated));
+
+    c.bench_function("validate", |b| {
+        b.iter(|| replica.validate(black_box(debit.clone())).unwrap())
+    });
+    let validated = replica.validate(debit).unwrap();
+    replica.apply(ReplicaEvent::TransferValidated(validated));
+
+    c.bench_function("register", |b| {
+        b.iter(|| replica.register(black_box(&debit_proof)).unwrap())
+    });
+}
+
+fn replay(c: &mut Criterion) {
+    let network = BenchNetwork::new(REPLICAS, REPLAY_WALLETS, SEED);
+    let mut group = c.benchmark_group("replay");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(REPLAY_EVENTS as u64));
+    // the events are built before each iteration, so that only their replay is timed
+    group.bench_function("apply", |b| {
+        b.iter_batched(
+            || {
+                network
+                    .propagated_credits(REPLAY_EVENTS)
+                    .collect::<Vec<_>>()
+            },
+            |events| {
+                TransferReplica::from_history_iter(
+                    network.replicas.secret_key_share(0),
+                    0,
+                    network.replicas.public_keys(),
+                    events.into_iter().map(Ok),
+                )
+                .unwrap()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("history", |b| {
+        b.iter_batched(
+            || network.replayed_history(REPLAY_EVENTS).collect::<Vec<_>>(),
+            |events| {
+                TransferReplica::from_history(
+                    network.replicas.secret_key_share(0),
+                    0,
+                    network.replicas.public_keys(),
+                    events,
+                )
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn large_wallet(c: &mut Criterion) {
+    let network = BenchNetwork::new(REPLICAS, 1, SEED);
+    let mut replica = network.replica(0);
+    for event in network.propagated_credits(LARGE_WALLET_CREDITS) {
+        replica.apply(event);
+    }
+    let wallet = network.wallet(0);
+    let payer = network.payer.public_key();
+    let mut group = c.benchmark_group("large_wallet");
+    group.bench_function("balance", |b| {
+        b.iter(|| replica.balance(black_box(&wallet)))
+    });
+    group.bench_function("credits_page", |b| {
+        b.iter(|| replica.credits_page(black_box(&wallet), LARGE_WALLET_CREDITS / 2, MAX_PAGE_SIZE))
+    });
+    group.bench_function("credits_from", |b| {
+        b.iter(|| replica.credits_from(black_box(&wallet), &payer))
+    });
+    group.bench_function("history_root", |b| {
+        b.iter(|| replica.history_root(black_box(&wallet)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, transfers, replay, large_wallet);
+criterion_main!(benches);
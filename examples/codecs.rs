@@ -0,0 +1,67 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A micro-benchmark of the codecs of the signed payloads: the time to encode
+//! each payload, and its size, in each codec compiled in.
+//!
+//! Run with `cargo run --release --example codecs -- <iterations>`,
+//! e.g. `cargo run --release --features postcard --example codecs -- 1000000`.
+
+use crdts::Dot;
+use safe_nd::{DebitAgreementProof, Money, PublicKey, Signature, SignedTransfer, Transfer};
+use safe_transfers::{Codec, Payload};
+use std::{env, time::Instant};
+use threshold_crypto::SecretKey;
+
+const DEFAULT_ITERATIONS: usize = 100_000;
+
+fn main() {
+    let iterations: usize = env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS)
+        .max(1);
+
+    let secret_key = SecretKey::random();
+    let transfer = Transfer {
+        id: Dot::new(PublicKey::from(secret_key.public_key()), 0),
+        to: PublicKey::from(SecretKey::random().public_key()),
+        amount: Money::from_nano(1),
+    };
+    let signed_transfer = SignedTransfer {
+        transfer: transfer.clone(),
+        actor_signature: Signature::Bls(secret_key.sign(b"codecs")),
+    };
+    let debit_proof = DebitAgreementProof {
+        signed_transfer: signed_transfer.clone(),
+        debiting_replicas_sig: Signature::Bls(SecretKey::random().sign(b"codecs")),
+    };
+    let payloads = [
+        ("transfer", Payload::Transfer(&transfer)),
+        ("proof", Payload::Proof(&signed_transfer)),
+        ("credit", Payload::Credit(&debit_proof)),
+    ];
+
+    for (name, payload) in &payloads {
+        for codec in Codec::compiled() {
+            let size = codec.encode(*payload).map(|bytes| bytes.len()).unwrap_or(0);
+            let started = Instant::now();
+            for _ in 0..iterations {
+                let _ = codec.encode(*payload);
+            }
+            let elapsed = started.elapsed();
+            println!(
+                "{:>8} {:>9}: {:>4} bytes, {:>6} ns/op",
+                name,
+                codec.name(),
+                size,
+                elapsed.as_nanos() / iterations as u128
+            );
+        }
+    }
+}
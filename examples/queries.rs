@@ -0,0 +1,103 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A benchmark of the latency of queries of a Replica holding a wallet with a long
+//! history: the balance, the latest credits and debits, full histories, and the same
+//! answered by reference (see Replica::query_history), which copies none of the history.
+//!
+//! Run with `cargo run --release --example queries -- <entries> <runs>`,
+//! e.g. `cargo run --release --example queries -- 100000 100`.
+
+use crdts::Dot;
+use safe_nd::{Money, PublicKey, Transfer};
+use safe_transfers::{Account, TransferReplica};
+use std::{collections::HashMap, env, time::Instant};
+use threshold_crypto::{SecretKey, SecretKeySet};
+
+const DEFAULT_ENTRIES: usize = 100_000;
+const DEFAULT_RUNS: usize = 100;
+
+fn main() {
+    let mut args = env::args().skip(1).map(|arg| arg.parse().ok());
+    let entries: usize = args.next().flatten().unwrap_or(DEFAULT_ENTRIES).max(2);
+    let runs: usize = args.next().flatten().unwrap_or(DEFAULT_RUNS).max(1);
+
+    let mut rng = rand::thread_rng();
+    let secret_key_set = SecretKeySet::random(0, &mut rng);
+    let (payer, id) = (random_pk(), random_pk());
+    // half the entries credits, the other half debits of one nano each
+    let mut account = Account::new(id);
+    for counter in 0..entries / 2 {
+        account.append(Transfer {
+            id: Dot::new(payer, counter as u64),
+            to: id,
+            amount: Money::from_nano(2),
+        });
+    }
+    for counter in 0..entries / 2 {
+        account.append(Transfer {
+            id: Dot::new(id, counter as u64),
+            to: payer,
+            amount: Money::from_nano(1),
+        });
+    }
+    let mut accounts = HashMap::new();
+    let _ = accounts.insert(id, account);
+    let replica = TransferReplica::from_snapshot(
+        secret_key_set.secret_key_share(0),
+        0,
+        secret_key_set.public_keys(),
+        Default::default(),
+        accounts,
+        Default::default(),
+    );
+    let latest = entries / 2 - 10;
+
+    println!("{} entries, {} runs each", entries, runs);
+    time("balance", runs, || replica.balance(&id).map_or(0, |_| 1));
+    time("last 10 credits", runs, || {
+        replica.credits_since(&id, latest).map_or(0, |c| c.len())
+    });
+    time("last 10 debits", runs, || {
+        replica.debits_since(&id, latest).map_or(0, |d| d.len())
+    });
+    time("all credits", runs, || {
+        replica.credits_since(&id, 0).map_or(0, |c| c.len())
+    });
+    time("all credits, by reference", runs, || {
+        replica
+            .query_history(&id, |history| history.credits_iter(0).count())
+            .unwrap_or(0)
+    });
+    time("sum of debits, by reference", runs, || {
+        replica
+            .query_history(&id, |history| {
+                history
+                    .debits_iter(0)
+                    .map(|debit| debit.amount.as_nano() as usize)
+                    .sum()
+            })
+            .unwrap_or(0)
+    });
+}
+
+/// Runs the query the number of times, and prints the mean latency.
+/// The results are summed, so that the queries are not optimised away.
+fn time<F: Fn() -> usize>(name: &str, runs: usize, query: F) {
+    let started = Instant::now();
+    let mut results = 0;
+    for _ in 0..runs {
+        results += query();
+    }
+    let mean = started.elapsed() / runs as u32;
+    println!("{:>30}: {:>10.1?} ({} results)", name, mean, results);
+}
+
+fn random_pk() -> PublicKey {
+    PublicKey::from(SecretKey::random().public_key())
+}
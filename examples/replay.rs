@@ -0,0 +1,104 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A micro-benchmark of the replay of a Replica history:
+//! credits to a number of accounts, each followed by a debit of it.
+//!
+//! Run with `cargo run --release --example replay -- <events> <accounts>`,
+//! e.g. `cargo run --release --example replay -- 1000000 10000`.
+//! Debug builds cross-check every balance, and are much slower.
+
+use crdts::Dot;
+use safe_nd::{
+    DebitAgreementProof, Money, PublicKey, Signature, SignatureShare, SignedTransfer, Transfer,
+    TransferPropagated, TransferRegistered,
+};
+use safe_transfers::{ReplicaEvent, TransferReplica};
+use std::{env, time::Instant};
+use threshold_crypto::{SecretKey, SecretKeySet};
+
+const DEFAULT_EVENTS: usize = 100_000;
+const DEFAULT_ACCOUNTS: usize = 1_000;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let events: usize = args
+        .next()
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_EVENTS);
+    let accounts: usize = args
+        .next()
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_ACCOUNTS)
+        .max(1);
+
+    let secret_key_set = SecretKeySet::random(0, &mut rand::thread_rng());
+    let group = secret_key_set.public_keys();
+    let keys: Vec<PublicKey> = (0..accounts)
+        .map(|_| PublicKey::from(SecretKey::random().public_key()))
+        .collect();
+    let history = history(events, &keys, &secret_key_set);
+
+    let started = Instant::now();
+    let replica =
+        TransferReplica::from_history(secret_key_set.secret_key_share(0), 0, group, history);
+    let elapsed = started.elapsed();
+
+    let per_sec = events as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    println!(
+        "replayed {} events of {} accounts in {:?} ({:.0} events/s)",
+        events, accounts, elapsed, per_sec
+    );
+    assert!(events == 0 || replica.balance(&keys[0]).is_some());
+}
+
+/// Signatures are not verified when replaying, so one of each kind is reused.
+fn history(events: usize, keys: &[PublicKey], secret_key_set: &SecretKeySet) -> Vec<ReplicaEvent> {
+    let debiting_replicas = PublicKey::from(secret_key_set.public_keys().public_key());
+    let group_sig = Signature::Bls(secret_key_set.secret_key().sign(b"replay"));
+    let crediting_replica_sig = SignatureShare {
+        index: 0,
+        share: secret_key_set.secret_key_share(0).sign(b"replay"),
+    };
+    let actor_sig = group_sig.clone();
+    let genesis = PublicKey::from(SecretKey::random().public_key());
+    let mut debit_counters = vec![0; keys.len()];
+
+    let mut history = Vec::with_capacity(events);
+    for index in 0..events {
+        let account = index / 2 % keys.len();
+        let (id, to) = if index % 2 == 0 {
+            (Dot::new(genesis, index as u64), keys[account])
+        } else {
+            let counter = debit_counters[account];
+            debit_counters[account] += 1;
+            (Dot::new(keys[account], counter), genesis)
+        };
+        let debit_proof = DebitAgreementProof {
+            signed_transfer: SignedTransfer {
+                transfer: Transfer {
+                    id,
+                    to,
+                    amount: Money::from_nano(1),
+                },
+                actor_signature: actor_sig.clone(),
+            },
+            debiting_replicas_sig: group_sig.clone(),
+        };
+        history.push(if index % 2 == 0 {
+            ReplicaEvent::TransferPropagated(TransferPropagated {
+                debit_proof,
+                debiting_replicas,
+                crediting_replica_sig: crediting_replica_sig.clone(),
+            })
+        } else {
+            ReplicaEvent::TransferRegistered(TransferRegistered { debit_proof })
+        });
+    }
+    history
+}
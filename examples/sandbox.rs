@@ -0,0 +1,241 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A complete single-section economy, in-process: one group of Replicas
+//! and a few funded Actors, driven from an interactive prompt.
+//!
+//! Run with `cargo run --example sandbox`, then type `help`.
+
+use crdts::Dot;
+use safe_nd::{ClientFullId, DebitAgreementProof, PublicKey, SafeKey, Transfer};
+use safe_transfers::{
+    format_money_trimmed, parse_money, Account, ActorEvent, ReplicaEvent, ReplicaValidator,
+    TransferActor, TransferReplica,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{self, BufRead, Write},
+};
+use threshold_crypto::{SecretKey, SecretKeySet};
+
+const REPLICA_COUNT: usize = 4;
+const ACTORS: &[(&str, u64)] = &[("alice", 1_000), ("bob", 100), ("carol", 0)];
+
+/// All Replicas of the sandbox are trusted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Trusted;
+
+impl ReplicaValidator for Trusted {
+    fn is_valid(&self, _replica_group: PublicKey) -> bool {
+        true
+    }
+}
+
+struct Sandbox {
+    replicas: Vec<TransferReplica>,
+    actors: BTreeMap<String, TransferActor<Trusted>>,
+}
+
+impl Sandbox {
+    /// Genesis: the initial balances are credited, without proof,
+    /// from a throwaway key, to every Replica and Actor.
+    fn new() -> Self {
+        let mut rng = rand::thread_rng();
+        let threshold = (2 * REPLICA_COUNT / 3) - 1;
+        let secret_key_set = SecretKeySet::random(threshold, &mut rng);
+        let group = secret_key_set.public_keys();
+        let genesis_key = PublicKey::from(SecretKey::random().public_key());
+
+        let mut accounts = HashMap::new();
+        let mut actors = BTreeMap::new();
+        for (counter, (name, tokens)) in ACTORS.iter().enumerate() {
+            let safe_key = SafeKey::client(ClientFullId::new_ed25519(&mut rng));
+            let id = safe_key.public_key();
+            let mut account = Account::new(id);
+            account.append(Transfer {
+                id: Dot::new(genesis_key, counter as u64),
+                to: id,
+                amount: parse_money(&tokens.to_string()).expect("valid amount"),
+            });
+            let _ = accounts.insert(id, account.clone());
+            let actor = TransferActor::from_snapshot(account, safe_key, group.clone(), Trusted);
+            let _ = actors.insert(name.to_string(), actor);
+        }
+
+        // Transfers within the section are propagated to the section itself.
+        let mut known_groups = HashSet::new();
+        let _ = known_groups.insert(group.clone());
+        let replicas = (0..REPLICA_COUNT)
+            .map(|index| {
+                TransferReplica::from_snapshot(
+                    secret_key_set.secret_key_share(index),
+                    index,
+                    group.clone(),
+                    known_groups.clone(),
+                    accounts.clone(),
+                    Default::default(),
+                )
+            })
+            .collect();
+
+        Self { replicas, actors }
+    }
+
+    fn send(&mut self, from: &str, to: &str, amount: &str) -> Result<(), String> {
+        let amount = parse_money(amount).map_err(|e| format!("{:?}", e))?;
+        let recipient = self.actor(to)?.id();
+        let sender = self
+            .actors
+            .get_mut(from)
+            .ok_or_else(|| format!("No such actor: {}", from))?;
+
+        // 1. Init at the sender.
+        let initiated = sender
+            .transfer(amount, recipient)
+            .map_err(|e| format!("{:?}", e))?;
+        sender.apply(ActorEvent::TransferInitiated(initiated.clone()));
+
+        // 2. Validate at the Replicas, until the sender has a proof.
+        let mut debit_proof: Option<DebitAgreementProof> = None;
+        for replica in &mut self.replicas {
+            let validated = replica
+                .validate(initiated.signed_transfer.clone())
+                .map_err(|e| format!("{:?}", e))?;
+            replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+            let received = sender.receive(validated).map_err(|e| format!("{:?}", e))?;
+            sender.apply(ActorEvent::TransferValidationReceived(received.clone()));
+            if let Some(proof) = received.proof {
+                let registered = sender
+                    .register(proof.clone())
+                    .map_err(|e| format!("{:?}", e))?;
+                sender.apply(ActorEvent::TransferRegistrationSent(registered));
+                debit_proof = Some(proof);
+                break;
+            }
+        }
+        let debit_proof = debit_proof.ok_or("Validations did not reach quorum")?;
+
+        // 3. Register and 4. propagate at the Replicas.
+        let mut events = vec![];
+        for replica in &mut self.replicas {
+            let registered = replica
+                .register(&debit_proof)
+                .map_err(|e| format!("{:?}", e))?
+                .ok_or("Debit was already registered")?;
+            replica.apply(ReplicaEvent::TransferRegistered(registered));
+            let propagated = replica
+                .receive_propagated(&debit_proof)
+                .map_err(|e| format!("{:?}", e))?;
+            replica.apply(ReplicaEvent::TransferPropagated(propagated.clone()));
+            events.push(ReplicaEvent::TransferPropagated(propagated));
+        }
+
+        // 5. Synch at the recipient.
+        let recipient = self
+            .actors
+            .get_mut(to)
+            .ok_or_else(|| format!("No such actor: {}", to))?;
+        let synched = recipient.synch(events).map_err(|e| format!("{:?}", e))?;
+        recipient.apply(ActorEvent::TransfersSynched(synched));
+        Ok(())
+    }
+
+    fn actor(&self, name: &str) -> Result<&TransferActor<Trusted>, String> {
+        self.actors
+            .get(name)
+            .ok_or_else(|| format!("No such actor: {}", name))
+    }
+
+    fn balance(&self, name: &str) -> Result<String, String> {
+        let actor = self.actor(name)?;
+        let replica_balance = self.replicas[0]
+            .balance(&actor.id())
+            .map(format_money_trimmed)
+            .unwrap_or_else(|| "-".to_string());
+        Ok(format!(
+            "{}: {} (replicas: {})",
+            name,
+            format_money_trimmed(actor.balance()),
+            replica_balance
+        ))
+    }
+
+    fn history(&self, name: &str) -> Result<Vec<String>, String> {
+        let actor = self.actor(name)?;
+        let name_of = |key: PublicKey| {
+            self.actors
+                .iter()
+                .find(|(_, a)| a.id() == key)
+                .map(|(n, _)| n.clone())
+                .unwrap_or_else(|| "genesis".to_string())
+        };
+        let credits = actor.credits_since(0).into_iter().map(|t| {
+            format!(
+                "+{} from {}",
+                format_money_trimmed(t.amount),
+                name_of(t.id.actor)
+            )
+        });
+        let debits = actor
+            .debits_since(0)
+            .into_iter()
+            .map(|t| format!("-{} to {}", format_money_trimmed(t.amount), name_of(t.to)));
+        Ok(credits.chain(debits).collect())
+    }
+}
+
+fn main() {
+    let mut sandbox = Sandbox::new();
+    println!(
+        "Sandbox with {} replicas and actors: {}",
+        REPLICA_COUNT,
+        sandbox
+            .actors
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    println!("Type `help` for commands.");
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => (),
+        }
+        let words: Vec<_> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["help"] => {
+                println!("send <from> <to> <amount>  transfer tokens, e.g. `send alice bob 1.5`");
+                println!("balance <name>             balance at the actor and the replicas");
+                println!("history <name>             credits and debits of the actor");
+                println!("quit                       exit the sandbox");
+                Ok(())
+            }
+            ["send", from, to, amount] => sandbox.send(from, to, amount).map(|_| {
+                println!("Sent {} from {} to {}.", amount, from, to);
+            }),
+            ["balance", name] => sandbox.balance(name).map(|b| println!("{}", b)),
+            ["history", name] => sandbox.history(name).map(|entries| {
+                for entry in entries {
+                    println!("{}", entry);
+                }
+            }),
+            ["quit"] | ["exit"] => break,
+            _ => Err("Unknown command, type `help` for commands.".to_string()),
+        };
+        if let Err(error) = result {
+            println!("Error: {}", error);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A benchmark of the throughput of a Replica served from many threads: debits of
+//! distinct wallets are validated and registered through a ReplicaHandle, by 1, 2, 4..
+//! threads, up to the given number, so that the scaling across cores can be compared.
+//! Each thread runs its cmds to completion on its own, as an executor of one task.
+//!
+//! Run with `cargo run --release --example throughput -- <wallets> <max threads>`,
+//! e.g. `cargo run --release --example throughput -- 4096 16`.
+
+use crdts::Dot;
+use futures::executor::block_on;
+use safe_nd::{ClientFullId, Money, PublicKey, SafeKey, SignedTransfer, Transfer};
+use safe_transfers::{
+    Account, ReplicaHandle, Signable, SignableTransfer, TransferReplica, ValidationAccumulator,
+};
+use std::{collections::HashMap, env, sync::Arc, thread, time::Instant};
+use threshold_crypto::{SecretKey, SecretKeySet};
+
+const DEFAULT_WALLETS: usize = 1024;
+const DEFAULT_MAX_THREADS: usize = 8;
+
+fn main() {
+    let mut args = env::args().skip(1).map(|arg| arg.parse().ok());
+    let wallets: usize = args.next().flatten().unwrap_or(DEFAULT_WALLETS).max(1);
+    let max_threads: usize = args.next().flatten().unwrap_or(DEFAULT_MAX_THREADS).max(1);
+
+    let mut rng = rand::thread_rng();
+    // a group of a single Replica, so that its validation is the proof
+    let secret_key_set = SecretKeySet::random(0, &mut rng);
+    let group = secret_key_set.public_keys();
+    let genesis_key = PublicKey::from(SecretKey::random().public_key());
+    let keys: Vec<_> = (0..wallets)
+        .map(|_| SafeKey::client(ClientFullId::new_ed25519(&mut rng)))
+        .collect();
+    let mut accounts = HashMap::new();
+    for (counter, key) in keys.iter().enumerate() {
+        let id = key.public_key();
+        let mut account = Account::new(id);
+        account.append(Transfer {
+            id: Dot::new(genesis_key, counter as u64),
+            to: id,
+            amount: Money::from_nano(10),
+        });
+        let _ = accounts.insert(id, account);
+    }
+    let debits: Arc<Vec<_>> = Arc::new(
+        keys.iter()
+            .map(|key| {
+                let transfer = Transfer {
+                    id: Dot::new(key.public_key(), 0),
+                    to: genesis_key,
+                    amount: Money::from_nano(1),
+                };
+                SignedTransfer {
+                    actor_signature: key.sign(&SignableTransfer::new(&transfer).to_bytes()),
+                    transfer,
+                }
+            })
+            .collect(),
+    );
+
+    let mut threads = 1;
+    while threads <= max_threads {
+        let handle = ReplicaHandle::new(TransferReplica::from_snapshot(
+            secret_key_set.secret_key_share(0),
+            0,
+            group.clone(),
+            Default::default(),
+            accounts.clone(),
+            Default::default(),
+        ));
+        let started = Instant::now();
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let handle = handle.clone();
+                let debits = Arc::clone(&debits);
+                let group = group.clone();
+                thread::spawn(move || {
+                    for signed_transfer in debits.iter().skip(worker).step_by(threads) {
+                        let validated = block_on(handle.validate(signed_transfer.clone())).expect("valid");
+                        let mut accumulator =
+                            ValidationAccumulator::new(signed_transfer.clone(), group.clone());
+                        let proof = accumulator.add(validated).expect("valid").expect("proof");
+                        let _ = block_on(handle.register(&proof)).expect("registered");
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().expect("worker panicked");
+        }
+        let elapsed = started.elapsed();
+        println!(
+            "{:>3} threads: {:>8.0} debits/s",
+            threads,
+            wallets as f64 / elapsed.as_secs_f64()
+        );
+        threads *= 2;
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    clock::Timestamp,
+    hashing::{hash, Digest},
+};
+use safe_nd::{AccountId, Error, PublicKey, Result, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// The kinds of queries of a wallet that are logged.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum QueryKind {
+    /// The balance, or a proof of it.
+    Balance,
+    /// The credits or debits.
+    History,
+    /// Payments matching a search.
+    Payments,
+    /// Attachments of credits.
+    Attachments,
+}
+
+/// A query of a wallet. The requester is only kept as a hash of its key,
+/// so that the log does not itself reveal who watches which wallet.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct AccessEntry {
+    /// The hash of the key of the requester (see AccessLog::requester_hash).
+    pub requester: Digest,
+    /// The kind of query.
+    pub query: QueryKind,
+    /// When the query was served.
+    pub at: Timestamp,
+}
+
+/// The most recent queries of each wallet, for the owner to see who is watching it.
+/// Only the owner can read or clear the log of a wallet, by signing the current
+/// challenge of it, which changes on every read, so that proofs cannot be replayed.
+/// The Replica itself does no IO, so queries are recorded here by the upper layers
+/// serving them, and the log is only kept by those which choose to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessLog {
+    capacity: usize,
+    entries: BTreeMap<AccountId, VecDeque<AccessEntry>>,
+    nonces: BTreeMap<AccountId, u64>,
+}
+
+impl AccessLog {
+    /// A log keeping the given number of most recent queries per wallet.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Default::default(),
+            nonces: Default::default(),
+        }
+    }
+
+    /// The hash under which the requester is logged,
+    /// for an owner to recognise requesters it knows of.
+    pub fn requester_hash(requester: &PublicKey) -> Result<Digest> {
+        match bincode::serialize(requester) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise requester".into())),
+            Ok(data) => Ok(hash(&[b"access_log_requester", &data])),
+        }
+    }
+
+    /// Records a query of the wallet, dropping the oldest one when over capacity.
+    pub fn record(
+        &mut self,
+        wallet: AccountId,
+        requester: &PublicKey,
+        query: QueryKind,
+        at: Timestamp,
+    ) -> Result<()> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let entry = AccessEntry {
+            requester: Self::requester_hash(requester)?,
+            query,
+            at,
+        };
+        let entries = self.entries.entry(wallet).or_default();
+        entries.push_back(entry);
+        while entries.len() > self.capacity {
+            let _ = entries.pop_front();
+        }
+        Ok(())
+    }
+
+    /// The challenge the owner signs to read or clear the log of the wallet.
+    pub fn challenge(&self, wallet: &AccountId) -> Result<Digest> {
+        let nonce = self.nonces.get(wallet).copied().unwrap_or(0);
+        match bincode::serialize(wallet) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise wallet".into())),
+            Ok(data) => Ok(hash(&[b"access_log", &data, &nonce.to_le_bytes()])),
+        }
+    }
+
+    /// Returns the logged queries of the wallet, oldest first,
+    /// if the proof is the signature of the owner over the current challenge.
+    pub fn read(&mut self, wallet: &AccountId, proof: &Signature) -> Result<Vec<AccessEntry>> {
+        self.verify_owner(wallet, proof)?;
+        Ok(self
+            .entries
+            .get(wallet)
+            .map(|entries| entries.iter().copied().collect())
+            .unwrap_or_default())
+    }
+
+    /// Drops the logged queries of the wallet,
+    /// if the proof is the signature of the owner over the current challenge.
+    pub fn clear(&mut self, wallet: &AccountId, proof: &Signature) -> Result<()> {
+        self.verify_owner(wallet, proof)?;
+        let _ = self.entries.remove(wallet);
+        Ok(())
+    }
+
+    fn verify_owner(&mut self, wallet: &AccountId, proof: &Signature) -> Result<()> {
+        let challenge = self.challenge(wallet)?;
+        if wallet.verify(proof, &challenge).is_err() {
+            return Err(Error::AccessDenied);
+        }
+        *self.nonces.entry(*wallet).or_insert(0) += 1;
+        Ok(())
+    }
+}
+
+mod test {
+    use super::*;
+    use safe_nd::{ClientFullId, SafeKey};
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn keeps_recent_queries_for_the_owner() -> Result<()> {
+        // Arrange
+        let owner = get_owner();
+        let wallet = owner.public_key();
+        let watcher = get_random_pk();
+        let mut log = AccessLog::new(2);
+        log.record(wallet, &watcher, QueryKind::Balance, 1)?;
+        log.record(wallet, &watcher, QueryKind::History, 2)?;
+        log.record(wallet, &get_random_pk(), QueryKind::Payments, 3)?;
+        let challenge = log.challenge(&wallet)?;
+        let proof = owner.sign(&challenge);
+
+        // Act
+        let entries = log.read(&wallet, &proof)?;
+
+        // Assert
+        assert!(entries.len() == 2);
+        assert!(entries[0].requester == AccessLog::requester_hash(&watcher)?);
+        assert!(entries[0].query == QueryKind::History);
+        assert!(entries[1].at == 3);
+        assert!(log.challenge(&wallet)? != challenge);
+        assert!(log.read(&wallet, &proof).is_err());
+        assert!(log
+            .read(&wallet, &owner.sign(&log.challenge(&watcher)?))
+            .is_err());
+        Ok(())
+    }
+
+    fn get_owner() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
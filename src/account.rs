@@ -6,17 +6,208 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use safe_nd::{AccountId, Error, Money, Result, Transfer, TransferId};
-use std::collections::HashSet;
+use super::{
+    checkpoint::AccountState,
+    codec::{verified_in, Codec, Payload},
+    consolidation::{CreditSummary, SignedCreditSummary},
+    disbursement::ReceivedPayout,
+    hashing::{hash, Digest, MerkleProof, MerkleTree},
+    money::{credited, debited, saturating_add},
+    search::PaymentDirection,
+    sync::SyncIndex,
+    verification::verify_signed_transfer,
+    wire::FieldReader,
+    ReceivedCredit,
+};
+use safe_nd::{
+    AccountId, DebitAgreementProof, Error, Money, PublicKey, Result, Transfer, TransferId,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+};
 
 /// The balance and history of transfers for an account id.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Account {
     id: AccountId,
     balance: Money,
-    credits: Vec<Transfer>,
-    debits: Vec<Transfer>,
+    credits: Vec<HistoryEntry>,
+    debits: Vec<HistoryEntry>,
     transfer_ids: HashSet<TransferId>,
+    /// The checkpointed state that the history continues from, if truncated.
+    checkpointed: Option<AccountState>,
+    /// Checksums of the credits and debits, if kept.
+    checksums: Option<(Vec<Digest>, Vec<Digest>)>,
+    /// The summary replacing the oldest credits, if consolidated.
+    consolidated: Option<SignedCreditSummary>,
+    /// The fees charged for our debits, in the order they were charged.
+    fees_paid: Vec<(TransferId, Money)>,
+    /// The sum of the fees credited to us, as the wallet that fees go to.
+    fees_received: Money,
+    /// The bytes taken by the entries of the history, serialised.
+    stored_bytes: u64,
+    /// The Merkle trees over the transfers of the credits and of the debits held.
+    credit_tree: MerkleTree,
+    debit_tree: MerkleTree,
+    /// The index of each credit and debit held, among the credits or debits.
+    positions: HashMap<TransferId, u64>,
+    /// The keys that the account was rotated from, oldest first.
+    previous_ids: Vec<AccountId>,
+}
+
+/// The serialisable form of an account, as kept in snapshots.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub(crate) struct AccountRecord {
+    id: AccountId,
+    checkpointed: Option<AccountState>,
+    credits: Vec<HistoryEntry>,
+    debits: Vec<HistoryEntry>,
+    checksums: bool,
+    consolidated: Option<SignedCreditSummary>,
+    /// The ids of the consolidated credits, and of the compacted credits and debits,
+    /// so that they are not applied again.
+    consolidated_ids: Vec<TransferId>,
+    fees_paid: Vec<(TransferId, Money)>,
+    fees_received: Money,
+    previous_ids: Vec<AccountId>,
+}
+
+/// The most transfers returned in a page of a history.
+pub const MAX_PAGE_SIZE: usize = 1_000;
+
+/// A page of the credits or debits of an account.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct HistoryPage {
+    /// The transfers, in the order of the history.
+    pub transfers: Vec<Transfer>,
+    /// The index to query the next page from, None if this is the last page.
+    pub next: Option<usize>,
+}
+
+/// Where a transfer is in the history of an account (see Account::get).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferInfo {
+    /// Whether the transfer is a debit or a credit of the account.
+    pub direction: PaymentDirection,
+    /// The index among the debits or the credits, as with debits_since and credits_since.
+    pub index: u64,
+    /// The transfer.
+    pub transfer: Transfer,
+}
+
+/// The proof that a transfer is in the history of an account, against the root of
+/// the history (see Account::history_root), for light clients to confirm that a
+/// payment landed without the whole history.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct InclusionProof {
+    /// The transfer.
+    pub transfer: Transfer,
+    /// Whether the transfer is a debit or a credit of the account.
+    pub direction: PaymentDirection,
+    /// The path from the transfer to the root of the tree of its direction.
+    pub proof: MerkleProof,
+    /// The root of the tree of the credits.
+    pub credits_root: Digest,
+    /// The root of the tree of the debits.
+    pub debits_root: Digest,
+}
+
+impl InclusionProof {
+    /// Verifies that the transfer is in the history with the root.
+    pub fn verify(&self, history_root: &Digest) -> Result<()> {
+        if &hash(&[&self.credits_root, &self.debits_root]) != history_root {
+            return Err(Error::from("Proof is not of the history"));
+        }
+        let root = match self.direction {
+            PaymentDirection::Received => &self.credits_root,
+            PaymentDirection::Sent => &self.debits_root,
+        };
+        let leaf = match bincode::serialize(&self.transfer) {
+            Err(_) => return Err(Error::NetworkOther("Could not serialise transfer".into())),
+            Ok(data) => hash(&[&data]),
+        };
+        if self.proof.verify(leaf, root) {
+            Ok(())
+        } else {
+            Err(Error::from("Transfer is not in the history"))
+        }
+    }
+}
+
+/// An entry in the history of an account,
+/// typed by how the transfer came to be part of it.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum HistoryEntry {
+    /// A debit registered at the Replicas of the account,
+    /// with the agreement proof from those Replicas.
+    RegisteredDebit(DebitAgreementProof),
+    /// A credit propagated from the Replicas of the sender,
+    /// with the agreement proof from, and the key of, those Replicas.
+    PropagatedCredit(ReceivedCredit),
+    /// A transfer without proof, such as initial state.
+    Unproven(Transfer),
+    /// A payout of a disbursement propagated from the Replicas of the sender,
+    /// with the proofs of the debit and of the payout, and the key of those Replicas.
+    PropagatedPayout(ReceivedPayout),
+}
+
+impl HistoryEntry {
+    /// The transfer of this entry.
+    pub fn transfer(&self) -> &Transfer {
+        match self {
+            HistoryEntry::RegisteredDebit(proof) => &proof.signed_transfer.transfer,
+            HistoryEntry::PropagatedCredit(credit) => &credit.debit_proof.signed_transfer.transfer,
+            HistoryEntry::PropagatedPayout(payout) => &payout.payout_proof.transfer,
+            HistoryEntry::Unproven(transfer) => transfer,
+        }
+    }
+
+    /// The agreement proof of this entry, if any.
+    /// That of a payout is the proof of the debit of its disbursement.
+    pub fn debit_proof(&self) -> Option<&DebitAgreementProof> {
+        match self {
+            HistoryEntry::RegisteredDebit(proof) => Some(proof),
+            HistoryEntry::PropagatedCredit(credit) => Some(&credit.debit_proof),
+            HistoryEntry::PropagatedPayout(payout) => Some(&payout.payout_proof.debit_proof),
+            HistoryEntry::Unproven(_) => None,
+        }
+    }
+}
+
+impl AccountRecord {
+    /// The id of the account.
+    pub(crate) fn id(&self) -> AccountId {
+        self.id
+    }
+
+    /// The credits held with their proofs, not those compacted or consolidated.
+    pub(crate) fn credits(&self) -> &[HistoryEntry] {
+        &self.credits
+    }
+
+    /// The debits held with their proofs, not those compacted or checkpointed.
+    pub(crate) fn debits(&self) -> &[HistoryEntry] {
+        &self.debits
+    }
+
+    /// Reads a record written in the layout of an older snapshot, of `fields` fields.
+    pub(crate) fn read(bytes: &mut &[u8], fields: usize) -> Result<Self> {
+        let mut reader = FieldReader::new(bytes, fields);
+        Ok(Self {
+            id: reader.required()?,
+            checkpointed: reader.required()?,
+            credits: reader.required()?,
+            debits: reader.required()?,
+            checksums: reader.required()?,
+            consolidated: reader.field()?,
+            consolidated_ids: reader.field()?,
+            fees_paid: reader.field()?,
+            fees_received: reader.field()?,
+            previous_ids: reader.field()?,
+        })
+    }
 }
 
 impl Account {
@@ -28,16 +219,195 @@ impl Account {
             credits: vec![],
             debits: Default::default(),
             transfer_ids: Default::default(),
+            checkpointed: None,
+            checksums: None,
+            consolidated: None,
+            fees_paid: vec![],
+            fees_received: Money::zero(),
+            stored_bytes: 0,
+            credit_tree: Default::default(),
+            debit_tree: Default::default(),
+            positions: Default::default(),
+            previous_ids: vec![],
+        }
+    }
+
+    /// Creates an account continuing from a checkpointed state,
+    /// without the history up to the checkpoint.
+    pub fn from_checkpoint(state: AccountState) -> Self {
+        Self {
+            id: state.id,
+            balance: state.balance,
+            credits: vec![],
+            debits: Default::default(),
+            transfer_ids: Default::default(),
+            checkpointed: Some(state),
+            checksums: None,
+            consolidated: None,
+            fees_paid: vec![],
+            fees_received: Money::zero(),
+            stored_bytes: 0,
+            credit_tree: Default::default(),
+            debit_tree: Default::default(),
+            positions: Default::default(),
+            previous_ids: vec![],
+        }
+    }
+
+    /// The serialisable form of the account.
+    pub(crate) fn to_record(&self) -> AccountRecord {
+        AccountRecord {
+            id: self.id,
+            checkpointed: self.checkpointed.clone(),
+            credits: self.credits.clone(),
+            debits: self.debits.clone(),
+            checksums: self.checksums.is_some(),
+            consolidated: self.consolidated.clone(),
+            consolidated_ids: self.consolidated_ids(),
+            fees_paid: self.fees_paid.clone(),
+            fees_received: self.fees_received,
+            previous_ids: self.previous_ids.clone(),
+        }
+    }
+
+    /// Restores an account from its serialisable form,
+    /// verifying that the entries belong to it, and add up.
+    pub(crate) fn from_record(record: AccountRecord) -> Result<Self> {
+        let mut account = match record.checkpointed {
+            Some(state) if state.id == record.id || record.previous_ids.contains(&state.id) => {
+                Self::from_checkpoint(state)
+            }
+            Some(_) => return Err(Error::from("Checkpoint is not of the account")),
+            None => Self::new(record.id),
+        };
+        account.id = record.id;
+        account.previous_ids = record.previous_ids;
+        if record.checksums {
+            account = account.with_checksums();
+        }
+        if let Some(signed) = record.consolidated {
+            if signed.summary.wallet != record.id
+                || signed.summary.count != record.consolidated_ids.len() as u64
+            {
+                return Err(Error::from("Summary is not of the account"));
+            }
+            account.balance = credited(account.balance, signed.summary.total)?;
+            account.consolidated = Some(signed);
         }
+        account.transfer_ids.extend(record.consolidated_ids);
+        account.receive_fee(record.fees_received);
+        let mut balance = account.balance;
+        for entry in &record.credits {
+            if !account.is_own(&entry.transfer().to) {
+                return Err(Error::from("Credit does not belong to the account"));
+            }
+            balance = credited(balance, entry.transfer().amount)?;
+        }
+        for entry in &record.debits {
+            if !account.is_own(&entry.transfer().id.actor) {
+                return Err(Error::from("Debit does not belong to the account"));
+            }
+            balance = debited(balance, entry.transfer().amount)?;
+        }
+        let debit_ids: HashSet<_> = record.debits.iter().map(|e| e.transfer().id).collect();
+        for (id, fee) in &record.fees_paid {
+            let compacted = account.is_own(&id.actor) && account.transfer_ids.contains(id);
+            if !debit_ids.contains(id) && !compacted {
+                return Err(Error::from("Fee is not of a debit of the account"));
+            }
+            balance = debited(balance, *fee)?;
+        }
+        account.credits.reserve(record.credits.len());
+        account.debits.reserve(record.debits.len());
+        account
+            .transfer_ids
+            .reserve(record.credits.len() + record.debits.len());
+        for entry in record.credits.into_iter().chain(record.debits) {
+            account.append_entry(entry);
+        }
+        for (id, fee) in record.fees_paid {
+            account.pay_fee(id, fee);
+        }
+        Ok(account)
+    }
+
+    /// Keeps a checksum of every entry, from now on verified by verify_integrity,
+    /// to catch corruption of the history in memory or on disk.
+    pub fn with_checksums(mut self) -> Self {
+        self.checksums = Some((
+            self.credits.iter().map(Self::checksum).collect(),
+            self.debits.iter().map(Self::checksum).collect(),
+        ));
+        self
     }
 
     pub fn id(&self) -> AccountId {
         self.id
     }
 
+    /// The keys that the account was rotated from, oldest first.
+    pub fn previous_ids(&self) -> &[AccountId] {
+        &self.previous_ids
+    }
+
+    /// Whether the key is the current or a previous key of the account.
+    pub fn is_own(&self, key: &AccountId) -> bool {
+        self.id == *key || self.previous_ids.contains(key)
+    }
+
+    /// Hands the account over to the new key, which signs its debits from then on.
+    /// The history is kept, and credits to the previous keys still belong to it.
+    pub(crate) fn rotate_key(&mut self, new_id: AccountId) {
+        self.previous_ids.push(self.id);
+        self.id = new_id;
+    }
+
     /// Query for next version.
     pub fn next_debit(&self) -> u64 {
-        self.debits.len() as u64
+        let checkpointed = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        checkpointed + self.debits.len() as u64
+    }
+
+    /// Query for number of credits, including those checkpointed or consolidated.
+    pub fn credit_count(&self) -> usize {
+        self.credits_before() + self.credits.len()
+    }
+
+    /// Query for the summary replacing the oldest credits, if consolidated.
+    pub fn consolidated(&self) -> Option<&SignedCreditSummary> {
+        self.consolidated.as_ref()
+    }
+
+    /// Query for the checkpointed state that the history continues from,
+    /// None if the history is complete.
+    pub fn checkpointed(&self) -> Option<&AccountState> {
+        self.checkpointed.as_ref()
+    }
+
+    /// The balance before the entries held, as checkpointed or consolidated.
+    fn balance_before(&self) -> Money {
+        let checkpointed = self
+            .checkpointed
+            .as_ref()
+            .map_or(Money::zero(), |s| s.balance);
+        let consolidated = self
+            .consolidated
+            .as_ref()
+            .map_or(Money::zero(), |s| s.summary.total);
+        saturating_add(checkpointed, consolidated)
+    }
+
+    /// The balance before the entries held, with the fees received, which are not
+    /// ordered in the history, as opening a statement of the account (see Statement).
+    pub(crate) fn opening_balance(&self) -> Money {
+        saturating_add(self.balance_before(), self.fees_received)
+    }
+
+    /// Number of credits not held as entries, as checkpointed or consolidated.
+    fn credits_before(&self) -> usize {
+        let checkpointed = self.checkpointed.as_ref().map_or(0, |s| s.credit_count);
+        let consolidated = self.consolidated.as_ref().map_or(0, |s| s.summary.count);
+        (checkpointed + consolidated) as usize
     }
 
     /// Query for balance.
@@ -45,11 +415,140 @@ impl Account {
         self.balance
     }
 
+    /// Recomputes the balance by folding the entire history.
+    /// The balance is maintained on every append, so this is only
+    /// for cross-checking it. None if the history under- or overflows.
+    pub fn recompute_balance(&self) -> Option<Money> {
+        let received = credited(self.balance_before(), self.fees_received).ok()?;
+        let credits = self
+            .credits
+            .iter()
+            .try_fold(received, |sum, e| credited(sum, e.transfer().amount).ok())?;
+        let debits = self
+            .debits
+            .iter()
+            .try_fold(credits, |sum, e| debited(sum, e.transfer().amount).ok())?;
+        self.fees_paid
+            .iter()
+            .try_fold(debits, |sum, (_, fee)| debited(sum, *fee).ok())
+    }
+
+    /// Query for the balance as of the given number of debits and credits in the history,
+    /// with the fees paid for those debits. Fees received, not being ordered in the
+    /// history, are not included. None if the index is beyond the history, or before
+    /// the entries held, or if the balance would be negative.
+    pub fn balance_at(&self, debit_index: u64, credit_index: u64) -> Option<Money> {
+        let first_debit = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        let credits = (credit_index as usize)
+            .checked_sub(self.credits_before())
+            .filter(|count| *count <= self.credits.len())?;
+        let debits = debit_index
+            .checked_sub(first_debit)
+            .filter(|count| *count <= self.debits.len() as u64)? as usize;
+        let nanos = |amount: Money| i128::from(amount.as_nano());
+        let credited: i128 = self.credits[..credits]
+            .iter()
+            .map(|e| nanos(e.transfer().amount))
+            .sum();
+        let debited: i128 = self.debits[..debits]
+            .iter()
+            .map(|e| nanos(e.transfer().amount))
+            .sum();
+        let fees: i128 = self
+            .fees_paid
+            .iter()
+            .filter(|(id, _)| id.counter < debit_index)
+            .map(|(_, fee)| nanos(*fee))
+            .sum();
+        let balance = nanos(self.balance_before()) + credited - debited - fees;
+        u64::try_from(balance).ok().map(Money::from_nano)
+    }
+
+    /// The balance the history adds up to, which is negative
+    /// if it debits more than it credits, as it never should.
+    pub(crate) fn history_balance(&self) -> i128 {
+        let nanos = |amount: Money| i128::from(amount.as_nano());
+        let received = nanos(self.balance_before()) + nanos(self.fees_received);
+        let credits: i128 = self
+            .credits
+            .iter()
+            .map(|e| nanos(e.transfer().amount))
+            .sum();
+        let debits: i128 = self.debits.iter().map(|e| nanos(e.transfer().amount)).sum();
+        let fees: i128 = self.fees_paid.iter().map(|(_, fee)| nanos(*fee)).sum();
+        received + credits - debits - fees
+    }
+
+    /// Query for the sum of the fees charged for our debits.
+    pub fn total_fees_paid(&self) -> Money {
+        self.fees_paid
+            .iter()
+            .fold(Money::zero(), |sum, (_, fee)| saturating_add(sum, *fee))
+    }
+
+    /// Query for the bytes taken by the entries of the history, serialised.
+    /// Entries replaced by a checkpoint or a consolidation no longer count.
+    pub fn storage_usage(&self) -> u64 {
+        self.stored_bytes
+    }
+
+    /// Query for the number and bytes of the entries held before the index,
+    /// i.e. of those that compacting up to the index would free.
+    pub fn storage_before(&self, up_to_index: SyncIndex) -> (u64, u64) {
+        let first_debit = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        let credits = (up_to_index.credit_index as usize)
+            .saturating_sub(self.credits_before())
+            .min(self.credits.len());
+        let debits =
+            (up_to_index.debit_index.saturating_sub(first_debit) as usize).min(self.debits.len());
+        let bytes = self.credits[..credits]
+            .iter()
+            .chain(&self.debits[..debits])
+            .map(Self::size_of)
+            .sum();
+        ((credits + debits) as u64, bytes)
+    }
+
+    /// Query for the fee charged for the debit, if any.
+    pub fn fee_paid(&self, id: &TransferId) -> Option<Money> {
+        self.fees_paid
+            .iter()
+            .find(|(fee_id, _)| fee_id == id)
+            .map(|(_, fee)| *fee)
+    }
+
+    /// Query for the sum of the fees credited to us.
+    pub fn fees_received(&self) -> Money {
+        self.fees_received
+    }
+
     /// Query for already stored transfer.
     pub fn contains(&self, id: &TransferId) -> bool {
         self.transfer_ids.contains(id)
     }
 
+    /// Query for a transfer held, as a debit or a credit, with its index, in constant time.
+    /// None if unknown, or replaced by a checkpoint or a consolidation (see contains).
+    pub fn get(&self, id: &TransferId) -> Option<TransferInfo> {
+        let index = *self.positions.get(id)?;
+        let (entries, before, direction) = if self.is_own(&id.actor) {
+            let checkpointed = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+            (&self.debits, checkpointed as usize, PaymentDirection::Sent)
+        } else {
+            (
+                &self.credits,
+                self.credits_before(),
+                PaymentDirection::Received,
+            )
+        };
+        let entry = entries.get((index as usize).checked_sub(before)?)?;
+        Some(TransferInfo {
+            direction,
+            index,
+            transfer: entry.transfer().clone(),
+        })
+    }
+
     /// Zero based indexing, first debit will be nr 0
     /// (we could just as well just compare debits.len()..)
     pub fn is_sequential(&self, transfer: &Transfer) -> Result<bool> {
@@ -58,8 +557,10 @@ impl Account {
             Err(Error::from("Operation is non-sequential"))
         } else {
             match self.debits.last() {
-                None => Ok(id.counter == 0), // if no debits have been made, transfer counter must be 0
-                Some(previous) => Ok(previous.id.counter + 1 == id.counter),
+                // if no debits have been made, transfer counter must be 0,
+                // or continue from the checkpoint
+                None => Ok(id.counter == self.next_debit()),
+                Some(previous) => Ok(previous.transfer().id.counter + 1 == id.counter),
             }
         }
     }
@@ -67,72 +568,499 @@ impl Account {
     /// Query for new credits since specified index.
     /// NB: This is not guaranteed to give you all unknown to you,
     /// since there is no absolute order on the credits!
+    /// Credits up to a checkpoint that the history continues from are not held.
     pub fn credits_since(&self, index: usize) -> Vec<Transfer> {
-        if self.credits.len() > index {
-            self.credits.split_at(index).1.to_vec()
-        } else {
-            vec![]
+        self.credits_iter(index).cloned().collect()
+    }
+
+    /// The credits since the index, as credits_since, by reference.
+    pub fn credits_iter(&self, index: usize) -> impl Iterator<Item = &Transfer> {
+        self.credit_entries_since(index)
+            .iter()
+            .map(HistoryEntry::transfer)
+    }
+
+    /// Query for at most `limit` credits from the index on, capped at MAX_PAGE_SIZE.
+    /// Credits up to a checkpoint or summary that the history continues from are not held,
+    /// so a page from before them starts at the first credit held.
+    pub fn credits_page(&self, from_index: usize, limit: usize) -> HistoryPage {
+        Self::page(&self.credits, self.credits_before(), from_index, limit)
+    }
+
+    /// Query for at most `limit` debits from the index on, capped at MAX_PAGE_SIZE.
+    /// Debits up to a checkpoint that the history continues from are not held,
+    /// so a page from before them starts at the first debit held.
+    pub fn debits_page(&self, from_index: usize, limit: usize) -> HistoryPage {
+        let checkpointed = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        Self::page(&self.debits, checkpointed as usize, from_index, limit)
+    }
+
+    fn page(
+        entries: &[HistoryEntry],
+        before: usize,
+        from_index: usize,
+        limit: usize,
+    ) -> HistoryPage {
+        let start = from_index.saturating_sub(before).min(entries.len());
+        let end = start
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(entries.len());
+        HistoryPage {
+            transfers: entries[start..end]
+                .iter()
+                .map(|e| e.transfer().clone())
+                .collect(),
+            next: if end < entries.len() {
+                Some(before + end)
+            } else {
+                None
+            },
         }
     }
 
     /// Query for new debit since specified index.
+    /// Debits up to a checkpoint that the history continues from are not held.
     pub fn debits_since(&self, index: usize) -> Vec<Transfer> {
-        if self.debits.len() > index {
-            self.debits.split_at(index).1.to_vec()
-        } else {
-            vec![]
+        self.debits_iter(index).cloned().collect()
+    }
+
+    /// The debits since the index, as debits_since, by reference.
+    pub fn debits_iter(&self, index: usize) -> impl Iterator<Item = &Transfer> {
+        self.debit_entries_since(index)
+            .iter()
+            .map(HistoryEntry::transfer)
+    }
+
+    /// The typed credit entries from the index on, of those held.
+    pub(crate) fn credit_entries_since(&self, index: usize) -> &[HistoryEntry] {
+        let index = index.saturating_sub(self.credits_before());
+        &self.credits[index.min(self.credits.len())..]
+    }
+
+    /// The typed debit entries from the index on, of those held.
+    pub(crate) fn debit_entries_since(&self, index: usize) -> &[HistoryEntry] {
+        let checkpointed = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        let index = index.saturating_sub(checkpointed as usize);
+        &self.debits[index.min(self.debits.len())..]
+    }
+
+    /// The credit and debit entries held of the history at the state, such as that
+    /// of a checkpoint. None if entries of it have been replaced since, or it is ahead.
+    pub(crate) fn entries_at(
+        &self,
+        state: &AccountState,
+    ) -> Option<(&[HistoryEntry], &[HistoryEntry])> {
+        let checkpointed = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        let credits = (state.credit_count as usize).checked_sub(self.credits_before())?;
+        let debits = state.debit_count.checked_sub(checkpointed)? as usize;
+        if credits > self.credits.len() || debits > self.debits.len() {
+            return None;
         }
+        Some((&self.credits[..credits], &self.debits[..debits]))
     }
 
-    /// Mutates state.
-    pub fn append(&mut self, transfer: Transfer) {
-        if self.id == transfer.id.actor {
-            match self.balance.checked_sub(transfer.amount) {
-                Some(amount) => self.balance = amount,
-                None => panic!("overflow when subtracting!"),
-            }
-            let _ = self.transfer_ids.insert(transfer.id);
-            self.debits.push(transfer);
-        } else if self.id == transfer.to {
-            match self.balance.checked_add(transfer.amount) {
-                Some(amount) => self.balance = amount,
-                None => panic!("overflow when adding!"),
-            }
-            let _ = self.transfer_ids.insert(transfer.id);
-            self.credits.push(transfer);
-        } else {
-            panic!("Transfer does not belong to this account")
+    /// Query for the root of the history: of the Merkle trees over the credits
+    /// and the debits held, in the order they were appended. Entries replaced by
+    /// a checkpoint or a consolidation are committed to by those instead.
+    pub fn history_root(&self) -> Digest {
+        hash(&[&self.credit_tree.root(), &self.debit_tree.root()])
+    }
+
+    /// The number of credits and debits in the trees of the history.
+    pub(crate) fn history_size(&self) -> (usize, usize) {
+        (self.credit_tree.len(), self.debit_tree.len())
+    }
+
+    /// Query for the proof that the transfer is in the history, against history_root.
+    /// None if the transfer is not held as an entry.
+    pub fn prove_inclusion(&self, id: &TransferId) -> Option<InclusionProof> {
+        self.inclusion(id, &self.credit_tree, &self.debit_tree)
+    }
+
+    /// Query for the proof that the transfer is in the history as it was when it had
+    /// the given number of credits and debits in its trees (see history_size), against
+    /// the history root of then. None if the transfer is not among those entries.
+    pub(crate) fn prove_inclusion_at(
+        &self,
+        id: &TransferId,
+        credits: usize,
+        debits: usize,
+    ) -> Option<InclusionProof> {
+        let credit_tree = self.credit_tree.prefix(credits)?;
+        let debit_tree = self.debit_tree.prefix(debits)?;
+        self.inclusion(id, &credit_tree, &debit_tree)
+    }
+
+    fn inclusion(
+        &self,
+        id: &TransferId,
+        credit_tree: &MerkleTree,
+        debit_tree: &MerkleTree,
+    ) -> Option<InclusionProof> {
+        let info = self.get(id)?;
+        let (tree, before) = match info.direction {
+            PaymentDirection::Sent => {
+                let checkpointed = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+                (debit_tree, checkpointed as usize)
+            }
+            PaymentDirection::Received => (credit_tree, self.credits_before()),
+        };
+        Some(InclusionProof {
+            transfer: info.transfer,
+            direction: info.direction,
+            proof: tree.proof(info.index as usize - before)?,
+            credits_root: credit_tree.root(),
+            debits_root: debit_tree.root(),
+        })
+    }
+
+    /// Query for the typed credit entries.
+    pub fn credit_entries(&self) -> &[HistoryEntry] {
+        &self.credits
+    }
+
+    /// Query for the typed debit entries.
+    pub fn debit_entries(&self) -> &[HistoryEntry] {
+        &self.debits
+    }
+
+    /// Verifies the entries against their checksums, and the balance against the
+    /// entries. Accounts without checksums (see with_checksums) always pass.
+    pub fn verify_integrity(&self) -> Result<()> {
+        let (credit_sums, debit_sums) = match &self.checksums {
+            Some(checksums) => checksums,
+            None => return Ok(()),
+        };
+        let intact = |entries: &[HistoryEntry], sums: &[Digest]| {
+            entries.len() == sums.len()
+                && entries
+                    .iter()
+                    .zip(sums)
+                    .all(|(entry, sum)| &Self::checksum(entry) == sum)
+        };
+        if !intact(&self.credits, credit_sums) || !intact(&self.debits, debit_sums) {
+            return Err(Error::from("History entry is corrupted"));
+        }
+        if self.recompute_balance() != Some(self.balance) {
+            return Err(Error::from("Balance is corrupted"));
+        }
+        Ok(())
+    }
+
+    /// Re-verifies the entire history against the stored proofs: the owner
+    /// signatures, the Replica signatures, the sequence of the debits, and the balance.
+    /// Debits, and the credit summary, must be signed by one of the own keys, those of
+    /// the group holding the account, now or in the past; credits by one of the trusted keys.
+    /// Entries without proof can not be verified, and thus fail the verification.
+    /// A history continuing from a checkpoint is verified from the checkpointed state.
+    /// Signatures are accepted over any of the codecs, such as those accepted at the
+    /// height (see accepted_codecs).
+    pub fn verify_full(
+        &self,
+        own_keys: &[PublicKey],
+        trusted_keys: &[PublicKey],
+        codecs: &[Codec],
+    ) -> Result<()> {
+        let mut balance = self.balance_before();
+        let first_debit = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        if let Some(signed) = &self.consolidated {
+            let signed_by_own = own_keys.iter().any(|key| signed.verify(key).is_ok());
+            if !signed_by_own {
+                return Err(Error::from(
+                    "Summary is not signed by the group of the account",
+                ));
+            }
         }
+        for entry in &self.credits {
+            let transfer = Self::verify_entry(entry, trusted_keys, codecs)?;
+            balance = credited(balance, transfer.amount)
+                .map_err(|_| Error::from("Overflow when adding credits"))?;
+        }
+        for (counter, entry) in self.debits.iter().enumerate() {
+            let transfer = Self::verify_entry(entry, own_keys, codecs)?;
+            if transfer.id.counter != first_debit + counter as u64 {
+                return Err(Error::from("Non-sequential debit in history"));
+            }
+            balance = debited(balance, transfer.amount)
+                .map_err(|_| Error::from("Overflow when subtracting debits"))?;
+        }
+        if balance != self.balance {
+            return Err(Error::from("Balance does not match history"));
+        }
+        Ok(())
     }
 
-    /// Test-helper API to simulate Client Transfers.
-    #[cfg(feature = "simulated-payouts")]
-    pub fn simulated_credit(&mut self, transfer: Transfer) {
-        if self.id == transfer.id.actor {
-            match self.balance.checked_add(transfer.amount) {
-                Some(amount) => self.balance = amount,
-                None => panic!("overflow when adding!"),
+    /// Verifies the proofs of the entry, returning its transfer.
+    fn verify_entry<'a>(
+        entry: &'a HistoryEntry,
+        trusted_keys: &[PublicKey],
+        codecs: &[Codec],
+    ) -> Result<&'a Transfer> {
+        let (proof, signers) = match entry {
+            HistoryEntry::RegisteredDebit(proof) => (proof, trusted_keys.to_vec()),
+            HistoryEntry::PropagatedCredit(credit) => {
+                if !trusted_keys.contains(&credit.debiting_replicas) {
+                    return Err(Error::from("Credit is signed by untrusted Replicas"));
+                }
+                (&credit.debit_proof, vec![credit.debiting_replicas])
             }
-            let _ = self.transfer_ids.insert(transfer.id);
-            self.credits.push(transfer);
+            HistoryEntry::PropagatedPayout(payout) => {
+                if !trusted_keys.contains(&payout.debiting_replicas) {
+                    return Err(Error::from("Payout is signed by untrusted Replicas"));
+                }
+                payout.payout_proof.verify(&payout.debiting_replicas)?;
+                return Ok(entry.transfer());
+            }
+            HistoryEntry::Unproven(_) => return Err(Error::from("History entry has no proof")),
+        };
+        verify_signed_transfer(&proof.signed_transfer, codecs)?;
+        let payload = Payload::Proof(&proof.signed_transfer);
+        let signature = &proof.debiting_replicas_sig;
+        let signed_by_trusted = signers
+            .iter()
+            .any(|key| verified_in(codecs, payload, |data| key.verify(signature, data).is_ok()));
+        if signed_by_trusted {
+            Ok(entry.transfer())
         } else {
-            panic!("Transfer does not belong to this account")
+            Err(Error::InvalidSignature)
+        }
+    }
+
+    /// Summarizes the oldest `count` credits held as entries,
+    /// along with those of the current summary, if any.
+    pub(crate) fn summarize(&self, count: usize) -> Result<CreditSummary> {
+        if self.checkpointed.is_some() {
+            return Err(Error::from("Account history is truncated"));
+        }
+        if count > self.credits.len() {
+            return Err(Error::from("Not enough credits to summarize"));
+        }
+        let (previous_count, mut total, mut parts) = match &self.consolidated {
+            Some(signed) => (
+                signed.summary.count,
+                signed.summary.total,
+                vec![signed.summary.transfers_hash.to_vec()],
+            ),
+            None => (0, Money::zero(), vec![]),
+        };
+        for entry in &self.credits[..count] {
+            total = credited(total, entry.transfer().amount)?;
+            match bincode::serialize(entry.transfer()) {
+                Err(_) => return Err(Error::NetworkOther("Could not serialise transfer".into())),
+                Ok(data) => parts.push(data),
+            }
+        }
+        let parts: Vec<_> = parts.iter().map(|p| p.as_slice()).collect();
+        Ok(CreditSummary {
+            wallet: self.id,
+            count: previous_count + count as u64,
+            total,
+            transfers_hash: hash(&parts),
+        })
+    }
+
+    /// The state of the account up to the index of its history, for the entries
+    /// before the index to be replaced by it, once signed by the Replicas (see compact).
+    /// The balance of the state is that of the transfers, the fees being kept apart.
+    /// Credits consolidated, or compacted before, are included in the state.
+    pub fn compaction(&self, up_to_index: SyncIndex) -> Result<AccountState> {
+        let first_debit = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        let credits = (up_to_index.credit_index as usize)
+            .checked_sub(self.credits_before())
+            .filter(|count| *count <= self.credits.len());
+        let debits = up_to_index
+            .debit_index
+            .checked_sub(first_debit)
+            .map(|count| count as usize)
+            .filter(|count| *count <= self.debits.len());
+        let (credits, debits) = match (credits, debits) {
+            (Some(credits), Some(debits)) if credits + debits > 0 => (credits, debits),
+            (Some(_), Some(_)) => return Err(Error::from("Nothing to compact")),
+            _ => return Err(Error::from("Index is not of the history held")),
+        };
+        let mut parts = vec![];
+        if let Some(state) = &self.checkpointed {
+            parts.push(state.history_hash.to_vec());
+        }
+        if let Some(signed) = &self.consolidated {
+            parts.push(signed.summary.transfers_hash.to_vec());
+        }
+        let mut balance = self.balance_before();
+        for entry in &self.credits[..credits] {
+            balance = credited(balance, entry.transfer().amount)?;
+        }
+        for entry in &self.debits[..debits] {
+            balance = debited(balance, entry.transfer().amount)
+                .map_err(|_| Error::from("Credits up to the index do not cover the debits"))?;
+        }
+        for entry in self.credits[..credits].iter().chain(&self.debits[..debits]) {
+            match bincode::serialize(entry.transfer()) {
+                Err(_) => return Err(Error::NetworkOther("Could not serialise transfer".into())),
+                Ok(data) => parts.push(data),
+            }
+        }
+        let parts: Vec<_> = parts.iter().map(|p| p.as_slice()).collect();
+        Ok(AccountState {
+            id: self.id,
+            balance,
+            credit_count: up_to_index.credit_index,
+            debit_count: up_to_index.debit_index,
+            history_hash: hash(&parts),
+        })
+    }
+
+    /// Mutates state, replacing the entries up to the index of the state with it.
+    /// The state is assumed to have been verified against the entries (see compaction).
+    /// Credits and debits are then counted from the state on, so that sequence checks
+    /// and queries since an index stay relative to the full history.
+    pub(crate) fn compact(&mut self, state: AccountState) {
+        let first_debit = self.checkpointed.as_ref().map_or(0, |s| s.debit_count);
+        let credits = state.credit_count as usize - self.credits_before();
+        let debits = (state.debit_count - first_debit) as usize;
+        let positions = &mut self.positions;
+        let freed: u64 = self
+            .credits
+            .drain(..credits)
+            .chain(self.debits.drain(..debits))
+            .map(|e| {
+                let _ = positions.remove(&e.transfer().id);
+                Self::size_of(&e)
+            })
+            .sum();
+        self.stored_bytes = self.stored_bytes.saturating_sub(freed);
+        if let Some((credit_sums, debit_sums)) = &mut self.checksums {
+            let _ = credit_sums.drain(..credits);
+            let _ = debit_sums.drain(..debits);
+        }
+        self.credit_tree = MerkleTree::new(self.credits.iter().map(Self::leaf));
+        self.debit_tree = MerkleTree::new(self.debits.iter().map(Self::leaf));
+        self.consolidated = None;
+        self.checkpointed = Some(state);
+        self.debug_check_balance();
+    }
+
+    /// Mutates state, charging the fee of one of our debits.
+    pub(crate) fn pay_fee(&mut self, id: TransferId, fee: Money) {
+        match debited(self.balance, fee) {
+            Ok(balance) => self.balance = balance,
+            Err(_) => panic!("overflow when subtracting!"),
+        }
+        self.fees_paid.push((id, fee));
+    }
+
+    /// Mutates state, crediting us with a fee charged to another account.
+    pub(crate) fn receive_fee(&mut self, fee: Money) {
+        self.balance = saturating_add(self.balance, fee);
+        self.fees_received = saturating_add(self.fees_received, fee);
+    }
+
+    /// Mutates state, replacing the oldest credits with the summary of them.
+    /// The summary is assumed to have been verified against the entries (see summarize).
+    pub(crate) fn consolidate(&mut self, signed: SignedCreditSummary) {
+        let previous_count = self.consolidated.as_ref().map_or(0, |s| s.summary.count);
+        let count = (signed.summary.count - previous_count) as usize;
+        let positions = &mut self.positions;
+        let freed: u64 = self
+            .credits
+            .drain(..count)
+            .map(|e| {
+                let _ = positions.remove(&e.transfer().id);
+                Self::size_of(&e)
+            })
+            .sum();
+        self.stored_bytes = self.stored_bytes.saturating_sub(freed);
+        if let Some((sums, _)) = &mut self.checksums {
+            let _ = sums.drain(..count);
         }
+        self.credit_tree = MerkleTree::new(self.credits.iter().map(Self::leaf));
+        self.consolidated = Some(signed);
+        self.debug_check_balance();
+    }
+
+    /// The ids of the consolidated credits, which are no longer held as entries.
+    fn consolidated_ids(&self) -> Vec<TransferId> {
+        let held: HashSet<_> = self
+            .credits
+            .iter()
+            .chain(&self.debits)
+            .map(|entry| entry.transfer().id)
+            .collect();
+        self.transfer_ids
+            .iter()
+            .filter(|id| !held.contains(id))
+            .cloned()
+            .collect()
+    }
+
+    /// Mutates state.
+    pub fn append(&mut self, transfer: Transfer) {
+        self.append_entry(HistoryEntry::Unproven(transfer))
+    }
+
+    /// Mutates state, with a debit registered at our Replicas.
+    pub fn append_debit(&mut self, debit_proof: DebitAgreementProof) {
+        self.append_entry(HistoryEntry::RegisteredDebit(debit_proof))
     }
 
-    /// Test-helper API to simulate section payments.
-    #[cfg(feature = "simulated-payouts")]
-    pub fn simulated_debit(&mut self, transfer: Transfer) {
-        if self.id == transfer.id.actor {
-            match self.balance.checked_sub(transfer.amount) {
-                Some(amount) => self.balance = amount,
-                None => panic!("overflow when subtracting!"),
+    /// Mutates state, with a credit propagated from the sender's Replicas.
+    pub fn append_credit(&mut self, credit: ReceivedCredit) {
+        self.append_entry(HistoryEntry::PropagatedCredit(credit))
+    }
+
+    /// Mutates state, with a payout of a disbursement propagated from the sender's Replicas.
+    pub fn append_payout(&mut self, payout: ReceivedPayout) {
+        self.append_entry(HistoryEntry::PropagatedPayout(payout))
+    }
+
+    fn append_entry(&mut self, entry: HistoryEntry) {
+        self.stored_bytes += Self::size_of(&entry);
+        let transfer = entry.transfer();
+        let (id, to, amount) = (transfer.id, transfer.to, transfer.amount);
+        if self.is_own(&id.actor) {
+            match debited(self.balance, amount) {
+                Ok(amount) => self.balance = amount,
+                Err(_) => panic!("overflow when subtracting!"),
+            }
+            let _ = self.transfer_ids.insert(id);
+            let _ = self.positions.insert(id, self.next_debit());
+            if let Some((_, sums)) = &mut self.checksums {
+                sums.push(Self::checksum(&entry));
             }
-            let _ = self.transfer_ids.insert(transfer.id);
-            self.debits.push(transfer);
+            self.debit_tree.append(Self::leaf(&entry));
+            self.debits.push(entry);
+        } else if self.is_own(&to) {
+            match credited(self.balance, amount) {
+                Ok(amount) => self.balance = amount,
+                Err(_) => panic!("overflow when adding!"),
+            }
+            let _ = self.transfer_ids.insert(id);
+            let _ = self.positions.insert(id, self.credit_count() as u64);
+            if let Some((sums, _)) = &mut self.checksums {
+                sums.push(Self::checksum(&entry));
+            }
+            self.credit_tree.append(Self::leaf(&entry));
+            self.credits.push(entry);
         } else {
             panic!("Transfer does not belong to this account")
         }
+        self.debug_check_balance();
+    }
+
+    fn size_of(entry: &HistoryEntry) -> u64 {
+        bincode::serialized_size(entry).unwrap_or_default()
+    }
+
+    fn leaf(entry: &HistoryEntry) -> Digest {
+        hash(&[&bincode::serialize(entry.transfer()).unwrap_or_default()])
+    }
+
+    fn checksum(entry: &HistoryEntry) -> Digest {
+        hash(&[&bincode::serialize(entry).unwrap_or_default()])
+    }
+
+    /// In debug builds, cross-checks the maintained balance against the history.
+    fn debug_check_balance(&self) {
+        debug_assert_eq!(Some(self.balance), self.recompute_balance());
     }
 }
 
@@ -142,6 +1070,115 @@ mod test {
     use safe_nd::{PublicKey, XorName};
     use threshold_crypto::SecretKey;
 
+    #[test]
+    fn keeps_history_of_previous_keys_in_records() -> Result<()> {
+        // Arrange
+        let previous = get_random_pk();
+        let current = get_random_pk();
+        let mut account = Account::new(previous);
+        account.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: previous,
+            amount: Money::from_nano(100),
+        });
+        account.append(Transfer {
+            id: Dot::new(previous, 0),
+            to: get_random_pk(),
+            amount: Money::from_nano(30),
+        });
+
+        // Act
+        account.rotate_key(current);
+        account.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: previous,
+            amount: Money::from_nano(10),
+        });
+        let next = Transfer {
+            id: Dot::new(current, 1),
+            to: get_random_pk(),
+            amount: Money::from_nano(20),
+        };
+        let sequential = account.is_sequential(&next)?;
+        account.append(next);
+        let restored = Account::from_record(account.to_record())?;
+
+        // Assert
+        assert!(sequential);
+        assert!(account.balance() == Money::from_nano(60));
+        assert!(restored == account);
+        assert!(restored.previous_ids() == &[previous][..]);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_fees_in_records() -> Result<()> {
+        // Arrange
+        let id = get_random_pk();
+        let mut account = Account::new(id);
+        account.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: id,
+            amount: Money::from_nano(100),
+        });
+        let debit = Transfer {
+            id: Dot::new(id, 0),
+            to: get_random_pk(),
+            amount: Money::from_nano(60),
+        };
+        account.append(debit.clone());
+
+        // Act
+        account.pay_fee(debit.id, Money::from_nano(10));
+        account.receive_fee(Money::from_nano(5));
+        let restored = Account::from_record(account.to_record())?;
+
+        // Assert
+        assert!(account.balance() == Money::from_nano(35));
+        assert!(account.recompute_balance() == Some(account.balance()));
+        assert!(account.fee_paid(&debit.id) == Some(Money::from_nano(10)));
+        assert!(restored == account);
+        Ok(())
+    }
+
+    #[test]
+    fn computes_balances_at_indices() {
+        // Arrange
+        let id = get_random_pk();
+        let mut account = Account::new(id);
+        for amount in &[100, 50] {
+            account.append(Transfer {
+                id: Dot::new(get_random_pk(), 0),
+                to: id,
+                amount: Money::from_nano(*amount),
+            });
+        }
+        for counter in 0..2 {
+            let debit = Transfer {
+                id: Dot::new(id, counter),
+                to: get_random_pk(),
+                amount: Money::from_nano(30),
+            };
+            account.append(debit.clone());
+            account.pay_fee(debit.id, Money::from_nano(5));
+        }
+
+        // Act
+        let start = account.balance_at(0, 0);
+        let first_credit = account.balance_at(0, 1);
+        let first_debit = account.balance_at(1, 2);
+        let latest = account.balance_at(2, 2);
+
+        // Assert
+        assert!(start == Some(Money::zero()));
+        assert!(first_credit == Some(Money::from_nano(100)));
+        assert!(first_debit == Some(Money::from_nano(115)));
+        assert!(latest == Some(account.balance()));
+        assert!(account.balance_at(3, 2).is_none());
+        assert!(account.balance_at(2, 3).is_none());
+        assert!(account.balance_at(1, 0).is_none());
+    }
+
     #[test]
     fn appends_credits() {
         // Arrange
@@ -174,11 +1211,34 @@ mod test {
         assert!(account.balance() == balance.checked_add(balance).unwrap());
         assert!(credits.len() == 2);
         assert!(credits[1] == second_credit);
+        assert!(account.credits_iter(1).eq(credits[1..].iter()));
         assert!(debits.len() == 0);
         assert!(account.next_debit() == 0);
         assert!(is_sequential.is_ok() && is_sequential.unwrap());
     }
 
+    #[test]
+    fn detects_corrupted_entries() {
+        // Arrange
+        let credit = Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: get_random_pk(),
+            amount: Money::from_nano(10),
+        };
+        let mut account = Account::new(credit.to).with_checksums();
+        account.append(credit.clone());
+        assert!(account.verify_integrity().is_ok());
+
+        // Act
+        account.credits[0] = HistoryEntry::Unproven(Transfer {
+            to: get_random_pk(),
+            ..credit
+        });
+
+        // Assert
+        assert!(account.verify_integrity().is_err());
+    }
+
     #[test]
     fn appends_debits() {
         // Arrange
@@ -215,12 +1275,130 @@ mod test {
         assert!(credits[0] == first_credit);
         assert!(account.next_debit() == 1);
         assert!(is_sequential.is_ok() && is_sequential.unwrap());
+        assert!(account.recompute_balance() == Some(account.balance()));
+    }
+
+    #[test]
+    fn proves_inclusion_of_transfers() -> Result<()> {
+        // Arrange
+        let id = get_random_pk();
+        let mut account = Account::new(id);
+        let credits: Vec<_> = (0..5)
+            .map(|_| Transfer {
+                id: Dot::new(get_random_pk(), 0),
+                to: id,
+                amount: Money::from_nano(10),
+            })
+            .collect();
+        for credit in &credits {
+            account.append(credit.clone());
+        }
+        let debit = Transfer {
+            id: Dot::new(id, 0),
+            to: get_random_pk(),
+            amount: Money::from_nano(10),
+        };
+        account.append(debit.clone());
+        let root = account.history_root();
+
+        // Act
+        let credit_proof = account.prove_inclusion(&credits[3].id).unwrap();
+        let debit_proof = account.prove_inclusion(&debit.id).unwrap();
+        account.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: id,
+            amount: Money::from_nano(10),
+        });
+
+        // Assert
+        assert!(credit_proof.transfer == credits[3]);
+        assert!(credit_proof.verify(&root).is_ok());
+        assert!(debit_proof.verify(&root).is_ok());
+        assert!(credit_proof.verify(&account.history_root()).is_err());
+        let mut forged = debit_proof.clone();
+        forged.transfer.amount = Money::from_nano(1);
+        assert!(forged.verify(&root).is_err());
+        assert!(account.prove_inclusion(&Dot::new(id, 1)).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn finds_transfers() -> Result<()> {
+        // Arrange
+        let id = get_random_pk();
+        let mut account = Account::new(id);
+        let credits: Vec<_> = (0..3)
+            .map(|_| Transfer {
+                id: Dot::new(get_random_pk(), 0),
+                to: id,
+                amount: Money::from_nano(10),
+            })
+            .collect();
+        for credit in &credits {
+            account.append(credit.clone());
+        }
+        let debit = Transfer {
+            id: Dot::new(id, 0),
+            to: get_random_pk(),
+            amount: Money::from_nano(10),
+        };
+        account.append(debit.clone());
+
+        // Act
+        let state = account.compaction(SyncIndex {
+            credit_index: 1,
+            debit_index: 0,
+        })?;
+        account.compact(state);
+
+        // Assert
+        assert!(account.get(&credits[0].id).is_none());
+        assert!(account.contains(&credits[0].id));
+        let credit = account.get(&credits[2].id).unwrap();
+        assert!(credit.direction == PaymentDirection::Received);
+        assert!(credit.index == 2);
+        assert!(credit.transfer == credits[2]);
+        let found = account.get(&debit.id).unwrap();
+        assert!(found.direction == PaymentDirection::Sent);
+        assert!(found.index == 0);
+        assert!(found.transfer == debit);
+        assert!(account.get(&Dot::new(id, 1)).is_none());
+        assert!(account.prove_inclusion(&credits[2].id).is_some());
+        Ok(())
     }
 
     fn get_random_xor() -> XorName {
         XorName::from(get_random_pk())
     }
 
+    #[test]
+    fn pages_through_credits() {
+        // Arrange
+        let id = get_random_pk();
+        let mut account = Account::new(id);
+        for _ in 0..5 {
+            account.append(Transfer {
+                id: Dot::new(get_random_pk(), 0),
+                to: id,
+                amount: Money::from_nano(1),
+            });
+        }
+
+        // Act
+        let first = account.credits_page(0, 2);
+        let last = account.credits_page(4, 2);
+        let unbounded = account.credits_page(0, usize::MAX);
+
+        // Assert
+        assert!(first.transfers.len() == 2);
+        assert!(first.next == Some(2));
+        assert!(account.credits_page(2, 2).transfers == account.credits_since(2)[..2].to_vec());
+        assert!(last.transfers.len() == 1);
+        assert!(last.next.is_none());
+        assert!(unbounded.transfers.len() == 5);
+        assert!(account.debits_page(0, 2).transfers.is_empty());
+    }
+
     fn get_random_pk() -> PublicKey {
         PublicKey::from(SecretKey::random().public_key())
     }
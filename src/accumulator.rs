@@ -0,0 +1,307 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    clock::Timestamp,
+    codec::{verified_in, Codec, Payload},
+    quorum::shares_needed,
+};
+use safe_nd::{
+    DebitAgreementProof, Error, Result, Signature, SignedTransfer, TransferId, TransferValidated,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    iter,
+};
+use threshold_crypto::PublicKeySet;
+
+/// The validations of a debit by a group of Replicas, by the index of their
+/// key shares, as received by the Actor (see Actor::validation_progress).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationProgress {
+    /// The debit.
+    pub transfer_id: TransferId,
+    /// The PK set of the Replicas.
+    pub replicas: PublicKeySet,
+    /// The Replicas whose validations have been received.
+    pub received: BTreeSet<usize>,
+    /// The Replicas that sent a share that did not verify. A Replica
+    /// may still send a valid one, and then is also among those received.
+    pub rejected: BTreeSet<usize>,
+    /// Number of validations needed for the proof.
+    pub required: usize,
+    /// Whether the proof has been produced.
+    pub agreed: bool,
+    /// The seconds since the first validation, or rejected share, was received.
+    pub elapsed: Option<u64>,
+}
+
+impl ValidationProgress {
+    /// Number of validations still needed for the proof.
+    pub fn remaining(&self) -> usize {
+        self.required.saturating_sub(self.received.len())
+    }
+
+    /// The Replicas, of a group of the size, whose validations have not been received,
+    /// such as to send the debit to again. The size of the group is not known from its
+    /// key set, and is as known to the caller.
+    pub fn missing(&self, group_size: usize) -> Vec<usize> {
+        (0..group_size)
+            .filter(|index| !self.received.contains(index))
+            .collect()
+    }
+}
+
+/// Collects the validations of a transfer by a group of Replicas,
+/// until a quorum of them (threshold + 1) can be combined into the
+/// proof of agreement of the group.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationAccumulator {
+    signed_transfer: SignedTransfer,
+    replicas: PublicKeySet,
+    /// The validations received, by the index of their share.
+    validations: BTreeMap<usize, TransferValidated>,
+    proof: Option<DebitAgreementProof>,
+    /// The indices of the shares that did not verify.
+    rejected: BTreeSet<usize>,
+    /// When the first validation, or rejected share, was received, if recorded.
+    first_received_at: Option<Timestamp>,
+    /// The codecs the shares are accepted over.
+    codecs: Vec<Codec>,
+}
+
+impl ValidationAccumulator {
+    /// Accumulates validations of the transfer by the Replicas with the key set,
+    /// with shares over the canonical encoding.
+    pub fn new(signed_transfer: SignedTransfer, replicas: PublicKeySet) -> Self {
+        Self {
+            signed_transfer,
+            replicas,
+            validations: Default::default(),
+            proof: None,
+            rejected: Default::default(),
+            first_received_at: None,
+            codecs: vec![Codec::Canonical],
+        }
+    }
+
+    /// Accepts shares over any of the codecs instead, such as those accepted at the
+    /// height (see accepted_codecs).
+    pub fn with_codecs(mut self, codecs: Vec<Codec>) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
+    /// Number of validations received.
+    pub fn received(&self) -> usize {
+        self.validations.len()
+    }
+
+    /// Number of validations needed for the proof.
+    pub fn required(&self) -> usize {
+        shares_needed(&self.replicas)
+    }
+
+    /// The proof, once enough validations have been received.
+    pub fn proof(&self) -> Option<&DebitAgreementProof> {
+        self.proof.as_ref()
+    }
+
+    /// The validations received by the time, and the shares rejected.
+    pub fn progress(&self, now: Timestamp) -> ValidationProgress {
+        ValidationProgress {
+            transfer_id: self.signed_transfer.id(),
+            replicas: self.replicas.clone(),
+            received: self.validations.keys().copied().collect(),
+            rejected: self.rejected.clone(),
+            required: self.required(),
+            agreed: self.proof.is_some(),
+            elapsed: self.first_received_at.map(|at| now.saturating_sub(at)),
+        }
+    }
+
+    /// Adds the validation, if of our transfer and Replicas, not already received,
+    /// and with a valid signature share. Returns the proof when this validation
+    /// completes the quorum. Validations received after that are only recorded.
+    pub fn add(&mut self, validation: TransferValidated) -> Result<Option<DebitAgreementProof>> {
+        self.check(&validation)?;
+        self.verify_share(&validation)?;
+        let proof = self.proof_with(&validation);
+        self.insert(validation, proof.clone());
+        Ok(proof)
+    }
+
+    /// Checks that the validation is of our transfer and Replicas, and not already received.
+    /// Its signature share is not verified.
+    pub(crate) fn check(&self, validation: &TransferValidated) -> Result<()> {
+        if validation.signed_transfer != self.signed_transfer {
+            return Err(Error::from("Validation is of another transfer"));
+        }
+        if validation.replicas != self.replicas {
+            return Err(Error::from("Validation is by another set of Replicas"));
+        }
+        if self
+            .validations
+            .contains_key(&validation.replica_signature.index)
+        {
+            return Err(Error::from("Already received validation"));
+        }
+        Ok(())
+    }
+
+    /// The proof, if the validation, with a verified share, completes the quorum.
+    pub(crate) fn proof_with(&self, validation: &TransferValidated) -> Option<DebitAgreementProof> {
+        if self.proof.is_some() || self.received() + 1 != self.required() {
+            return None;
+        }
+        let sig_shares: BTreeMap<_, _> = self
+            .validations
+            .values()
+            .chain(iter::once(validation))
+            .map(|v| (v.replica_signature.index, v.replica_signature.share.clone()))
+            .collect();
+        // Combine shares to produce the main signature.
+        let sig = self.replicas.combine_signatures(&sig_shares).ok()?;
+        // Validate the main signature. If the shares were valid, this can't fail.
+        let public_key = self.replicas.public_key();
+        let payload = Payload::Proof(&self.signed_transfer);
+        if verified_in(&self.codecs, payload, |data| public_key.verify(&sig, data)) {
+            Some(DebitAgreementProof {
+                signed_transfer: self.signed_transfer.clone(),
+                debiting_replicas_sig: Signature::Bls(sig),
+            })
+        } else {
+            None // we have some corrupt data. (todo: Do we need to act on that fact?)
+        }
+    }
+
+    /// Records the validation, and the proof it completed, if any.
+    pub(crate) fn insert(
+        &mut self,
+        validation: TransferValidated,
+        proof: Option<DebitAgreementProof>,
+    ) {
+        let _ = self
+            .validations
+            .insert(validation.replica_signature.index, validation);
+        if proof.is_some() {
+            self.proof = proof;
+        }
+    }
+
+    /// Records that the Replica with the index sent a share that did not verify.
+    pub(crate) fn reject(&mut self, index: usize) {
+        let _ = self.rejected.insert(index);
+    }
+
+    /// Records the time a validation, or rejected share, was received, if the first.
+    pub(crate) fn stamp(&mut self, at: Timestamp) {
+        if self.first_received_at.is_none() {
+            self.first_received_at = Some(at);
+        }
+    }
+
+    fn verify_share(&self, validation: &TransferValidated) -> Result<()> {
+        let share = &validation.replica_signature;
+        let key = self.replicas.public_key_share(share.index);
+        let payload = Payload::Proof(&self.signed_transfer);
+        if verified_in(&self.codecs, payload, |data| key.verify(&share.share, data)) {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use crate::signable::{Signable, SignableProof, SignableTransfer};
+    use crdts::Dot;
+    use safe_nd::{Money, PublicKey, SignatureShare, Transfer};
+    use threshold_crypto::{SecretKey, SecretKeySet};
+
+    #[test]
+    fn accumulates_quorum_of_validations() -> Result<()> {
+        // Arrange
+        let keys = SecretKeySet::random(1, &mut rand::thread_rng());
+        let signed_transfer = get_signed_transfer();
+        let mut accumulator =
+            ValidationAccumulator::new(signed_transfer.clone(), keys.public_keys());
+        let other_keys = SecretKeySet::random(1, &mut rand::thread_rng());
+
+        // Act
+        let first = accumulator.add(get_validation(&signed_transfer, &keys, 0))?;
+        let duplicate = accumulator.add(get_validation(&signed_transfer, &keys, 0));
+        let mismatched = accumulator.add(get_validation(&signed_transfer, &other_keys, 1));
+        let second = accumulator.add(get_validation(&signed_transfer, &keys, 1))?;
+
+        // Assert
+        assert!(first.is_none());
+        assert!(duplicate.is_err());
+        assert!(mismatched.is_err());
+        assert!(accumulator.received() == accumulator.required());
+        assert!(second.is_some());
+        assert!(accumulator.proof() == second.as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn reports_progress_of_validations() -> Result<()> {
+        // Arrange
+        let keys = SecretKeySet::random(2, &mut rand::thread_rng());
+        let signed_transfer = get_signed_transfer();
+        let mut accumulator =
+            ValidationAccumulator::new(signed_transfer.clone(), keys.public_keys());
+
+        // Act
+        let _ = accumulator.add(get_validation(&signed_transfer, &keys, 1))?;
+        accumulator.stamp(100);
+        accumulator.reject(3);
+        accumulator.stamp(130);
+        let progress = accumulator.progress(160);
+
+        // Assert
+        assert!(progress.received.iter().eq([1].iter()));
+        assert!(progress.rejected.iter().eq([3].iter()));
+        assert!(progress.remaining() == 2);
+        assert!(progress.missing(4) == vec![0, 2, 3]);
+        assert!(progress.elapsed == Some(60));
+        assert!(progress.transfer_id == signed_transfer.id());
+        Ok(())
+    }
+
+    fn get_validation(
+        signed_transfer: &SignedTransfer,
+        keys: &SecretKeySet,
+        index: usize,
+    ) -> TransferValidated {
+        let data = SignableProof::new(signed_transfer).to_bytes();
+        TransferValidated {
+            signed_transfer: signed_transfer.clone(),
+            replica_signature: SignatureShare {
+                index,
+                share: keys.secret_key_share(index).sign(&data),
+            },
+            replicas: keys.public_keys(),
+        }
+    }
+
+    fn get_signed_transfer() -> SignedTransfer {
+        let sender = SecretKey::random();
+        let transfer = Transfer {
+            id: Dot::new(PublicKey::from(sender.public_key()), 0),
+            to: PublicKey::from(SecretKey::random().public_key()),
+            amount: Money::from_nano(10),
+        };
+        let data = SignableTransfer::new(&transfer).to_bytes();
+        SignedTransfer {
+            transfer,
+            actor_signature: Signature::Bls(sender.sign(&data)),
+        }
+    }
+}
@@ -7,16 +7,57 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    account::Account, ActorEvent, ReceivedCredit, ReplicaValidator, TransferInitiated,
-    TransferRegistrationSent, TransferValidated, TransferValidationReceived, TransfersSynched,
+    account::{Account, HistoryEntry},
+    accumulator::{ValidationAccumulator, ValidationProgress},
+    analytics::SpendingAnalytics,
+    attachment::{join_tags, Attachment, SignedAttachment, MEMO_KEY, TAGS_KEY},
+    attestation::ReadFloor,
+    budget::Budget,
+    cache::{VerificationCache, VerificationKey},
+    chart::ChartState,
+    checkpoint::CheckpointedHistory,
+    clock::{Clock, TimeSource, Timestamp},
+    codec::{accepted_codecs, verified_in, Codec, CodecMigration, Payload},
+    disbursement::{Disbursement, Payout},
+    economics::{EconomicParams, FeePolicy, SignedEconomicParams},
+    error::{Outcome, RejectionReason, TernaryResult, TransferError, TransferResult},
+    escrow::{EscrowDecision, EscrowRequest, EscrowResolution},
+    finality::Finality,
+    hashing::Digest,
+    import::{ImportHistory, ProofOfControl},
+    invoice::{Invoice, PaymentMatch},
+    limits::{SignedSpendingLimits, SpendingLimits},
+    messages::ActorCmd,
+    money::{checked_sum, covers, saturating_add, saturating_sub},
+    multisig::MultisigPolicy,
+    outbox::{Outbox, OutboxItem},
+    pending::DebitRelease,
+    pipeline::{TransferIntent, TransferQueue, TransferStatus},
+    quorum::shares_needed,
+    refund::Refund,
+    replica::Replica,
+    rotation::WalletKeyRotation,
+    schedule::{DueTransfer, ScheduleEnd, ScheduledTransfer, TransferSchedule},
+    search::PaymentDirection,
+    signable::{Signable, SignableTransfer},
+    signer::{Signer, SigningRequest, TransferSigner},
+    statement::Statement,
+    ActorEvent, AttachmentStored, CheckpointSynched, OutboxAcknowledged, OverageRefundInitiated,
+    QueuedTransferFailed, ReceivedCredit, ReplicaEvent, ReplicaValidator,
+    ScheduledTransferCancelled, TransferAborted, TransferInitiated, TransferQueued,
+    TransferRegistrationSent, TransferScheduled, TransferValidated, TransferValidationReceived,
+    TransfersSynched, ValidationShareRejected, WalletFeature,
 };
 use crdts::Dot;
 use itertools::Itertools;
 use safe_nd::{
-    AccountId, DebitAgreementProof, Error, Money, ReplicaEvent, Result, SafeKey, Signature,
-    SignatureShare, SignedTransfer, Transfer,
+    AccountId, DebitAgreementProof, Error, Money, PublicKey, Result, Signature, SignatureShare,
+    SignedTransfer, Transfer, TransferId,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
 };
-use std::collections::{BTreeMap, HashSet};
 use threshold_crypto::PublicKeySet;
 
 /// A signature share, with its index in the combined collection.
@@ -32,25 +73,80 @@ pub struct SecretKeyShare {
 /// that initiates transfers, by requesting Replicas
 /// to validate them, and then receive the proof of agreement.
 /// It also syncs transfers from the Replicas.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct Actor<V: ReplicaValidator> {
     id: AccountId,
-    client_safe_key: SafeKey,
+    /// Signs our transfers, and is the only holder of our secret key.
+    signer: Signer,
     /// Set of all transfers impacting a given identity
     account: Account,
     /// Ensures that the actor's transfer
     /// initiations (ValidateTransfer cmd) are sequential.
     next_debit_version: u64,
+    /// The debit initiated, until registered or aborted.
+    initiated: Option<SignedTransfer>,
+    /// The debits aborted from counters not yet registered,
+    /// whose validations arriving later are refused.
+    aborted: Vec<SignedTransfer>,
+    /// The credits of invoices whose overage has been refunded,
+    /// with the id of the refund, until the refund is dropped.
+    refunded_overages: BTreeMap<TransferId, TransferId>,
+    /// The attachments of the senders of our credits, by the credit attached to.
+    attachments: HashMap<TransferId, SignedAttachment>,
     /// When a transfer is initiated, validations are accumulated here.
     /// After quorum is reached and proof produced, the set is cleared.
-    accumulating_validations: BTreeMap<PublicKeySet, HashSet<TransferValidated>>,
+    accumulating_validations: BTreeMap<PublicKeySet, ValidationAccumulator>,
     /// The PK Set of the Replicas
     replicas: PublicKeySet,
     /// The passed in replica_validator, contains the logic from upper layers
     /// for determining if a remote group of Replicas, represented by a PublicKey, is indeed valid.
     replica_validator: V,
+    /// The source of time for all time-based features.
+    clock: Clock,
+    /// Outcomes of signature verifications, for retransmitted duplicates.
+    verification_cache: VerificationCache,
+    /// The artifacts produced, until acknowledged as delivered.
+    outbox: Outbox,
+    /// The transfers queued, to be initiated one at a time.
+    queue: TransferQueue,
+    /// The transfers scheduled to recur, with the payments made of each.
+    schedule: TransferSchedule,
+    /// The economic rules of our section, as last fetched from our Replicas.
+    economic_params: Option<EconomicParams>,
+    /// The analytics of our spending, if kept.
+    analytics: Option<SpendingAnalytics>,
+    /// The fees our Replicas charge for debits, if any.
+    fee_policy: Option<Arc<dyn FeePolicy>>,
+    /// The move between the codecs that Replicas sign in, if any.
+    codec_migration: Option<CodecMigration>,
+}
+
+/// Equal if in the same state, with the same key. The signer, the clock and the fee policy
+/// are handles to what is plugged in, and not compared, nor is the cache.
+impl<V: ReplicaValidator + PartialEq> PartialEq for Actor<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.signer.public_key() == other.signer.public_key()
+            && self.account == other.account
+            && self.next_debit_version == other.next_debit_version
+            && self.initiated == other.initiated
+            && self.aborted == other.aborted
+            && self.refunded_overages == other.refunded_overages
+            && self.attachments == other.attachments
+            && self.accumulating_validations == other.accumulating_validations
+            && self.replicas == other.replicas
+            && self.replica_validator == other.replica_validator
+            && self.outbox == other.outbox
+            && self.queue == other.queue
+            && self.schedule == other.schedule
+            && self.economic_params == other.economic_params
+            && self.analytics == other.analytics
+            && self.codec_migration == other.codec_migration
+    }
 }
 
+impl<V: ReplicaValidator + Eq> Eq for Actor<V> {}
+
 impl<V: ReplicaValidator> Actor<V> {
     /// Use this ctor for a new instance,
     /// or to rehydrate from events ([see the synch method](Actor::synch)).
@@ -60,42 +156,222 @@ impl<V: ReplicaValidator> Actor<V> {
     /// If upper layer trusts them, the validator might do nothing but return "true".
     /// If it wants to execute some logic for verifying that the remote replicas are in fact part of the system,
     /// before accepting credits, it then implements that in the replica_validator.
-    pub fn new(client_safe_key: SafeKey, replicas: PublicKeySet, replica_validator: V) -> Actor<V> {
-        let id = client_safe_key.public_key();
+    /// The signer is a SafeKey, or any other TransferSigner, such as a hardware wallet.
+    /// Without the system-clock feature, the clock to read time from is given (see Clock).
+    pub fn new<S: TransferSigner + 'static>(
+        signer: S,
+        replicas: PublicKeySet,
+        replica_validator: V,
+        #[cfg(not(feature = "system-clock"))] clock: Clock,
+    ) -> Actor<V> {
+        #[cfg(feature = "system-clock")]
+        let clock = Clock::default();
+        let id = signer.public_key();
         Actor {
             id,
-            client_safe_key,
+            signer: Signer::new(signer),
             replicas,
             replica_validator,
             account: Account::new(id),
             next_debit_version: 0,
+            initiated: None,
+            aborted: Default::default(),
+            refunded_overages: Default::default(),
+            attachments: Default::default(),
+            codec_migration: None,
             accumulating_validations: Default::default(),
+            clock,
+            verification_cache: Default::default(),
+            outbox: Default::default(),
+            queue: Default::default(),
+            schedule: Default::default(),
+            economic_params: None,
+            analytics: None,
+            fee_policy: None,
         }
     }
 
     /// Temp, for test purposes
-    pub fn from_snapshot(
+    pub fn from_snapshot<S: TransferSigner + 'static>(
         account: Account,
-        client_safe_key: SafeKey,
+        signer: S,
         replicas: PublicKeySet,
         replica_validator: V,
+        #[cfg(not(feature = "system-clock"))] clock: Clock,
     ) -> Actor<V> {
-        let id = client_safe_key.public_key();
+        #[cfg(feature = "system-clock")]
+        let clock = Clock::default();
+        let id = signer.public_key();
         Actor {
             id,
-            client_safe_key,
+            signer: Signer::new(signer),
             replicas,
             replica_validator,
+            next_debit_version: account.next_debit(),
             account,
-            next_debit_version: 0,
+            initiated: None,
+            aborted: Default::default(),
+            refunded_overages: Default::default(),
+            attachments: Default::default(),
+            codec_migration: None,
             accumulating_validations: Default::default(),
+            clock,
+            verification_cache: Default::default(),
+            outbox: Default::default(),
+            queue: Default::default(),
+            schedule: Default::default(),
+            economic_params: None,
+            analytics: None,
+            fee_policy: None,
         }
     }
 
+    /// Imports the wallet of an externally generated key. The signer must hold the key
+    /// the proof of control was signed with, and our state is initialised from the
+    /// history queried from our Replicas. The wallet may have activity this device has
+    /// never seen, so the history must account for all credits and debits proven by the
+    /// balance of our Replicas: otherwise our debits would reuse counters already spent.
+    /// The Replicas consume the nonce of the proof before serving the history
+    /// (see Replica::consume_import_nonce), so that the proof is only good for one import.
+    pub fn import<S: TransferSigner + 'static>(
+        signer: S,
+        replicas: PublicKeySet,
+        replica_validator: V,
+        proof: &ProofOfControl,
+        history: ImportHistory,
+        #[cfg(not(feature = "system-clock"))] clock: Clock,
+    ) -> Result<Actor<V>> {
+        if proof.challenge.wallet != signer.public_key() {
+            return Err(Error::from("Proof of control is not of the imported key"));
+        }
+        proof.verify()?;
+        history
+            .balance
+            .verify(&PublicKey::Bls(replicas.public_key()))?;
+        let expected = history.balance.attestation;
+        if expected.wallet != signer.public_key() {
+            return Err(Error::from("Balance is not of the imported wallet"));
+        }
+
+        let mut actor = Self::new(
+            signer,
+            replicas,
+            replica_validator,
+            #[cfg(not(feature = "system-clock"))]
+            clock,
+        );
+        let credits = actor.validate_credits(&history.events);
+        let debits = actor.validate_debits(history.events);
+        actor.apply(ActorEvent::TransfersSynched(TransfersSynched {
+            credits,
+            debits,
+        }));
+
+        let credit_index = actor.account.credit_count() as u64;
+        let debit_index = actor.account.next_debit();
+        if credit_index < expected.credit_index || debit_index < expected.debit_index {
+            return Err(Error::from(
+                "History is missing prior activity of the wallet",
+            ));
+        }
+        if credit_index == expected.credit_index
+            && debit_index == expected.debit_index
+            && actor.balance() != expected.balance
+        {
+            return Err(Error::from("History does not add up to the proven balance"));
+        }
+        Ok(actor)
+    }
+
+    /// Sets the source of time used by this Actor.
+    pub fn with_time_source<T: TimeSource + 'static>(mut self, source: T) -> Self {
+        self.clock = Clock::new(source);
+        self
+    }
+
+    /// Sets the cache of signature verification outcomes used by this Actor.
+    pub fn with_verification_cache(mut self, cache: VerificationCache) -> Self {
+        self.verification_cache = cache;
+        self
+    }
+
+    /// Caches the economic rules fetched from our Replicas (see Replica::economic_params),
+    /// for transfers to be checked against them before being signed.
+    pub fn cache_economic_params(&mut self, signed: &SignedEconomicParams) -> Result<()> {
+        signed.verify(&self.replicas)?;
+        self.economic_params = Some(signed.params);
+        Ok(())
+    }
+
+    /// Projects the fees charged by our Replicas (see Replica::with_fee_policy) onto our
+    /// balance: debits are only initiated if the balance covers the fee on top of the amount,
+    /// and the fee is deducted from the balance once the debit is registered.
+    pub fn with_fee_policy<P: FeePolicy + 'static>(mut self, policy: P) -> Self {
+        self.fee_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Sets the move between the codecs that Replicas sign in, by the height of our
+    /// clock, as set at our Replicas (see Replica::with_codec_migration). Without one,
+    /// only signatures over the canonical encoding are accepted.
+    pub fn with_codec_migration(mut self, migration: CodecMigration) -> Self {
+        self.codec_migration = Some(migration);
+        self
+    }
+
+    /// Restores the outbox persisted before a restart.
+    pub fn with_outbox(mut self, outbox: Outbox) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    /// Keeps analytics of our spending, computed locally from the events applied,
+    /// starting from those persisted before a restart, or from the default ones.
+    pub fn with_spending_analytics(mut self, analytics: SpendingAnalytics) -> Self {
+        self.analytics = Some(analytics);
+        self
+    }
+
     /// -----------------------------------------------------------------
     /// ---------------------- Queries ----------------------------------
     /// -----------------------------------------------------------------
 
+    /// Query for the analytics of our spending, if kept (see with_spending_analytics).
+    /// They are to be persisted along with the events applied.
+    pub fn spending_analytics(&self) -> Option<&SpendingAnalytics> {
+        self.analytics.as_ref()
+    }
+
+    /// Query for the artifacts produced, that are yet to be acknowledged as delivered.
+    /// It is to be persisted after each applied event, and sent from after a restart.
+    pub fn outbox(&self) -> &Outbox {
+        &self.outbox
+    }
+
+    /// Query for the transfers queued, and the one in flight, if any.
+    pub fn transfer_queue(&self) -> &TransferQueue {
+        &self.queue
+    }
+
+    /// Query for the transfers scheduled to recur, with the payments made of each.
+    pub fn transfer_schedule(&self) -> &TransferSchedule {
+        &self.schedule
+    }
+
+    /// Query for how far the queued transfer with the sequence number has come,
+    /// if it is queued, or among the last finished ones.
+    pub fn transfer_status(&self, sequence: u64) -> Option<TransferStatus> {
+        let (_, id) = self.queue.get(sequence)?;
+        if let Some(reason) = self.queue.failure(sequence) {
+            return Some(TransferStatus::Failed(reason.to_string()));
+        }
+        match id.and_then(|id| self.finality(&id)) {
+            Some(Finality::Validated) => Some(TransferStatus::Validated),
+            Some(_) => Some(TransferStatus::Registered),
+            None => Some(TransferStatus::Pending),
+        }
+    }
+
     /// Query for the id of the Actor.
     pub fn id(&self) -> AccountId {
         self.id
@@ -111,119 +387,742 @@ impl<V: ReplicaValidator> Actor<V> {
         self.account.debits_since(index)
     }
 
-    /// Query for the balance of the Actor.
+    /// Query for the statement of the Actor (see Statement). The credits and debits of
+    /// the Actor are synched separately, so the credits come first in it.
+    pub fn statement(&self) -> Statement {
+        Statement::of(&self.account, &[], Some(&self.attachments))
+    }
+
+    /// Query for the balance of the Actor, less the fees of our debits, if projected
+    /// (see with_fee_policy).
     pub fn balance(&self) -> Money {
         self.account.balance()
     }
 
+    /// Query for the indices of our history that responses of our Replicas
+    /// must reflect, for us to read our own writes (see Client::fresh_balance).
+    pub fn read_floor(&self) -> ReadFloor {
+        ReadFloor::of(&self.account)
+    }
+
+    /// Query for the typed credit entries, carrying their proofs.
+    pub fn credit_entries(&self) -> &[HistoryEntry] {
+        self.account.credit_entries()
+    }
+
+    /// Query for the typed debit entries, carrying their proofs.
+    pub fn debit_entries(&self) -> &[HistoryEntry] {
+        self.account.debit_entries()
+    }
+
+    /// Query for how final a transfer of ours is: our debits are Validated once
+    /// we hold the proof of agreement, and Registered once we have sent it for
+    /// registration. Our credits are PropagatedQuorum once synched.
+    pub fn finality(&self, id: &TransferId) -> Option<Finality> {
+        if self.account.contains(id) {
+            if id.actor == self.id {
+                Some(Finality::Registered)
+            } else {
+                Some(Finality::PropagatedQuorum)
+            }
+        } else if self
+            .accumulating_validations
+            .values()
+            .filter_map(ValidationAccumulator::proof)
+            .any(|proof| proof.id() == *id)
+        {
+            Some(Finality::Validated)
+        } else {
+            None
+        }
+    }
+
+    /// Query for the progress of the validations of our debit in flight, by each group of
+    /// Replicas that has sent any: which Replicas have sent theirs, which sent shares that
+    /// did not verify, and for how long they have been coming in, such as to send the debit
+    /// again to the Replicas missing, or to show progress. Empty if the debit is not in flight.
+    pub fn validation_progress(&self, id: &TransferId) -> Vec<ValidationProgress> {
+        let now = self.clock.now();
+        self.accumulating_validations
+            .values()
+            .map(|accumulator| accumulator.progress(now))
+            .filter(|progress| progress.transfer_id == *id)
+            .collect()
+    }
+
+    /// Query for the state of our debits, as charted by StateChart::of(Machine::Actor).
+    pub fn chart_state(&self) -> ChartState {
+        if self.accumulating_validations.is_empty() {
+            ChartState::Idle
+        } else if self
+            .accumulating_validations
+            .values()
+            .any(|accumulator| accumulator.proof().is_some())
+        {
+            ChartState::Agreed
+        } else {
+            ChartState::Accumulating
+        }
+    }
+
     /// -----------------------------------------------------------------
     /// ---------------------- Cmds -------------------------------------
     /// -----------------------------------------------------------------
 
     /// Step 1. Build a valid cmd for validation of a debit.
-    pub fn transfer(&self, amount: Money, to: AccountId) -> Result<TransferInitiated> {
+    pub fn transfer(&self, amount: Money, to: AccountId) -> TransferResult<TransferInitiated> {
+        let request = self.signing_request(amount, to)?;
+        let actor_signature = self.signer.sign(&request.payload)?;
+        self.complete_transfer(request, actor_signature)
+    }
+
+    /// Queues a transfer, instead of initiating it right away, so that several transfers
+    /// can be made without waiting for each to complete. Queued transfers are initiated
+    /// in the order they were queued, each once the one before it is registered, with
+    /// the counter following it (see dispatch). The balance is checked in its turn.
+    pub fn queue_transfer(&self, amount: Money, to: AccountId) -> TransferResult<TransferQueued> {
         if to == self.id {
-            return Err(Error::from("Sender and recipient are the same"));
+            return Err(TransferError::SameSenderAndRecipient);
+        }
+        if amount == Money::zero() {
+            return Err(TransferError::ZeroValueTransfer);
         }
+        Ok(TransferQueued {
+            intent: TransferIntent {
+                sequence: self.queue.next_sequence(),
+                to,
+                amount,
+            },
+        })
+    }
 
-        let id = Dot::new(self.id, self.account.next_debit());
+    /// Step 1, for queued transfers. Builds the cmd for validation of the next queued
+    /// transfer, once the one before it is registered. Nothing if none is queued, or one
+    /// is in flight. A transfer that can not be initiated, such as for a lack of balance,
+    /// is to be dropped with [fail_queued](Actor::fail_queued), for the next to go on.
+    /// Transfers initiated other than from the queue are to be completed before it.
+    pub fn dispatch(&self) -> Outcome<TransferInitiated> {
+        if self.queue.in_flight().is_some() {
+            return Outcome::no_change();
+        }
+        match self.queue.queued().first() {
+            None => Outcome::no_change(),
+            Some(intent) => self.transfer(intent.amount, intent.to).map(Some),
+        }
+    }
+
+    /// Drops the queued transfer that could not be initiated, or that was refused by
+    /// our Replicas while in flight, for the error. A transfer agreed by a quorum of
+    /// our Replicas is not dropped, as it is to be registered.
+    pub fn fail_queued(
+        &self,
+        sequence: u64,
+        error: &TransferError,
+    ) -> Result<QueuedTransferFailed> {
+        match self.transfer_status(sequence) {
+            Some(TransferStatus::Pending) => Ok(QueuedTransferFailed {
+                sequence,
+                reason: error.to_string(),
+            }),
+            Some(_) => Err(Error::from("Queued transfer is no longer pending")),
+            None => Err(Error::from("No such queued transfer")),
+        }
+    }
+
+    /// Schedules a transfer to recur every interval, in seconds, from when the first payment
+    /// is due until the end, such as for a subscription. The payments are not made by the
+    /// Actor, but returned once due by [due_transfers](Actor::due_transfers), to be signed.
+    pub fn schedule_transfer(
+        &self,
+        amount: Money,
+        to: AccountId,
+        first_due: Timestamp,
+        interval: u64,
+        end: ScheduleEnd,
+    ) -> TransferResult<TransferScheduled> {
+        if to == self.id {
+            return Err(TransferError::SameSenderAndRecipient);
+        }
+        if amount == Money::zero() {
+            return Err(TransferError::ZeroValueTransfer);
+        }
+        Replica::check_recipient(&to)?;
+        if interval == 0 {
+            return Err(Error::from("Scheduled transfers recur at an interval").into());
+        }
+        Ok(TransferScheduled {
+            scheduled: ScheduledTransfer {
+                id: self.schedule.next_id(),
+                to,
+                amount,
+                first_due,
+                interval,
+                end,
+            },
+        })
+    }
+
+    /// Cancels the scheduled transfer, so that no more payments of it are due.
+    /// A payment of it in flight is to be completed, or aborted, as any other debit.
+    pub fn cancel_scheduled(&self, id: u64) -> Result<ScheduledTransferCancelled> {
+        match self.schedule.get(id) {
+            Some(_) => Ok(ScheduledTransferCancelled { id }),
+            None => Err(Error::from("No such scheduled transfer")),
+        }
+    }
 
+    /// Query for the payments of our scheduled transfers due by the time and not yet
+    /// made, the earliest due first, as many as our balance covers, each ready to sign at
+    /// its counter: following our debit in flight, if any, and the payment before it.
+    /// Each is completed with [complete_transfer](Actor::complete_transfer) once the one
+    /// before it is registered. Debits initiated otherwise take the counters, so the
+    /// payments are then to be queried again.
+    pub fn due_transfers(&self, now: Timestamp) -> Result<Vec<DueTransfer>> {
+        let (mut counter, mut balance) = match &self.initiated {
+            Some(in_flight) => (
+                in_flight.id().counter + 1,
+                saturating_sub(self.balance(), in_flight.transfer.amount),
+            ),
+            None => (self.account.next_debit(), self.balance()),
+        };
+        let mut due_transfers = vec![];
+        for (scheduled, payment, due_at) in self.schedule.due(now) {
+            if !covers(balance, scheduled.amount) {
+                break;
+            }
+            balance = saturating_sub(balance, scheduled.amount);
+            let transfer = Transfer {
+                id: Dot::new(self.id, counter),
+                to: scheduled.to,
+                amount: scheduled.amount,
+            };
+            due_transfers.push(DueTransfer {
+                schedule: scheduled.id,
+                payment,
+                due_at,
+                request: SigningRequest::new(transfer)?,
+            });
+            counter += 1;
+        }
+        Ok(due_transfers)
+    }
+
+    /// Aborts the debit we initiated, such as when it failed to reach a quorum of
+    /// validations, so that the next debit takes its counter. Validations of it arriving
+    /// later are refused, and it is no longer sent from the outbox. A debit agreed by a
+    /// quorum is not aborted, as it is to be registered. The release of the debit is sent
+    /// from the outbox instead, as Replicas that validated it refuse another debit from
+    /// the counter until they have agreed on the release (see Replica::release_debit).
+    pub fn abort(&self, transfer_id: TransferId) -> Result<TransferAborted> {
+        let signed_transfer = match &self.initiated {
+            Some(signed_transfer) if signed_transfer.id() == transfer_id => signed_transfer,
+            _ => return Err(Error::from("No such transfer in flight")),
+        };
+        if self.finality(&transfer_id).is_some() {
+            return Err(Error::from("Transfer is agreed, and is to be registered"));
+        }
+        Ok(TransferAborted {
+            signed_transfer: signed_transfer.clone(),
+            release: DebitRelease::new(&self.signer, transfer_id)?,
+        })
+    }
+
+    /// Step 1, for a debit paying back a credit of ours to its sender, such as a merchant
+    /// refunding a payment, for the Replicas to validate (see Replica::validate_refund).
+    /// The debit is initiated as any other, by applying TransferInitiated with the
+    /// signed transfer of the refund.
+    pub fn refund(&self, original: TransferId, amount: Money) -> TransferResult<Refund> {
+        match self.account.get(&original) {
+            Some(info) if info.direction == PaymentDirection::Received => {
+                if !covers(info.transfer.amount, amount) {
+                    return Err(Error::from("Refund exceeds the credit").into());
+                }
+            }
+            _ => return Err(Error::from("No such credit to refund").into()),
+        }
+        let initiated = self.transfer(amount, original.actor)?;
+        Ok(Refund::new(
+            &self.signer,
+            initiated.signed_transfer,
+            original,
+        )?)
+    }
+
+    /// Step 1, for a debit crediting several recipients atomically, such as a payroll.
+    /// The outputs are paid with a single disbursement (see disburse), whose sum is
+    /// checked by our Replicas, and which credits each recipient at the Replicas of its
+    /// own group once the debit is registered. Outputs to the same recipient are paid as one.
+    pub fn transfer_to_many(
+        &self,
+        outputs: Vec<(AccountId, Money)>,
+    ) -> TransferResult<Disbursement> {
+        let mut payouts: Vec<Payout> = vec![];
+        for (to, amount) in outputs {
+            match payouts.iter_mut().find(|payout| payout.to == to) {
+                Some(payout) => match checked_sum(vec![payout.amount, amount]) {
+                    Some(sum) => payout.amount = sum,
+                    None => return Err(Error::from("Payouts overflow").into()),
+                },
+                None => payouts.push(Payout { to, amount }),
+            }
+        }
+        self.disburse(payouts)
+    }
+
+    /// Step 1, for many payouts at once, such as of farming rewards. Builds a disbursement
+    /// of the payouts with a single debit, from us to ourselves, of their sum, to be
+    /// validated by our Replicas as a whole (see Replica::validate_disbursement). The debit
+    /// is initiated as any other, and its validations aggregated as those of any other.
+    pub fn disburse(&self, payouts: Vec<Payout>) -> TransferResult<Disbursement> {
+        let amount = match checked_sum(payouts.iter().map(|payout| payout.amount)) {
+            Some(amount) => amount,
+            None => return Err(Error::from("Payouts overflow").into()),
+        };
+        for payout in &payouts {
+            if payout.to == self.id {
+                return Err(TransferError::SameSenderAndRecipient);
+            }
+            if payout.amount == Money::zero() {
+                return Err(TransferError::ZeroValueTransfer);
+            }
+            Replica::check_recipient(&payout.to)?;
+            if let Some(params) = &self.economic_params {
+                if !covers(payout.amount, params.dust_threshold) {
+                    return Err(TransferError::Unsatisfied(
+                        RejectionReason::BelowDustThreshold {
+                            threshold: params.dust_threshold,
+                            requested: payout.amount,
+                        },
+                    ));
+                }
+            }
+        }
         // ensures one debit is completed at a time
         if self.next_debit_version != self.account.next_debit() {
-            return Err(Error::from("Current pending debit has not been completed"));
+            return Err(TransferError::PendingDebit);
         }
-        if self.next_debit_version != id.counter {
-            return Err(Error::from("Debit already proposed or out of order"));
+        if !covers(self.balance(), amount) {
+            return Err(Error::InsufficientBalance.into());
         }
-        if amount > self.balance() {
-            return Err(Error::InsufficientBalance);
+        self.check_fee(amount, self.id)?;
+        let id = Dot::new(self.id, self.account.next_debit());
+        let request = SigningRequest::new(Transfer {
+            id,
+            to: self.id,
+            amount,
+        })?;
+        let signed_transfer = SignedTransfer {
+            actor_signature: self.signer.sign(&request.payload)?,
+            transfer: request.transfer,
+        };
+        let disbursement = Disbursement::new(&self.signer, signed_transfer, payouts)?;
+        disbursement.verify()?;
+        Ok(disbursement)
+    }
+
+    /// Step 1, for signers that sign asynchronously. Builds the request for a signature
+    /// of a debit, the payload of which is handed to the signer. The cmd for validation
+    /// of the debit is then built with the signature (see Actor::complete_transfer).
+    pub fn signing_request(&self, amount: Money, to: AccountId) -> TransferResult<SigningRequest> {
+        self.check_transfer(amount, to)?;
+        let id = Dot::new(self.id, self.account.next_debit());
+        Ok(SigningRequest::new(Transfer { id, to, amount })?)
+    }
+
+    /// Runs the checks that our Replicas run on a debit against our local history,
+    /// without signing anything, so that apps can show why a transfer would be refused
+    /// before any round trip. Also refused while a debit of ours is in flight, as our
+    /// Replicas refuse another debit from its counter (see abort). Passing them does
+    /// not guarantee validation, as our Replicas may know of debits or rules we do not.
+    pub fn preflight(&self, amount: Money, to: AccountId) -> TransferResult<()> {
+        if self.initiated.is_some() {
+            return Err(TransferError::PendingDebit);
         }
-        let transfer = Transfer { id, to, amount };
-        match self.sign(&transfer) {
-            Ok(actor_signature) => {
-                let signed_transfer = SignedTransfer {
-                    transfer,
-                    actor_signature,
-                };
-                Ok(TransferInitiated { signed_transfer })
+        self.check_transfer(amount, to)
+    }
+
+    /// Builds the cmd for validation of a debit, with the signature returned by the
+    /// signer for the request, verifying that the signature is ours, over the transfer,
+    /// and that the transfer is still our next debit.
+    pub fn complete_transfer(
+        &self,
+        request: SigningRequest,
+        actor_signature: Signature,
+    ) -> TransferResult<TransferInitiated> {
+        let signed_transfer = SignedTransfer {
+            transfer: request.transfer,
+            actor_signature,
+        };
+        if signed_transfer.from() != self.id {
+            return Err(TransferError::NotOurTransfer);
+        }
+        if signed_transfer.transfer.id.counter != self.account.next_debit() {
+            return Err(TransferError::OutOfOrderDebit {
+                expected: self.account.next_debit(),
+                got: signed_transfer.transfer.id.counter,
+            });
+        }
+        self.verify_is_our_transfer(&signed_transfer)?;
+        Ok(TransferInitiated { signed_transfer })
+    }
+
+    /// Builds the cmd for validation of a debit spent from an envelope of our budget,
+    /// refusing amounts exceeding what remains of it. Once initiated, the transfer
+    /// is to be recorded in the budget with Budget::record.
+    pub fn transfer_from_envelope(
+        &self,
+        budget: &Budget,
+        envelope: &str,
+        amount: Money,
+        to: AccountId,
+    ) -> TransferResult<TransferInitiated> {
+        budget.check(envelope, amount)?;
+        self.transfer(amount, to)
+    }
+
+    /// Matches our credits against an invoice we issued, by the memo of their
+    /// attachments (see store_attachment), and if it has been
+    /// overpaid, builds the cmd for validation of a refund of the overage to the payer.
+    /// The refund goes through the same steps as any other transfer. The credits matched
+    /// are recorded as refunded, and an invoice is refused once any of them has been,
+    /// unless that refund was dropped before registration.
+    pub fn refund_overage(
+        &self,
+        invoice: &Invoice,
+    ) -> TransferResult<Option<OverageRefundInitiated>> {
+        if invoice.payee != self.id {
+            return Err(Error::from("Invoice was not issued by this actor").into());
+        }
+        let credits = self.account.credits_iter(0).map(|credit| {
+            let memo = self
+                .attachments
+                .get(&credit.id)
+                .and_then(|signed| signed.attachment.get_memo());
+            (credit, memo)
+        });
+        let report = invoice.match_payment(credits);
+        if report
+            .matched
+            .iter()
+            .any(|id| self.refunded_overages.contains_key(id))
+        {
+            return Err(Error::from("Overage of the invoice has already been refunded").into());
+        }
+        match report.status {
+            PaymentMatch::Overpaid { overage, .. } => {
+                let initiated = self.transfer(overage, invoice.payer)?;
+                Ok(Some(OverageRefundInitiated {
+                    refunded: report.matched,
+                    initiated,
+                }))
             }
-            Err(e) => Err(e),
+            _ => Ok(None),
+        }
+    }
+
+    /// Signs the challenge returned by Replicas when our account is quarantined,
+    /// confirming that the transfer is ours (see Replica::validate_confirmed).
+    pub fn sign_challenge(&self, challenge: &Digest) -> Result<Signature> {
+        self.signer.sign(challenge)
+    }
+
+    /// Signs the entries as an attachment to a transfer of ours, to be sent to the
+    /// Replicas of the recipient once the credit has been propagated to them.
+    pub fn attach(
+        &self,
+        transfer: &Transfer,
+        entries: BTreeMap<String, String>,
+    ) -> Result<SignedAttachment> {
+        if transfer.id.actor != self.id {
+            return Err(Error::from("Only the sender can attach to a transfer"));
+        }
+        let attachment = Attachment::new(transfer.id, transfer.to, entries)?;
+        match bincode::serialize(&attachment) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise attachment".into())),
+            Ok(data) => Ok(SignedAttachment {
+                attachment,
+                signature: self.signer.sign(&data)?,
+            }),
+        }
+    }
+
+    /// Stores the attachment of the sender with a credit of ours, such as queried from
+    /// our Replicas (see Replica::attachment), for its memo to show in our statement.
+    /// A credit has at most one attachment.
+    pub fn store_attachment(
+        &self,
+        signed_attachment: &SignedAttachment,
+    ) -> TransferResult<AttachmentStored> {
+        signed_attachment.verify()?;
+        let attachment = &signed_attachment.attachment;
+        if attachment.to != self.id || !self.account.contains(&attachment.transfer_id) {
+            return Err(Error::from("No such credit").into());
+        }
+        if self.attachments.contains_key(&attachment.transfer_id) {
+            return Err(Error::DataExists.into());
+        }
+        Ok(AttachmentStored {
+            signed_attachment: signed_attachment.clone(),
+        })
+    }
+
+    /// Signs the memo as an attachment to a transfer of ours (see attach), such as the
+    /// reference of the payment for the recipient to reconcile its invoices by.
+    pub fn attach_memo(&self, transfer: &Transfer, memo: &str) -> Result<SignedAttachment> {
+        let mut entries = BTreeMap::new();
+        let _ = entries.insert(MEMO_KEY.to_string(), memo.to_string());
+        self.attach(transfer, entries)
+    }
+
+    /// Signs the tags as an attachment to a transfer of ours (see attach), for the
+    /// recipient to find the credit by any of them (see Replica::credits_tagged).
+    pub fn attach_tags(&self, transfer: &Transfer, tags: &[&str]) -> Result<SignedAttachment> {
+        let mut entries = BTreeMap::new();
+        let _ = entries.insert(TAGS_KEY.to_string(), join_tags(tags)?);
+        self.attach(transfer, entries)
+    }
+
+    /// Authorizes the upgrade of our account with the feature (see Replica::upgrade_wallet).
+    pub fn authorize_upgrade(&self, feature: &WalletFeature) -> Result<Signature> {
+        match bincode::serialize(&(&self.id, feature)) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise feature".into())),
+            Ok(data) => self.signer.sign(&data),
+        }
+    }
+
+    /// Authorizes our wallet to be owned by several keys (see Replica::set_multisig_policy).
+    pub fn authorize_multisig(&self, policy: &MultisigPolicy) -> Result<Signature> {
+        self.signer.sign(&policy.to_bytes(&self.id)?)
+    }
+
+    /// Signs limits on the debits of our wallet, as the update of the sequence given
+    /// by our Replicas (see Replica::set_spending_limits and Replica::limits_sequence).
+    pub fn limit_spending(
+        &self,
+        limits: SpendingLimits,
+        sequence: u64,
+    ) -> Result<SignedSpendingLimits> {
+        SignedSpendingLimits::new(&self.signer, limits, sequence)
+    }
+
+    /// Requests our Replicas to hand our wallet over to the new key, such as when ours
+    /// may have been compromised (see Replica::rotate_wallet_key). Once rotated, the
+    /// wallet is continued by an Actor of the new key, from the history of the Replicas.
+    /// The sequence is that of the next rotation (see Replica::rotation_sequence).
+    pub fn rotate_key(&self, new_key: PublicKey, sequence: u64) -> Result<WalletKeyRotation> {
+        WalletKeyRotation::new(&self.signer, new_key, sequence)
+    }
+
+    /// Requests our Replicas to hold our debit in escrow for the timeout, in seconds,
+    /// instead of validating it, until the recipient accepts it (see Replica::open_escrow).
+    pub fn request_escrow(
+        &self,
+        signed_transfer: SignedTransfer,
+        timeout: u64,
+    ) -> Result<EscrowRequest> {
+        if signed_transfer.from() != self.id {
+            return Err(Error::from("Transfer is not ours"));
+        }
+        EscrowRequest::new(&self.signer, signed_transfer, timeout)
+    }
+
+    /// Accepts a debit to us held in escrow, for the Replicas of the sender
+    /// to validate it (see Replica::accept_escrow).
+    pub fn accept_escrow(&self, transfer_id: TransferId) -> Result<EscrowResolution> {
+        EscrowResolution::new(&self.signer, transfer_id, EscrowDecision::Accept)
+    }
+
+    /// Cancels our debit held in escrow, releasing it back to us
+    /// unless the recipient has accepted it (see Replica::cancel_escrow).
+    pub fn cancel_escrow(&self, transfer_id: TransferId) -> Result<EscrowResolution> {
+        if transfer_id.actor != self.id {
+            return Err(Error::from("Transfer is not ours"));
+        }
+        EscrowResolution::new(&self.signer, transfer_id, EscrowDecision::Cancel)
+    }
+
+    /// Releases our debit left pending at our Replicas, such as one we aborted or never
+    /// aggregated the proof of, for them to expire it once overdue, and free its counter
+    /// (see Replica::expire_pending_debit). The proof of a released debit is not to be
+    /// registered, nor sent to the recipient, as its counter is to be debited again.
+    pub fn release(&self, transfer_id: TransferId) -> Result<DebitRelease> {
+        if transfer_id.actor != self.id {
+            return Err(Error::from("Transfer is not ours"));
+        }
+        DebitRelease::new(&self.signer, transfer_id)
+    }
+
+    /// Acknowledges the delivery of an artifact of the outbox,
+    /// once the Replicas have accepted it.
+    pub fn acknowledge(&self, sequence: u64) -> Result<OutboxAcknowledged> {
+        if !self.outbox.is_pending(sequence) {
+            return Err(Error::from("No such artifact pending in the outbox"));
         }
+        Ok(OutboxAcknowledged { sequence })
+    }
+
+    /// Handles a cmd received over the wire, with the Actor cmd of the same name,
+    /// returning the event to apply, so that transports need not translate each cmd.
+    /// A validation whose share does not verify returns the evidence, to be applied.
+    pub fn handle_cmd(&self, cmd: ActorCmd) -> Outcome<ActorEvent> {
+        let event = match cmd {
+            ActorCmd::Transfer { amount, to } => {
+                ActorEvent::TransferInitiated(self.transfer(amount, to)?)
+            }
+            ActorCmd::ReceiveValidation(validation) => match self.receive(validation) {
+                Ok(received) => ActorEvent::TransferValidationReceived(received),
+                Err(TransferError::InvalidShare(rejected)) => {
+                    ActorEvent::ValidationShareRejected(*rejected)
+                }
+                Err(error) => return Err(error),
+            },
+            ActorCmd::RegisterTransfer(debit_proof) => {
+                ActorEvent::TransferRegistrationSent(self.register(debit_proof)?)
+            }
+            ActorCmd::AbortTransfer(transfer_id) => {
+                ActorEvent::TransferAborted(self.abort(transfer_id)?)
+            }
+            ActorCmd::SynchTransfers(events) => ActorEvent::TransfersSynched(self.synch(events)?),
+            ActorCmd::SynchFromCheckpoint(history) => {
+                ActorEvent::CheckpointSynched(self.synch_from_checkpoint(history)?)
+            }
+            ActorCmd::Acknowledge(sequence) => {
+                ActorEvent::OutboxAcknowledged(self.acknowledge(sequence)?)
+            }
+        };
+        Outcome::success(event)
     }
 
     /// Step 2. Receive validations from Replicas, aggregate the signatures.
-    pub fn receive(&self, validation: TransferValidated) -> Result<TransferValidationReceived> {
+    pub fn receive(
+        &self,
+        validation: TransferValidated,
+    ) -> TransferResult<TransferValidationReceived> {
         // Always verify signature first! (as to not leak any information).
         if !self.verify(&validation).is_ok() {
-            return Err(Error::InvalidSignature);
+            return Err(self.share_rejected(validation));
         }
+        self.aggregate(validation, &self.accumulating_validations)
+    }
+
+    /// Step 2, for a burst of validations. Same as [receive](Actor::receive), but
+    /// the signature shares are verified in batches, with a single pairing check per
+    /// quorum of shares. Individual verification is only done when a batch fails.
+    /// The results are in the order of the validations, and are to be applied in that order.
+    pub fn receive_batch(
+        &self,
+        validations: Vec<TransferValidated>,
+    ) -> Vec<TransferResult<TransferValidationReceived>> {
+        let verifications = self.verify_batch(&validations);
+        let codecs = self.accepted_codecs();
+        // validations earlier in the batch count towards the quorum of later ones
+        let mut accumulating_validations = self.accumulating_validations.clone();
+        validations
+            .into_iter()
+            .zip(verifications)
+            .map(|(validation, verification)| {
+                if verification.is_err() {
+                    return Err(self.share_rejected(validation));
+                }
+                let received = self.aggregate(validation, &accumulating_validations)?;
+                let at = self.clock.now();
+                Self::accumulate(&mut accumulating_validations, &received, at, &codecs);
+                Ok(received)
+            })
+            .collect()
+    }
+
+    /// Checks a validation with verified signatures
+    /// against the accumulated ones, and produces the proof if quorum is reached.
+    fn aggregate(
+        &self,
+        validation: TransferValidated,
+        accumulating_validations: &BTreeMap<PublicKeySet, ValidationAccumulator>,
+    ) -> TransferResult<TransferValidationReceived> {
         let signed_transfer = &validation.signed_transfer;
         // check if validation was initiated by this actor
         if self.id != signed_transfer.transfer.id.actor {
-            return Err(Error::from("Validation not intended for this actor")); // "validation is not intended for this actor"
+            return Err(TransferError::NotOurTransfer);
         }
         // check if expected this validation
         if self.next_debit_version != signed_transfer.transfer.id.counter {
-            return Err(Error::from("Out of order validation"));
+            return Err(TransferError::OutOfOrderDebit {
+                expected: self.next_debit_version,
+                got: signed_transfer.transfer.id.counter,
+            });
         }
-        // check if already received
-        for (_, validations) in &self.accumulating_validations {
-            if validations.contains(&validation) {
-                return Err(Error::from("Already received validation"));
-            }
+        if self.aborted.contains(signed_transfer) {
+            return Err(Error::from("Transfer was aborted").into());
         }
 
-        let mut proof = None;
-        let accumulating_validations = &self.accumulating_validations;
-        let largest_group = accumulating_validations
-            .clone()
-            .into_iter()
-            .max_by_key(|c| c.1.len());
-        match largest_group {
-            None => (),
-            Some((replicas, accumulated)) => {
-                // If received validation is made by same set of replicas as this group,
-                // and the current count of accumulated is same as the threshold,
-                // then we have reached the quorum needed to build the proof. (Quorum = threshold + 1)
-                let quorum =
-                    accumulated.len() == replicas.threshold() && replicas == validation.replicas;
-                if quorum {
-                    // collect sig shares
-                    let last_sig = validation.clone().replica_signature;
-                    let sig_shares: BTreeMap<_, _> = accumulated
-                        .into_iter()
-                        .map(|v| v.replica_signature)
-                        .map(|s| (s.index, s.share))
-                        .chain(vec![(last_sig.index, last_sig.share)])
-                        .collect();
-
-                    if let Ok(data) = bincode::serialize(&signed_transfer) {
-                        // Combine shares to produce the main signature.
-                        let sig = replicas
-                            .combine_signatures(&sig_shares)
-                            .expect("not enough shares");
-                        // Validate the main signature. If the shares were valid, this can't fail.
-                        if replicas.public_key().verify(&sig, data) {
-                            proof = Some(DebitAgreementProof {
-                                signed_transfer: signed_transfer.clone(),
-                                debiting_replicas_sig: safe_nd::Signature::Bls(sig),
-                            });
-                        } // else, we have some corrupt data. (todo: Do we need to act on that fact?)
-                    };
-                }
+        // The shares are already verified, so they are only checked against those
+        // accumulated from the same replicas, which produce the proof once a quorum
+        // (threshold + 1) of them is reached.
+        let proof = match accumulating_validations.get(&validation.replicas) {
+            Some(accumulator) => {
+                accumulator.check(&validation)?;
+                accumulator.proof_with(&validation)
             }
-        }
+            None => {
+                ValidationAccumulator::new(signed_transfer.clone(), validation.replicas.clone())
+                    .with_codecs(self.accepted_codecs())
+                    .proof_with(&validation)
+            }
+        };
 
         Ok(TransferValidationReceived { validation, proof })
     }
 
+    /// The error for a validation that does not verify. If it is of the debit in flight,
+    /// its share did not verify, and the evidence is returned, to be applied.
+    fn share_rejected(&self, validation: TransferValidated) -> TransferError {
+        if self.initiated.as_ref() == Some(&validation.signed_transfer) {
+            TransferError::InvalidShare(Box::new(ValidationShareRejected { validation }))
+        } else {
+            Error::InvalidSignature.into()
+        }
+    }
+
+    /// Adds a received validation, and its proof if any, to the accumulated ones.
+    fn accumulate(
+        accumulating_validations: &mut BTreeMap<PublicKeySet, ValidationAccumulator>,
+        received: &TransferValidationReceived,
+        at: Timestamp,
+        codecs: &[Codec],
+    ) {
+        let validation = &received.validation;
+        let accumulator = Self::accumulator_of(accumulating_validations, validation, codecs);
+        accumulator.insert(validation.clone(), received.proof.clone());
+        accumulator.stamp(at);
+    }
+
+    /// The accumulator of the validations of the Replicas of the validation.
+    fn accumulator_of<'a>(
+        accumulating_validations: &'a mut BTreeMap<PublicKeySet, ValidationAccumulator>,
+        validation: &TransferValidated,
+        codecs: &[Codec],
+    ) -> &'a mut ValidationAccumulator {
+        accumulating_validations
+            .entry(validation.replicas.clone())
+            .or_insert_with(|| {
+                ValidationAccumulator::new(
+                    validation.signed_transfer.clone(),
+                    validation.replicas.clone(),
+                )
+                .with_codecs(codecs.to_vec())
+            })
+    }
+
     /// Step 3. Registration of an agreed transfer.
     /// (The actual sending of the registration over the wire is done by upper layer,
     /// only after that, the event is applied to the actor instance.)
-    pub fn register(&self, debit_proof: DebitAgreementProof) -> Result<TransferRegistrationSent> {
+    pub fn register(
+        &self,
+        debit_proof: DebitAgreementProof,
+    ) -> TransferResult<TransferRegistrationSent> {
         // Always verify signature first! (as to not leak any information).
         if !self.verify_debit_proof(&debit_proof).is_ok() {
-            return Err(Error::InvalidSignature);
+            return Err(Error::InvalidSignature.into());
         }
         match self
             .account
@@ -233,10 +1132,10 @@ impl<V: ReplicaValidator> Actor<V> {
                 if is_sequential {
                     Ok(TransferRegistrationSent { debit_proof })
                 } else {
-                    Err(Error::from("Non-sequential opertaion")) // "Non-sequential operation"
+                    Err(TransferError::NonSequentialRegistration)
                 }
             }
-            Err(_) => Err(Error::InvalidOperation), // from this place this code won't happen, but account validates the transfer is actually debits from it's owner.
+            Err(_) => Err(Error::InvalidOperation.into()), // from this place this code won't happen, but account validates the transfer is actually debits from it's owner.
         }
     }
 
@@ -263,6 +1162,40 @@ impl<V: ReplicaValidator> Actor<V> {
         }
     }
 
+    /// Step xx, for a light Actor. Instead of synching the full history, which is
+    /// costly for old accounts, continues from the last checkpoint of our Replicas.
+    /// The checkpoint must be signed by our Replicas and contain our state, and the
+    /// transfers since must all be valid. Only done when we are behind the checkpoint.
+    pub fn synch_from_checkpoint(&self, history: CheckpointedHistory) -> Result<CheckpointSynched> {
+        history.verify_checkpoint(&self.replicas)?;
+        let state = history.state;
+        if state.id != self.id {
+            return Err(Error::from("Checkpointed state is not of this actor"));
+        }
+        if state.debit_count < self.account.next_debit()
+            || state.credit_count < self.account.credit_count() as u64
+        {
+            return Err(Error::from("Already past the checkpoint"));
+        }
+        for credit in &history.credits {
+            if credit.to() != self.id {
+                return Err(Error::from("Credit is not to this actor"));
+            }
+            self.verify_credit_proof(credit)?;
+        }
+        for (counter, debit) in (state.debit_count..).zip(&history.debits) {
+            if debit.from() != self.id || debit.id().counter != counter {
+                return Err(Error::from("Non-sequential debit since checkpoint"));
+            }
+            self.verify_debit_proof(debit)?;
+        }
+        Ok(CheckpointSynched {
+            state,
+            credits: history.credits,
+            debits: history.debits,
+        })
+    }
+
     fn validate_credits(&self, events: &Vec<ReplicaEvent>) -> Vec<ReceivedCredit> {
         let valid_credits: Vec<_> = events
             .into_iter()
@@ -322,64 +1255,237 @@ impl<V: ReplicaValidator> Actor<V> {
     /// There is no validation of an event, it is assumed to have
     /// been properly validated before raised, and thus anything that breaks is a bug.
     pub fn apply(&mut self, event: ActorEvent) {
+        if let Some(analytics) = &mut self.analytics {
+            analytics.apply(&event, self.clock.now());
+        }
         match event {
             ActorEvent::TransferInitiated(e) => {
+                self.queue.initiated(&e.signed_transfer.transfer);
+                self.schedule.initiated(&e.signed_transfer.transfer);
                 self.next_debit_version = e.id().counter;
+                self.initiated = Some(e.signed_transfer.clone());
+                self.outbox.push(OutboxItem::Transfer(e.signed_transfer));
             }
             ActorEvent::TransferValidationReceived(e) => {
                 if let Some(_) = e.proof {
                     // if we have a proof, then we have a valid set of replicas (potentially new) to update with
                     self.replicas = e.validation.replicas.clone();
                 }
-                match self
-                    .accumulating_validations
-                    .get_mut(&e.validation.replicas)
-                {
-                    Some(set) => {
-                        let _ = set.insert(e.validation.clone());
-                    }
-                    None => {
-                        // Creates if not exists.
-                        let mut set = HashSet::new();
-                        let _ = set.insert(e.validation.clone());
-                        let _ = self
-                            .accumulating_validations
-                            .insert(e.validation.replicas.clone(), set);
-                    }
-                }
+                let codecs = self.accepted_codecs();
+                let at = self.clock.now();
+                Self::accumulate(&mut self.accumulating_validations, &e, at, &codecs);
             }
             ActorEvent::TransferRegistrationSent(e) => {
-                self.account.append(e.debit_proof.signed_transfer.transfer);
+                self.outbox
+                    .push(OutboxItem::Registration(e.debit_proof.clone()));
+                self.queue.registered(&e.debit_proof.id());
+                let transfer = e.debit_proof.signed_transfer.transfer.clone();
+                self.account.append_debit(e.debit_proof);
+                self.project_fee(&transfer);
+                self.next_debit_version = self.account.next_debit();
                 self.accumulating_validations.clear();
+                self.settle_initiated();
             }
             ActorEvent::TransfersSynched(e) => {
                 for credit in e.credits {
                     // append credits _before_ debits
-                    self.account
-                        .append(credit.debit_proof.signed_transfer.transfer);
+                    self.account.append_credit(credit);
                 }
                 let any_debits = e.debits.len() > 0;
                 for proof in e.debits {
                     // append debits _after_ credits
-                    self.account.append(proof.signed_transfer.transfer);
+                    let transfer = proof.signed_transfer.transfer.clone();
+                    self.account.append_debit(proof);
+                    self.project_fee(&transfer);
                 }
                 if any_debits {
                     // set the synchronisation counter
-                    self.next_debit_version = self.account.next_debit() - 1;
+                    self.next_debit_version = self.account.next_debit();
+                }
+                self.settle_queue();
+                self.settle_initiated();
+            }
+            ActorEvent::CheckpointSynched(e) => {
+                self.account = Account::from_checkpoint(e.state);
+                for credit in e.credits {
+                    self.account.append_credit(credit);
+                }
+                for proof in e.debits {
+                    self.account.append_debit(proof);
+                }
+                self.next_debit_version = self.account.next_debit();
+                self.accumulating_validations.clear();
+                self.settle_queue();
+                self.settle_initiated();
+            }
+            ActorEvent::OutboxAcknowledged(e) => self.outbox.acknowledge(e.sequence),
+            ActorEvent::TransferQueued(e) => self.queue.push(e.intent),
+            ActorEvent::QueuedTransferFailed(e) => {
+                let in_flight = self.queue.in_flight().map(|(intent, _)| intent.sequence);
+                if in_flight == Some(e.sequence) {
+                    // the counter of the refused debit is taken by the next one
+                    self.discard_initiated();
                 }
+                self.queue.failed(e.sequence, e.reason);
+            }
+            ActorEvent::TransferAborted(e) => {
+                let in_flight = self
+                    .queue
+                    .in_flight()
+                    .filter(|(_, id)| *id == e.signed_transfer.id())
+                    .map(|(intent, _)| intent.sequence);
+                if let Some(sequence) = in_flight {
+                    self.queue
+                        .failed(sequence, "Transfer was aborted".to_string());
+                }
+                self.initiated = Some(e.signed_transfer);
+                self.discard_initiated();
+                self.outbox.push(OutboxItem::Release(e.release));
+            }
+            ActorEvent::TransferScheduled(e) => self.schedule.push(e.scheduled),
+            ActorEvent::ScheduledTransferCancelled(e) => self.schedule.cancel(e.id),
+            ActorEvent::ValidationShareRejected(e) => {
+                let validation = e.validation;
+                let codecs = self.accepted_codecs();
+                let accumulator =
+                    Self::accumulator_of(&mut self.accumulating_validations, &validation, &codecs);
+                accumulator.reject(validation.replica_signature.index);
+                accumulator.stamp(self.clock.now());
+            }
+            ActorEvent::OverageRefundInitiated(e) => {
+                for credit in e.refunded {
+                    let _ = self.refunded_overages.insert(credit, e.initiated.id());
+                }
+                self.apply(ActorEvent::TransferInitiated(e.initiated));
+            }
+            ActorEvent::AttachmentStored(e) => {
+                let transfer_id = e.signed_attachment.attachment.transfer_id;
+                let _ = self.attachments.insert(transfer_id, e.signed_attachment);
             }
         };
         // consider event log, to properly be able to reconstruct state from restart
     }
 
+    /// Settles the queued transfer in flight against the debits synched: registered if
+    /// it is among them, failed if its counter was taken by a debit of another instance.
+    fn settle_queue(&mut self) {
+        if let Some((intent, id)) = self.queue.in_flight() {
+            if self.account.contains(&id) {
+                self.queue.registered(&id);
+            } else if self.account.next_debit() > id.counter {
+                let sequence = intent.sequence;
+                self.queue
+                    .failed(sequence, "Counter was taken by another debit".to_string());
+            }
+        }
+    }
+
+    /// Drops the debit initiated, refusing validations of it from then on,
+    /// for the next debit to take its counter.
+    fn discard_initiated(&mut self) {
+        if let Some(signed_transfer) = self.initiated.take() {
+            self.schedule.discarded(&signed_transfer.id());
+            self.refunded_overages
+                .retain(|_, refund| *refund != signed_transfer.id());
+            self.outbox
+                .withdraw(&OutboxItem::Transfer(signed_transfer.clone()));
+            self.aborted.push(signed_transfer);
+        }
+        self.next_debit_version = self.account.next_debit();
+        self.accumulating_validations.clear();
+    }
+
+    /// Forgets the debit initiated once its counter is registered,
+    /// and the debits aborted from counters registered since. A payment
+    /// of a schedule whose counter was taken by another debit is not made.
+    fn settle_initiated(&mut self) {
+        let next_debit = self.account.next_debit();
+        if let Some(signed_transfer) = &self.initiated {
+            if signed_transfer.id().counter < next_debit {
+                if !self.account.contains(&signed_transfer.id()) {
+                    self.schedule.discarded(&signed_transfer.id());
+                    self.refunded_overages
+                        .retain(|_, refund| *refund != signed_transfer.id());
+                }
+                self.initiated = None;
+            }
+        }
+        self.schedule.registered(next_debit);
+        self.aborted
+            .retain(|signed_transfer| signed_transfer.id().counter >= next_debit);
+    }
+
+    /// Categorises our debit by the `category` entry of the attachment we signed
+    /// for it (see attach), in the analytics of our spending.
+    pub fn categorise(&mut self, attachment: &SignedAttachment) -> Result<()> {
+        match &mut self.analytics {
+            None => Err(Error::from("Spending analytics are not kept")),
+            Some(analytics) => analytics.categorise(self.id, attachment),
+        }
+    }
+
     /// -----------------------------------------------------------------
     /// ---------------------- Private methods --------------------------
     /// -----------------------------------------------------------------
 
-    fn sign(&self, transfer: &Transfer) -> Result<Signature> {
-        match bincode::serialize(transfer) {
-            Err(_) => Err(Error::NetworkOther("Could not serialise transfer".into())),
-            Ok(data) => Ok(self.client_safe_key.sign(&data)),
+    /// Checks a debit of the amount to the recipient as our Replicas do,
+    /// with our balance and the counter of our next debit.
+    fn check_transfer(&self, amount: Money, to: AccountId) -> TransferResult<()> {
+        if to == self.id {
+            return Err(TransferError::SameSenderAndRecipient);
+        }
+        if amount == Money::zero() {
+            return Err(TransferError::ZeroValueTransfer);
+        }
+        Replica::check_recipient(&to)?;
+        // ensures one debit is completed at a time
+        if self.next_debit_version != self.account.next_debit() {
+            return Err(TransferError::PendingDebit);
+        }
+        if !covers(self.balance(), amount) {
+            return Err(Error::InsufficientBalance.into());
+        }
+        if let Some(params) = &self.economic_params {
+            if let Err(reason) = params.check(self.balance(), amount) {
+                return Err(TransferError::Unsatisfied(reason));
+            }
+        }
+        self.check_fee(amount, to)
+    }
+
+    /// Checks that our balance covers the fee of a debit of the amount on top of it,
+    /// as our Replicas do (see with_fee_policy).
+    fn check_fee(&self, amount: Money, to: AccountId) -> TransferResult<()> {
+        let transfer = Transfer {
+            id: Dot::new(self.id, self.account.next_debit()),
+            to,
+            amount,
+        };
+        let balance = self.balance();
+        let required = saturating_add(amount, self.fee(&transfer));
+        if !covers(balance, required) {
+            let reason = RejectionReason::BelowRequiredBalance { balance, required };
+            return Err(TransferError::Unsatisfied(reason));
+        }
+        Ok(())
+    }
+
+    /// The fee our Replicas charge for the transfer, if projected.
+    fn fee(&self, transfer: &Transfer) -> Money {
+        self.fee_policy
+            .as_ref()
+            .map_or_else(Money::zero, |policy| policy.fee(transfer))
+    }
+
+    /// Deducts the fee of our registered debit from our balance, if projected,
+    /// and not already deducted, such as by the Replicas of a checkpoint synched.
+    fn project_fee(&mut self, transfer: &Transfer) {
+        let fee = self.fee(transfer);
+        if fee != Money::zero()
+            && self.account.fee_paid(&transfer.id).is_none()
+            && covers(self.account.balance(), fee)
+        {
+            self.account.pay_fee(transfer.id, fee);
         }
     }
 
@@ -397,28 +1503,114 @@ impl<V: ReplicaValidator> Actor<V> {
         self.verify_share(cmd, &event.replica_signature, &event.replicas)
     }
 
+    /// Verifies the validations of a burst. Shares over the same transfer, from the same
+    /// key set, are verified a quorum at a time, by combining them and checking the
+    /// combined signature. (Since exactly threshold + 1 shares are combined, it only verifies
+    /// if each of them is valid.) A failed batch falls back to verifying its shares one by one.
+    fn verify_batch(&self, validations: &[TransferValidated]) -> Vec<Result<()>> {
+        let mut results: Vec<_> = validations
+            .iter()
+            .map(|v| self.verify_is_our_transfer(&v.signed_transfer))
+            .collect();
+        let codecs = self.accepted_codecs();
+
+        let mut groups = HashMap::new();
+        for (i, validation) in validations.iter().enumerate() {
+            if results[i].is_ok() {
+                groups
+                    .entry((&validation.replicas, &validation.signed_transfer))
+                    .or_insert_with(Vec::new)
+                    .push(i);
+            }
+        }
+
+        for ((replicas, signed_transfer), indices) in groups {
+            // Batches are verified over the signing codec only, shares
+            // over another accepted one are verified when falling back.
+            let data = codecs[0]
+                .encode(Payload::Proof(signed_transfer))
+                .unwrap_or_default();
+            // Shares with an index already seen can't be part of a batch.
+            let mut share_indices = HashSet::new();
+            let mut unique = vec![];
+            for i in indices {
+                if share_indices.insert(validations[i].replica_signature.index) {
+                    unique.push(i);
+                } else {
+                    results[i] = self.verify_share(
+                        signed_transfer,
+                        &validations[i].replica_signature,
+                        replicas,
+                    );
+                }
+            }
+
+            let quorum = shares_needed(replicas);
+            let mut verified: Vec<usize> = vec![];
+            for chunk in unique.chunks(quorum) {
+                // A trailing chunk is filled up with shares that are already verified.
+                let batch: Vec<_> = chunk
+                    .iter()
+                    .chain(verified.iter().take(quorum - chunk.len()))
+                    .map(|i| &validations[*i].replica_signature)
+                    .collect();
+                if batch.len() == quorum && Self::verify_combined(&batch, replicas, &data) {
+                    for i in chunk {
+                        results[*i] = Ok(());
+                    }
+                    verified.extend(chunk);
+                } else {
+                    for i in chunk {
+                        let result = self.verify_share(
+                            signed_transfer,
+                            &validations[*i].replica_signature,
+                            replicas,
+                        );
+                        if result.is_ok() {
+                            verified.push(*i);
+                        }
+                        results[*i] = result;
+                    }
+                }
+            }
+        }
+
+        results
+    }
+
+    // Combines the shares and verifies the resulting signature.
+    fn verify_combined(shares: &[&SignatureShare], replicas: &PublicKeySet, data: &[u8]) -> bool {
+        let sig_shares: BTreeMap<_, _> =
+            shares.iter().map(|s| (s.index, s.share.clone())).collect();
+        match replicas.combine_signatures(&sig_shares) {
+            Ok(sig) => replicas.public_key().verify(&sig, data),
+            Err(_) => false,
+        }
+    }
+
     // Check that the replica signature is valid per the provided public key set.
     // (if we only use this in one place we can move the content to that method)
-    fn verify_share<T: serde::Serialize>(
+    fn verify_share(
         &self,
-        item: T,
+        signed_transfer: &SignedTransfer,
         replica_signature: &SignatureShare,
         replicas: &PublicKeySet,
     ) -> Result<()> {
         let sig_share = &replica_signature.share;
         let share_index = replica_signature.index;
-        match bincode::serialize(&item) {
-            Err(_) => Err(Error::NetworkOther("Could not serialise item".into())),
-            Ok(data) => {
-                let verified = replicas
+        let payload = Payload::Proof(signed_transfer);
+        let verified = verified_in(&self.accepted_codecs(), payload, |data| {
+            let key = VerificationKey::new(data, sig_share, replicas, Some(share_index));
+            self.verification_cache.verify(key, || {
+                replicas
                     .public_key_share(share_index)
-                    .verify(sig_share, data);
-                if verified {
-                    Ok(())
-                } else {
-                    Err(Error::InvalidSignature)
-                }
-            }
+                    .verify(sig_share, data)
+            })
+        });
+        if verified {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
         }
     }
 
@@ -431,13 +1623,8 @@ impl<V: ReplicaValidator> Actor<V> {
         }
 
         // Check that the proof corresponds to a/the public key set of our Replicas.
-        match bincode::serialize(&proof.signed_transfer) {
-            Err(_) => Err(Error::NetworkOther("Could not serialise transfer".into())),
-            Ok(data) => {
-                let public_key = safe_nd::PublicKey::Bls(self.replicas.public_key());
-                public_key.verify(&proof.debiting_replicas_sig, &data)
-            }
-        }
+        let public_key = PublicKey::Bls(self.replicas.public_key());
+        self.verify_proof_signature(public_key, &proof.debiting_replicas_sig, proof)
     }
 
     /// Verify that this is a valid ReceivedCredit.
@@ -448,34 +1635,47 @@ impl<V: ReplicaValidator> Actor<V> {
         let proof = &credit.debit_proof;
 
         // Check that the proof corresponds to a/the public key set of our Replicas.
-        match bincode::serialize(&proof.signed_transfer) {
-            Err(_) => Err(Error::NetworkOther("Could not serialise transfer".into())),
-            Ok(data) => credit
-                .debiting_replicas
-                .verify(&proof.debiting_replicas_sig, &data),
+        self.verify_proof_signature(
+            credit.debiting_replicas,
+            &proof.debiting_replicas_sig,
+            proof,
+        )
+    }
+
+    /// Verifies the signature of a proof, or returns the cached outcome.
+    fn verify_proof_signature(
+        &self,
+        public_key: PublicKey,
+        signature: &Signature,
+        proof: &DebitAgreementProof,
+    ) -> Result<()> {
+        let payload = Payload::Proof(&proof.signed_transfer);
+        let verified = verified_in(&self.accepted_codecs(), payload, |data| {
+            let key = VerificationKey::new(data, signature, &public_key, None);
+            self.verification_cache
+                .verify(key, || public_key.verify(signature, data).is_ok())
+        });
+        if verified {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
         }
     }
 
+    /// The codecs accepted at the height of our clock, the signing one first.
+    fn accepted_codecs(&self) -> Vec<Codec> {
+        accepted_codecs(self.codec_migration.as_ref(), self.clock.height())
+    }
+
     /// Check that we signed this.
     fn verify_is_our_transfer(&self, signed_transfer: &SignedTransfer) -> Result<()> {
-        match bincode::serialize(&signed_transfer.transfer) {
-            Err(_) => Err(Error::NetworkOther("Could not serialise transfer".into())),
-            Ok(data) => {
-                let actor_sig = self
-                    .client_safe_key
-                    .public_id()
-                    .public_key()
-                    .verify(&signed_transfer.actor_signature, data);
-                if actor_sig.is_ok() {
-                    Ok(())
-                } else {
-                    Err(Error::InvalidSignature)
-                }
-            }
-        }
+        SignableTransfer::new(&signed_transfer.transfer)
+            .verify(&self.id, &signed_transfer.actor_signature)
     }
 }
 
+// the tests build instances with the default clock, that of the system
+#[cfg(feature = "system-clock")]
 mod test {
     use super::{Account, Actor, ActorEvent, ReplicaValidator, TransferInitiated};
     use crdts::Dot;
@@ -519,6 +1719,144 @@ mod test {
         actor.apply(ActorEvent::TransferInitiated(debit))
     }
 
+    #[test]
+    fn initiates_transfers_from_a_snapshot_with_debits() {
+        // Arrange
+        let mut rng = rand::thread_rng();
+        let client_safe_key = SafeKey::client(ClientFullId::new_ed25519(&mut rng));
+        let client_pubkey = client_safe_key.public_key();
+        let replicas_id = SecretKeySet::random(1, &mut rng).public_keys();
+        let credit_id = Dot::new(get_random_pk(), 0);
+        let debit_id = Dot::new(client_pubkey, 0);
+        let mut account = Account::new(client_pubkey);
+        account.append(get_transfer(credit_id, client_pubkey, Money::from_nano(10)));
+        account.append(get_transfer(debit_id, get_random_pk(), Money::from_nano(1)));
+        let actor = Actor::from_snapshot(account, client_safe_key, replicas_id, Validator {});
+
+        // Act
+        let result = actor.transfer(Money::from_nano(1), get_random_pk());
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn refuses_transfers_to_self() {
+        let actor = get_actor(10);
+        let result = actor.transfer(Money::from_nano(1), actor.id());
+        assert!(result == Err(super::TransferError::SameSenderAndRecipient));
+    }
+
+    #[test]
+    fn refuses_transfers_with_typed_errors() {
+        // Arrange
+        let actor = get_actor(10);
+        let later = Transfer {
+            id: Dot::new(actor.id(), 1),
+            to: get_random_pk(),
+            amount: Money::from_nano(4),
+        };
+        let request = super::SigningRequest::new(later).unwrap();
+        let signature = actor.signer.sign(&request.payload).unwrap();
+
+        // Act
+        let zero_value = actor.transfer(Money::zero(), get_random_pk());
+        let out_of_order = actor.complete_transfer(request, signature);
+
+        // Assert
+        assert!(zero_value == Err(super::TransferError::ZeroValueTransfer));
+        assert!(
+            out_of_order
+                == Err(super::TransferError::OutOfOrderDebit {
+                    expected: 0,
+                    got: 1
+                })
+        );
+    }
+
+    #[test]
+    fn preflights_transfers_locally() {
+        // Arrange
+        let actor = get_actor(10);
+        let recipient = get_random_pk();
+        let mut in_flight = get_actor(10);
+        let debit = get_debit(&in_flight);
+        in_flight.apply(ActorEvent::TransferInitiated(debit));
+
+        // Act
+        let fits = actor.preflight(Money::from_nano(10), recipient);
+        let too_much = actor.preflight(Money::from_nano(11), recipient);
+        let to_self = actor.preflight(Money::from_nano(1), actor.id());
+        let pending = in_flight.preflight(Money::from_nano(1), recipient);
+
+        // Assert
+        assert!(fits.is_ok());
+        assert!(too_much.is_err());
+        assert!(to_self == Err(super::TransferError::SameSenderAndRecipient));
+        assert!(pending == Err(super::TransferError::PendingDebit));
+    }
+
+    #[test]
+    fn completes_transfers_signed_externally() {
+        // Arrange
+        let actor = get_actor(10);
+        let other_key = SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()));
+        let request = actor
+            .signing_request(Money::from_nano(4), get_random_pk())
+            .unwrap();
+        let foreign_signature = other_key.sign(&request.payload);
+
+        // Act
+        let signature = actor.signer.sign(&request.payload).unwrap();
+        let initiated = actor.complete_transfer(request.clone(), signature);
+
+        // Assert
+        assert!(initiated.is_ok());
+        assert!(actor.complete_transfer(request, foreign_signature).is_err());
+    }
+
+    #[test]
+    fn returns_scheduled_payments_once_due() {
+        // Arrange
+        let mut actor = get_actor(100);
+        let amount = Money::from_nano(30);
+        let end = super::ScheduleEnd::After(5);
+        let scheduled = actor
+            .schedule_transfer(amount, get_random_pk(), 1_000, 60, end)
+            .unwrap();
+        actor.apply(ActorEvent::TransferScheduled(scheduled));
+
+        // Act
+        let not_yet_due = actor.due_transfers(999).unwrap();
+        let due = actor.due_transfers(2_000).unwrap();
+        let first = due[0].request.clone();
+        let signature = actor.signer.sign(&first.payload).unwrap();
+        let initiated = actor.complete_transfer(first, signature).unwrap();
+        actor.apply(ActorEvent::TransferInitiated(initiated.clone()));
+        let due_in_flight = actor.due_transfers(2_000).unwrap();
+        let made_in_flight = actor.transfer_schedule().payments_made(0);
+        let aborted = actor.abort(initiated.id()).unwrap();
+        actor.apply(ActorEvent::TransferAborted(aborted));
+        let made_after_abort = actor.transfer_schedule().payments_made(0);
+        let cancelled = actor.cancel_scheduled(0).unwrap();
+        actor.apply(ActorEvent::ScheduledTransferCancelled(cancelled));
+
+        // Assert
+        let counters = |due: &[super::DueTransfer]| -> Vec<_> {
+            due.iter()
+                .map(|d| (d.payment, d.request.transfer.id.counter))
+                .collect()
+        };
+        assert!(not_yet_due.is_empty());
+        // the balance covers three payments of the five due
+        assert!(counters(&due) == vec![(0, 0), (1, 1), (2, 2)]);
+        assert!(counters(&due_in_flight) == vec![(1, 1), (2, 2)]);
+        assert!(made_in_flight == Some(1));
+        assert!(made_after_abort == Some(0));
+        assert!(actor.transfer_schedule().scheduled().is_empty());
+        assert!(actor.due_transfers(2_000).unwrap().is_empty());
+    }
+
     fn get_debit(actor: &Actor<Validator>) -> TransferInitiated {
         match actor.transfer(Money::from_nano(10), get_random_pk()) {
             Ok(event) => event,
@@ -0,0 +1,245 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{attachment::SignedAttachment, clock::Timestamp, money::saturating_add, ActorEvent};
+use safe_nd::{AccountId, DebitAgreementProof, Error, Money, Result, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// The key of the entry of an attachment that categorises the transfer.
+pub const CATEGORY_KEY: &str = "category";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// A calendar month, in UTC.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Month {
+    /// The year.
+    pub year: u64,
+    /// The month of the year, from 1 to 12.
+    pub month: u8,
+}
+
+impl Month {
+    /// The month of the timestamp.
+    pub fn of(timestamp: Timestamp) -> Self {
+        // the civil calendar from days since the epoch, in eras of 400 years from March 0000
+        let days = timestamp / SECONDS_PER_DAY + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self {
+            year,
+            month: month as u8,
+        }
+    }
+}
+
+/// The money sent and received in a month.
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct MonthlyTotals {
+    /// The sum of the debits.
+    pub spent: Money,
+    /// The sum of the credits.
+    pub received: Money,
+}
+
+impl Default for MonthlyTotals {
+    fn default() -> Self {
+        Self {
+            spent: Money::zero(),
+            received: Money::zero(),
+        }
+    }
+}
+
+/// A debit or credit, as recorded.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+struct Record {
+    counterparty: AccountId,
+    amount: Money,
+    month: Month,
+}
+
+/// Analytics of the spending of a wallet, for wallet apps to show to its owner.
+/// They are computed locally, from the events of the Actor, and nothing of them
+/// leaves the device (see Actor::with_spending_analytics). Transfers carry no time,
+/// so they are dated when their event is applied, and the analytics are to be kept
+/// along with the Actor, for replays not to date them again. Debits are categorised
+/// by the `category` entry of the attachments signed by the Actor (see Actor::attach).
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct SpendingAnalytics {
+    debits: HashMap<TransferId, Record>,
+    credits: HashMap<TransferId, Record>,
+    categories: HashMap<TransferId, String>,
+}
+
+impl SpendingAnalytics {
+    /// The sums of debits and credits of each month, in order.
+    pub fn monthly_totals(&self) -> BTreeMap<Month, MonthlyTotals> {
+        let mut months: BTreeMap<Month, MonthlyTotals> = BTreeMap::new();
+        for record in self.debits.values() {
+            let totals = months.entry(record.month).or_default();
+            totals.spent = saturating_add(totals.spent, record.amount);
+        }
+        for record in self.credits.values() {
+            let totals = months.entry(record.month).or_default();
+            totals.received = saturating_add(totals.received, record.amount);
+        }
+        months
+    }
+
+    /// The recipients most spent on, at most limit of them, the largest sum first.
+    pub fn top_recipients(&self, limit: usize) -> Vec<(AccountId, Money)> {
+        let mut sums: HashMap<AccountId, Money> = HashMap::new();
+        for record in self.debits.values() {
+            let sum = sums.entry(record.counterparty).or_insert_with(Money::zero);
+            *sum = saturating_add(*sum, record.amount);
+        }
+        let mut recipients: Vec<_> = sums.into_iter().collect();
+        recipients.sort_by(|(a, a_sum), (b, b_sum)| b_sum.cmp(a_sum).then(a.cmp(b)));
+        recipients.truncate(limit);
+        recipients
+    }
+
+    /// The sum of debits in each category, those not categorised under None.
+    pub fn category_totals(&self) -> BTreeMap<Option<String>, Money> {
+        let mut totals: BTreeMap<Option<String>, Money> = BTreeMap::new();
+        for (id, record) in &self.debits {
+            let total = totals
+                .entry(self.categories.get(id).cloned())
+                .or_insert_with(Money::zero);
+            *total = saturating_add(*total, record.amount);
+        }
+        totals
+    }
+
+    /// Records the debits and credits of the event, dated at the timestamp.
+    /// Transfers already recorded keep their date.
+    pub fn apply(&mut self, event: &ActorEvent, at: Timestamp) {
+        match event {
+            ActorEvent::TransferRegistrationSent(e) => self.record_debit(&e.debit_proof, at),
+            ActorEvent::TransfersSynched(e) => {
+                for credit in &e.credits {
+                    self.record_credit(&credit.debit_proof, at);
+                }
+                for debit_proof in &e.debits {
+                    self.record_debit(debit_proof, at);
+                }
+            }
+            ActorEvent::CheckpointSynched(e) => {
+                for credit in &e.credits {
+                    self.record_credit(&credit.debit_proof, at);
+                }
+                for debit_proof in &e.debits {
+                    self.record_debit(debit_proof, at);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Categorises the debit of the attachment by its `category` entry,
+    /// if the attachment was signed by us, the sender of the debit.
+    pub fn categorise(&mut self, wallet: AccountId, signed: &SignedAttachment) -> Result<()> {
+        let attachment = &signed.attachment;
+        if attachment.transfer_id.actor != wallet {
+            return Err(Error::from("Attachment is not of our debit"));
+        }
+        signed.verify()?;
+        match attachment.get(CATEGORY_KEY) {
+            None => Err(Error::from("Attachment has no category")),
+            Some(category) => {
+                let _ = self
+                    .categories
+                    .insert(attachment.transfer_id, category.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    fn record_debit(&mut self, debit_proof: &DebitAgreementProof, at: Timestamp) {
+        let _ = self
+            .debits
+            .entry(debit_proof.id())
+            .or_insert_with(|| Record {
+                counterparty: debit_proof.to(),
+                amount: debit_proof.amount(),
+                month: Month::of(at),
+            });
+    }
+
+    fn record_credit(&mut self, debit_proof: &DebitAgreementProof, at: Timestamp) {
+        let _ = self
+            .credits
+            .entry(debit_proof.id())
+            .or_insert_with(|| Record {
+                counterparty: debit_proof.from(),
+                amount: debit_proof.amount(),
+                month: Month::of(at),
+            });
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn dates_timestamps_in_calendar_months() {
+        // Arrange
+        let epoch = 0;
+        let leap_day = 951_782_400; // 2000-02-29
+        let new_year = 1_609_459_200; // 2021-01-01
+
+        // Act
+        let months = vec![
+            Month::of(epoch),
+            Month::of(leap_day),
+            Month::of(new_year - 1),
+        ];
+
+        // Assert
+        assert!(
+            months[0]
+                == Month {
+                    year: 1970,
+                    month: 1
+                }
+        );
+        assert!(
+            months[1]
+                == Month {
+                    year: 2000,
+                    month: 2
+                }
+        );
+        assert!(
+            months[2]
+                == Month {
+                    year: 2020,
+                    month: 12
+                }
+        );
+        assert!(
+            Month::of(new_year)
+                == Month {
+                    year: 2021,
+                    month: 1
+                }
+        );
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    checkpoint::SignedCheckpoint,
+    quorum::combine,
+    signable::{Signable, SignableProof},
+    ReceivedCredit,
+};
+use safe_nd::{Error, PublicKey, Result, Signature, SignatureShare};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use threshold_crypto::PublicKeySet;
+
+/// How long a reshare of the keys of a group may take to complete, in seconds,
+/// during which proofs signed by the incoming keys are valid (see Replica::begin_reshare).
+pub const DEFAULT_RESHARE_GRACE: u64 = 10 * 60;
+
+/// The handover from a group of Replicas to the group succeeding it,
+/// signed by the superseded group.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct KeySuccession {
+    /// The key of the succeeding group.
+    pub successor: PublicKey,
+    /// The aggregated signature of the superseded group, over the successor key.
+    pub group_sig: Signature,
+}
+
+impl KeySuccession {
+    /// Combines the signature shares from a quorum of the superseded
+    /// Replicas (see Replica::sign_succession) into a key succession.
+    pub fn combine(
+        successor: PublicKey,
+        replicas: &PublicKeySet,
+        shares: &[SignatureShare],
+    ) -> Result<Self> {
+        let group_sig = combine(replicas, shares)?;
+        let succession = KeySuccession {
+            successor,
+            group_sig,
+        };
+        succession.verify(&PublicKey::Bls(replicas.public_key()))?;
+        Ok(succession)
+    }
+
+    /// Verifies that the successor was signed by the given key.
+    pub fn verify(&self, predecessor: &PublicKey) -> Result<()> {
+        match bincode::serialize(&self.successor) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise key".into())),
+            Ok(data) => predecessor.verify(&self.group_sig, &data),
+        }
+    }
+}
+
+/// The final state of a superseded group of Replicas, along with the chain of
+/// keys to its successors, so that credits signed by the group can still be
+/// verified by anyone trusting a later group, however many times it has changed.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct GroupArchive {
+    /// The PK set of the superseded group.
+    pub replicas: PublicKeySet,
+    /// The last checkpoint signed by the group.
+    pub final_checkpoint: SignedCheckpoint,
+    /// The successions from the group, oldest first.
+    pub succession: Vec<KeySuccession>,
+}
+
+impl GroupArchive {
+    /// The key of the archived group.
+    pub fn group_key(&self) -> PublicKey {
+        PublicKey::Bls(self.replicas.public_key())
+    }
+
+    /// Appends the next succession, when the latest group is itself superseded.
+    pub fn extend(&mut self, succession: KeySuccession) -> Result<()> {
+        let latest = self.latest_key();
+        succession.verify(&latest)?;
+        self.succession.push(succession);
+        Ok(())
+    }
+
+    /// The key of the latest group in the chain.
+    pub fn latest_key(&self) -> PublicKey {
+        self.succession
+            .last()
+            .map_or_else(|| self.group_key(), |s| s.successor)
+    }
+
+    /// Verifies the final checkpoint, and that the chain of keys leads
+    /// from the archived group to one of the trusted keys.
+    /// Returns the key of the archived group, which can then be trusted
+    /// in verification of histories (see Account::verify_full).
+    pub fn verify(&self, trusted_keys: &[PublicKey]) -> Result<PublicKey> {
+        self.final_checkpoint.verify(&self.replicas)?;
+        let mut key = self.group_key();
+        let mut trusted = trusted_keys.contains(&key);
+        for succession in &self.succession {
+            succession.verify(&key)?;
+            key = succession.successor;
+            trusted = trusted || trusted_keys.contains(&key);
+        }
+        if trusted {
+            Ok(self.group_key())
+        } else {
+            Err(Error::from("Archived group does not lead to a trusted key"))
+        }
+    }
+
+    /// Verifies that the credit was signed by the archived group,
+    /// and that the group leads to one of the trusted keys.
+    pub fn verify_credit(&self, credit: &ReceivedCredit, trusted_keys: &[PublicKey]) -> Result<()> {
+        let group_key = self.verify(trusted_keys)?;
+        if credit.debiting_replicas != group_key {
+            return Err(Error::from("Credit is not signed by the archived group"));
+        }
+        SignableProof::new(&credit.debit_proof.signed_transfer)
+            .verify(&group_key, &credit.debit_proof.debiting_replicas_sig)
+    }
+}
+
+/// A chain of keys of a section, from a root key through each succession
+/// to the latest key, for proofs signed by a section we do not know of
+/// to be verified against a root key that we trust.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SectionProofChain {
+    /// The first key of the chain.
+    pub root: PublicKey,
+    /// The successions from the root key, oldest first.
+    pub links: Vec<KeySuccession>,
+}
+
+impl SectionProofChain {
+    /// A chain of the root key only.
+    pub fn new(root: PublicKey) -> Self {
+        Self {
+            root,
+            links: Default::default(),
+        }
+    }
+
+    /// Appends the next succession, signed by the latest key.
+    pub fn push(&mut self, succession: KeySuccession) -> Result<()> {
+        succession.verify(&self.last_key())?;
+        self.links.push(succession);
+        Ok(())
+    }
+
+    /// The latest key of the chain.
+    pub fn last_key(&self) -> PublicKey {
+        self.links.last().map_or(self.root, |s| s.successor)
+    }
+
+    /// Verifies that every key of the chain was signed by the one before it,
+    /// and that the root is one of the trusted keys.
+    /// Returns the latest key, which can then be trusted.
+    pub fn verify(&self, trusted_roots: &[PublicKey]) -> Result<PublicKey> {
+        if !trusted_roots.contains(&self.root) {
+            return Err(Error::from("Chain does not start at a trusted key"));
+        }
+        let mut key = self.root;
+        for succession in &self.links {
+            succession.verify(&key)?;
+            key = succession.successor;
+        }
+        Ok(key)
+    }
+}
+
+/// The keys a Replica trusts without a proof, such as the genesis key of the network
+/// and the keys of the sections it bootstraps from. Other groups are only trusted once
+/// a section proof chain leads to their key from one of these (see Replica::add_known_group).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct TrustAnchors {
+    keys: BTreeSet<PublicKey>,
+}
+
+impl TrustAnchors {
+    /// Anchors of the keys.
+    pub fn new<I: IntoIterator<Item = PublicKey>>(keys: I) -> Self {
+        Self {
+            keys: keys.into_iter().collect(),
+        }
+    }
+
+    /// Adds the key, returning whether it was not an anchor already.
+    pub fn insert(&mut self, key: PublicKey) -> bool {
+        self.keys.insert(key)
+    }
+
+    /// Whether the key is an anchor.
+    pub fn contains(&self, key: &PublicKey) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// The keys, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &PublicKey> {
+        self.keys.iter()
+    }
+
+    /// Whether there is no anchor.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use safe_nd::{AccountId, Error, Result, Signature, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The maximum number of entries of an attachment.
+pub const MAX_ATTACHMENT_ENTRIES: usize = 16;
+/// The maximum size of an attachment, as the sum of the lengths of its keys and values.
+pub const MAX_ATTACHMENT_BYTES: usize = 1024;
+/// The key of the entry of an attachment holding the memo of the transfer, such as
+/// the reference of a payment that the recipient reconciles its invoices by.
+pub const MEMO_KEY: &str = "memo";
+/// The maximum size of a memo, in bytes.
+pub const MAX_MEMO_BYTES: usize = 64;
+/// The key of the entry of an attachment holding the tags of the transfer, separated
+/// by TAG_SEPARATOR, such as the labels an app files its payments under, for the
+/// recipient to find its credits by (see Replica::credits_tagged).
+pub const TAGS_KEY: &str = "tags";
+/// The separator of the tags in the entry of TAGS_KEY, which no tag may contain.
+pub const TAG_SEPARATOR: char = ',';
+
+/// Key-value entries attached by the sender to a credit, such as an order id or
+/// an app id, stored with the credit by the Replicas of the recipient, for the
+/// recipient to find its credits by them.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Attachment {
+    /// The transfer attached to.
+    pub transfer_id: TransferId,
+    /// The recipient of the transfer.
+    pub to: AccountId,
+    /// The entries.
+    pub entries: BTreeMap<String, String>,
+}
+
+impl Attachment {
+    /// An attachment of the entries to the transfer, if within bounds.
+    pub fn new(
+        transfer_id: TransferId,
+        to: AccountId,
+        entries: BTreeMap<String, String>,
+    ) -> Result<Self> {
+        let attachment = Self {
+            transfer_id,
+            to,
+            entries,
+        };
+        attachment.check_bounds()?;
+        Ok(attachment)
+    }
+
+    /// An attachment of the memo alone to the transfer, if within bounds (see MEMO_KEY).
+    pub fn memo(transfer_id: TransferId, to: AccountId, memo: &str) -> Result<Self> {
+        let mut entries = BTreeMap::new();
+        let _ = entries.insert(MEMO_KEY.to_string(), memo.to_string());
+        Self::new(transfer_id, to, entries)
+    }
+
+    /// An attachment of the tags alone to the transfer, if within bounds (see TAGS_KEY).
+    pub fn tags(transfer_id: TransferId, to: AccountId, tags: &[&str]) -> Result<Self> {
+        let mut entries = BTreeMap::new();
+        let _ = entries.insert(TAGS_KEY.to_string(), join_tags(tags)?);
+        Self::new(transfer_id, to, entries)
+    }
+
+    /// The value of the entry with the key, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// The memo of the transfer, if any.
+    pub fn get_memo(&self) -> Option<&str> {
+        self.get(MEMO_KEY)
+    }
+
+    /// The tags of the transfer, none if it has no entry of TAGS_KEY.
+    pub fn get_tags(&self) -> Vec<&str> {
+        self.get(TAGS_KEY)
+            .map(|tags| {
+                tags.split(TAG_SEPARATOR)
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn check_bounds(&self) -> Result<()> {
+        if self.entries.len() > MAX_ATTACHMENT_ENTRIES {
+            return Err(Error::from("Attachment has too many entries"));
+        }
+        let size: usize = self.entries.iter().map(|(k, v)| k.len() + v.len()).sum();
+        if size > MAX_ATTACHMENT_BYTES {
+            return Err(Error::from("Attachment is too large"));
+        }
+        if self
+            .get_memo()
+            .map_or(false, |memo| memo.len() > MAX_MEMO_BYTES)
+        {
+            return Err(Error::from("Memo is too large"));
+        }
+        Ok(())
+    }
+}
+
+/// The value of the entry of TAGS_KEY, if no tag is empty or contains the separator.
+pub(crate) fn join_tags(tags: &[&str]) -> Result<String> {
+    if tags
+        .iter()
+        .any(|tag| tag.is_empty() || tag.contains(TAG_SEPARATOR))
+    {
+        return Err(Error::from(
+            "Tags can not be empty or contain the separator",
+        ));
+    }
+    Ok(tags.join(&TAG_SEPARATOR.to_string()))
+}
+
+/// An attachment, signed by the sender of the transfer (see Actor::attach).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedAttachment {
+    /// The attachment.
+    pub attachment: Attachment,
+    /// The signature of the sender.
+    pub signature: Signature,
+}
+
+impl SignedAttachment {
+    /// Verifies that the attachment is within bounds,
+    /// and was signed by the sender of the transfer.
+    pub fn verify(&self) -> Result<()> {
+        self.attachment.check_bounds()?;
+        match bincode::serialize(&self.attachment) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise attachment".into())),
+            Ok(data) => self
+                .attachment
+                .transfer_id
+                .actor
+                .verify(&self.signature, &data),
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn bounds_attachments() {
+        // Arrange
+        let id = Dot::new(get_random_pk(), 0);
+        let to = get_random_pk();
+        let many: BTreeMap<_, _> = (0..=MAX_ATTACHMENT_ENTRIES)
+            .map(|i| (i.to_string(), String::new()))
+            .collect();
+        let mut large = BTreeMap::new();
+        let _ = large.insert("order".to_string(), "x".repeat(MAX_ATTACHMENT_BYTES));
+
+        // Act
+        let attachment = Attachment::new(id, to, BTreeMap::new());
+
+        // Assert
+        assert!(attachment.is_ok());
+        assert!(Attachment::new(id, to, many).is_err());
+        assert!(Attachment::new(id, to, large).is_err());
+    }
+
+    #[test]
+    fn bounds_memos() {
+        // Arrange
+        let id = Dot::new(get_random_pk(), 0);
+        let to = get_random_pk();
+
+        // Act
+        let memo = Attachment::memo(id, to, "INV-2020-0042");
+
+        // Assert
+        assert!(memo.map(|a| a.get_memo() == Some("INV-2020-0042")) == Ok(true));
+        assert!(Attachment::memo(id, to, &"x".repeat(MAX_MEMO_BYTES)).is_ok());
+        assert!(Attachment::memo(id, to, &"x".repeat(MAX_MEMO_BYTES + 1)).is_err());
+    }
+
+    #[test]
+    fn reads_back_tags() {
+        // Arrange
+        let id = Dot::new(get_random_pk(), 0);
+        let to = get_random_pk();
+
+        // Act
+        let tagged = Attachment::tags(id, to, &["rent", "2020"]);
+
+        // Assert
+        assert!(tagged.map(|a| a.get_tags() == vec!["rent", "2020"]) == Ok(true));
+        assert!(Attachment::memo(id, to, "no tags").map(|a| a.get_tags().is_empty()) == Ok(true));
+        assert!(Attachment::tags(id, to, &["rent,2020"]).is_err());
+        assert!(Attachment::tags(id, to, &[""]).is_err());
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
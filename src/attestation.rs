@@ -0,0 +1,128 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{account::Account, quorum::combine};
+use safe_nd::{AccountId, Error, Money, PublicKey, Result, Signature, SignatureShare};
+use serde::{Deserialize, Serialize};
+use threshold_crypto::PublicKeySet;
+
+/// The balance of an account, at a point of its history.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct BalanceAttestation {
+    /// The account.
+    pub wallet: AccountId,
+    /// The balance.
+    pub balance: Money,
+    /// The number of credits in the history, i.e. the index of the next credit.
+    pub credit_index: u64,
+    /// The number of debits in the history, i.e. the index of the next debit.
+    pub debit_index: u64,
+}
+
+impl BalanceAttestation {
+    /// The balance of the account, at its latest entries.
+    pub(crate) fn of(account: &Account) -> Self {
+        Self {
+            wallet: account.id(),
+            balance: account.balance(),
+            credit_index: account.credit_count() as u64,
+            debit_index: account.next_debit(),
+        }
+    }
+}
+
+/// The least indices of the history of an account that a response of a Replica
+/// must reflect, being those already known to the querying Actor, so that it
+/// always reads its own writes, such as a registered debit.
+#[derive(Clone, Copy, Default, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ReadFloor {
+    /// The number of credits known.
+    pub credit_index: u64,
+    /// The number of debits known.
+    pub debit_index: u64,
+}
+
+impl ReadFloor {
+    /// The indices of the latest entries of the account.
+    pub(crate) fn of(account: &Account) -> Self {
+        Self {
+            credit_index: account.credit_count() as u64,
+            debit_index: account.next_debit(),
+        }
+    }
+
+    /// Whether the balance is at least as fresh as the floor.
+    pub fn is_met_by(&self, attestation: &BalanceAttestation) -> bool {
+        attestation.credit_index >= self.credit_index && attestation.debit_index >= self.debit_index
+    }
+}
+
+/// A balance, signed by the Replica that was queried (see Replica::balance_proof).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedBalance {
+    /// The balance.
+    pub attestation: BalanceAttestation,
+    /// The signature of the Replica over the balance.
+    pub replica_signature: SignatureShare,
+}
+
+impl SignedBalance {
+    /// Verifies that the balance was signed by a Replica of the given group.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        match bincode::serialize(&self.attestation) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise balance".into())),
+            Ok(data) => {
+                let key = replicas.public_key_share(self.replica_signature.index);
+                if key.verify(&self.replica_signature.share, data) {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidSignature)
+                }
+            }
+        }
+    }
+}
+
+/// A balance signed by a group of Replicas, for third parties
+/// to verify with the key of the group alone, such as in solvency checks.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct BalanceProof {
+    /// The balance.
+    pub attestation: BalanceAttestation,
+    /// The aggregated signature of the Replicas over the balance.
+    pub group_sig: Signature,
+}
+
+impl BalanceProof {
+    /// Combines the balances signed by a quorum of the Replicas into a proof.
+    /// All of them must attest to the same balance, at the same point of the history.
+    pub fn combine(shares: &[SignedBalance], replicas: &PublicKeySet) -> Result<Self> {
+        let attestation = match shares.first() {
+            Some(share) => share.attestation,
+            None => return Err(Error::from("Not enough signature shares")),
+        };
+        if shares.iter().any(|share| share.attestation != attestation) {
+            return Err(Error::from("Replicas attest to different balances"));
+        }
+        let shares: Vec<_> = shares.iter().map(|s| s.replica_signature.clone()).collect();
+        let proof = BalanceProof {
+            attestation,
+            group_sig: combine(replicas, &shares)?,
+        };
+        proof.verify(&PublicKey::Bls(replicas.public_key()))?;
+        Ok(proof)
+    }
+
+    /// Verifies that the balance was signed by the group with the given key.
+    pub fn verify(&self, group_key: &PublicKey) -> Result<()> {
+        match bincode::serialize(&self.attestation) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise balance".into())),
+            Ok(data) => group_key.verify(&self.group_sig, &data),
+        }
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::{Account, HistoryEntry},
+    codec::{verified_in, Codec, Payload},
+    economics::FeeDestination,
+    sync::SyncIndex,
+    verification::{verify_debit_agreement_proof, verify_signed_transfer},
+    KnownGroupChained, ReceivedCredit, ReplicaEvent,
+};
+use safe_nd::{
+    AccountId, DebitAgreementProof, Error, KnownGroupAdded, Money, PublicKey, Result, Transfer,
+};
+use std::collections::{HashMap, HashSet};
+use threshold_crypto::PublicKeySet;
+
+/// A Replica that only observes a group of Replicas: it applies their events,
+/// verifying the proofs they carry, answers queries about the resulting state,
+/// and checks its invariants. It holds no key share, and never signs,
+/// for network observers, explorers and auditors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditorReplica {
+    /// The PK set of the observed group.
+    replicas: PublicKeySet,
+    /// The PK sets the observed group had before, oldest first.
+    past_replicas: Vec<PublicKeySet>,
+    /// The other groups known to the observed group.
+    other_groups: HashSet<PublicKeySet>,
+    /// The accounts held by the observed group.
+    accounts: HashMap<AccountId, Account>,
+    /// The codecs the proofs are accepted over.
+    codecs: Vec<Codec>,
+}
+
+impl AuditorReplica {
+    /// An auditor of the group with the PK set, from its genesis.
+    pub fn new(replicas: PublicKeySet) -> Self {
+        Self {
+            replicas,
+            past_replicas: vec![],
+            other_groups: Default::default(),
+            accounts: Default::default(),
+            codecs: vec![Codec::Canonical],
+        }
+    }
+
+    /// Accepts proofs over any of the codecs, instead of over the canonical encoding
+    /// only, such as while the observed group moves between them (see CodecMigration).
+    pub fn with_codecs(mut self, codecs: Vec<Codec>) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
+    /// An auditor of the group with the PK set, from a history of its events,
+    /// stopping at the first event that does not verify.
+    pub fn from_history(replicas: PublicKeySet, events: Vec<ReplicaEvent>) -> Result<Self> {
+        let mut instance = Self::new(replicas);
+        for event in events {
+            instance.verify(&event)?;
+            instance.apply(event);
+        }
+        Ok(instance)
+    }
+
+    /// -----------------------------------------------------------------
+    /// ---------------------- Queries ----------------------------------
+    /// -----------------------------------------------------------------
+
+    /// Query for the balance of an account.
+    pub fn balance(&self, account_id: &AccountId) -> Option<Money> {
+        self.accounts.get(account_id).map(Account::balance)
+    }
+
+    /// Query for new credits since specified index.
+    pub fn credits_since(&self, account_id: &AccountId, index: usize) -> Option<Vec<Transfer>> {
+        self.accounts
+            .get(account_id)
+            .map(|history| history.credits_since(index))
+    }
+
+    /// Query for new debits since specified index.
+    pub fn debits_since(&self, account_id: &AccountId, index: usize) -> Option<Vec<Transfer>> {
+        self.accounts
+            .get(account_id)
+            .map(|history| history.debits_since(index))
+    }
+
+    /// Query for the typed credit entries of an account, carrying their proofs.
+    pub fn credit_entries(&self, account_id: &AccountId) -> Option<&[HistoryEntry]> {
+        self.accounts.get(account_id).map(Account::credit_entries)
+    }
+
+    /// Query for the typed debit entries of an account, carrying their proofs.
+    pub fn debit_entries(&self, account_id: &AccountId) -> Option<&[HistoryEntry]> {
+        self.accounts.get(account_id).map(Account::debit_entries)
+    }
+
+    /// Checks that the debits of every account are proven by the observed group, now or
+    /// in the past, and its credits by groups known to it, that debits are sequential,
+    /// and that balances match histories.
+    pub fn check_invariants(&self) -> Result<()> {
+        let own_keys: Vec<_> = self
+            .own_groups()
+            .map(|group| PublicKey::Bls(group.public_key()))
+            .collect();
+        let trusted_keys: Vec<_> = self
+            .own_groups()
+            .chain(self.other_groups.iter())
+            .map(|group| PublicKey::Bls(group.public_key()))
+            .collect();
+        for account in self.accounts.values() {
+            account.verify_full(&own_keys, &trusted_keys, &self.codecs)?;
+        }
+        Ok(())
+    }
+
+    /// Verifies the proofs carried by the event, and that it applies to our state.
+    /// Events that carry no proof, and have no bearing on balances, always verify.
+    pub fn verify(&self, event: &ReplicaEvent) -> Result<()> {
+        match event {
+            ReplicaEvent::TransferRegistered(e) => {
+                self.verify_registered_proof(&e.debit_proof)?;
+                match self.accounts.get(&e.from()) {
+                    None => Err(Error::NoSuchSender),
+                    Some(account) => {
+                        if account.is_sequential(&e.debit_proof.signed_transfer.transfer)? {
+                            Ok(())
+                        } else {
+                            Err(Error::from("Non-sequential debit"))
+                        }
+                    }
+                }
+            }
+            ReplicaEvent::TransferPropagated(e) => {
+                let known = self
+                    .own_groups()
+                    .chain(self.other_groups.iter())
+                    .any(|group| PublicKey::Bls(group.public_key()) == e.debiting_replicas);
+                if !known {
+                    return Err(Error::from("Credit is signed by unknown Replicas"));
+                }
+                verify_signed_transfer(&e.debit_proof.signed_transfer, &self.codecs)?;
+                let payload = Payload::Proof(&e.debit_proof.signed_transfer);
+                let signature = &e.debit_proof.debiting_replicas_sig;
+                if !verified_in(&self.codecs, payload, |data| {
+                    e.debiting_replicas.verify(signature, data).is_ok()
+                }) {
+                    return Err(Error::InvalidSignature);
+                }
+                let exists = self
+                    .accounts
+                    .get(&e.to())
+                    .map_or(false, |account| account.contains(&e.id()));
+                if exists {
+                    Err(Error::TransferIdExists)
+                } else {
+                    Ok(())
+                }
+            }
+            ReplicaEvent::DisbursementRegistered(e) => {
+                self.verify(&ReplicaEvent::TransferRegistered(e.registered()))
+            }
+            ReplicaEvent::PayoutPropagated(e) => {
+                let payout = &e.payout;
+                let known = self
+                    .own_groups()
+                    .chain(self.other_groups.iter())
+                    .any(|group| PublicKey::Bls(group.public_key()) == payout.debiting_replicas);
+                if !known {
+                    return Err(Error::from("Payout is signed by unknown Replicas"));
+                }
+                payout.payout_proof.verify(&payout.debiting_replicas)?;
+                let exists = self
+                    .accounts
+                    .get(&payout.payout_proof.to())
+                    .map_or(false, |account| account.contains(&payout.payout_proof.id()));
+                if exists {
+                    Err(Error::TransferIdExists)
+                } else {
+                    Ok(())
+                }
+            }
+            ReplicaEvent::CreditsConsolidated(e) => {
+                let summary = &e.signed_summary.summary;
+                if !self.accounts.contains_key(&summary.wallet) {
+                    return Err(Error::from("No such account"));
+                }
+                e.signed_summary
+                    .verify(&PublicKey::Bls(self.replicas.public_key()))
+            }
+            ReplicaEvent::WalletCompacted(e) => {
+                let state = &e.signed_compaction.state;
+                let account = match self.accounts.get(&state.id) {
+                    Some(account) => account,
+                    None => return Err(Error::from("No such account")),
+                };
+                e.signed_compaction
+                    .verify(&PublicKey::Bls(self.replicas.public_key()))?;
+                let up_to_index = SyncIndex {
+                    credit_index: state.credit_count,
+                    debit_index: state.debit_count,
+                };
+                if &account.compaction(up_to_index)? != state {
+                    return Err(Error::from("State does not match the history"));
+                }
+                Ok(())
+            }
+            ReplicaEvent::FeeCharged(e) => {
+                let charged = self
+                    .accounts
+                    .get(&e.transfer_id.actor)
+                    .map_or(false, |account| account.contains(&e.transfer_id));
+                if !charged {
+                    return Err(Error::from("Fee is not of a registered debit"));
+                }
+                match e.destination {
+                    FeeDestination::Wallet(wallet) if !self.accounts.contains_key(&wallet) => {
+                        Err(Error::from("No such account"))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            ReplicaEvent::OwnKeyRotated(e) => {
+                if e.previous != self.replicas {
+                    return Err(Error::from("Rotation is not of the current key"));
+                }
+                if e.succession.successor != PublicKey::Bls(e.replicas.public_key()) {
+                    return Err(Error::from("Succession is not to the new group"));
+                }
+                e.succession
+                    .verify(&PublicKey::Bls(self.replicas.public_key()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// -----------------------------------------------------------------
+    /// ---------------------- Mutation ---------------------------------
+    /// -----------------------------------------------------------------
+
+    /// Mutation of state, for the events bearing on balances and on the keys
+    /// we verify with. Others are ignored. There is no validation of an event,
+    /// it is assumed to have been verified before (see verify).
+    pub fn apply(&mut self, event: ReplicaEvent) {
+        match event {
+            ReplicaEvent::KnownGroupAdded(KnownGroupAdded { group })
+            | ReplicaEvent::KnownGroupChained(KnownGroupChained { group, .. }) => {
+                let _ = self.other_groups.insert(group);
+            }
+            ReplicaEvent::KnownGroupForgotten(e) => {
+                self.other_groups
+                    .retain(|group| PublicKey::Bls(group.public_key()) != e.group);
+            }
+            ReplicaEvent::TransferRegistered(e) => {
+                self.accounts
+                    .get_mut(&e.from())
+                    .unwrap() // this is OK, since eventsourcing implies events are _facts_, you have a bug if it fails here..
+                    .append_debit(e.debit_proof);
+            }
+            ReplicaEvent::TransferPropagated(e) => {
+                let to = e.to();
+                let credit = ReceivedCredit {
+                    debit_proof: e.debit_proof,
+                    debiting_replicas: e.debiting_replicas,
+                };
+                self.accounts
+                    .entry(to)
+                    .or_insert_with(|| Account::new(to))
+                    .append_credit(credit);
+            }
+            ReplicaEvent::DisbursementRegistered(e) => {
+                self.apply(ReplicaEvent::TransferRegistered(e.registered()));
+            }
+            ReplicaEvent::PayoutPropagated(e) => {
+                let to = e.payout.payout_proof.to();
+                self.accounts
+                    .entry(to)
+                    .or_insert_with(|| Account::new(to))
+                    .append_payout(e.payout);
+            }
+            ReplicaEvent::CreditsConsolidated(e) => {
+                let wallet = e.signed_summary.summary.wallet;
+                self.accounts
+                    .get_mut(&wallet)
+                    .unwrap() // this is OK, since eventsourcing implies events are _facts_, you have a bug if it fails here..
+                    .consolidate(e.signed_summary);
+            }
+            ReplicaEvent::WalletCompacted(e) => {
+                let state = e.signed_compaction.state;
+                self.accounts
+                    .get_mut(&state.id)
+                    .unwrap() // this is OK, since eventsourcing implies events are _facts_, you have a bug if it fails here..
+                    .compact(state);
+            }
+            ReplicaEvent::FeeCharged(e) => {
+                self.accounts
+                    .get_mut(&e.transfer_id.actor)
+                    .unwrap() // this is OK, since eventsourcing implies events are _facts_, you have a bug if it fails here..
+                    .pay_fee(e.transfer_id, e.fee);
+                if let FeeDestination::Wallet(wallet) = e.destination {
+                    self.accounts
+                        .get_mut(&wallet)
+                        .unwrap() // this is OK, since eventsourcing implies events are _facts_, you have a bug if it fails here..
+                        .receive_fee(e.fee);
+                }
+            }
+            ReplicaEvent::OwnKeyRotated(e) => {
+                self.past_replicas.push(e.previous);
+                self.replicas = e.replicas;
+            }
+            _ => (),
+        }
+    }
+
+    /// -----------------------------------------------------------------
+    /// ---------------------- Private methods --------------------------
+    /// -----------------------------------------------------------------
+
+    /// The current and past PK sets of the observed group, the current first.
+    fn own_groups(&self) -> impl Iterator<Item = &PublicKeySet> {
+        std::iter::once(&self.replicas).chain(self.past_replicas.iter().rev())
+    }
+
+    /// Verify that the debit was agreed by the observed group, now or in the past.
+    fn verify_registered_proof(&self, proof: &DebitAgreementProof) -> Result<()> {
+        if self
+            .own_groups()
+            .any(|group| verify_debit_agreement_proof(proof, group, &self.codecs).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+}
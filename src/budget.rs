@@ -0,0 +1,169 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::money::{covers, credited, saturating_add, saturating_sub};
+use safe_nd::{Error, Money, Result, Transfer, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A part of the balance of an account, set aside for some purpose.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Envelope {
+    /// The amount set aside.
+    pub allocated: Money,
+    /// The amount spent from it.
+    pub spent: Money,
+    /// The transfers that were spent from it.
+    pub transfers: Vec<TransferId>,
+}
+
+impl Envelope {
+    /// What can still be spent from the envelope.
+    pub fn remaining(&self) -> Money {
+        saturating_sub(self.allocated, self.spent)
+    }
+}
+
+/// Envelopes partitioning the balance of a single account.
+/// They only exist client side: to the Replicas there is just the one balance.
+/// The remainders of all envelopes never exceed the balance they were allocated from.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct Budget {
+    envelopes: BTreeMap<String, Envelope>,
+}
+
+impl Budget {
+    /// A budget without envelopes.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The envelope with the given name.
+    pub fn envelope(&self, name: &str) -> Option<&Envelope> {
+        self.envelopes.get(name)
+    }
+
+    /// All envelopes, by name.
+    pub fn envelopes(&self) -> &BTreeMap<String, Envelope> {
+        &self.envelopes
+    }
+
+    /// What can still be spent from the envelope, zero if there is no such envelope.
+    pub fn remaining(&self, name: &str) -> Money {
+        self.envelope(name)
+            .map(|envelope| envelope.remaining())
+            .unwrap_or_else(Money::zero)
+    }
+
+    /// The part of the balance that is not in any envelope.
+    pub fn unallocated(&self, balance: Money) -> Money {
+        let allocated = self
+            .envelopes
+            .values()
+            .fold(Money::zero(), |sum, e| saturating_add(sum, e.remaining()));
+        saturating_sub(balance, allocated)
+    }
+
+    /// Sets aside more of the balance in the envelope, creating it if need be.
+    /// Only what is not already in an envelope can be allocated.
+    pub fn allocate(&mut self, name: &str, amount: Money, balance: Money) -> Result<()> {
+        if !covers(self.unallocated(balance), amount) {
+            return Err(Error::InsufficientBalance);
+        }
+        let envelope = self
+            .envelopes
+            .entry(name.to_string())
+            .or_insert_with(|| Envelope {
+                allocated: Money::zero(),
+                spent: Money::zero(),
+                transfers: vec![],
+            });
+        envelope.allocated = match credited(envelope.allocated, amount) {
+            Ok(allocated) => allocated,
+            Err(_) => return Err(Error::from("Overflow when allocating to envelope")),
+        };
+        Ok(())
+    }
+
+    /// Returns what remains of the envelope to the unallocated balance, and removes it.
+    pub fn close(&mut self, name: &str) -> Option<Envelope> {
+        self.envelopes.remove(name)
+    }
+
+    /// Checks that the amount can be spent from the envelope.
+    pub fn check(&self, name: &str, amount: Money) -> Result<()> {
+        match self.envelope(name) {
+            None => Err(Error::from("No such envelope")),
+            Some(envelope) if !covers(envelope.remaining(), amount) => {
+                Err(Error::from("Amount exceeds what remains of the envelope"))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Records the transfer as spent from the envelope.
+    /// A transfer already recorded is not counted twice.
+    pub fn record(&mut self, name: &str, transfer: &Transfer) -> Result<()> {
+        if self
+            .envelopes
+            .values()
+            .any(|envelope| envelope.transfers.contains(&transfer.id))
+        {
+            return Ok(());
+        }
+        self.check(name, transfer.amount)?;
+        if let Some(envelope) = self.envelopes.get_mut(name) {
+            envelope.spent = saturating_add(envelope.spent, transfer.amount);
+            envelope.transfers.push(transfer.id);
+        }
+        Ok(())
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn partitions_balance() -> Result<()> {
+        // Arrange
+        let balance = Money::from_nano(100);
+        let mut budget = Budget::new();
+        budget.allocate("rent", Money::from_nano(60), balance)?;
+        budget.allocate("food", Money::from_nano(30), balance)?;
+        let transfer = get_transfer(0, 50);
+
+        // Act
+        budget.record("rent", &transfer)?;
+        budget.record("rent", &transfer)?;
+
+        // Assert
+        assert!(budget.remaining("rent") == Money::from_nano(10));
+        assert!(budget.unallocated(Money::from_nano(50)) == Money::from_nano(10));
+        assert!(budget.record("food", &get_transfer(1, 31)).is_err());
+        assert!(budget.record("fun", &get_transfer(1, 1)).is_err());
+        assert!(budget
+            .allocate("fun", Money::from_nano(11), Money::from_nano(50))
+            .is_err());
+        Ok(())
+    }
+
+    fn get_transfer(counter: u64, amount: u64) -> Transfer {
+        Transfer {
+            id: Dot::new(get_random_pk(), counter),
+            to: get_random_pk(),
+            amount: Money::from_nano(amount),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::hashing::{hash, Digest};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+};
+
+/// The number of outcomes kept by default.
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// Identifies a signature verification, by the hash of the signed content,
+/// the signature and the key, serialised together so that each part is
+/// length-prefixed, and no split of the same bytes into other parts collides,
+/// along with the index of the signing Replica when the signature is a share.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub struct VerificationKey {
+    content: Digest,
+    replica_index: Option<usize>,
+}
+
+impl VerificationKey {
+    /// None if the signature or key could not be serialised,
+    /// in which case the verification is just not cached.
+    pub(crate) fn new<S: Serialize, K: Serialize>(
+        data: &[u8],
+        signature: &S,
+        key: &K,
+        replica_index: Option<usize>,
+    ) -> Option<Self> {
+        let parts = bincode::serialize(&(data, signature, key)).ok()?;
+        Some(Self {
+            content: hash(&[&parts]),
+            replica_index,
+        })
+    }
+}
+
+/// A bounded cache of signature verification outcomes,
+/// so that retransmitted duplicates of shares and proofs are
+/// accepted or rejected without repeating the pairing operations.
+/// Clones share the same cache. The oldest outcomes are evicted first.
+#[derive(Clone)]
+pub struct VerificationCache(Arc<Mutex<Outcomes>>);
+
+struct Outcomes {
+    capacity: usize,
+    verified: HashMap<VerificationKey, bool>,
+    order: VecDeque<VerificationKey>,
+}
+
+impl VerificationCache {
+    /// A cache keeping at most `capacity` outcomes.
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(Outcomes {
+            capacity,
+            verified: Default::default(),
+            order: Default::default(),
+        })))
+    }
+
+    /// Number of cached outcomes.
+    pub fn len(&self) -> usize {
+        match self.0.lock() {
+            Ok(outcomes) => outcomes.verified.len(),
+            Err(_) => 0,
+        }
+    }
+
+    /// Whether there are any cached outcomes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the cached outcome, or runs the verification and caches its outcome.
+    pub(crate) fn verify<F: FnOnce() -> bool>(
+        &self,
+        key: Option<VerificationKey>,
+        verify: F,
+    ) -> bool {
+        let key = match key {
+            Some(key) => key,
+            None => return verify(),
+        };
+        if let Some(verified) = self.get(&key) {
+            return verified;
+        }
+        let verified = verify();
+        self.insert(key, verified);
+        verified
+    }
+
+    /// The cached outcome of the verification, if any.
+    pub(crate) fn get(&self, key: &VerificationKey) -> Option<bool> {
+        self.0.lock().ok()?.verified.get(key).cloned()
+    }
+
+    /// Caches the outcome of a verification run elsewhere, such as in a batch.
+    pub(crate) fn insert(&self, key: VerificationKey, verified: bool) {
+        if let Ok(mut outcomes) = self.0.lock() {
+            outcomes.insert(key, verified);
+        }
+    }
+}
+
+impl Outcomes {
+    fn insert(&mut self, key: VerificationKey, verified: bool) {
+        if self.capacity == 0 || self.verified.insert(key, verified).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                let _ = self.verified.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl Default for VerificationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+impl Debug for VerificationCache {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "VerificationCache({})", self.len())
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn repeated_verification_is_cached() {
+        // Arrange
+        let cache = VerificationCache::new(10);
+        let key = VerificationKey::new(b"data", &1u8, &2u8, Some(0));
+        let mut runs = 0;
+
+        // Act
+        for _ in 0..3 {
+            let _ = cache.verify(key, || {
+                runs += 1;
+                true
+            });
+        }
+
+        // Assert
+        assert!(runs == 1);
+        assert!(cache.len() == 1);
+    }
+
+    #[test]
+    fn keys_do_not_collide_across_parts() {
+        // the same bytes, split differently between the parts
+        let key = VerificationKey::new(b"data\x01", &2u8, &(), None);
+        let shifted = VerificationKey::new(b"data", &1u8, &2u8, None);
+        assert!(key.is_some() && shifted.is_some());
+        assert!(key != shifted);
+    }
+
+    #[test]
+    fn evicts_oldest_outcome() {
+        let cache = VerificationCache::new(2);
+        for index in 0..3 {
+            let key = VerificationKey::new(b"data", &1u8, &2u8, Some(index));
+            let _ = cache.verify(key, || false);
+        }
+        assert!(cache.len() == 2);
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    clock::Timestamp,
+    hashing::{hash, Digest},
+    quorum::combine,
+};
+use safe_nd::{AccountId, Error, PublicKey, Result, Signature, SignatureShare};
+use serde::{Deserialize, Serialize};
+use threshold_crypto::PublicKeySet;
+
+/// How far, in seconds, the time of a query made with a token may be from the
+/// time of the Replica, for the proof of the holder to be accepted.
+pub const CAPABILITY_REQUEST_WINDOW: u64 = 60;
+
+const CAPABILITY_TAG: &[u8] = b"safe-transfers/capability";
+const REQUEST_TAG: &[u8] = b"safe-transfers/capability-request";
+
+/// The expensive queries that are gated by capability tokens.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum QueryScope {
+    /// Export of the full history of an account.
+    HistoryExport,
+    /// Statistics over all accounts held by a Replica.
+    Stats,
+}
+
+/// What a group of Replicas grants when issuing a token:
+/// access for the holder to the listed queries, until expiry.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct QueryCapability {
+    /// The key of the party allowed to use the token.
+    pub holder: PublicKey,
+    /// The queries the holder may run.
+    pub scopes: Vec<QueryScope>,
+    /// The token is not valid after this time.
+    pub expires_at: Timestamp,
+}
+
+impl QueryCapability {
+    /// What the issuing Replicas sign: the capability, after a tag naming it,
+    /// so that the signature is not valid for anything else signed by the group.
+    pub fn to_signed_bytes(&self) -> Result<Vec<u8>> {
+        match bincode::serialize(self) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise capability".into())),
+            Ok(data) => Ok([CAPABILITY_TAG, &data].concat()),
+        }
+    }
+}
+
+/// The proof that the holder of a token makes a query: its signature over
+/// the challenge of the query, at the time it was made (see CapabilityToken::challenge).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CapabilityProof {
+    /// When the query was made.
+    pub at: Timestamp,
+    /// The signature of the holder over the challenge of the query.
+    pub signature: Signature,
+}
+
+/// A short-lived QueryCapability, signed by the issuing group of Replicas.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CapabilityToken {
+    /// The granted capability.
+    pub capability: QueryCapability,
+    /// The aggregated signature of the issuing Replicas.
+    pub group_sig: Signature,
+}
+
+impl CapabilityToken {
+    /// Combines the signature shares from a quorum of the
+    /// issuing Replicas (see Replica::sign_capability) into a token.
+    pub fn combine(
+        capability: QueryCapability,
+        replicas: &PublicKeySet,
+        shares: &[SignatureShare],
+    ) -> Result<Self> {
+        let group_sig = combine(replicas, shares)?;
+        let token = CapabilityToken {
+            capability,
+            group_sig,
+        };
+        token.verify_signature(replicas)?;
+        Ok(token)
+    }
+
+    /// The challenge the holder signs to make the query of the scope, of the
+    /// wallet if any, at the time. It names the token, so that a proof is only
+    /// good for the query it was made for, with this token.
+    pub fn challenge(
+        &self,
+        scope: QueryScope,
+        wallet: Option<&AccountId>,
+        at: Timestamp,
+    ) -> Result<Digest> {
+        match bincode::serialize(&(&self.capability, &self.group_sig, scope, wallet, at)) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise challenge".into())),
+            Ok(data) => Ok(hash(&[REQUEST_TAG, &data])),
+        }
+    }
+
+    /// Verifies that the token was issued by the given Replicas, covers the
+    /// scope and has not expired, and that the proof is the signature of the
+    /// holder over the challenge of the query, made within CAPABILITY_REQUEST_WINDOW
+    /// of now. A proof seen on the wire can thus only be replayed for the same
+    /// query, until the window has passed.
+    pub fn verify(
+        &self,
+        replicas: &PublicKeySet,
+        proof: &CapabilityProof,
+        scope: QueryScope,
+        wallet: Option<&AccountId>,
+        now: Timestamp,
+    ) -> Result<()> {
+        self.verify_signature(replicas)?;
+        if !self.capability.scopes.contains(&scope) {
+            return Err(Error::AccessDenied);
+        }
+        if self.capability.expires_at < now {
+            return Err(Error::from("Capability token has expired"));
+        }
+        let earliest = now.saturating_sub(CAPABILITY_REQUEST_WINDOW);
+        let latest = now.saturating_add(CAPABILITY_REQUEST_WINDOW);
+        if proof.at < earliest || proof.at > latest {
+            return Err(Error::from("Capability proof is not recent"));
+        }
+        let challenge = self.challenge(scope, wallet, proof.at)?;
+        if self
+            .capability
+            .holder
+            .verify(&proof.signature, &challenge)
+            .is_err()
+        {
+            return Err(Error::AccessDenied);
+        }
+        Ok(())
+    }
+
+    fn verify_signature(&self, replicas: &PublicKeySet) -> Result<()> {
+        let data = self.capability.to_signed_bytes()?;
+        PublicKey::Bls(replicas.public_key()).verify(&self.group_sig, &data)
+    }
+}
+
+mod test {
+    use super::*;
+    use threshold_crypto::{SecretKey, SecretKeySet};
+
+    #[test]
+    fn combines_and_verifies_token() {
+        // Arrange
+        let holder = SecretKey::random();
+        let (replicas, token) = get_token(get_pk(&holder), 100);
+        let proof = get_proof(&holder, &token, QueryScope::Stats, 50);
+
+        // Act
+        let result = token.verify(&replicas, &proof, QueryScope::Stats, None, 50);
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn rejects_expired_or_foreign_token() {
+        let holder = SecretKey::random();
+        let (replicas, token) = get_token(get_pk(&holder), 200);
+        let proof = get_proof(&holder, &token, QueryScope::Stats, 150);
+        let foreign = get_proof(&SecretKey::random(), &token, QueryScope::Stats, 150);
+        let export = get_proof(&holder, &token, QueryScope::HistoryExport, 150);
+
+        assert!(token
+            .verify(&replicas, &proof, QueryScope::Stats, None, 201)
+            .is_err());
+        assert!(token
+            .verify(&replicas, &foreign, QueryScope::Stats, None, 150)
+            .is_err());
+        assert!(token
+            .verify(&replicas, &export, QueryScope::HistoryExport, None, 150)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_replayed_proofs() {
+        let holder = SecretKey::random();
+        let (replicas, token) = get_token(get_pk(&holder), 1000);
+        let proof = get_proof(&holder, &token, QueryScope::Stats, 100);
+        let later = 100 + CAPABILITY_REQUEST_WINDOW + 1;
+        let wallet = get_pk(&SecretKey::random());
+
+        assert!(token
+            .verify(&replicas, &proof, QueryScope::Stats, None, later)
+            .is_err());
+        assert!(token
+            .verify(&replicas, &proof, QueryScope::Stats, Some(&wallet), 100)
+            .is_err());
+    }
+
+    fn get_proof(
+        holder: &SecretKey,
+        token: &CapabilityToken,
+        scope: QueryScope,
+        at: Timestamp,
+    ) -> CapabilityProof {
+        let challenge = token.challenge(scope, None, at).unwrap();
+        CapabilityProof {
+            at,
+            signature: Signature::Bls(holder.sign(challenge)),
+        }
+    }
+
+    fn get_token(holder: PublicKey, expires_at: Timestamp) -> (PublicKeySet, CapabilityToken) {
+        let mut rng = rand::thread_rng();
+        let secret_key_set = SecretKeySet::random(1, &mut rng);
+        let replicas = secret_key_set.public_keys();
+        let capability = QueryCapability {
+            holder,
+            scopes: vec![QueryScope::Stats],
+            expires_at,
+        };
+        let data = capability.to_signed_bytes().unwrap();
+        let shares: Vec<_> = (0..2)
+            .map(|index| SignatureShare {
+                index,
+                share: secret_key_set.secret_key_share(index).sign(&data),
+            })
+            .collect();
+        let token = CapabilityToken::combine(capability, &replicas, &shares).unwrap();
+        (replicas, token)
+    }
+
+    fn get_pk(secret_key: &SecretKey) -> PublicKey {
+        PublicKey::from(secret_key.public_key())
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::{Deserialize, Serialize};
+
+/// A state machine of the protocol, charted by StateChart.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum Machine {
+    /// The Actor of a wallet, applying ActorEvents.
+    Actor,
+    /// A Replica holding a wallet, applying ReplicaEvents.
+    Replica,
+}
+
+/// The state of the debits of a wallet, as held by its Actor (see Actor::chart_state)
+/// or by one of its Replicas (see Replica::chart_state).
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum ChartState {
+    /// The wallet is not held. Replicas only.
+    Unknown,
+    /// No debit is in progress. An initiated debit is only held
+    /// by the Actor as a cmd to send, so the Actor is still idle.
+    Idle,
+    /// The next debit has been validated, and awaits registration. Replicas only.
+    Validated,
+    /// Validations of the next debit are being accumulated. Actors only.
+    Accumulating,
+    /// A quorum of validations has been combined into a proof,
+    /// which is yet to be sent for registration. Actors only.
+    Agreed,
+}
+
+/// A transition of a state machine, on applying an event.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Transition {
+    /// The state before.
+    pub from: ChartState,
+    /// The name of the event applied (see ActorEvent::name and ReplicaEvent::name).
+    pub event: String,
+    /// The state after.
+    pub to: ChartState,
+}
+
+/// The states and allowed transitions of a state machine of the protocol, for
+/// integrators and auditors to check their understanding against. The charts are
+/// verified against the Actor and Replica by the tests, and are serialisable to any
+/// format supported by serde, or rendered for Graphviz with to_dot.
+/// Events not in the chart do not bear on the state of the debits of a wallet.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct StateChart {
+    /// The machine.
+    pub machine: Machine,
+    /// The state of a new machine.
+    pub initial: ChartState,
+    /// All states of the machine.
+    pub states: Vec<ChartState>,
+    /// The events charted, in the order of the protocol.
+    pub events: Vec<String>,
+    /// All allowed transitions.
+    pub transitions: Vec<Transition>,
+}
+
+impl StateChart {
+    /// The chart of the machine.
+    pub fn of(machine: Machine) -> Self {
+        match machine {
+            Machine::Actor => Self::actor(),
+            Machine::Replica => Self::replica(),
+        }
+    }
+
+    /// Whether applying the event in the state may lead to the other state.
+    pub fn allows(&self, from: ChartState, event: &str, to: ChartState) -> bool {
+        self.transitions
+            .iter()
+            .any(|t| t.from == from && t.event == event && t.to == to)
+    }
+
+    /// The states that applying the event in the state may lead to,
+    /// none if the event is not allowed in the state.
+    pub fn targets(&self, from: ChartState, event: &str) -> Vec<ChartState> {
+        self.transitions
+            .iter()
+            .filter(|t| t.from == from && t.event == event)
+            .map(|t| t.to)
+            .collect()
+    }
+
+    /// The chart in the DOT language of Graphviz.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph {:?} {{\n", self.machine);
+        dot.push_str(&format!(
+            "    start [shape=point];\n    start -> {:?};\n",
+            self.initial
+        ));
+        for t in &self.transitions {
+            dot.push_str(&format!(
+                "    {:?} -> {:?} [label=\"{}\"];\n",
+                t.from, t.to, t.event
+            ));
+        }
+        dot.push('}');
+        dot
+    }
+
+    fn actor() -> Self {
+        use ChartState::*;
+        let states = vec![Idle, Accumulating, Agreed];
+        let mut transitions = vec![
+            transition(Idle, "TransferInitiated", Idle),
+            // a refund of an overage is initiated as any other transfer
+            transition(Idle, "OverageRefundInitiated", Idle),
+            transition(Idle, "TransferValidationReceived", Accumulating),
+            // with a single Replica, the first validation is a quorum
+            transition(Idle, "TransferValidationReceived", Agreed),
+            transition(Accumulating, "TransferValidationReceived", Accumulating),
+            transition(Accumulating, "TransferValidationReceived", Agreed),
+            // validations beyond the quorum
+            transition(Agreed, "TransferValidationReceived", Agreed),
+            transition(Agreed, "TransferRegistrationSent", Idle),
+            // the debit in flight was refused, and is dropped
+            transition(Idle, "QueuedTransferFailed", Idle),
+            transition(Accumulating, "QueuedTransferFailed", Idle),
+            // the debit in flight was short of a quorum, and is dropped
+            transition(Idle, "TransferAborted", Idle),
+            transition(Accumulating, "TransferAborted", Idle),
+            // shares that do not verify are recorded with the validations
+            transition(Idle, "ValidationShareRejected", Accumulating),
+            transition(Accumulating, "ValidationShareRejected", Accumulating),
+            transition(Agreed, "ValidationShareRejected", Agreed),
+        ];
+        for state in &states {
+            // synched debits are of another instance of the Actor
+            transitions.push(transition(*state, "TransfersSynched", *state));
+            transitions.push(transition(*state, "CheckpointSynched", Idle));
+            transitions.push(transition(*state, "OutboxAcknowledged", *state));
+            transitions.push(transition(*state, "TransferQueued", *state));
+            transitions.push(transition(*state, "TransferScheduled", *state));
+            transitions.push(transition(*state, "ScheduledTransferCancelled", *state));
+            transitions.push(transition(*state, "AttachmentStored", *state));
+        }
+        Self::new(
+            Machine::Actor,
+            Idle,
+            states,
+            &[
+                "TransferInitiated",
+                "OverageRefundInitiated",
+                "TransferValidationReceived",
+                "TransferRegistrationSent",
+                "TransfersSynched",
+                "CheckpointSynched",
+                "OutboxAcknowledged",
+                "TransferQueued",
+                "QueuedTransferFailed",
+                "TransferAborted",
+                "TransferScheduled",
+                "ScheduledTransferCancelled",
+                "ValidationShareRejected",
+                "AttachmentStored",
+            ],
+            transitions,
+        )
+    }
+
+    fn replica() -> Self {
+        use ChartState::*;
+        let transitions = vec![
+            // credits open the wallet
+            transition(Unknown, "TransferPropagated", Idle),
+            transition(Idle, "TransferPropagated", Idle),
+            transition(Validated, "TransferPropagated", Validated),
+            transition(Idle, "TransferValidated", Validated),
+            // the same debit, validated again on a retry
+            transition(Validated, "TransferValidated", Validated),
+            transition(Validated, "TransferRegistered", Idle),
+            // the debit was never registered
+            transition(Validated, "PendingDebitExpired", Idle),
+            // the debit was aborted by its Actor
+            transition(Validated, "DebitReleased", Idle),
+            // the debit was validated by a quorum of our peers, without us
+            transition(Idle, "TransferRegistered", Idle),
+            // a disbursement goes through the same states as any other debit
+            transition(Unknown, "PayoutPropagated", Idle),
+            transition(Idle, "PayoutPropagated", Idle),
+            transition(Validated, "PayoutPropagated", Validated),
+            transition(Idle, "DisbursementValidated", Validated),
+            transition(Validated, "DisbursementValidated", Validated),
+            transition(Validated, "DisbursementRegistered", Idle),
+            transition(Idle, "DisbursementRegistered", Idle),
+        ];
+        Self::new(
+            Machine::Replica,
+            Unknown,
+            vec![Unknown, Idle, Validated],
+            &[
+                "TransferPropagated",
+                "TransferValidated",
+                "TransferRegistered",
+                "PendingDebitExpired",
+                "DebitReleased",
+                "DisbursementValidated",
+                "DisbursementRegistered",
+                "PayoutPropagated",
+            ],
+            transitions,
+        )
+    }
+
+    fn new(
+        machine: Machine,
+        initial: ChartState,
+        states: Vec<ChartState>,
+        events: &[&str],
+        transitions: Vec<Transition>,
+    ) -> Self {
+        Self {
+            machine,
+            initial,
+            states,
+            events: events.iter().map(|event| event.to_string()).collect(),
+            transitions,
+        }
+    }
+}
+
+fn transition(from: ChartState, event: &str, to: ChartState) -> Transition {
+    Transition {
+        from,
+        event: event.to_string(),
+        to,
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn charts_are_closed() {
+        for machine in vec![Machine::Actor, Machine::Replica] {
+            // Arrange
+            let chart = StateChart::of(machine);
+
+            // Act
+            let dot = chart.to_dot();
+
+            // Assert
+            assert!(chart.states.contains(&chart.initial));
+            assert!(chart.transitions.iter().all(|t| {
+                chart.states.contains(&t.from)
+                    && chart.states.contains(&t.to)
+                    && chart.events.contains(&t.event)
+            }));
+            assert!(chart
+                .events
+                .iter()
+                .all(|event| chart.transitions.iter().any(|t| &t.event == event)));
+            assert!(dot.matches("->").count() == chart.transitions.len() + 1);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::{Account, HistoryEntry, InclusionProof},
+    hashing::{hash, merkle_proof, merkle_root, Digest, MerkleProof},
+    quorum::combine,
+    ReceivedCredit,
+};
+use safe_nd::{
+    AccountId, DebitAgreementProof, Error, Money, PublicKey, Result, Signature, SignatureShare,
+    Transfer,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use threshold_crypto::PublicKeySet;
+
+/// The number of blocks in an epoch, by default.
+pub const DEFAULT_EPOCH_LENGTH: u64 = 1024;
+
+/// The state of an account, as committed to by a checkpoint.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct AccountState {
+    /// The account.
+    pub id: AccountId,
+    /// The balance of the account.
+    pub balance: Money,
+    /// Number of credits in the history.
+    pub credit_count: u64,
+    /// Number of debits in the history.
+    pub debit_count: u64,
+    /// Hash of the transfers of the history, credits first, in the order they were appended,
+    /// preceded by the hash of the summary of the consolidated credits, if any.
+    pub history_hash: Digest,
+}
+
+impl AccountState {
+    /// The current state of the account. The history hash of an account
+    /// continuing from a checkpointed state is chained from that of the state.
+    pub fn of(account: &Account) -> Result<Self> {
+        let transfers = account
+            .credit_entries()
+            .iter()
+            .chain(account.debit_entries())
+            .map(HistoryEntry::transfer);
+        Ok(Self {
+            id: account.id(),
+            balance: account.balance(),
+            credit_count: account.credit_count() as u64,
+            debit_count: account.next_debit(),
+            history_hash: history_hash(&history_prefix(account), transfers)?,
+        })
+    }
+
+    /// The leaf of this account in the state hash.
+    pub fn digest(&self) -> Result<Digest> {
+        match bincode::serialize(self) {
+            Err(_) => Err(Error::NetworkOther(
+                "Could not serialise account state".into(),
+            )),
+            Ok(data) => Ok(hash(&[&data])),
+        }
+    }
+}
+
+/// The digests that the history hash of the account is chained from: of the state it
+/// continues from, if checkpointed, and of the summary of its consolidated credits, if any.
+pub(crate) fn history_prefix(account: &Account) -> Vec<Digest> {
+    let checkpointed = account.checkpointed().map(|state| state.history_hash);
+    let consolidated = account
+        .consolidated()
+        .map(|signed| signed.summary.transfers_hash);
+    checkpointed.into_iter().chain(consolidated).collect()
+}
+
+/// The hash of the transfers of a history, credits first, chained from the prefix.
+pub(crate) fn history_hash<'a, I: IntoIterator<Item = &'a Transfer>>(
+    prefix: &[Digest],
+    transfers: I,
+) -> Result<Digest> {
+    let mut parts: Vec<Vec<u8>> = prefix.iter().map(|digest| digest.to_vec()).collect();
+    for transfer in transfers {
+        match bincode::serialize(transfer) {
+            Err(_) => return Err(Error::NetworkOther("Could not serialise transfer".into())),
+            Ok(data) => parts.push(data),
+        }
+    }
+    let parts: Vec<_> = parts.iter().map(Vec::as_slice).collect();
+    Ok(hash(&parts))
+}
+
+/// A commitment to the state of all accounts held by a group of Replicas, at an epoch.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Checkpoint {
+    /// The epoch at which the state was committed to.
+    pub epoch: u64,
+    /// Number of accounts.
+    pub account_count: u64,
+    /// Merkle root of the states of all accounts, ordered by account id.
+    pub state_hash: Digest,
+    /// Merkle root of the history roots of all accounts (see Account::history_root),
+    /// ordered by account id, for inclusion proofs to be verified against a signed root.
+    pub history_root: Digest,
+}
+
+impl Checkpoint {
+    /// Commits to the current state of the accounts.
+    pub fn of<'a, I: IntoIterator<Item = &'a Account>>(epoch: u64, accounts: I) -> Result<Self> {
+        Ok(CheckpointStates::capture(epoch, accounts)?.checkpoint)
+    }
+}
+
+/// A checkpoint along with the states it commits to,
+/// as kept by the Replicas to prove the state of each account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct CheckpointStates {
+    pub(crate) checkpoint: Checkpoint,
+    states: BTreeMap<AccountId, AccountState>,
+    leaves: Vec<Digest>,
+    /// The history root of each account, with the number of credits and debits in its trees.
+    histories: BTreeMap<AccountId, (Digest, u64, u64)>,
+    history_leaves: Vec<Digest>,
+}
+
+impl CheckpointStates {
+    /// Captures the current state of the accounts.
+    pub(crate) fn capture<'a, I: IntoIterator<Item = &'a Account>>(
+        epoch: u64,
+        accounts: I,
+    ) -> Result<Self> {
+        let mut states = BTreeMap::new();
+        let mut histories = BTreeMap::new();
+        for account in accounts {
+            let _ = states.insert(account.id(), AccountState::of(account)?);
+            let (credits, debits) = account.history_size();
+            let _ = histories.insert(
+                account.id(),
+                (account.history_root(), credits as u64, debits as u64),
+            );
+        }
+        let mut leaves = vec![];
+        for state in states.values() {
+            leaves.push(state.digest()?);
+        }
+        let mut history_leaves = vec![];
+        for (id, (root, _, _)) in &histories {
+            history_leaves.push(history_leaf(id, root)?);
+        }
+        let checkpoint = Checkpoint {
+            epoch,
+            account_count: leaves.len() as u64,
+            state_hash: merkle_root(&leaves),
+            history_root: merkle_root(&history_leaves),
+        };
+        Ok(Self {
+            checkpoint,
+            states,
+            leaves,
+            histories,
+            history_leaves,
+        })
+    }
+
+    /// The checkpointed state of the account.
+    pub(crate) fn state(&self, account_id: &AccountId) -> Option<&AccountState> {
+        self.states.get(account_id)
+    }
+
+    /// The checkpointed state of the account, with its proof against the state hash.
+    pub(crate) fn prove(&self, account_id: &AccountId) -> Option<(AccountState, MerkleProof)> {
+        let index = self.states.keys().position(|id| id == account_id)?;
+        let state = self.states.get(account_id)?.clone();
+        Some((state, merkle_proof(&self.leaves, index)?))
+    }
+
+    /// The checkpointed history root of the account, with the number of credits and
+    /// debits in its trees, and its proof against the history root of the checkpoint.
+    pub(crate) fn prove_history(
+        &self,
+        account_id: &AccountId,
+    ) -> Option<((Digest, u64, u64), MerkleProof)> {
+        let index = self.histories.keys().position(|id| id == account_id)?;
+        let history = *self.histories.get(account_id)?;
+        Some((history, merkle_proof(&self.history_leaves, index)?))
+    }
+}
+
+/// The leaf of an account in the history root of a checkpoint.
+fn history_leaf(id: &AccountId, history_root: &Digest) -> Result<Digest> {
+    match bincode::serialize(id) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise account id".into())),
+        Ok(data) => Ok(hash(&[&data, history_root])),
+    }
+}
+
+/// A Checkpoint, signed by the group of Replicas holding the accounts.
+/// Histories up to a signed checkpoint are final.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedCheckpoint {
+    /// The commitment.
+    pub checkpoint: Checkpoint,
+    /// The aggregated signature of the Replicas.
+    pub group_sig: Signature,
+}
+
+impl SignedCheckpoint {
+    /// Combines the signature shares from a quorum of the
+    /// Replicas (see Replica::sign_checkpoint) into a signed checkpoint.
+    pub fn combine(
+        checkpoint: Checkpoint,
+        replicas: &PublicKeySet,
+        shares: &[SignatureShare],
+    ) -> Result<Self> {
+        let group_sig = combine(replicas, shares)?;
+        let signed = SignedCheckpoint {
+            checkpoint,
+            group_sig,
+        };
+        signed.verify(replicas)?;
+        Ok(signed)
+    }
+
+    /// Verifies that the checkpoint was signed by the given Replicas.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        match bincode::serialize(&self.checkpoint) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise checkpoint".into())),
+            Ok(data) => PublicKey::Bls(replicas.public_key()).verify(&self.group_sig, &data),
+        }
+    }
+}
+
+/// The state of an account up to an index of its history, signed by the group of
+/// Replicas holding the account, which replaces the entries before the index
+/// (see Replica::compact_wallet).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedCompaction {
+    /// The state of the account at the index.
+    pub state: AccountState,
+    /// The aggregated signature of the Replicas.
+    pub group_sig: Signature,
+}
+
+impl SignedCompaction {
+    /// Combines the signature shares from a quorum of the
+    /// Replicas (see Replica::sign_compaction) into a signed compaction.
+    pub fn combine(
+        state: AccountState,
+        replicas: &PublicKeySet,
+        shares: &[SignatureShare],
+    ) -> Result<Self> {
+        let group_sig = combine(replicas, shares)?;
+        let signed = SignedCompaction { state, group_sig };
+        signed.verify(&PublicKey::Bls(replicas.public_key()))?;
+        Ok(signed)
+    }
+
+    /// Verifies that the state was signed by the group with the given key.
+    pub fn verify(&self, group_key: &PublicKey) -> Result<()> {
+        match bincode::serialize(&self.state) {
+            Err(_) => Err(Error::NetworkOther(
+                "Could not serialise account state".into(),
+            )),
+            Ok(data) => group_key.verify(&self.group_sig, &data),
+        }
+    }
+}
+
+/// The history of an account after a checkpoint: the signed checkpoint,
+/// the state of the account at the checkpoint, and the transfers since.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CheckpointedHistory {
+    /// The last checkpoint of the Replicas.
+    pub signed_checkpoint: SignedCheckpoint,
+    /// The state of the account at the checkpoint.
+    pub state: AccountState,
+    /// The proof of the state, against the state hash of the checkpoint.
+    pub proof: MerkleProof,
+    /// The credits since the checkpoint.
+    pub credits: Vec<ReceivedCredit>,
+    /// The debits since the checkpoint.
+    pub debits: Vec<DebitAgreementProof>,
+}
+
+impl CheckpointedHistory {
+    /// Verifies that the checkpoint was signed by the given Replicas,
+    /// and that the state of the account is part of it.
+    pub fn verify_checkpoint(&self, replicas: &PublicKeySet) -> Result<()> {
+        self.signed_checkpoint.verify(replicas)?;
+        let state_hash = &self.signed_checkpoint.checkpoint.state_hash;
+        if self.proof.verify(self.state.digest()?, state_hash) {
+            Ok(())
+        } else {
+            Err(Error::from("Account state is not part of the checkpoint"))
+        }
+    }
+}
+
+/// The proof that a transfer is in the history of an account as of a checkpoint,
+/// for light clients to confirm that a payment landed, trusting only the signature
+/// of the Replicas over the checkpoint (see Replica::checkpointed_inclusion).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CheckpointedInclusion {
+    /// The checkpoint, signed by the Replicas.
+    pub signed_checkpoint: SignedCheckpoint,
+    /// The account.
+    pub account_id: AccountId,
+    /// The history root of the account at the checkpoint.
+    pub history_root: Digest,
+    /// The proof of the history root, against the history root of the checkpoint.
+    pub root_proof: MerkleProof,
+    /// The proof of the transfer, against the history root of the account.
+    pub inclusion: InclusionProof,
+}
+
+impl CheckpointedInclusion {
+    /// Verifies that the checkpoint was signed by the given Replicas, that the
+    /// history root of the account is part of it, and the transfer part of that.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        self.signed_checkpoint.verify(replicas)?;
+        let leaf = history_leaf(&self.account_id, &self.history_root)?;
+        let history_root = &self.signed_checkpoint.checkpoint.history_root;
+        if !self.root_proof.verify(leaf, history_root) {
+            return Err(Error::from("History root is not part of the checkpoint"));
+        }
+        self.inclusion.verify(&self.history_root)
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn state_hash_is_independent_of_order() -> Result<()> {
+        // Arrange
+        let first = get_account(10);
+        let second = get_account(20);
+
+        // Act
+        let checkpoint = Checkpoint::of(1, vec![&first, &second])?;
+        let reversed = Checkpoint::of(1, vec![&second, &first])?;
+        let changed = Checkpoint::of(1, vec![&first, &get_account(20)])?;
+
+        // Assert
+        assert!(checkpoint == reversed);
+        assert!(checkpoint.account_count == 2);
+        assert!(checkpoint.state_hash != changed.state_hash);
+        assert!(checkpoint.history_root != changed.history_root);
+        Ok(())
+    }
+
+    #[test]
+    fn proves_account_states() -> Result<()> {
+        let accounts: Vec<_> = (0..5).map(get_account).collect();
+        let captured = CheckpointStates::capture(1, &accounts)?;
+        for account in &accounts {
+            let (state, proof) = captured.prove(&account.id()).unwrap();
+            assert!(state == AccountState::of(account)?);
+            assert!(proof.verify(state.digest()?, &captured.checkpoint.state_hash));
+            let ((root, _, _), proof) = captured.prove_history(&account.id()).unwrap();
+            assert!(root == account.history_root());
+            assert!(proof.verify(
+                history_leaf(&account.id(), &root)?,
+                &captured.checkpoint.history_root
+            ));
+        }
+        assert!(captured.prove(&get_random_pk()).is_none());
+        Ok(())
+    }
+
+    fn get_account(balance: u64) -> Account {
+        let id = get_random_pk();
+        let mut account = Account::new(id);
+        account.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: id,
+            amount: Money::from_nano(balance),
+        });
+        account
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,390 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[cfg(not(feature = "system-clock"))]
+use super::clock::Clock;
+use super::{
+    account::Account,
+    actor::Actor,
+    attestation::{ReadFloor, SignedBalance},
+    ActorEvent, ReplicaEvent, ReplicaValidator,
+};
+use safe_nd::{
+    AccountId, DebitAgreementProof, Error, Money, PublicKey, Result, SafeKey, SignedTransfer,
+    Transfer, TransferValidated,
+};
+use std::collections::BTreeSet;
+use threshold_crypto::PublicKeySet;
+
+/// The calls a Client makes to the Replicas of its account.
+/// Implemented by upper layers, over the network or in-process.
+pub trait ReplicaTransport {
+    /// Sends the transfer to the Replicas for validation,
+    /// and returns the validations received in response.
+    fn validate(&mut self, signed_transfer: &SignedTransfer) -> Vec<TransferValidated>;
+    /// Sends the agreed transfer to the Replicas for registration.
+    fn register(&mut self, debit_proof: &DebitAgreementProof) -> Result<()>;
+    /// Fetches the events of the account from the Replicas.
+    fn history(&mut self, account_id: AccountId) -> Result<Vec<ReplicaEvent>>;
+    /// Fetches the balance of the account signed by each Replica (see Replica::balance_proof).
+    /// The query is tagged with the floor that responses must reach, for Replicas
+    /// that have not reached it to catch up first, or not to respond.
+    fn balance_proofs(&mut self, account_id: AccountId, floor: ReadFloor) -> Vec<SignedBalance>;
+}
+
+/// How many times a call to the Replicas is made before giving up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of calls, including the first one.
+    pub max_attempts: usize,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3 }
+    }
+}
+
+impl RetryPolicy {
+    fn run<T, F: FnMut() -> Result<T>>(&self, mut call: F) -> Result<T> {
+        let mut result = call();
+        for _ in 1..self.max_attempts {
+            if result.is_ok() {
+                break;
+            }
+            result = call();
+        }
+        result
+    }
+}
+
+/// The groups of Replicas whose credits are accepted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrustRegistry {
+    groups: BTreeSet<PublicKey>,
+}
+
+impl TrustRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Accepts credits from the group.
+    pub fn trust(&mut self, group: PublicKey) {
+        let _ = self.groups.insert(group);
+    }
+}
+
+impl ReplicaValidator for TrustRegistry {
+    fn is_valid(&self, replica_group: PublicKey) -> bool {
+        self.groups.contains(&replica_group)
+    }
+}
+
+/// The one flow for sending and receiving money: an Actor, its accumulation
+/// of validations and its synching, over a [ReplicaTransport](ReplicaTransport),
+/// with credits accepted from the groups of a [TrustRegistry](TrustRegistry),
+/// and calls retried according to a [RetryPolicy](RetryPolicy).
+#[derive(Debug)]
+pub struct Client<T: ReplicaTransport> {
+    actor: Actor<TrustRegistry>,
+    replicas: PublicKeySet,
+    transport: T,
+    retry_policy: RetryPolicy,
+}
+
+impl<T: ReplicaTransport> Client<T> {
+    /// Connects to the Replicas, from the locally stored state
+    /// of the account (`Account::new` if there is none), and synchs
+    /// the transfers missing from it. Our own Replicas are always trusted.
+    /// Without the system-clock feature, the clock of our Actor is given (see Clock).
+    pub fn connect_state(
+        state: Account,
+        client_safe_key: SafeKey,
+        replicas: PublicKeySet,
+        mut trust: TrustRegistry,
+        transport: T,
+        #[cfg(not(feature = "system-clock"))] clock: Clock,
+    ) -> Result<Self> {
+        if state.id() != client_safe_key.public_key() {
+            return Err(Error::from("State is not of this client's account"));
+        }
+        trust.trust(PublicKey::Bls(replicas.public_key()));
+        let mut client = Self {
+            actor: Actor::from_snapshot(
+                state,
+                client_safe_key,
+                replicas.clone(),
+                trust,
+                #[cfg(not(feature = "system-clock"))]
+                clock,
+            ),
+            replicas,
+            transport,
+            retry_policy: Default::default(),
+        };
+        client.synch()?;
+        Ok(client)
+    }
+
+    /// Sets the retry policy of calls to the Replicas.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// The id of our account.
+    pub fn id(&self) -> AccountId {
+        self.actor.id()
+    }
+
+    /// Our balance, as of the last synch.
+    pub fn balance(&self) -> Money {
+        self.actor.balance()
+    }
+
+    /// Our credits and debits, as of the last synch.
+    pub fn history(&self) -> (Vec<Transfer>, Vec<Transfer>) {
+        (self.actor.credits_since(0), self.actor.debits_since(0))
+    }
+
+    /// Fetches our balance from our Replicas, from the first response signed by one
+    /// of them that reflects all our history known so far, so that a registered
+    /// debit never seems to disappear. Stale responses are skipped, and the query
+    /// retried if all of them were.
+    pub fn fresh_balance(&mut self) -> Result<Money> {
+        let id = self.id();
+        let floor = self.actor.read_floor();
+        let replicas = &self.replicas;
+        let transport = &mut self.transport;
+        self.retry_policy.run(|| {
+            transport
+                .balance_proofs(id, floor)
+                .into_iter()
+                .filter(|signed| signed.attestation.wallet == id)
+                .filter(|signed| floor.is_met_by(&signed.attestation))
+                .find(|signed| signed.verify(replicas).is_ok())
+                .map(|signed| signed.attestation.balance)
+                .ok_or_else(|| Error::from("No response of the Replicas is fresh enough"))
+        })
+    }
+
+    /// Fetches and applies the transfers we do not have yet.
+    pub fn synch(&mut self) -> Result<()> {
+        let id = self.id();
+        let transport = &mut self.transport;
+        let events = self.retry_policy.run(|| transport.history(id))?;
+        // an error here only means that there was nothing new
+        if let Ok(synched) = self.actor.synch(events) {
+            self.actor.apply(ActorEvent::TransfersSynched(synched));
+        }
+        Ok(())
+    }
+
+    /// Sends money, returning the proof of the debit once it has been registered.
+    /// If no proof is obtained, the transfer stays pending, and the
+    /// account can not send again until its registration has been synched.
+    pub fn send(&mut self, amount: Money, to: AccountId) -> Result<DebitAgreementProof> {
+        let initiated = self.actor.transfer(amount, to)?;
+        self.actor
+            .apply(ActorEvent::TransferInitiated(initiated.clone()));
+
+        let retry_policy = self.retry_policy;
+        let actor = &mut self.actor;
+        let transport = &mut self.transport;
+        let debit_proof = retry_policy.run(|| {
+            let validations = transport.validate(&initiated.signed_transfer);
+            for received in actor.receive_batch(validations) {
+                // duplicates and invalid validations are skipped
+                if let Ok(received) = received {
+                    let proof = received.proof.clone();
+                    actor.apply(ActorEvent::TransferValidationReceived(received));
+                    if let Some(proof) = proof {
+                        return Ok(proof);
+                    }
+                }
+            }
+            Err(Error::from("Validations did not reach quorum"))
+        })?;
+
+        let registration = self.actor.register(debit_proof.clone())?;
+        let transport = &mut self.transport;
+        retry_policy.run(|| transport.register(&debit_proof))?;
+        self.actor
+            .apply(ActorEvent::TransferRegistrationSent(registration));
+        Ok(debit_proof)
+    }
+}
+
+// the tests build instances with the default clock, that of the system
+#[cfg(feature = "system-clock")]
+mod test {
+    use super::*;
+    use crate::replica::Replica;
+    use crdts::Dot;
+    use safe_nd::ClientFullId;
+    use std::{
+        cell::RefCell,
+        collections::{HashMap, HashSet},
+        rc::Rc,
+    };
+    use threshold_crypto::{SecretKey, SecretKeySet};
+
+    /// A single group of Replicas, shared by all clients.
+    #[derive(Clone)]
+    struct Section(Rc<RefCell<(Vec<Replica>, Vec<ReplicaEvent>)>>);
+
+    impl ReplicaTransport for Section {
+        fn validate(&mut self, signed_transfer: &SignedTransfer) -> Vec<TransferValidated> {
+            let (replicas, _) = &mut *self.0.borrow_mut();
+            replicas
+                .iter_mut()
+                .filter_map(|replica| {
+                    let validated = replica.validate(signed_transfer.clone()).ok()?;
+                    replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+                    Some(validated)
+                })
+                .collect()
+        }
+
+        fn register(&mut self, debit_proof: &DebitAgreementProof) -> Result<()> {
+            let (replicas, events) = &mut *self.0.borrow_mut();
+            for replica in replicas {
+                let registered = match replica.register(debit_proof)? {
+                    Some(registered) => registered,
+                    None => continue,
+                };
+                replica.apply(ReplicaEvent::TransferRegistered(registered.clone()));
+                events.push(ReplicaEvent::TransferRegistered(registered));
+                let propagated = replica.receive_propagated(debit_proof)?;
+                replica.apply(ReplicaEvent::TransferPropagated(propagated.clone()));
+                events.push(ReplicaEvent::TransferPropagated(propagated));
+            }
+            Ok(())
+        }
+
+        fn history(&mut self, _: AccountId) -> Result<Vec<ReplicaEvent>> {
+            Ok(self.0.borrow().1.clone())
+        }
+
+        fn balance_proofs(&mut self, account_id: AccountId, _: ReadFloor) -> Vec<SignedBalance> {
+            let (replicas, _) = &*self.0.borrow();
+            replicas
+                .iter()
+                .filter_map(|replica| replica.balance_proof(&account_id).ok()?)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn sends_and_synchs() -> Result<()> {
+        // Arrange
+        let secret_key_set = SecretKeySet::random(1, &mut rand::thread_rng());
+        let group = secret_key_set.public_keys();
+        let alice_key = get_safe_key();
+        let bob_key = get_safe_key();
+        let bob_id = bob_key.public_key();
+        let mut alice_state = Account::new(alice_key.public_key());
+        alice_state.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: alice_key.public_key(),
+            amount: Money::from_nano(10),
+        });
+        let section = get_section(&secret_key_set, vec![alice_state.clone()]);
+        let mut alice = Client::connect_state(
+            alice_state,
+            alice_key,
+            group.clone(),
+            TrustRegistry::new(),
+            section.clone(),
+        )?;
+
+        // Act
+        let _ = alice.send(Money::from_nano(4), bob_id)?;
+        let _ = alice.send(Money::from_nano(4), bob_id)?;
+        let bob = Client::connect_state(
+            Account::new(bob_id),
+            bob_key,
+            group,
+            TrustRegistry::new(),
+            section,
+        )?;
+
+        // Assert
+        assert!(alice.balance() == Money::from_nano(2));
+        assert!(alice.history().1.len() == 2);
+        assert!(bob.balance() == Money::from_nano(8));
+        assert!(bob.history().0.len() == 2);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_own_debits() -> Result<()> {
+        // Arrange
+        let secret_key_set = SecretKeySet::random(1, &mut rand::thread_rng());
+        let alice_key = get_safe_key();
+        let mut alice_state = Account::new(alice_key.public_key());
+        alice_state.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: alice_key.public_key(),
+            amount: Money::from_nano(10),
+        });
+        let section = get_section(&secret_key_set, vec![alice_state.clone()]);
+        let lagging = get_section(&secret_key_set, vec![alice_state.clone()]);
+        let mut alice = Client::connect_state(
+            alice_state,
+            alice_key,
+            secret_key_set.public_keys(),
+            TrustRegistry::new(),
+            section.clone(),
+        )?;
+        let _ = alice.send(Money::from_nano(4), get_random_pk())?;
+
+        // Act
+        // the first Replica to respond has not registered the debit
+        let stale = lagging.0.borrow().0[0].clone();
+        section.0.borrow_mut().0[0] = stale;
+        let balance = alice.fresh_balance()?;
+        let stale = lagging.0.borrow().0.clone();
+        section.0.borrow_mut().0 = stale;
+
+        // Assert
+        assert!(balance == Money::from_nano(6));
+        assert!(alice.fresh_balance().is_err());
+        Ok(())
+    }
+
+    fn get_section(secret_key_set: &SecretKeySet, accounts: Vec<Account>) -> Section {
+        let group = secret_key_set.public_keys();
+        let accounts: HashMap<_, _> = accounts.into_iter().map(|a| (a.id(), a)).collect();
+        let mut known_groups = HashSet::new();
+        let _ = known_groups.insert(group.clone());
+        let replicas = (0..3)
+            .map(|index| {
+                Replica::from_snapshot(
+                    secret_key_set.secret_key_share(index),
+                    index,
+                    group.clone(),
+                    known_groups.clone(),
+                    accounts.clone(),
+                    Default::default(),
+                )
+            })
+            .collect();
+        Section(Rc::new(RefCell::new((replicas, vec![]))))
+    }
+
+    fn get_safe_key() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "system-clock")]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// A coarse point in time, in seconds since the unix epoch.
+pub type Timestamp = u64;
+
+/// When a Replica applied an entry to the history of a wallet,
+/// by its clock, for queries of the history by time.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct AppliedAt {
+    /// The time, by the clock of the Replica.
+    pub time: Timestamp,
+    /// The epoch of the Replica at the time (see Replica::epoch).
+    pub epoch: u64,
+}
+
+/// The single source of time for Replicas and Actors.
+/// All time-based features (expiries, delays, leases, rate limits)
+/// read time through this, so that their semantics are consistent,
+/// and can be driven manually in tests.
+pub trait TimeSource: Send + Sync {
+    /// Current coarse timestamp, in seconds since the unix epoch.
+    fn now(&self) -> Timestamp;
+    /// Current section height, as tracked by upper layers.
+    fn height(&self) -> u64;
+}
+
+impl<T: TimeSource + ?Sized> TimeSource for Arc<T> {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+
+    fn height(&self) -> u64 {
+        (**self).height()
+    }
+}
+
+/// Reads time from the system clock.
+/// There is no notion of section height here, so it is always 0.
+#[cfg(feature = "system-clock")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemTimeSource;
+
+#[cfg(feature = "system-clock")]
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> Timestamp {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    fn height(&self) -> u64 {
+        0
+    }
+}
+
+/// A time source that only moves when told to.
+/// Share it (in an Arc) between a test and the instances under test.
+#[derive(Debug, Default)]
+pub struct ManualTimeSource {
+    now: AtomicU64,
+    height: AtomicU64,
+}
+
+impl ManualTimeSource {
+    /// Starts at the given timestamp and height.
+    pub fn new(now: Timestamp, height: u64) -> Self {
+        Self {
+            now: AtomicU64::new(now),
+            height: AtomicU64::new(height),
+        }
+    }
+
+    /// Sets the current timestamp.
+    pub fn set_now(&self, now: Timestamp) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    /// Moves the current timestamp forward.
+    pub fn advance(&self, secs: u64) {
+        let _ = self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+
+    /// Sets the current section height.
+    pub fn set_height(&self, height: u64) {
+        self.height.store(height, Ordering::SeqCst);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Timestamp {
+        self.now.load(Ordering::SeqCst)
+    }
+
+    fn height(&self) -> u64 {
+        self.height.load(Ordering::SeqCst)
+    }
+}
+
+/// A cheaply cloneable handle to the TimeSource used by an instance.
+/// Two clocks are equal if they read from the same source.
+/// Without the system-clock feature, such as on wasm32-unknown-unknown where
+/// there is no system clock, there is no default clock: the constructors of
+/// Replicas, Actors and faucets then take the clock they are to read, so that
+/// none of them reads a time standing still at 0 for want of one.
+#[derive(Clone)]
+pub struct Clock(Arc<dyn TimeSource>);
+
+impl Clock {
+    /// Wraps the given source.
+    pub fn new<T: TimeSource + 'static>(source: T) -> Self {
+        Self(Arc::new(source))
+    }
+
+    /// Current coarse timestamp.
+    pub fn now(&self) -> Timestamp {
+        self.0.now()
+    }
+
+    /// Current section height.
+    pub fn height(&self) -> u64 {
+        self.0.height()
+    }
+}
+
+/// Reads the system clock.
+#[cfg(feature = "system-clock")]
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new(SystemTimeSource)
+    }
+}
+
+/// Times work, for metrics and progress reports. Without the
+/// system-clock feature, no time is measured, and none elapses.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Stopwatch {
+    #[cfg(feature = "system-clock")]
+    started: Instant,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "system-clock")]
+            started: Instant::now(),
+        }
+    }
+
+    #[cfg(feature = "system-clock")]
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    #[cfg(not(feature = "system-clock"))]
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::default()
+    }
+}
+
+impl Debug for Clock {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Clock")
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn manual_source_is_shared() {
+        // Arrange
+        let source = Arc::new(ManualTimeSource::new(100, 1));
+        let clock = Clock::new(source.clone());
+
+        // Act
+        source.advance(20);
+        source.set_height(2);
+
+        // Assert
+        assert!(clock.now() == 120);
+        assert!(clock.height() == 2);
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::signable::{Signable, SignableCredit, SignableProof, SignableTransfer};
+use safe_nd::{DebitAgreementProof, Error, Result, SignedTransfer, Transfer};
+use serde::{Deserialize, Serialize};
+
+/// A payload signed on the hot paths of validation and propagation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Payload<'a> {
+    /// A transfer, as signed by its sender.
+    Transfer(&'a Transfer),
+    /// A signed transfer, as signed by the Replicas of the sender.
+    Proof(&'a SignedTransfer),
+    /// A debit proof, as signed by the Replicas of the recipient.
+    Credit(&'a DebitAgreementProof),
+}
+
+/// An encoding of the signed payloads, for comparing their costs (see the codecs
+/// example), and for planning a move from one to another (see CodecMigration).
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum Codec {
+    /// The bincode serialisation, signed over before the canonical encoding.
+    Bincode,
+    /// The canonical encoding (see Signable), signed over now.
+    Canonical,
+    /// The postcard serialisation. Compiled in with the `postcard` feature,
+    /// and last, so that the serialisation of the others does not depend on it.
+    #[cfg(feature = "postcard")]
+    Postcard,
+}
+
+impl Codec {
+    /// The codecs compiled in.
+    pub fn compiled() -> Vec<Codec> {
+        vec![
+            Codec::Bincode,
+            Codec::Canonical,
+            #[cfg(feature = "postcard")]
+            Codec::Postcard,
+        ]
+    }
+
+    /// The name of the codec.
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Bincode => "bincode",
+            Codec::Canonical => "canonical",
+            #[cfg(feature = "postcard")]
+            Codec::Postcard => "postcard",
+        }
+    }
+
+    /// The bytes of the payload in this encoding.
+    pub fn encode(self, payload: Payload) -> Result<Vec<u8>> {
+        match self {
+            Codec::Bincode => match payload {
+                Payload::Transfer(transfer) => serialise(bincode::serialize(transfer)),
+                Payload::Proof(signed_transfer) => serialise(bincode::serialize(signed_transfer)),
+                Payload::Credit(debit_proof) => serialise(bincode::serialize(debit_proof)),
+            },
+            Codec::Canonical => Ok(match payload {
+                Payload::Transfer(transfer) => SignableTransfer::new(transfer).to_bytes(),
+                Payload::Proof(signed_transfer) => SignableProof::new(signed_transfer).to_bytes(),
+                Payload::Credit(debit_proof) => SignableCredit::new(debit_proof).to_bytes(),
+            }),
+            #[cfg(feature = "postcard")]
+            Codec::Postcard => match payload {
+                Payload::Transfer(transfer) => serialise(postcard::to_allocvec(transfer)),
+                Payload::Proof(signed_transfer) => {
+                    serialise(postcard::to_allocvec(signed_transfer))
+                }
+                Payload::Credit(debit_proof) => serialise(postcard::to_allocvec(debit_proof)),
+            },
+        }
+    }
+}
+
+/// The codecs whose signatures are accepted at the height, the signing one first:
+/// those of the migration if any (see CodecMigration::accepted_codecs), or else
+/// the canonical encoding alone.
+pub fn accepted_codecs(migration: Option<&CodecMigration>, height: u64) -> Vec<Codec> {
+    match migration {
+        Some(migration) => migration.accepted_codecs(height),
+        None => vec![Codec::Canonical],
+    }
+}
+
+/// Whether the check passes for the payload in any of the codecs.
+pub fn verified_in<F: FnMut(&[u8]) -> bool>(
+    codecs: &[Codec],
+    payload: Payload,
+    mut check: F,
+) -> bool {
+    codecs
+        .iter()
+        .filter_map(|codec| codec.encode(payload).ok())
+        .any(|data| check(&data))
+}
+
+fn serialise<E>(result: std::result::Result<Vec<u8>, E>) -> Result<Vec<u8>> {
+    result.map_err(|_| Error::NetworkOther("Could not serialise payload".into()))
+}
+
+/// A plan to move the signed payloads from one codec to another, by section height:
+/// payloads are signed in the codec `from` until the switch height, and in the codec
+/// `to` from then on. Signatures over `from` are accepted until the retire height, so
+/// that payloads signed before the switch can still be verified while in flight.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CodecMigration {
+    /// The codec signed over before the switch.
+    pub from: Codec,
+    /// The codec signed over from the switch on.
+    pub to: Codec,
+    /// The height from which payloads are signed in the codec `to`.
+    pub switch_height: u64,
+    /// The height from which signatures over the codec `from` are no longer accepted.
+    pub retire_height: u64,
+}
+
+impl CodecMigration {
+    /// A plan to move between the codecs, retiring the old one at the retire height.
+    pub fn new(from: Codec, to: Codec, switch_height: u64, retire_height: u64) -> Result<Self> {
+        if from == to {
+            return Err(Error::from("Migration between the same codec"));
+        }
+        if retire_height < switch_height {
+            return Err(Error::from("Codec retired before the switch"));
+        }
+        Ok(Self {
+            from,
+            to,
+            switch_height,
+            retire_height,
+        })
+    }
+
+    /// The codec to sign in at the height.
+    pub fn signing_codec(&self, height: u64) -> Codec {
+        if height < self.switch_height {
+            self.from
+        } else {
+            self.to
+        }
+    }
+
+    /// The codecs whose signatures are accepted at the height, the signing one first.
+    /// The codec `to` is accepted before the switch too, as peers are not at the same height.
+    pub fn accepted_codecs(&self, height: u64) -> Vec<Codec> {
+        if height < self.switch_height {
+            vec![self.from, self.to]
+        } else if height < self.retire_height {
+            vec![self.to, self.from]
+        } else {
+            vec![self.to]
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::{Money, PublicKey};
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn encodes_in_every_codec() -> Result<()> {
+        // Arrange
+        let transfer = get_transfer();
+
+        // Act
+        let encoded = Codec::compiled()
+            .into_iter()
+            .map(|codec| codec.encode(Payload::Transfer(&transfer)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Assert
+        assert!(encoded.len() == Codec::compiled().len());
+        assert!(encoded[1] == SignableTransfer::new(&transfer).to_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn plans_codec_migrations() -> Result<()> {
+        // Arrange
+        let migration = CodecMigration::new(Codec::Bincode, Codec::Canonical, 10, 20)?;
+
+        // Act
+        let before = migration.accepted_codecs(9);
+        let during = migration.accepted_codecs(10);
+        let after = migration.accepted_codecs(20);
+
+        // Assert
+        assert!(migration.signing_codec(9) == Codec::Bincode);
+        assert!(migration.signing_codec(10) == Codec::Canonical);
+        assert!(before == vec![Codec::Bincode, Codec::Canonical]);
+        assert!(during == vec![Codec::Canonical, Codec::Bincode]);
+        assert!(after == vec![Codec::Canonical]);
+        assert!(CodecMigration::new(Codec::Bincode, Codec::Bincode, 10, 20).is_err());
+        assert!(CodecMigration::new(Codec::Bincode, Codec::Canonical, 20, 10).is_err());
+        Ok(())
+    }
+
+    fn get_transfer() -> Transfer {
+        Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: get_random_pk(),
+            amount: Money::from_nano(10),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,381 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[cfg(not(feature = "system-clock"))]
+use super::clock::{Clock, ManualTimeSource};
+use super::{
+    account::Account,
+    accumulator::ValidationAccumulator,
+    codec::Codec,
+    replica::Replica,
+    signable::{Signable, SignableProof, SignableTransfer},
+    verification::{verify_debit_agreement_proof, verify_transfer_validated},
+    ReplicaEvent,
+};
+use crdts::Dot;
+use safe_nd::{
+    AccountId, ClientFullId, DebitAgreementProof, Error, Money, PublicKey, Result, SafeKey,
+    Signature, SignedTransfer, Transfer, TransferPropagated, TransferRegistered, TransferValidated,
+};
+use std::collections::{HashMap, HashSet};
+use threshold_crypto::{PublicKeySet, SecretKey, SecretKeySet, SecretKeyShare};
+
+/// A Replica implementation to run the conformance suite against (see run_conformance).
+/// The suite drives it through the steps of the protocol, as a group of one Replica.
+pub trait ConformanceReplica: Sized {
+    /// A Replica of the group, with the key share at the index, knowing the other
+    /// groups, and holding the accounts with the initial balances.
+    fn genesis(
+        secret_key: SecretKeyShare,
+        key_index: usize,
+        peer_replicas: PublicKeySet,
+        other_groups: HashSet<PublicKeySet>,
+        balances: HashMap<AccountId, Money>,
+    ) -> Self;
+    /// Step 1. Validates a debit of an account held.
+    fn validate(&self, signed_transfer: SignedTransfer) -> Result<TransferValidated>;
+    /// Step 2. Registers a debit agreed by our group. None if it is already registered.
+    fn register(&self, debit_proof: &DebitAgreementProof) -> Result<Option<TransferRegistered>>;
+    /// Step 3. Receives a debit agreed by a group we know of, crediting the recipient.
+    fn receive_propagated(&self, debit_proof: &DebitAgreementProof) -> Result<TransferPropagated>;
+    /// Applies an event raised by any of the above.
+    fn apply(&mut self, event: ReplicaEvent);
+    /// The balance of an account, None if not held.
+    fn balance(&self, account_id: &AccountId) -> Option<Money>;
+}
+
+impl ConformanceReplica for Replica {
+    fn genesis(
+        secret_key: SecretKeyShare,
+        key_index: usize,
+        peer_replicas: PublicKeySet,
+        other_groups: HashSet<PublicKeySet>,
+        balances: HashMap<AccountId, Money>,
+    ) -> Self {
+        let accounts = balances
+            .into_iter()
+            .map(|(id, amount)| {
+                let mut account = Account::new(id);
+                account.append(Transfer {
+                    id: Dot::new(PublicKey::Bls(SecretKey::random().public_key()), 0),
+                    to: id,
+                    amount,
+                });
+                (id, account)
+            })
+            .collect();
+        Replica::from_snapshot(
+            secret_key,
+            key_index,
+            peer_replicas,
+            other_groups,
+            accounts,
+            Default::default(),
+            #[cfg(not(feature = "system-clock"))]
+            Clock::new(ManualTimeSource::default()),
+        )
+    }
+
+    fn validate(&self, signed_transfer: SignedTransfer) -> Result<TransferValidated> {
+        Replica::validate(self, signed_transfer).map_err(Error::from)
+    }
+
+    fn register(&self, debit_proof: &DebitAgreementProof) -> Result<Option<TransferRegistered>> {
+        Replica::register(self, debit_proof).map_err(Error::from)
+    }
+
+    fn receive_propagated(&self, debit_proof: &DebitAgreementProof) -> Result<TransferPropagated> {
+        Replica::receive_propagated(self, debit_proof).map_err(Error::from)
+    }
+
+    fn apply(&mut self, event: ReplicaEvent) {
+        Replica::apply(self, event)
+    }
+
+    fn balance(&self, account_id: &AccountId) -> Option<Money> {
+        Replica::balance(self, account_id)
+    }
+}
+
+/// The outcome of a case of the conformance suite.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConformanceCase {
+    /// The rule of the protocol checked.
+    pub name: &'static str,
+    /// Why the implementation does not follow the rule, if it does not.
+    pub failure: Option<Error>,
+}
+
+/// The outcomes of all cases of the conformance suite.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConformanceReport {
+    /// The cases, in the order run.
+    pub cases: Vec<ConformanceCase>,
+}
+
+impl ConformanceReport {
+    /// Whether the implementation follows every rule.
+    pub fn is_conformant(&self) -> bool {
+        self.cases.iter().all(|case| case.failure.is_none())
+    }
+
+    /// The cases the implementation fails.
+    pub fn failures(&self) -> impl Iterator<Item = &ConformanceCase> {
+        self.cases.iter().filter(|case| case.failure.is_some())
+    }
+}
+
+/// Runs the protocol conformance suite against a Replica implementation: the validation
+/// rules, the sequencing of debits, the verification of proofs, and the idempotency
+/// of credits, as implemented by the Replica of this crate, which is the reference.
+/// Each case runs on a new network, of a debiting and a crediting group.
+pub fn run_conformance<R: ConformanceReplica>() -> ConformanceReport {
+    let cases: Vec<(&'static str, fn(&mut Network<R>) -> Result<()>)> = vec![
+        ("validates a valid debit", validates_valid_debit),
+        ("rejects an overdraft", rejects_overdraft),
+        ("rejects a zero value debit", rejects_zero_value),
+        ("rejects a debit to self", rejects_debit_to_self),
+        (
+            "rejects a forged actor signature",
+            rejects_forged_actor_signature,
+        ),
+        (
+            "rejects an out of sequence debit",
+            rejects_out_of_sequence_debit,
+        ),
+        ("registers a debit in sequence", registers_in_sequence),
+        (
+            "rejects a forged proof at registration",
+            rejects_forged_registration,
+        ),
+        ("credits a propagated debit once", credits_once),
+        ("rejects a proof of an unknown group", rejects_unknown_group),
+    ];
+    let cases = cases
+        .into_iter()
+        .map(|(name, case)| ConformanceCase {
+            name,
+            failure: case(&mut Network::new()).err(),
+        })
+        .collect();
+    ConformanceReport { cases }
+}
+
+/// The balance of the sender at genesis.
+const GENESIS_BALANCE: u64 = 100;
+
+/// A debiting group holding the sender, and a crediting group holding
+/// the recipient, of one Replica each.
+struct Network<R: ConformanceReplica> {
+    sender_key: SafeKey,
+    sender: AccountId,
+    recipient: AccountId,
+    debiting_keys: SecretKeySet,
+    debiting: R,
+    crediting: R,
+}
+
+impl<R: ConformanceReplica> Network<R> {
+    fn new() -> Self {
+        let mut rng = rand::thread_rng();
+        let sender_key = SafeKey::client(ClientFullId::new_ed25519(&mut rng));
+        let sender = sender_key.public_key();
+        let recipient = PublicKey::Bls(SecretKey::random().public_key());
+        let debiting_keys = SecretKeySet::random(0, &mut rng);
+        let crediting_keys = SecretKeySet::random(0, &mut rng);
+        let group = |keys: &SecretKeySet, other: &SecretKeySet, balances| {
+            R::genesis(
+                keys.secret_key_share(0),
+                0,
+                keys.public_keys(),
+                vec![other.public_keys()].into_iter().collect(),
+                balances,
+            )
+        };
+        let mut sender_balance = HashMap::new();
+        let _ = sender_balance.insert(sender, Money::from_nano(GENESIS_BALANCE));
+        let debiting = group(&debiting_keys, &crediting_keys, sender_balance);
+        let crediting = group(&crediting_keys, &debiting_keys, HashMap::new());
+        Self {
+            sender_key,
+            sender,
+            recipient,
+            debiting_keys,
+            debiting,
+            crediting,
+        }
+    }
+
+    fn sign(&self, counter: u64, to: AccountId, amount: u64) -> SignedTransfer {
+        let transfer = Transfer {
+            id: Dot::new(self.sender, counter),
+            to,
+            amount: Money::from_nano(amount),
+        };
+        let data = SignableTransfer::new(&transfer).to_bytes();
+        SignedTransfer {
+            actor_signature: self.sender_key.sign(&data),
+            transfer,
+        }
+    }
+
+    /// Validates the debit at the debiting group, and combines the proof.
+    fn agree(&mut self, signed_transfer: SignedTransfer) -> Result<DebitAgreementProof> {
+        let validated = self.debiting.validate(signed_transfer.clone())?;
+        verify_transfer_validated(&validated, &[Codec::Canonical])?;
+        self.debiting
+            .apply(ReplicaEvent::TransferValidated(validated.clone()));
+        let mut accumulator =
+            ValidationAccumulator::new(signed_transfer, self.debiting_keys.public_keys());
+        match accumulator.add(validated)? {
+            Some(proof) => Ok(proof),
+            None => Err(Error::from("Validation did not complete a proof")),
+        }
+    }
+
+    /// Agrees on and registers the debit at the debiting group.
+    fn debit(&mut self, counter: u64, amount: u64) -> Result<DebitAgreementProof> {
+        let proof = self.agree(self.sign(counter, self.recipient, amount))?;
+        let registered = self
+            .debiting
+            .register(&proof)?
+            .ok_or_else(|| Error::from("A new debit was not registered"))?;
+        self.debiting
+            .apply(ReplicaEvent::TransferRegistered(registered));
+        Ok(proof)
+    }
+}
+
+fn expect(condition: bool, rule: &str) -> Result<()> {
+    if condition {
+        Ok(())
+    } else {
+        Err(Error::from(rule))
+    }
+}
+
+fn validates_valid_debit<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let proof = network.agree(network.sign(0, network.recipient, 10))?;
+    let replicas = network.debiting_keys.public_keys();
+    verify_debit_agreement_proof(&proof, &replicas, &[Codec::Canonical])
+}
+
+fn rejects_overdraft<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let signed = network.sign(0, network.recipient, GENESIS_BALANCE + 1);
+    expect(
+        network.debiting.validate(signed).is_err(),
+        "A debit of more than the balance was validated",
+    )
+}
+
+fn rejects_zero_value<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let signed = network.sign(0, network.recipient, 0);
+    expect(
+        network.debiting.validate(signed).is_err(),
+        "A debit of zero was validated",
+    )
+}
+
+fn rejects_debit_to_self<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let signed = network.sign(0, network.sender, 10);
+    expect(
+        network.debiting.validate(signed).is_err(),
+        "A debit to the sender was validated",
+    )
+}
+
+fn rejects_forged_actor_signature<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let mut signed = network.sign(0, network.recipient, 10);
+    let forger = SecretKey::random();
+    signed.actor_signature = Signature::Bls(forger.sign(b"forged"));
+    expect(
+        network.debiting.validate(signed).is_err(),
+        "A debit with a forged signature was validated",
+    )
+}
+
+fn rejects_out_of_sequence_debit<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let skipped = network.sign(1, network.recipient, 10);
+    expect(
+        network.debiting.validate(skipped).is_err(),
+        "A debit skipping a counter was validated",
+    )?;
+    let _ = network.debit(0, 10)?;
+    let replayed = network.sign(0, network.recipient, 20);
+    expect(
+        network.debiting.validate(replayed).is_err(),
+        "A debit reusing a counter was validated",
+    )
+}
+
+fn registers_in_sequence<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let first = network.debit(0, 10)?;
+    let _ = network.debit(1, 20)?;
+    expect(
+        network.debiting.balance(&network.sender) == Some(Money::from_nano(GENESIS_BALANCE - 30)),
+        "The balance does not reflect the registered debits",
+    )?;
+    expect(
+        network
+            .debiting
+            .register(&first)
+            .map_or(false, |registered| registered.is_none()),
+        "A retransmitted debit was registered twice",
+    )
+}
+
+fn rejects_forged_registration<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let mut proof = network.agree(network.sign(0, network.recipient, 10))?;
+    let forger = SecretKey::random();
+    proof.debiting_replicas_sig = Signature::Bls(forger.sign(b"forged"));
+    expect(
+        network.debiting.register(&proof).is_err(),
+        "A debit with a forged proof was registered",
+    )
+}
+
+fn credits_once<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let proof = network.debit(0, 10)?;
+    let propagated = network.crediting.receive_propagated(&proof)?;
+    network
+        .crediting
+        .apply(ReplicaEvent::TransferPropagated(propagated));
+    expect(
+        network.crediting.balance(&network.recipient) == Some(Money::from_nano(10)),
+        "The balance does not reflect the propagated credit",
+    )?;
+    expect(
+        network.crediting.receive_propagated(&proof).is_err(),
+        "A credit was propagated twice",
+    )
+}
+
+fn rejects_unknown_group<R: ConformanceReplica>(network: &mut Network<R>) -> Result<()> {
+    let mut proof = network.debit(0, 10)?;
+    let unknown = SecretKey::random();
+    let data = SignableProof::new(&proof.signed_transfer).to_bytes();
+    proof.debiting_replicas_sig = Signature::Bls(unknown.sign(data));
+    expect(
+        network.crediting.receive_propagated(&proof).is_err(),
+        "A debit agreed by an unknown group was credited",
+    )
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn reference_replica_conforms() {
+        // Act
+        let report = run_conformance::<Replica>();
+
+        // Assert
+        assert!(report.failures().count() == 0);
+        assert!(report.is_conformant());
+    }
+}
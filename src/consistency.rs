@@ -0,0 +1,132 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::Account,
+    checkpoint::{AccountState, Checkpoint},
+    hashing::Digest,
+};
+use safe_nd::{AccountId, Error, Result, SignatureShare};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use threshold_crypto::PublicKeySet;
+
+/// The commitment of a Replica to the state of all its accounts,
+/// exchanged with its peers at startup, before serving clients.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct StateCommitment {
+    /// The commitment to the state of all accounts.
+    pub checkpoint: Checkpoint,
+    /// The digest of the state of each account, for finding those that diverge.
+    pub account_digests: BTreeMap<AccountId, Digest>,
+    /// The signature of the Replica over the above.
+    pub replica_signature: SignatureShare,
+}
+
+impl StateCommitment {
+    /// The digests of the states of the accounts.
+    pub(crate) fn digests<'a, I: IntoIterator<Item = &'a Account>>(
+        accounts: I,
+    ) -> Result<BTreeMap<AccountId, Digest>> {
+        let mut digests = BTreeMap::new();
+        for account in accounts {
+            let _ = digests.insert(account.id(), AccountState::of(account)?.digest()?);
+        }
+        Ok(digests)
+    }
+
+    /// Verifies that the commitment was signed by a Replica of the given group.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        match bincode::serialize(&(&self.checkpoint, &self.account_digests)) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise commitment".into())),
+            Ok(data) => {
+                let key = replicas.public_key_share(self.replica_signature.index);
+                if key.verify(&self.replica_signature.share, data) {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidSignature)
+                }
+            }
+        }
+    }
+}
+
+/// The outcome of comparing the commitments of a group of Replicas.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ConsistencyReport {
+    /// Whether all Replicas committed to the same state.
+    pub agreed: bool,
+    /// The state committed to by the most Replicas.
+    pub majority_state: Digest,
+    /// The indices of the Replicas committed to that state.
+    pub majority: BTreeSet<usize>,
+    /// The indices of the Replicas committed to another state.
+    pub dissenting: BTreeSet<usize>,
+    /// The accounts whose states differ between the Replicas, or that some Replicas lack.
+    pub diverging_accounts: BTreeSet<AccountId>,
+}
+
+impl ConsistencyReport {
+    /// Compares the commitments, verifying that they were signed by the
+    /// given group. A Replica with several commitments only counts once.
+    pub fn of(commitments: &[StateCommitment], replicas: &PublicKeySet) -> Result<Self> {
+        let mut by_replica = BTreeMap::new();
+        for commitment in commitments {
+            commitment.verify(replicas)?;
+            let _ = by_replica.insert(commitment.replica_signature.index, commitment);
+        }
+        if by_replica.is_empty() {
+            return Err(Error::from("No commitments to compare"));
+        }
+        let mut by_state: BTreeMap<Digest, BTreeSet<usize>> = BTreeMap::new();
+        for (index, commitment) in &by_replica {
+            let _ = by_state
+                .entry(commitment.checkpoint.state_hash)
+                .or_default()
+                .insert(*index);
+        }
+        let (majority_state, majority) = by_state
+            .iter()
+            .max_by_key(|(_, indices)| indices.len())
+            .map(|(state, indices)| (*state, indices.clone()))
+            .ok_or_else(|| Error::from("No commitments to compare"))?;
+        let dissenting = by_replica
+            .keys()
+            .filter(|index| !majority.contains(index))
+            .cloned()
+            .collect();
+
+        let mut digests: BTreeMap<AccountId, BTreeSet<Option<Digest>>> = BTreeMap::new();
+        let all_accounts: BTreeSet<_> = by_replica
+            .values()
+            .flat_map(|c| c.account_digests.keys())
+            .cloned()
+            .collect();
+        for account in all_accounts {
+            for commitment in by_replica.values() {
+                let _ = digests
+                    .entry(account)
+                    .or_default()
+                    .insert(commitment.account_digests.get(&account).cloned());
+            }
+        }
+        let diverging_accounts = digests
+            .into_iter()
+            .filter(|(_, states)| states.len() > 1)
+            .map(|(account, _)| account)
+            .collect();
+
+        Ok(Self {
+            agreed: by_state.len() == 1,
+            majority_state,
+            majority,
+            dissenting,
+            diverging_accounts,
+        })
+    }
+}
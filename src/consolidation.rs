@@ -0,0 +1,69 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{hashing::Digest, quorum::combine};
+use safe_nd::{AccountId, Error, Money, PublicKey, Result, Signature, SignatureShare};
+use serde::{Deserialize, Serialize};
+use threshold_crypto::PublicKeySet;
+
+/// When the credits of an account are consolidated, as configured
+/// by its owner (see WalletFeature::Consolidation).
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ConsolidationPolicy {
+    /// Credits of at most this amount are consolidated.
+    pub max_amount: Money,
+    /// The least number of credits consolidated at once.
+    pub min_count: u64,
+}
+
+/// A summary of the oldest credits of an account, which replaces them in its history.
+/// Each summary also covers the credits of the summary it replaces, if any.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CreditSummary {
+    /// The account.
+    pub wallet: AccountId,
+    /// Number of credits covered.
+    pub count: u64,
+    /// The sum of the credits covered.
+    pub total: Money,
+    /// Hash of the previous summary, if any, and of the credits since, in order.
+    pub transfers_hash: Digest,
+}
+
+/// A summary of credits, signed by the group of Replicas holding the account,
+/// so that the history stays verifiable without the individual credits.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedCreditSummary {
+    /// The summary.
+    pub summary: CreditSummary,
+    /// The aggregated signature of the Replicas.
+    pub group_sig: Signature,
+}
+
+impl SignedCreditSummary {
+    /// Combines the signature shares from a quorum of the
+    /// Replicas (see Replica::sign_consolidation) into a signed summary.
+    pub fn combine(
+        summary: CreditSummary,
+        replicas: &PublicKeySet,
+        shares: &[SignatureShare],
+    ) -> Result<Self> {
+        let group_sig = combine(replicas, shares)?;
+        let signed = SignedCreditSummary { summary, group_sig };
+        signed.verify(&PublicKey::Bls(replicas.public_key()))?;
+        Ok(signed)
+    }
+
+    /// Verifies that the summary was signed by the group with the given key.
+    pub fn verify(&self, group_key: &PublicKey) -> Result<()> {
+        match bincode::serialize(&self.summary) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise summary".into())),
+            Ok(data) => group_key.verify(&self.group_sig, &data),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::hashing::{hash, Digest};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use safe_nd::{AccountId, ClientFullId, Error, Result, SafeKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Formatter};
+
+/// The maximum size of the id of an app, in bytes.
+pub const MAX_APP_ID_BYTES: usize = 256;
+
+/// The secret that the owner key and all app-scoped wallet keys of a user are derived
+/// from. The same seed and app id always derive the same key, so the wallets of all apps
+/// can be restored from the seed alone, and apps need never hold the owner key.
+#[derive(Clone)]
+pub struct MasterSeed([u8; 32]);
+
+impl MasterSeed {
+    /// The seed with the given bytes, such as restored from a backup.
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// The key of the owner, signing the registrations of the app wallets.
+    pub fn owner_key(&self) -> SafeKey {
+        self.derive(&[b"owner"])
+    }
+
+    /// The key of the wallet of the app.
+    pub fn app_key(&self, app_id: &str) -> Result<SafeKey> {
+        check_app_id(app_id)?;
+        Ok(self.derive(&[b"app", app_id.as_bytes()]))
+    }
+
+    /// The registration of the wallet of the app, signed by the owner key and by the
+    /// key of the app wallet, proving that the owner controls both (see AppWalletRegistered).
+    pub fn register_app(&self, app_id: &str) -> Result<SignedAppWallet> {
+        let owner_key = self.owner_key();
+        let app_key = self.app_key(app_id)?;
+        let registration = AppWalletRegistration {
+            owner: owner_key.public_key(),
+            app_id: app_id.to_string(),
+            wallet: app_key.public_key(),
+        };
+        let bytes = registration.to_bytes()?;
+        Ok(SignedAppWallet {
+            owner_sig: owner_key.sign(&bytes),
+            wallet_sig: app_key.sign(&bytes),
+            registration,
+        })
+    }
+
+    fn derive(&self, path: &[&[u8]]) -> SafeKey {
+        let mut parts: Vec<&[u8]> = vec![b"safe_transfers_wallet", &self.0];
+        parts.extend_from_slice(path);
+        let seed: Digest = hash(&parts);
+        // ChaCha20 is specified, unlike StdRng, whose algorithm may change between releases
+        // of rand, which would derive other keys from the same seed, losing the wallets
+        SafeKey::client(ClientFullId::new_bls(&mut ChaChaRng::from_seed(seed)))
+    }
+}
+
+impl Debug for MasterSeed {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        // never leak the seed into logs
+        write!(formatter, "MasterSeed(..)")
+    }
+}
+
+/// The link between the owner of app-scoped wallets and the wallet of an app.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct AppWalletRegistration {
+    /// The owner key.
+    pub owner: AccountId,
+    /// The id of the app.
+    pub app_id: String,
+    /// The wallet of the app, derived from the seed of the owner.
+    pub wallet: AccountId,
+}
+
+impl AppWalletRegistration {
+    /// The bytes signed by the owner and the app wallet.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match bincode::serialize(self) {
+            Err(_) => Err(Error::NetworkOther(
+                "Could not serialise registration".into(),
+            )),
+            Ok(data) => Ok(data),
+        }
+    }
+}
+
+/// A registration of an app wallet, signed by both of its keys.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedAppWallet {
+    /// The registration.
+    pub registration: AppWalletRegistration,
+    /// The signature of the owner.
+    pub owner_sig: Signature,
+    /// The signature of the app wallet.
+    pub wallet_sig: Signature,
+}
+
+impl SignedAppWallet {
+    /// Verifies the app id, and that both the owner and the wallet signed the registration.
+    pub fn verify(&self) -> Result<()> {
+        let registration = &self.registration;
+        check_app_id(&registration.app_id)?;
+        if registration.owner == registration.wallet {
+            return Err(Error::from("App wallet is the owner key"));
+        }
+        let bytes = registration.to_bytes()?;
+        registration.owner.verify(&self.owner_sig, &bytes)?;
+        registration.wallet.verify(&self.wallet_sig, &bytes)
+    }
+}
+
+fn check_app_id(app_id: &str) -> Result<()> {
+    if app_id.is_empty() {
+        return Err(Error::from("App id is empty"));
+    }
+    if app_id.len() > MAX_APP_ID_BYTES {
+        return Err(Error::from("App id is too long"));
+    }
+    Ok(())
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn derives_the_same_keys_from_the_same_seed() -> Result<()> {
+        // Arrange
+        let seed = MasterSeed::new([1; 32]);
+        let restored = MasterSeed::new([1; 32]);
+
+        // Act
+        let signed = seed.register_app("chat")?;
+
+        // Assert
+        assert!(signed.verify().is_ok());
+        assert!(signed.registration.owner == restored.owner_key().public_key());
+        assert!(signed.registration.wallet == restored.app_key("chat")?.public_key());
+        assert!(signed.registration.wallet != seed.app_key("games")?.public_key());
+        assert!(seed.app_key("").is_err());
+        assert!(seed.app_key(&"x".repeat(MAX_APP_ID_BYTES + 1)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::account::Account;
+use safe_nd::{Error, Result, Transfer, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Differences between two histories of the same account,
+/// such as held by two Replicas, or by a Replica and the Actor.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct AccountDiff {
+    /// Credits of the second history that the first does not have.
+    pub credits_missing_in_first: Vec<TransferId>,
+    /// Credits of the first history that the second does not have.
+    pub credits_missing_in_second: Vec<TransferId>,
+    /// Debits of the second history that the first does not have.
+    pub debits_missing_in_first: Vec<TransferId>,
+    /// Debits of the first history that the second does not have.
+    pub debits_missing_in_second: Vec<TransferId>,
+    /// Different debits at the same counter, as (first, second).
+    pub conflicting_debits: Vec<(Transfer, Transfer)>,
+    /// The lowest debit counter at which the histories differ.
+    pub divergence_point: Option<u64>,
+}
+
+impl AccountDiff {
+    /// Whether the histories hold the same transfers.
+    pub fn is_empty(&self) -> bool {
+        self == &Default::default()
+    }
+}
+
+/// Compares two histories of the same account.
+/// Credits have no absolute order, so only their presence is compared,
+/// while debits are compared counter by counter.
+pub fn diff_accounts(first: &Account, second: &Account) -> Result<AccountDiff> {
+    if first.id() != second.id() {
+        return Err(Error::from("Histories are not of the same account"));
+    }
+    let credits = |account: &Account| -> BTreeSet<TransferId> {
+        account
+            .credit_entries()
+            .iter()
+            .map(|entry| entry.transfer().id)
+            .collect()
+    };
+    let debits = |account: &Account| -> BTreeMap<u64, Transfer> {
+        account
+            .debit_entries()
+            .iter()
+            .map(|entry| (entry.transfer().id.counter, entry.transfer().clone()))
+            .collect()
+    };
+    let (first_credits, second_credits) = (credits(first), credits(second));
+    let (first_debits, second_debits) = (debits(first), debits(second));
+
+    let mut diff = AccountDiff {
+        credits_missing_in_first: second_credits.difference(&first_credits).cloned().collect(),
+        credits_missing_in_second: first_credits.difference(&second_credits).cloned().collect(),
+        ..Default::default()
+    };
+    let counters: BTreeSet<_> = first_debits.keys().chain(second_debits.keys()).collect();
+    for counter in counters {
+        match (first_debits.get(counter), second_debits.get(counter)) {
+            (Some(a), Some(b)) if a == b => continue,
+            (Some(a), Some(b)) => diff.conflicting_debits.push((a.clone(), b.clone())),
+            (Some(a), None) => diff.debits_missing_in_second.push(a.id),
+            (None, Some(b)) => diff.debits_missing_in_first.push(b.id),
+            (None, None) => continue,
+        }
+        if diff.divergence_point.is_none() {
+            diff.divergence_point = Some(*counter);
+        }
+    }
+    Ok(diff)
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::{Money, PublicKey};
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn finds_missing_and_conflicting_transfers() -> Result<()> {
+        // Arrange
+        let id = get_random_pk();
+        let credit = get_transfer(get_random_pk(), 0, id, 10);
+        let late_credit = get_transfer(get_random_pk(), 0, id, 5);
+        let mut first = Account::new(id);
+        let mut second = Account::new(id);
+        for account in vec![&mut first, &mut second] {
+            account.append(credit.clone());
+            account.append(get_transfer(id, 0, get_random_pk(), 1));
+        }
+        first.append(late_credit.clone());
+        let debit = get_transfer(id, 1, get_random_pk(), 2);
+        first.append(debit.clone());
+        second.append(get_transfer(id, 1, get_random_pk(), 2));
+        second.append(get_transfer(id, 2, get_random_pk(), 2));
+
+        // Act
+        let diff = diff_accounts(&first, &second)?;
+
+        // Assert
+        assert!(diff.credits_missing_in_second == vec![late_credit.id]);
+        assert!(diff.credits_missing_in_first.is_empty());
+        assert!(diff.conflicting_debits.len() == 1);
+        assert!(diff.conflicting_debits[0].0 == debit);
+        assert!(diff.debits_missing_in_first == vec![Dot::new(id, 2)]);
+        assert!(diff.divergence_point == Some(1));
+        assert!(diff_accounts(&first, &first.clone())?.is_empty());
+        Ok(())
+    }
+
+    fn get_transfer(from: PublicKey, counter: u64, to: PublicKey, amount: u64) -> Transfer {
+        Transfer {
+            id: Dot::new(from, counter),
+            to,
+            amount: Money::from_nano(amount),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
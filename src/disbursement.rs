@@ -0,0 +1,385 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    codec::Codec,
+    hashing::{hash, merkle_proof, merkle_root, Digest, MerkleProof},
+    money::checked_sum,
+    signable::{Signable, SignableProof},
+    signer::TransferSigner,
+    verification::verify_signed_transfer,
+};
+use safe_nd::{
+    AccountId, DebitAgreementProof, Error, Money, PublicKey, Result, Signature, SignatureShare,
+    SignedTransfer, Transfer, TransferId,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use threshold_crypto::PublicKeySet;
+
+/// The most payouts of a single disbursement.
+pub const MAX_DISBURSEMENT_PAYOUTS: usize = 10_000;
+
+/// A payment to a single recipient of a disbursement.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Payout {
+    /// The recipient.
+    pub to: AccountId,
+    /// The amount paid.
+    pub amount: Money,
+}
+
+/// Many payouts made with a single debit, such as of farming rewards. The debit is
+/// from the wallet to itself, of the sum of the payouts, and the wallet signs the list
+/// of payouts along with it. Its Replicas validate the disbursement as a whole (see
+/// Replica::validate_disbursement), and sign the root of the payouts once the debit is
+/// registered, from which a proof of each payout is derived (see payout_proofs), to be
+/// propagated to the Replicas of its recipient (see Replica::receive_payout).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Disbursement {
+    /// The debit, signed by the disbursing wallet.
+    pub signed_transfer: SignedTransfer,
+    /// The payouts, in the order of the Merkle tree over them.
+    pub payouts: Vec<Payout>,
+    /// The signature of the disbursing wallet over the id of the debit and the root.
+    pub signature: Signature,
+}
+
+impl Disbursement {
+    /// The disbursement by the signer of the payouts, with the debit.
+    pub fn new<S: TransferSigner + ?Sized>(
+        signer: &S,
+        signed_transfer: SignedTransfer,
+        payouts: Vec<Payout>,
+    ) -> Result<Self> {
+        let root = payouts_root(&payouts)?;
+        let payload = disbursement_payload(&signed_transfer.id(), &root)?;
+        Ok(Self {
+            signature: signer.sign(&payload)?,
+            signed_transfer,
+            payouts,
+        })
+    }
+
+    /// The id of the debit.
+    pub fn id(&self) -> TransferId {
+        self.signed_transfer.id()
+    }
+
+    /// The Merkle root of the payouts.
+    pub fn root(&self) -> Result<Digest> {
+        payouts_root(&self.payouts)
+    }
+
+    /// Verifies that the debit is from the wallet to itself, of the sum of the payouts,
+    /// that the payouts are to distinct recipients other than the wallet, none of them
+    /// empty, and that the disbursement was signed by the disbursing wallet.
+    pub fn verify(&self) -> Result<()> {
+        let transfer = &self.signed_transfer.transfer;
+        if transfer.to != transfer.id.actor {
+            return Err(Error::from(
+                "Disbursement is not debited to the wallet itself",
+            ));
+        }
+        if self.payouts.is_empty() || self.payouts.len() > MAX_DISBURSEMENT_PAYOUTS {
+            return Err(Error::from("Disbursement has too few or too many payouts"));
+        }
+        let mut recipients = HashSet::new();
+        for payout in &self.payouts {
+            if payout.amount == Money::zero() {
+                return Err(Error::from("Payout of zero value"));
+            }
+            if payout.to == transfer.id.actor || !recipients.insert(payout.to) {
+                return Err(Error::from("Payout recipients are not distinct"));
+            }
+        }
+        if checked_sum(self.payouts.iter().map(|payout| payout.amount)) != Some(transfer.amount) {
+            return Err(Error::from("Payouts do not sum to the debit"));
+        }
+        let payload = disbursement_payload(&transfer.id, &self.root()?)?;
+        transfer.id.actor.verify(&self.signature, &payload)
+    }
+
+    /// The proof of each payout, from the proof of the debit and the
+    /// root signed by the Replicas that registered it, in the order of the payouts.
+    pub fn payout_proofs(
+        &self,
+        debit_proof: &DebitAgreementProof,
+        signed: &SignedDisbursement,
+    ) -> Result<Vec<PayoutProof>> {
+        if debit_proof.signed_transfer != self.signed_transfer {
+            return Err(Error::from(
+                "Proof is not of the debit of this disbursement",
+            ));
+        }
+        let leaves = self
+            .payouts
+            .iter()
+            .map(payout_leaf)
+            .collect::<Result<Vec<_>>>()?;
+        if signed.id != self.id() || signed.root != merkle_root(&leaves) {
+            return Err(Error::from(
+                "Signed disbursement is not of this disbursement",
+            ));
+        }
+        Ok(self
+            .payouts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, payout)| {
+                Some(PayoutProof {
+                    debit_proof: debit_proof.clone(),
+                    signed_disbursement: signed.clone(),
+                    transfer: Transfer {
+                        id: self.id(),
+                        to: payout.to,
+                        amount: payout.amount,
+                    },
+                    proof: merkle_proof(&leaves, index)?,
+                })
+            })
+            .collect())
+    }
+}
+
+/// The root of the payouts of a disbursement, signed
+/// by the group of Replicas that registered its debit.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedDisbursement {
+    /// The id of the debit.
+    pub id: TransferId,
+    /// The Merkle root of the payouts.
+    pub root: Digest,
+    /// The aggregated signature of the Replicas.
+    pub group_sig: Signature,
+}
+
+impl SignedDisbursement {
+    /// Combines the signature shares from a quorum of the Replicas
+    /// (see Replica::register_disbursement) into a signed disbursement.
+    pub fn combine(
+        disbursement: &Disbursement,
+        replicas: &PublicKeySet,
+        shares: &[SignatureShare],
+    ) -> Result<Self> {
+        let sig_shares: BTreeMap<_, _> =
+            shares.iter().map(|s| (s.index, s.share.clone())).collect();
+        let sig = match replicas.combine_signatures(&sig_shares) {
+            Ok(sig) => sig,
+            Err(_) => return Err(Error::from("Not enough signature shares")),
+        };
+        let signed = SignedDisbursement {
+            id: disbursement.id(),
+            root: disbursement.root()?,
+            group_sig: Signature::Bls(sig),
+        };
+        signed.verify(&PublicKey::Bls(replicas.public_key()))?;
+        Ok(signed)
+    }
+
+    /// Verifies that the root was signed by the Replicas with the given key.
+    pub fn verify(&self, replicas: &PublicKey) -> Result<()> {
+        replicas.verify(&self.group_sig, &signed_payload(&self.id, &self.root)?)
+    }
+}
+
+/// A single payout of a disbursement, with the proof of the debit, and
+/// the proof that the payout is one of those whose root the Replicas signed.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct PayoutProof {
+    /// The proof of the debit of the disbursement.
+    pub debit_proof: DebitAgreementProof,
+    /// The root of the payouts, signed by the Replicas that registered the debit.
+    pub signed_disbursement: SignedDisbursement,
+    /// The payout, as a transfer with the id of the debit.
+    pub transfer: Transfer,
+    /// The proof of the payout, against the signed root.
+    pub proof: MerkleProof,
+}
+
+impl PayoutProof {
+    /// The id of the debit of the disbursement.
+    pub fn id(&self) -> TransferId {
+        self.transfer.id
+    }
+
+    /// The recipient of the payout.
+    pub fn to(&self) -> AccountId {
+        self.transfer.to
+    }
+
+    /// Verifies that the debit was signed by its sender and agreed by the Replicas with
+    /// the given key, and that the payout is one of those whose root they signed.
+    pub fn verify(&self, replicas: &PublicKey) -> Result<()> {
+        let signed_transfer = &self.debit_proof.signed_transfer;
+        verify_signed_transfer(signed_transfer, &[Codec::Canonical])?;
+        SignableProof::new(signed_transfer)
+            .verify(replicas, &self.debit_proof.debiting_replicas_sig)?;
+        self.verify_payout(replicas)
+    }
+
+    /// Same as verify, for a debit proof already verified against the key.
+    pub(crate) fn verify_payout(&self, replicas: &PublicKey) -> Result<()> {
+        let debit = &self.debit_proof.signed_transfer.transfer;
+        if self.transfer.id != debit.id || debit.to != debit.id.actor {
+            return Err(Error::from("Payout is not of a disbursement"));
+        }
+        if self.signed_disbursement.id != debit.id {
+            return Err(Error::from("Signed disbursement is not of the debit"));
+        }
+        self.signed_disbursement.verify(replicas)?;
+        let leaf = payout_leaf(&Payout {
+            to: self.transfer.to,
+            amount: self.transfer.amount,
+        })?;
+        if self.proof.verify(leaf, &self.signed_disbursement.root) {
+            Ok(())
+        } else {
+            Err(Error::from("Payout is not part of the disbursement"))
+        }
+    }
+}
+
+/// A received payout, with the key of the Replicas that registered its disbursement.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ReceivedPayout {
+    /// The proof of the payout.
+    pub payout_proof: PayoutProof,
+    /// The public key of the Replicas that registered the disbursement.
+    pub debiting_replicas: PublicKey,
+}
+
+/// The bytes of the root of a disbursement signed by the Replicas.
+pub(crate) fn signed_payload(id: &TransferId, root: &Digest) -> Result<Vec<u8>> {
+    match bincode::serialize(&(b"disbursed", id, root)) {
+        Err(_) => Err(Error::NetworkOther(
+            "Could not serialise disbursement".into(),
+        )),
+        Ok(data) => Ok(data),
+    }
+}
+
+/// The bytes of a disbursement signed by the disbursing wallet.
+fn disbursement_payload(id: &TransferId, root: &Digest) -> Result<Vec<u8>> {
+    match bincode::serialize(&(b"disbursement", id, root)) {
+        Err(_) => Err(Error::NetworkOther(
+            "Could not serialise disbursement".into(),
+        )),
+        Ok(data) => Ok(data),
+    }
+}
+
+fn payouts_root(payouts: &[Payout]) -> Result<Digest> {
+    let leaves = payouts
+        .iter()
+        .map(payout_leaf)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(merkle_root(&leaves))
+}
+
+fn payout_leaf(payout: &Payout) -> Result<Digest> {
+    match bincode::serialize(payout) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise payout".into())),
+        Ok(data) => Ok(hash(&[&data])),
+    }
+}
+
+mod test {
+    use super::*;
+    use crate::signable::SignableTransfer;
+    use crdts::Dot;
+    use safe_nd::{ClientFullId, SafeKey};
+
+    #[test]
+    fn refuses_payouts_that_do_not_sum_to_the_debit() -> Result<()> {
+        // Arrange
+        let wallet = get_safe_key();
+        let (first, second) = (get_random_pk(), get_random_pk());
+        let payouts = vec![payout(first, 10), payout(second, 20)];
+
+        // Act
+        let disbursement = Disbursement::new(&wallet, get_debit(&wallet, 30), payouts.clone())?;
+        let short = Disbursement::new(&wallet, get_debit(&wallet, 40), payouts)?;
+        let repeated = vec![payout(first, 10), payout(first, 20)];
+        let repeated = Disbursement::new(&wallet, get_debit(&wallet, 30), repeated)?;
+
+        // Assert
+        assert!(disbursement.verify().is_ok());
+        assert!(short.verify().is_err());
+        assert!(repeated.verify().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn derives_a_proof_of_each_payout() -> Result<()> {
+        // Arrange
+        let wallet = get_safe_key();
+        let payouts: Vec<_> = (1..=5)
+            .map(|amount| payout(get_random_pk(), amount))
+            .collect();
+        let disbursement = Disbursement::new(&wallet, get_debit(&wallet, 15), payouts)?;
+        let replicas = threshold_crypto::SecretKeySet::random(0, &mut rand::thread_rng());
+        let signed_transfer = disbursement.signed_transfer.clone();
+        let debit_proof = DebitAgreementProof {
+            debiting_replicas_sig: Signature::Bls(
+                replicas
+                    .secret_key()
+                    .sign(SignableProof::new(&signed_transfer).to_bytes()),
+            ),
+            signed_transfer,
+        };
+        let root = disbursement.root()?;
+        let share = SignatureShare {
+            index: 0,
+            share: replicas
+                .secret_key_share(0)
+                .sign(signed_payload(&disbursement.id(), &root)?),
+        };
+        let signed = SignedDisbursement::combine(&disbursement, &replicas.public_keys(), &[share])?;
+
+        // Act
+        let proofs = disbursement.payout_proofs(&debit_proof, &signed)?;
+        let mut inflated = proofs[0].clone();
+        inflated.transfer.amount = Money::from_nano(15);
+
+        // Assert
+        let key = PublicKey::Bls(replicas.public_keys().public_key());
+        assert!(proofs.len() == 5);
+        assert!(proofs.iter().all(|proof| proof.verify(&key).is_ok()));
+        assert!(inflated.verify(&key).is_err());
+        assert!(proofs[0].verify(&get_random_pk()).is_err());
+        Ok(())
+    }
+
+    fn payout(to: PublicKey, amount: u64) -> Payout {
+        Payout {
+            to,
+            amount: Money::from_nano(amount),
+        }
+    }
+
+    fn get_debit(from: &SafeKey, amount: u64) -> SignedTransfer {
+        let transfer = Transfer {
+            id: Dot::new(from.public_key(), 0),
+            to: from.public_key(),
+            amount: Money::from_nano(amount),
+        };
+        SignedTransfer {
+            actor_signature: from.sign(&SignableTransfer::new(&transfer).to_bytes()),
+            transfer,
+        }
+    }
+
+    fn get_safe_key() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(threshold_crypto::SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    error::RejectionReason,
+    money::{covers, saturating_add},
+};
+use safe_nd::{AccountId, Error, Money, Result, SignatureShare, Transfer};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+use threshold_crypto::PublicKeySet;
+
+/// The fee of a transfer: a base amount, and a rate of the amount transferred.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct FeeSchedule {
+    /// The fee of any transfer.
+    pub base: Money,
+    /// The fee per million nanos transferred.
+    pub per_million: u64,
+}
+
+impl FeeSchedule {
+    /// The fee of transferring the amount.
+    pub fn fee(&self, amount: Money) -> Money {
+        let rate = amount.as_nano() as u128 * self.per_million as u128 / 1_000_000;
+        let rate = Money::from_nano(rate.min(u64::MAX as u128) as u64);
+        saturating_add(self.base, rate)
+    }
+}
+
+/// Computes the fee of a transfer, charged to the sender on top of the amount
+/// by Replicas configured with it (see Replica::with_fee_policy).
+pub trait FeePolicy: Send + Sync {
+    /// The fee of the transfer.
+    fn fee(&self, transfer: &Transfer) -> Money;
+}
+
+impl FeePolicy for FeeSchedule {
+    fn fee(&self, transfer: &Transfer) -> Money {
+        FeeSchedule::fee(self, transfer.amount)
+    }
+}
+
+impl Debug for dyn FeePolicy {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "FeePolicy")
+    }
+}
+
+/// Where the fees charged go.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum FeeDestination {
+    /// The fees are removed from circulation.
+    Burn,
+    /// The fees are credited to this account,
+    /// which must be held by the same Replicas as the payers.
+    Wallet(AccountId),
+}
+
+/// The fee policy of a Replica, with where the fees go.
+/// Clones share the same policy.
+#[derive(Clone)]
+pub(crate) struct FeeModel {
+    policy: Arc<dyn FeePolicy>,
+    pub(crate) destination: FeeDestination,
+}
+
+impl FeeModel {
+    pub(crate) fn new<P: FeePolicy + 'static>(policy: P, destination: FeeDestination) -> Self {
+        Self {
+            policy: Arc::new(policy),
+            destination,
+        }
+    }
+
+    pub(crate) fn fee(&self, transfer: &Transfer) -> Money {
+        self.policy.fee(transfer)
+    }
+}
+
+impl Debug for FeeModel {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "FeeModel({:?})", self.destination)
+    }
+}
+
+/// The economic rules of a section, that debits must satisfy.
+/// Transfers do not carry fees, so the fee is not charged,
+/// but it must be covered by the balance on top of the amount.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct EconomicParams {
+    /// The fees of transfers.
+    pub fee_schedule: FeeSchedule,
+    /// Transfers of less than this amount are rejected.
+    pub dust_threshold: Money,
+    /// The balance a debit must leave in the account, besides the fee.
+    pub min_reserve: Money,
+}
+
+impl EconomicParams {
+    /// The balance needed for a debit of the amount.
+    pub fn required_balance(&self, amount: Money) -> Money {
+        let with_fee = saturating_add(amount, self.fee_schedule.fee(amount));
+        saturating_add(with_fee, self.min_reserve)
+    }
+
+    /// Checks a debit of the amount from the balance against the rules.
+    pub fn check(&self, balance: Money, amount: Money) -> std::result::Result<(), RejectionReason> {
+        if !covers(amount, self.dust_threshold) {
+            return Err(RejectionReason::BelowDustThreshold {
+                threshold: self.dust_threshold,
+                requested: amount,
+            });
+        }
+        let required = self.required_balance(amount);
+        if !covers(balance, required) {
+            return Err(RejectionReason::BelowRequiredBalance { balance, required });
+        }
+        Ok(())
+    }
+}
+
+/// The economic rules of a section, signed by the Replica that was queried,
+/// for Actors to cache and check their transfers against before signing them.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedEconomicParams {
+    /// The rules.
+    pub params: EconomicParams,
+    /// The signature of the Replica over the rules.
+    pub replica_signature: SignatureShare,
+}
+
+impl SignedEconomicParams {
+    /// Verifies that the rules were signed by a Replica of the given group.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        match bincode::serialize(&self.params) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise params".into())),
+            Ok(data) => {
+                let key = replicas.public_key_share(self.replica_signature.index);
+                if key.verify(&self.replica_signature.share, data) {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidSignature)
+                }
+            }
+        }
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn checks_debits_against_the_rules() {
+        // Arrange
+        let params = EconomicParams {
+            fee_schedule: FeeSchedule {
+                base: Money::from_nano(10),
+                per_million: 10_000,
+            },
+            dust_threshold: Money::from_nano(100),
+            min_reserve: Money::from_nano(50),
+        };
+
+        // Act
+        let required = params.required_balance(Money::from_nano(1_000));
+
+        // Assert
+        assert!(required == Money::from_nano(1_070));
+        assert!(params
+            .check(Money::from_nano(1_070), Money::from_nano(1_000))
+            .is_ok());
+        let reason = params
+            .check(Money::from_nano(1_000), Money::from_nano(1_000))
+            .unwrap_err();
+        assert!(reason.missing() == Some(Money::from_nano(70)));
+        assert!(params
+            .check(Money::from_nano(1_000), Money::from_nano(99))
+            .is_err());
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::hashing::Digest;
+use safe_nd::{Error, PublicKey, Result, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use threshold_crypto::{PublicKeySet, PublicKeyShare};
+
+/// What a remote attestation service vouches for: that the key share at the index
+/// of the group was provisioned to, and only lives in, a trusted execution environment
+/// running the measured code. The evidence of the platform, such as its quote, is
+/// opaque to the Replicas, and kept for auditors to check with the vendor of the platform.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct KeyShareStatement {
+    /// The key of the group the share is of.
+    pub group: PublicKey,
+    /// The index of the share in the group.
+    pub key_index: usize,
+    /// The public key of the share.
+    pub key_share: PublicKeyShare,
+    /// The measurement of the code of the enclave holding the share.
+    pub measurement: Digest,
+    /// The evidence of the platform that the attestation service checked.
+    pub evidence: Vec<u8>,
+}
+
+impl KeyShareStatement {
+    /// The bytes signed by the attestation service.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match bincode::serialize(self) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise statement".into())),
+            Ok(data) => Ok(data),
+        }
+    }
+}
+
+/// A statement about a key share, signed by a remote attestation service.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct KeyShareAttestation {
+    /// The statement.
+    pub statement: KeyShareStatement,
+    /// The key of the attestation service.
+    pub attester: PublicKey,
+    /// The signature of the attestation service over the statement.
+    pub signature: Signature,
+}
+
+impl KeyShareAttestation {
+    /// Verifies that the statement is about a share of the group, made by a trusted
+    /// attestation service about an accepted enclave, and signed by that service.
+    pub fn verify(&self, group: &PublicKeySet, policy: &AttestationPolicy) -> Result<()> {
+        if !policy.attesters.contains(&self.attester) {
+            return Err(Error::from("Attester is not trusted"));
+        }
+        if !policy.measurements.contains(&self.statement.measurement) {
+            return Err(Error::from("Enclave measurement is not accepted"));
+        }
+        self.verify_signed(group)
+    }
+
+    /// Verifies that the statement is about a share of the group, and signed by the
+    /// attestation service, whether or not the service and the enclave are trusted.
+    pub fn verify_signed(&self, group: &PublicKeySet) -> Result<()> {
+        let statement = &self.statement;
+        if statement.group != PublicKey::Bls(group.public_key()) {
+            return Err(Error::from("Attestation is not of the group"));
+        }
+        if statement.key_share != group.public_key_share(statement.key_index) {
+            return Err(Error::from("Key share is not of the group"));
+        }
+        self.attester
+            .verify(&self.signature, &statement.to_bytes()?)
+    }
+}
+
+/// The attestation services and enclaves that the key shares of a group
+/// may be attested by and live in (see Replica::with_attestation_policy).
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct AttestationPolicy {
+    /// The keys of the trusted attestation services.
+    pub attesters: BTreeSet<PublicKey>,
+    /// The accepted measurements of enclave code.
+    pub measurements: BTreeSet<Digest>,
+    /// Whether a Replica on standby is only promoted with an attested key share.
+    pub required: bool,
+}
+
+mod test {
+    use super::*;
+    use threshold_crypto::{SecretKey, SecretKeySet};
+
+    #[test]
+    fn verifies_attestations_against_the_policy() -> Result<()> {
+        // Arrange
+        let attester = SecretKey::random();
+        let group = get_group();
+        let policy = AttestationPolicy {
+            attesters: vec![PublicKey::Bls(attester.public_key())]
+                .into_iter()
+                .collect(),
+            measurements: vec![[1; 32]].into_iter().collect(),
+            required: true,
+        };
+        let attested = get_attestation(&attester, &group, [1; 32])?;
+        let unmeasured = get_attestation(&attester, &group, [2; 32])?;
+        let untrusted = get_attestation(&SecretKey::random(), &group, [1; 32])?;
+
+        // Act
+        let accepted = attested.verify(&group, &policy);
+        let other_group = attested.verify(&get_group(), &policy);
+
+        // Assert
+        assert!(accepted.is_ok());
+        assert!(other_group.is_err());
+        assert!(unmeasured.verify(&group, &policy).is_err());
+        assert!(untrusted.verify(&group, &policy).is_err());
+        Ok(())
+    }
+
+    fn get_group() -> PublicKeySet {
+        SecretKeySet::random(0, &mut rand::thread_rng()).public_keys()
+    }
+
+    fn get_attestation(
+        attester: &SecretKey,
+        group: &PublicKeySet,
+        measurement: Digest,
+    ) -> Result<KeyShareAttestation> {
+        let statement = KeyShareStatement {
+            group: PublicKey::Bls(group.public_key()),
+            key_index: 0,
+            key_share: group.public_key_share(0),
+            measurement,
+            evidence: vec![],
+        };
+        let signature = Signature::Bls(attester.sign(&statement.to_bytes()?));
+        Ok(KeyShareAttestation {
+            statement,
+            attester: PublicKey::Bls(attester.public_key()),
+            signature,
+        })
+    }
+}
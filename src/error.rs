@@ -0,0 +1,489 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    hashing::Digest,
+    money::{format_money_trimmed, saturating_sub},
+    wire::DecodeError,
+    CreditHeld, DoubleSpendAttempted, ValidationShareRejected,
+};
+use safe_nd::{Error, Money, PublicKey, Result, SignatureShare, TransferId};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use threshold_crypto::PublicKeySet;
+
+/// The result of transfer operations that can be rejected with a reason.
+pub type TransferResult<T> = std::result::Result<T, TransferError>;
+
+/// The outcome of a cmd: the event to apply, nothing if the cmd
+/// changes nothing, such as a retransmitted duplicate, or the error.
+/// A rejection, signed with its reason, is told apart from other errors
+/// by TransferError::rejection, and each error by TransferError::kind.
+pub type Outcome<T> = TransferResult<Option<T>>;
+
+/// Constructors of the kinds of outcome.
+pub trait TernaryResult<T> {
+    /// The cmd succeeded, with the event to apply.
+    fn success(item: T) -> Self;
+    /// The cmd changes nothing.
+    fn no_change() -> Self;
+    /// The cmd was rejected, for a reason the sender can correct.
+    fn rejected(rejection: TransferRejected) -> Self;
+    /// The cmd failed, for any other reason.
+    fn failed(error: TransferError) -> Self;
+}
+
+impl<T> TernaryResult<T> for Outcome<T> {
+    fn success(item: T) -> Self {
+        Ok(Some(item))
+    }
+
+    fn no_change() -> Self {
+        Ok(None)
+    }
+
+    fn rejected(rejection: TransferRejected) -> Self {
+        Err(TransferError::Rejected(Box::new(rejection)))
+    }
+
+    fn failed(error: TransferError) -> Self {
+        Err(error)
+    }
+}
+
+/// What the caller of a cmd is to make of its failure (see TransferError::kind).
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum FailureKind {
+    /// The sender can correct the request, as told by the reason, which is to be shown to them.
+    Rejected,
+    /// The request as it is may succeed later, such as once the Replica has caught up.
+    Retry,
+    /// The request breaks the protocol, such as with a bad signature, and the sender is at fault.
+    Violation,
+    /// Any other error, such as of signing or storage at the Replica.
+    Other,
+}
+
+/// Errors of transfer operations.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum TransferError {
+    /// The transfer was rejected by a Replica, for the signed reason.
+    Rejected(Box<TransferRejected>),
+    /// The sender and the recipient are the same account.
+    /// Transfers to self are not supported, as they would only
+    /// consume a debit counter without changing any balance.
+    SameSenderAndRecipient,
+    /// The amount of the transfer is zero. Such transfers are not supported,
+    /// for the same reason as transfers to self.
+    ZeroValueTransfer,
+    /// A debit of the Actor has been initiated but not yet registered,
+    /// and debits are completed one at a time.
+    PendingDebit,
+    /// The debit, or its validation, is not the next debit of the Actor:
+    /// it was already proposed, or is out of order.
+    OutOfOrderDebit {
+        /// The counter of the next debit.
+        expected: u64,
+        /// The counter of the debit.
+        got: u64,
+    },
+    /// The transfer, or its validation, is not of a debit of the Actor.
+    NotOurTransfer,
+    /// The registered debit does not follow the last debit registered for the account.
+    NonSequentialRegistration,
+    /// The recipient is of a kind of key that can not own an account,
+    /// such as a share of a group key, so credits to it could never be spent.
+    UnsupportedRecipientKey(PublicKey),
+    /// The transfer would be rejected by the Replicas, for the reason,
+    /// as checked by the Actor against the cached rules of the section.
+    Unsatisfied(RejectionReason),
+    /// The account has been moved to another group, which is to be used instead.
+    WalletMoved {
+        /// The key of the group now holding the account.
+        new_group: PublicKey,
+    },
+    /// The account is quarantined, and the owner must sign
+    /// the challenge for the transfer to be validated.
+    ConfirmationRequired {
+        /// The challenge to sign.
+        challenge: Digest,
+    },
+    /// The account has signed two different debits with the same counter, and its
+    /// debits are not validated until that is resolved. When first detected, the
+    /// evidence is to be applied as ReplicaEvent::DoubleSpendAttempted.
+    DoubleSpend(Box<DoubleSpendAttempted>),
+    /// The credit is signed by a group we do not know of, and is held until we do
+    /// (see Replica::with_held_credits). The proof is to be applied as ReplicaEvent::CreditHeld.
+    CreditHeld(Box<CreditHeld>),
+    /// The sender has requested too many validations of late (see Replica::with_rate_limit).
+    /// Unlike rejections, it is not signed, so that refusing a request takes no signing.
+    RateLimited {
+        /// The seconds until a validation is allowed again.
+        retry_after: u64,
+    },
+    /// The validation of our debit carries a share that does not verify. The evidence
+    /// is to be applied as ActorEvent::ValidationShareRejected.
+    InvalidShare(Box<ValidationShareRejected>),
+    /// The cmd received could not be decoded within the size limits
+    /// of the Replica (see Replica::handle_cmd_bytes).
+    Malformed(DecodeError),
+    /// Any other error.
+    Network(Error),
+}
+
+impl TransferError {
+    /// The reason of a rejection, signed by a Replica, or found by the
+    /// Actor checking the rules of the section. None for other errors.
+    pub fn rejection(&self) -> Option<&RejectionReason> {
+        match self {
+            TransferError::Rejected(rejected) => Some(&rejected.reason),
+            TransferError::Unsatisfied(reason) => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// Whether to correct the request, retry it, penalise the sender, or none of those.
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            TransferError::Rejected(_)
+            | TransferError::Unsatisfied(_)
+            | TransferError::SameSenderAndRecipient
+            | TransferError::ZeroValueTransfer
+            | TransferError::OutOfOrderDebit { .. }
+            | TransferError::UnsupportedRecipientKey(_)
+            | TransferError::WalletMoved { .. }
+            | TransferError::ConfirmationRequired { .. } => FailureKind::Rejected,
+            TransferError::PendingDebit
+            | TransferError::NonSequentialRegistration
+            | TransferError::CreditHeld(_)
+            | TransferError::RateLimited { .. } => FailureKind::Retry,
+            TransferError::DoubleSpend(_)
+            | TransferError::NotOurTransfer
+            | TransferError::Malformed(_)
+            | TransferError::InvalidShare(_)
+            | TransferError::Network(Error::InvalidSignature) => FailureKind::Violation,
+            TransferError::Network(_) => FailureKind::Other,
+        }
+    }
+}
+
+impl From<Error> for TransferError {
+    fn from(error: Error) -> Self {
+        TransferError::Network(error)
+    }
+}
+
+impl From<TransferError> for Error {
+    fn from(error: TransferError) -> Self {
+        match error {
+            TransferError::Rejected(rejected) => rejected.reason.into(),
+            TransferError::SameSenderAndRecipient => {
+                Error::from("Sender and recipient are the same")
+            }
+            TransferError::ZeroValueTransfer => Error::InvalidOperation,
+            TransferError::PendingDebit => {
+                Error::from("Current pending debit has not been completed")
+            }
+            TransferError::OutOfOrderDebit { .. } => {
+                Error::from("Debit already proposed or out of order")
+            }
+            TransferError::NotOurTransfer => Error::from("Transfer is not from this actor"),
+            TransferError::NonSequentialRegistration => Error::from("Non-sequential operation"),
+            TransferError::UnsupportedRecipientKey(_) => Error::InvalidOperation,
+            TransferError::Unsatisfied(reason) => reason.into(),
+            TransferError::WalletMoved { .. } => Error::NoSuchSender,
+            TransferError::ConfirmationRequired { .. } => Error::AccessDenied,
+            TransferError::DoubleSpend(_) => Error::AccessDenied,
+            TransferError::CreditHeld(_) => Error::InvalidSignature,
+            TransferError::RateLimited { .. } => Error::from("Too many validations requested"),
+            TransferError::InvalidShare(_) => Error::InvalidSignature,
+            TransferError::Malformed(error) => error.into(),
+            TransferError::Network(error) => error,
+        }
+    }
+}
+
+impl Display for TransferError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TransferError::Rejected(rejected) => write!(f, "{}", rejected.reason),
+            TransferError::SameSenderAndRecipient => {
+                write!(f, "Sender and recipient are the same")
+            }
+            TransferError::ZeroValueTransfer => write!(f, "Amount of the transfer is zero"),
+            TransferError::PendingDebit => {
+                write!(f, "Current pending debit has not been completed")
+            }
+            TransferError::OutOfOrderDebit { expected, got } => write!(
+                f,
+                "Out of order debit: expected counter {}, got {}",
+                expected, got
+            ),
+            TransferError::NotOurTransfer => write!(f, "Transfer is not from this actor"),
+            TransferError::NonSequentialRegistration => {
+                write!(f, "Debit does not follow the last registered one")
+            }
+            TransferError::UnsupportedRecipientKey(key) => {
+                write!(f, "Recipient key {:?} can not own an account", key)
+            }
+            TransferError::Unsatisfied(reason) => write!(f, "{}", reason),
+            TransferError::WalletMoved { new_group } => {
+                write!(f, "Account has moved to the group {:?}", new_group)
+            }
+            TransferError::ConfirmationRequired { .. } => write!(
+                f,
+                "Account is quarantined: the owner must sign the challenge of the transfer"
+            ),
+            TransferError::DoubleSpend(evidence) => write!(
+                f,
+                "Account {:?} signed two debits with counter {}",
+                evidence.wallet(),
+                evidence.first.id().counter
+            ),
+            TransferError::CreditHeld(held) => write!(
+                f,
+                "Credit {:?} is signed by an unknown group, and is held until it is known",
+                held.debit_proof.id()
+            ),
+            TransferError::RateLimited { retry_after } => write!(
+                f,
+                "Too many validations requested: retry in {} seconds",
+                retry_after
+            ),
+            TransferError::InvalidShare(rejected) => write!(
+                f,
+                "Replica {} sent a signature share that does not verify",
+                rejected.validation.replica_signature.index
+            ),
+            TransferError::Malformed(error) => write!(f, "{}", error),
+            TransferError::Network(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for TransferError {}
+
+/// Why a Replica rejected a transfer, with what is
+/// needed to correct it, or to resynch with the Replica.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum RejectionReason {
+    /// The balance does not cover the amount.
+    InsufficientBalance {
+        /// The balance of the sender, at the Replica.
+        balance: Money,
+        /// The amount of the transfer.
+        requested: Money,
+    },
+    /// The transfer is not the next debit of the sender.
+    OutOfOrder {
+        /// The counter the Replica expects of the next debit.
+        expected_counter: u64,
+        /// The counter of the transfer.
+        received_counter: u64,
+    },
+    /// The amount is less than the dust threshold of the section.
+    BelowDustThreshold {
+        /// The least amount of a transfer.
+        threshold: Money,
+        /// The amount of the transfer.
+        requested: Money,
+    },
+    /// The balance does not cover the amount, along with
+    /// the fee and the reserve required by the section.
+    BelowRequiredBalance {
+        /// The balance of the sender, at the Replica.
+        balance: Money,
+        /// The balance needed for the transfer.
+        required: Money,
+    },
+    /// The transfer breaks a validation policy of the section (see ValidationPolicy).
+    Policy {
+        /// What rule was broken.
+        reason: String,
+    },
+    /// The transfer exceeds a spending limit set by the owner of the wallet.
+    SpendingLimit {
+        /// The limit exceeded.
+        limit: Money,
+        /// The amount counted against the limit, the transfer included.
+        requested: Money,
+    },
+    /// The sender and the recipient are the same wallet.
+    SameSenderAndRecipient,
+    /// The amount of the transfer is zero.
+    ZeroValue,
+    /// The owner condition, multisig policy or second factor of the wallet
+    /// is not satisfied by the signatures given along with the transfer.
+    MissingWitnesses,
+    /// The validated debit was left pending until it expired, and the
+    /// wallet is to debit again from its counter (see PendingDebitExpired).
+    DebitExpired {
+        /// The counter of the expired debit.
+        counter: u64,
+    },
+}
+
+impl RejectionReason {
+    /// How much more the sender would need, if the balance is insufficient.
+    pub fn missing(&self) -> Option<Money> {
+        match self {
+            RejectionReason::InsufficientBalance { balance, requested } => {
+                Some(saturating_sub(*requested, *balance))
+            }
+            RejectionReason::BelowRequiredBalance { balance, required } => {
+                Some(saturating_sub(*required, *balance))
+            }
+            RejectionReason::OutOfOrder { .. }
+            | RejectionReason::BelowDustThreshold { .. }
+            | RejectionReason::Policy { .. }
+            | RejectionReason::SpendingLimit { .. }
+            | RejectionReason::SameSenderAndRecipient
+            | RejectionReason::ZeroValue
+            | RejectionReason::MissingWitnesses
+            | RejectionReason::DebitExpired { .. } => None,
+        }
+    }
+}
+
+impl From<RejectionReason> for Error {
+    fn from(reason: RejectionReason) -> Self {
+        match reason {
+            RejectionReason::InsufficientBalance { .. }
+            | RejectionReason::BelowRequiredBalance { .. } => Error::InsufficientBalance,
+            RejectionReason::BelowDustThreshold { .. }
+            | RejectionReason::Policy { .. }
+            | RejectionReason::ZeroValue => Error::InvalidOperation,
+            RejectionReason::OutOfOrder { .. } => {
+                Error::from("either already proposed or out of order msg")
+            }
+            RejectionReason::SpendingLimit { .. } | RejectionReason::MissingWitnesses => {
+                Error::AccessDenied
+            }
+            RejectionReason::SameSenderAndRecipient => {
+                Error::from("Sender and recipient are the same")
+            }
+            RejectionReason::DebitExpired { .. } => {
+                Error::from("Debit expired before it was registered")
+            }
+        }
+    }
+}
+
+impl Display for RejectionReason {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            RejectionReason::InsufficientBalance { balance, .. } => write!(
+                f,
+                "Insufficient balance: you need {} more (balance: {})",
+                format_money_trimmed(self.missing().unwrap_or_else(Money::zero)),
+                format_money_trimmed(*balance)
+            ),
+            RejectionReason::OutOfOrder {
+                expected_counter,
+                received_counter,
+            } => write!(
+                f,
+                "Out of order debit: expected counter {}, got {}",
+                expected_counter, received_counter
+            ),
+            RejectionReason::BelowDustThreshold { threshold, .. } => write!(
+                f,
+                "Amount is below the dust threshold of {}",
+                format_money_trimmed(*threshold)
+            ),
+            RejectionReason::BelowRequiredBalance { balance, .. } => write!(
+                f,
+                "Balance does not cover the fee and reserve: you need {} more (balance: {})",
+                format_money_trimmed(self.missing().unwrap_or_else(Money::zero)),
+                format_money_trimmed(*balance)
+            ),
+            RejectionReason::Policy { reason } => {
+                write!(f, "Rejected by a policy of the section: {}", reason)
+            }
+            RejectionReason::SpendingLimit { limit, requested } => write!(
+                f,
+                "Spending limit of {} exceeded: {} requested",
+                format_money_trimmed(*limit),
+                format_money_trimmed(*requested)
+            ),
+            RejectionReason::SameSenderAndRecipient => {
+                write!(f, "Sender and recipient are the same")
+            }
+            RejectionReason::ZeroValue => write!(f, "Amount of the transfer is zero"),
+            RejectionReason::MissingWitnesses => write!(
+                f,
+                "The signatures required by the conditions of the wallet are missing"
+            ),
+            RejectionReason::DebitExpired { counter } => write!(
+                f,
+                "Debit {} expired before it was registered: debit again from its counter",
+                counter
+            ),
+        }
+    }
+}
+
+/// A rejection of a transfer, signed by the rejecting Replica.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferRejected {
+    /// The rejected transfer.
+    pub transfer_id: TransferId,
+    /// Why it was rejected.
+    pub reason: RejectionReason,
+    /// The signature of the Replica over the id and the reason.
+    pub replica_signature: SignatureShare,
+    /// The PK set of the Replica's group.
+    pub replicas: PublicKeySet,
+}
+
+impl TransferRejected {
+    /// Verifies that the rejection was signed by a Replica of the given group.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        if &self.replicas != replicas {
+            return Err(Error::from("Rejection is not from the expected Replicas"));
+        }
+        match bincode::serialize(&(&self.transfer_id, &self.reason)) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise rejection".into())),
+            Ok(data) => {
+                let key = replicas.public_key_share(self.replica_signature.index);
+                if key.verify(&self.replica_signature.share, data) {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidSignature)
+                }
+            }
+        }
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn reports_missing_amount() {
+        let reason = RejectionReason::InsufficientBalance {
+            balance: Money::from_nano(800_000_000),
+            requested: Money::from_nano(1_000_000_000),
+        };
+        assert!(reason.missing() == Some(Money::from_nano(200_000_000)));
+        assert!(reason.to_string() == "Insufficient balance: you need 0.2 more (balance: 0.8)");
+    }
+
+    #[test]
+    fn tells_rejections_from_violations() {
+        let unsatisfied = TransferError::Unsatisfied(RejectionReason::ZeroValue);
+        let forged = TransferError::from(Error::InvalidSignature);
+        let limited = TransferError::RateLimited { retry_after: 10 };
+        assert!(unsatisfied.rejection() == Some(&RejectionReason::ZeroValue));
+        assert!(unsatisfied.kind() == FailureKind::Rejected);
+        assert!(forged.rejection().is_none());
+        assert!(forged.kind() == FailureKind::Violation);
+        assert!(limited.kind() == FailureKind::Retry);
+        assert!(TransferError::from(Error::NoSuchSender).kind() == FailureKind::Other);
+    }
+}
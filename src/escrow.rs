@@ -0,0 +1,166 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::signer::TransferSigner;
+use safe_nd::{Error, Result, Signature, SignedTransfer, Transfer, TransferId};
+use serde::{Deserialize, Serialize};
+
+/// The longest a debit is held in escrow, in seconds.
+pub const MAX_ESCROW_TIMEOUT: u64 = 30 * 24 * 60 * 60;
+
+/// The request of the sender to hold a debit in escrow until the recipient accepts it,
+/// instead of having it validated right away. Signed by the sender, along with how long
+/// the debit is held before it is released back (see Replica::open_escrow).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct EscrowRequest {
+    /// The debit, signed by the sender.
+    pub signed_transfer: SignedTransfer,
+    /// How long the debit is held, in seconds.
+    pub timeout: u64,
+    /// The signature of the sender over the id of the transfer and the timeout.
+    pub sender_sig: Signature,
+}
+
+impl EscrowRequest {
+    /// The request of the signer to hold its debit for the timeout.
+    pub fn new<S: TransferSigner + ?Sized>(
+        signer: &S,
+        signed_transfer: SignedTransfer,
+        timeout: u64,
+    ) -> Result<Self> {
+        let payload = request_payload(&signed_transfer.transfer.id, timeout)?;
+        Ok(Self {
+            sender_sig: signer.sign(&payload)?,
+            signed_transfer,
+            timeout,
+        })
+    }
+
+    /// Verifies the timeout, and that the request was signed by the sender.
+    pub fn verify(&self) -> Result<()> {
+        if self.timeout == 0 || self.timeout > MAX_ESCROW_TIMEOUT {
+            return Err(Error::from("Escrow timeout is out of bounds"));
+        }
+        let id = self.signed_transfer.transfer.id;
+        id.actor
+            .verify(&self.sender_sig, &request_payload(&id, self.timeout)?)
+    }
+}
+
+/// How the party to an escrow decides it.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum EscrowDecision {
+    /// The recipient accepts the debit.
+    Accept,
+    /// The sender cancels the debit.
+    Cancel,
+}
+
+/// The decision over a debit held in escrow, signed by the recipient
+/// when accepting it, and by the sender when cancelling it.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct EscrowResolution {
+    /// The debit held.
+    pub transfer_id: TransferId,
+    /// The decision.
+    pub decision: EscrowDecision,
+    /// The signature of the party over the above.
+    pub signature: Signature,
+}
+
+impl EscrowResolution {
+    /// The decision of the signer over the debit.
+    pub fn new<S: TransferSigner + ?Sized>(
+        signer: &S,
+        transfer_id: TransferId,
+        decision: EscrowDecision,
+    ) -> Result<Self> {
+        let payload = resolution_payload(&transfer_id, decision)?;
+        Ok(Self {
+            transfer_id,
+            decision,
+            signature: signer.sign(&payload)?,
+        })
+    }
+
+    /// Verifies that the decision is over the transfer, and was signed by the party
+    /// allowed to take it: the recipient to accept, the sender to cancel.
+    pub fn verify(&self, transfer: &Transfer) -> Result<()> {
+        if self.transfer_id != transfer.id {
+            return Err(Error::from("Decision is over another transfer"));
+        }
+        let party = match self.decision {
+            EscrowDecision::Accept => transfer.to,
+            EscrowDecision::Cancel => transfer.id.actor,
+        };
+        let payload = resolution_payload(&self.transfer_id, self.decision)?;
+        party.verify(&self.signature, &payload)
+    }
+}
+
+/// Why a debit held in escrow was released back to the sender.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum ReleaseReason {
+    /// The sender cancelled it.
+    Cancelled(EscrowResolution),
+    /// The recipient did not accept it in time.
+    Expired,
+}
+
+/// The bytes of a request signed by the sender.
+fn request_payload(transfer_id: &TransferId, timeout: u64) -> Result<Vec<u8>> {
+    match bincode::serialize(&(b"escrow_request", transfer_id, timeout)) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise escrow".into())),
+        Ok(data) => Ok(data),
+    }
+}
+
+/// The bytes of a decision signed by a party.
+fn resolution_payload(transfer_id: &TransferId, decision: EscrowDecision) -> Result<Vec<u8>> {
+    match bincode::serialize(&(b"escrow_resolution", transfer_id, decision)) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise escrow".into())),
+        Ok(data) => Ok(data),
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::{ClientFullId, Money, SafeKey};
+
+    #[test]
+    fn only_the_parties_decide() -> Result<()> {
+        // Arrange
+        let sender = get_safe_key();
+        let recipient = get_safe_key();
+        let transfer = get_transfer(&sender, &recipient);
+
+        // Act
+        let accepted = EscrowResolution::new(&recipient, transfer.id, EscrowDecision::Accept)?;
+        let cancelled = EscrowResolution::new(&sender, transfer.id, EscrowDecision::Cancel)?;
+        let forged = EscrowResolution::new(&sender, transfer.id, EscrowDecision::Accept)?;
+
+        // Assert
+        assert!(accepted.verify(&transfer).is_ok());
+        assert!(cancelled.verify(&transfer).is_ok());
+        assert!(forged.verify(&transfer).is_err());
+        Ok(())
+    }
+
+    fn get_transfer(sender: &SafeKey, recipient: &SafeKey) -> Transfer {
+        Transfer {
+            id: Dot::new(sender.public_key(), 0),
+            to: recipient.public_key(),
+            amount: Money::from_nano(10),
+        }
+    }
+
+    fn get_safe_key() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    clock::{Clock, TimeSource, Timestamp},
+    hashing::{hash, Digest},
+    money::{checked_sum, NANOS_PER_TOKEN},
+    simulation::PayoutMint,
+};
+use safe_nd::{AccountId, DebitAgreementProof, Error, Money, PublicKey, Result, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use threshold_crypto::SecretKey;
+
+/// The limits of a faucet, per recipient key.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct FaucetLimits {
+    /// The most paid out per claim.
+    pub max_claim: Money,
+    /// The most paid out to a recipient within a window.
+    pub max_per_window: Money,
+    /// The length of the window, in seconds.
+    pub window: u64,
+    /// How long a challenge can be claimed for, in seconds.
+    pub challenge_ttl: u64,
+}
+
+impl Default for FaucetLimits {
+    fn default() -> Self {
+        Self {
+            max_claim: Money::from_nano(10 * NANOS_PER_TOKEN),
+            max_per_window: Money::from_nano(100 * NANOS_PER_TOKEN),
+            window: 24 * 60 * 60,
+            challenge_ttl: 5 * 60,
+        }
+    }
+}
+
+/// The answer of a faucet to a request of test money, signed by the faucet,
+/// for the recipient to sign in turn, proving that it holds the key it asks for.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct FaucetChallenge {
+    /// The key requesting the money, and credited with it.
+    pub recipient: AccountId,
+    /// The amount requested.
+    pub amount: Money,
+    /// The number of the challenge, claimed at most once.
+    pub nonce: u64,
+    /// When the challenge can no longer be claimed.
+    pub expires: Timestamp,
+    /// The signature of the faucet over the above.
+    pub faucet_sig: Signature,
+}
+
+impl FaucetChallenge {
+    /// The digest the recipient signs to claim the money (see Actor::sign_challenge).
+    pub fn digest(&self) -> Result<Digest> {
+        Ok(hash(&[b"faucet_challenge", &self.payload()?]))
+    }
+
+    fn payload(&self) -> Result<Vec<u8>> {
+        challenge_payload(&self.recipient, self.amount, self.nonce, self.expires)
+    }
+}
+
+/// The bytes of a challenge signed by the faucet.
+fn challenge_payload(
+    recipient: &AccountId,
+    amount: Money,
+    nonce: u64,
+    expires: Timestamp,
+) -> Result<Vec<u8>> {
+    match bincode::serialize(&(recipient, amount, nonce, expires)) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise challenge".into())),
+        Ok(data) => Ok(data),
+    }
+}
+
+/// A challenge, signed by the recipient.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct FaucetClaim {
+    /// The challenge of the faucet.
+    pub challenge: FaucetChallenge,
+    /// The signature of the recipient over the digest of the challenge.
+    pub recipient_sig: Signature,
+}
+
+/// Raised when a faucet has paid out test money, attributing the credit
+/// to the key that claimed it. The proof is to be propagated to the
+/// Replicas of the recipient, as any other credit.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct FaucetPaid {
+    /// The key credited.
+    pub recipient: AccountId,
+    /// The amount paid out.
+    pub amount: Money,
+    /// The number of the challenge claimed.
+    pub nonce: u64,
+    /// When the claim was paid out.
+    pub at: Timestamp,
+    /// The proof of the credit, signed by the group of the mint.
+    pub debit_proof: DebitAgreementProof,
+}
+
+/// A faucet of test money, paying out of a mint of simulated payouts (see PayoutMint).
+/// A recipient requests an amount, and is returned a challenge, which it signs with
+/// its key to claim the credit. The credit carries the full proof of the group of the
+/// mint, so it is verified by Replicas knowing that group as any other. Payouts are
+/// limited per recipient key, and every payout is recorded with the key it went to,
+/// so that community testnets can share the one flow instead of each running their own.
+#[derive(Debug)]
+pub struct Faucet {
+    mint: PayoutMint,
+    key: SecretKey,
+    limits: FaucetLimits,
+    clock: Clock,
+    next_nonce: u64,
+    claimed: HashSet<u64>,
+    payouts: HashMap<AccountId, Vec<FaucetPaid>>,
+}
+
+impl Faucet {
+    /// A faucet paying out of the mint, within the limits.
+    /// Without the system-clock feature, the clock to read time from is given (see Clock).
+    pub fn new(
+        mint: PayoutMint,
+        limits: FaucetLimits,
+        #[cfg(not(feature = "system-clock"))] clock: Clock,
+    ) -> Self {
+        #[cfg(feature = "system-clock")]
+        let clock = Clock::default();
+        Self {
+            mint,
+            key: SecretKey::random(),
+            limits,
+            clock,
+            next_nonce: 0,
+            claimed: Default::default(),
+            payouts: Default::default(),
+        }
+    }
+
+    /// Sets the source of time used by this faucet.
+    pub fn with_time_source<T: TimeSource + 'static>(mut self, source: T) -> Self {
+        self.clock = Clock::new(source);
+        self
+    }
+
+    /// The key that the faucet signs its challenges with.
+    pub fn key(&self) -> PublicKey {
+        PublicKey::Bls(self.key.public_key())
+    }
+
+    /// The payouts to the recipient, oldest first.
+    pub fn payouts(&self, recipient: &AccountId) -> &[FaucetPaid] {
+        self.payouts
+            .get(recipient)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The most the recipient can still claim within the current window.
+    pub fn claimable(&self, recipient: &AccountId) -> Result<Money> {
+        let paid = self.paid_in_window(recipient)?;
+        let remaining = self
+            .limits
+            .max_per_window
+            .as_nano()
+            .saturating_sub(paid.as_nano());
+        Ok(Money::from_nano(
+            remaining.min(self.limits.max_claim.as_nano()),
+        ))
+    }
+
+    /// A challenge for the recipient to sign, if the amount is within its limits.
+    pub fn challenge(&mut self, recipient: AccountId, amount: Money) -> Result<FaucetChallenge> {
+        self.check_limits(&recipient, amount)?;
+        let nonce = self.next_nonce;
+        let expires = self.clock.now() + self.limits.challenge_ttl;
+        let payload = challenge_payload(&recipient, amount, nonce, expires)?;
+        self.next_nonce += 1;
+        Ok(FaucetChallenge {
+            recipient,
+            amount,
+            nonce,
+            expires,
+            faucet_sig: Signature::Bls(self.key.sign(payload)),
+        })
+    }
+
+    /// Pays out the claimed challenge, if issued by us, unexpired, not claimed before,
+    /// signed by the recipient, and still within its limits. The payout is to be applied.
+    pub fn claim(&mut self, claim: &FaucetClaim) -> Result<FaucetPaid> {
+        let challenge = &claim.challenge;
+        self.key()
+            .verify(&challenge.faucet_sig, &challenge.payload()?)?;
+        if challenge.expires <= self.clock.now() {
+            return Err(Error::from("Challenge has expired"));
+        }
+        if self.claimed.contains(&challenge.nonce) {
+            return Err(Error::from("Challenge has been claimed"));
+        }
+        challenge
+            .recipient
+            .verify(&claim.recipient_sig, &challenge.digest()?)?;
+        self.check_limits(&challenge.recipient, challenge.amount)?;
+        let debit_proof = self.mint.credit(challenge.recipient, challenge.amount)?;
+        Ok(FaucetPaid {
+            recipient: challenge.recipient,
+            amount: challenge.amount,
+            nonce: challenge.nonce,
+            at: self.clock.now(),
+            debit_proof,
+        })
+    }
+
+    /// Records the payout, counting it towards the limits of the recipient.
+    pub fn apply(&mut self, paid: FaucetPaid) {
+        let _ = self.claimed.insert(paid.nonce);
+        self.payouts.entry(paid.recipient).or_default().push(paid);
+    }
+
+    fn check_limits(&self, recipient: &AccountId, amount: Money) -> Result<()> {
+        if amount == Money::zero() {
+            return Err(Error::from("Nothing to pay out"));
+        }
+        if amount > self.claimable(recipient)? {
+            return Err(Error::from("Faucet limit reached"));
+        }
+        Ok(())
+    }
+
+    fn paid_in_window(&self, recipient: &AccountId) -> Result<Money> {
+        let since = self.clock.now().saturating_sub(self.limits.window);
+        let paid = self
+            .payouts(recipient)
+            .iter()
+            .filter(|paid| paid.at > since)
+            .map(|paid| paid.amount);
+        checked_sum(paid).ok_or_else(|| Error::from("Payouts overflow"))
+    }
+}
+
+// the tests build instances with the default clock, that of the system
+#[cfg(feature = "system-clock")]
+mod test {
+    use super::*;
+    use crate::clock::ManualTimeSource;
+    use safe_nd::{ClientFullId, SafeKey};
+    use std::sync::Arc;
+    use threshold_crypto::SecretKeySet;
+
+    #[test]
+    fn pays_out_within_limits() -> Result<()> {
+        // Arrange
+        let mint = get_mint();
+        let mut replica = mint.replica(0);
+        let time = get_time_source();
+        let limits = FaucetLimits {
+            max_claim: Money::from_nano(10),
+            max_per_window: Money::from_nano(15),
+            window: 100,
+            challenge_ttl: 10,
+        };
+        let mut faucet = Faucet::new(mint, limits).with_time_source(Arc::clone(&time));
+        let key = get_safe_key();
+        let recipient = key.public_key();
+
+        // Act
+        let challenge = faucet.challenge(recipient, Money::from_nano(10))?;
+        let claim = FaucetClaim {
+            recipient_sig: key.sign(&challenge.digest()?),
+            challenge,
+        };
+        let paid = faucet.claim(&claim)?;
+        faucet.apply(paid.clone());
+        let propagated = replica.receive_propagated(&paid.debit_proof)?;
+        replica.apply(crate::ReplicaEvent::TransferPropagated(propagated));
+
+        // Assert
+        assert!(replica.balance(&recipient) == Some(Money::from_nano(10)));
+        assert!(faucet.payouts(&recipient) == &[paid][..]);
+        assert!(faucet.claim(&claim).is_err());
+        assert!(faucet.challenge(recipient, Money::from_nano(10)).is_err());
+        assert!(faucet.claimable(&recipient)? == Money::from_nano(5));
+        time.advance(100);
+        assert!(faucet.claimable(&recipient)? == Money::from_nano(10));
+        Ok(())
+    }
+
+    fn get_mint() -> PayoutMint {
+        PayoutMint::new(SecretKeySet::random(0, &mut rand::thread_rng()))
+    }
+
+    fn get_time_source() -> Arc<ManualTimeSource> {
+        Arc::new(ManualTimeSource::new(1_000, 0))
+    }
+
+    fn get_safe_key() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+}
@@ -0,0 +1,375 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    hashing::Digest,
+    store::{
+        link, link_at, serialise, verify_links, ReplicaEventStore, SequencedEvent, GENESIS_LINK,
+    },
+    wire::SizeLimits,
+    ReplicaEvent,
+};
+use safe_nd::{Error, Result};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+const EVENTS_FILE: &str = "events";
+const LINK_SIZE: usize = 32;
+const SNAPSHOT_FILE: &str = "snapshot";
+/// The header of both files, so that files of another kind, or of another
+/// version of the format, are refused instead of being read as events.
+const MAGIC: &[u8; 4] = b"SNTE";
+/// The version of the format of the files written by this version of the crate.
+pub const EVENT_STORE_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 6;
+
+/// A store keeping the events in a file of a directory, each as its serialised
+/// length, its link and its serialisation, and the latest snapshot, with the link
+/// of the last event before it, in another file. Both files start with a header
+/// of a magic number and the version of the format (see EVENT_STORE_VERSION).
+/// Events are synced to disk before append returns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileEventStore {
+    dir: PathBuf,
+    limits: SizeLimits,
+    next_index: u64,
+    tail_hash: Digest,
+}
+
+impl FileEventStore {
+    /// Opens the store in the directory, creating it if needed.
+    /// Fails if the files are not of the format of this version.
+    /// A record torn by a crash while it was appended is truncated, as the
+    /// append never returned, and the events before it are kept.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(io_error)?;
+        let mut store = Self {
+            dir,
+            limits: Default::default(),
+            next_index: 0,
+            tail_hash: GENESIS_LINK,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(store.dir.join(EVENTS_FILE))
+            .map_err(io_error)?;
+        if file.metadata().map_err(io_error)?.len() == 0 {
+            file.write_all(&header()).map_err(io_error)?;
+            file.sync_data().map_err(io_error)?;
+        }
+        let _ = store.read_snapshot()?;
+        let (records, length) = store.read_records()?;
+        if file.metadata().map_err(io_error)?.len() > length {
+            file.set_len(length).map_err(io_error)?;
+            file.sync_data().map_err(io_error)?;
+        }
+        store.next_index = records.len() as u64;
+        store.tail_hash = records.last().map_or(GENESIS_LINK, |(link, _)| *link);
+        Ok(store)
+    }
+
+    /// Sets the limits that the events read back are decoded within, as those
+    /// received over the wire are, since the files may have been tampered with.
+    pub fn with_size_limits(mut self, limits: SizeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The latest snapshot, with the index of the first event after it,
+    /// and the link of the last event before it.
+    fn read_snapshot(&self) -> Result<Option<(u64, Digest, Vec<u8>)>> {
+        let bytes = match fs::read(self.dir.join(SNAPSHOT_FILE)) {
+            Ok(bytes) => bytes,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(e)),
+        };
+        let bytes = check_header(&bytes)?;
+        if bytes.len() < 8 + LINK_SIZE {
+            return Err(Error::FailedToParse("Snapshot has no index".into()));
+        }
+        let (index, rest) = bytes.split_at(8);
+        let (link_bytes, snapshot) = rest.split_at(LINK_SIZE);
+        let mut index_bytes = [0; 8];
+        index_bytes.copy_from_slice(index);
+        let mut link = [0; LINK_SIZE];
+        link.copy_from_slice(link_bytes);
+        Ok(Some((
+            u64::from_le_bytes(index_bytes),
+            link,
+            snapshot.to_vec(),
+        )))
+    }
+
+    /// The records of the events, with their links, and the length of the file up to
+    /// the end of the last complete record. A record torn at the end is left out.
+    fn read_records(&self) -> Result<(Vec<(Digest, Vec<u8>)>, u64)> {
+        let mut bytes = vec![];
+        match File::open(self.dir.join(EVENTS_FILE)) {
+            Ok(mut file) => {
+                let _ = file.read_to_end(&mut bytes).map_err(io_error)?;
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((vec![], 0)),
+            Err(e) => return Err(io_error(e)),
+        }
+        let mut records = vec![];
+        let mut rest = check_header(&bytes)?;
+        while rest.len() >= 4 {
+            let (length, tail) = rest.split_at(4);
+            let length = u32::from_le_bytes([length[0], length[1], length[2], length[3]]);
+            if tail.len() < LINK_SIZE + length as usize {
+                break;
+            }
+            let (link_bytes, tail) = tail.split_at(LINK_SIZE);
+            let mut link = [0; LINK_SIZE];
+            link.copy_from_slice(link_bytes);
+            let (record, tail) = tail.split_at(length as usize);
+            records.push((link, record.to_vec()));
+            rest = tail;
+        }
+        Ok((records, (bytes.len() - rest.len()) as u64))
+    }
+}
+
+impl ReplicaEventStore for FileEventStore {
+    fn append(&mut self, event: &ReplicaEvent) -> Result<u64> {
+        let record = serialise(event)?;
+        let link = link(&self.tail_hash, &record);
+        let mut bytes = (record.len() as u32).to_le_bytes().to_vec();
+        bytes.extend(&link);
+        bytes.extend(record);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(EVENTS_FILE))
+            .map_err(io_error)?;
+        file.write_all(&bytes).map_err(io_error)?;
+        file.sync_data().map_err(io_error)?;
+        self.tail_hash = link;
+        self.next_index += 1;
+        Ok(self.next_index - 1)
+    }
+
+    fn read_from(&self, index: u64) -> Result<Vec<ReplicaEvent>> {
+        self.read_records()?
+            .0
+            .iter()
+            .skip(index as usize)
+            .map(|(_, record)| self.limits.event(record))
+            .collect()
+    }
+
+    fn read_sequenced_from(&self, index: u64) -> Result<Vec<SequencedEvent>> {
+        self.read_records()?
+            .0
+            .iter()
+            .enumerate()
+            .skip(index as usize)
+            .map(|(sequence, (hash, record))| {
+                Ok(SequencedEvent {
+                    sequence: sequence as u64,
+                    hash: *hash,
+                    event: self.limits.event(record)?,
+                })
+            })
+            .collect()
+    }
+
+    fn snapshot(&mut self, index: u64, snapshot: &[u8]) -> Result<()> {
+        let (records, _) = self.read_records()?;
+        let links: Vec<_> = records.into_iter().map(|(l, _)| l).collect();
+        let mut bytes = header();
+        bytes.extend(&index.to_le_bytes());
+        bytes.extend(&link_at(&links, index)?);
+        bytes.extend(snapshot);
+        // written aside first, so that a crash never leaves a partial snapshot
+        let partial = self.dir.join(format!("{}.partial", SNAPSHOT_FILE));
+        let mut file = File::create(&partial).map_err(io_error)?;
+        file.write_all(&bytes).map_err(io_error)?;
+        file.sync_data().map_err(io_error)?;
+        fs::rename(partial, self.dir.join(SNAPSHOT_FILE)).map_err(io_error)
+    }
+
+    fn latest_snapshot(&self) -> Result<Option<(u64, Vec<u8>)>> {
+        Ok(self
+            .read_snapshot()?
+            .map(|(index, _, snapshot)| (index, snapshot)))
+    }
+
+    fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    fn tail_hash(&self) -> Digest {
+        self.tail_hash
+    }
+
+    fn verify_chain(&self) -> Result<()> {
+        let (records, _) = self.read_records()?;
+        let snapshot = self.read_snapshot()?.map(|(index, link, _)| (index, link));
+        verify_links(
+            records
+                .iter()
+                .map(|(link, record)| (link, record.as_slice())),
+            snapshot,
+        )
+    }
+}
+
+fn header() -> Vec<u8> {
+    let mut header = MAGIC.to_vec();
+    header.extend(&EVENT_STORE_VERSION.to_le_bytes());
+    header
+}
+
+/// The bytes after the header, if it is of the format of this version.
+fn check_header(bytes: &[u8]) -> Result<&[u8]> {
+    if bytes.len() < HEADER_SIZE || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(Error::FailedToParse("Not a file of an event store".into()));
+    }
+    let version = u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+    if version != EVENT_STORE_VERSION {
+        return Err(Error::FailedToParse(format!(
+            "Unsupported event store version {}",
+            version
+        )));
+    }
+    Ok(&bytes[HEADER_SIZE..])
+}
+
+fn io_error(error: std::io::Error) -> Error {
+    Error::NetworkOther(format!("Event store: {}", error))
+}
+
+mod test {
+    use super::*;
+    use safe_nd::KnownGroupAdded;
+    use threshold_crypto::SecretKeySet;
+
+    #[test]
+    fn file_store_reads_back_what_was_appended() -> Result<()> {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("events-{}", rand::random::<u64>()));
+        let mut store = FileEventStore::open(&dir)?;
+        let first = get_event();
+        let second = get_event();
+
+        // Act
+        let _ = store.append(&first)?;
+        store.snapshot(1, &[1, 2, 3])?;
+        let _ = store.append(&second)?;
+        let reopened = FileEventStore::open(&dir)?;
+
+        // Assert
+        assert!(reopened.next_index() == 2);
+        assert!(reopened.read_from(0)? == vec![first, second.clone()]);
+        assert!(reopened.read_from(1)? == vec![second]);
+        assert!(reopened.latest_snapshot()? == Some((1, vec![1, 2, 3])));
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[test]
+    fn detects_altered_and_missing_events() -> Result<()> {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("events-{}", rand::random::<u64>()));
+        let mut store = FileEventStore::open(&dir)?;
+        let first = get_event();
+        let second = get_event();
+        let _ = store.append(&first)?;
+        let _ = store.append(&second)?;
+        store.snapshot(2, &[1, 2, 3])?;
+        let first_link = crate::store::link_event(&GENESIS_LINK, &first)?;
+        let tail = crate::store::link_event(&first_link, &second)?;
+        let events_file = dir.join(EVENTS_FILE);
+        let bytes = fs::read(&events_file).map_err(io_error)?;
+        let first_length = HEADER_SIZE + 4 + LINK_SIZE + bincode::serialize(&first).unwrap().len();
+
+        // Act
+        let intact = FileEventStore::open(&dir)?.verify_chain();
+        let mut altered = bytes.clone();
+        let last = altered.len() - 1;
+        altered[last] ^= 1;
+        fs::write(&events_file, &altered).map_err(io_error)?;
+        let after_alteration = FileEventStore::open(&dir)?.verify_chain();
+        fs::write(&events_file, &bytes[..first_length]).map_err(io_error)?;
+        let after_truncation = FileEventStore::open(&dir)?.verify_chain();
+
+        // Assert
+        assert!(intact.is_ok());
+        assert!(store.tail_hash() == tail);
+        assert!(after_alteration.is_err());
+        assert!(after_truncation.is_err());
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[test]
+    fn truncates_a_torn_tail() -> Result<()> {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("events-{}", rand::random::<u64>()));
+        let mut store = FileEventStore::open(&dir)?;
+        let first = get_event();
+        let second = get_event();
+        let _ = store.append(&first)?;
+        let events_file = dir.join(EVENTS_FILE);
+        let intact = fs::read(&events_file).map_err(io_error)?;
+        let _ = store.append(&second)?;
+        let bytes = fs::read(&events_file).map_err(io_error)?;
+        fs::write(&events_file, &bytes[..intact.len() + 10]).map_err(io_error)?;
+
+        // Act
+        let mut reopened = FileEventStore::open(&dir)?;
+        let truncated = fs::read(&events_file).map_err(io_error)?;
+        let appended = reopened.append(&second)?;
+
+        // Assert
+        assert!(truncated == intact);
+        assert!(appended == 1);
+        assert!(reopened.read_from(0)? == vec![first, second]);
+        assert!(reopened.verify_chain().is_ok());
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_files_of_other_formats() -> Result<()> {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("events-{}", rand::random::<u64>()));
+        let mut store = FileEventStore::open(&dir)?;
+        let _ = store.append(&get_event())?;
+        let events_file = dir.join(EVENTS_FILE);
+        let bytes = fs::read(&events_file).map_err(io_error)?;
+
+        // Act
+        let mut newer = bytes.clone();
+        newer[MAGIC.len()..HEADER_SIZE].copy_from_slice(&(EVENT_STORE_VERSION + 1).to_le_bytes());
+        fs::write(&events_file, &newer).map_err(io_error)?;
+        let of_newer_version = FileEventStore::open(&dir);
+        fs::write(&events_file, &bytes[HEADER_SIZE..]).map_err(io_error)?;
+        let without_header = FileEventStore::open(&dir);
+        fs::write(&events_file, &bytes).map_err(io_error)?;
+        let intact = FileEventStore::open(&dir);
+
+        // Assert
+        assert!(of_newer_version.is_err());
+        assert!(without_header.is_err());
+        assert!(intact.is_ok());
+        let _ = fs::remove_dir_all(dir);
+        Ok(())
+    }
+
+    fn get_event() -> ReplicaEvent {
+        let group = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+        ReplicaEvent::KnownGroupAdded(KnownGroupAdded { group })
+    }
+}
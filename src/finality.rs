@@ -0,0 +1,55 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::{Deserialize, Serialize};
+
+/// How far a transfer has come, from least to most final,
+/// for wallets to present pending and complete transfers alike.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum Finality {
+    /// A quorum of the Replicas of the sender validated the debit, and their
+    /// agreement has been combined into a proof, which is yet to be registered.
+    Validated,
+    /// The debit is registered. It can no longer be undone,
+    /// but the credit has not yet reached the recipient.
+    Registered,
+    /// The credit has been propagated to the Replicas of the recipient,
+    /// with the agreement of a quorum of the Replicas of the sender.
+    PropagatedQuorum,
+}
+
+impl Finality {
+    /// Whether the recipient can spend the amount.
+    pub fn is_spendable(self) -> bool {
+        self == Finality::PropagatedQuorum
+    }
+
+    /// Whether the transfer is still pending, i.e. could yet fail to be registered.
+    pub fn is_pending(self) -> bool {
+        self == Finality::Validated
+    }
+}
+
+/// Where a transfer is in its lifecycle at a Replica, by its id alone
+/// (see Replica::transfer_status). A transfer credited to a wallet we hold
+/// is Propagated, even if we also hold the wallet it was debited from.
+/// Only the transfers in the histories we hold are known: a transfer compacted
+/// away from both of its wallets, or whose wallets were exported to another
+/// group, is Unknown, though it was once registered or propagated here.
+/// Evicted wallets are still held, and their transfers still known.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum TransferLifecycle {
+    /// We have not validated, registered nor credited it.
+    Unknown,
+    /// We have validated the debit, which is yet to be registered.
+    Pending,
+    /// The debit is registered in the history of the sender.
+    Registered,
+    /// The credit is in the history of the recipient.
+    Propagated,
+}
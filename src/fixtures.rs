@@ -0,0 +1,210 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Fixtures of keys, wallets and proofs for the benchmarks of the hot paths of a Replica.
+//! Keys are drawn from a StdRng seeded by the caller, so that runs are compared alike.
+//! Proofs are signed with the secret key of the group, which gives the signature that
+//! the shares of a quorum combine to, at the cost of a single signing.
+
+#[cfg(not(feature = "system-clock"))]
+use super::clock::{Clock, ManualTimeSource};
+use super::{
+    account::Account,
+    quorum::QuorumRule,
+    replica::Replica,
+    signable::{sign_transfer, Signable, SignableProof},
+    ReplicaEvent,
+};
+use crdts::Dot;
+use rand::{rngs::StdRng, SeedableRng};
+use safe_nd::{
+    AccountId, ClientFullId, DebitAgreementProof, Money, SafeKey, Signature, SignedTransfer,
+    TransferRegistered,
+};
+use std::collections::HashSet;
+use threshold_crypto::SecretKeySet;
+
+/// A group of Replicas holding wallets, and a payer of another group crediting them.
+#[derive(Clone, Debug)]
+pub struct BenchNetwork {
+    /// The keys of the group holding the wallets.
+    pub replicas: SecretKeySet,
+    /// The keys of the group of the payer.
+    pub senders: SecretKeySet,
+    /// The key of the payer.
+    pub payer: SafeKey,
+    /// The keys of the wallets.
+    pub wallets: Vec<SafeKey>,
+}
+
+impl BenchNetwork {
+    /// A group of `replicas` Replicas, with the threshold of a supermajority, holding
+    /// `wallets` wallets, with all keys drawn from the seed.
+    pub fn new(replicas: usize, wallets: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let threshold = QuorumRule::Supermajority.threshold(replicas);
+        let replicas = SecretKeySet::random(threshold, &mut rng);
+        let senders = SecretKeySet::random(threshold, &mut rng);
+        let payer = SafeKey::client(ClientFullId::new_ed25519(&mut rng));
+        let wallets = (0..wallets)
+            .map(|_| SafeKey::client(ClientFullId::new_ed25519(&mut rng)))
+            .collect();
+        Self {
+            replicas,
+            senders,
+            payer,
+            wallets,
+        }
+    }
+
+    /// The id of the wallet at the index.
+    pub fn wallet(&self, index: usize) -> AccountId {
+        self.wallets[index].public_key()
+    }
+
+    /// The Replica of the group at the index, holding the wallets with
+    /// no history, and knowing the group of the payer. Without the system
+    /// clock, its time stands still, which the benchmarks do not read.
+    pub fn replica(&self, index: usize) -> Replica {
+        let mut other_groups = HashSet::new();
+        let _ = other_groups.insert(self.senders.public_keys());
+        let accounts = self
+            .wallets
+            .iter()
+            .map(|key| (key.public_key(), Account::new(key.public_key())))
+            .collect();
+        Replica::from_snapshot(
+            self.replicas.secret_key_share(index),
+            index,
+            self.replicas.public_keys(),
+            other_groups,
+            accounts,
+            Default::default(),
+            #[cfg(not(feature = "system-clock"))]
+            Clock::new(ManualTimeSource::default()),
+        )
+    }
+
+    /// The debit of the wallet at the counter, to the payer, signed by the wallet.
+    pub fn debit(&self, wallet: usize, counter: u64, amount: Money) -> SignedTransfer {
+        sign_transfer(
+            &self.wallets[wallet],
+            counter,
+            self.payer.public_key(),
+            amount,
+        )
+    }
+
+    /// The proof of agreement of the group over the debit of a wallet.
+    pub fn agree(&self, signed_transfer: SignedTransfer) -> DebitAgreementProof {
+        agree(&self.replicas, signed_transfer)
+    }
+
+    /// The credit to the wallet from the payer at the counter, agreed by the group of the payer.
+    pub fn credit(&self, wallet: usize, counter: u64, amount: Money) -> DebitAgreementProof {
+        let signed_transfer = sign_transfer(&self.payer, counter, self.wallet(wallet), amount);
+        agree(&self.senders, signed_transfer)
+    }
+
+    /// `count` credits from the payer to the wallets in turn, as propagated to the Replica
+    /// at index 0, built as they are iterated. Only the first is signed: the others carry
+    /// its signatures, which are not verified when events are applied.
+    pub fn propagated_credits(&self, count: usize) -> impl Iterator<Item = ReplicaEvent> + '_ {
+        let first = self.credit(0, 0, Money::from_nano(1));
+        let propagated = self
+            .replica(0)
+            .receive_propagated(&first)
+            .unwrap_or_else(|error| panic!("Could not propagate a fixture: {:?}", error));
+        let payer = self.payer.public_key();
+        (0..count).map(move |counter| {
+            let mut e = propagated.clone();
+            let transfer = &mut e.debit_proof.signed_transfer.transfer;
+            transfer.id = Dot::new(payer, counter as u64);
+            transfer.to = self.wallet(counter % self.wallets.len());
+            ReplicaEvent::TransferPropagated(e)
+        })
+    }
+
+    /// `count` events of a history of the Replica at index 0: credits from the payer to
+    /// the wallets in turn, each followed by the registration of a debit of the wallet
+    /// credited, built as they are iterated. As with propagated_credits, only the first
+    /// debit is signed.
+    pub fn replayed_history(&self, count: usize) -> impl Iterator<Item = ReplicaEvent> + '_ {
+        let mut credits = self.propagated_credits((count + 1) / 2);
+        let first = self.agree(self.debit(0, 0, Money::from_nano(1)));
+        let mut counters = vec![0; self.wallets.len()];
+        (0..count).filter_map(move |index| {
+            if index % 2 == 0 {
+                return credits.next();
+            }
+            let wallet = index / 2 % self.wallets.len();
+            let mut debit_proof = first.clone();
+            debit_proof.signed_transfer.transfer.id =
+                Dot::new(self.wallet(wallet), counters[wallet]);
+            counters[wallet] += 1;
+            Some(ReplicaEvent::TransferRegistered(TransferRegistered {
+                debit_proof,
+            }))
+        })
+    }
+}
+
+fn agree(keys: &SecretKeySet, signed_transfer: SignedTransfer) -> DebitAgreementProof {
+    let data = SignableProof::new(&signed_transfer).to_bytes();
+    DebitAgreementProof {
+        signed_transfer,
+        debiting_replicas_sig: Signature::Bls(keys.secret_key().sign(data)),
+    }
+}
+
+// the tests build instances with the default clock, that of the system
+#[cfg(feature = "system-clock")]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_valid_proofs_and_credits() {
+        // Arrange
+        let network = BenchNetwork::new(4, 3, 0);
+        let mut replica = network.replica(0);
+
+        // Act
+        let credit = network.credit(1, 0, Money::from_nano(10));
+        let debit = network.agree(network.debit(1, 0, Money::from_nano(10)));
+        for event in network.propagated_credits(7) {
+            replica.apply(event);
+        }
+        let replayed = Replica::from_history(
+            network.replicas.secret_key_share(0),
+            0,
+            network.replicas.public_keys(),
+            network.replayed_history(7).collect(),
+        );
+
+        // Assert
+        let codecs = [crate::Codec::Canonical];
+        assert!(crate::verify_debit_agreement_proof(
+            &credit,
+            &network.senders.public_keys(),
+            &codecs
+        )
+        .is_ok());
+        assert!(crate::verify_debit_agreement_proof(
+            &debit,
+            &network.replicas.public_keys(),
+            &codecs
+        )
+        .is_ok());
+        assert!(replica.balance(&network.wallet(0)) == Some(Money::from_nano(3)));
+        assert!(replica.balance(&network.wallet(2)) == Some(Money::from_nano(2)));
+        assert!(BenchNetwork::new(4, 3, 0).wallet(2) == network.wallet(2));
+        // each credit of the history is debited again, but the last
+        assert!(replayed.balance(&network.wallet(0)) == Some(Money::from_nano(1)));
+        assert!(replayed.balance(&network.wallet(1)) == Some(Money::zero()));
+    }
+}
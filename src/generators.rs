@@ -0,0 +1,302 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[cfg(not(feature = "system-clock"))]
+use super::clock::{Clock, ManualTimeSource};
+use super::{
+    account::Account,
+    quorum::{combine, QuorumRule},
+    replica::Replica,
+    signable::{sign_transfer, Signable, SignableProof},
+    ReplicaEvent, TransferError,
+};
+use crdts::{quickcheck::Arbitrary, quickcheck::Gen, Dot};
+use rand::{rngs::StdRng, SeedableRng};
+use safe_nd::{
+    ClientFullId, DebitAgreementProof, Money, Result, SafeKey, SignatureShare, SignedTransfer,
+    Transfer,
+};
+use std::collections::HashSet;
+use threshold_crypto::{PublicKeySet, SecretKeySet, SecretKeyShare};
+
+// Generators of valid transfers, proofs, key shares and event sequences, for property tests.
+// The types wrap those of safe_nd, as Arbitrary can not be implemented for them here.
+// Keys are drawn from a StdRng seeded by the Gen, as the signing crates use another
+// version of rand than quickcheck.
+
+/// The most Replicas in a generated group.
+pub const MAX_ARBITRARY_REPLICAS: usize = 7;
+/// The most events in a generated sequence.
+pub const MAX_ARBITRARY_EVENTS: usize = 16;
+/// The largest amount of a generated transfer, in nanos.
+pub const MAX_ARBITRARY_AMOUNT: u64 = 1_000_000_000;
+
+/// The keys of a group of Replicas, with the threshold of a supermajority of the group.
+#[derive(Clone, Debug)]
+pub struct ArbitraryReplicaKeys {
+    /// The secret key set of the group.
+    pub keys: SecretKeySet,
+    /// The number of Replicas in the group.
+    pub replicas: usize,
+}
+
+impl ArbitraryReplicaKeys {
+    /// The PK set of the group.
+    pub fn public_keys(&self) -> PublicKeySet {
+        self.keys.public_keys()
+    }
+
+    /// The key share of the Replica at the index.
+    pub fn share(&self, index: usize) -> SecretKeyShare {
+        self.keys.secret_key_share(index)
+    }
+
+    /// The proof of agreement of the group over the debit, combined
+    /// from the signature shares of a quorum of its Replicas.
+    pub fn agree(&self, signed_transfer: SignedTransfer) -> Result<DebitAgreementProof> {
+        let data = SignableProof::new(&signed_transfer).to_bytes();
+        let shares: Vec<_> = (0..=self.keys.threshold())
+            .map(|index| SignatureShare {
+                index,
+                share: self.share(index).sign(&data),
+            })
+            .collect();
+        let debiting_replicas_sig = combine(&self.public_keys(), &shares)?;
+        Ok(DebitAgreementProof {
+            signed_transfer,
+            debiting_replicas_sig,
+        })
+    }
+
+    /// A Replica of the group, holding the accounts, and knowing the groups.
+    /// Without the system clock, its time stands still, until set with with_time_source.
+    pub fn replica(
+        &self,
+        index: usize,
+        accounts: Vec<Account>,
+        other_groups: HashSet<PublicKeySet>,
+    ) -> Replica {
+        Replica::from_snapshot(
+            self.share(index),
+            index,
+            self.public_keys(),
+            other_groups,
+            accounts.into_iter().map(|a| (a.id(), a)).collect(),
+            Default::default(),
+            #[cfg(not(feature = "system-clock"))]
+            Clock::new(ManualTimeSource::default()),
+        )
+    }
+}
+
+impl Arbitrary for ArbitraryReplicaKeys {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let replicas = 1 + usize::arbitrary(g) % MAX_ARBITRARY_REPLICAS;
+        let threshold = QuorumRule::Supermajority.threshold(replicas);
+        Self {
+            keys: SecretKeySet::random(threshold, &mut rng(g)),
+            replicas,
+        }
+    }
+}
+
+/// A transfer between random wallets, at a random counter.
+#[derive(Clone, Debug)]
+pub struct ArbitraryTransfer(pub Transfer);
+
+impl Arbitrary for ArbitraryTransfer {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let from = safe_key(g).public_key();
+        Self(Transfer {
+            id: Dot::new(from, u64::arbitrary(g)),
+            to: safe_key(g).public_key(),
+            amount: amount(g),
+        })
+    }
+}
+
+/// A transfer signed by the key of its sender, which is kept for signing more.
+#[derive(Clone, Debug)]
+pub struct ArbitrarySignedTransfer {
+    /// The signed transfer.
+    pub signed_transfer: SignedTransfer,
+    /// The key of the sender.
+    pub key: SafeKey,
+}
+
+impl Arbitrary for ArbitrarySignedTransfer {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let key = safe_key(g);
+        let to = safe_key(g).public_key();
+        let signed_transfer = sign_transfer(&key, u64::arbitrary(g), to, amount(g));
+        Self {
+            signed_transfer,
+            key,
+        }
+    }
+}
+
+/// A signed transfer agreed by a group of Replicas.
+#[derive(Clone, Debug)]
+pub struct ArbitraryDebitProof {
+    /// The proof.
+    pub debit_proof: DebitAgreementProof,
+    /// The keys of the group that agreed.
+    pub replicas: ArbitraryReplicaKeys,
+}
+
+impl Arbitrary for ArbitraryDebitProof {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let replicas = ArbitraryReplicaKeys::arbitrary(g);
+        let signed_transfer = ArbitrarySignedTransfer::arbitrary(g).signed_transfer;
+        Self {
+            debit_proof: generated(replicas.agree(signed_transfer)),
+            replicas,
+        }
+    }
+}
+
+/// The events raised by a Replica holding a wallet, in the order raised: credits
+/// propagated from another group, and debits of the wallet validated and registered,
+/// never more than its balance. Applied in order to a new Replica of the group
+/// holding the wallet, they rebuild the state of the Replica that raised them.
+#[derive(Clone, Debug)]
+pub struct ArbitraryEvents {
+    /// The keys of the group holding the wallet.
+    pub replicas: ArbitraryReplicaKeys,
+    /// The keys of the group the credits are from.
+    pub senders: ArbitraryReplicaKeys,
+    /// The key of the wallet.
+    pub wallet: SafeKey,
+    /// The events.
+    pub events: Vec<ReplicaEvent>,
+}
+
+impl ArbitraryEvents {
+    /// A Replica of the group at the index, holding the wallet with no history.
+    pub fn replica(&self, index: usize) -> Replica {
+        let mut other_groups = HashSet::new();
+        let _ = other_groups.insert(self.senders.public_keys());
+        let account = Account::new(self.wallet.public_key());
+        self.replicas.replica(index, vec![account], other_groups)
+    }
+}
+
+impl Arbitrary for ArbitraryEvents {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let mut generated_events = Self {
+            replicas: ArbitraryReplicaKeys::arbitrary(g),
+            senders: ArbitraryReplicaKeys::arbitrary(g),
+            wallet: safe_key(g),
+            events: vec![],
+        };
+        let mut replica = generated_events.replica(0);
+        let mut events = vec![];
+        let id = generated_events.wallet.public_key();
+        let (mut credits, mut debits) = (0, 0);
+        for _ in 0..usize::arbitrary(g) % (MAX_ARBITRARY_EVENTS / 2 + 1) {
+            let balance = replica.balance(&id).unwrap_or_else(Money::zero).as_nano();
+            if balance == 0 || u64::arbitrary(g) % 2 == 0 {
+                let credit = sign_transfer(&safe_key(g), credits, id, amount(g));
+                credits += 1;
+                let debit_proof = generated(generated_events.senders.agree(credit));
+                let propagated = generated(replica.receive_propagated(&debit_proof));
+                raise(
+                    &mut replica,
+                    &mut events,
+                    ReplicaEvent::TransferPropagated(propagated),
+                );
+            } else {
+                let amount = Money::from_nano(1 + u64::arbitrary(g) % balance);
+                let debit = sign_transfer(
+                    &generated_events.wallet,
+                    debits,
+                    safe_key(g).public_key(),
+                    amount,
+                );
+                debits += 1;
+                let validated = generated(replica.validate(debit.clone()));
+                raise(
+                    &mut replica,
+                    &mut events,
+                    ReplicaEvent::TransferValidated(validated),
+                );
+                let debit_proof = generated(generated_events.replicas.agree(debit));
+                if let Some(registered) = generated(replica.register(&debit_proof)) {
+                    raise(
+                        &mut replica,
+                        &mut events,
+                        ReplicaEvent::TransferRegistered(registered),
+                    );
+                }
+            }
+        }
+        generated_events.events = events;
+        generated_events
+    }
+}
+
+/// Applies the event at the Replica that raised it, and adds it to the sequence.
+fn raise(replica: &mut Replica, events: &mut Vec<ReplicaEvent>, event: ReplicaEvent) {
+    replica.apply(event.clone());
+    events.push(event);
+}
+
+/// A seeded rng of the version of rand used by the signing crates.
+fn rng<G: Gen>(g: &mut G) -> StdRng {
+    StdRng::seed_from_u64(u64::arbitrary(g))
+}
+
+fn safe_key<G: Gen>(g: &mut G) -> SafeKey {
+    SafeKey::client(ClientFullId::new_ed25519(&mut rng(g)))
+}
+
+fn amount<G: Gen>(g: &mut G) -> Money {
+    Money::from_nano(1 + u64::arbitrary(g) % MAX_ARBITRARY_AMOUNT)
+}
+
+/// Generators only build what is valid, so failing to is a bug of theirs.
+fn generated<T, E: Into<TransferError>>(result: std::result::Result<T, E>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => panic!("Generated an invalid value: {:?}", error.into()),
+    }
+}
+
+mod test {
+    use super::*;
+    use crate::{codec::Codec, verification::verify_debit_agreement_proof};
+
+    #[test]
+    fn generates_valid_proofs_and_histories() {
+        crdts::quickcheck::quickcheck(proofs_verify as fn(ArbitraryDebitProof) -> bool);
+        crdts::quickcheck::quickcheck(histories_replay as fn(ArbitraryEvents) -> bool);
+    }
+
+    fn proofs_verify(proof: ArbitraryDebitProof) -> bool {
+        let replicas = proof.replicas.public_keys();
+        verify_debit_agreement_proof(&proof.debit_proof, &replicas, &[Codec::Canonical]).is_ok()
+    }
+
+    fn histories_replay(generated: ArbitraryEvents) -> bool {
+        let mut replica = generated.replica(0);
+        for event in generated.events.clone() {
+            replica.apply(event);
+        }
+        let wallet = generated.wallet.public_key();
+        let expected = generated
+            .events
+            .iter()
+            .fold(0, |balance, event| match event {
+                ReplicaEvent::TransferPropagated(e) => balance + e.debit_proof.amount().as_nano(),
+                ReplicaEvent::TransferRegistered(e) => balance - e.debit_proof.amount().as_nano(),
+                _ => balance,
+            });
+        replica.balance(&wallet) == Some(Money::from_nano(expected))
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    codec::Codec,
+    money::checked_sum,
+    quorum::combine,
+    signable::{Signable, SignableProof, SignableTransfer},
+    verification::verify_debit_agreement_proof,
+};
+use crdts::Dot;
+use safe_nd::{
+    AccountId, DebitAgreementProof, Error, Money, PublicKey, Result, Signature, SignatureShare,
+    SignedTransfer, Transfer,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use threshold_crypto::{PublicKeySet, SecretKey, SecretKeyShare};
+
+/// The genesis transfers funding the first wallets of a network, such as a test
+/// network with several pre-funded wallets, credited at once by Replica::genesis.
+/// They are sent by the same key, from counter 0 on, to distinct wallets, and add
+/// up to the declared supply. A single genesis proof is a set of one.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct GenesisSet {
+    /// The total of the funding.
+    pub supply: Money,
+    /// The proofs of the genesis transfers, in the order of their counters.
+    pub proofs: Vec<DebitAgreementProof>,
+}
+
+impl GenesisSet {
+    /// The amount funded to each wallet.
+    pub fn funding(&self) -> BTreeMap<AccountId, Money> {
+        self.proofs
+            .iter()
+            .map(|proof| (proof.to(), proof.amount()))
+            .collect()
+    }
+
+    /// Verifies that the transfers were agreed by the Replicas with the key set,
+    /// follow each other from the same sender, and add up to the supply.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        let sender = match self.proofs.first() {
+            Some(proof) => proof.from(),
+            None => return Err(Error::from("Genesis funds no wallet")),
+        };
+        let mut recipients = HashSet::new();
+        for (counter, proof) in self.proofs.iter().enumerate() {
+            if proof.id() != Dot::new(sender, counter as u64) {
+                return Err(Error::from("Genesis transfers do not follow each other"));
+            }
+            if !recipients.insert(proof.to()) {
+                return Err(Error::from("Genesis funds a wallet twice"));
+            }
+            verify_debit_agreement_proof(proof, replicas, &[Codec::Canonical])?;
+        }
+        if checked_sum(self.proofs.iter().map(|proof| proof.amount())) != Some(self.supply) {
+            return Err(Error::from("Genesis does not add up to the supply"));
+        }
+        Ok(())
+    }
+}
+
+impl From<DebitAgreementProof> for GenesisSet {
+    fn from(proof: DebitAgreementProof) -> Self {
+        Self {
+            supply: proof.amount(),
+            proofs: vec![proof],
+        }
+    }
+}
+
+/// The proof of the genesis transfer of the amount to the recipient, agreed by the
+/// first group of Replicas, with the PK set, from the shares of a quorum of its
+/// Replicas, each with its index. The proof is verified against the PK set, so
+/// that it is credited by Replica::genesis at any Replica of the group.
+pub fn get_genesis(
+    amount: Money,
+    recipient: AccountId,
+    pk_set: &PublicKeySet,
+    secret_shares: &[(usize, SecretKeyShare)],
+) -> Result<DebitAgreementProof> {
+    // Nothing comes before genesis, so it is sent by a key used only for it.
+    let proof = agree(
+        &SecretKey::random(),
+        0,
+        recipient,
+        amount,
+        pk_set,
+        secret_shares,
+    )?;
+    verify_debit_agreement_proof(&proof, pk_set, &[Codec::Canonical])?;
+    Ok(proof)
+}
+
+/// The genesis transfers of the funding, which must add up to the supply, agreed as
+/// in get_genesis. The set is verified, so that it is credited by Replica::genesis.
+pub fn get_genesis_set(
+    supply: Money,
+    funding: &BTreeMap<AccountId, Money>,
+    pk_set: &PublicKeySet,
+    secret_shares: &[(usize, SecretKeyShare)],
+) -> Result<GenesisSet> {
+    if checked_sum(funding.values().copied()) != Some(supply) {
+        return Err(Error::from("Funding does not add up to the supply"));
+    }
+    let sender = SecretKey::random();
+    let proofs = funding
+        .iter()
+        .enumerate()
+        .map(|(counter, (to, amount))| {
+            agree(&sender, counter as u64, *to, *amount, pk_set, secret_shares)
+        })
+        .collect::<Result<_>>()?;
+    let set = GenesisSet { supply, proofs };
+    set.verify(pk_set)?;
+    Ok(set)
+}
+
+/// The transfer from the sender, agreed by the Replicas with the shares.
+fn agree(
+    sender: &SecretKey,
+    counter: u64,
+    to: AccountId,
+    amount: Money,
+    pk_set: &PublicKeySet,
+    secret_shares: &[(usize, SecretKeyShare)],
+) -> Result<DebitAgreementProof> {
+    let transfer = Transfer {
+        id: Dot::new(PublicKey::Bls(sender.public_key()), counter),
+        to,
+        amount,
+    };
+    let actor_signature = Signature::Bls(sender.sign(SignableTransfer::new(&transfer).to_bytes()));
+    let signed_transfer = SignedTransfer {
+        transfer,
+        actor_signature,
+    };
+    let data = SignableProof::new(&signed_transfer).to_bytes();
+    let shares: Vec<_> = secret_shares
+        .iter()
+        .map(|(index, secret_share)| SignatureShare {
+            index: *index,
+            share: secret_share.sign(&data),
+        })
+        .collect();
+    Ok(DebitAgreementProof {
+        signed_transfer,
+        debiting_replicas_sig: combine(pk_set, &shares)?,
+    })
+}
+
+// the tests build instances with the default clock, that of the system
+#[cfg(feature = "system-clock")]
+mod test {
+    use super::*;
+    use crate::{replica::Replica, ReplicaEvent};
+    use threshold_crypto::SecretKeySet;
+
+    #[test]
+    fn genesis_is_credited_once() -> Result<()> {
+        // Arrange
+        let keys = SecretKeySet::random(1, &mut rand::thread_rng());
+        let recipient = PublicKey::Bls(SecretKey::random().public_key());
+        let shares = get_shares(&keys, 2);
+        let mut replica = get_replica(&keys);
+
+        // Act
+        let proof = get_genesis(
+            Money::from_nano(100),
+            recipient,
+            &keys.public_keys(),
+            &shares,
+        )?;
+        credit_genesis(&mut replica, &GenesisSet::from(proof.clone()))?;
+
+        // Assert
+        assert!(replica.balance(&recipient) == Some(Money::from_nano(100)));
+        assert!(replica.genesis(&GenesisSet::from(proof)).is_err());
+        let too_few = get_shares(&keys, 1);
+        assert!(get_genesis(
+            Money::from_nano(100),
+            recipient,
+            &keys.public_keys(),
+            &too_few
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn funds_several_wallets_at_genesis() -> Result<()> {
+        // Arrange
+        let keys = SecretKeySet::random(1, &mut rand::thread_rng());
+        let shares = get_shares(&keys, 2);
+        let mut funding = BTreeMap::new();
+        for amount in &[10, 20, 30] {
+            let wallet = PublicKey::Bls(SecretKey::random().public_key());
+            let _ = funding.insert(wallet, Money::from_nano(*amount));
+        }
+        let mut replica = get_replica(&keys);
+
+        // Act
+        let set = get_genesis_set(Money::from_nano(60), &funding, &keys.public_keys(), &shares)?;
+        credit_genesis(&mut replica, &set)?;
+
+        // Assert
+        assert!(set.funding() == funding);
+        for (wallet, amount) in &funding {
+            assert!(replica.balance(wallet) == Some(*amount));
+        }
+        let wrong_supply =
+            get_genesis_set(Money::from_nano(61), &funding, &keys.public_keys(), &shares);
+        assert!(wrong_supply.is_err());
+        let mut inflated = set;
+        inflated.supply = Money::from_nano(61);
+        assert!(inflated.verify(&keys.public_keys()).is_err());
+        Ok(())
+    }
+
+    fn credit_genesis(replica: &mut Replica, set: &GenesisSet) -> Result<()> {
+        let credits = replica.genesis(set).map_err(Error::from)?;
+        for propagated in credits {
+            replica.apply(ReplicaEvent::TransferPropagated(propagated));
+        }
+        Ok(())
+    }
+
+    fn get_shares(keys: &SecretKeySet, count: usize) -> Vec<(usize, SecretKeyShare)> {
+        (0..count)
+            .map(|index| (index, keys.secret_key_share(index)))
+            .collect()
+    }
+
+    fn get_replica(keys: &SecretKeySet) -> Replica {
+        Replica::from_snapshot(
+            keys.secret_key_share(0),
+            0,
+            keys.public_keys(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use safe_nd::PublicKey;
+use std::{collections::HashMap, iter::FromIterator};
+use threshold_crypto::PublicKeySet;
+
+/// The default number of other groups that a Replica knows of at most.
+pub const DEFAULT_KNOWN_GROUPS_CAPACITY: usize = 1024;
+
+/// How long a credit signed by a group we do not know of is held before it may be
+/// expired, in seconds, unless set otherwise (see Replica::with_held_credit_timeout).
+pub const DEFAULT_HELD_CREDIT_TIMEOUT: u64 = 24 * 60 * 60;
+
+/// The PK sets of other groups known to a Replica, keyed by the key of the group.
+/// At most `capacity` are kept: when a new group is added to a full set, the one
+/// least recently used, i.e. added or signing a credit propagated to us, is evicted.
+/// Recency is counted in uses, not time, so that replaying the same events
+/// evicts the same groups.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KnownGroups {
+    capacity: usize,
+    groups: HashMap<PublicKey, (PublicKeySet, u64)>,
+    uses: u64,
+}
+
+impl KnownGroups {
+    pub(crate) fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        while self.groups.len() > self.capacity {
+            let _ = self.evict();
+        }
+        self
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub(crate) fn contains_key(&self, key: &PublicKey) -> bool {
+        self.groups.contains_key(key)
+    }
+
+    pub(crate) fn contains(&self, group: &PublicKeySet) -> bool {
+        self.contains_key(&PublicKey::Bls(group.public_key()))
+    }
+
+    pub(crate) fn get(&self, key: &PublicKey) -> Option<&PublicKeySet> {
+        self.groups.get(key).map(|(group, _)| group)
+    }
+
+    /// The groups, in no particular order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &PublicKeySet> {
+        self.groups.values().map(|(group, _)| group)
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &PublicKey> {
+        self.groups.keys()
+    }
+
+    /// The groups, the least recently used first.
+    pub(crate) fn by_recency(&self) -> Vec<&PublicKeySet> {
+        let mut groups: Vec<_> = self.groups.values().collect();
+        groups.sort_by_key(|(_, used)| *used);
+        groups.into_iter().map(|(group, _)| group).collect()
+    }
+
+    /// Adds the group, evicting the least recently used one if full.
+    /// Returns the key of the evicted group, if any.
+    pub(crate) fn insert(&mut self, group: PublicKeySet) -> Option<PublicKey> {
+        let key = PublicKey::Bls(group.public_key());
+        let evicted = if !self.groups.contains_key(&key) && self.groups.len() >= self.capacity {
+            self.evict()
+        } else {
+            None
+        };
+        self.uses += 1;
+        let _ = self.groups.insert(key, (group, self.uses));
+        evicted
+    }
+
+    /// Marks the group as used, if we know of it.
+    pub(crate) fn touch(&mut self, key: &PublicKey) {
+        if let Some((_, used)) = self.groups.get_mut(key) {
+            self.uses += 1;
+            *used = self.uses;
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &PublicKey) -> Option<PublicKeySet> {
+        self.groups.remove(key).map(|(group, _)| group)
+    }
+
+    fn evict(&mut self) -> Option<PublicKey> {
+        let key = *self
+            .groups
+            .iter()
+            .min_by_key(|(_, (_, used))| *used)
+            .map(|(key, _)| key)?;
+        let _ = self.groups.remove(&key);
+        Some(key)
+    }
+}
+
+impl Default for KnownGroups {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_KNOWN_GROUPS_CAPACITY,
+            groups: Default::default(),
+            uses: 0,
+        }
+    }
+}
+
+impl FromIterator<PublicKeySet> for KnownGroups {
+    fn from_iter<I: IntoIterator<Item = PublicKeySet>>(iter: I) -> Self {
+        let mut groups = Self::default();
+        for group in iter {
+            let _ = groups.insert(group);
+        }
+        groups
+    }
+}
+
+mod test {
+    use super::*;
+    use threshold_crypto::SecretKeySet;
+
+    #[test]
+    fn evicts_the_least_recently_used_group() {
+        // Arrange
+        let first = get_group();
+        let second = get_group();
+        let third = get_group();
+        let mut groups: KnownGroups = vec![first.clone(), second.clone()].into_iter().collect();
+        groups = groups.with_capacity(2);
+
+        // Act
+        groups.touch(&PublicKey::Bls(first.public_key()));
+        let evicted = groups.insert(third.clone());
+
+        // Assert
+        assert!(evicted == Some(PublicKey::Bls(second.public_key())));
+        assert!(groups.len() == 2);
+        assert!(groups.contains(&first));
+        assert!(!groups.contains(&second));
+        assert!(groups.by_recency() == vec![&first, &third]);
+        assert!(groups.get(&PublicKey::Bls(third.public_key())) == Some(&third));
+    }
+
+    fn get_group() -> PublicKeySet {
+        SecretKeySet::random(0, &mut rand::thread_rng()).public_keys()
+    }
+}
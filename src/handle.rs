@@ -0,0 +1,174 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    error::{Outcome, TransferResult},
+    replica::Replica,
+    shard::ShardLocks,
+    ReplicaEvent,
+};
+use safe_nd::{
+    AccountId, DebitAgreementProof, Error, Money, Result, SignedTransfer, Transfer,
+    TransferPropagated, TransferRegistered, TransferValidated,
+};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A cheaply cloneable, thread-safe handle to a Replica, shared by the tasks serving it.
+/// Queries run concurrently. Cmds are async, and serialised per shard of wallets, each
+/// shard with a lock of its own (see ShardLocks), from the cmd to the application of its
+/// event, so that cmds of a wallet see the events of those before them, while cmds of
+/// wallets in other shards, with their signature checks and signing, run in parallel.
+/// A cmd waiting for its shard yields to the executor instead of blocking its thread.
+/// The events of cmds are persisted to the event store of the Replica, if any, without
+/// holding the Replica, which is only held for its in-memory work, though in the order
+/// they are applied in, so that the store replays to the same state. Snapshots are to be
+/// stored with snapshot_to_store, so that none is taken between the persisting of an
+/// event and its application.
+#[derive(Clone, Debug)]
+pub struct ReplicaHandle {
+    replica: Arc<RwLock<Replica>>,
+    shard_locks: Arc<ShardLocks>,
+    /// Held by cmds while their event is persisted and applied, and by snapshots exclusively.
+    persisting: Arc<RwLock<()>>,
+    /// Held by cmds from the appending of their event to the store to its application,
+    /// so that events are applied in the order they are appended.
+    appending: Arc<Mutex<()>>,
+}
+
+impl ReplicaHandle {
+    /// Takes ownership of the Replica.
+    pub fn new(replica: Replica) -> Self {
+        Self {
+            replica: Arc::new(RwLock::new(replica)),
+            shard_locks: Arc::new(ShardLocks::default()),
+            persisting: Arc::new(RwLock::new(())),
+            appending: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Runs the query on the Replica, concurrently with other queries.
+    pub fn read<T, F: FnOnce(&Replica) -> T>(&self, query: F) -> Result<T> {
+        Ok(query(&*self.read_lock()?))
+    }
+
+    /// Runs the mutation on the Replica, exclusively.
+    pub fn write<T, F: FnOnce(&mut Replica) -> T>(&self, mutation: F) -> Result<T> {
+        Ok(mutation(&mut *self.write_lock()?))
+    }
+
+    /// Query for the balance of the wallet.
+    pub fn balance(&self, wallet: &AccountId) -> Result<Option<Money>> {
+        self.read(|replica| replica.balance(wallet))
+    }
+
+    /// Query for the credits of the wallet since the index.
+    pub fn credits_since(&self, wallet: &AccountId, index: usize) -> Result<Option<Vec<Transfer>>> {
+        self.read(|replica| replica.credits_since(wallet, index))
+    }
+
+    /// Query for the debits of the wallet since the index.
+    pub fn debits_since(&self, wallet: &AccountId, index: usize) -> Result<Option<Vec<Transfer>>> {
+        self.read(|replica| replica.debits_since(wallet, index))
+    }
+
+    /// Validates the debit (see Replica::validate), and applies the validation.
+    pub async fn validate(
+        &self,
+        signed_transfer: SignedTransfer,
+    ) -> TransferResult<TransferValidated> {
+        let _shard = self.shard_locks.lock(&signed_transfer.from()).await;
+        let validated = self.read_lock()?.validate(signed_transfer)?;
+        self.persist_and_apply(ReplicaEvent::TransferValidated(validated.clone()))?;
+        Ok(validated)
+    }
+
+    /// Registers the agreed debit (see Replica::register), and applies the registration.
+    /// Nothing if the debit is already registered.
+    pub async fn register(&self, debit_proof: &DebitAgreementProof) -> Outcome<TransferRegistered> {
+        let _shard = self.shard_locks.lock(&debit_proof.from()).await;
+        let registered = match self.read_lock()?.register(debit_proof)? {
+            Some(registered) => registered,
+            None => return Ok(None),
+        };
+        self.persist_and_apply(ReplicaEvent::TransferRegistered(registered.clone()))?;
+        Ok(Some(registered))
+    }
+
+    /// Credits the propagated debit (see Replica::receive_propagated), and applies the credit.
+    pub async fn receive_propagated(
+        &self,
+        debit_proof: &DebitAgreementProof,
+    ) -> TransferResult<TransferPropagated> {
+        let _shard = self.shard_locks.lock(&debit_proof.to()).await;
+        let propagated = self.read_lock()?.receive_propagated(debit_proof)?;
+        self.persist_and_apply(ReplicaEvent::TransferPropagated(propagated.clone()))?;
+        Ok(propagated)
+    }
+
+    /// Persists and applies the event, such as one of another cmd (see Replica::persist_and_apply),
+    /// serialised with the cmds of the wallet it applies to, or with those of every wallet,
+    /// if it applies to no single one.
+    pub async fn apply(&self, event: ReplicaEvent) -> Result<()> {
+        let _shards = match event.wallet() {
+            Some(wallet) => vec![self.shard_locks.lock(&wallet).await],
+            None => self.shard_locks.lock_all().await,
+        };
+        self.persist_and_apply(event)
+    }
+
+    /// Stores a snapshot of the Replica in its event store (see Replica::snapshot_to_store),
+    /// once the events being persisted are applied.
+    pub fn snapshot_to_store(&self) -> Result<()> {
+        let _persisting = match self.persisting.write() {
+            Ok(guard) => guard,
+            Err(_) => return Err(Error::from("Replica is poisoned")),
+        };
+        self.read_lock()?.snapshot_to_store()
+    }
+
+    /// Same as Replica::persist_and_apply, except that the Replica is not held while the
+    /// event is persisted, as queries can run meanwhile. Events of other shards wait for
+    /// it to be applied before they are appended, so that the store has them in the order
+    /// they are applied in. The wallets of the event are loaded before it is persisted, so
+    /// that a wallet the wallet store fails to load fails the event, and again after, in
+    /// case they were evicted meanwhile.
+    fn persist_and_apply(&self, event: ReplicaEvent) -> Result<()> {
+        let _persisting = match self.persisting.read() {
+            Ok(guard) => guard,
+            Err(_) => return Err(Error::from("Replica is poisoned")),
+        };
+        let _appending = match self.appending.lock() {
+            Ok(guard) => guard,
+            Err(_) => return Err(Error::from("Replica is poisoned")),
+        };
+        let mut replica = self.write_lock()?;
+        replica.load_wallets(&event)?;
+        if let Some(store) = replica.event_store().cloned() {
+            drop(replica);
+            let _ = store.append(&event)?;
+            replica = self.write_lock()?;
+            replica.load_wallets(&event)?;
+        }
+        replica.apply(event);
+        Ok(())
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, Replica>> {
+        match self.replica.read() {
+            Ok(replica) => Ok(replica),
+            Err(_) => Err(Error::from("Replica is poisoned")),
+        }
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, Replica>> {
+        match self.replica.write() {
+            Ok(replica) => Ok(replica),
+            Err(_) => Err(Error::from("Replica is poisoned")),
+        }
+    }
+}
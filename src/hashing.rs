@@ -0,0 +1,271 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::{Deserialize, Serialize};
+use tiny_keccak::{Hasher, Sha3};
+
+/// A SHA3-256 content hash.
+pub type Digest = [u8; 32];
+
+/// Hashes the concatenation of the parts.
+pub(crate) fn hash(parts: &[&[u8]]) -> Digest {
+    let mut hasher = Sha3::v256();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut digest = [0; 32];
+    hasher.finalize(&mut digest);
+    digest
+}
+
+/// Prefix of the hash of a leaf, so that no leaf can pass for a node.
+const LEAF_PREFIX: u8 = 0x00;
+/// Prefix of the hash of a node, over its two children.
+const NODE_PREFIX: u8 = 0x01;
+
+/// The node of a leaf in a Merkle tree.
+fn leaf_node(leaf: &Digest) -> Digest {
+    hash(&[&[LEAF_PREFIX], leaf])
+}
+
+/// The node over two children in a Merkle tree.
+fn parent_node(left: &Digest, right: &Digest) -> Digest {
+    hash(&[&[NODE_PREFIX], left, right])
+}
+
+/// The path from a leaf to the root of a Merkle tree.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct MerkleProof {
+    /// The position of the leaf.
+    pub index: u64,
+    /// The number of leaves of the tree.
+    pub leaf_count: u64,
+    /// The sibling at each level, from the leaf up, skipping the levels where the
+    /// node had no sibling, and was carried up unchanged (as derived from leaf_count).
+    pub siblings: Vec<Digest>,
+}
+
+impl MerkleProof {
+    /// Whether the leaf is part of the tree with the given root.
+    pub fn verify(&self, leaf: Digest, root: &Digest) -> bool {
+        if self.index >= self.leaf_count {
+            return false;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut index = self.index;
+        let mut width = self.leaf_count;
+        let mut node = leaf_node(&leaf);
+        while width > 1 {
+            if index ^ 1 < width {
+                let sibling = match siblings.next() {
+                    Some(sibling) => sibling,
+                    None => return false,
+                };
+                node = if index % 2 == 0 {
+                    parent_node(&node, sibling)
+                } else {
+                    parent_node(sibling, &node)
+                };
+            }
+            index /= 2;
+            width = (width + 1) / 2;
+        }
+        siblings.next().is_none() && &node == root
+    }
+}
+
+/// The root of a binary Merkle tree over the leaves, in the given order.
+/// Leaves and nodes are hashed with prefixes of their own, and a node
+/// without a sibling is carried up unchanged.
+pub(crate) fn merkle_root(leaves: &[Digest]) -> Digest {
+    if leaves.is_empty() {
+        return hash(&[]);
+    }
+    let mut level: Vec<_> = leaves.iter().map(leaf_node).collect();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// The proof of the leaf at the index, None if there is no such leaf.
+pub(crate) fn merkle_proof(leaves: &[Digest], index: usize) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut siblings = vec![];
+    let mut position = index;
+    let mut level: Vec<_> = leaves.iter().map(leaf_node).collect();
+    while level.len() > 1 {
+        siblings.extend(level.get(position ^ 1).copied());
+        position /= 2;
+        level = next_level(&level);
+    }
+    Some(MerkleProof {
+        index: index as u64,
+        leaf_count: leaves.len() as u64,
+        siblings,
+    })
+}
+
+/// A binary Merkle tree that leaves are appended to, in O(log n) each, with the
+/// same root and proofs as merkle_root and merkle_proof over its leaves.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct MerkleTree {
+    /// The leaves, in the order appended.
+    leaves: Vec<Digest>,
+    /// The nodes of each level, from those of the leaves up to the root.
+    levels: Vec<Vec<Digest>>,
+}
+
+impl MerkleTree {
+    /// A tree over the leaves, in the given order.
+    pub(crate) fn new<I: IntoIterator<Item = Digest>>(leaves: I) -> Self {
+        let mut tree = Self::default();
+        for leaf in leaves {
+            tree.append(leaf);
+        }
+        tree
+    }
+
+    /// Appends the leaf, updating the nodes on its path to the root.
+    pub(crate) fn append(&mut self, leaf: Digest) {
+        if self.levels.is_empty() {
+            self.levels.push(vec![]);
+        }
+        self.leaves.push(leaf);
+        self.levels[0].push(leaf_node(&leaf));
+        let mut depth = 0;
+        let mut index = self.levels[0].len() - 1;
+        while self.levels[depth].len() > 1 {
+            let level = &self.levels[depth];
+            let parent = if index % 2 == 1 {
+                parent_node(&level[index - 1], &level[index])
+            } else {
+                level[index]
+            };
+            if self.levels.len() == depth + 1 {
+                self.levels.push(vec![]);
+            }
+            index /= 2;
+            let above = &mut self.levels[depth + 1];
+            if index < above.len() {
+                above[index] = parent;
+            } else {
+                above.push(parent);
+            }
+            depth += 1;
+        }
+    }
+
+    /// The number of leaves.
+    pub(crate) fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// The root, as merkle_root over the leaves.
+    pub(crate) fn root(&self) -> Digest {
+        match self.levels.last() {
+            Some(top) if !top.is_empty() => top[0],
+            _ => hash(&[]),
+        }
+    }
+
+    /// The tree over the first leaves, as it was before the rest were appended.
+    /// None if there are fewer leaves.
+    pub(crate) fn prefix(&self, len: usize) -> Option<Self> {
+        Some(Self::new(self.leaves.get(..len)?.iter().copied()))
+    }
+
+    /// The proof of the leaf at the index, None if there is no such leaf.
+    pub(crate) fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut position = index;
+        let mut siblings = vec![];
+        for level in &self.levels[..self.levels.len() - 1] {
+            siblings.extend(level.get(position ^ 1).copied());
+            position /= 2;
+        }
+        Some(MerkleProof {
+            index: index as u64,
+            leaf_count: self.len() as u64,
+            siblings,
+        })
+    }
+}
+
+fn next_level(level: &[Digest]) -> Vec<Digest> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => parent_node(left, right),
+            _ => pair[0],
+        })
+        .collect()
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn proves_every_leaf() {
+        for count in 1..8u8 {
+            let leaves: Vec<_> = (0..count).map(|i| hash(&[&[i]])).collect();
+            let root = merkle_root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(&leaves, index).unwrap();
+                assert!(proof.verify(*leaf, &root));
+                assert!(!proof.verify(hash(&[b"other"]), &root));
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_nodes_as_leaves_and_forged_counts() {
+        let leaves: Vec<_> = (0..5u8).map(|i| hash(&[&[i]])).collect();
+        let root = merkle_root(&leaves);
+        // the nodes over the first two leaves, and over the first four
+        let pair = parent_node(&leaf_node(&leaves[0]), &leaf_node(&leaves[1]));
+        let quad = merkle_root(&leaves[..4]);
+        let proof = merkle_proof(&leaves, 0).unwrap();
+        let node_proof = MerkleProof {
+            index: 0,
+            leaf_count: 3,
+            siblings: proof.siblings[1..].to_vec(),
+        };
+        let mut recounted = merkle_proof(&leaves, 4).unwrap();
+        recounted.leaf_count = 8;
+
+        assert!(!node_proof.verify(pair, &root));
+        assert!(!MerkleProof {
+            index: 0,
+            leaf_count: 2,
+            siblings: vec![leaf_node(&leaves[4])],
+        }
+        .verify(quad, &root));
+        assert!(!recounted.verify(leaves[4], &root));
+        assert!(merkle_proof(&leaves, 4).unwrap().verify(leaves[4], &root));
+    }
+
+    #[test]
+    fn appends_to_trees() {
+        let leaves: Vec<_> = (0..9u8).map(|i| hash(&[&[i]])).collect();
+        let mut tree = MerkleTree::default();
+        for count in 1..=leaves.len() {
+            tree.append(leaves[count - 1]);
+            assert!(tree.root() == merkle_root(&leaves[..count]));
+            for index in 0..count {
+                assert!(tree.proof(index) == merkle_proof(&leaves[..count], index));
+            }
+        }
+        assert!(MerkleTree::default().root() == merkle_root(&[]));
+        assert!(tree.proof(leaves.len()).is_none());
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{attestation::BalanceProof, hashing::Digest, signer::TransferSigner, ReplicaEvent};
+use safe_nd::{AccountId, Error, Result, Signature};
+use serde::{Deserialize, Serialize};
+
+const IMPORT_TAG: &[u8] = b"safe-transfers/import";
+
+/// A challenge for the holder of an externally generated key to sign,
+/// proving control of the key before its wallet is imported (see Actor::import).
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ImportChallenge {
+    /// The wallet to import, i.e. the public key of the keypair.
+    pub wallet: AccountId,
+    /// A random nonce, so that the signature can not be of anything else.
+    pub nonce: Digest,
+}
+
+impl ImportChallenge {
+    /// A new challenge for importing the wallet.
+    pub fn new(wallet: AccountId) -> Self {
+        Self {
+            wallet,
+            nonce: rand::random(),
+        }
+    }
+
+    /// What is signed: tagged, so that it is never a transfer or a proof.
+    pub fn payload(&self) -> Result<Vec<u8>> {
+        match bincode::serialize(&self.wallet) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise wallet".into())),
+            Ok(wallet) => Ok([IMPORT_TAG, &wallet, &self.nonce].concat()),
+        }
+    }
+
+    /// Signs the challenge with the key, wherever it is held.
+    pub fn prove<S: TransferSigner>(&self, signer: &S) -> Result<ProofOfControl> {
+        Ok(ProofOfControl {
+            challenge: *self,
+            signature: signer.sign(&self.payload()?)?,
+        })
+    }
+}
+
+/// A challenge, signed with the key of the wallet.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ProofOfControl {
+    /// The challenge.
+    pub challenge: ImportChallenge,
+    /// The signature of the key of the wallet over the challenge.
+    pub signature: Signature,
+}
+
+impl ProofOfControl {
+    /// Verifies that the challenge was signed by the key of the wallet.
+    pub fn verify(&self) -> Result<()> {
+        let data = self.challenge.payload()?;
+        self.challenge.wallet.verify(&self.signature, &data)
+    }
+}
+
+/// The history of a wallet as queried from its Replicas, to initialise
+/// the state of an imported wallet with.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ImportHistory {
+    /// The events of the Replicas registering the debits of the wallet,
+    /// and propagating its credits.
+    pub events: Vec<ReplicaEvent>,
+    /// The balance of the wallet, signed by its Replicas, proving
+    /// how many credits and debits the events must account for.
+    pub balance: BalanceProof,
+}
@@ -0,0 +1,158 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::money::{checked_sum, covers, saturating_sub};
+use safe_nd::{AccountId, Money, Transfer, TransferId};
+use serde::{Deserialize, Serialize};
+
+/// A request for an exact amount, from a payer to a payee.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Invoice {
+    /// The reference of the invoice, unique among those of the payee, that the payer
+    /// attaches to its payments as their memo (see Actor::attach_memo).
+    pub reference: String,
+    /// The account expected to pay.
+    pub payer: AccountId,
+    /// The account to be paid.
+    pub payee: AccountId,
+    /// The amount due.
+    pub amount: Money,
+}
+
+/// How the observed credits match an invoice.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum PaymentMatch {
+    /// No credits from the payer with the reference of the invoice.
+    Unpaid,
+    /// Less than the amount due has been received.
+    Underpaid {
+        /// Sum of the matching credits.
+        received: Money,
+        /// What is still due.
+        missing: Money,
+    },
+    /// Exactly the amount due has been received.
+    Exact,
+    /// More than the amount due has been received.
+    Overpaid {
+        /// Sum of the matching credits.
+        received: Money,
+        /// What should be returned to the payer.
+        overage: Money,
+    },
+}
+
+/// The outcome of matching credits against an invoice.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct PaymentMatchReport {
+    /// The classification of the payment.
+    pub status: PaymentMatch,
+    /// The credits that were counted towards the invoice.
+    pub matched: Vec<TransferId>,
+}
+
+impl Invoice {
+    /// Classifies the payment of this invoice, counting every credit from the payer
+    /// to the payee among the given transfers, each with its memo if any, whose memo
+    /// is the reference of the invoice. Payments of other invoices are not counted.
+    /// Sums that would overflow are reported as overpaid by the maximum amount.
+    pub fn match_payment<'a, I: IntoIterator<Item = (&'a Transfer, Option<&'a str>)>>(
+        &self,
+        credits: I,
+    ) -> PaymentMatchReport {
+        let matching: Vec<_> = credits
+            .into_iter()
+            .filter(|(_, memo)| *memo == Some(self.reference.as_str()))
+            .map(|(t, _)| t)
+            .filter(|t| t.id.actor == self.payer && t.to == self.payee)
+            .collect();
+        let matched = matching.iter().map(|t| t.id).collect();
+        let received = checked_sum(matching.iter().map(|t| t.amount));
+        let status = match received {
+            None => PaymentMatch::Overpaid {
+                received: Money::from_nano(u64::max_value()),
+                overage: saturating_sub(Money::from_nano(u64::max_value()), self.amount),
+            },
+            Some(_) if matching.is_empty() => PaymentMatch::Unpaid,
+            Some(received) if !covers(received, self.amount) => PaymentMatch::Underpaid {
+                received,
+                missing: saturating_sub(self.amount, received),
+            },
+            Some(received) if !covers(self.amount, received) => PaymentMatch::Overpaid {
+                received,
+                overage: saturating_sub(received, self.amount),
+            },
+            Some(_) => PaymentMatch::Exact,
+        };
+        PaymentMatchReport { status, matched }
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn classifies_payments() {
+        // Arrange
+        let payer = get_random_pk();
+        let payee = get_random_pk();
+        let invoice = Invoice {
+            reference: "INV-1".to_string(),
+            payer,
+            payee,
+            amount: Money::from_nano(10),
+        };
+        let paid = Some("INV-1");
+        let credit = |counter, amount| Transfer {
+            id: Dot::new(payer, counter),
+            to: payee,
+            amount: Money::from_nano(amount),
+        };
+        let unrelated = Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: payee,
+            amount: Money::from_nano(10),
+        };
+
+        let (first, second, third) = (credit(0, 4), credit(1, 6), credit(2, 9));
+
+        // Act
+        let unpaid = invoice.match_payment(vec![(&unrelated, paid), (&first, None)]);
+        let under = invoice.match_payment(vec![(&first, paid), (&unrelated, paid)]);
+        let exact = invoice.match_payment(vec![(&first, paid), (&second, paid)]);
+        let over = invoice.match_payment(vec![(&first, paid), (&third, paid)]);
+        let other = invoice.match_payment(vec![(&first, paid), (&second, Some("INV-2"))]);
+
+        // Assert
+        assert!(unpaid.status == PaymentMatch::Unpaid);
+        assert!(
+            under.status
+                == PaymentMatch::Underpaid {
+                    received: Money::from_nano(4),
+                    missing: Money::from_nano(6),
+                }
+        );
+        assert!(under.matched.len() == 1);
+        assert!(exact.status == PaymentMatch::Exact);
+        assert!(
+            over.status
+                == PaymentMatch::Overpaid {
+                    received: Money::from_nano(13),
+                    overage: Money::from_nano(3),
+                }
+        );
+        assert!(other.matched == vec![first.id]);
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -25,170 +25,5754 @@
     unused_results
 )]
 
+mod access;
 mod account;
+mod accumulator;
 mod actor;
+mod analytics;
+mod archive;
+mod attachment;
+mod attestation;
+mod auditor;
+mod budget;
+mod cache;
+mod capability;
+mod chart;
+mod checkpoint;
+mod client;
+mod clock;
+mod codec;
+#[cfg(feature = "conformance")]
+mod conformance;
+mod consistency;
+mod consolidation;
+mod derivation;
+mod diff;
+mod disbursement;
+mod economics;
+mod enclave;
+mod error;
+mod escrow;
+#[cfg(feature = "simulated-payouts")]
+mod faucet;
+#[cfg(feature = "file-store")]
+mod file_store;
+mod finality;
+#[cfg(feature = "bench-utils")]
+mod fixtures;
+#[cfg(feature = "test-utils")]
+mod generators;
+mod genesis;
+mod groups;
+mod handle;
+mod hashing;
+mod import;
+mod invoice;
+mod limits;
+mod merge;
+mod messages;
+mod metrics;
+mod money;
+mod multisig;
+mod notary;
+mod outbox;
+mod pending;
+mod pipeline;
+mod policy;
+mod progress;
+mod projection;
+mod quorum;
+mod ratelimit;
+mod receipt;
+mod redaction;
+mod refund;
 mod replica;
+mod residency;
+#[cfg(feature = "simulated-payouts")]
+mod rewards;
+mod rotation;
+mod schedule;
+mod scheme;
+mod search;
+mod settlement;
+mod shard;
+mod signable;
+mod signer;
+#[cfg(feature = "simulation")]
+mod simnet;
+#[cfg(feature = "simulated-payouts")]
+mod simulation;
+mod slo;
+mod snapshot;
+mod sparse;
+mod split;
+mod statement;
+mod store;
+mod subscription;
+mod subsystem;
+mod supply;
+mod sync;
+mod trace;
+mod upgrade;
+mod validation;
+mod verification;
+mod wallets;
+mod wire;
 
 pub use self::{
-    account::Account, actor::Actor as TransferActor, replica::Replica as TransferReplica,
+    access::{AccessEntry, AccessLog, QueryKind},
+    account::{Account, HistoryEntry, HistoryPage, InclusionProof, TransferInfo, MAX_PAGE_SIZE},
+    accumulator::{ValidationAccumulator, ValidationProgress},
+    actor::Actor as TransferActor,
+    analytics::{Month, MonthlyTotals, SpendingAnalytics, CATEGORY_KEY},
+    archive::{
+        GroupArchive, KeySuccession, SectionProofChain, TrustAnchors, DEFAULT_RESHARE_GRACE,
+    },
+    attachment::{
+        Attachment, SignedAttachment, MAX_ATTACHMENT_BYTES, MAX_ATTACHMENT_ENTRIES, MAX_MEMO_BYTES,
+        MEMO_KEY, TAGS_KEY, TAG_SEPARATOR,
+    },
+    attestation::{BalanceAttestation, BalanceProof, ReadFloor, SignedBalance},
+    auditor::AuditorReplica,
+    budget::{Budget, Envelope},
+    cache::{VerificationCache, DEFAULT_CACHE_CAPACITY},
+    capability::{
+        CapabilityProof, CapabilityToken, QueryCapability, QueryScope, CAPABILITY_REQUEST_WINDOW,
+    },
+    chart::{ChartState, Machine, StateChart, Transition},
+    checkpoint::{
+        AccountState, Checkpoint, CheckpointedHistory, CheckpointedInclusion, SignedCheckpoint,
+        SignedCompaction, DEFAULT_EPOCH_LENGTH,
+    },
+    client::{Client, ReplicaTransport, RetryPolicy, TrustRegistry},
+    clock::{AppliedAt, Clock, ManualTimeSource, TimeSource, Timestamp},
+    codec::{accepted_codecs, verified_in, Codec, CodecMigration, Payload},
+    consistency::{ConsistencyReport, StateCommitment},
+    consolidation::{ConsolidationPolicy, CreditSummary, SignedCreditSummary},
+    derivation::{AppWalletRegistration, MasterSeed, SignedAppWallet, MAX_APP_ID_BYTES},
+    diff::{diff_accounts, AccountDiff},
+    disbursement::{
+        Disbursement, Payout, PayoutProof, ReceivedPayout, SignedDisbursement,
+        MAX_DISBURSEMENT_PAYOUTS,
+    },
+    economics::{EconomicParams, FeeDestination, FeePolicy, FeeSchedule, SignedEconomicParams},
+    enclave::{AttestationPolicy, KeyShareAttestation, KeyShareStatement},
+    error::{
+        FailureKind, Outcome, RejectionReason, TernaryResult, TransferError, TransferRejected,
+        TransferResult,
+    },
+    escrow::{EscrowDecision, EscrowRequest, EscrowResolution, ReleaseReason, MAX_ESCROW_TIMEOUT},
+    finality::{Finality, TransferLifecycle},
+    genesis::{get_genesis, get_genesis_set, GenesisSet},
+    groups::{DEFAULT_HELD_CREDIT_TIMEOUT, DEFAULT_KNOWN_GROUPS_CAPACITY},
+    handle::ReplicaHandle,
+    hashing::{Digest, MerkleProof},
+    import::{ImportChallenge, ImportHistory, ProofOfControl},
+    invoice::{Invoice, PaymentMatch, PaymentMatchReport},
+    limits::{
+        SignedSpendingLimits, SpendingLimits, WindowLimit, LIMITS_LOOSENING_DELAY, MAX_LIMIT_WINDOW,
+    },
+    merge::{MergeConflict, MergeReport},
+    messages::{ActorCmd, TransferCmd, TransferEvent, TransferQuery, TransferQueryResponse},
+    metrics::{NoMetrics, ReplicaMetrics},
+    money::{
+        checked_sum, covers, credited, debited, format_money, format_money_trimmed, parse_money,
+        saturating_add, saturating_sub, NANOS_PER_TOKEN,
+    },
+    multisig::{combine_shares, MultisigPolicy, MAX_MULTISIG_KEYS},
+    notary::{
+        receipt_digest, Notarization, NotarizationBatch, NotarizedReceipt, SignedNotarization,
+        MAX_NOTARIZATION_SKEW,
+    },
+    outbox::{Outbox, OutboxEntry, OutboxItem},
+    pending::{DebitRelease, PendingDebit, DEFAULT_PENDING_DEBIT_TIMEOUT},
+    pipeline::{TransferIntent, TransferQueue, TransferStatus, MAX_FINISHED_INTENTS},
+    policy::{Condition, Witness, MAX_CONDITION_COUNT, MAX_CONDITION_DEPTH},
+    progress::{ReplayControl, ReplayObserver, ReplayProgress, REPLAY_REPORT_INTERVAL},
+    projection::{
+        rebuild_projection, BalancesProjection, CountersProjection, Projection, VolumesProjection,
+    },
+    quorum::{is_quorum, shares_needed, QuorumRule},
+    ratelimit::{BucketState, RateLimit, RateLimiter, DEFAULT_RATE_LIMITED_WALLETS},
+    receipt::{verify_propagated, CreditAgreementProof},
+    redaction::{
+        RedactedAmount, RedactedExport, RedactedPayment, RedactionProfile, RedactionStatement,
+    },
+    refund::Refund,
+    replica::{
+        OwnerKind, QueryResponse, ReplayError, ReplayFailure, Replica as TransferReplica,
+        SignedBalances, WalletStorage, WalletSummary,
+    },
+    residency::{MemoryWalletBackend, WalletBackend, WalletStore},
+    rotation::WalletKeyRotation,
+    schedule::{DueTransfer, ScheduleEnd, ScheduledTransfer, TransferSchedule, MAX_DUE_TRANSFERS},
+    scheme::{HybridProof, HybridSignature, SafeNdScheme, SchemeVerifier, SignatureScheme},
+    search::{Payment, PaymentDirection, PaymentQuery},
+    settlement::{Settlement, SettlementStep},
+    shard::SHARD_COUNT,
+    signable::{Signable, SignableCredit, SignableProof, SignableTransfer, SIGNABLE_VERSION},
+    signer::{SigningRequest, TransferSigner},
+    slo::{ReplicaOperation, SloEvent, SloMetrics, SloTarget, SloTracker},
+    snapshot::{SnapshotCompression, SNAPSHOT_VERSION},
+    sparse::{SparseProof, WalletSlice, MAX_SPARSE_PROOF_WALLETS},
+    split::{ExportedPendingDebit, WalletExportPacket},
+    statement::{Statement, StatementLine, StatementLineKind, STATEMENT_CSV_HEADER},
+    store::{
+        link_event, EventStore, MemoryEventStore, ReplicaEventStore, SequencedEvent, GENESIS_LINK,
+    },
+    subscription::WalletNotification,
+    subsystem::{Subsystem, Subsystems},
+    supply::{AnomalyKind, Discrepancy, SupplyReport, WalletAnomaly},
+    sync::{SyncIndex, SyncPacket},
+    trace::{Hop, HopRecord, PaymentTrace, PaymentTracer},
+    upgrade::{dry_run_upgrade, UpgradeReport, UPGRADE_STATE_VERSION},
+    validation::{ValidationContext, ValidationPipeline, ValidationPolicy},
+    verification::{
+        verify_debit_agreement_proof, verify_signed_transfer, verify_transfer_validated,
+        CheckOutcome, VerificationCheck, VerificationReport,
+    },
+    wallets::Wallets,
+    wire::{decode, deserialize_strict, DecodeError, SizeLimits},
+};
+
+#[cfg(feature = "system-clock")]
+pub use self::clock::SystemTimeSource;
+#[cfg(feature = "conformance")]
+pub use self::conformance::{
+    run_conformance, ConformanceCase, ConformanceReplica, ConformanceReport,
+};
+#[cfg(feature = "file-store")]
+pub use self::file_store::{FileEventStore, EVENT_STORE_VERSION};
+#[cfg(feature = "bench-utils")]
+pub use self::fixtures::BenchNetwork;
+#[cfg(feature = "test-utils")]
+pub use self::generators::{
+    ArbitraryDebitProof, ArbitraryEvents, ArbitraryReplicaKeys, ArbitrarySignedTransfer,
+    ArbitraryTransfer, MAX_ARBITRARY_AMOUNT, MAX_ARBITRARY_EVENTS, MAX_ARBITRARY_REPLICAS,
+};
+#[cfg(feature = "stats")]
+pub use self::replica::ReplicaStats;
+#[cfg(feature = "simulation")]
+pub use self::simnet::{Simulation, SimulationConfig, SimulationReport};
+#[cfg(feature = "simulated-payouts")]
+pub use self::{
+    faucet::{Faucet, FaucetChallenge, FaucetClaim, FaucetLimits, FaucetPaid},
+    rewards::RewardFlow,
+    simulation::PayoutMint,
 };
 
 use safe_nd::{
-    DebitAgreementProof, Money, PublicKey, ReplicaEvent, SignedTransfer, TransferId,
-    TransferValidated,
+    AccountId, DebitAgreementProof, KnownGroupAdded, Money, PublicKey, Signature, SignatureShare,
+    SignedTransfer, TransferId, TransferPropagated, TransferRegistered, TransferValidated,
 };
 use serde::{Deserialize, Serialize};
+use threshold_crypto::PublicKeySet;
+
+/// A received credit, contains the DebitAgreementProof from the sender Replicas,
+/// as well as the public key of those Replicas, for us to verify that they are valid Replicas.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ReceivedCredit {
+    /// The sender's aggregated Replica signatures of the sender debit.
+    pub debit_proof: DebitAgreementProof,
+    /// The public key of the signing Replicas.
+    pub debiting_replicas: PublicKey,
+}
+
+impl ReceivedCredit {
+    /// Get the transfer id
+    pub fn id(&self) -> TransferId {
+        self.debit_proof.id()
+    }
+
+    /// Get the amount of this transfer
+    pub fn amount(&self) -> Money {
+        self.debit_proof.amount()
+    }
+
+    /// Get the recipient of this transfer
+    pub fn from(&self) -> PublicKey {
+        self.debit_proof.from()
+    }
+
+    /// Get the recipient of this transfer
+    pub fn to(&self) -> PublicKey {
+        self.debit_proof.to()
+    }
+}
+
+// ------------------------------------------------------------
+//                      Actor
+// ------------------------------------------------------------
+
+/// An implementation of the ReplicaValidator, should contain the logic from upper layers
+/// for determining if a remote group of Replicas, represented by a PublicKey, is indeed valid.
+/// This is logic from the membership part of the system, and thus handled by the upper layers
+/// membership implementation.
+pub trait ReplicaValidator {
+    /// Determines if a remote group of Replicas, represented by a PublicKey, is indeed valid.
+    fn is_valid(&self, replica_group: PublicKey) -> bool;
+}
+
+/// Events raised by the Actor.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum ActorEvent {
+    /// Raised when a request to create
+    /// a transfer validation cmd for Replicas,
+    /// has been successful (valid on local state).
+    TransferInitiated(TransferInitiated),
+    /// Raised when an Actor receives a Replica transfer validation.
+    TransferValidationReceived(TransferValidationReceived),
+    /// Raised when the Actor has accumulated a
+    /// quorum of validations, and produced a RegisterTransfer cmd
+    /// for sending to Replicas.
+    TransferRegistrationSent(TransferRegistrationSent),
+    /// Raised when the Actor has received
+    /// unknown credits on querying Replicas.
+    TransfersSynched(TransfersSynched),
+    /// Raised when the Actor has received a checkpointed
+    /// history, that it continues from instead of its own.
+    CheckpointSynched(CheckpointSynched),
+    /// Raised when an artifact of the outbox has been delivered.
+    OutboxAcknowledged(OutboxAcknowledged),
+    /// Raised when a transfer has been queued, to be initiated in its turn.
+    TransferQueued(TransferQueued),
+    /// Raised when a queued transfer could not be initiated, or was refused.
+    QueuedTransferFailed(QueuedTransferFailed),
+    /// Raised when a transfer short of a quorum of validations has been aborted.
+    TransferAborted(TransferAborted),
+    /// Raised when a transfer has been scheduled to recur.
+    TransferScheduled(TransferScheduled),
+    /// Raised when a scheduled transfer has been cancelled.
+    ScheduledTransferCancelled(ScheduledTransferCancelled),
+    /// Raised when a Replica has sent a share over our debit that does not verify.
+    ValidationShareRejected(ValidationShareRejected),
+    /// Raised when the Actor has initiated the refund of the overage of an invoice.
+    OverageRefundInitiated(OverageRefundInitiated),
+    /// Raised when the Actor has stored the attachment of the sender of a credit.
+    AttachmentStored(AttachmentStored),
+}
+
+impl ActorEvent {
+    /// The name of the event, as in the state charts (see StateChart).
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActorEvent::TransferInitiated(_) => "TransferInitiated",
+            ActorEvent::TransferValidationReceived(_) => "TransferValidationReceived",
+            ActorEvent::TransferRegistrationSent(_) => "TransferRegistrationSent",
+            ActorEvent::TransfersSynched(_) => "TransfersSynched",
+            ActorEvent::CheckpointSynched(_) => "CheckpointSynched",
+            ActorEvent::OutboxAcknowledged(_) => "OutboxAcknowledged",
+            ActorEvent::TransferQueued(_) => "TransferQueued",
+            ActorEvent::QueuedTransferFailed(_) => "QueuedTransferFailed",
+            ActorEvent::TransferAborted(_) => "TransferAborted",
+            ActorEvent::TransferScheduled(_) => "TransferScheduled",
+            ActorEvent::ScheduledTransferCancelled(_) => "ScheduledTransferCancelled",
+            ActorEvent::ValidationShareRejected(_) => "ValidationShareRejected",
+            ActorEvent::OverageRefundInitiated(_) => "OverageRefundInitiated",
+            ActorEvent::AttachmentStored(_) => "AttachmentStored",
+        }
+    }
+}
+
+/// Raised when an artifact of the outbox of the Actor has been
+/// delivered, and is not to be sent again.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct OutboxAcknowledged {
+    /// The sequence number of the artifact in the outbox.
+    pub sequence: u64,
+}
+
+/// Raised when the Actor has queued a transfer, to be initiated once
+/// those queued before it are registered (see Actor::dispatch).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferQueued {
+    /// The transfer queued.
+    pub intent: TransferIntent,
+}
+
+/// Raised when a queued transfer could not be initiated in its turn, such as for
+/// a lack of balance, or was refused by our Replicas. The transfers queued after
+/// it go on without it.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct QueuedTransferFailed {
+    /// The sequence number of the transfer in the queue.
+    pub sequence: u64,
+    /// Why it failed.
+    pub reason: String,
+}
+
+/// Raised when the Actor has aborted the debit it initiated, before a quorum of
+/// our Replicas validated it, so that the next debit takes its counter. Validations
+/// of the aborted debit arriving later are refused (see Actor::abort).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferAborted {
+    /// The debit aborted.
+    pub signed_transfer: SignedTransfer,
+    /// The release of the debit, for the Replicas that validated it.
+    pub release: DebitRelease,
+}
+
+/// Raised when a Replica has sent a validation of the debit in flight of the Actor, with
+/// a share that does not verify, for the Replica to be told apart from those yet to send
+/// theirs (see Actor::validation_progress). The validation is not otherwise accumulated.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ValidationShareRejected {
+    /// The validation.
+    pub validation: TransferValidated,
+}
+
+/// Raised when the Actor has initiated the refund of the overage of an invoice we issued,
+/// recording the credits it refunds so that they are not refunded again (see Actor::refund_overage).
+/// The refund is then applied as any other transfer initiated.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct OverageRefundInitiated {
+    /// The credits counted towards the invoice.
+    pub refunded: Vec<TransferId>,
+    /// The refund to the payer.
+    pub initiated: TransferInitiated,
+}
+
+/// Raised when the Actor has scheduled a transfer to recur, its payments
+/// to be made once due (see Actor::due_transfers).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferScheduled {
+    /// The transfer scheduled.
+    pub scheduled: ScheduledTransfer,
+}
+
+/// Raised when the Actor has cancelled a scheduled transfer,
+/// of which no more payments are due.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ScheduledTransferCancelled {
+    /// The id of the schedule.
+    pub id: u64,
+}
+
+/// Raised when the Actor has received
+/// f.ex. credits that its Replicas were holding upon
+/// the propagation of them from a remote group of Replicas,
+/// or unknown debits that its Replicas were holding
+/// upon the registration of them from another
+/// instance of the same Actor.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransfersSynched {
+    /// Credits we don't have locally.
+    credits: Vec<ReceivedCredit>,
+    /// The debits we don't have locally.
+    debits: Vec<DebitAgreementProof>,
+}
+
+/// Raised when the Actor has verified the checkpoint of a history
+/// received from its Replicas, and the transfers since.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CheckpointSynched {
+    /// The state of our account at the checkpoint.
+    state: AccountState,
+    /// The credits since the checkpoint.
+    credits: Vec<ReceivedCredit>,
+    /// The debits since the checkpoint.
+    debits: Vec<DebitAgreementProof>,
+}
+
+/// This event is raised by the Actor after having
+/// successfully created a transfer cmd to send to the
+/// Replicas for validation.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferInitiated {
+    /// The transfer signed by the initiating Actor.
+    pub signed_transfer: SignedTransfer,
+}
+
+impl TransferInitiated {
+    /// Get the transfer id
+    pub fn id(&self) -> TransferId {
+        self.signed_transfer.id()
+    }
+}
+
+/// Raised when a Replica responds with
+/// a successful validation of a transfer.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferValidationReceived {
+    /// The event raised by a Replica.
+    validation: TransferValidated,
+    /// Added when quorum of validations
+    /// have been received from Replicas.
+    pub proof: Option<DebitAgreementProof>,
+}
+
+/// Raised when the Actor has accumulated a
+/// quorum of validations, and produced a RegisterTransfer cmd
+/// for sending to Replicas.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferRegistrationSent {
+    debit_proof: DebitAgreementProof,
+}
+
+// ------------------------------------------------------------
+//                      Replica
+// ------------------------------------------------------------
+
+/// Events raised by the Replica.
+/// The events of safe_nd are converted into these.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum ReplicaEvent {
+    /// The PK set of a new group that we learnt of, without a chain of keys to it,
+    /// as in safe_nd. Only verified for a group keyed by a trust anchor or by a key
+    /// of our own group (see Replica::apply_checked); others are added chained.
+    KnownGroupAdded(KnownGroupAdded),
+    /// Raised when a group we knew of has been forgotten.
+    KnownGroupForgotten(KnownGroupForgotten),
+    /// A transfer that this Replica has validated.
+    TransferValidated(TransferValidated),
+    /// A debit that has been registered at this Replica.
+    TransferRegistered(TransferRegistered),
+    /// A credit that has been propagated to this Replica.
+    TransferPropagated(TransferPropagated),
+    /// Raised when the Replica has signed a checkpoint of its accounts.
+    CheckpointSigned(CheckpointSigned),
+    /// Raised when the Replica has recorded a checkpoint signed by its group.
+    CheckpointRecorded(CheckpointRecorded),
+    /// Raised when a condition has been attached to an account as its owner.
+    OwnerConditionAttached(OwnerConditionAttached),
+    /// Raised when an account has been placed in quarantine.
+    AccountQuarantined(AccountQuarantined),
+    /// Raised when the quarantine of an account has been lifted.
+    QuarantineLifted(QuarantineLifted),
+    /// Raised when an account has opted in to a feature.
+    WalletUpgraded(WalletUpgraded),
+    /// Raised when the keys of our group have been rotated.
+    OwnKeyRotated(OwnKeyRotated),
+    /// Raised when an account has been moved to another group.
+    WalletMoved(WalletMoved),
+    /// Raised when a moved account has been restored.
+    WalletRestored(WalletRestored),
+    /// Raised when the oldest credits of an account have been consolidated.
+    CreditsConsolidated(CreditsConsolidated),
+    /// Raised when the history of an account up to an index has been compacted.
+    WalletCompacted(WalletCompacted),
+    /// Raised when an attachment of the sender has been stored with a credit.
+    AttachmentStored(AttachmentStored),
+    /// Raised when an account has signed two different debits with the same counter.
+    DoubleSpendAttempted(DoubleSpendAttempted),
+    /// Raised when the double spend attempt of an account has been resolved.
+    DoubleSpendResolved(DoubleSpendResolved),
+    /// Raised when another valid proof of a credit already held has been recorded.
+    AlternateProofRecorded(AlternateProofRecorded),
+    /// Raised when the fee of a registered debit has been charged to its sender.
+    FeeCharged(FeeCharged),
+    /// Raised when a credit signed by a group we do not know of has been held.
+    CreditHeld(CreditHeld),
+    /// Raised when a key share of our group has been attested to live in an enclave.
+    KeyShareAttested(KeyShareAttested),
+    /// Raised when the wallet of an app has been registered to its owner.
+    AppWalletRegistered(AppWalletRegistered),
+    /// Raised when the owner of a wallet has handed it over to a new key.
+    WalletOwnerChanged(WalletOwnerChanged),
+    /// Raised when a wallet has come to be owned by several keys.
+    MultisigPolicySet(MultisigPolicySet),
+    /// Raised when a debit has been held in escrow, until accepted by the recipient.
+    EscrowOpened(EscrowOpened),
+    /// Raised when a debit held in escrow has been released back to the sender.
+    EscrowReleased(EscrowReleased),
+    /// Raised when a debit refunding a credit of the wallet has been validated.
+    RefundValidated(RefundValidated),
+    /// Raised when the owner of a wallet has set limits on its debits.
+    SpendingLimitsSet(SpendingLimitsSet),
+    /// Raised when a validated debit that was never registered has expired.
+    PendingDebitExpired(PendingDebitExpired),
+    /// Raised when a reshare of the keys of our group has begun.
+    KeyReshareStarted(KeyReshareStarted),
+    /// Raised when wallets have been split off to a sibling group.
+    WalletsExported(WalletsExported),
+    /// Raised when wallets split off by a sibling group have been taken over.
+    WalletsAbsorbed(WalletsAbsorbed),
+    /// Raised when the debit of a disbursement of many payouts has been validated.
+    DisbursementValidated(DisbursementValidated),
+    /// Raised when the debit of a disbursement has been registered at this Replica.
+    DisbursementRegistered(DisbursementRegistered),
+    /// Raised when a payout of a disbursement has been propagated to this Replica.
+    PayoutPropagated(PayoutPropagated),
+    /// The PK set of a new group that we learnt of, with the chain of keys to it.
+    KnownGroupChained(KnownGroupChained),
+    /// Raised when a validated debit has been released by its Actor, having aborted it.
+    DebitReleased(DebitReleased),
+    /// Raised when the nonce of a proof of control of a wallet to import has been consumed.
+    ImportNonceConsumed(ImportNonceConsumed),
+    /// Raised when a credit held for a group we do not know of has been dropped, unreleased.
+    HeldCreditExpired(HeldCreditExpired),
+}
+
+impl From<safe_nd::ReplicaEvent> for ReplicaEvent {
+    fn from(event: safe_nd::ReplicaEvent) -> Self {
+        match event {
+            safe_nd::ReplicaEvent::KnownGroupAdded(e) => ReplicaEvent::KnownGroupAdded(e),
+            safe_nd::ReplicaEvent::TransferValidated(e) => ReplicaEvent::TransferValidated(e),
+            safe_nd::ReplicaEvent::TransferRegistered(e) => ReplicaEvent::TransferRegistered(e),
+            safe_nd::ReplicaEvent::TransferPropagated(e) => ReplicaEvent::TransferPropagated(e),
+        }
+    }
+}
+
+impl ReplicaEvent {
+    /// The name of the event, as in the state charts (see StateChart).
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReplicaEvent::KnownGroupAdded(_) => "KnownGroupAdded",
+            ReplicaEvent::KnownGroupForgotten(_) => "KnownGroupForgotten",
+            ReplicaEvent::TransferValidated(_) => "TransferValidated",
+            ReplicaEvent::TransferRegistered(_) => "TransferRegistered",
+            ReplicaEvent::TransferPropagated(_) => "TransferPropagated",
+            ReplicaEvent::CheckpointSigned(_) => "CheckpointSigned",
+            ReplicaEvent::CheckpointRecorded(_) => "CheckpointRecorded",
+            ReplicaEvent::OwnerConditionAttached(_) => "OwnerConditionAttached",
+            ReplicaEvent::AccountQuarantined(_) => "AccountQuarantined",
+            ReplicaEvent::QuarantineLifted(_) => "QuarantineLifted",
+            ReplicaEvent::WalletUpgraded(_) => "WalletUpgraded",
+            ReplicaEvent::OwnKeyRotated(_) => "OwnKeyRotated",
+            ReplicaEvent::WalletMoved(_) => "WalletMoved",
+            ReplicaEvent::WalletRestored(_) => "WalletRestored",
+            ReplicaEvent::CreditsConsolidated(_) => "CreditsConsolidated",
+            ReplicaEvent::WalletCompacted(_) => "WalletCompacted",
+            ReplicaEvent::AttachmentStored(_) => "AttachmentStored",
+            ReplicaEvent::DoubleSpendAttempted(_) => "DoubleSpendAttempted",
+            ReplicaEvent::DoubleSpendResolved(_) => "DoubleSpendResolved",
+            ReplicaEvent::AlternateProofRecorded(_) => "AlternateProofRecorded",
+            ReplicaEvent::FeeCharged(_) => "FeeCharged",
+            ReplicaEvent::CreditHeld(_) => "CreditHeld",
+            ReplicaEvent::KeyShareAttested(_) => "KeyShareAttested",
+            ReplicaEvent::AppWalletRegistered(_) => "AppWalletRegistered",
+            ReplicaEvent::WalletOwnerChanged(_) => "WalletOwnerChanged",
+            ReplicaEvent::MultisigPolicySet(_) => "MultisigPolicySet",
+            ReplicaEvent::EscrowOpened(_) => "EscrowOpened",
+            ReplicaEvent::EscrowReleased(_) => "EscrowReleased",
+            ReplicaEvent::RefundValidated(_) => "RefundValidated",
+            ReplicaEvent::SpendingLimitsSet(_) => "SpendingLimitsSet",
+            ReplicaEvent::PendingDebitExpired(_) => "PendingDebitExpired",
+            ReplicaEvent::KeyReshareStarted(_) => "KeyReshareStarted",
+            ReplicaEvent::WalletsExported(_) => "WalletsExported",
+            ReplicaEvent::WalletsAbsorbed(_) => "WalletsAbsorbed",
+            ReplicaEvent::DisbursementValidated(_) => "DisbursementValidated",
+            ReplicaEvent::DisbursementRegistered(_) => "DisbursementRegistered",
+            ReplicaEvent::PayoutPropagated(_) => "PayoutPropagated",
+            ReplicaEvent::KnownGroupChained(_) => "KnownGroupChained",
+            ReplicaEvent::DebitReleased(_) => "DebitReleased",
+            ReplicaEvent::ImportNonceConsumed(_) => "ImportNonceConsumed",
+            ReplicaEvent::HeldCreditExpired(_) => "HeldCreditExpired",
+        }
+    }
+
+    /// The wallet the event is of, if it is of a single one.
+    pub fn wallet(&self) -> Option<AccountId> {
+        match self {
+            ReplicaEvent::TransferValidated(e) => Some(e.signed_transfer.from()),
+            ReplicaEvent::TransferRegistered(e) => Some(e.from()),
+            ReplicaEvent::TransferPropagated(e) => Some(e.to()),
+            ReplicaEvent::OwnerConditionAttached(e) => Some(e.account_id),
+            ReplicaEvent::AccountQuarantined(e) => Some(e.account_id),
+            ReplicaEvent::QuarantineLifted(e) => Some(e.account_id),
+            ReplicaEvent::WalletUpgraded(e) => Some(e.wallet),
+            ReplicaEvent::WalletMoved(e) => Some(e.wallet),
+            ReplicaEvent::WalletRestored(e) => Some(e.wallet),
+            ReplicaEvent::CreditsConsolidated(e) => Some(e.signed_summary.summary.wallet),
+            ReplicaEvent::DoubleSpendAttempted(e) => Some(e.wallet()),
+            ReplicaEvent::DoubleSpendResolved(e) => Some(e.wallet),
+            ReplicaEvent::FeeCharged(e) => Some(e.transfer_id.actor),
+            ReplicaEvent::CreditHeld(e) => Some(e.debit_proof.to()),
+            ReplicaEvent::WalletOwnerChanged(e) => Some(e.rotation.wallet),
+            ReplicaEvent::MultisigPolicySet(e) => Some(e.wallet),
+            ReplicaEvent::EscrowOpened(e) => Some(e.request.signed_transfer.from()),
+            ReplicaEvent::EscrowReleased(e) => Some(e.transfer_id.actor),
+            ReplicaEvent::PendingDebitExpired(e) => Some(e.release.transfer_id.actor),
+            ReplicaEvent::DebitReleased(e) => Some(e.release.transfer_id.actor),
+            ReplicaEvent::ImportNonceConsumed(e) => Some(e.proof.challenge.wallet),
+            ReplicaEvent::HeldCreditExpired(e) => Some(e.wallet),
+            ReplicaEvent::DisbursementValidated(e) => Some(e.disbursement.signed_transfer.from()),
+            ReplicaEvent::DisbursementRegistered(e) => Some(e.debit_proof.from()),
+            ReplicaEvent::PayoutPropagated(e) => Some(e.payout.payout_proof.to()),
+            _ => None,
+        }
+    }
+}
+
+/// Raised when a Replica has signed a checkpoint,
+/// its share is to be sent to its peers for combining.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CheckpointSigned {
+    /// The checkpoint of the state of our accounts.
+    pub checkpoint: Checkpoint,
+    /// Our share of the group signature.
+    pub replica_signature: SignatureShare,
+}
+
+/// Raised when a condition has been attached to an account, as its owner.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct OwnerConditionAttached {
+    /// The account.
+    pub account_id: AccountId,
+    /// The condition that its debits must satisfy.
+    pub condition: Condition,
+}
+
+/// Raised when an account has been placed in quarantine by its Replicas.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct AccountQuarantined {
+    /// The account.
+    pub account_id: AccountId,
+    /// The nonce, decided by the group, from which the challenges are derived.
+    pub nonce: Digest,
+}
+
+/// Raised when the quarantine of an account has been lifted by its Replicas.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct QuarantineLifted {
+    /// The account.
+    pub account_id: AccountId,
+}
+
+/// Raised when an account has been moved to another group, such as on a split.
+/// The account is kept until restored or dropped, but no longer debited nor credited.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletMoved {
+    /// The account.
+    pub wallet: AccountId,
+    /// The key of the group now holding the account.
+    pub new_group: PublicKey,
+}
+
+/// Raised when an account moved to another group is held by us again,
+/// such as when the migration was aborted.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletRestored {
+    /// The account.
+    pub wallet: AccountId,
+}
+
+/// Raised when wallets have been split off to a sibling group, such as when our
+/// section splits. Unlike a moved account, nothing of the wallets is kept.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletsExported {
+    /// The wallets.
+    pub wallets: Vec<AccountId>,
+    /// The hash of the packet they were exported in (see WalletExportPacket::hash).
+    pub packet_hash: Digest,
+}
+
+/// Raised when wallets split off by a sibling group have been verified and taken over.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletsAbsorbed {
+    /// The packet the wallets were exported in.
+    pub packet: WalletExportPacket,
+}
+
+/// Raised when the owner of a wallet has rotated its key. The history and balance of
+/// the wallet are held under the new key from then on, which signs its debits, while
+/// credits to the previous key are still credited to it (see Replica::rotate_wallet_key).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletOwnerChanged {
+    /// The rotation, signed by the previous key.
+    pub rotation: WalletKeyRotation,
+}
+
+/// Raised when the owner of a wallet has handed it over to several keys, enough of
+/// which must sign its debits from then on (see Replica::set_multisig_policy).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct MultisigPolicySet {
+    /// The wallet.
+    pub wallet: AccountId,
+    /// The owners of the wallet, and how many of them must sign.
+    pub policy: MultisigPolicy,
+    /// The signature of the key of the wallet over the policy.
+    pub signature: Signature,
+}
+
+/// Raised when a debit has been checked as any other, but is held in escrow instead of
+/// being validated, locking the wallet until the recipient accepts it, the sender cancels
+/// it, or it expires. Only once accepted do the Replicas validate it, so that no proof of
+/// the debit exists that could be propagated to the recipient without its acceptance
+/// (see Replica::open_escrow).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct EscrowOpened {
+    /// The request of the sender.
+    pub request: EscrowRequest,
+    /// When the debit is released back to the sender, if not accepted.
+    pub expires: Timestamp,
+}
+
+/// Raised when a debit held in escrow has been released back to the sender,
+/// which can then debit the wallet again, from the same counter.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct EscrowReleased {
+    /// The debit released.
+    pub transfer_id: TransferId,
+    /// Why it was released.
+    pub reason: ReleaseReason,
+}
+
+/// Raised when a Replica has validated a debit refunding a credit of the wallet,
+/// which is counted against the amount of the credit once its debit is registered
+/// (see Replica::validate_refund).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct RefundValidated {
+    /// The refund, signed by the refunding wallet.
+    pub refund: Refund,
+    /// Replica signature over the debit.
+    pub replica_signature: SignatureShare,
+    /// The PK Set of the Replicas
+    pub replicas: PublicKeySet,
+}
+
+impl RefundValidated {
+    /// The validation of the debit, for the Actor to aggregate as any other.
+    pub fn validated(&self) -> TransferValidated {
+        TransferValidated {
+            signed_transfer: self.refund.signed_transfer.clone(),
+            replica_signature: self.replica_signature.clone(),
+            replicas: self.replicas.clone(),
+        }
+    }
+}
+
+/// Raised when a Replica has validated the debit of a disbursement, having checked
+/// its payouts as a whole, along with the root of the payouts, which is then agreed
+/// with the debit (see Replica::validate_disbursement).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct DisbursementValidated {
+    /// The disbursement, signed by the disbursing wallet.
+    pub disbursement: Disbursement,
+    /// Replica signature over the debit.
+    pub replica_signature: SignatureShare,
+    /// The PK Set of the Replicas
+    pub replicas: PublicKeySet,
+    /// Replica signature over the id of the debit and the root of the payouts.
+    pub payouts_signature: SignatureShare,
+}
+
+impl DisbursementValidated {
+    /// The validation of the debit, for the Actor to aggregate as any other.
+    pub fn validated(&self) -> TransferValidated {
+        TransferValidated {
+            signed_transfer: self.disbursement.signed_transfer.clone(),
+            replica_signature: self.replica_signature.clone(),
+            replicas: self.replicas.clone(),
+        }
+    }
+}
+
+/// Raised when a Replica has registered the debit of a disbursement, along with
+/// its share of the signature over the root of the payouts, to be combined into
+/// a SignedDisbursement (see Replica::register_disbursement).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct DisbursementRegistered {
+    /// The disbursement.
+    pub disbursement: Disbursement,
+    /// The proof of its debit.
+    pub debit_proof: DebitAgreementProof,
+    /// Replica signature over the id of the debit and the root of the payouts.
+    pub payouts_signature: SignatureShare,
+}
+
+impl DisbursementRegistered {
+    /// The registration of the debit, as of any other.
+    pub fn registered(&self) -> TransferRegistered {
+        TransferRegistered {
+            debit_proof: self.debit_proof.clone(),
+        }
+    }
+}
+
+/// Raised when a Replica has credited a payout of a disbursement to its recipient
+/// (see Replica::receive_payout).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct PayoutPropagated {
+    /// The payout, with the key of the Replicas that registered the disbursement.
+    pub payout: ReceivedPayout,
+    /// Replica signature over the proof of the debit of the disbursement.
+    pub crediting_replica_sig: SignatureShare,
+}
+
+/// Raised when the owner of a wallet has set limits on its debits. Limits stricter
+/// than those in effect take effect at once, looser ones after LIMITS_LOOSENING_DELAY,
+/// unless updated again in the meantime (see Replica::set_spending_limits).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SpendingLimitsSet {
+    /// The limits, signed by the key of the wallet.
+    pub signed: SignedSpendingLimits,
+    /// When they were set.
+    pub set_at: Timestamp,
+    /// When they take effect.
+    pub effective: Timestamp,
+}
+
+/// Raised when a debit that was validated but never registered has been left pending
+/// for longer than the timeout, such as when the client crashed before aggregating the
+/// proof of it, and its Actor has released it. The wallet can then debit again, from the
+/// same counter. The expired debit is no longer registered (see Replica::expire_pending_debit).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct PendingDebitExpired {
+    /// The release of the debit expired, signed by its Actor.
+    pub release: DebitRelease,
+}
+
+/// Raised when the Actor of a validated debit has aborted it, short of a quorum of
+/// validations, and released it. The wallet can then debit again, from the same counter,
+/// without the debit being left pending until it expires (see Replica::release_debit).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct DebitReleased {
+    /// The release of the debit, signed by its Actor.
+    pub release: DebitRelease,
+}
+
+/// Raised when the Replicas of a wallet have consumed the nonce of a proof of control
+/// of its key, so that the proof can not be replayed to import the wallet again
+/// (see Replica::consume_import_nonce).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ImportNonceConsumed {
+    /// The proof of control, signed by the key of the wallet.
+    pub proof: ProofOfControl,
+}
+
+/// Raised when the oldest credits of an account have been replaced
+/// with a summary of them, signed by the Replicas of the account.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CreditsConsolidated {
+    /// The signed summary.
+    pub signed_summary: SignedCreditSummary,
+}
+
+/// Raised when the entries of an account up to an index of its history have been
+/// replaced with its state there, signed by the Replicas of the account.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletCompacted {
+    /// The signed state.
+    pub signed_compaction: SignedCompaction,
+}
+
+/// Raised when a Replica holds a propagated credit signed by a group it does
+/// not know of, until it does, instead of rejecting it (see Replica::with_held_credits).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CreditHeld {
+    /// The proof of the debit, signed by the unknown group.
+    pub debit_proof: DebitAgreementProof,
+}
+
+/// Raised when a credit has been held for longer than the timeout without the group
+/// that signed it becoming known, and is dropped (see Replica::expired_held_credits).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct HeldCreditExpired {
+    /// The recipient of the credit.
+    pub wallet: AccountId,
+    /// The id of the credit.
+    pub transfer_id: TransferId,
+}
+
+/// Raised when the Replicas of the recipient of a credit, or its Actor, have
+/// stored the attachment of its sender along with it.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct AttachmentStored {
+    /// The attachment, signed by the sender.
+    pub signed_attachment: SignedAttachment,
+}
+
+/// Raised when a Replica has seen two different debits signed by an account with
+/// the same counter. The two signed transfers are the evidence of the attempt,
+/// verifiable by anyone. Validations for the account are refused until resolved.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct DoubleSpendAttempted {
+    /// The debit seen first.
+    pub first: SignedTransfer,
+    /// The conflicting debit.
+    pub second: SignedTransfer,
+}
+
+impl DoubleSpendAttempted {
+    /// The account that attempted the double spend.
+    pub fn wallet(&self) -> AccountId {
+        self.first.from()
+    }
+
+    /// Verifies that the two transfers are different debits of the same
+    /// account with the same counter, both signed by the account.
+    /// The same transfer under two signatures does not conflict.
+    pub fn verify(&self) -> safe_nd::Result<()> {
+        if self.first.id() != self.second.id() || self.first.transfer == self.second.transfer {
+            return Err(safe_nd::Error::from("Transfers do not conflict"));
+        }
+        for signed in &[&self.first, &self.second] {
+            SignableTransfer::new(&signed.transfer)
+                .verify(&signed.from(), &signed.actor_signature)?;
+        }
+        Ok(())
+    }
+}
+
+/// Raised when the double spend attempt of an account has been resolved,
+/// upon a decision of its group, and its debits are validated again.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct DoubleSpendResolved {
+    /// The account.
+    pub wallet: AccountId,
+}
+
+/// Raised when a credit already held has been propagated again with a different
+/// valid proof, such as one signed by the debiting group before the rotation of its
+/// keys and one after. The proof propagated first stays the one in the history.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct AlternateProofRecorded {
+    /// The credit, with the alternate proof.
+    pub credit: ReceivedCredit,
+}
+
+/// Raised when the fee of a registered debit has been charged to its sender,
+/// and credited to the wallet that fees go to, or burnt.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct FeeCharged {
+    /// The debit the fee is charged for.
+    pub transfer_id: TransferId,
+    /// The fee.
+    pub fee: Money,
+    /// Where the fee goes.
+    pub destination: FeeDestination,
+}
+
+/// Raised when the keys of the group of a Replica have been rotated, such as on churn.
+/// The previous key set is kept, along with the succession it signed, so that
+/// proofs signed by any of our past keys can still be verified.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct OwnKeyRotated {
+    /// The PK set of our group before the rotation.
+    pub previous: PublicKeySet,
+    /// The PK set of our group after the rotation.
+    pub replicas: PublicKeySet,
+    /// The index of our key share in the new set.
+    pub key_index: usize,
+    /// The handover from the previous key to the new one, signed by the previous group.
+    pub succession: KeySuccession,
+}
+
+/// Raised when a Replica has begun a reshare of the keys of its group, such as by a
+/// distributed key generation on churn, with its share of the incoming keys at hand.
+/// Until the reshare is completed, by rotating to the incoming keys (see OwnKeyRotated),
+/// we keep signing with our current share, and proofs signed by either the outgoing
+/// or the incoming keys are valid, for as long as the grace window lasts.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct KeyReshareStarted {
+    /// The PK set of our group before the reshare.
+    pub outgoing: PublicKeySet,
+    /// The PK set of our group after the reshare.
+    pub incoming: PublicKeySet,
+    /// The index of our key share in the incoming set.
+    pub key_index: usize,
+    /// The handover from the outgoing key to the incoming one, signed by the outgoing group.
+    pub succession: KeySuccession,
+    /// When the grace window ends. A reshare not completed by then has lapsed.
+    pub grace_until: Timestamp,
+}
+
+/// Raised when a key share of the group of a Replica has been attested, by a trusted
+/// attestation service, to live in an enclave running accepted code, so that peers can
+/// verify where the signing shares of the group live (see Replica::record_attestation).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct KeyShareAttested {
+    /// The attestation of the key share.
+    pub attestation: KeyShareAttestation,
+}
+
+/// Raised when an app-scoped wallet has been registered to the owner it was derived for,
+/// so that the owner can prove control of it, and enumerate the wallets of its apps
+/// (see MasterSeed and Replica::register_app_wallet).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct AppWalletRegistered {
+    /// The registration, signed by the owner and the app wallet.
+    pub signed: SignedAppWallet,
+}
+
+/// Raised when a Replica has learnt of a group, with the chain of keys leading to the key
+/// of the group from a key the Replica trusts (see Replica::add_known_group).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct KnownGroupChained {
+    /// The PK set of the group.
+    pub group: PublicKeySet,
+    /// The chain from a trust anchor, or a key of our own group, to the key of the group.
+    pub chain: SectionProofChain,
+}
+
+/// Raised when a Replica has forgotten a group it knew of, such as a group merged
+/// away, so that credits signed by it are no longer accepted (see Replica::forget_group).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct KnownGroupForgotten {
+    /// The key of the group.
+    pub group: PublicKey,
+}
+
+/// A feature that an account opts in to after its creation.
+#[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum WalletFeature {
+    /// The history keeps checksums of its entries,
+    /// verified before Replicas sign anything about it.
+    Checksums,
+    /// Debits must also be signed by this key, as a second factor (see Witness).
+    SecondFactor(PublicKey),
+    /// Small credits are periodically consolidated into a summary signed by
+    /// the Replicas, so that the history does not grow with every one of them.
+    Consolidation(ConsolidationPolicy),
+}
+
+/// Raised when an account has opted in to a feature, so that the
+/// features of accounts are restored when replaying the events.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletUpgraded {
+    /// The account.
+    pub wallet: AccountId,
+    /// The feature.
+    pub feature: WalletFeature,
+    /// The key that authorized the upgrade, of the owner or of the group.
+    pub authorized_by: PublicKey,
+}
+
+/// Raised when a Replica has recorded a checkpoint signed by its group.
+/// Histories are then served from that checkpoint.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CheckpointRecorded {
+    /// The checkpoint, with the group signature.
+    pub signed_checkpoint: SignedCheckpoint,
+}
+
+// the tests build instances with the default clock, that of the system
+#[cfg(feature = "system-clock")]
+#[allow(unused)]
+mod test {
+    use crate::{
+        actor::Actor, decode, dry_run_upgrade, rebuild_projection, receipt_digest,
+        replica::Replica, shares_needed, verify_debit_agreement_proof, verify_propagated,
+        verify_signed_transfer, verify_transfer_validated, Account, ActorCmd, ActorEvent,
+        AttestationPolicy, AuditorReplica, BalanceProof, BalancesProjection, ChartState,
+        CheckOutcome, CheckpointRecorded, Clock, Codec, CodecMigration, Condition,
+        ConsolidationPolicy, CountersProjection, CreditAgreementProof, DecodeError, Discrepancy,
+        DoubleSpendAttempted, EconomicParams, EventStore, FailureKind, FeeDestination, FeeSchedule,
+        Finality, HistoryEntry, Hop, ImportChallenge, ImportHistory, Invoice, KeyShareAttestation,
+        KeyShareAttested, KeyShareStatement, KeySuccession, KnownGroupChained, Machine,
+        ManualTimeSource, MasterSeed, MemoryEventStore, MemoryWalletBackend, MergeConflict,
+        MergeReport, Month, MonthlyTotals, MultisigPolicy, NotarizationBatch, OutboxItem,
+        OwnerConditionAttached, OwnerKind, PaymentDirection, PaymentTracer, Payout, Projection,
+        QuarantineLifted, QueryResponse, QuorumRule, RateLimit, ReceivedCredit, RejectionReason,
+        ReplayControl, ReplayFailure, ReplayProgress, ReplicaEvent, ReplicaHandle, ReplicaMetrics,
+        ReplicaValidator, SectionProofChain, Settlement, SettlementStep, Signable, SignableProof,
+        SignableTransfer, SignedCheckpoint, SignedCompaction, SignedCreditSummary,
+        SignedDisbursement, SignedNotarization, SizeLimits, SpendingAnalytics, SpendingLimits,
+        StateChart, StatementLineKind, Subsystem, Subsystems, SyncIndex, TimeSource, TransferCmd,
+        TransferError, TransferInitiated, TransferLifecycle, TransferQuery, TransferQueryResponse,
+        TransferStatus, TrustAnchors, ValidationAccumulator, ValidationContext, ValidationPolicy,
+        VerificationCheck, VolumesProjection, WalletFeature, WalletKeyRotation, WalletRestored,
+        WalletStore, Witness, CATEGORY_KEY, DEFAULT_HELD_CREDIT_TIMEOUT,
+        DEFAULT_PENDING_DEBIT_TIMEOUT, DEFAULT_RESHARE_GRACE, GENESIS_LINK, LIMITS_LOOSENING_DELAY,
+        REPLAY_REPORT_INTERVAL, SNAPSHOT_VERSION, UPGRADE_STATE_VERSION,
+    };
+    use crdts::{
+        quickcheck::{quickcheck, TestResult},
+        Dot,
+    };
+    use futures::executor::block_on;
+    use rand::Rng;
+    use safe_nd::{
+        AccountId, ClientFullId, DebitAgreementProof, Error, KnownGroupAdded, Money, PublicKey,
+        SafeKey, Signature, SignedTransfer, Transfer, TransferRegistered,
+    };
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+    use threshold_crypto::{PublicKeySet, SecretKey, SecretKeySet, SecretKeyShare};
+
+    macro_rules! hashmap {
+        ($( $key: expr => $val: expr ),*) => {{
+             let mut map = ::std::collections::HashMap::new();
+             $( let _ = map.insert($key, $val); )*
+             map
+        }}
+    }
+
+    // ------------------------------------------------------------------------
+    // ------------------------ Basic Transfer --------------------------------
+    // ------------------------------------------------------------------------
+
+    #[test]
+    fn basic_transfer() {
+        let _ = transfer_between_actors(100, 10, 2, 3, 0, 1);
+    }
+
+    // #[test]
+    // fn reproduce_quickcheck_basic_transfer() {
+    //     let _ = transfer_between_actors(1, 0, 2, 4, 0, 1);
+    // }
+
+    #[test]
+    fn batched_validations_produce_proof() {
+        // --- Arrange ---
+        let account_configs = hashmap![0 => 100, 1 => 0];
+        let (_, mut actors) = get_network(2, 4, account_configs);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+
+        // --- Act ---
+        let validations = sender
+            .replica_group
+            .replicas
+            .iter_mut()
+            .map(|replica| {
+                let validated = replica.validate(transfer.signed_transfer.clone()).unwrap();
+                replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+                validated
+            })
+            .collect();
+        let received: Vec<_> = sender
+            .actor
+            .receive_batch(validations)
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+
+        // --- Assert ---
+        assert!(received.iter().filter(|r| r.proof.is_some()).count() == 1);
+    }
+
+    #[test]
+    fn verifies_full_history() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 4, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let debiting_replicas = PublicKey::Bls(sender.replica_group.id.public_key());
+        let mut account = Account::new(recipient.actor.id());
+
+        // --- Act ---
+        account.append_credit(ReceivedCredit {
+            debit_proof,
+            debiting_replicas,
+        });
+
+        // --- Assert ---
+        let codecs = [Codec::Canonical];
+        assert!(account
+            .verify_full(&[], &[debiting_replicas], &codecs)
+            .is_ok());
+        assert!(account.verify_full(&[], &[], &codecs).is_err());
+        // the credit is not ours to trust as a debit
+        assert!(account
+            .verify_full(&[debiting_replicas], &[], &codecs)
+            .is_err());
+    }
+
+    #[test]
+    fn synchs_from_checkpoint() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 4, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        checkpoint(&mut recipient.replica_group);
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+
+        // --- Act ---
+        let history = recipient.replica_group.replicas[0]
+            .checkpointed_history(&recipient.actor.id())
+            .unwrap()
+            .unwrap();
+        let synched = recipient.actor.synch_from_checkpoint(history).unwrap();
+        recipient
+            .actor
+            .apply(ActorEvent::CheckpointSynched(synched));
+
+        // --- Assert ---
+        assert!(recipient.actor.credits_since(0).len() == 1);
+        assert_balance(recipient, Money::from_nano(100));
+    }
+
+    #[test]
+    fn proves_inclusion_against_checkpoints() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let payment = transfer.id();
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let _ = checkpoint(&mut recipient.replica_group);
+        let replica = &recipient.replica_group.replicas[0];
+
+        // --- Act ---
+        let proven = replica
+            .checkpointed_inclusion(&recipient.actor.id(), &payment)
+            .unwrap()
+            .unwrap();
+        let unknown = replica
+            .checkpointed_inclusion(&recipient.actor.id(), &Dot::new(get_random_pk(), 0))
+            .unwrap();
+        let mut forged = proven.clone();
+        forged.inclusion.transfer.amount = Money::from_nano(1);
+
+        // --- Assert ---
+        assert!(proven.verify(&recipient.replica_group.id).is_ok());
+        assert!(proven.verify(&sender.replica_group.id).is_err());
+        assert!(forged.verify(&recipient.replica_group.id).is_err());
+        assert!(unknown.is_none());
+    }
+
+    #[test]
+    fn rejects_with_signed_reason() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let replica = &mut sender.replica_group.replicas[0];
+        let validated = replica.validate(transfer.signed_transfer.clone()).unwrap();
+        replica.apply(ReplicaEvent::TransferValidated(validated));
+
+        // --- Act ---
+        let result = replica.validate(transfer.signed_transfer);
+
+        // --- Assert ---
+        assert!(matches!(
+            result,
+            Err(TransferError::Rejected(rejected)) if rejected.reason
+                == RejectionReason::OutOfOrder {
+                    expected_counter: 1,
+                    received_counter: 0,
+                }
+                && rejected.verify(&sender.replica_group.id).is_ok()
+        ));
+    }
+
+    #[test]
+    fn fails_forged_debits_as_violations() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let mut forged = transfer.signed_transfer;
+        forged.transfer.amount = Money::from_nano(50);
+
+        // --- Act ---
+        let result = sender.replica_group.replicas[0].validate(forged);
+
+        // --- Assert ---
+        let error = result.unwrap_err();
+        assert!(error.rejection().is_none());
+        assert!(error.kind() == FailureKind::Violation);
+    }
+
+    #[test]
+    fn verifies_credits_of_archived_group() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 4, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        checkpoint(&mut sender.replica_group);
+        let successor = SecretKeySet::random(1, &mut rand::thread_rng()).public_keys();
+        let successor_key = PublicKey::Bls(successor.public_key());
+        let credit = ReceivedCredit {
+            debit_proof,
+            debiting_replicas: PublicKey::Bls(sender.replica_group.id.public_key()),
+        };
+
+        // --- Act ---
+        let shares: Vec<_> = sender
+            .replica_group
+            .replicas
+            .iter()
+            .map(|replica| replica.sign_succession(&successor).unwrap())
+            .collect();
+        let succession =
+            KeySuccession::combine(successor_key, &sender.replica_group.id, &shares).unwrap();
+        let archive = sender.replica_group.replicas[0]
+            .archive(succession)
+            .unwrap();
+
+        // --- Assert ---
+        assert!(archive.verify_credit(&credit, &[successor_key]).is_ok());
+        assert!(archive.verify_credit(&credit, &[get_random_pk()]).is_err());
+    }
+
+    #[test]
+    fn verifies_proofs_signed_by_past_keys() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let successor = SecretKeySet::random(1, &mut rand::thread_rng());
+        let successor_key = PublicKey::Bls(successor.public_keys().public_key());
+        let shares: Vec<_> = sender
+            .replica_group
+            .replicas
+            .iter()
+            .map(|replica| replica.sign_succession(&successor.public_keys()).unwrap())
+            .collect();
+        let succession =
+            KeySuccession::combine(successor_key, &sender.replica_group.id, &shares).unwrap();
+
+        // --- Act ---
+        let replica = &mut sender.replica_group.replicas[0];
+        let rotated = replica
+            .rotate_keys(
+                successor.secret_key_share(0),
+                0,
+                successor.public_keys(),
+                succession.clone(),
+            )
+            .unwrap();
+
+        // --- Assert ---
+        assert!(rotated.previous == sender.replica_group.id);
+        assert!(replica.past_keys() == vec![sender.replica_group.id.clone()]);
+        assert!(replica.verify_key_history().is_ok());
+        // the proof was signed by the previous key set
+        assert!(replica.register(&debit_proof).is_ok());
+        let other = &mut sender.replica_group.replicas[1];
+        assert!(other
+            .rotate_keys(
+                successor.secret_key_share(1),
+                0,
+                successor.public_keys(),
+                succession
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn verifies_debits_of_histories_against_our_own_keys_only() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let key = SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()));
+        let wallet = key.public_id().public_key();
+        let transfer = init_transfer(&mut sender, wallet);
+        let credit = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let _ = propagate_to_crediting_replicas(&credit, &mut recipient.replica_group);
+        let sender_group = PublicKey::Bls(sender.replica_group.id.public_key());
+        let mut account = Account::new(wallet);
+        account.append_credit(ReceivedCredit {
+            debit_proof: credit,
+            debiting_replicas: sender_group,
+        });
+        let mut holder = TestActor {
+            actor: Actor::from_snapshot(
+                account,
+                key,
+                recipient.replica_group.id.clone(),
+                Validator {},
+            ),
+            replica_group: recipient.replica_group.clone(),
+        };
+        let transfer = init_transfer(&mut holder, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut holder).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut holder.replica_group);
+        let successor = SecretKeySet::random(1, &mut rand::thread_rng());
+        let successor_key = PublicKey::Bls(successor.public_keys().public_key());
+        let shares: Vec<_> = holder
+            .replica_group
+            .replicas
+            .iter()
+            .map(|replica| replica.sign_succession(&successor.public_keys()).unwrap())
+            .collect();
+        let succession =
+            KeySuccession::combine(successor_key, &holder.replica_group.id, &shares).unwrap();
+
+        // --- Act ---
+        let replica = &mut holder.replica_group.replicas[0];
+        let _ = replica
+            .rotate_keys(
+                successor.secret_key_share(0),
+                0,
+                successor.public_keys(),
+                succession,
+            )
+            .unwrap();
+
+        // --- Assert ---
+        // the debit was signed by our previous key set
+        assert!(replica.verify_history(&wallet, &[], &[]).is_ok());
+        // and the keys of other groups are trusted for credits only
+        assert!(replica
+            .query_history(&wallet, |history| history.verify_full(
+                &[sender_group],
+                &[sender_group],
+                &[Codec::Canonical]
+            ))
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn reshares_keys_within_a_grace_window() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let incoming = SecretKeySet::random(1, &mut rand::thread_rng());
+        let incoming_key = PublicKey::Bls(incoming.public_keys().public_key());
+        let shares: Vec<_> = sender
+            .replica_group
+            .replicas
+            .iter()
+            .map(|replica| replica.sign_succession(&incoming.public_keys()).unwrap())
+            .collect();
+        let succession =
+            KeySuccession::combine(incoming_key, &sender.replica_group.id, &shares).unwrap();
+        let data = SignableProof::new(&debit_proof.signed_transfer).to_bytes();
+        let incoming_proof = DebitAgreementProof {
+            signed_transfer: debit_proof.signed_transfer.clone(),
+            debiting_replicas_sig: Signature::Bls(incoming.secret_key().sign(data)),
+        };
+        let mut replica = sender.replica_group.replicas[0]
+            .clone()
+            .with_time_source(source.clone());
+        let lapsed = replica.clone();
+        assert!(replica.register(&incoming_proof).is_err());
+
+        // --- Act ---
+        let started = replica
+            .begin_reshare(
+                incoming.secret_key_share(0),
+                0,
+                incoming.public_keys(),
+                succession.clone(),
+            )
+            .unwrap();
+        let during = (
+            replica.register(&debit_proof).is_ok(),
+            replica.register(&incoming_proof).is_ok(),
+            replica.balance(&sender.actor.id()).is_some(),
+        );
+        let rotated = replica.complete_reshare().unwrap();
+
+        // --- Assert ---
+        assert!(started.outgoing == sender.replica_group.id);
+        assert!(started.grace_until == 1_000 + DEFAULT_RESHARE_GRACE);
+        assert!(during == (true, true, true));
+        assert!(rotated.replicas == incoming.public_keys());
+        assert!(replica.reshare().is_none());
+        assert!(replica.past_keys() == vec![sender.replica_group.id.clone()]);
+        // both the outgoing and the incoming keys are ours now
+        assert!(replica.register(&debit_proof).is_ok());
+        assert!(replica.register(&incoming_proof).is_ok());
+        // a reshare not completed within the grace window lapses
+        let mut lapsed = lapsed.with_reshare_grace(60);
+        let _ = lapsed
+            .begin_reshare(
+                incoming.secret_key_share(0),
+                0,
+                incoming.public_keys(),
+                succession,
+            )
+            .unwrap();
+        source.advance(60);
+        assert!(lapsed.reshare().is_none());
+        assert!(lapsed.register(&incoming_proof).is_err());
+        assert!(lapsed.complete_reshare().is_err());
+    }
+
+    #[test]
+    fn hands_wallets_over_to_the_sibling_on_a_split() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let wallet = get_random_pk();
+        let transfer = init_transfer(&mut sender, wallet);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let mut splitting = recipient.replica_group.replicas[0].clone();
+        let mut sibling = sender.replica_group.replicas[0].clone();
+        let mut unknown = sibling.clone();
+
+        // --- Act ---
+        let (retained, packet) = splitting.split(|id| *id != wallet).unwrap();
+        let absorbed = sibling.absorb(packet.clone()).unwrap();
+
+        // --- Assert ---
+        assert!(retained.contains(&recipient.actor.id()));
+        assert!(!retained.contains(&wallet));
+        assert!(packet.wallets() == vec![wallet]);
+        assert!(packet.group == recipient.replica_group.id);
+        assert!(absorbed.packet == packet);
+        assert!(splitting.balance(&wallet).is_none());
+        assert!(splitting.balance(&recipient.actor.id()).is_some());
+        assert!(sibling.balance(&wallet) == Some(debit_proof.amount()));
+        assert!(sibling.verify_history(&wallet, &[], &[]).is_ok());
+        // a wallet is taken over once
+        assert!(sibling.absorb(packet.clone()).is_err());
+        // and only from a group we know of
+        let mut forged = packet;
+        forged.group = SecretKeySet::random(1, &mut rand::thread_rng()).public_keys();
+        assert!(unknown.absorb(forged).is_err());
+        assert!(unknown.balance(&wallet).is_none());
+    }
+
+    #[test]
+    fn loads_wallets_evicted_to_the_store_on_demand() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let wallet = get_random_pk();
+        let store = WalletStore::new(MemoryWalletBackend::new());
+        let mut replica = recipient.replica_group.replicas[0]
+            .clone()
+            .with_wallet_store(store, 1);
+        let transfer = init_transfer(&mut sender, wallet);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+
+        // --- Act ---
+        let propagated = replica.receive_propagated(&debit_proof).unwrap();
+        replica.apply(ReplicaEvent::TransferPropagated(propagated));
+
+        // --- Assert ---
+        assert!(replica.wallets_in_memory() == 1);
+        assert!(replica.balance(&wallet) == Some(debit_proof.amount()));
+        assert!(replica.balance(&recipient.actor.id()).is_some());
+        assert!(replica.wallets_iter().count() == 2);
+        assert!(replica.verify_history(&wallet, &[], &[]).is_ok());
+        assert!(replica.to_snapshot().is_ok());
+        assert!(replica.wallets_in_memory() == 1);
+    }
+
+    #[test]
+    fn lists_and_summarizes_held_wallets() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        let mut replica = recipient.replica_group.replicas[0]
+            .clone()
+            .with_time_source(source.clone());
+        let wallet = get_random_pk();
+        let transfer = init_transfer(&mut sender, wallet);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+
+        // --- Act ---
+        source.advance(500);
+        let propagated = replica.receive_propagated(&debit_proof).unwrap();
+        replica.apply(ReplicaEvent::TransferPropagated(propagated));
+        let first_page = replica.wallet_ids(0, 1);
+        let second_page = replica.wallet_ids(1, 1);
+
+        // --- Assert ---
+        let mut all = first_page.clone();
+        all.extend(second_page);
+        let mut expected = vec![wallet, recipient.actor.id()];
+        expected.sort();
+        assert!(replica.wallet_count() == 2);
+        assert!(first_page.len() == 1);
+        assert!(all == expected);
+        assert!(replica.wallet_ids(2, 1).is_empty());
+        let summary = replica.wallet_summary(&wallet).unwrap();
+        assert!(summary.balance == debit_proof.amount());
+        assert!(summary.credit_count == 1 && summary.debit_count == 0);
+        assert!(summary.last_activity.map(|applied| applied.time) == Some(1_500));
+        // the initial state of a wallet was not applied by us
+        let initial = replica.wallet_summary(&recipient.actor.id()).unwrap();
+        assert!(initial.last_activity.is_none());
+        assert!(replica.wallet_summary(&get_random_pk()).is_none());
+    }
+
+    #[test]
+    fn registers_retransmitted_debits_once() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let replica = &sender.replica_group.replicas[0];
+
+        // --- Act ---
+        let retransmitted = replica.register(&debit_proof);
+        let handled = replica.handle_cmd(TransferCmd::RegisterTransfer(debit_proof));
+
+        // --- Assert ---
+        assert!(retransmitted.unwrap().is_none());
+        assert!(handled.unwrap().is_none());
+        assert!(replica.balance(&sender.actor.id()) == Some(Money::zero()));
+    }
+
+    #[test]
+    fn refuses_events_of_the_group_that_do_not_apply() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let signed_transfer = transfer.signed_transfer;
+        let wallet = sender.actor.id();
+        let replica = &mut sender.replica_group.replicas[0];
+        let attempted = DoubleSpendAttempted {
+            first: signed_transfer.clone(),
+            second: signed_transfer,
+        };
+
+        // --- Act ---
+        let restored =
+            replica.apply_checked(ReplicaEvent::WalletRestored(WalletRestored { wallet }));
+        let lifted = replica.apply_checked(ReplicaEvent::QuarantineLifted(QuarantineLifted {
+            account_id: wallet,
+        }));
+        let double_spent = replica.apply_checked(ReplicaEvent::DoubleSpendAttempted(attempted));
+
+        // --- Assert ---
+        assert!(restored.is_err());
+        assert!(lifted.is_err());
+        assert!(double_spent.is_err());
+    }
+
+    #[test]
+    fn does_not_take_a_transfer_signed_again_for_a_double_spend() {
+        // --- Arrange ---
+        let client_full_id = ClientFullId::new_ed25519(&mut rand::thread_rng());
+        let owned = setup_owned_account(100, 0, client_full_id.clone());
+        let group_keys = setup_replica_group_keys(1, 3);
+        let mut replica_groups = setup_replica_groups(group_keys, vec![owned.clone()]);
+        let mut sender = setup_actor(owned, &mut replica_groups);
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let signed_transfer = transfer.signed_transfer;
+        let source = Arc::new(ManualTimeSource::new(1_000, 10));
+        let migration = CodecMigration::new(Codec::Canonical, Codec::Bincode, 10, 20).unwrap();
+        let mut replica = sender.replica_group.replicas[0]
+            .clone()
+            .with_time_source(source)
+            .with_codec_migration(migration);
+        let validated = replica.validate(signed_transfer.clone()).unwrap();
+        replica.apply(ReplicaEvent::TransferValidated(validated));
+        let legacy = bincode::serialize(&signed_transfer.transfer).unwrap();
+        let signed_again = SignedTransfer {
+            transfer: signed_transfer.transfer.clone(),
+            actor_signature: client_full_id.sign(&legacy),
+        };
+
+        // --- Act ---
+        let detected = replica.detect_double_spend(&signed_again);
+
+        // --- Assert ---
+        assert!(signed_again != signed_transfer);
+        assert!(detected.unwrap().is_none());
+        assert!(DoubleSpendAttempted {
+            first: signed_transfer,
+            second: signed_again,
+        }
+        .verify()
+        .is_err());
+    }
+
+    #[test]
+    fn signs_and_verifies_in_the_codecs_of_the_migration() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let signed_transfer = transfer.signed_transfer;
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        let migration = CodecMigration::new(Codec::Canonical, Codec::Bincode, 10, 20).unwrap();
+        let replica = sender.replica_group.replicas[0]
+            .clone()
+            .with_time_source(source.clone())
+            .with_codec_migration(migration);
+        let (_, index) = sender.replica_group.keys[0];
+        let key_share = sender.replica_group.id.public_key_share(index);
+        let migrating = sender
+            .actor
+            .clone()
+            .with_time_source(source.clone())
+            .with_codec_migration(migration);
+
+        // --- Act ---
+        let before = replica.validate(signed_transfer.clone()).unwrap();
+        source.set_height(10);
+        let switched = replica.validate(signed_transfer.clone()).unwrap();
+        let received = migrating.receive(switched.clone());
+        let refused = sender.actor.receive(switched.clone());
+        source.set_height(20);
+        let retired = replica.validate(signed_transfer.clone());
+
+        // --- Assert ---
+        let canonical = SignableProof::new(&signed_transfer).to_bytes();
+        let legacy = bincode::serialize(&signed_transfer).unwrap();
+        assert!(key_share.verify(&before.replica_signature.share, &canonical));
+        assert!(key_share.verify(&switched.replica_signature.share, &legacy));
+        assert!(received.is_ok());
+        assert!(refused.is_err());
+        assert!(retired.is_err());
+    }
+
+    #[test]
+    fn replays_untrusted_events_strictly() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 4, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let replica = &recipient.replica_group.replicas[0];
+        let propagated = replica.receive_propagated(&debit_proof).unwrap();
+        let mut forged = propagated.clone();
+        forged.debit_proof.signed_transfer.transfer.amount = Money::from_nano(1_000);
+        let event = ReplicaEvent::TransferPropagated(propagated);
+        let replay = |events: Vec<ReplicaEvent>| {
+            let mut replica = replica.clone();
+            replica
+                .replay_strict(events.into_iter().map(Ok))
+                .map(|_| replica)
+        };
+
+        // --- Act ---
+        let replayed = replay(vec![event.clone()]).unwrap();
+        let repeated = replay(vec![event.clone(), event]).err().unwrap();
+        let tampered = replay(vec![ReplicaEvent::TransferPropagated(forged)])
+            .err()
+            .unwrap();
+
+        // --- Assert ---
+        assert!(replayed.balance(&recipient.actor.id()) == Some(Money::from_nano(100)));
+        assert!(repeated.index == 1);
+        assert!(repeated.event == Some("TransferPropagated"));
+        assert!(repeated.wallet == Some(recipient.actor.id()));
+        assert!(repeated.failure == ReplayFailure::Failed(Error::TransferIdExists));
+        assert!(tampered.index == 0);
+        assert!(!tampered.is_cancelled());
+    }
+
+    #[test]
+    fn exports_events_with_chained_hashes() {
+        // --- Arrange ---
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let store = EventStore::new(MemoryEventStore::new());
+        let mut replica = Replica::from_event_store(
+            keys.secret_key_share(0),
+            0,
+            keys.public_keys(),
+            store.clone(),
+        )
+        .unwrap();
+        for _ in 0..3 {
+            let group = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+            replica
+                .persist_and_apply(ReplicaEvent::KnownGroupAdded(KnownGroupAdded { group }))
+                .unwrap();
+        }
+        let unstored =
+            Replica::from_history(keys.secret_key_share(0), 0, keys.public_keys(), vec![]);
+
+        // --- Act ---
+        let exported = replica.events_since(0).unwrap();
+        let resumed = replica.events_since(2).unwrap();
+
+        // --- Assert ---
+        assert!(exported.len() == 3);
+        assert!(exported[0].verify(&GENESIS_LINK).is_ok());
+        assert!(exported[2].hash == store.tail().unwrap().1);
+        assert!(resumed.len() == 1 && resumed[0].sequence == 2);
+        assert!(resumed[0].verify(&exported[1].hash).is_ok());
+        assert!(unstored.events_since(0).is_err());
+    }
+
+    #[test]
+    fn states_wallets_in_the_order_applied() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+
+        // --- Act ---
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let statement = sender.replica_group.replicas[0]
+            .statement(&sender.actor.id())
+            .unwrap();
+
+        // --- Assert ---
+        let kinds: Vec<_> = statement.lines.iter().map(|l| l.kind).collect();
+        assert!(kinds == vec![StatementLineKind::Credit, StatementLineKind::Debit]);
+        assert!(statement.lines[1].counterparty == Some(recipient.actor.id()));
+        assert!(statement.lines[1].balance == Money::zero());
+        assert!(statement.closing_balance == Money::zero());
+        assert!(statement.to_csv().lines().count() == 3);
+        let unknown = sender.replica_group.replicas[0].statement(&get_random_pk());
+        assert!(unknown.is_none());
+    }
+
+    #[test]
+    fn queries_histories_by_time_applied() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        recipient.replica_group.replicas = recipient
+            .replica_group
+            .replicas
+            .drain(..)
+            .map(|replica| replica.with_time_source(source.clone()))
+            .collect();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+
+        // --- Act ---
+        source.advance(500);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+
+        // --- Assert ---
+        let replica = &recipient.replica_group.replicas[0];
+        let wallet = recipient.actor.id();
+        let applied_at = replica.applied_at(&wallet, &debit_proof.id()).unwrap();
+        assert!(applied_at.time == 1_500);
+        assert!(applied_at.epoch == replica.epoch());
+        let credits = replica.credits_between(&wallet, 1_500, 1_501).unwrap();
+        assert!(credits == vec![debit_proof.signed_transfer.transfer.clone()]);
+        assert!(replica.credits_between(&wallet, 1_000, 1_500) == Some(vec![]));
+        assert!(replica.debits_between(&wallet, 0, 2_000) == Some(vec![]));
+        assert!(replica
+            .credits_between(&get_random_pk(), 0, 2_000)
+            .is_none());
+    }
+
+    #[test]
+    fn handles_cmds_and_queries_over_the_wire() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let wallet = sender.actor.id();
+        let transfer = sender
+            .actor
+            .transfer(Money::from_nano(10), get_random_pk())
+            .unwrap();
+        sender
+            .actor
+            .apply(ActorEvent::TransferInitiated(transfer.clone()));
+        let over_the_wire = |cmd: TransferCmd| -> TransferCmd {
+            SizeLimits::default()
+                .cmd(&bincode::serialize(&cmd).unwrap())
+                .unwrap()
+        };
+
+        // --- Act ---
+        let mut proof = None;
+        for replica in &mut sender.replica_group.replicas {
+            let cmd = over_the_wire(TransferCmd::ValidateTransfer(
+                transfer.signed_transfer.clone(),
+            ));
+            let event = replica.handle_cmd(cmd).unwrap().unwrap();
+            replica.apply_checked(event.clone()).unwrap();
+            if let (None, ReplicaEvent::TransferValidated(validated)) = (&proof, event) {
+                let received = sender.actor.receive(validated).unwrap();
+                proof = received.proof.clone();
+                sender
+                    .actor
+                    .apply(ActorEvent::TransferValidationReceived(received));
+            }
+        }
+        let cmd = over_the_wire(TransferCmd::RegisterTransfer(proof.unwrap()));
+        for replica in &mut sender.replica_group.replicas {
+            let event = replica.handle_cmd(cmd.clone()).unwrap().unwrap();
+            replica.apply_checked(event).unwrap();
+        }
+        let replica = &sender.replica_group.replicas[0];
+        let balance = replica.handle_query(&TransferQuery::GetBalance(wallet));
+        let debits = replica.handle_query(&TransferQuery::GetDebits { wallet, since: 0 });
+
+        // --- Assert ---
+        assert!(
+            balance
+                == TransferQueryResponse::GetBalance(Some(QueryResponse::Found(Money::from_nano(
+                    90
+                ))))
+        );
+        assert!(
+            debits
+                == TransferQueryResponse::GetDebits(Some(QueryResponse::Found(vec![
+                    transfer.signed_transfer.transfer
+                ])))
+        );
+    }
+
+    #[test]
+    fn handles_actor_cmds_over_the_wire() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let over_the_wire = |cmd: ActorCmd| -> ActorCmd {
+            decode(
+                &bincode::serialize(&cmd).unwrap(),
+                SizeLimits::default().cmd,
+            )
+            .unwrap()
+        };
+
+        // --- Act ---
+        let initiated = sender
+            .actor
+            .handle_cmd(over_the_wire(ActorCmd::Transfer {
+                amount: Money::from_nano(10),
+                to: get_random_pk(),
+            }))
+            .unwrap()
+            .unwrap();
+        sender.actor.apply(initiated.clone());
+        let signed_transfer = match initiated {
+            ActorEvent::TransferInitiated(e) => e.signed_transfer,
+            _ => panic!("Expected TransferInitiated"),
+        };
+        let mut proof = None;
+        for replica in &mut sender.replica_group.replicas {
+            let validated = replica.validate(signed_transfer.clone()).unwrap();
+            replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+            if proof.is_none() {
+                let cmd = over_the_wire(ActorCmd::ReceiveValidation(validated));
+                let event = sender.actor.handle_cmd(cmd).unwrap().unwrap();
+                if let ActorEvent::TransferValidationReceived(received) = &event {
+                    proof = received.proof.clone();
+                }
+                sender.actor.apply(event);
+            }
+        }
+        let cmd = over_the_wire(ActorCmd::RegisterTransfer(proof.unwrap()));
+        let registered = sender.actor.handle_cmd(cmd).unwrap().unwrap();
+        sender.actor.apply(registered.clone());
+
+        // --- Assert ---
+        assert!(matches!(
+            registered,
+            ActorEvent::TransferRegistrationSent(_)
+        ));
+        assert!(sender.actor.balance() == Money::from_nano(90));
+        assert!(sender
+            .actor
+            .handle_cmd(over_the_wire(ActorCmd::Acknowledge(u64::MAX)))
+            .is_err());
+    }
+
+    #[test]
+    fn refuses_cmds_not_decoded_within_size_limits() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let replica = sender.replica_group.replicas[0].clone();
+        let bytes =
+            bincode::serialize(&TransferCmd::ValidateTransfer(transfer.signed_transfer)).unwrap();
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let limits = SizeLimits {
+            cmd: 64,
+            ..Default::default()
+        };
+
+        // --- Act ---
+        let handled = replica.handle_cmd_bytes(&bytes);
+        let malformed = replica.handle_cmd_bytes(&trailing);
+        let too_large = replica
+            .clone()
+            .with_size_limits(limits)
+            .handle_cmd_bytes(&bytes);
+        // a cmd of a variant that does not exist, claiming a payload of u64::MAX bytes
+        let mut crafted = u32::max_value().to_le_bytes().to_vec();
+        crafted.extend(&u64::max_value().to_le_bytes());
+        let unknown = replica.handle_cmd_bytes(&crafted);
+
+        // --- Assert ---
+        assert!(matches!(
+            handled,
+            Ok(Some(ReplicaEvent::TransferValidated(_)))
+        ));
+        assert!(matches!(
+            malformed,
+            Err(TransferError::Malformed(DecodeError::Malformed(_)))
+        ));
+        assert!(
+            too_large
+                == Err(TransferError::Malformed(DecodeError::TooLarge {
+                    limit: 64
+                }))
+        );
+        assert!(unknown.is_err());
+        assert!(too_large.unwrap_err().kind() == FailureKind::Violation);
+    }
+
+    #[test]
+    fn reports_the_progress_of_validations() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        sender.actor = sender.actor.clone().with_time_source(source.clone());
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let replicas = &sender.replica_group.replicas;
+        let validate = |index: usize| {
+            replicas[index]
+                .validate(transfer.signed_transfer.clone())
+                .unwrap()
+        };
+        let first = validate(0);
+        let mut forged = validate(2);
+        forged.replica_signature.share = first.replica_signature.share.clone();
+
+        // --- Act ---
+        let received = sender.actor.receive(first).unwrap();
+        sender
+            .actor
+            .apply(ActorEvent::TransferValidationReceived(received));
+        source.advance(30);
+        let rejected = sender.actor.receive(forged);
+        if let Err(TransferError::InvalidShare(rejected)) = rejected.clone() {
+            sender
+                .actor
+                .apply(ActorEvent::ValidationShareRejected(*rejected));
+        }
+        let progress = sender.actor.validation_progress(&transfer.id());
+
+        // --- Assert ---
+        assert!(matches!(rejected, Err(TransferError::InvalidShare(_))));
+        assert!(progress.len() == 1);
+        let progress = &progress[0];
+        assert!(progress.received.iter().copied().collect::<Vec<_>>() == vec![0]);
+        assert!(progress.rejected.iter().copied().collect::<Vec<_>>() == vec![2]);
+        assert!(progress.missing(4) == vec![1, 2, 3]);
+        assert!(progress.remaining() == progress.required - 1);
+        assert!(progress.elapsed == Some(30));
+        assert!(!progress.agreed);
+        assert!(sender
+            .actor
+            .validation_progress(&Dot::new(get_random_pk(), 0))
+            .is_empty());
+    }
+
+    #[test]
+    fn reports_the_status_of_transfers_through_their_lifecycle() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let id = transfer.id();
+        let status = |replica: &Replica| replica.transfer_status(&id);
+
+        // --- Act ---
+        let before = status(&sender.replica_group.replicas[0]);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let validated = status(&sender.replica_group.replicas[0]);
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let registered = status(&sender.replica_group.replicas[0]);
+        let crediting = &mut find_group(1, &mut groups).unwrap().replicas[0];
+        let unpropagated = status(crediting);
+        let propagated = crediting.receive_propagated(&debit_proof).unwrap();
+        crediting.apply(ReplicaEvent::TransferPropagated(propagated));
+        let response = crediting.handle_query(&TransferQuery::GetTransferStatus(id));
+
+        // --- Assert ---
+        assert!(before == TransferLifecycle::Unknown);
+        assert!(validated == TransferLifecycle::Pending);
+        assert!(registered == TransferLifecycle::Registered);
+        assert!(unpropagated == TransferLifecycle::Unknown);
+        assert!(
+            response == TransferQueryResponse::GetTransferStatus(TransferLifecycle::Propagated)
+        );
+    }
+
+    #[test]
+    fn aborts_transfers_short_of_quorum() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let aborted = init_transfer(&mut sender, get_random_pk());
+        let validate = |replica: &mut Replica, transfer: &TransferInitiated| {
+            let validated = replica.validate(transfer.signed_transfer.clone()).unwrap();
+            replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+            validated
+        };
+        let first = validate(&mut sender.replica_group.replicas[0], &aborted);
+        let late = validate(&mut sender.replica_group.replicas[1], &aborted);
+        let received = sender.actor.receive(first).unwrap();
+        sender
+            .actor
+            .apply(ActorEvent::TransferValidationReceived(received));
+
+        // --- Act ---
+        let abort = sender.actor.abort(aborted.id()).unwrap();
+        let release = abort.release.clone();
+        sender.actor.apply(ActorEvent::TransferAborted(abort));
+        let refused = sender.actor.receive(late);
+        let retried = init_transfer(&mut sender, get_random_pk());
+        let unreleased = sender.replica_group.replicas[0].validate(retried.signed_transfer.clone());
+        let mut released = vec![];
+        for replica in &mut sender.replica_group.replicas[..2] {
+            let event = replica
+                .handle_cmd(TransferCmd::ReleaseDebit(release.clone()))
+                .unwrap()
+                .unwrap();
+            replica.apply_checked(event.clone()).unwrap();
+            released.push(event);
+        }
+        let mut proof = None;
+        for replica in &mut sender.replica_group.replicas[..3] {
+            let validated = validate(replica, &retried);
+            let received = sender.actor.receive(validated).unwrap();
+            proof = proof.or(received.proof.clone());
+            sender
+                .actor
+                .apply(ActorEvent::TransferValidationReceived(received));
+        }
+
+        // --- Assert ---
+        assert!(refused.is_err());
+        assert!(retried.id() == aborted.id());
+        assert!(unreleased.is_err());
+        assert!(released
+            .iter()
+            .all(|event| matches!(event, ReplicaEvent::DebitReleased(_))));
+        assert!(proof.unwrap().signed_transfer == retried.signed_transfer);
+        let pending = sender.actor.outbox().pending();
+        assert!(pending
+            .iter()
+            .all(|entry| entry.item != OutboxItem::Transfer(aborted.signed_transfer.clone())));
+        assert!(pending
+            .iter()
+            .any(|entry| entry.item == OutboxItem::Release(release.clone())));
+    }
+
+    #[test]
+    fn sequences_queued_transfers() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        for amount in vec![30, 40, 50] {
+            let queued = sender
+                .actor
+                .queue_transfer(Money::from_nano(amount), get_random_pk())
+                .unwrap();
+            sender.actor.apply(ActorEvent::TransferQueued(queued));
+        }
+        let queued = sender.actor.transfer_status(1);
+
+        // --- Act ---
+        loop {
+            match sender.actor.dispatch() {
+                Ok(Some(initiated)) => {
+                    sender
+                        .actor
+                        .apply(ActorEvent::TransferInitiated(initiated.clone()));
+                    let proof = validate_at_sender_replicas(initiated, &mut sender).unwrap();
+                    register_at_debiting_replicas(&proof, &mut sender.replica_group);
+                }
+                Ok(None) => break,
+                Err(error) => {
+                    let sequence = sender.actor.transfer_queue().queued()[0].sequence;
+                    let failed = sender.actor.fail_queued(sequence, &error).unwrap();
+                    sender.actor.apply(ActorEvent::QueuedTransferFailed(failed));
+                }
+            }
+        }
+
+        // --- Assert ---
+        assert!(queued == Some(TransferStatus::Pending));
+        assert!(sender.actor.transfer_status(0) == Some(TransferStatus::Registered));
+        assert!(sender.actor.transfer_status(1) == Some(TransferStatus::Registered));
+        assert!(matches!(
+            sender.actor.transfer_status(2),
+            Some(TransferStatus::Failed(_))
+        ));
+        assert!(sender.actor.transfer_queue().is_empty());
+        assert!(sender.actor.balance() == Money::from_nano(30));
+        let replica = &sender.replica_group.replicas[0];
+        assert!(replica.balance(&sender.actor.id()) == Some(Money::from_nano(30)));
+    }
+
+    #[test]
+    fn expires_debits_left_pending() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        sender.replica_group.replicas = sender
+            .replica_group
+            .replicas
+            .drain(..)
+            .map(|replica| replica.with_time_source(source.clone()))
+            .collect();
+        let wallet = sender.actor.id();
+        let abandoned = sender
+            .actor
+            .transfer(Money::from_nano(10), get_random_pk())
+            .unwrap();
+        let retried = sender
+            .actor
+            .transfer(Money::from_nano(20), get_random_pk())
+            .unwrap();
+        sender
+            .actor
+            .apply(ActorEvent::TransferInitiated(abandoned.clone()));
+        let mut proof = None;
+        for replica in &mut sender.replica_group.replicas {
+            let validated = replica.validate(abandoned.signed_transfer.clone()).unwrap();
+            replica
+                .apply_checked(ReplicaEvent::TransferValidated(validated.clone()))
+                .unwrap();
+            proof = proof.or(sender.actor.receive(validated).unwrap().proof);
+        }
+        let proof = proof.unwrap();
+        let pending = sender.replica_group.replicas[0].pending_debit(&wallet);
+
+        let release = sender.actor.release(abandoned.id()).unwrap();
+        let mut forged = release.clone();
+        forged.actor_signature = sender.actor.release(retried.id()).unwrap().actor_signature;
+
+        // --- Act ---
+        let early = sender.replica_group.replicas[0].expire_pending_debit(release.clone());
+        source.advance(DEFAULT_PENDING_DEBIT_TIMEOUT);
+        let overdue = sender.replica_group.replicas[0].overdue_debits();
+        let unreleased = sender.replica_group.replicas[0].expire_pending_debit(forged);
+        for replica in &mut sender.replica_group.replicas {
+            let expired = replica.expire_pending_debit(release.clone()).unwrap();
+            replica
+                .apply_checked(ReplicaEvent::PendingDebitExpired(expired))
+                .unwrap();
+        }
+        let replica = &sender.replica_group.replicas[0];
+        let registered = replica.register(&proof);
+        let revalidated = replica.validate(retried.signed_transfer);
+
+        // --- Assert ---
+        let pending = pending.unwrap();
+        assert!(pending.counter == 0);
+        assert!(pending.since == Some(1_000));
+        assert!(pending.signed_transfer == Some(abandoned.signed_transfer));
+        assert!(early.is_err());
+        assert!(overdue == vec![release.transfer_id]);
+        assert!(unreleased.is_err());
+        assert!(replica.pending_debit(&wallet).is_none());
+        assert!(replica.chart_state(&wallet) == ChartState::Idle);
+        let error = registered.unwrap_err();
+        assert!(error.rejection() == Some(&RejectionReason::DebitExpired { counter: 0 }));
+        assert!(error.kind() == FailureKind::Rejected);
+        assert!(revalidated.is_ok());
+    }
+
+    #[test]
+    fn limits_the_rate_of_validations() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        let limit = RateLimit {
+            burst: 2,
+            refill_secs: 10,
+        };
+        sender.replica_group.replicas = sender
+            .replica_group
+            .replicas
+            .drain(..)
+            .map(|replica| {
+                replica
+                    .with_time_source(source.clone())
+                    .with_rate_limit(limit)
+            })
+            .collect();
+        let wallet = sender.actor.id();
+        let transfer = sender
+            .actor
+            .transfer(Money::from_nano(10), get_random_pk())
+            .unwrap();
+        let replica = &sender.replica_group.replicas[0];
+
+        // --- Act ---
+        let burst: Vec<_> = (0..2)
+            .map(|_| replica.validate(transfer.signed_transfer.clone()))
+            .collect();
+        let limited = replica.validate(transfer.signed_transfer.clone());
+        let state = replica.rate_limit_state(&wallet);
+        source.advance(10);
+        let refilled = replica.validate(transfer.signed_transfer);
+
+        // --- Assert ---
+        assert!(burst.iter().all(|validated| validated.is_ok()));
+        assert!(matches!(
+            limited,
+            Err(TransferError::RateLimited { retry_after: 10 })
+        ));
+        assert!(state.map(|bucket| bucket.tokens) == Some(0));
+        assert!(refilled.is_ok());
+    }
+
+    #[test]
+    fn takes_no_token_for_replayed_debits() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        let limit = RateLimit {
+            burst: 3,
+            refill_secs: 10,
+        };
+        sender.replica_group.replicas = sender
+            .replica_group
+            .replicas
+            .drain(..)
+            .map(|replica| {
+                replica
+                    .with_time_source(source.clone())
+                    .with_rate_limit(limit)
+            })
+            .collect();
+        let wallet = sender.actor.id();
+        let mut transfers = vec![];
+        for _ in 0..2 {
+            let transfer = sender
+                .actor
+                .transfer(Money::from_nano(10), get_random_pk())
+                .unwrap();
+            sender
+                .actor
+                .apply(ActorEvent::TransferInitiated(transfer.clone()));
+            let debit_proof = validate_at_sender_replicas(transfer.clone(), &mut sender).unwrap();
+            register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+            transfers.push(transfer.signed_transfer);
+        }
+        let replica = &sender.replica_group.replicas[0];
+        let before = replica.rate_limit_state(&wallet);
+
+        // --- Act ---
+        let replayed: Vec<_> = (0..3)
+            .map(|_| replica.validate(transfers[0].clone()))
+            .collect();
+
+        // --- Assert ---
+        assert!(before.map(|bucket| bucket.tokens) == Some(1));
+        assert!(replayed.iter().all(|validated| validated.is_err()));
+        assert!(!replayed
+            .iter()
+            .any(|validated| matches!(validated, Err(TransferError::RateLimited { .. }))));
+        assert!(replica.rate_limit_state(&wallet) == before);
+    }
+
+    #[test]
+    fn loosens_spending_limits_only_after_a_delay() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        sender.replica_group.replicas = sender
+            .replica_group
+            .replicas
+            .drain(..)
+            .map(|replica| replica.with_time_source(source.clone()))
+            .collect();
+        let wallet = sender.actor.id();
+        let strict = SpendingLimits {
+            per_debit: Some(Money::from_nano(10)),
+            per_window: None,
+        };
+        let set_limits = |sender: &mut TestActor, limits: SpendingLimits| {
+            let sequence = sender.replica_group.replicas[0].limits_sequence(&wallet);
+            let signed = sender.actor.limit_spending(limits, sequence).unwrap();
+            for replica in &mut sender.replica_group.replicas {
+                let set = replica.set_spending_limits(signed.clone()).unwrap();
+                replica
+                    .apply_checked(ReplicaEvent::SpendingLimitsSet(set))
+                    .unwrap();
+            }
+        };
+        let transfer = sender
+            .actor
+            .transfer(Money::from_nano(20), get_random_pk())
+            .unwrap();
+
+        // --- Act ---
+        set_limits(&mut sender, strict);
+        let limited = sender.replica_group.replicas[0].validate(transfer.signed_transfer.clone());
+        set_limits(&mut sender, SpendingLimits::default());
+        let waiting = sender.replica_group.replicas[0].validate(transfer.signed_transfer.clone());
+        source.advance(LIMITS_LOOSENING_DELAY);
+        let lifted = sender.replica_group.replicas[0].validate(transfer.signed_transfer);
+
+        // --- Assert ---
+        assert!(matches!(
+            limited,
+            Err(TransferError::Rejected(rejected)) if rejected.reason
+                == RejectionReason::SpendingLimit {
+                    limit: Money::from_nano(10),
+                    requested: Money::from_nano(20),
+                }
+        ));
+        assert!(waiting.is_err());
+        assert!(lifted.is_ok());
+        let replica = &sender.replica_group.replicas[0];
+        assert!(replica.spending_limits(&wallet) == Some(&SpendingLimits::default()));
+        assert!(replica.limits_sequence(&wallet) == 2);
+    }
+
+    #[test]
+    fn detects_misconfigured_groups() {
+        // --- Arrange ---
+        let (groups, _) = get_network(1, 3, hashmap![0 => 0]);
+        let replica = &groups[0].replicas[0];
+        let keys = SecretKeySet::random(1, &mut rand::thread_rng());
+        let misplaced = Replica::from_snapshot(
+            keys.secret_key_share(0),
+            1,
+            keys.public_keys(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+
+        // --- Act ---
+        let majority = replica.check_quorum(3, QuorumRule::Majority);
+        let supermajority = replica.check_quorum(3, QuorumRule::Supermajority);
+
+        // --- Assert ---
+        assert!(majority.is_ok());
+        assert!(supermajority.is_err());
+        assert!(replica.check_quorum(1, QuorumRule::Majority).is_err());
+        assert!(misplaced.check_quorum(3, QuorumRule::Majority).is_err());
+        assert!(shares_needed(&groups[0].id) == 2);
+    }
+
+    #[test]
+    fn refunds_no_more_than_was_paid() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut customer = actors.remove(&0).unwrap();
+        let mut merchant = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut customer, merchant.actor.id());
+        let payment = transfer.id();
+        let debit_proof = validate_at_sender_replicas(transfer, &mut customer).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut customer.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut merchant.replica_group);
+        synch(&mut merchant, events);
+
+        // --- Act ---
+        let refund = merchant
+            .actor
+            .refund(payment, Money::from_nano(60))
+            .unwrap();
+        let mut accumulator = ValidationAccumulator::new(
+            refund.signed_transfer.clone(),
+            merchant.replica_group.id.clone(),
+        );
+        let mut refund_proof = None;
+        for replica in &mut merchant.replica_group.replicas {
+            let validated = replica.validate_refund(refund.clone()).unwrap();
+            if let Some(proof) = accumulator.add(validated.validated()).unwrap() {
+                refund_proof = Some(proof);
+            }
+            replica
+                .apply_checked(ReplicaEvent::RefundValidated(validated))
+                .unwrap();
+        }
+        let validated_only = merchant.replica_group.replicas[0].refunded(&payment);
+        register_at_debiting_replicas(&refund_proof.unwrap(), &mut merchant.replica_group);
+        let too_much = merchant
+            .actor
+            .refund(payment, Money::from_nano(50))
+            .unwrap();
+
+        // --- Assert ---
+        assert!(refund.verify().is_ok());
+        assert!(refund.signed_transfer.transfer.to == customer.actor.id());
+        let replica = &merchant.replica_group.replicas[0];
+        assert!(validated_only == Money::zero());
+        assert!(replica.refunded(&payment) == Money::from_nano(60));
+        assert!(replica.validate_refund(too_much).is_err());
+        assert!(merchant
+            .actor
+            .refund(payment, Money::from_nano(101))
+            .is_err());
+        assert!(merchant
+            .actor
+            .refund(Dot::new(get_random_pk(), 0), Money::from_nano(1))
+            .is_err());
+    }
+
+    #[test]
+    fn refunds_an_overage_once() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut customer = actors.remove(&0).unwrap();
+        let mut merchant = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut customer, merchant.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer.clone(), &mut customer).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut customer.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut merchant.replica_group);
+        synch(&mut merchant, events);
+        let memo = customer
+            .actor
+            .attach_memo(&transfer.signed_transfer.transfer, "INV-1")
+            .unwrap();
+        let stored = merchant.actor.store_attachment(&memo).unwrap();
+        merchant.actor.apply(ActorEvent::AttachmentStored(stored));
+        let invoice = Invoice {
+            reference: "INV-1".to_string(),
+            payer: customer.actor.id(),
+            payee: merchant.actor.id(),
+            amount: Money::from_nano(60),
+        };
+        let later_invoice = Invoice {
+            reference: "INV-2".to_string(),
+            ..invoice.clone()
+        };
+
+        // --- Act ---
+        let refund = merchant.actor.refund_overage(&invoice).unwrap().unwrap();
+        merchant
+            .actor
+            .apply(ActorEvent::OverageRefundInitiated(refund.clone()));
+        let repeated = merchant.actor.refund_overage(&invoice);
+        let unpaid = merchant.actor.refund_overage(&later_invoice);
+        let abort = merchant.actor.abort(refund.initiated.id()).unwrap();
+        merchant.actor.apply(ActorEvent::TransferAborted(abort));
+        let retried = merchant.actor.refund_overage(&invoice).unwrap();
+
+        // --- Assert ---
+        assert!(refund.refunded == vec![debit_proof.id()]);
+        assert!(refund.initiated.signed_transfer.transfer.amount == Money::from_nano(40));
+        assert!(refund.initiated.signed_transfer.transfer.to == customer.actor.id());
+        assert!(repeated.is_err());
+        assert!(unpaid == Ok(None));
+        assert!(retried.is_some());
+    }
+
+    #[test]
+    fn transfers_to_many_recipients_with_a_single_debit() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let sender = actors.remove(&0).unwrap();
+        let (first, second) = (get_random_pk(), get_random_pk());
+        let outputs = vec![
+            (first, Money::from_nano(10)),
+            (second, Money::from_nano(20)),
+            (first, Money::from_nano(5)),
+        ];
+
+        // --- Act ---
+        let disbursement = sender.actor.transfer_to_many(outputs).unwrap();
+        let too_much = sender.actor.transfer_to_many(vec![
+            (first, Money::from_nano(60)),
+            (second, Money::from_nano(50)),
+        ]);
+
+        // --- Assert ---
+        assert!(
+            disbursement.payouts
+                == vec![
+                    Payout {
+                        to: first,
+                        amount: Money::from_nano(15),
+                    },
+                    Payout {
+                        to: second,
+                        amount: Money::from_nano(20),
+                    },
+                ]
+        );
+        assert!(disbursement.signed_transfer.transfer.amount == Money::from_nano(35));
+        assert!(disbursement.signed_transfer.transfer.to == sender.actor.id());
+        assert!(too_much.is_err());
+    }
+
+    #[test]
+    fn disburses_many_payouts_with_a_single_debit() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut farmer = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let payouts = vec![
+            Payout {
+                to: recipient.actor.id(),
+                amount: Money::from_nano(30),
+            },
+            Payout {
+                to: get_random_pk(),
+                amount: Money::from_nano(20),
+            },
+            Payout {
+                to: get_random_pk(),
+                amount: Money::from_nano(10),
+            },
+        ];
+
+        let mut other_payouts = payouts.clone();
+        other_payouts[0].amount = Money::from_nano(20);
+        other_payouts[1].amount = Money::from_nano(30);
+
+        // --- Act ---
+        let disbursement = farmer.actor.disburse(payouts.clone()).unwrap();
+        let other = farmer.actor.disburse(other_payouts).unwrap();
+        farmer
+            .actor
+            .apply(ActorEvent::TransferInitiated(TransferInitiated {
+                signed_transfer: disbursement.signed_transfer.clone(),
+            }));
+        let mut debit_proof = None;
+        for replica in &mut farmer.replica_group.replicas {
+            let validated = replica.validate_disbursement(disbursement.clone()).unwrap();
+            replica.apply(ReplicaEvent::DisbursementValidated(validated.clone()));
+            if debit_proof.is_none() {
+                let received = farmer.actor.receive(validated.validated()).unwrap();
+                farmer
+                    .actor
+                    .apply(ActorEvent::TransferValidationReceived(received.clone()));
+                debit_proof = received.proof;
+            }
+        }
+        let debit_proof = debit_proof.unwrap();
+        let registered = farmer.actor.register(debit_proof.clone()).unwrap();
+        farmer
+            .actor
+            .apply(ActorEvent::TransferRegistrationSent(registered));
+        let first = &farmer.replica_group.replicas[0];
+        let other_validation = first.validate_disbursement(other.clone());
+        let other_registration = first.register_disbursement(&debit_proof, &other);
+        let mut shares = vec![];
+        for replica in &mut farmer.replica_group.replicas {
+            let registered = replica
+                .register_disbursement(&debit_proof, &disbursement)
+                .unwrap()
+                .unwrap();
+            shares.push(registered.payouts_signature.clone());
+            replica
+                .apply_checked(ReplicaEvent::DisbursementRegistered(registered))
+                .unwrap();
+        }
+        let signed =
+            SignedDisbursement::combine(&disbursement, &farmer.replica_group.id, &shares).unwrap();
+        let payout_proofs = disbursement.payout_proofs(&debit_proof, &signed).unwrap();
+        for payout_proof in &payout_proofs {
+            for replica in &mut recipient.replica_group.replicas {
+                let propagated = replica.receive_payout(payout_proof).unwrap();
+                replica
+                    .apply_checked(ReplicaEvent::PayoutPropagated(propagated))
+                    .unwrap();
+            }
+        }
+
+        // --- Assert ---
+        // the payouts were agreed with the debit
+        assert!(other.signed_transfer == disbursement.signed_transfer);
+        assert!(other_validation.is_err());
+        assert!(other_registration.is_err());
+        let farmer_replica = &farmer.replica_group.replicas[0];
+        let recipient_replica = &recipient.replica_group.replicas[0];
+        assert!(farmer.actor.balance() == Money::from_nano(40));
+        assert!(farmer_replica.balance(&farmer.actor.id()) == Some(Money::from_nano(40)));
+        for payout in &payouts {
+            assert!(recipient_replica.balance(&payout.to) == Some(payout.amount));
+        }
+        // retransmits, and the debit itself, credit nothing more
+        assert!(farmer.replica_group.replicas[0]
+            .register_disbursement(&debit_proof, &disbursement)
+            .unwrap()
+            .is_none());
+        assert!(recipient_replica.receive_payout(&payout_proofs[0]).is_err());
+        assert!(recipient_replica.receive_propagated(&debit_proof).is_err());
+        let farmer_group = PublicKey::Bls(farmer.replica_group.id.public_key());
+        assert!(recipient_replica
+            .query_history(&payouts[1].to, |history| history.verify_full(
+                &[],
+                &[farmer_group],
+                &[Codec::Canonical]
+            ))
+            .unwrap()
+            .is_ok());
+    }
+
+    #[test]
+    fn holds_debits_in_escrow_until_accepted() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        sender.replica_group.replicas = sender
+            .replica_group
+            .replicas
+            .drain(..)
+            .map(|replica| replica.with_time_source(source.clone()))
+            .collect();
+        let wallet = sender.actor.id();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let signed_transfer = transfer.signed_transfer;
+        let transfer_id = signed_transfer.id();
+        let open = |sender: &mut TestActor| {
+            let request = sender
+                .actor
+                .request_escrow(signed_transfer.clone(), 60)
+                .unwrap();
+            for replica in &mut sender.replica_group.replicas {
+                let opened = replica.open_escrow(request.clone()).unwrap();
+                replica
+                    .apply_checked(ReplicaEvent::EscrowOpened(opened))
+                    .unwrap();
+            }
+        };
+        open(&mut sender);
+        source.advance(60);
+        for replica in &mut sender.replica_group.replicas {
+            for released in replica.expire_escrows() {
+                replica
+                    .apply_checked(ReplicaEvent::EscrowReleased(released))
+                    .unwrap();
+            }
+        }
+        open(&mut sender);
+
+        // --- Act ---
+        let held = sender.replica_group.replicas[0].validate(signed_transfer.clone());
+        let forged = sender.actor.accept_escrow(transfer_id).unwrap();
+        let forged_accept = sender.replica_group.replicas[0].accept_escrow(&forged);
+        let acceptance = recipient.actor.accept_escrow(transfer_id).unwrap();
+        let mut debit_proof = None;
+        for replica in &mut sender.replica_group.replicas {
+            let validated = replica.accept_escrow(&acceptance).unwrap();
+            replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+            let received = sender.actor.receive(validated).unwrap();
+            sender
+                .actor
+                .apply(ActorEvent::TransferValidationReceived(received.clone()));
+            if received.proof.is_some() {
+                debit_proof = received.proof;
+            }
+        }
+        let debit_proof = debit_proof.unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let crediting = find_group(1, &mut groups).unwrap();
+        let _ = propagate_to_crediting_replicas(&debit_proof, crediting);
+
+        // --- Assert ---
+        assert!(held.is_err());
+        assert!(forged_accept.is_err());
+        let replica = &sender.replica_group.replicas[0];
+        assert!(replica.escrow(&wallet).is_none());
+        assert!(replica.balance(&wallet) == Some(Money::zero()));
+        assert!(replica
+            .cancel_escrow(sender.actor.cancel_escrow(transfer_id).unwrap())
+            .is_err());
+        let recipient_balance = crediting.replicas[0].balance(&recipient.actor.id());
+        assert!(recipient_balance == Some(Money::from_nano(100)));
+    }
+
+    #[test]
+    fn proves_the_deposits_of_a_few_wallets() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(2, 3, hashmap![0 => 10, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let deposit = recipient.actor.id();
+        let transfer = init_transfer(&mut sender, deposit);
+        let transfer_id = transfer.signed_transfer.id();
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let crediting = find_group(1, &mut groups).unwrap();
+        let _ = propagate_to_crediting_replicas(&debit_proof, crediting);
+        let _ = checkpoint(crediting);
+
+        // --- Act ---
+        let replica = &crediting.replicas[0];
+        let proof = replica.sparse_proof(&[deposit, deposit]).unwrap();
+
+        // --- Assert ---
+        assert!(proof.verify(&crediting.id).is_ok());
+        assert!(proof.verify(&sender.replica_group.id).is_err());
+        assert!(proof.wallets.len() == 1);
+        let slice = proof.wallet(&deposit).unwrap();
+        assert!(slice.state.balance == Money::from_nano(10));
+        assert!(slice
+            .credits
+            .iter()
+            .any(|credit| credit.transfer().id == transfer_id));
+        assert!(replica.sparse_proof(&[get_random_pk()]).is_err());
+    }
+
+    #[test]
+    fn requires_enough_owners_of_multisig_wallets() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let owners: Vec<_> = (0..3).map(|_| SecretKey::random()).collect();
+        let keys = owners
+            .iter()
+            .map(|sk| PublicKey::Bls(sk.public_key()))
+            .collect();
+        let policy = MultisigPolicy::Keys { keys, threshold: 2 };
+        let signature = sender.actor.authorize_multisig(&policy).unwrap();
+        let wallet = sender.actor.id();
+        for replica in &mut sender.replica_group.replicas {
+            let set = replica
+                .set_multisig_policy(wallet, policy.clone(), signature.clone())
+                .unwrap();
+            replica
+                .apply_checked(ReplicaEvent::MultisigPolicySet(set))
+                .unwrap();
+        }
+        let transfer = init_transfer(&mut sender, get_random_pk())
+            .signed_transfer
+            .transfer;
+        let data = SignableTransfer::new(&transfer).to_bytes();
+        let witnesses: Vec<_> = owners
+            .iter()
+            .map(|sk| Witness {
+                key: PublicKey::Bls(sk.public_key()),
+                signature: Signature::Bls(sk.sign(&data)),
+            })
+            .collect();
+        // signed by an owner, in place of the key of the wallet
+        let signed_transfer = SignedTransfer {
+            transfer: transfer.clone(),
+            actor_signature: witnesses[1].signature.clone(),
+        };
+        let stranger = SignedTransfer {
+            transfer,
+            actor_signature: Signature::Bls(SecretKey::random().sign(&data)),
+        };
+
+        // --- Act ---
+        let replica = &sender.replica_group.replicas[0];
+        let unsigned = replica.validate(signed_transfer.clone());
+        let one = replica.validate_with_witnesses(signed_transfer.clone(), &witnesses[..1]);
+        let by_stranger = replica.validate_with_witnesses(stranger, &witnesses[1..]);
+        let two = replica.validate_with_witnesses(signed_transfer, &witnesses[1..]);
+
+        // --- Assert ---
+        assert!(unsigned.is_err());
+        assert!(one.is_err());
+        assert!(by_stranger.is_err());
+        assert!(two.is_ok());
+        assert!(replica.multisig_policy(&wallet) == Some(&policy));
+        assert!(replica.accounts_by_owner(OwnerKind::Multisig, 0, 10) == vec![wallet]);
+        assert!(replica
+            .set_multisig_policy(wallet, policy, signature)
+            .is_err());
+    }
+
+    #[test]
+    fn hands_over_live_state_to_a_new_version() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 10, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let signed_transfer = transfer.signed_transfer.clone();
+        let _ = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let replica = &sender.replica_group.replicas[0];
+        let validated = replica.validate(signed_transfer.clone()).unwrap();
+        let (secret_key, index) = sender.replica_group.keys[0].clone();
+        let group = sender.replica_group.id.clone();
+        let import = |bytes: &[u8]| {
+            Replica::import_upgrade_state(secret_key.clone(), index, group.clone(), bytes)
+        };
+
+        // --- Act ---
+        let mut bytes = replica.export_upgrade_state().unwrap();
+        let imported = import(&bytes).unwrap();
+
+        // --- Assert ---
+        let id = sender.actor.id();
+        assert!(imported.balance(&id) == replica.balance(&id));
+        assert!(imported.validate(signed_transfer).unwrap() == validated);
+        assert!(imported.checkpoint().unwrap() == replica.checkpoint().unwrap());
+        // without checkpoints, the state is the same in version 1
+        bytes[..2].copy_from_slice(&2u16.to_le_bytes());
+        assert!(import(&bytes).unwrap().checkpoint().unwrap() == replica.checkpoint().unwrap());
+        bytes[..2].copy_from_slice(&1u16.to_le_bytes());
+        assert!(import(&bytes).unwrap().balance(&id) == replica.balance(&id));
+        bytes[..2].copy_from_slice(&(UPGRADE_STATE_VERSION + 1).to_le_bytes());
+        assert!(import(&bytes).is_err());
+    }
+
+    #[test]
+    fn rotates_wallet_keys_keeping_credits_to_previous_keys() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(3, 3, hashmap![0 => 100, 1 => 0, 2 => 50]);
+        let recipient = actors.remove(&1).unwrap();
+        let previous_key = recipient.actor.id();
+        let new_owner = ClientFullId::new_ed25519(&mut rand::thread_rng());
+        let new_key = *new_owner.public_id().public_key();
+        let mut debit_proofs = vec![];
+        for index in vec![0, 2] {
+            let mut sender = actors.remove(&index).unwrap();
+            let transfer = init_transfer(&mut sender, previous_key);
+            let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+            register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+            debit_proofs.push(debit_proof);
+        }
+        let crediting = find_group(1, &mut groups).unwrap();
+        let _ = propagate_to_crediting_replicas(&debit_proofs[0], crediting);
+        let sequence = crediting.replicas[0]
+            .rotation_sequence(&previous_key)
+            .unwrap()
+            .unwrap();
+        let rotation = recipient.actor.rotate_key(new_key, sequence).unwrap();
+
+        // --- Act ---
+        for replica in &mut crediting.replicas {
+            let changed = replica.rotate_wallet_key(rotation.clone()).unwrap();
+            replica
+                .apply_checked(ReplicaEvent::WalletOwnerChanged(changed))
+                .unwrap();
+        }
+        let _ = propagate_to_crediting_replicas(&debit_proofs[1], crediting);
+        let replica = &crediting.replicas[0];
+        let sequence = replica.rotation_sequence(&new_key).unwrap().unwrap();
+        let second_rotation =
+            WalletKeyRotation::new(&new_owner, get_random_pk(), sequence).unwrap();
+
+        // --- Assert ---
+        assert!(replica.current_key(&previous_key) == new_key);
+        assert!(replica.balance(&previous_key).is_none());
+        assert!(replica.balance(&new_key) == Some(Money::from_nano(150)));
+        let sender = debit_proofs[0].from();
+        assert!(replica.credits_from(&new_key, &sender).unwrap().len() == 1);
+        assert!(replica.rotate_wallet_key(rotation).is_err());
+        assert!(sequence == 1);
+        assert!(replica.rotate_wallet_key(second_rotation).is_ok());
+    }
+
+    #[test]
+    fn reports_replay_progress_until_cancelled() {
+        // --- Arrange ---
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let group = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+        let total = 2 * REPLAY_REPORT_INTERVAL + 1;
+        let events = || {
+            (0..total).map(|_| {
+                Ok(ReplicaEvent::KnownGroupAdded(KnownGroupAdded {
+                    group: group.clone(),
+                }))
+            })
+        };
+        let mut reports = vec![];
+        let mut record = |progress: &ReplayProgress| {
+            reports.push(*progress);
+            ReplayControl::Continue
+        };
+        let mut cancel = |_: &ReplayProgress| ReplayControl::Cancel;
+
+        // --- Act ---
+        let replayed = Replica::from_history_observed(
+            keys.secret_key_share(0),
+            0,
+            keys.public_keys(),
+            events(),
+            Some(total),
+            &mut record,
+        );
+        let cancelled = Replica::from_history_observed(
+            keys.secret_key_share(0),
+            0,
+            keys.public_keys(),
+            events(),
+            Some(total),
+            &mut cancel,
+        );
+
+        // --- Assert ---
+        assert!(replayed.is_ok());
+        let applied: Vec<_> = reports.iter().map(|progress| progress.applied).collect();
+        assert!(applied == vec![REPLAY_REPORT_INTERVAL, 2 * REPLAY_REPORT_INTERVAL, total]);
+        assert!(reports[0].percent() == Some(49));
+        assert!(reports[2].percent() == Some(100));
+        assert!(matches!(
+            cancelled,
+            Err(error) if error.is_cancelled() && error.index == REPLAY_REPORT_INTERVAL
+        ));
+    }
+
+    #[test]
+    fn registers_app_wallets_to_their_owner() {
+        // --- Arrange ---
+        let (mut groups, _) = get_network(1, 3, hashmap![0 => 0]);
+        let replica = &mut find_group(0, &mut groups).unwrap().replicas[0];
+        let seed = MasterSeed::new(rand::thread_rng().gen());
+        let owner = seed.owner_key().public_key();
+        let chat = seed.register_app("chat").unwrap();
+        let games = seed.register_app("games").unwrap();
+
+        // --- Act ---
+        for signed in vec![games.clone(), chat.clone()] {
+            let registered = replica.register_app_wallet(signed).unwrap();
+            replica
+                .apply_checked(ReplicaEvent::AppWalletRegistered(registered))
+                .unwrap();
+        }
+
+        // --- Assert ---
+        let chat_wallet = chat.registration.wallet;
+        assert!(replica.app_wallets(&owner) == vec![&chat, &games]);
+        assert!(replica.app_wallet_owner(&chat_wallet) == Some(owner));
+        assert!(chat_wallet == seed.app_key("chat").unwrap().public_key());
+        assert!(replica.register_app_wallet(chat).is_err());
+    }
+
+    #[test]
+    fn evicts_and_forgets_known_groups() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(3, 3, hashmap![0 => 100, 1 => 50, 2 => 0]);
+        let recipient_id = actors[&2].actor.id();
+        let mut proofs = vec![];
+        let mut keys = vec![];
+        for index in 0..2 {
+            let mut sender = actors.remove(&index).unwrap();
+            let transfer = init_transfer(&mut sender, recipient_id);
+            let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+            register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+            keys.push(PublicKey::Bls(sender.replica_group.id.public_key()));
+            proofs.push(debit_proof);
+        }
+        let new_group = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+        let new_key = PublicKey::Bls(new_group.public_key());
+        let (anchor, chain) = get_anchored_chain(&new_group);
+        let replica = find_group(2, &mut groups).unwrap().replicas[0].clone();
+        let mut replica = replica
+            .with_known_groups_capacity(2)
+            .with_trust_anchors(TrustAnchors::new(vec![anchor]));
+
+        // --- Act ---
+        let propagated = replica
+            .receive_propagated_from(&proofs[0], keys[0])
+            .unwrap();
+        replica.apply(ReplicaEvent::TransferPropagated(propagated));
+        let added = replica.add_known_group(new_group.clone(), chain).unwrap();
+        replica.apply(ReplicaEvent::KnownGroupChained(added));
+        let evicted = replica.receive_propagated_from(&proofs[1], keys[1]);
+        let forgotten = replica.forget_group(&keys[0]).unwrap();
+        replica
+            .apply_checked(ReplicaEvent::KnownGroupForgotten(forgotten.clone()))
+            .unwrap();
+
+        // --- Assert ---
+        assert!(evicted.is_err());
+        assert!(replica.known_group(&keys[0]).is_none());
+        assert!(replica.known_group(&keys[1]).is_none());
+        assert!(replica.known_group(&new_key) == Some(&new_group));
+        assert!(replica.forget_group(&keys[0]).is_err());
+        assert!(replica.balance(&recipient_id) == Some(Money::from_nano(100)));
+    }
+
+    #[test]
+    fn receives_propagated_proofs_in_batches() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(3, 3, hashmap![0 => 100, 1 => 50, 2 => 0]);
+        let recipient_id = actors[&2].actor.id();
+        let mut proofs = vec![];
+        for index in 0..2 {
+            let mut sender = actors.remove(&index).unwrap();
+            let transfer = init_transfer(&mut sender, recipient_id);
+            let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+            register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+            proofs.push(debit_proof);
+        }
+        let mut forged = proofs[0].clone();
+        forged.signed_transfer.transfer.amount = Money::from_nano(1000);
+        proofs.push(proofs[1].clone());
+        proofs.push(forged);
+        let replica = &mut find_group(2, &mut groups).unwrap().replicas[0];
+
+        // --- Act ---
+        let outcomes = replica.receive_propagated_batch(&proofs);
+        for outcome in outcomes.iter().cloned() {
+            if let Ok(Some(propagated)) = outcome {
+                replica.apply(ReplicaEvent::TransferPropagated(propagated));
+            }
+        }
+
+        // --- Assert ---
+        let kinds: Vec<_> = outcomes
+            .iter()
+            .map(|outcome| match outcome {
+                Ok(Some(_)) => "propagated",
+                Ok(None) => "unchanged",
+                Err(_) => "rejected",
+            })
+            .collect();
+        assert!(kinds == vec!["propagated", "propagated", "unchanged", "rejected"]);
+        assert!(replica.balance(&recipient_id) == Some(Money::from_nano(150)));
+    }
+
+    #[test]
+    fn computes_spending_analytics_locally() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient_id = actors[&1].actor.id();
+        let march = 1_583_020_800; // 2020-03-01
+        sender.actor = sender
+            .actor
+            .with_time_source(ManualTimeSource::new(march, 0))
+            .with_spending_analytics(SpendingAnalytics::default());
+        let transfer = init_transfer(&mut sender, recipient_id);
+        let _ = validate_at_sender_replicas(transfer.clone(), &mut sender).unwrap();
+        let mut entries = std::collections::BTreeMap::new();
+        let _ = entries.insert(CATEGORY_KEY.to_string(), "groceries".to_string());
+        let signed = sender
+            .actor
+            .attach(&transfer.signed_transfer.transfer, entries)
+            .unwrap();
+
+        // --- Act ---
+        let categorised = sender.actor.categorise(&signed);
+        let analytics = sender.actor.spending_analytics().unwrap();
+
+        // --- Assert ---
+        let month = Month {
+            year: 2020,
+            month: 3,
+        };
+        let totals = MonthlyTotals {
+            spent: Money::from_nano(100),
+            received: Money::zero(),
+        };
+        assert!(categorised.is_ok());
+        assert!(analytics.monthly_totals() == vec![(month, totals)].into_iter().collect());
+        assert!(analytics.top_recipients(1) == vec![(recipient_id, Money::from_nano(100))]);
+        assert!(
+            analytics.category_totals()
+                == vec![(Some("groceries".to_string()), Money::from_nano(100))]
+                    .into_iter()
+                    .collect()
+        );
+    }
+
+    #[test]
+    fn promotes_standby_replicas_with_attested_key_shares() {
+        // --- Arrange ---
+        let group_keys = setup_replica_group_keys(1, 3);
+        let group = group_keys[&0].id.clone();
+        let attester = SecretKey::random();
+        let policy = AttestationPolicy {
+            attesters: vec![PublicKey::Bls(attester.public_key())]
+                .into_iter()
+                .collect(),
+            measurements: vec![[7; 32]].into_iter().collect(),
+            required: true,
+        };
+        let mut standby =
+            Replica::standby(group.clone(), Default::default()).with_attestation_policy(policy);
+        let (secret_key, key_index) = group_keys[&0].keys[0].clone();
+        let statement = KeyShareStatement {
+            group: PublicKey::Bls(group.public_key()),
+            key_index,
+            key_share: secret_key.public_key_share(),
+            measurement: [7; 32],
+            evidence: b"quote".to_vec(),
+        };
+        let untrusted = KeyShareAttestation {
+            signature: Signature::Bls(SecretKey::random().sign(&statement.to_bytes().unwrap())),
+            attester: get_random_pk(),
+            statement: statement.clone(),
+        };
+        let attestation = KeyShareAttestation {
+            signature: Signature::Bls(attester.sign(&statement.to_bytes().unwrap())),
+            attester: PublicKey::Bls(attester.public_key()),
+            statement,
+        };
+
+        // --- Act ---
+        let unattested = standby.promote(secret_key.clone(), key_index);
+        let rejected = standby.record_attestation(untrusted);
+        let attested = standby.record_attestation(attestation.clone()).unwrap();
+        let applied = standby.apply_checked(ReplicaEvent::KeyShareAttested(attested));
+        let promoted = standby.promote(secret_key, key_index);
+
+        // --- Assert ---
+        assert!(unattested.is_err());
+        assert!(rejected.is_err());
+        assert!(applied.is_ok());
+        assert!(promoted.is_ok());
+        assert!(standby.key_share_attestation(key_index) == Some(&attestation));
+    }
+
+    #[test]
+    fn verifies_attestations_restored_from_snapshots() {
+        // --- Arrange ---
+        let group_keys = setup_replica_group_keys(1, 3);
+        let group = group_keys[&0].id.clone();
+        let (secret_key, key_index) = group_keys[&0].keys[0].clone();
+        let attester = SecretKey::random();
+        let statement = KeyShareStatement {
+            group: PublicKey::Bls(group.public_key()),
+            key_index,
+            key_share: secret_key.public_key_share(),
+            measurement: [7; 32],
+            evidence: b"quote".to_vec(),
+        };
+        let attestation = KeyShareAttestation {
+            signature: Signature::Bls(attester.sign(&statement.to_bytes().unwrap())),
+            attester: PublicKey::Bls(attester.public_key()),
+            statement,
+        };
+        let forged = KeyShareAttestation {
+            signature: Signature::Bls(SecretKey::random().sign(b"quote")),
+            ..attestation.clone()
+        };
+        let snapshot_with = |attestation: KeyShareAttestation| {
+            let mut replica = Replica::from_snapshot(
+                secret_key.clone(),
+                key_index,
+                group.clone(),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            );
+            replica.apply(ReplicaEvent::KeyShareAttested(KeyShareAttested {
+                attestation,
+            }));
+            replica.to_snapshot().unwrap()
+        };
+        let (forged, attested) = (snapshot_with(forged), snapshot_with(attestation.clone()));
+        let untrusting = AttestationPolicy {
+            attesters: vec![get_random_pk()].into_iter().collect(),
+            measurements: vec![[7; 32]].into_iter().collect(),
+            required: true,
+        };
+
+        // --- Act ---
+        let forged =
+            Replica::try_from_snapshot(secret_key.clone(), key_index, group.clone(), &forged);
+        let restored = Replica::try_from_snapshot(secret_key, key_index, group, &attested).unwrap();
+        let untrusted = restored.clone().with_attestation_policy(untrusting);
+
+        // --- Assert ---
+        assert!(forged.is_err());
+        assert!(restored.key_share_attestation(key_index) == Some(&attestation));
+        assert!(untrusted.key_share_attestation(key_index).is_none());
+    }
+
+    #[test]
+    fn serves_replicas_from_many_threads() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let id = sender.actor.id();
+        let handles: Vec<_> = sender
+            .replica_group
+            .replicas
+            .iter()
+            .map(|replica| ReplicaHandle::new(replica.clone()))
+            .collect();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+
+        // --- Act ---
+        let validations: Vec<_> = handles
+            .iter()
+            .map(|handle| {
+                let (handle, signed_transfer) = (handle.clone(), transfer.signed_transfer.clone());
+                thread::spawn(move || block_on(handle.validate(signed_transfer)).unwrap())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|validation| validation.join().unwrap())
+            .collect();
+        let mut debit_proof = None;
+        for validated in validations {
+            let received = sender.actor.receive(validated).unwrap();
+            sender
+                .actor
+                .apply(ActorEvent::TransferValidationReceived(received.clone()));
+            if received.proof.is_some() {
+                debit_proof = received.proof;
+                break;
+            }
+        }
+        let debit_proof = debit_proof.unwrap();
+        let registrations: Vec<_> = handles
+            .iter()
+            .map(|handle| {
+                let (handle, debit_proof) = (handle.clone(), debit_proof.clone());
+                thread::spawn(move || block_on(handle.register(&debit_proof)))
+            })
+            .collect();
+        let reader = handles[0].clone();
+        let balance = thread::spawn(move || reader.balance(&id).unwrap());
+
+        // --- Assert ---
+        assert!(registrations
+            .into_iter()
+            .all(|registration| registration.join().unwrap().is_ok()));
+        let balance = balance.join().unwrap();
+        assert!(balance == Some(Money::zero()) || balance == Some(Money::from_nano(100)));
+        for handle in &handles {
+            assert!(handle.balance(&id).unwrap() == Some(Money::zero()));
+            assert!(handle.debits_since(&id, 0).unwrap().unwrap().len() == 1);
+        }
+    }
+
+    #[test]
+    fn persists_the_events_of_the_handle() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(2, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let store = EventStore::new(MemoryEventStore::new());
+        let handle = ReplicaHandle::new(
+            Replica::from_event_store(
+                keys.secret_key_share(0),
+                0,
+                keys.public_keys(),
+                store.clone(),
+            )
+            .unwrap(),
+        );
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut groups[1]);
+        let known = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+
+        // --- Act ---
+        block_on(handle.apply(events[0].clone())).unwrap();
+        handle.snapshot_to_store().unwrap();
+        block_on(handle.apply(ReplicaEvent::KnownGroupAdded(KnownGroupAdded {
+            group: known,
+        })))
+        .unwrap();
+        let restored =
+            Replica::from_event_store(keys.secret_key_share(0), 0, keys.public_keys(), store)
+                .unwrap();
+
+        // --- Assert ---
+        assert!(handle.balance(&recipient.actor.id()).unwrap() == Some(Money::from_nano(100)));
+        assert!(
+            restored.to_snapshot().unwrap()
+                == handle
+                    .read(|replica| replica.to_snapshot())
+                    .unwrap()
+                    .unwrap()
+        );
+    }
+
+    #[test]
+    fn skips_verifying_proofs_verified_before() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let metrics = Arc::new(CountingMetrics::default());
+        let replica = recipient
+            .replica_group
+            .replicas
+            .remove(0)
+            .with_metrics(Arc::clone(&metrics));
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let propagated = replica.receive_propagated(&debit_proof);
+        let verified = metrics.verified.load(Ordering::SeqCst);
+
+        // --- Act ---
+        let redelivered = replica.receive_propagated(&debit_proof);
+        let batched = replica.receive_propagated_batch(&[debit_proof]);
+
+        // --- Assert ---
+        assert!(propagated.is_ok());
+        assert!(verified > 0);
+        assert!(redelivered.is_ok());
+        assert!(batched[0].as_ref().map_or(false, |o| o.is_some()));
+        assert!(metrics.verified.load(Ordering::SeqCst) == verified);
+    }
+
+    #[test]
+    fn reports_to_metrics() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let sending = Arc::new(CountingMetrics::default());
+        let receiving = Arc::new(CountingMetrics::default());
+        let replica = sender.replica_group.replicas.remove(0);
+        sender
+            .replica_group
+            .replicas
+            .insert(0, replica.with_metrics(Arc::clone(&sending)));
+        let replica = recipient.replica_group.replicas.remove(0);
+        recipient
+            .replica_group
+            .replicas
+            .insert(0, replica.with_metrics(Arc::clone(&receiving)));
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let mut tampered = transfer.signed_transfer.clone();
+        tampered.transfer.amount = Money::from_nano(1_000);
+
+        // --- Act ---
+        let rejected = sender.replica_group.replicas[0].validate(tampered);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+
+        // --- Assert ---
+        assert!(rejected.is_err());
+        assert!(sending.rejected.load(Ordering::SeqCst) == 1);
+        assert!(sending.accepted.load(Ordering::SeqCst) == 1);
+        assert!(sending.registered.load(Ordering::SeqCst) == 1);
+        assert!(sending.verified.load(Ordering::SeqCst) > 0);
+        assert!(sending.wallets.load(Ordering::SeqCst) == 1);
+        assert!(receiving.propagated.load(Ordering::SeqCst) == 1);
+        assert!(receiving.wallets.load(Ordering::SeqCst) == 2);
+    }
+
+    #[test]
+    fn advises_wallets_to_compact() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let id = recipient.actor.id();
+        let transfer = init_transfer(&mut sender, id);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let before_checkpoint = recipient.replica_group.replicas[0].compaction_advice(10);
+        let _ = checkpoint(&mut recipient.replica_group);
+
+        // --- Act ---
+        let advice = recipient.replica_group.replicas[0].compaction_advice(10);
+        let storage = advice[0];
+        let group = &mut recipient.replica_group;
+        let state = group.replicas[0]
+            .compaction(&id, storage.up_to_index)
+            .unwrap();
+        let shares: Vec<_> = group
+            .replicas
+            .iter()
+            .map(|replica| replica.sign_compaction(&state).unwrap())
+            .collect();
+        let signed_compaction = SignedCompaction::combine(state, &group.id, &shares).unwrap();
+        let replica = &mut group.replicas[0];
+        let compacted = replica.compact_wallet(signed_compaction).unwrap();
+        replica.apply(ReplicaEvent::WalletCompacted(compacted));
+
+        // --- Assert ---
+        assert!(before_checkpoint.is_empty());
+        assert!(advice.len() == 1);
+        assert!(storage.wallet == id);
+        assert!(storage.checkpointable == storage.held);
+        assert!(storage.reclaimable_bytes == storage.stored_bytes);
+        let after = replica.wallet_storage(&id).unwrap();
+        assert!(after.appended == storage.appended);
+        assert!(after.held == 0);
+        assert!(after.stored_bytes == 0);
+        assert!(replica.compaction_advice(10).is_empty());
+    }
+
+    #[test]
+    fn notifies_subscribers_of_wallets() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 10, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let (sender_id, recipient_id) = (sender.actor.id(), recipient.actor.id());
+        let (debits, debited) = std::sync::mpsc::channel();
+        let (credits, credited) = std::sync::mpsc::channel();
+        sender.replica_group.replicas[0]
+            .subscribe(sender_id, debits)
+            .unwrap();
+        recipient.replica_group.replicas[0]
+            .subscribe(recipient_id, credits)
+            .unwrap();
+        let transfer = init_transfer(&mut sender, recipient_id);
+        let mut disabled = sender.replica_group.replicas[1]
+            .clone()
+            .with_subsystems(Subsystems::all().disable(Subsystem::Subscriptions));
+        let (unused, _) = std::sync::mpsc::channel();
+
+        // --- Act ---
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+
+        // --- Assert ---
+        let debit = debited.try_recv().unwrap();
+        assert!(debit.wallet == sender_id);
+        assert!(debit.direction == PaymentDirection::Sent);
+        assert!(debit.balance == Money::zero());
+        let credit = credited.try_recv().unwrap();
+        assert!(credit.wallet == recipient_id);
+        assert!(credit.direction == PaymentDirection::Received);
+        assert!(credit.transfer == debit.transfer);
+        assert!(credit.balance == Money::from_nano(10));
+        assert!(debited.try_recv().is_err());
+        assert!(disabled.subscribe(sender_id, unused).is_err());
+    }
+
+    #[test]
+    fn runs_consensus_with_subsystems_disabled() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let id = sender.actor.id();
+        sender.replica_group.replicas = sender
+            .replica_group
+            .replicas
+            .iter()
+            .map(|replica| {
+                replica
+                    .clone()
+                    .with_subsystems(Subsystems::consensus_only())
+            })
+            .collect();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+
+        // --- Act ---
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+
+        // --- Assert ---
+        for replica in &sender.replica_group.replicas {
+            assert!(replica.balance(&id) == Some(Money::zero()));
+            assert!(replica.balance_or_redirect(&id) == Some(QueryResponse::Disabled));
+            assert!(
+                replica.handle_query(&TransferQuery::GetCredits {
+                    wallet: id,
+                    since: 0
+                }) == TransferQueryResponse::Disabled
+            );
+        }
+    }
+
+    #[test]
+    fn holds_credits_of_unknown_groups_until_known() {
+        // --- Arrange ---
+        let group_keys = setup_replica_group_keys(2, 3);
+        let sender_account = setup_account(100, 0);
+        let mut groups = setup_replica_groups(group_keys.clone(), vec![sender_account.clone()]);
+        let mut sender = setup_actor(sender_account, &mut groups);
+        let (secret_key, key_index) = group_keys[&1].keys[0].clone();
+        let strict = Replica::from_snapshot(
+            secret_key,
+            key_index,
+            group_keys[&1].id.clone(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        let (anchor, chain) = get_anchored_chain(&group_keys[&0].id);
+        let mut replica = strict
+            .clone()
+            .with_held_credits(1)
+            .with_trust_anchors(TrustAnchors::new(vec![anchor]));
+        let recipient = get_random_pk();
+        let transfer = init_transfer(&mut sender, recipient);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+
+        // --- Act ---
+        let held = replica.receive_propagated(&debit_proof);
+        if let Err(TransferError::CreditHeld(held)) = held.clone() {
+            replica.apply(ReplicaEvent::CreditHeld(*held));
+        }
+        let held_again = replica.receive_propagated(&debit_proof);
+        let unreleased = replica.release_held_credits();
+        let known = replica
+            .add_known_group(sender.replica_group.id.clone(), chain)
+            .unwrap();
+        replica.apply(ReplicaEvent::KnownGroupChained(known));
+        let released = replica.release_held_credits();
+        for propagated in released.clone() {
+            replica.apply(ReplicaEvent::TransferPropagated(propagated));
+        }
+
+        // --- Assert ---
+        assert!(matches!(held, Err(TransferError::CreditHeld(_))));
+        assert!(matches!(
+            strict.receive_propagated(&debit_proof),
+            Err(error) if !matches!(error, TransferError::CreditHeld(_))
+        ));
+        assert!(held_again.is_err());
+        assert!(unreleased.is_empty());
+        assert!(released.len() == 1);
+        assert!(replica.held_credits().is_empty());
+        assert!(replica.balance(&recipient) == Some(Money::from_nano(100)));
+    }
+
+    #[test]
+    fn expires_credits_of_groups_never_known() {
+        // --- Arrange ---
+        let group_keys = setup_replica_group_keys(2, 3);
+        let sender_account = setup_account(100, 0);
+        let mut groups = setup_replica_groups(group_keys.clone(), vec![sender_account.clone()]);
+        let mut sender = setup_actor(sender_account, &mut groups);
+        let (secret_key, key_index) = group_keys[&1].keys[0].clone();
+        let source = Arc::new(ManualTimeSource::new(1_000, 0));
+        let mut replica = Replica::from_snapshot(
+            secret_key,
+            key_index,
+            group_keys[&1].id.clone(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+        .with_held_credits(1)
+        .with_time_source(source.clone());
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        if let Err(TransferError::CreditHeld(held)) = replica.receive_propagated(&debit_proof) {
+            replica.apply(ReplicaEvent::CreditHeld(*held));
+        }
+
+        // --- Act ---
+        let early = replica.expired_held_credits();
+        source.advance(DEFAULT_HELD_CREDIT_TIMEOUT);
+        let expired = replica.expired_held_credits();
+        let handed_over = Replica::import_upgrade_state(
+            group_keys[&1].keys[0].0.clone(),
+            key_index,
+            group_keys[&1].id.clone(),
+            &replica.export_upgrade_state().unwrap(),
+        )
+        .unwrap()
+        .with_time_source(source.clone());
+        for expired in expired.clone() {
+            replica
+                .apply_checked(ReplicaEvent::HeldCreditExpired(expired))
+                .unwrap();
+        }
+
+        // --- Assert ---
+        assert!(early.is_empty());
+        assert!(expired.len() == 1);
+        assert!(expired[0].transfer_id == debit_proof.id());
+        assert!(handed_over.expired_held_credits() == expired);
+        assert!(replica.held_credits().is_empty());
+        assert!(replica.expired_held_credits().is_empty());
+        assert!(matches!(
+            replica.receive_propagated(&debit_proof),
+            Err(TransferError::CreditHeld(_))
+        ));
+    }
+
+    #[test]
+    fn follows_the_state_charts() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let (from, to) = (sender.actor.id(), recipient.actor.id());
+        let actor_chart = StateChart::of(Machine::Actor);
+        let replica_chart = StateChart::of(Machine::Replica);
+        let mut observed = vec![];
+
+        // --- Act ---
+        let transfer = sender.actor.transfer(sender.actor.balance(), to).unwrap();
+        observed.push(at_actor(
+            &mut sender.actor,
+            ActorEvent::TransferInitiated(transfer.clone()),
+        ));
+        let mut debit_proof = None;
+        for replica in &mut sender.replica_group.replicas {
+            let validated = replica.validate(transfer.signed_transfer.clone()).unwrap();
+            let event = ReplicaEvent::TransferValidated(validated.clone());
+            observed.push(at_replica(replica, from, event));
+            let received = sender.actor.receive(validated).unwrap();
+            debit_proof = debit_proof.or(received.proof.clone());
+            let event = ActorEvent::TransferValidationReceived(received);
+            observed.push(at_actor(&mut sender.actor, event));
+        }
+        let debit_proof = debit_proof.unwrap();
+        let registered = sender.actor.register(debit_proof.clone()).unwrap();
+        observed.push(at_actor(
+            &mut sender.actor,
+            ActorEvent::TransferRegistrationSent(registered),
+        ));
+        for replica in &mut sender.replica_group.replicas {
+            let registered = replica.register(&debit_proof).unwrap().unwrap();
+            let event = ReplicaEvent::TransferRegistered(registered);
+            observed.push(at_replica(replica, from, event));
+        }
+        let mut events = vec![];
+        for replica in &mut recipient.replica_group.replicas {
+            let propagated = replica.receive_propagated(&debit_proof).unwrap();
+            events.push(ReplicaEvent::TransferPropagated(propagated.clone()));
+            let event = ReplicaEvent::TransferPropagated(propagated);
+            observed.push(at_replica(replica, to, event));
+        }
+        let synched = recipient.actor.synch(events).unwrap();
+        observed.push(at_actor(
+            &mut recipient.actor,
+            ActorEvent::TransfersSynched(synched),
+        ));
+
+        // --- Assert ---
+        for (machine, before, event, after) in &observed {
+            let chart = match machine {
+                Machine::Actor => &actor_chart,
+                Machine::Replica => &replica_chart,
+            };
+            assert!(chart.allows(*before, event, *after));
+        }
+        assert!(observed
+            .iter()
+            .any(|(_, before, _, after)| *before == ChartState::Accumulating
+                && *after == ChartState::Agreed));
+        assert!(observed
+            .iter()
+            .any(|(_, before, _, after)| *before == ChartState::Validated
+                && *after == ChartState::Idle));
+        let replica = &sender.replica_group.replicas[0];
+        assert!(replica.chart_state(&get_random_pk()) == ChartState::Unknown);
+        assert!(sender.actor.chart_state() == ChartState::Idle);
+        assert!(!replica_chart.allows(ChartState::Unknown, "TransferRegistered", ChartState::Idle));
+
+        fn at_actor(
+            actor: &mut Actor<Validator>,
+            event: ActorEvent,
+        ) -> (Machine, ChartState, &'static str, ChartState) {
+            let before = actor.chart_state();
+            let name = event.name();
+            actor.apply(event);
+            (Machine::Actor, before, name, actor.chart_state())
+        }
+
+        fn at_replica(
+            replica: &mut Replica,
+            wallet: AccountId,
+            event: ReplicaEvent,
+        ) -> (Machine, ChartState, &'static str, ChartState) {
+            let before = replica.chart_state(&wallet);
+            let name = event.name();
+            replica.apply(event);
+            (Machine::Replica, before, name, replica.chart_state(&wallet))
+        }
+    }
+
+    #[test]
+    fn transfers_between_ed25519_and_bls_owners() {
+        // --- Arrange ---
+        let mut rng = rand::thread_rng();
+        let client_full_id = ClientFullId::new_ed25519(&mut rng);
+        let bls_owned = setup_owned_account(100, 0, ClientFullId::new_bls(&mut rng));
+        let ed25519_owned = setup_owned_account(0, 1, client_full_id.clone());
+        let group_keys = setup_replica_group_keys(2, 3);
+        let accounts = vec![bls_owned.clone(), ed25519_owned.clone()];
+        let mut replica_groups = setup_replica_groups(group_keys, accounts);
+        let mut bls_client = setup_actor(bls_owned, &mut replica_groups);
+        // the lightweight client signs with its Ed25519 identity only
+        let replica_group = find_group(1, &mut replica_groups).unwrap().clone();
+        let mut ed25519_client = TestActor {
+            actor: Actor::from_snapshot(
+                ed25519_owned.account,
+                client_full_id,
+                replica_group.id.clone(),
+                Validator {},
+            ),
+            replica_group,
+        };
+        let (bls_id, ed25519_id) = (bls_client.actor.id(), ed25519_client.actor.id());
+
+        // --- Act ---
+        // all of it to the Ed25519 wallet, and back again
+        let transfer = init_transfer(&mut bls_client, ed25519_id);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut bls_client).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut bls_client.replica_group);
+        let events =
+            propagate_to_crediting_replicas(&debit_proof, &mut ed25519_client.replica_group);
+        synch(&mut ed25519_client, events);
+        let transfer = init_transfer(&mut ed25519_client, bls_id);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut ed25519_client).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut ed25519_client.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut bls_client.replica_group);
+        synch(&mut bls_client, events);
+
+        // --- Assert ---
+        assert!(matches!(bls_id, PublicKey::Bls(_)));
+        assert!(matches!(ed25519_id, PublicKey::Ed25519(_)));
+        assert!(bls_client.actor.balance() == Money::from_nano(100));
+        assert!(ed25519_client.actor.balance() == Money::zero());
+        for replica in &bls_client.replica_group.replicas {
+            assert!(replica.balance(&bls_id) == Some(Money::from_nano(100)));
+            assert!(replica.accounts_by_owner(OwnerKind::Bls, 0, 10) == vec![bls_id]);
+        }
+        for replica in &ed25519_client.replica_group.replicas {
+            assert!(replica.balance(&ed25519_id) == Some(Money::zero()));
+            assert!(replica.accounts_by_owner(OwnerKind::Ed25519, 0, 10) == vec![ed25519_id]);
+            // the history holds a credit by a BLS key, and a debit by an Ed25519 key
+            let schemes = replica.query_history(&ed25519_id, |history| {
+                let credited_by_bls = history
+                    .credits_iter(0)
+                    .any(|credit| credit.id.actor == bls_id);
+                let debited_to_bls = history.debits_iter(0).all(|debit| debit.to == bls_id);
+                (credited_by_bls, debited_to_bls)
+            });
+            assert!(schemes == Some((true, true)));
+        }
+    }
+
+    #[test]
+    fn compacts_wallet_histories() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let id = recipient.actor.id();
+        let transfer = init_transfer(&mut sender, id);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        synch(&mut recipient, events);
+        let group = &recipient.replica_group;
+        let up_to_index = SyncIndex {
+            credit_index: 1,
+            debit_index: 0,
+        };
+        let state = group.replicas[0].compaction(&id, up_to_index).unwrap();
+        let shares: Vec<_> = group
+            .replicas
+            .iter()
+            .map(|replica| replica.sign_compaction(&state).unwrap())
+            .collect();
+        let signed_compaction =
+            SignedCompaction::combine(state.clone(), &group.id, &shares).unwrap();
+
+        // --- Act ---
+        for replica in &mut recipient.replica_group.replicas {
+            let compacted = replica.compact_wallet(signed_compaction.clone()).unwrap();
+            replica.apply(ReplicaEvent::WalletCompacted(compacted));
+        }
+        let transfer = init_transfer(&mut recipient, sender.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut recipient).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut recipient.replica_group);
+
+        // --- Assert ---
+        let replica = &recipient.replica_group.replicas[0];
+        assert!(state.balance == Money::from_nano(100));
+        assert!(replica.credit_entries(&id).unwrap().is_empty());
+        assert!(replica.credits_since(&id, 0).unwrap().is_empty());
+        assert!(replica.debits_since(&id, 0).unwrap().len() == 1);
+        assert!(replica.balance(&id) == Some(Money::zero()));
+        assert!(replica.compact_wallet(signed_compaction).is_err());
+        assert!(replica.compaction(&id, up_to_index).is_err());
+        assert!(replica.checkpoint().is_ok());
+        let (secret_key, index) = recipient.replica_group.keys[0].clone();
+        let group = recipient.replica_group.id.clone();
+        let bytes = replica.to_snapshot().unwrap();
+        let restored = Replica::try_from_snapshot(secret_key, index, group, &bytes).unwrap();
+        assert!(restored.balance(&id) == replica.balance(&id));
+        assert!(restored.checkpoint().unwrap() == replica.checkpoint().unwrap());
+    }
+
+    #[test]
+    fn traces_payments_across_sections() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 10, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let id = transfer.signed_transfer.id();
+        let sending = sender.replica_group.id.clone();
+        let receiving = recipient.replica_group.id.clone();
+        let mut tracer = PaymentTracer::new();
+
+        // --- Act ---
+        let unvalidated = sender.replica_group.replicas[0].sign_hop(&id, Hop::Validated);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let validated = sender.replica_group.replicas[0]
+            .sign_hop(&id, Hop::Validated)
+            .unwrap();
+        tracer.record_hop(validated.clone(), &sending).unwrap();
+        let stuck_at = tracer.trace(&id).unwrap().pending_hop();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        for replica in &sender.replica_group.replicas {
+            let registered = replica.sign_hop(&id, Hop::Registered).unwrap();
+            tracer.record_hop(registered, &sending).unwrap();
+        }
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let propagated = recipient.replica_group.replicas[0]
+            .sign_hop(&id, Hop::Propagated)
+            .unwrap();
+        let misattributed = tracer.record_hop(propagated.clone(), &sending);
+        tracer.record_hop(propagated, &receiving).unwrap();
+        let propagations: Vec<_> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                ReplicaEvent::TransferPropagated(propagated) => Some(propagated),
+                _ => None,
+            })
+            .collect();
+        let receipt = CreditAgreementProof::combine(&propagations, &receiving).unwrap();
+        let before_credit = tracer.trace(&id).unwrap().last_hop();
+        tracer
+            .record_credit(receipt, &PublicKey::Bls(receiving.public_key()))
+            .unwrap();
+
+        // --- Assert ---
+        assert!(unvalidated.is_err());
+        assert!(stuck_at == Some(Hop::Registered));
+        assert!(misattributed.is_err());
+        assert!(before_credit == Some(Hop::Propagated));
+        let trace = tracer.trace(&id).unwrap();
+        assert!(trace.records_of(Hop::Registered).count() == 3);
+        assert!(trace.last_hop() == Some(Hop::Credited));
+        assert!(trace.pending_hop().is_none());
+        assert!(tracer.record_hop(validated, &sending).is_ok());
+        assert!(tracer.trace(&id).unwrap().records.len() == 5);
+        assert!(recipient.replica_group.replicas[0]
+            .sign_hop(&id, Hop::Credited)
+            .is_err());
+    }
+
+    #[test]
+    fn detects_divergence_by_state_hash() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let group = &mut sender.replica_group;
+        let initial: Vec<_> = group.replicas.iter().map(Replica::state_hash).collect();
+        let validated = group.replicas[0]
+            .validate(transfer.signed_transfer)
+            .unwrap();
+
+        // --- Act ---
+        group.replicas[0].apply(ReplicaEvent::TransferValidated(validated.clone()));
+        let diverged: Vec<_> = group.replicas.iter().map(Replica::state_hash).collect();
+        for replica in &mut group.replicas[1..] {
+            replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+        }
+        let converged: Vec<_> = group.replicas.iter().map(Replica::state_hash).collect();
+
+        // --- Assert ---
+        assert!(initial.iter().all(|hash| *hash == initial[0]));
+        assert!(diverged[0] != diverged[1]);
+        assert!(diverged[1] == diverged[2]);
+        assert!(converged.iter().all(|hash| *hash == converged[0]));
+        assert!(converged[0] != initial[0]);
+    }
+
+    #[test]
+    fn promotes_standby_replicas_without_syncing() {
+        // --- Arrange ---
+        let group_keys = setup_replica_group_keys(2, 3);
+        let sender_account = setup_account(100, 0);
+        let mut groups = setup_replica_groups(group_keys.clone(), vec![sender_account.clone()]);
+        let mut sender = setup_actor(sender_account, &mut groups);
+        let mut crediting = find_group(1, &mut groups).unwrap().clone();
+        let other_groups = vec![sender.replica_group.id.clone()].into_iter().collect();
+        let mut standby = Replica::standby(crediting.id.clone(), other_groups);
+        let key = SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()));
+        let wallet = key.public_key();
+        let transfer = init_transfer(&mut sender, wallet);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut crediting);
+        let transfer = Transfer {
+            id: Dot::new(wallet, 0),
+            to: get_random_pk(),
+            amount: Money::from_nano(10),
+        };
+        let signed_transfer = SignedTransfer {
+            actor_signature: key.sign(&SignableTransfer::new(&transfer).to_bytes()),
+            transfer,
+        };
+        let (secret_key, key_index) = group_keys[&1].keys[0].clone();
+
+        // --- Act ---
+        let applied = standby.apply_checked(events[0].clone());
+        let duplicate = standby.apply_checked(events[1].clone());
+        let refused = standby.validate(signed_transfer.clone());
+        let promoted = standby.promote(secret_key, key_index);
+        let validated = standby.validate(signed_transfer);
+
+        // --- Assert ---
+        assert!(applied.is_ok());
+        assert!(duplicate.is_err());
+        assert!(refused.is_err());
+        assert!(promoted.is_ok());
+        assert!(validated.is_ok());
+        assert!(!standby.is_standby());
+        assert!(standby.balance(&wallet) == Some(Money::from_nano(100)));
+    }
+
+    #[test]
+    fn catches_up_with_sync_packets() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let mut offline_sender_replica = sender.replica_group.replicas[0].clone();
+        let mut offline_recipient_replica = recipient.replica_group.replicas[0].clone();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let debit_packet = sender.replica_group.replicas[1]
+            .sync_delta(&offline_sender_replica.sync_index().unwrap())
+            .unwrap();
+        let credit_packet = recipient.replica_group.replicas[1]
+            .sync_delta(&offline_recipient_replica.sync_index().unwrap())
+            .unwrap();
+        let mut forged_packet = debit_packet.clone();
+        let forger = SecretKey::random();
+        let data = SignableProof::new(&debit_proof.signed_transfer).to_bytes();
+        forged_packet.registered[0]
+            .debit_proof
+            .debiting_replicas_sig = Signature::Bls(forger.sign(data));
+        let mut forged_replica = offline_sender_replica.clone();
+        let fresh_packet = sender.replica_group.replicas[1]
+            .sync_delta(&HashMap::new())
+            .unwrap();
+
+        // --- Act ---
+        let debits_applied = offline_sender_replica
+            .apply_sync(debit_packet.clone())
+            .unwrap();
+        let credits_applied = offline_recipient_replica.apply_sync(credit_packet).unwrap();
+        let reapplied = offline_sender_replica.apply_sync(debit_packet).unwrap();
+        let forged = forged_replica.apply_sync(forged_packet);
+
+        // --- Assert ---
+        assert!(debits_applied == 1);
+        assert!(credits_applied == 1);
+        assert!(reapplied == 0);
+        assert!(forged.is_err());
+        assert!(fresh_packet.registered.len() == 1);
+        assert!(fresh_packet.registered[0].id() == debit_proof.id());
+        assert!(offline_sender_replica.balance(&sender.actor.id()) == Some(Money::zero()));
+        assert!(
+            offline_recipient_replica.balance(&recipient.actor.id()) == Some(Money::from_nano(100))
+        );
+        assert!(sender.replica_group.replicas[1]
+            .sync_delta(&offline_sender_replica.sync_index().unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn imports_wallets_with_prior_activity() {
+        // --- Arrange ---
+        let (groups, mut actors) = get_network(2, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut group = groups.into_iter().find(|g| g.index == 1).unwrap();
+        let key = SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()));
+        let wallet = key.public_key();
+        let transfer = init_transfer(&mut sender, wallet);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut group);
+        let shares: Vec<_> = group
+            .replicas
+            .iter()
+            .map(|replica| replica.balance_proof(&wallet).unwrap().unwrap())
+            .collect();
+        let balance = BalanceProof::combine(&shares, &group.id).unwrap();
+        let control = ImportChallenge::new(wallet).prove(&key).unwrap();
+        let other_key = SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()));
+        let forged = ImportChallenge::new(wallet).prove(&other_key).unwrap();
+        let history = |events| ImportHistory {
+            events,
+            balance: balance.clone(),
+        };
+
+        // --- Act ---
+        let imported = Actor::import(
+            key.clone(),
+            group.id.clone(),
+            Validator {},
+            &control,
+            history(events.clone()),
+        )
+        .unwrap();
+        let unseen = Actor::import(
+            key.clone(),
+            group.id.clone(),
+            Validator {},
+            &control,
+            history(vec![]),
+        );
+        let uncontrolled = Actor::import(
+            key,
+            group.id.clone(),
+            Validator {},
+            &forged,
+            history(events),
+        );
+        let consumed = group.replicas[0]
+            .consume_import_nonce(control.clone())
+            .unwrap();
+        let consumed_applied =
+            group.replicas[0].apply_checked(ReplicaEvent::ImportNonceConsumed(consumed));
+        let replayed = group.replicas[0].consume_import_nonce(control.clone());
+        let (secret_key, key_index) = group.keys[0].clone();
+        let restored = Replica::try_from_snapshot(
+            secret_key,
+            key_index,
+            group.id.clone(),
+            &group.replicas[0].to_snapshot().unwrap(),
+        )
+        .unwrap();
+
+        // --- Assert ---
+        assert!(imported.id() == wallet);
+        assert!(imported.balance() == Money::from_nano(100));
+        assert!(unseen.is_err());
+        assert!(uncontrolled.is_err());
+        assert!(consumed_applied.is_ok());
+        assert!(replayed.is_err());
+        assert!(restored.consume_import_nonce(control).is_err());
+        assert!(group.replicas[0].consume_import_nonce(forged).is_err());
+    }
+
+    #[test]
+    fn audits_total_supply() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 10]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let replica = &sender.replica_group.replicas[0];
+
+        // --- Act ---
+        let report = replica.audit_supply(Money::from_nano(10)).unwrap();
+        let created = replica.audit_supply(Money::from_nano(4)).unwrap();
+
+        // --- Assert ---
+        assert!(report.is_sound());
+        assert!(report.total_balance == Money::zero());
+        assert!(report.sent_elsewhere == Money::from_nano(10));
+        assert!(!created.is_sound());
+        assert!(created.discrepancy == Some(Discrepancy::Surplus(Money::from_nano(6))));
+    }
+
+    #[test]
+    fn tracks_storage_usage_of_wallets() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 10]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let before = sender.replica_group.replicas[0]
+            .storage_usage(&sender.actor.id())
+            .unwrap();
+
+        // --- Act ---
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+
+        // --- Assert ---
+        let replica = &sender.replica_group.replicas[0];
+        let after = replica.storage_usage(&sender.actor.id()).unwrap();
+        assert!(before > 0);
+        assert!(after > before);
+        assert!(replica.total_storage_usage().unwrap() == after);
+        assert!(replica.storage_usage(&get_random_pk()).is_none());
+    }
+
+    #[test]
+    fn audits_without_key_share() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 10, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let mut auditor = AuditorReplica::new(recipient.replica_group.id.clone());
+        let unknown_group = auditor.verify(&events[0]);
+        auditor.apply(ReplicaEvent::KnownGroupAdded(KnownGroupAdded {
+            group: sender.replica_group.id.clone(),
+        }));
+        let mut forged = events[0].clone();
+        if let ReplicaEvent::TransferPropagated(e) = &mut forged {
+            e.debit_proof.signed_transfer.transfer.amount = Money::from_nano(1000);
+        }
+        let registered = ReplicaEvent::TransferRegistered(TransferRegistered {
+            debit_proof: debit_proof.clone(),
+        });
+
+        // --- Act ---
+        let forgery = auditor.verify(&forged);
+        let verified = auditor.verify(&events[0]);
+        auditor.apply(events[0].clone());
+
+        // --- Assert ---
+        assert!(unknown_group.is_err());
+        assert!(forgery.is_err());
+        assert!(verified.is_ok());
+        assert!(auditor.balance(&recipient.actor.id()) == Some(Money::from_nano(10)));
+        assert!(auditor.check_invariants().is_ok());
+        assert!(auditor.verify(&events[1]).is_err());
+        assert!(auditor.verify(&registered).is_err());
+    }
+
+    #[test]
+    fn verifies_artifacts_without_replica() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 10]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let validated = sender.replica_group.replicas[0]
+            .validate(transfer.signed_transfer.clone())
+            .unwrap();
+        let debit_proof = validate_at_sender_replicas(transfer.clone(), &mut sender).unwrap();
+        let mut forged = transfer.signed_transfer.clone();
+        forged.transfer.amount = Money::from_nano(1);
+        let other_group = get_network(1, 3, hashmap![0 => 0]).0.remove(0).id;
+
+        // --- Act ---
+        let codecs = [Codec::Canonical];
+        let signed = verify_signed_transfer(&transfer.signed_transfer, &codecs);
+        let validation = verify_transfer_validated(&validated, &codecs);
+        let proof = verify_debit_agreement_proof(&debit_proof, &sender.replica_group.id, &codecs);
+
+        // --- Assert ---
+        assert!(signed.is_ok());
+        assert!(validation.is_ok());
+        assert!(proof.is_ok());
+        assert!(verify_signed_transfer(&forged, &codecs).is_err());
+        assert!(verify_debit_agreement_proof(&debit_proof, &other_group, &codecs).is_err());
+    }
+
+    #[test]
+    fn combines_receipts_of_credits() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 10, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let propagations: Vec<_> =
+            propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group)
+                .into_iter()
+                .filter_map(|event| match event {
+                    ReplicaEvent::TransferPropagated(propagated) => Some(propagated),
+                    _ => None,
+                })
+                .collect();
+        let crediting_replicas = recipient.replica_group.id.clone();
+
+        // --- Act ---
+        let receipt = CreditAgreementProof::combine(&propagations, &crediting_replicas).unwrap();
+
+        // --- Assert ---
+        assert!(propagations.iter().all(|p| verify_propagated(
+            p,
+            &crediting_replicas,
+            &[Codec::Canonical]
+        )
+        .is_ok()));
+        assert!(receipt
+            .verify(&PublicKey::Bls(crediting_replicas.public_key()))
+            .is_ok());
+        assert!(receipt.to() == recipient.actor.id());
+        assert!(receipt.amount() == Money::from_nano(10));
+        assert!(CreditAgreementProof::combine(&[], &crediting_replicas).is_err());
+    }
+
+    #[test]
+    fn marks_finality_of_transfers() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 10, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let id = transfer.id();
+        let unknown = sender.actor.finality(&id);
+        let mut proof = None;
+        for replica in &mut sender.replica_group.replicas {
+            let validated = replica.validate(transfer.signed_transfer.clone()).unwrap();
+            replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+            let received = sender.actor.receive(validated).unwrap();
+            sender
+                .actor
+                .apply(ActorEvent::TransferValidationReceived(received.clone()));
+            if received.proof.is_some() {
+                proof = received.proof;
+                break;
+            }
+        }
+        let debit_proof = proof.unwrap();
+        let validated = sender.actor.finality(&id);
+
+        // --- Act ---
+        let registered = sender.actor.register(debit_proof.clone()).unwrap();
+        sender
+            .actor
+            .apply(ActorEvent::TransferRegistrationSent(registered));
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        synch(&mut recipient, events);
+
+        // --- Assert ---
+        let transfer = &debit_proof.signed_transfer.transfer;
+        assert!(unknown.is_none());
+        assert!(validated == Some(Finality::Validated));
+        assert!(validated.unwrap().is_pending());
+        assert!(sender.actor.finality(&id) == Some(Finality::Registered));
+        assert!(sender.replica_group.replicas[0].finality(transfer) == Some(Finality::Registered));
+        let credited = recipient.actor.finality(&id).unwrap();
+        assert!(credited == Finality::PropagatedQuorum);
+        assert!(credited.is_spendable());
+        assert!(recipient.replica_group.replicas[0].finality(transfer) == Some(credited));
+    }
+
+    #[test]
+    fn iterates_all_wallets_in_order() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 10, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+
+        // --- Act ---
+        let sender_wallets: Vec<_> = sender.replica_group.replicas[0].wallets_iter().collect();
+        let recipient_wallets: Vec<_> =
+            recipient.replica_group.replicas[0].wallets_iter().collect();
+
+        // --- Assert ---
+        let sender_summary = sender_wallets
+            .iter()
+            .find(|summary| summary.wallet == sender.actor.id())
+            .unwrap();
+        assert!(sender_summary.balance == Money::zero());
+        assert!(sender_summary.credit_count == 1);
+        assert!(sender_summary.debit_count == 1);
+        let recipient_summary = recipient_wallets
+            .iter()
+            .find(|summary| summary.wallet == recipient.actor.id())
+            .unwrap();
+        assert!(recipient_summary.balance == Money::from_nano(10));
+        assert!(recipient_summary.debit_count == 0);
+        assert!(sender_wallets
+            .windows(2)
+            .all(|pair| pair[0].wallet < pair[1].wallet));
+    }
+
+    #[test]
+    fn reports_all_checks_of_transfers() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 10]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let mut overdraft = transfer.signed_transfer.clone();
+        overdraft.transfer.amount = Money::from_nano(11);
+        overdraft.transfer.id.counter = 1;
+
+        // --- Act ---
+        let valid = sender.replica_group.replicas[0].report_debit(&transfer.signed_transfer);
+        let invalid = sender.replica_group.replicas[0].report_debit(&overdraft);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let proof_report = sender.replica_group.replicas[0].report_proof(&debit_proof);
+
+        // --- Assert ---
+        assert!(valid.is_valid());
+        assert!(
+            invalid.failed()
+                == vec![
+                    VerificationCheck::ActorSignature,
+                    VerificationCheck::Sequencing,
+                    VerificationCheck::Balance,
+                ]
+        );
+        assert!(
+            proof_report.outcome(VerificationCheck::GroupSignature) == Some(&CheckOutcome::Passed)
+        );
+        assert!(
+            proof_report.outcome(VerificationCheck::ActorSignature) == Some(&CheckOutcome::Passed)
+        );
+    }
+
+    #[test]
+    fn charges_fees_of_registered_debits() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let id = sender.actor.id();
+        let schedule = FeeSchedule {
+            base: Money::from_nano(10),
+            per_million: 0,
+        };
+        sender.replica_group.replicas = sender
+            .replica_group
+            .replicas
+            .drain(..)
+            .map(|replica| replica.with_fee_policy(schedule, FeeDestination::Burn))
+            .collect();
+        let whole = sender
+            .actor
+            .transfer(Money::from_nano(100), get_random_pk())
+            .unwrap();
+        sender.actor = sender.actor.with_fee_policy(schedule);
+        let refused = sender.actor.transfer(Money::from_nano(95), get_random_pk());
+        let transfer = sender
+            .actor
+            .transfer(Money::from_nano(60), get_random_pk())
+            .unwrap();
+        sender
+            .actor
+            .apply(ActorEvent::TransferInitiated(transfer.clone()));
+
+        // --- Act ---
+        let rejected = sender.replica_group.replicas[0].validate(whole.signed_transfer);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let overcharged = sender.replica_group.replicas[0]
+            .clone()
+            .with_fee_policy(
+                FeeSchedule {
+                    base: Money::from_nano(50),
+                    per_million: 0,
+                },
+                FeeDestination::Burn,
+            )
+            .charge_fee(&debit_proof);
+        for replica in &mut sender.replica_group.replicas {
+            let charged = replica.charge_fee(&debit_proof).unwrap().unwrap();
+            replica.apply(ReplicaEvent::FeeCharged(charged));
+        }
+
+        // --- Assert ---
+        assert!(matches!(
+            rejected,
+            Err(TransferError::Rejected(rejected)) if rejected.reason
+                == RejectionReason::BelowRequiredBalance {
+                    balance: Money::from_nano(100),
+                    required: Money::from_nano(110),
+                }
+        ));
+        assert!(matches!(refused, Err(TransferError::Unsatisfied(_))));
+        assert!(overcharged.is_err());
+        let replica = &sender.replica_group.replicas[0];
+        assert!(replica.balance(&id) == Some(Money::from_nano(30)));
+        assert!(replica.charge_fee(&debit_proof) == Ok(None));
+        assert!(sender.actor.balance() == Money::from_nano(30));
+    }
+
+    #[test]
+    fn records_alternate_proofs_of_credits() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let other_group = SecretKeySet::random(1, &mut rand::thread_rng());
+        let data = SignableProof::new(&debit_proof.signed_transfer).to_bytes();
+        let alternate = DebitAgreementProof {
+            signed_transfer: debit_proof.signed_transfer.clone(),
+            debiting_replicas_sig: Signature::Bls(other_group.secret_key().sign(data)),
+        };
+        let replica = &mut recipient.replica_group.replicas[0];
+        replica.apply(ReplicaEvent::KnownGroupAdded(KnownGroupAdded {
+            group: other_group.public_keys(),
+        }));
+
+        // --- Act ---
+        let duplicate = replica.receive_propagated(&alternate);
+        let recorded = replica.record_alternate_proof(&alternate).unwrap().unwrap();
+        replica.apply(ReplicaEvent::AlternateProofRecorded(recorded));
+
+        // --- Assert ---
+        assert!(duplicate.is_err());
+        let alternates = replica.alternate_proofs(&debit_proof.id());
+        assert!(alternates.len() == 1);
+        assert!(alternates[0].debit_proof == alternate);
+        assert!(replica.record_alternate_proof(&alternate) == Ok(None));
+        assert!(replica.record_alternate_proof(&debit_proof) == Ok(None));
+    }
+
+    #[test]
+    fn consults_validation_policies() {
+        // --- Arrange ---
+        struct DenyList(Vec<PublicKey>);
+        impl ValidationPolicy for DenyList {
+            fn check(
+                &self,
+                transfer: &Transfer,
+                _: &ValidationContext,
+            ) -> Result<(), RejectionReason> {
+                if self.0.contains(&transfer.to) {
+                    Err(RejectionReason::Policy {
+                        reason: "Recipient is denied".to_string(),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+        }
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let sender = actors.remove(&0).unwrap();
+        let denied = get_random_pk();
+        let amount = sender.actor.balance();
+        let transfer = sender.actor.transfer(amount, denied).unwrap();
+        let allowed = sender.actor.transfer(amount, get_random_pk()).unwrap();
+        let replica = sender.replica_group.replicas[0].clone();
+
+        // --- Act ---
+        let replica = replica.with_validation_policy(DenyList(vec![denied]));
+
+        // --- Assert ---
+        assert!(matches!(
+            replica.validate(transfer.signed_transfer),
+            Err(TransferError::Rejected(rejected)) if rejected.reason
+                == RejectionReason::Policy {
+                    reason: "Recipient is denied".to_string(),
+                }
+        ));
+        assert!(replica.validate(allowed.signed_transfer).is_ok());
+    }
+
+    #[test]
+    fn detects_double_spend_attempts() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let id = sender.actor.id();
+        let amount = sender.actor.balance();
+        let first = sender.actor.transfer(amount, get_random_pk()).unwrap();
+        let second = sender.actor.transfer(amount, get_random_pk()).unwrap();
+        let replica = &mut sender.replica_group.replicas[0];
+        let validated = replica.validate(first.signed_transfer.clone()).unwrap();
+        replica.apply(ReplicaEvent::TransferValidated(validated));
+
+        // --- Act ---
+        let attempted = replica.validate(second.signed_transfer.clone());
+        if let Err(TransferError::DoubleSpend(evidence)) = attempted.clone() {
+            replica.apply(ReplicaEvent::DoubleSpendAttempted(*evidence));
+        }
+
+        // --- Assert ---
+        assert!(matches!(
+            attempted,
+            Err(TransferError::DoubleSpend(evidence)) if evidence.verify().is_ok()
+                && evidence.wallet() == id
+                && evidence.first == first.signed_transfer
+                && evidence.second == second.signed_transfer
+        ));
+        assert!(matches!(
+            replica.validate(second.signed_transfer.clone()),
+            Err(TransferError::DoubleSpend(_))
+        ));
+        let resolved = replica.resolve_double_spend(id).unwrap();
+        replica.apply(ReplicaEvent::DoubleSpendResolved(resolved));
+        assert!(replica.resolve_double_spend(id).is_err());
+    }
+
+    #[test]
+    fn finds_credits_by_attachment() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let recipient_id = recipient.actor.id();
+        let transfer = init_transfer(&mut sender, recipient_id);
+        let debit_proof = validate_at_sender_replicas(transfer.clone(), &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let mut entries = std::collections::BTreeMap::new();
+        let _ = entries.insert("order".to_string(), "42".to_string());
+
+        // --- Act ---
+        let signed = sender
+            .actor
+            .attach(&transfer.signed_transfer.transfer, entries)
+            .unwrap();
+        for replica in &mut recipient.replica_group.replicas {
+            let stored = replica.store_attachment(&signed).unwrap();
+            replica.apply(ReplicaEvent::AttachmentStored(stored));
+        }
+
+        // --- Assert ---
+        let replica = &recipient.replica_group.replicas[0];
+        assert!(replica.attachment(&recipient_id, &transfer.id()) == Some(&signed));
+        let credits = replica
+            .credits_attached(&recipient_id, "order", "42")
+            .unwrap();
+        assert!(credits == vec![transfer.signed_transfer.transfer]);
+        assert!(replica.credits_attached(&recipient_id, "order", "7") == Some(vec![]));
+        assert!(replica.store_attachment(&signed).is_err());
+    }
+
+    #[test]
+    fn shows_memos_in_the_statement_of_the_actor() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer.clone(), &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        synch(&mut recipient, events);
+        let signed = sender
+            .actor
+            .attach_memo(&transfer.signed_transfer.transfer, "INV-2020-0042")
+            .unwrap();
+
+        // --- Act ---
+        let stored = recipient.actor.store_attachment(&signed).unwrap();
+        recipient.actor.apply(ActorEvent::AttachmentStored(stored));
+
+        // --- Assert ---
+        let statement = recipient.actor.statement();
+        let line = statement
+            .lines
+            .iter()
+            .find(|l| l.transfer_id == transfer.id());
+        assert!(line.and_then(|l| l.memo.clone()) == Some("INV-2020-0042".to_string()));
+        assert!(recipient.actor.store_attachment(&signed).is_err());
+        assert!(sender.actor.store_attachment(&signed).is_err());
+    }
+
+    #[test]
+    fn filters_history_by_counterparty_and_tag() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let sender_id = sender.actor.id();
+        let recipient_id = recipient.actor.id();
+        let mut transfers = vec![];
+        for to in vec![recipient_id, get_random_pk(), recipient_id] {
+            let transfer = sender.actor.transfer(Money::from_nano(10), to).unwrap();
+            sender
+                .actor
+                .apply(ActorEvent::TransferInitiated(transfer.clone()));
+            let debit_proof = validate_at_sender_replicas(transfer.clone(), &mut sender).unwrap();
+            register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+            if to == recipient_id {
+                let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+            }
+            transfers.push(transfer.signed_transfer.transfer);
+        }
+        let (first, last) = (transfers[0].clone(), transfers[2].clone());
+
+        // --- Act ---
+        let signed = sender.actor.attach_tags(&last, &["rent", "2020"]).unwrap();
+        for replica in &mut recipient.replica_group.replicas {
+            let stored = replica.store_attachment(&signed).unwrap();
+            replica.apply(ReplicaEvent::AttachmentStored(stored));
+        }
+        let debits_to = sender.replica_group.replicas[0].debits_to(&sender_id, &recipient_id);
+        let replica = &recipient.replica_group.replicas[0];
+        let credits_from = replica.credits_from(&recipient_id, &sender_id);
+        let tagged = replica.handle_query(&TransferQuery::GetCreditsTagged {
+            wallet: recipient_id,
+            tag: "rent".to_string(),
+        });
+
+        // --- Assert ---
+        assert!(debits_to == Some(vec![last.clone(), first.clone()]));
+        assert!(credits_from == Some(vec![last.clone(), first]));
+        assert!(tagged == TransferQueryResponse::GetCreditsTagged(Some(vec![last])));
+        assert!(replica.credits_tagged(&recipient_id, "2021") == Some(vec![]));
+        assert!(replica.credits_from(&recipient_id, &recipient_id) == Some(vec![]));
+        assert!(replica.debits_to(&get_random_pk(), &recipient_id) == None);
+    }
+
+    #[test]
+    fn merges_wallets_of_diverged_replicas() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 3, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let (sender_id, recipient_id) = (sender.actor.id(), recipient.actor.id());
+        let mut behind = sender.replica_group.replicas[0].clone();
+        let mut recipient_behind = recipient.replica_group.replicas[0].clone();
+        let mut fork = sender.clone();
+        let transfer_to = |actor: &mut TestActor, to| {
+            let transfer = actor.actor.transfer(Money::from_nano(10), to).unwrap();
+            actor
+                .actor
+                .apply(ActorEvent::TransferInitiated(transfer.clone()));
+            let debit_proof = validate_at_sender_replicas(transfer, actor).unwrap();
+            register_at_debiting_replicas(&debit_proof, &mut actor.replica_group);
+            debit_proof
+        };
+        let debit_proof = transfer_to(&mut sender, recipient_id);
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        // the other side of a netsplit, where the same debit counter was agreed on for another
+        let _ = transfer_to(&mut fork, get_random_pk());
+        let mut diverged = fork.replica_group.replicas[0].clone();
+        let exported = sender.replica_group.replicas[0]
+            .export_wallets(&[sender_id])
+            .unwrap();
+
+        // --- Act ---
+        let caught_up = behind.merge(exported.clone()).unwrap();
+        let conflicted = diverged.merge(exported).unwrap();
+        let credited = recipient_behind
+            .merge(
+                recipient.replica_group.replicas[0]
+                    .export_wallets(&[recipient_id])
+                    .unwrap(),
+            )
+            .unwrap();
+
+        // --- Assert ---
+        assert!(caught_up.is_reconciled());
+        assert!(caught_up.debits_merged == 1);
+        assert!(behind.balance(&sender_id) == Some(Money::from_nano(90)));
+        assert!(conflicted.debits_merged == 0);
+        assert!(matches!(
+            &conflicted.conflicts[..],
+            [MergeConflict::ConflictingDebits { wallet, ours, theirs }]
+                if *wallet == sender_id
+                    && ours != theirs
+                    && *theirs == debit_proof.signed_transfer.transfer
+        ));
+        assert!(credited.wallets_added == vec![recipient_id]);
+        assert!(credited.credits_merged == 1);
+        assert!(recipient_behind.balance(&recipient_id) == Some(Money::from_nano(10)));
+        let merged_again = behind
+            .merge(
+                sender.replica_group.replicas[0]
+                    .export_wallets(&[sender_id])
+                    .unwrap(),
+            )
+            .unwrap();
+        assert!(merged_again == MergeReport::default());
+    }
+
+    #[test]
+    fn revalidates_already_signed_debits() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let replica = &mut sender.replica_group.replicas[0];
+        let validated = replica.validate(transfer.signed_transfer.clone()).unwrap();
+        replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+
+        // --- Act ---
+        let retried = replica.validate(transfer.signed_transfer.clone()).unwrap();
+
+        // --- Assert ---
+        assert!(retried == validated);
+        let mut other = transfer.signed_transfer;
+        other.transfer.amount = Money::from_nano(1);
+        assert!(replica.validate(other).is_err());
+    }
+
+    #[test]
+    fn consolidates_small_credits() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let id = sender.actor.id();
+        let feature = WalletFeature::Consolidation(ConsolidationPolicy {
+            max_amount: Money::from_nano(100),
+            min_count: 1,
+        });
+        let signature = sender.actor.authorize_upgrade(&feature).unwrap();
+        for replica in &mut sender.replica_group.replicas {
+            let upgraded = replica
+                .upgrade_wallet(id, feature.clone(), id, &signature)
+                .unwrap();
+            replica.apply(ReplicaEvent::WalletUpgraded(upgraded));
+        }
+        let group = &mut sender.replica_group;
+        let summary = group.replicas[0]
+            .propose_consolidation(&id)
+            .unwrap()
+            .unwrap();
+        let shares: Vec<_> = group
+            .replicas
+            .iter()
+            .map(|replica| replica.sign_consolidation(&summary).unwrap())
+            .collect();
+        let signed_summary = SignedCreditSummary::combine(summary, &group.id, &shares).unwrap();
+
+        // --- Act ---
+        for replica in &mut group.replicas {
+            let consolidated = replica.consolidate(signed_summary.clone()).unwrap();
+            replica.apply(ReplicaEvent::CreditsConsolidated(consolidated));
+        }
+
+        // --- Assert ---
+        let replica = &group.replicas[0];
+        assert!(summary.count == 1);
+        assert!(replica.balance(&id) == Some(Money::from_nano(100)));
+        assert!(replica.credit_entries(&id).unwrap().is_empty());
+        assert!(replica.credits_since(&id, 1).unwrap().is_empty());
+        assert!(replica.propose_consolidation(&id).unwrap().is_none());
+        assert!(replica.consolidate(signed_summary).is_err());
+        assert!(replica.checkpoint().is_ok());
+    }
+
+    #[test]
+    fn proves_balances_with_a_quorum_of_shares() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let sender = actors.remove(&0).unwrap();
+        let id = sender.actor.id();
+        let group = &sender.replica_group;
+
+        // --- Act ---
+        let shares: Vec<_> = group
+            .replicas
+            .iter()
+            .map(|replica| replica.balance_proof(&id).unwrap().unwrap())
+            .collect();
+        let proof = BalanceProof::combine(&shares, &group.id).unwrap();
+
+        // --- Assert ---
+        assert!(shares.iter().all(|share| share.verify(&group.id).is_ok()));
+        assert!(proof.attestation.balance == Money::from_nano(100));
+        assert!(proof.attestation.credit_index == 1);
+        assert!(proof.attestation.debit_index == 0);
+        assert!(proof.verify(&PublicKey::Bls(group.id.public_key())).is_ok());
+        assert!(group.replicas[0]
+            .balance_proof(&get_random_pk())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn redirects_moved_wallets() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let id = sender.actor.id();
+        let new_group = get_random_pk();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let replica = &mut sender.replica_group.replicas[0];
+
+        // --- Act ---
+        let moved = replica.move_wallet(id, new_group).unwrap();
+        replica.apply(ReplicaEvent::WalletMoved(moved));
+
+        // --- Assert ---
+        assert!(replica.balance_or_redirect(&id) == Some(QueryResponse::Moved(new_group)));
+        assert!(matches!(
+            replica.validate(transfer.signed_transfer.clone()),
+            Err(TransferError::WalletMoved { new_group: key }) if key == new_group
+        ));
+        let restored = replica.restore_wallet(id).unwrap();
+        replica.apply(ReplicaEvent::WalletRestored(restored));
+        assert!(
+            replica.balance_or_redirect(&id) == Some(QueryResponse::Found(Money::from_nano(100)))
+        );
+        assert!(replica.validate(transfer.signed_transfer).is_ok());
+    }
+
+    #[test]
+    fn restores_from_event_store() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(2, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let store = EventStore::new(MemoryEventStore::new());
+        let mut replica = Replica::from_event_store(
+            keys.secret_key_share(0),
+            0,
+            keys.public_keys(),
+            store.clone(),
+        )
+        .unwrap();
+        let debit_proof = |sender: &mut TestActor, to| {
+            let transfer = init_transfer(sender, to);
+            validate_at_sender_replicas(transfer, sender).unwrap()
+        };
+        let first = debit_proof(&mut sender, recipient.actor.id());
+        let events = propagate_to_crediting_replicas(&first, &mut groups[1]);
+
+        // --- Act ---
+        replica.persist_and_apply(events[0].clone()).unwrap();
+        replica.snapshot_to_store().unwrap();
+        let known = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+        replica
+            .persist_and_apply(ReplicaEvent::KnownGroupAdded(KnownGroupAdded {
+                group: known,
+            }))
+            .unwrap();
+        let restored =
+            Replica::from_event_store(keys.secret_key_share(0), 0, keys.public_keys(), store)
+                .unwrap();
+
+        // --- Assert ---
+        assert!(restored.balance(&recipient.actor.id()) == Some(Money::from_nano(100)));
+        assert!(restored.to_snapshot().unwrap() == replica.to_snapshot().unwrap());
+    }
+
+    #[test]
+    fn enforces_economic_params() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 1_000]);
+        let mut sender = actors.remove(&0).unwrap();
+        let params = EconomicParams {
+            fee_schedule: FeeSchedule {
+                base: Money::from_nano(10),
+                per_million: 0,
+            },
+            dust_threshold: Money::from_nano(100),
+            min_reserve: Money::from_nano(90),
+        };
+        let replicas: Vec<_> = sender
+            .replica_group
+            .replicas
+            .drain(..)
+            .map(|replica| replica.with_economic_params(params))
+            .collect();
+        sender.replica_group.replicas = replicas;
+        let signed = sender.replica_group.replicas[0].economic_params().unwrap();
+        let too_much = sender
+            .actor
+            .transfer(Money::from_nano(901), get_random_pk())
+            .unwrap();
+
+        // --- Act ---
+        sender.actor.cache_economic_params(&signed).unwrap();
+
+        // --- Assert ---
+        assert!(signed.params == params);
+        let replica = &sender.replica_group.replicas[0];
+        assert!(matches!(
+            replica.handle_query(&TransferQuery::GetEconomicParams),
+            TransferQueryResponse::GetEconomicParams(Some(queried))
+                if queried.params.dust_threshold == Money::from_nano(100)
+        ));
+        assert!(sender
+            .actor
+            .preflight(Money::from_nano(99), get_random_pk())
+            .is_err());
+        let dust = Payout {
+            to: get_random_pk(),
+            amount: Money::from_nano(99),
+        };
+        assert!(sender.actor.disburse(vec![dust]).is_err());
+        assert!(matches!(
+            replica.validate(too_much.signed_transfer),
+            Err(TransferError::Rejected(rejected))
+                if rejected.reason.missing() == Some(Money::from_nano(1))
+        ));
+        assert!(sender
+            .actor
+            .transfer(Money::from_nano(901), get_random_pk())
+            .is_err());
+        assert!(sender
+            .actor
+            .transfer(Money::from_nano(99), get_random_pk())
+            .is_err());
+        let transfer = sender
+            .actor
+            .transfer(Money::from_nano(900), get_random_pk())
+            .unwrap();
+        assert!(replica.validate(transfer.signed_transfer).is_ok());
+    }
+
+    #[test]
+    fn credits_from_sections_chained_to_a_trusted_root() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let genesis = SecretKey::random();
+        let genesis_key = PublicKey::Bls(genesis.public_key());
+        let section_key = PublicKey::Bls(sender.replica_group.id.public_key());
+        let succession = KeySuccession {
+            successor: section_key,
+            group_sig: Signature::Bls(genesis.sign(bincode::serialize(&section_key).unwrap())),
+        };
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let replica = Replica::from_snapshot(
+            keys.secret_key_share(0),
+            0,
+            keys.public_keys(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+        .with_trusted_root(genesis_key);
+        let mut chain = SectionProofChain::new(genesis_key);
+        chain.push(succession.clone()).unwrap();
+        let mut untrusted = SectionProofChain::new(get_random_pk());
+        untrusted.links.push(succession);
+
+        // --- Act ---
+        let propagated = replica.receive_propagated_with_chain(&debit_proof, &chain);
+
+        // --- Assert ---
+        assert!(replica.receive_propagated(&debit_proof).is_err());
+        assert!(propagated.unwrap().debiting_replicas == section_key);
+        assert!(replica
+            .receive_propagated_with_chain(&debit_proof, &untrusted)
+            .is_err());
+    }
+
+    #[test]
+    fn adds_known_groups_chained_to_a_trust_anchor() {
+        // --- Arrange ---
+        let genesis = SecretKey::random();
+        let genesis_key = PublicKey::Bls(genesis.public_key());
+        let section = SecretKey::random();
+        let section_key = PublicKey::Bls(section.public_key());
+        let group = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+        let group_key = PublicKey::Bls(group.public_key());
+        let succession = |signer: &SecretKey, successor: PublicKey| KeySuccession {
+            successor,
+            group_sig: Signature::Bls(signer.sign(bincode::serialize(&successor).unwrap())),
+        };
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let mut replica = Replica::from_snapshot(
+            keys.secret_key_share(0),
+            0,
+            keys.public_keys(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+        .with_trust_anchors(TrustAnchors::new(vec![genesis_key]));
+        let mut chain = SectionProofChain::new(genesis_key);
+        chain.push(succession(&genesis, section_key)).unwrap();
+        chain.push(succession(&section, group_key)).unwrap();
+        let mut unanchored = SectionProofChain::new(section_key);
+        unanchored.push(succession(&section, group_key)).unwrap();
+        let mut elsewhere = SectionProofChain::new(genesis_key);
+        elsewhere.push(succession(&genesis, section_key)).unwrap();
+
+        // --- Act ---
+        let added = replica.add_known_group(group.clone(), chain.clone());
+        let unanchored = replica.add_known_group(group.clone(), unanchored);
+        let elsewhere = replica.add_known_group(group.clone(), elsewhere);
+        let forged = ReplicaEvent::KnownGroupChained(KnownGroupChained {
+            group: group.clone(),
+            chain: SectionProofChain::new(group_key),
+        });
+        let checked_forged = replica.apply_checked(forged);
+        let unchained = ReplicaEvent::KnownGroupAdded(KnownGroupAdded {
+            group: group.clone(),
+        });
+        let checked_unchained = replica.apply_checked(unchained);
+        let added = added.unwrap();
+        replica
+            .apply_checked(ReplicaEvent::KnownGroupChained(added.clone()))
+            .unwrap();
+
+        // --- Assert ---
+        assert!(added.chain == chain);
+        assert!(unanchored.is_err());
+        assert!(elsewhere.is_err());
+        assert!(checked_forged.is_err());
+        assert!(checked_unchained.is_err());
+        assert!(replica.trust_anchors().contains(&genesis_key));
+        assert!(replica.known_group(&group_key) == Some(&group));
+        assert!(replica.add_known_group(group, chain).is_err());
+    }
+
+    #[test]
+    fn dry_runs_upgrades() {
+        // --- Arrange ---
+        let (mut groups, mut actors) = get_network(2, 3, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut groups[1]);
+        let log: Vec<_> = events[..1]
+            .iter()
+            .map(|e| bincode::serialize(e).unwrap())
+            .collect();
+        let old = |bytes: &[u8]| SizeLimits::default().event(bytes);
+        // a new version that no longer credits on propagation
+        let new = |bytes: &[u8]| match SizeLimits::default().event(bytes)? {
+            ReplicaEvent::TransferPropagated(_) => {
+                Ok(ReplicaEvent::KnownGroupAdded(KnownGroupAdded {
+                    group: sender.replica_group.id.clone(),
+                }))
+            }
+            event => Ok(event),
+        };
+
+        // --- Act ---
+        let same = dry_run_upgrade(&log, old, old).unwrap();
+        let changed = dry_run_upgrade(&log, old, new).unwrap();
+
+        // --- Assert ---
+        assert!(same.is_safe());
+        assert!(same.event_count == log.len() as u64);
+        assert!(!changed.is_safe());
+        assert!(changed.diverging_accounts.contains(&recipient.actor.id()));
+        assert!(dry_run_upgrade(&log, old, |_: &[u8]| Err(Error::InvalidOperation)).is_err());
+    }
+
+    #[test]
+    fn reports_diverging_replicas() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let replicas = &mut sender.replica_group.replicas;
+        let registered = replicas[0].register(&debit_proof).unwrap().unwrap();
+        replicas[0].apply(ReplicaEvent::TransferRegistered(registered));
+        let commitments: Vec<_> = replicas[1..]
+            .iter()
+            .map(|replica| replica.state_commitment().unwrap())
+            .collect();
+
+        // --- Act ---
+        let report = replicas[0].check_consistency(&commitments).unwrap();
+        let agreement = replicas[1].check_consistency(&commitments[1..]).unwrap();
+
+        // --- Assert ---
+        assert!(!report.agreed);
+        assert!(report.dissenting.len() == 1 && report.dissenting.contains(&0));
+        assert!(report.diverging_accounts.len() == 1);
+        assert!(report.diverging_accounts.contains(&sender.actor.id()));
+        assert!(agreement.agreed && agreement.diverging_accounts.is_empty());
+    }
+
+    #[test]
+    fn quarantined_debits_require_confirmation() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let account_id = sender.actor.id();
+        let nonce = [7; 32];
+        for replica in &mut sender.replica_group.replicas {
+            let quarantined = replica.quarantine(account_id, nonce).unwrap();
+            replica.apply(ReplicaEvent::AccountQuarantined(quarantined));
+        }
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let replica = &sender.replica_group.replicas[0];
+
+        // --- Act ---
+        let unconfirmed = replica.validate(transfer.signed_transfer.clone());
+        let challenge = replica
+            .quarantine_challenge(&transfer.signed_transfer.transfer)
+            .unwrap()
+            .unwrap();
+        let confirmation = sender.actor.sign_challenge(&challenge).unwrap();
+        let confirmed =
+            replica.validate_confirmed(transfer.signed_transfer.clone(), &[], &confirmation);
+
+        // --- Assert ---
+        assert!(matches!(
+            unconfirmed,
+            Err(TransferError::ConfirmationRequired { challenge: c }) if c == challenge
+        ));
+        assert!(confirmed.is_ok());
+        let replica = &mut sender.replica_group.replicas[0];
+        let lifted = replica.lift_quarantine(account_id).unwrap();
+        replica.apply(ReplicaEvent::QuarantineLifted(lifted));
+        assert!(replica.validate(transfer.signed_transfer).is_ok());
+    }
+
+    #[test]
+    fn enumerates_accounts_by_owner_kind() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 4, hashmap![0 => 100, 1 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut recipient = actors.remove(&1).unwrap();
+        let section = PublicKey::Bls(sender.replica_group.id.public_key());
+        let transfer = init_transfer(&mut sender, section);
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let _ = propagate_to_crediting_replicas(&debit_proof, &mut recipient.replica_group);
+        let replica = &mut recipient.replica_group.replicas[0];
+        let counts_before = replica.owner_kind_counts();
+        replica.apply(ReplicaEvent::OwnerConditionAttached(
+            OwnerConditionAttached {
+                account_id: recipient.actor.id(),
+                condition: Condition::MaxAmount(Money::from_nano(10)),
+            },
+        ));
+
+        // --- Act ---
+        let counts = replica.owner_kind_counts();
+        let groups = replica.accounts_by_owner(OwnerKind::Group, 0, 10);
+        let skipped = replica.accounts_by_owner(OwnerKind::Group, 1, 10);
+
+        // --- Assert ---
+        assert!(
+            counts_before
+                == vec![(OwnerKind::Ed25519, 1), (OwnerKind::Group, 1)]
+                    .into_iter()
+                    .collect()
+        );
+        assert!(
+            counts
+                == vec![(OwnerKind::Conditioned, 1), (OwnerKind::Group, 1)]
+                    .into_iter()
+                    .collect()
+        );
+        assert!(groups == vec![section]);
+        assert!(skipped.is_empty());
+        assert!(replica
+            .accounts_by_owner(OwnerKind::Ed25519, 0, 10)
+            .is_empty());
+    }
 
-/// A received credit, contains the DebitAgreementProof from the sender Replicas,
-/// as well as the public key of those Replicas, for us to verify that they are valid Replicas.
-#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
-pub struct ReceivedCredit {
-    /// The sender's aggregated Replica signatures of the sender debit.
-    pub debit_proof: DebitAgreementProof,
-    /// The public key of the signing Replicas.
-    pub debiting_replicas: PublicKey,
-}
+    #[test]
+    fn upgraded_wallets_require_second_factor() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let second_factor = SecretKey::random();
+        let feature = WalletFeature::SecondFactor(PublicKey::Bls(second_factor.public_key()));
+        let signature = sender.actor.authorize_upgrade(&feature).unwrap();
+        let account_id = sender.actor.id();
+        let mut events = vec![];
+        for replica in &mut sender.replica_group.replicas {
+            let upgraded = replica
+                .upgrade_wallet(account_id, feature.clone(), account_id, &signature)
+                .unwrap();
+            replica.apply(ReplicaEvent::WalletUpgraded(upgraded.clone()));
+            events.push(ReplicaEvent::WalletUpgraded(upgraded));
+        }
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let signed_transfer = transfer.signed_transfer;
+        let witness = Witness {
+            key: PublicKey::Bls(second_factor.public_key()),
+            signature: Signature::Bls(
+                second_factor.sign(SignableTransfer::new(&signed_transfer.transfer).to_bytes()),
+            ),
+        };
+        let replica = &sender.replica_group.replicas[0];
 
-impl ReceivedCredit {
-    /// Get the transfer id
-    pub fn id(&self) -> TransferId {
-        self.debit_proof.id()
+        // --- Act ---
+        let unsigned = replica.validate(signed_transfer.clone());
+        let signed = replica.validate_with_witnesses(signed_transfer.clone(), &[witness.clone()]);
+        let unsigned_batch = replica.validate_batch(vec![signed_transfer.clone()]);
+        let signed_batch =
+            replica.validate_batch_with_witnesses(vec![(signed_transfer.clone(), vec![witness])]);
+
+        // --- Assert ---
+        assert!(unsigned.is_err());
+        assert!(signed.is_ok());
+        assert!(unsigned_batch[0].is_err());
+        assert!(signed_batch[0] == Ok(Some(signed.unwrap())));
+        assert!(replica.wallet_features(&account_id) == vec![feature.clone()]);
+        assert!(replica
+            .upgrade_wallet(account_id, feature, account_id, &signature)
+            .is_err());
+        let replayed = Replica::from_history(
+            SecretKeySet::random(0, &mut rand::thread_rng()).secret_key_share(0),
+            0,
+            sender.replica_group.id.clone(),
+            events,
+        );
+        assert!(replayed.wallet_features(&account_id).len() == 1);
     }
 
-    /// Get the amount of this transfer
-    pub fn amount(&self) -> Money {
-        self.debit_proof.amount()
+    #[test]
+    fn queries_balances_in_batch() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let sender = actors.remove(&0).unwrap();
+        let replica = &sender.replica_group.replicas[0];
+        let ids = vec![sender.actor.id(), get_random_pk()];
+
+        // --- Act ---
+        let balances = replica.balances(&ids);
+        let signed = replica.signed_balances(&ids).unwrap();
+
+        // --- Assert ---
+        assert!(balances == vec![Some(Money::from_nano(100)), None]);
+        assert!(signed.verify(&sender.replica_group.id).is_ok());
+        assert!(signed.balances[0] == (ids[0], Some(Money::from_nano(100))));
+        let mut forged = signed.clone();
+        forged.balances[1].1 = Some(Money::from_nano(1));
+        assert!(forged.verify(&sender.replica_group.id).is_err());
     }
 
-    /// Get the recipient of this transfer
-    pub fn from(&self) -> PublicKey {
-        self.debit_proof.from()
+    #[test]
+    fn validates_batches_of_debits() {
+        // --- Arrange ---
+        let client_safe_key = SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()));
+        let id = client_safe_key.public_key();
+        let mut account = Account::new(id);
+        account.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: id,
+            amount: Money::from_nano(10),
+        });
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let replica = Replica::from_snapshot(
+            keys.secret_key_share(0),
+            0,
+            keys.public_keys(),
+            Default::default(),
+            hashmap![id => account],
+            Default::default(),
+        );
+        let debit = |counter, amount| {
+            let transfer = Transfer {
+                id: Dot::new(id, counter),
+                to: get_random_pk(),
+                amount: Money::from_nano(amount),
+            };
+            let data = SignableTransfer::new(&transfer).to_bytes();
+            SignedTransfer {
+                actor_signature: client_safe_key.sign(&data),
+                transfer,
+            }
+        };
+        let first = debit(0, 4);
+        let batch = vec![first.clone(), first, debit(1, 5), debit(2, 2), debit(4, 1)];
+
+        // --- Act ---
+        let outcomes = replica.validate_batch(batch);
+
+        // --- Assert ---
+        assert!(outcomes.len() == 5);
+        assert!(outcomes[0].as_ref().map_or(false, |o| o.is_some()));
+        assert!(outcomes[1] == Ok(None));
+        assert!(outcomes[2].as_ref().map_or(false, |o| o.is_some()));
+        assert!(matches!(
+            &outcomes[3],
+            Err(TransferError::Rejected(rejected)) if rejected.reason
+                == RejectionReason::InsufficientBalance {
+                    balance: Money::from_nano(1),
+                    requested: Money::from_nano(2),
+                }
+        ));
+        assert!(matches!(
+            &outcomes[4],
+            Err(TransferError::Rejected(rejected)) if rejected.reason
+                == RejectionReason::OutOfOrder {
+                    expected_counter: 2,
+                    received_counter: 4,
+                }
+        ));
     }
 
-    /// Get the recipient of this transfer
-    pub fn to(&self) -> PublicKey {
-        self.debit_proof.to()
+    #[test]
+    fn outbox_holds_artifacts_until_acknowledged() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let debit_proof = validate_at_sender_replicas(transfer.clone(), &mut sender).unwrap();
+
+        // --- Act ---
+        let pending = sender.actor.outbox().pending();
+        let acknowledged = sender.actor.acknowledge(pending[0].sequence).unwrap();
+        sender
+            .actor
+            .apply(ActorEvent::OutboxAcknowledged(acknowledged));
+        let persisted = sender.actor.outbox().clone();
+
+        // --- Assert ---
+        assert!(pending.len() == 2);
+        assert!(pending[0].item == OutboxItem::Transfer(transfer.signed_transfer));
+        assert!(pending[1].item == OutboxItem::Registration(debit_proof));
+        assert!(persisted.pending().len() == 1);
+        assert!(!persisted.is_pending(pending[0].sequence));
+        assert!(sender.actor.acknowledge(pending[0].sequence).is_err());
+        let restored = sender.actor.clone().with_outbox(persisted.clone());
+        assert!(restored.outbox() == &persisted);
     }
-}
 
-// ------------------------------------------------------------
-//                      Actor
-// ------------------------------------------------------------
+    #[test]
+    fn settles_chained_transfers() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 4, hashmap![0 => 100, 1 => 0]);
+        let mut buyer = actors.remove(&0).unwrap();
+        let mut market = actors.remove(&1).unwrap();
+        let seller = get_random_pk();
+        let mut settlement = Settlement::new();
+        let paid = settlement
+            .add(
+                SettlementStep::Receive {
+                    from: buyer.actor.id(),
+                    amount: Money::from_nano(100),
+                },
+                &[],
+            )
+            .unwrap();
+        let _ = settlement
+            .add(
+                SettlementStep::Send {
+                    to: seller,
+                    amount: Money::from_nano(90),
+                },
+                &[paid],
+            )
+            .unwrap();
 
-/// An implementation of the ReplicaValidator, should contain the logic from upper layers
-/// for determining if a remote group of Replicas, represented by a PublicKey, is indeed valid.
-/// This is logic from the membership part of the system, and thus handled by the upper layers
-/// membership implementation.
-pub trait ReplicaValidator {
-    /// Determines if a remote group of Replicas, represented by a PublicKey, is indeed valid.
-    fn is_valid(&self, replica_group: PublicKey) -> bool;
-}
+        // --- Act ---
+        let before_payment = settlement.advance(&market.actor).unwrap();
+        let transfer = init_transfer(&mut buyer, market.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut buyer).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut buyer.replica_group);
+        let events = propagate_to_crediting_replicas(&debit_proof, &mut market.replica_group);
+        synch(&mut market, events);
+        let forward = settlement.advance(&market.actor).unwrap().unwrap();
+        market
+            .actor
+            .apply(ActorEvent::TransferInitiated(forward.clone()));
+        let in_flight = settlement.advance(&market.actor).unwrap();
+        let debit_proof = validate_at_sender_replicas(forward, &mut market).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut market.replica_group);
+        let after = settlement.advance(&market.actor).unwrap();
 
-/// Events raised by the Actor.
-#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
-pub enum ActorEvent {
-    /// Raised when a request to create
-    /// a transfer validation cmd for Replicas,
-    /// has been successful (valid on local state).
-    TransferInitiated(TransferInitiated),
-    /// Raised when an Actor receives a Replica transfer validation.
-    TransferValidationReceived(TransferValidationReceived),
-    /// Raised when the Actor has accumulated a
-    /// quorum of validations, and produced a RegisterTransfer cmd
-    /// for sending to Replicas.
-    TransferRegistrationSent(TransferRegistrationSent),
-    /// Raised when the Actor has received
-    /// unknown credits on querying Replicas.
-    TransfersSynched(TransfersSynched),
-}
+        // --- Assert ---
+        assert!(before_payment.is_none());
+        assert!(
+            settlement.completed_by(paid) == Some(buyer.actor.debit_entries()[0].transfer().id)
+        );
+        assert!(in_flight.is_none());
+        assert!(after.is_none());
+        assert!(settlement.is_complete());
+        assert!(settlement
+            .add(
+                SettlementStep::Send {
+                    to: seller,
+                    amount: Money::from_nano(1)
+                },
+                &[5]
+            )
+            .is_err());
+    }
 
-/// Raised when the Actor has received
-/// f.ex. credits that its Replicas were holding upon
-/// the propagation of them from a remote group of Replicas,
-/// or unknown debits that its Replicas were holding
-/// upon the registration of them from another
-/// instance of the same Actor.
-#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
-pub struct TransfersSynched {
-    /// Credits we don't have locally.
-    credits: Vec<ReceivedCredit>,
-    /// The debits we don't have locally.
-    debits: Vec<DebitAgreementProof>,
-}
+    #[test]
+    fn compares_replicas_by_state_only() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 1, hashmap![0 => 100]);
+        let replica = actors.remove(&0).unwrap().replica_group.replicas[0].clone();
 
-/// This event is raised by the Actor after having
-/// successfully created a transfer cmd to send to the
-/// Replicas for validation.
-#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
-pub struct TransferInitiated {
-    /// The transfer signed by the initiating Actor.
-    pub signed_transfer: SignedTransfer,
-}
+        // --- Act ---
+        let plugged = replica
+            .clone()
+            .with_time_source(ManualTimeSource::new(1_000, 0))
+            .with_event_store(EventStore::new(MemoryEventStore::new()));
+        let other = replica.clone().with_held_credits(1);
 
-impl TransferInitiated {
-    /// Get the transfer id
-    pub fn id(&self) -> TransferId {
-        self.signed_transfer.id()
+        // --- Assert ---
+        assert!(plugged == replica);
+        assert!(other != replica);
     }
-}
 
-/// Raised when a Replica responds with
-/// a successful validation of a transfer.
-#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
-pub struct TransferValidationReceived {
-    /// The event raised by a Replica.
-    validation: TransferValidated,
-    /// Added when quorum of validations
-    /// have been received from Replicas.
-    pub proof: Option<DebitAgreementProof>,
-}
+    #[test]
+    fn restores_replicas_from_snapshots() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let transfer = init_transfer(&mut sender, get_random_pk());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+        let (secret_key, index) = sender.replica_group.keys[0].clone();
+        let group = sender.replica_group.id.clone();
+        let other = SecretKeySet::random(0, &mut rand::thread_rng());
+        let replica = &sender.replica_group.replicas[0];
+        let restore = |bytes: &[u8]| {
+            Replica::try_from_snapshot(secret_key.clone(), index, group.clone(), bytes)
+        };
 
-/// Raised when the Actor has accumulated a
-/// quorum of validations, and produced a RegisterTransfer cmd
-/// for sending to Replicas.
-#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
-pub struct TransferRegistrationSent {
-    debit_proof: DebitAgreementProof,
-}
+        // --- Act ---
+        let bytes = replica.to_snapshot().unwrap();
+        let restored = restore(&bytes).unwrap();
+        let anchor = get_random_pk();
+        let anchored = replica.clone().with_trusted_root(anchor);
+        let restored_anchored = restore(&anchored.to_snapshot().unwrap()).unwrap();
+        let of_other_group =
+            Replica::try_from_snapshot(other.secret_key_share(0), 0, other.public_keys(), &bytes);
+        let truncated = restore(&bytes[..bytes.len() - 1]);
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
 
-#[allow(unused)]
-mod test {
-    use crate::{
-        actor::Actor, replica::Replica, Account, ActorEvent, ReplicaEvent, ReplicaValidator,
-        TransferInitiated,
-    };
-    use crdts::{
-        quickcheck::{quickcheck, TestResult},
-        Dot,
-    };
-    use rand::Rng;
-    use safe_nd::{
-        AccountId, ClientFullId, DebitAgreementProof, Money, PublicKey, SafeKey, Transfer,
-    };
-    use std::collections::{HashMap, HashSet};
-    use threshold_crypto::{PublicKeySet, SecretKey, SecretKeySet, SecretKeyShare};
+        // --- Assert ---
+        let id = sender.actor.id();
+        assert!(restored.balance(&id) == replica.balance(&id));
+        assert!(restored.debit_entries(&id) == replica.debit_entries(&id));
+        assert!(restored_anchored.trust_anchors().contains(&anchor));
+        assert!(
+            restored.checkpoint().unwrap().state_hash == replica.checkpoint().unwrap().state_hash
+        );
+        let mut unsupported = bytes.clone();
+        unsupported[0] = (SNAPSHOT_VERSION + 1) as u8;
+        assert!(restore(&unsupported).is_err());
+        assert!(of_other_group.is_err());
+        assert!(truncated.is_err());
+        assert!(restore(&corrupted).is_err());
+        #[cfg(feature = "zstd")]
+        {
+            let compressed = replica
+                .clone()
+                .with_snapshot_compression(crate::SnapshotCompression::Zstd)
+                .to_snapshot()
+                .unwrap();
+            assert!(restore(&compressed).unwrap().balance(&id) == replica.balance(&id));
+        }
+    }
 
-    macro_rules! hashmap {
-        ($( $key: expr => $val: expr ),*) => {{
-             let mut map = ::std::collections::HashMap::new();
-             $( let _ = map.insert($key, $val); )*
-             map
-        }}
+    #[test]
+    fn rebuilds_projections_from_events() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 4, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let registered = sender.replica_group.replicas[0]
+            .register(&debit_proof)
+            .unwrap()
+            .unwrap();
+        let propagated = recipient.replica_group.replicas[0]
+            .receive_propagated(&debit_proof)
+            .unwrap();
+        let events = vec![
+            ReplicaEvent::TransferRegistered(registered),
+            ReplicaEvent::TransferPropagated(propagated),
+        ];
+
+        // --- Act ---
+        let balances: BalancesProjection = rebuild_projection(&events);
+        let counters: CountersProjection = rebuild_projection(&events);
+        let mut volumes: VolumesProjection = rebuild_projection(&events);
+        let recorded = checkpoint(&mut sender.replica_group);
+        volumes.apply(&ReplicaEvent::CheckpointRecorded(CheckpointRecorded {
+            signed_checkpoint: recorded.clone(),
+        }));
+
+        // --- Assert ---
+        assert!(balances.0[&sender.actor.id()] == Money::zero());
+        assert!(balances.0[&recipient.actor.id()] == Money::from_nano(100));
+        assert!(counters.0[&sender.actor.id()] == 1);
+        assert!(volumes.epochs[&recorded.checkpoint.epoch] == Money::from_nano(100));
+        assert!(volumes.current == Money::zero());
     }
 
-    // ------------------------------------------------------------------------
-    // ------------------------ Basic Transfer --------------------------------
-    // ------------------------------------------------------------------------
+    #[test]
+    fn replays_streamed_events() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(2, 4, hashmap![0 => 100, 1 => 0]);
+        let mut sender = actors.remove(&0).unwrap();
+        let recipient = actors.remove(&1).unwrap();
+        let transfer = init_transfer(&mut sender, recipient.actor.id());
+        let debit_proof = validate_at_sender_replicas(transfer, &mut sender).unwrap();
+        let propagated = recipient.replica_group.replicas[0]
+            .receive_propagated(&debit_proof)
+            .unwrap();
+        let event = ReplicaEvent::TransferPropagated(propagated);
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let replay = |events: Vec<_>| {
+            Replica::from_history_iter(keys.secret_key_share(0), 0, keys.public_keys(), events)
+        };
+
+        // --- Act ---
+        let replayed = replay(vec![Ok(event.clone())]);
+        let failed = replay(vec![
+            Ok(event),
+            Err(Error::FailedToParse("truncated".into())),
+            Err(Error::FailedToParse("never read".into())),
+        ]);
+
+        // --- Assert ---
+        let replica = replayed.unwrap();
+        assert!(replica.balance(&recipient.actor.id()) == Some(Money::from_nano(100)));
+        assert!(matches!(failed, Err(error) if error.index == 1));
+    }
 
     #[test]
-    fn basic_transfer() {
-        let _ = transfer_between_actors(100, 10, 2, 3, 0, 1);
+    fn rejects_recipients_that_can_not_own_accounts() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+        let share = PublicKey::BlsShare(keys.secret_key_share(0).public_key_share());
+        let transfer = init_transfer(&mut sender, share);
+
+        // --- Act ---
+        let result = sender.replica_group.replicas[0].validate(transfer.signed_transfer);
+
+        // --- Assert ---
+        assert!(result == Err(TransferError::UnsupportedRecipientKey(share)));
     }
 
-    // #[test]
-    // fn reproduce_quickcheck_basic_transfer() {
-    //     let _ = transfer_between_actors(1, 0, 2, 4, 0, 1);
-    // }
+    #[test]
+    fn notarizes_receipts() {
+        // --- Arrange ---
+        let (_, mut actors) = get_network(1, 4, hashmap![0 => 100]);
+        let mut sender = actors.remove(&0).unwrap();
+        let mut debit_proofs = vec![];
+        for _ in 0..3 {
+            let transfer = sender
+                .actor
+                .transfer(Money::from_nano(10), get_random_pk())
+                .unwrap();
+            sender
+                .actor
+                .apply(ActorEvent::TransferInitiated(transfer.clone()));
+            let validations = sender
+                .replica_group
+                .replicas
+                .iter_mut()
+                .map(|replica| {
+                    let validated = replica.validate(transfer.signed_transfer.clone()).unwrap();
+                    replica.apply(ReplicaEvent::TransferValidated(validated.clone()));
+                    validated
+                })
+                .collect();
+            let mut debit_proof = None;
+            for received in sender.actor.receive_batch(validations) {
+                let received = received.unwrap();
+                debit_proof = debit_proof.or_else(|| received.proof.clone());
+                sender
+                    .actor
+                    .apply(ActorEvent::TransferValidationReceived(received));
+            }
+            let debit_proof = debit_proof.unwrap();
+            let registration = sender.actor.register(debit_proof.clone()).unwrap();
+            sender
+                .actor
+                .apply(ActorEvent::TransferRegistrationSent(registration));
+            register_at_debiting_replicas(&debit_proof, &mut sender.replica_group);
+            debit_proofs.push(debit_proof);
+        }
+        let now = Clock::default().now();
+        let batch = NotarizationBatch::new(&debit_proofs, now).unwrap();
+
+        // --- Act ---
+        let shares: Vec<_> = sender
+            .replica_group
+            .replicas
+            .iter()
+            .map(|replica| replica.sign_notarization(&debit_proofs, now).unwrap())
+            .collect();
+        let signed = SignedNotarization::combine(
+            batch.notarization().clone(),
+            &sender.replica_group.id,
+            &shares,
+        )
+        .unwrap();
+        let receipts = batch.export(&signed).unwrap();
+
+        // --- Assert ---
+        assert!(receipts.len() == 3);
+        for (receipt, debit_proof) in receipts.iter().zip(&debit_proofs) {
+            assert!(receipt.verify(&sender.replica_group.id).is_ok());
+            assert!(receipt.receipt == receipt_digest(debit_proof).unwrap());
+        }
+        let replica = &sender.replica_group.replicas[0];
+        assert!(replica
+            .sign_notarization(&debit_proofs, now + 3600)
+            .is_err());
+        let mut unregistered = debit_proofs[0].clone();
+        unregistered.signed_transfer.transfer.amount = Money::from_nano(1);
+        assert!(replica.sign_notarization(&[unregistered], now).is_err());
+    }
 
     #[test]
     fn quickcheck_basic_transfer() {
@@ -239,12 +5823,25 @@ mod test {
         synch(&mut recipient, events);
 
         // --- Assert ---
+        // Actor and Replicas hold the proofs of the transfer.
+        assert_proof(&recipient, &debit_proof);
         // Actor and Replicas have the correct balance.
         assert_balance(sender, Money::zero());
         assert_balance(recipient, Money::from_nano(recipient_final));
         TestResult::passed()
     }
 
+    fn assert_proof(actor: &TestActor, debit_proof: &DebitAgreementProof) {
+        let has_proof =
+            |entries: &[HistoryEntry]| entries.iter().any(|e| e.debit_proof() == Some(debit_proof));
+        assert!(has_proof(actor.actor.credit_entries()));
+        actor.replica_group.replicas.iter().for_each(|replica| {
+            assert!(has_proof(
+                &replica.credit_entries(&actor.actor.id()).unwrap()
+            ))
+        });
+    }
+
     fn assert_balance(actor: TestActor, amount: Money) {
         assert!(actor.actor.balance() == amount);
         actor
@@ -299,7 +5896,7 @@ mod test {
         replica_group: &mut ReplicaGroup,
     ) {
         for replica in &mut replica_group.replicas {
-            let registered = replica.register(debit_proof).unwrap();
+            let registered = replica.register(debit_proof).unwrap().unwrap();
             replica.apply(ReplicaEvent::TransferRegistered(registered));
         }
     }
@@ -328,6 +5925,29 @@ mod test {
             .apply(ActorEvent::TransfersSynched(transfers));
     }
 
+    // Checkpoint of the state of all accounts of the group.
+    fn checkpoint(replica_group: &mut ReplicaGroup) -> SignedCheckpoint {
+        let checkpoint = replica_group.replicas[0].checkpoint().unwrap();
+        let shares: Vec<_> = replica_group
+            .replicas
+            .iter_mut()
+            .map(|replica| {
+                let signed = replica.sign_checkpoint(&checkpoint).unwrap();
+                replica.apply(ReplicaEvent::CheckpointSigned(signed.clone()));
+                signed.replica_signature
+            })
+            .collect();
+        let signed_checkpoint =
+            SignedCheckpoint::combine(checkpoint, &replica_group.id, &shares).unwrap();
+        for replica in &mut replica_group.replicas {
+            let recorded = replica
+                .record_checkpoint(signed_checkpoint.clone())
+                .unwrap();
+            replica.apply(ReplicaEvent::CheckpointRecorded(recorded));
+        }
+        signed_checkpoint
+    }
+
     // ------------------------------------------------------------------------
     // ------------------------ Setup Helpers ---------------------------------
     // ------------------------------------------------------------------------
@@ -366,10 +5986,30 @@ mod test {
         PublicKey::from(SecretKey::random().public_key())
     }
 
+    fn get_anchored_chain(group: &PublicKeySet) -> (PublicKey, SectionProofChain) {
+        let genesis = SecretKey::random();
+        let anchor = PublicKey::Bls(genesis.public_key());
+        let successor = PublicKey::Bls(group.public_key());
+        let mut chain = SectionProofChain::new(anchor);
+        chain
+            .push(KeySuccession {
+                successor,
+                group_sig: Signature::Bls(genesis.sign(bincode::serialize(&successor).unwrap())),
+            })
+            .unwrap();
+        (anchor, chain)
+    }
+
     fn setup_account(balance: u64, replica_group: u8) -> TestAccount {
         let mut rng = rand::thread_rng();
-        let client_full_id = ClientFullId::new_ed25519(&mut rng);
+        setup_owned_account(balance, replica_group, ClientFullId::new_ed25519(&mut rng))
+    }
 
+    fn setup_owned_account(
+        balance: u64,
+        replica_group: u8,
+        client_full_id: ClientFullId,
+    ) -> TestAccount {
         let client_safe_key = SafeKey::client(client_full_id);
         let to = client_safe_key.public_id().public_key();
         let mut account = Account::new(to);
@@ -462,7 +6102,7 @@ mod test {
 
             let mut replicas = vec![];
             let group = group_keys[i].clone();
-            for (secret_key, index) in group.keys {
+            for (secret_key, index) in group.keys.clone() {
                 let peer_replicas = group.id.clone();
                 let other_groups = other.clone();
                 let accounts = group_accounts.clone();
@@ -480,6 +6120,7 @@ mod test {
             let _ = replica_groups.push(ReplicaGroup {
                 index: *i,
                 id: group.id,
+                keys: group.keys,
                 replicas,
             });
         }
@@ -516,6 +6157,7 @@ mod test {
     struct ReplicaGroup {
         index: u8,
         id: PublicKeySet,
+        keys: Vec<(SecretKeyShare, usize)>,
         replicas: Vec<Replica>,
     }
 
@@ -525,4 +6167,40 @@ mod test {
         id: PublicKeySet,
         keys: Vec<(SecretKeyShare, usize)>,
     }
+
+    #[derive(Debug, Default)]
+    struct CountingMetrics {
+        accepted: AtomicUsize,
+        rejected: AtomicUsize,
+        registered: AtomicUsize,
+        propagated: AtomicUsize,
+        verified: AtomicUsize,
+        wallets: AtomicUsize,
+    }
+
+    impl ReplicaMetrics for CountingMetrics {
+        fn validation_accepted(&self, _: &AccountId) {
+            let _ = self.accepted.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn validation_rejected(&self, _: &AccountId, _: &TransferError) {
+            let _ = self.rejected.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn debit_registered(&self, _: &AccountId) {
+            let _ = self.registered.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn credit_propagated(&self, _: &AccountId) {
+            let _ = self.propagated.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn signature_verified(&self, _: Duration) {
+            let _ = self.verified.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn wallet_count(&self, count: usize) {
+            self.wallets.store(count, Ordering::SeqCst);
+        }
+    }
 }
@@ -0,0 +1,237 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    clock::Timestamp,
+    error::RejectionReason,
+    money::{covers, saturating_add},
+    signer::TransferSigner,
+    SpendingLimitsSet,
+};
+use safe_nd::{AccountId, Error, Money, Result, Signature};
+use serde::{Deserialize, Serialize};
+
+/// The longest window of debits that a limit may span.
+pub const MAX_LIMIT_WINDOW: u64 = 1024;
+/// How long limits that are looser than those in effect wait before taking effect,
+/// in seconds, so that a leaked key can not lift them at once. Stricter limits
+/// take effect at once, and cancel looser ones still waiting.
+pub const LIMITS_LOOSENING_DELAY: u64 = 24 * 60 * 60;
+
+/// The most that a number of consecutive debits may move in all.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WindowLimit {
+    /// The number of debits, the one validated included.
+    pub debits: u64,
+    /// The most they may move in all.
+    pub total: Money,
+}
+
+/// Limits on the debits of a wallet, set by its owner, such as to bound what a
+/// hot wallet can lose if its key leaks. Replicas only validate the debits of the
+/// wallet that are within them (see Replica::set_spending_limits).
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct SpendingLimits {
+    /// The most a single debit may move.
+    pub per_debit: Option<Money>,
+    /// The most the last debits may move in all.
+    pub per_window: Option<WindowLimit>,
+}
+
+impl SpendingLimits {
+    /// Checks that the window is within bounds.
+    pub fn check_bounds(&self) -> Result<()> {
+        match self.per_window {
+            Some(window) if window.debits == 0 || window.debits > MAX_LIMIT_WINDOW => {
+                Err(Error::from("Limit window is out of bounds"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether these limits are at least as strict as the other ones.
+    pub fn is_within(&self, other: &SpendingLimits) -> bool {
+        let per_debit = match (self.per_debit, other.per_debit) {
+            (_, None) => true,
+            (Some(ours), Some(theirs)) => covers(theirs, ours),
+            (None, Some(_)) => false,
+        };
+        let per_window = match (self.per_window, other.per_window) {
+            (_, None) => true,
+            (Some(ours), Some(theirs)) => {
+                ours.debits >= theirs.debits && covers(theirs.total, ours.total)
+            }
+            (None, Some(_)) => false,
+        };
+        per_debit && per_window
+    }
+
+    /// Checks the amount of a debit, given the amounts of the debits before it,
+    /// the latest last, of which as many as the window spans are counted.
+    pub fn check(
+        &self,
+        amount: Money,
+        previous: &[Money],
+    ) -> std::result::Result<(), RejectionReason> {
+        if let Some(limit) = self.per_debit {
+            if !covers(limit, amount) {
+                return Err(RejectionReason::SpendingLimit {
+                    limit,
+                    requested: amount,
+                });
+            }
+        }
+        if let Some(window) = self.per_window {
+            let counted = (window.debits - 1) as usize;
+            let requested = previous
+                .iter()
+                .rev()
+                .take(counted)
+                .fold(amount, |total, amount| saturating_add(total, *amount));
+            if !covers(window.total, requested) {
+                return Err(RejectionReason::SpendingLimit {
+                    limit: window.total,
+                    requested,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The limits of a wallet, signed by its key, with the number of updates of the
+/// limits of the wallet before it, so that an update can not be replayed.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedSpendingLimits {
+    /// The wallet.
+    pub wallet: AccountId,
+    /// The limits.
+    pub limits: SpendingLimits,
+    /// The number of updates before this one.
+    pub sequence: u64,
+    /// The signature of the key of the wallet over the above.
+    pub signature: Signature,
+}
+
+impl SignedSpendingLimits {
+    /// The limits of the wallet of the signer.
+    pub fn new<S: TransferSigner + ?Sized>(
+        signer: &S,
+        limits: SpendingLimits,
+        sequence: u64,
+    ) -> Result<Self> {
+        let wallet = signer.public_key();
+        let signature = signer.sign(&limits_payload(&wallet, &limits, sequence)?)?;
+        Ok(Self {
+            wallet,
+            limits,
+            sequence,
+            signature,
+        })
+    }
+
+    /// Verifies the bounds of the limits, and that they were signed by the wallet.
+    pub fn verify(&self) -> Result<()> {
+        self.limits.check_bounds()?;
+        let payload = limits_payload(&self.wallet, &self.limits, self.sequence)?;
+        self.wallet.verify(&self.signature, &payload)
+    }
+}
+
+/// The limits of a wallet held by a Replica: those in effect,
+/// and looser ones waiting to take effect, if any.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(crate) struct WalletLimits {
+    active: SpendingLimitsSet,
+    pending: Option<SpendingLimitsSet>,
+}
+
+impl WalletLimits {
+    pub(crate) fn new(set: SpendingLimitsSet) -> Self {
+        Self {
+            active: set,
+            pending: None,
+        }
+    }
+
+    /// The limits in effect at the time.
+    pub(crate) fn in_effect(&self, now: Timestamp) -> &SpendingLimits {
+        match &self.pending {
+            Some(pending) if pending.effective <= now => &pending.signed.limits,
+            _ => &self.active.signed.limits,
+        }
+    }
+
+    /// The sequence of the next update.
+    pub(crate) fn next_sequence(&self) -> u64 {
+        let latest = self.pending.as_ref().unwrap_or(&self.active);
+        latest.signed.sequence + 1
+    }
+
+    /// The updates, the one in effect first, for a snapshot.
+    pub(crate) fn sets(&self) -> Vec<SpendingLimitsSet> {
+        let mut sets = vec![self.active.clone()];
+        sets.extend(self.pending.clone());
+        sets
+    }
+
+    /// Applies an update, which replaces any that is still waiting.
+    pub(crate) fn update(&mut self, set: SpendingLimitsSet) {
+        if let Some(pending) = self.pending.take() {
+            if pending.effective <= set.set_at {
+                self.active = pending;
+            }
+        }
+        if set.effective <= set.set_at {
+            self.active = set;
+        } else {
+            self.pending = Some(set);
+        }
+    }
+}
+
+/// The bytes of limits signed by the wallet.
+fn limits_payload(wallet: &AccountId, limits: &SpendingLimits, sequence: u64) -> Result<Vec<u8>> {
+    match bincode::serialize(&(b"spending_limits", wallet, limits, sequence)) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise limits".into())),
+        Ok(data) => Ok(data),
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn limits_the_debits_of_a_window() {
+        // Arrange
+        let limits = SpendingLimits {
+            per_debit: Some(nanos(50)),
+            per_window: Some(WindowLimit {
+                debits: 3,
+                total: nanos(100),
+            }),
+        };
+        let previous = vec![nanos(50), nanos(30), nanos(40)];
+
+        // Act
+        let within = limits.check(nanos(30), &previous);
+        let over_window = limits.check(nanos(31), &previous);
+        let over_debit = limits.check(nanos(51), &[]);
+
+        // Assert
+        assert!(within.is_ok());
+        assert!(over_window.is_err());
+        assert!(over_debit.is_err());
+        assert!(limits.is_within(&SpendingLimits::default()));
+        assert!(!SpendingLimits::default().is_within(&limits));
+    }
+
+    fn nanos(amount: u64) -> Money {
+        Money::from_nano(amount)
+    }
+}
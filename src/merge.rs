@@ -0,0 +1,124 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use safe_nd::{AccountId, Transfer, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// What could not be merged of the history of a wallet held by another Replica
+/// (see Replica::merge), left for the group, or an operator, to resolve.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum MergeConflict {
+    /// Both histories have a debit of the wallet at the same counter, and they differ,
+    /// such as when each side of a netsplit agreed on a debit of its own. The debit of
+    /// the other history is not merged, nor are those after it.
+    ConflictingDebits {
+        /// The wallet.
+        wallet: AccountId,
+        /// Our debit.
+        ours: Transfer,
+        /// The debit of the other history.
+        theirs: Transfer,
+    },
+    /// An entry of the other history that does not verify, or does not apply to our
+    /// state, such as a debit the balance does not cover. After a debit, those that
+    /// follow it are not merged either.
+    Unmerged {
+        /// The wallet.
+        wallet: AccountId,
+        /// The transfer of the entry.
+        transfer_id: TransferId,
+        /// Why it was not merged.
+        reason: String,
+    },
+}
+
+impl MergeConflict {
+    /// The wallet of the conflict.
+    pub fn wallet(&self) -> AccountId {
+        match self {
+            MergeConflict::ConflictingDebits { wallet, .. }
+            | MergeConflict::Unmerged { wallet, .. } => *wallet,
+        }
+    }
+}
+
+/// The outcome of merging the wallets held by another Replica (see Replica::merge).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct MergeReport {
+    /// The wallets we did not hold before the merge, in the order exported.
+    pub wallets_added: Vec<AccountId>,
+    /// Number of credits, and payouts, merged.
+    pub credits_merged: usize,
+    /// Number of debits merged.
+    pub debits_merged: usize,
+    /// What could not be merged.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeReport {
+    /// Whether everything of the other histories is now held by us.
+    pub fn is_reconciled(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// The wallets with conflicts, in order.
+    pub fn conflicted_wallets(&self) -> BTreeSet<AccountId> {
+        self.conflicts.iter().map(MergeConflict::wallet).collect()
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn lists_wallets_with_conflicts() {
+        // Arrange
+        let (wallet, other) = (get_random_pk(), get_random_pk());
+        let debit = |to| Transfer {
+            id: Dot::new(wallet, 0),
+            to,
+            amount: safe_nd::Money::from_nano(10),
+        };
+        let report = MergeReport {
+            conflicts: vec![
+                MergeConflict::ConflictingDebits {
+                    wallet,
+                    ours: debit(get_random_pk()),
+                    theirs: debit(get_random_pk()),
+                },
+                MergeConflict::Unmerged {
+                    wallet: other,
+                    transfer_id: Dot::new(get_random_pk(), 0),
+                    reason: "Invalid signature".to_string(),
+                },
+                MergeConflict::Unmerged {
+                    wallet,
+                    transfer_id: Dot::new(wallet, 1),
+                    reason: "Non-sequential operation".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        // Act
+        let wallets = report.conflicted_wallets();
+
+        // Assert
+        assert!(!report.is_reconciled());
+        assert!(wallets.len() == 2 && wallets.contains(&wallet) && wallets.contains(&other));
+        assert!(MergeReport::default().is_reconciled());
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    checkpoint::CheckpointedHistory,
+    disbursement::{Disbursement, PayoutProof},
+    economics::SignedEconomicParams,
+    escrow::{EscrowRequest, EscrowResolution},
+    finality::TransferLifecycle,
+    limits::{SignedSpendingLimits, SpendingLimits},
+    pending::DebitRelease,
+    refund::Refund,
+    replica::QueryResponse,
+    rotation::WalletKeyRotation,
+    ActorEvent, ReplicaEvent,
+};
+use safe_nd::{
+    AccountId, DebitAgreementProof, Money, SignedTransfer, Transfer, TransferId, TransferValidated,
+};
+use serde::{Deserialize, Serialize};
+
+/// The cmds handled by Replicas, each mapping to the Replica cmd of the same name,
+/// for transports to send as they are (see Replica::handle_cmd).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum TransferCmd {
+    /// Validate a debit (see Replica::validate).
+    ValidateTransfer(SignedTransfer),
+    /// Register an agreed debit (see Replica::register).
+    RegisterTransfer(DebitAgreementProof),
+    /// Credit an agreed debit to its recipient (see Replica::receive_propagated).
+    PropagateTransfer(DebitAgreementProof),
+    /// Validate a refund of a credit (see Replica::validate_refund).
+    ValidateRefund(Refund),
+    /// Hold a debit in escrow (see Replica::open_escrow).
+    OpenEscrow(EscrowRequest),
+    /// Accept a debit held in escrow (see Replica::accept_escrow).
+    AcceptEscrow(EscrowResolution),
+    /// Cancel a debit held in escrow (see Replica::cancel_escrow).
+    CancelEscrow(EscrowResolution),
+    /// Set limits on the debits of a wallet (see Replica::set_spending_limits).
+    SetSpendingLimits(SignedSpendingLimits),
+    /// Hand a wallet over to a new key (see Replica::rotate_wallet_key).
+    RotateWalletKey(WalletKeyRotation),
+    /// Validate a disbursement of many payouts (see Replica::validate_disbursement).
+    ValidateDisbursement(Disbursement),
+    /// Register the agreed debit of a disbursement (see Replica::register_disbursement).
+    RegisterDisbursement {
+        /// The proof of the debit.
+        debit_proof: DebitAgreementProof,
+        /// The disbursement.
+        disbursement: Disbursement,
+    },
+    /// Credit a payout of a disbursement to its recipient (see Replica::receive_payout).
+    PropagatePayout(PayoutProof),
+    /// Release a debit aborted by its Actor (see Replica::release_debit).
+    ReleaseDebit(DebitRelease),
+}
+
+/// The cmds handled by Actors, each mapping to the Actor cmd of the same name, for
+/// transports to deliver what Replicas and clients send as they are (see Actor::handle_cmd).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum ActorCmd {
+    /// Initiate a debit (see Actor::transfer).
+    Transfer {
+        /// The amount.
+        amount: Money,
+        /// The recipient.
+        to: AccountId,
+    },
+    /// Receive the validation of a debit by a Replica (see Actor::receive).
+    ReceiveValidation(TransferValidated),
+    /// Send an agreed debit for registration (see Actor::register).
+    RegisterTransfer(DebitAgreementProof),
+    /// Abort the debit in flight (see Actor::abort).
+    AbortTransfer(TransferId),
+    /// Synch the credits and debits of events of Replicas (see Actor::synch).
+    SynchTransfers(Vec<ReplicaEvent>),
+    /// Continue from the last checkpoint of our Replicas (see Actor::synch_from_checkpoint).
+    SynchFromCheckpoint(CheckpointedHistory),
+    /// Acknowledge the delivery of an artifact of the outbox (see Actor::acknowledge).
+    Acknowledge(u64),
+}
+
+/// The queries handled by Replicas, each mapping to the Replica query
+/// of the same name (see Replica::handle_query).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum TransferQuery {
+    /// The balance of a wallet (see Replica::balance_or_redirect).
+    GetBalance(AccountId),
+    /// The credits of a wallet since an index (see Replica::credits_since).
+    GetCredits {
+        /// The wallet.
+        wallet: AccountId,
+        /// The index of the first credit.
+        since: usize,
+    },
+    /// The debits of a wallet since an index (see Replica::debits_or_redirect).
+    GetDebits {
+        /// The wallet.
+        wallet: AccountId,
+        /// The index of the first debit.
+        since: usize,
+    },
+    /// The debits of a wallet to a counterparty (see Replica::debits_to).
+    GetDebitsTo {
+        /// The wallet.
+        wallet: AccountId,
+        /// The recipient of the debits.
+        counterparty: AccountId,
+    },
+    /// The credits of a wallet from a counterparty (see Replica::credits_from).
+    GetCreditsFrom {
+        /// The wallet.
+        wallet: AccountId,
+        /// The sender of the credits.
+        counterparty: AccountId,
+    },
+    /// The credits of a wallet tagged by their sender (see Replica::credits_tagged).
+    GetCreditsTagged {
+        /// The wallet.
+        wallet: AccountId,
+        /// The tag.
+        tag: String,
+    },
+    /// The limits on the debits of a wallet (see Replica::spending_limits).
+    GetSpendingLimits(AccountId),
+    /// The economic rules of the section, such as the smallest amount
+    /// transferred (see Replica::economic_params).
+    GetEconomicParams,
+    /// Where a transfer is in its lifecycle (see Replica::transfer_status).
+    GetTransferStatus(TransferId),
+}
+
+/// The responses to the queries, in the variant of the same name. None when the
+/// Replica does not hold the wallet.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum TransferQueryResponse {
+    /// The response to GetBalance.
+    GetBalance(Option<QueryResponse<Money>>),
+    /// The response to GetCredits.
+    GetCredits(Option<Vec<Transfer>>),
+    /// The response to GetDebits.
+    GetDebits(Option<QueryResponse<Vec<Transfer>>>),
+    /// The response to GetDebitsTo.
+    GetDebitsTo(Option<Vec<Transfer>>),
+    /// The response to GetCreditsFrom.
+    GetCreditsFrom(Option<Vec<Transfer>>),
+    /// The response to GetCreditsTagged.
+    GetCreditsTagged(Option<Vec<Transfer>>),
+    /// The response to GetSpendingLimits.
+    GetSpendingLimits(Option<SpendingLimits>),
+    /// The response to GetEconomicParams, None if the Replica could not sign them.
+    GetEconomicParams(Option<SignedEconomicParams>),
+    /// The response to GetTransferStatus.
+    GetTransferStatus(TransferLifecycle),
+    /// Queries are disabled on the Replica (see Subsystem::Queries), to query another one.
+    Disabled,
+}
+
+/// The events of Replicas and Actors, for transports to send as they are,
+/// such as when synching an Actor with the events of its Replicas.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum TransferEvent {
+    /// An event of a Replica.
+    Replica(ReplicaEvent),
+    /// An event of an Actor.
+    Actor(ActorEvent),
+}
@@ -0,0 +1,105 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::error::TransferError;
+use safe_nd::AccountId;
+use std::{
+    fmt::{self, Debug, Formatter},
+    ops::Deref,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Callbacks of a Replica on what it does, for operators to feed counters and
+/// histograms, such as of Prometheus, from (see Replica::with_metrics).
+/// Every callback does nothing by default, so only those of interest need be implemented.
+/// They are called on the hot paths of the Replica, so are to return quickly.
+pub trait ReplicaMetrics: Send + Sync {
+    /// A debit of the wallet was validated.
+    fn validation_accepted(&self, _wallet: &AccountId) {}
+
+    /// A debit of the wallet was rejected, for the reason.
+    fn validation_rejected(&self, _wallet: &AccountId, _reason: &TransferError) {}
+
+    /// A debit of the wallet was registered.
+    fn debit_registered(&self, _wallet: &AccountId) {}
+
+    /// A credit to the wallet was propagated.
+    fn credit_propagated(&self, _wallet: &AccountId) {}
+
+    /// A signature was verified, taking the time. Outcomes
+    /// served from the verification cache are not reported.
+    fn signature_verified(&self, _latency: Duration) {}
+
+    /// The number of wallets held changed to the count.
+    fn wallet_count(&self, _count: usize) {}
+}
+
+impl<T: ReplicaMetrics + ?Sized> ReplicaMetrics for Arc<T> {
+    fn validation_accepted(&self, wallet: &AccountId) {
+        (**self).validation_accepted(wallet)
+    }
+
+    fn validation_rejected(&self, wallet: &AccountId, reason: &TransferError) {
+        (**self).validation_rejected(wallet, reason)
+    }
+
+    fn debit_registered(&self, wallet: &AccountId) {
+        (**self).debit_registered(wallet)
+    }
+
+    fn credit_propagated(&self, wallet: &AccountId) {
+        (**self).credit_propagated(wallet)
+    }
+
+    fn signature_verified(&self, latency: Duration) {
+        (**self).signature_verified(latency)
+    }
+
+    fn wallet_count(&self, count: usize) {
+        (**self).wallet_count(count)
+    }
+}
+
+/// Metrics that are not recorded, as by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoMetrics;
+
+impl ReplicaMetrics for NoMetrics {}
+
+/// A cheaply cloneable handle to the ReplicaMetrics of an instance.
+/// Two handles are equal if they report to the same metrics.
+#[derive(Clone)]
+pub(crate) struct Metrics(Arc<dyn ReplicaMetrics>);
+
+impl Metrics {
+    /// Wraps the given metrics.
+    pub(crate) fn new<M: ReplicaMetrics + 'static>(metrics: M) -> Self {
+        Self(Arc::new(metrics))
+    }
+}
+
+impl Deref for Metrics {
+    type Target = dyn ReplicaMetrics;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(NoMetrics)
+    }
+}
+
+impl Debug for Metrics {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Metrics")
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use safe_nd::{Error, Money, Result};
+
+/// The number of nanos in one whole token.
+pub const NANOS_PER_TOKEN: u64 = 1_000_000_000;
+/// The number of decimals of one nano.
+const DECIMALS: usize = 9;
+
+/// Formats the amount as whole tokens with nano precision, e.g. "1.500000000".
+/// The format does not depend on locale.
+pub fn format_money(amount: Money) -> String {
+    let nanos = amount.as_nano();
+    format!("{}.{:09}", nanos / NANOS_PER_TOKEN, nanos % NANOS_PER_TOKEN)
+}
+
+/// Formats the amount as whole tokens, without trailing zeros, e.g. "1.5" or "2".
+pub fn format_money_trimmed(amount: Money) -> String {
+    let formatted = format_money(amount);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Parses user input of whole tokens, such as "10", "1.5" or "0.000000001".
+/// Only ascii digits and a single decimal point are accepted (no signs, separators or exponents),
+/// surrounding whitespace aside. Input with more precision than a nano is rejected,
+/// instead of being rounded, as are amounts that do not fit.
+pub fn parse_money(input: &str) -> Result<Money> {
+    let input = input.trim();
+    let (whole, fraction) = match input.find('.') {
+        Some(index) => (&input[..index], Some(&input[index + 1..])),
+        None => (input, None),
+    };
+    if !is_digits(whole) {
+        return Err(Error::FailedToParse(format!("Invalid amount: {:?}", input)));
+    }
+    let fraction_nanos = match fraction {
+        None => 0,
+        Some(fraction) => {
+            if !is_digits(fraction) {
+                return Err(Error::FailedToParse(format!("Invalid amount: {:?}", input)));
+            }
+            if fraction.len() > DECIMALS && fraction[DECIMALS..].bytes().any(|b| b != b'0') {
+                return Err(Error::LossOfPrecision);
+            }
+            let significant = &fraction[..fraction.len().min(DECIMALS)];
+            let padded = format!("{:0<width$}", significant, width = DECIMALS);
+            match padded.parse::<u64>() {
+                Ok(nanos) => nanos,
+                Err(_) => return Err(Error::FailedToParse(format!("Invalid amount: {:?}", input))),
+            }
+        }
+    };
+    let whole_tokens = match whole.parse::<u64>() {
+        Ok(tokens) => tokens,
+        Err(_) => return Err(Error::ExcessiveValue),
+    };
+    match whole_tokens
+        .checked_mul(NANOS_PER_TOKEN)
+        .and_then(|nanos| nanos.checked_add(fraction_nanos))
+    {
+        Some(nanos) => Ok(Money::from_nano(nanos)),
+        None => Err(Error::ExcessiveValue),
+    }
+}
+
+/// Whether the balance covers the amount.
+/// All comparisons of amounts against balances in transfer paths go through this.
+pub fn covers(balance: Money, amount: Money) -> bool {
+    amount.as_nano() <= balance.as_nano()
+}
+
+/// The balance after crediting the amount, failing if it would not fit.
+pub fn credited(balance: Money, amount: Money) -> Result<Money> {
+    match balance.as_nano().checked_add(amount.as_nano()) {
+        Some(nanos) => Ok(Money::from_nano(nanos)),
+        None => Err(Error::ExcessiveValue),
+    }
+}
+
+/// The balance after debiting the amount, failing if the balance does not cover it.
+pub fn debited(balance: Money, amount: Money) -> Result<Money> {
+    match balance.as_nano().checked_sub(amount.as_nano()) {
+        Some(nanos) => Ok(Money::from_nano(nanos)),
+        None => Err(Error::InsufficientBalance),
+    }
+}
+
+/// The sum of the amounts, None if it would not fit.
+pub fn checked_sum<I: IntoIterator<Item = Money>>(amounts: I) -> Option<Money> {
+    amounts
+        .into_iter()
+        .try_fold(Money::zero(), |sum, amount| credited(sum, amount).ok())
+}
+
+/// The sum of the amounts, capped at the maximum amount.
+pub fn saturating_add(first: Money, second: Money) -> Money {
+    Money::from_nano(first.as_nano().saturating_add(second.as_nano()))
+}
+
+/// The difference of the amounts, zero if the second is larger.
+pub fn saturating_sub(first: Money, second: Money) -> Money {
+    Money::from_nano(first.as_nano().saturating_sub(second.as_nano()))
+}
+
+fn is_digits(input: &str) -> bool {
+    !input.is_empty() && input.bytes().all(|b| b.is_ascii_digit())
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_with_nano_precision() {
+        assert!(format_money(Money::from_nano(1_500_000_000)) == "1.500000000");
+        assert!(format_money(Money::from_nano(1)) == "0.000000001");
+        assert!(format_money_trimmed(Money::from_nano(1_500_000_000)) == "1.5");
+        assert!(format_money_trimmed(Money::from_nano(2_000_000_000)) == "2");
+        assert!(format_money_trimmed(Money::zero()) == "0");
+    }
+
+    #[test]
+    fn parses_whole_and_fractional_tokens() {
+        assert!(parse_money("10").unwrap() == Money::from_nano(10 * NANOS_PER_TOKEN));
+        assert!(parse_money(" 1.5 ").unwrap() == Money::from_nano(1_500_000_000));
+        assert!(parse_money("0.000000001").unwrap() == Money::from_nano(1));
+        assert!(parse_money("1.0000000000").unwrap() == Money::from_nano(NANOS_PER_TOKEN));
+    }
+
+    #[test]
+    fn rejects_malformed_or_lossy_input() {
+        for input in &[
+            "", ".", "1.", ".5", "-1", "+1", "1,5", "1_000", "1e9", "1.2.3",
+        ] {
+            assert!(parse_money(input).is_err());
+        }
+        assert!(parse_money("0.0000000001") == Err(Error::LossOfPrecision));
+        assert!(parse_money("18446744073.709551616") == Err(Error::ExcessiveValue));
+    }
+
+    #[test]
+    fn arithmetic_at_the_edges() {
+        let max = Money::from_nano(u64::max_value());
+        let one = Money::from_nano(1);
+        let zero = Money::zero();
+        for amount in &[zero, one, max] {
+            assert!(covers(*amount, *amount));
+            assert!(covers(*amount, zero));
+            assert!(covers(max, *amount));
+            assert!(debited(*amount, *amount) == Ok(zero));
+            assert!(credited(zero, *amount) == Ok(*amount));
+            assert!(saturating_sub(zero, *amount) == zero);
+            assert!(saturating_add(max, *amount) == max);
+        }
+        assert!(!covers(zero, one));
+        assert!(!covers(Money::from_nano(u64::max_value() - 1), max));
+        assert!(credited(max, one) == Err(Error::ExcessiveValue));
+        assert!(debited(zero, one) == Err(Error::InsufficientBalance));
+        assert!(checked_sum(vec![max, zero]) == Some(max));
+        assert!(checked_sum(vec![max, one]).is_none());
+        assert!(checked_sum(vec![]) == Some(zero));
+    }
+
+    #[test]
+    fn format_and_parse_roundtrip() {
+        for nanos in &[0, 1, 999_999_999, 1_000_000_000, u64::max_value()] {
+            let amount = Money::from_nano(*nanos);
+            assert!(parse_money(&format_money(amount)).unwrap() == amount);
+            assert!(parse_money(&format_money_trimmed(amount)).unwrap() == amount);
+        }
+    }
+}
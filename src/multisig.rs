@@ -0,0 +1,233 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    policy::Witness,
+    quorum::{is_quorum, shares_needed},
+    signable::{Signable, SignableTransfer},
+};
+use safe_nd::{AccountId, Error, PublicKey, Result, Signature, SignedTransfer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use threshold_crypto::{PublicKeySet, SignatureShare};
+
+/// The maximum number of keys owning a multisig wallet.
+pub const MAX_MULTISIG_KEYS: usize = 64;
+
+/// The owners of a wallet held by several keys, such as by an exchange or an
+/// organisation, and how many of them must sign its debits. Replicas only validate
+/// the debits of the wallet that are signed by enough of its owners
+/// (see Replica::set_multisig_policy).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum MultisigPolicy {
+    /// The owners each hold a share of the key of the set, which is the key of the wallet.
+    /// Any threshold + 1 of them sign a debit by combining their shares into the signature
+    /// of the transfer (see combine_shares), so it carries no other signatures.
+    KeySet(PublicKeySet),
+    /// At least `threshold` of the keys sign a debit as witnesses (see Witness).
+    /// The transfer itself is signed by any of them, in place of the key of the wallet,
+    /// which is not needed once the wallet is handed over to the policy.
+    Keys {
+        /// The keys of the owners.
+        keys: Vec<PublicKey>,
+        /// How many of them must sign.
+        threshold: usize,
+    },
+}
+
+impl MultisigPolicy {
+    /// Checks that the policy can own the wallet, and can be satisfied.
+    pub fn check(&self, wallet: &AccountId) -> Result<()> {
+        match self {
+            MultisigPolicy::KeySet(keys) => {
+                if *wallet != PublicKey::Bls(keys.public_key()) {
+                    return Err(Error::from("Wallet is not the key of the set"));
+                }
+            }
+            MultisigPolicy::Keys { keys, threshold } => {
+                if keys.len() > MAX_MULTISIG_KEYS {
+                    return Err(Error::ExceededSize);
+                }
+                let unique: BTreeSet<_> = keys.iter().collect();
+                if unique.len() != keys.len() || *threshold == 0 || *threshold > keys.len() {
+                    return Err(Error::InvalidOwners);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of owners that must sign a debit.
+    pub fn required(&self) -> usize {
+        match self {
+            MultisigPolicy::KeySet(keys) => shares_needed(keys),
+            MultisigPolicy::Keys { threshold, .. } => *threshold,
+        }
+    }
+
+    /// Whether enough owners signed the transfer. Only signatures that verify count.
+    pub fn is_satisfied(&self, signed_transfer: &SignedTransfer, witnesses: &[Witness]) -> bool {
+        let transfer = &signed_transfer.transfer;
+        match self {
+            // a valid signature by the key of the set is combined from enough shares
+            MultisigPolicy::KeySet(keys) => SignableTransfer::new(transfer)
+                .verify(
+                    &PublicKey::Bls(keys.public_key()),
+                    &signed_transfer.actor_signature,
+                )
+                .is_ok(),
+            MultisigPolicy::Keys { keys, threshold } => {
+                let signers: BTreeSet<_> = witnesses
+                    .iter()
+                    .filter(|w| keys.contains(&w.key) && w.verify(transfer).is_ok())
+                    .map(|w| w.key)
+                    .collect();
+                signers.len() >= *threshold
+            }
+        }
+    }
+
+    /// The keys that may sign the transfer of a debit of the wallet, in place of its own.
+    pub fn signers(&self) -> &[PublicKey] {
+        match self {
+            MultisigPolicy::KeySet(_) => &[],
+            MultisigPolicy::Keys { keys, .. } => keys,
+        }
+    }
+
+    /// The bytes that the key of the wallet signs to be owned by the policy.
+    pub fn to_bytes(&self, wallet: &AccountId) -> Result<Vec<u8>> {
+        match bincode::serialize(&(b"multisig_policy", wallet, self)) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise policy".into())),
+            Ok(data) => Ok(data),
+        }
+    }
+}
+
+/// The signature of the key of the set over the payload, such as that of a
+/// SigningRequest, combined from the shares of its owners, by their index.
+/// Each share is verified, so that an invalid one is pointed out.
+pub fn combine_shares(
+    keys: &PublicKeySet,
+    payload: &[u8],
+    shares: &BTreeMap<usize, SignatureShare>,
+) -> Result<Signature> {
+    if !is_quorum(keys, shares.len()) {
+        return Err(Error::from("Not enough signature shares"));
+    }
+    for (index, share) in shares {
+        if !keys.public_key_share(*index).verify(share, payload) {
+            return Err(Error::InvalidSignature);
+        }
+    }
+    match keys.combine_signatures(shares) {
+        Ok(signature) => Ok(Signature::Bls(signature)),
+        Err(_) => Err(Error::InvalidSignature),
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::{Money, Transfer};
+    use threshold_crypto::{SecretKey, SecretKeySet};
+
+    #[test]
+    fn requires_enough_owners() -> Result<()> {
+        // Arrange
+        let owners = get_owners(1);
+        let set = MultisigPolicy::KeySet(owners.public_keys());
+        let wallet = PublicKey::Bls(owners.public_keys().public_key());
+        let transfer = get_transfer(wallet);
+        let payload = get_payload(&transfer);
+        let sign = |index: usize| (index, owners.secret_key_share(index).sign(&payload));
+
+        // Act
+        let one: BTreeMap<_, _> = vec![sign(0)].into_iter().collect();
+        let two: BTreeMap<_, _> = vec![sign(0), sign(2)].into_iter().collect();
+        let signature = combine_shares(&owners.public_keys(), &payload, &two)?;
+        let combined = SignedTransfer {
+            transfer: transfer.clone(),
+            actor_signature: signature.clone(),
+        };
+        let single = SignedTransfer {
+            transfer,
+            actor_signature: Signature::BlsShare(safe_nd::SignatureShare {
+                index: 0,
+                share: owners.secret_key_share(0).sign(&payload),
+            }),
+        };
+
+        // Assert
+        assert!(set.check(&wallet).is_ok());
+        assert!(set.check(&get_random_pk()).is_err());
+        assert!(set.required() == 2);
+        assert!(combine_shares(&owners.public_keys(), &payload, &one).is_err());
+        assert!(wallet.verify(&signature, &payload).is_ok());
+        assert!(set.is_satisfied(&combined, &[]));
+        assert!(!set.is_satisfied(&single, &[]));
+        Ok(())
+    }
+
+    #[test]
+    fn counts_each_owner_once() {
+        // Arrange
+        let owners: Vec<_> = (0..3).map(|_| SecretKey::random()).collect();
+        let keys = owners
+            .iter()
+            .map(|sk| PublicKey::Bls(sk.public_key()))
+            .collect();
+        let policy = MultisigPolicy::Keys { keys, threshold: 2 };
+        let transfer = get_transfer(get_random_pk());
+        let payload = get_payload(&transfer);
+        let signed_transfer = SignedTransfer {
+            transfer,
+            actor_signature: Signature::Bls(owners[0].sign(&payload)),
+        };
+        let witness = |sk: &SecretKey| Witness {
+            key: PublicKey::Bls(sk.public_key()),
+            signature: Signature::Bls(sk.sign(&payload)),
+        };
+
+        // Act
+        let twice = vec![witness(&owners[0]), witness(&owners[0])];
+        let stranger = vec![witness(&owners[0]), witness(&SecretKey::random())];
+        let two = vec![witness(&owners[0]), witness(&owners[1])];
+
+        // Assert
+        assert!(policy.check(&get_random_pk()).is_ok());
+        assert!(!policy.is_satisfied(&signed_transfer, &twice));
+        assert!(!policy.is_satisfied(&signed_transfer, &stranger));
+        assert!(policy.is_satisfied(&signed_transfer, &two));
+        let unsatisfiable = MultisigPolicy::Keys {
+            keys: vec![get_random_pk()],
+            threshold: 2,
+        };
+        assert!(unsatisfiable.check(&get_random_pk()).is_err());
+    }
+
+    fn get_transfer(from: AccountId) -> Transfer {
+        Transfer {
+            id: Dot::new(from, 0),
+            to: get_random_pk(),
+            amount: Money::from_nano(1),
+        }
+    }
+
+    fn get_owners(threshold: usize) -> SecretKeySet {
+        SecretKeySet::random(threshold, &mut rand::thread_rng())
+    }
+
+    fn get_payload(transfer: &Transfer) -> Vec<u8> {
+        SignableTransfer::new(transfer).to_bytes()
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
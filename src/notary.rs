@@ -0,0 +1,159 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    clock::Timestamp,
+    hashing::{hash, merkle_proof, merkle_root, Digest, MerkleProof},
+};
+use safe_nd::{DebitAgreementProof, Error, PublicKey, Result, Signature, SignatureShare};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use threshold_crypto::PublicKeySet;
+
+/// How far, in seconds, the time of a notarization may be from the time of a notarizing Replica.
+pub const MAX_NOTARIZATION_SKEW: Timestamp = 300;
+
+/// The digest of a completed transfer, i.e. of its agreement proof.
+/// It reveals nothing of the transfer, but can be recomputed by anyone holding the proof.
+pub fn receipt_digest(debit_proof: &DebitAgreementProof) -> Result<Digest> {
+    match bincode::serialize(debit_proof) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise proof".into())),
+        Ok(data) => Ok(hash(&[&data])),
+    }
+}
+
+/// A commitment to a batch of receipts, at a point in time.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Notarization {
+    /// Merkle root of the receipt digests, in the order of the batch.
+    pub root: Digest,
+    /// Number of receipts in the batch.
+    pub receipt_count: u64,
+    /// When the batch was notarized.
+    pub notarized_at: Timestamp,
+}
+
+/// A batch of receipts, along with its notarization.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct NotarizationBatch {
+    notarization: Notarization,
+    receipts: Vec<Digest>,
+}
+
+impl NotarizationBatch {
+    /// Commits to the completed transfers, at the given time.
+    pub fn new(debit_proofs: &[DebitAgreementProof], notarized_at: Timestamp) -> Result<Self> {
+        let mut receipts = vec![];
+        for proof in debit_proofs {
+            receipts.push(receipt_digest(proof)?);
+        }
+        Ok(Self {
+            notarization: Notarization {
+                root: merkle_root(&receipts),
+                receipt_count: receipts.len() as u64,
+                notarized_at,
+            },
+            receipts,
+        })
+    }
+
+    /// The commitment to the batch.
+    pub fn notarization(&self) -> &Notarization {
+        &self.notarization
+    }
+
+    /// The notarized receipt of each transfer in the batch, for export.
+    pub fn export(&self, signed: &SignedNotarization) -> Result<Vec<NotarizedReceipt>> {
+        if signed.notarization != self.notarization {
+            return Err(Error::from("Signed notarization is not of this batch"));
+        }
+        Ok(self
+            .receipts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, receipt)| {
+                Some(NotarizedReceipt {
+                    signed_notarization: signed.clone(),
+                    receipt: *receipt,
+                    proof: merkle_proof(&self.receipts, index)?,
+                })
+            })
+            .collect())
+    }
+}
+
+/// A Notarization, signed by the group of Replicas that registered the transfers.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SignedNotarization {
+    /// The commitment.
+    pub notarization: Notarization,
+    /// The aggregated signature of the Replicas.
+    pub group_sig: Signature,
+}
+
+impl SignedNotarization {
+    /// Combines the signature shares from a quorum of the
+    /// Replicas (see Replica::sign_notarization) into a signed notarization.
+    pub fn combine(
+        notarization: Notarization,
+        replicas: &PublicKeySet,
+        shares: &[SignatureShare],
+    ) -> Result<Self> {
+        let sig_shares: BTreeMap<_, _> =
+            shares.iter().map(|s| (s.index, s.share.clone())).collect();
+        let sig = match replicas.combine_signatures(&sig_shares) {
+            Ok(sig) => sig,
+            Err(_) => return Err(Error::from("Not enough signature shares")),
+        };
+        let signed = SignedNotarization {
+            notarization,
+            group_sig: Signature::Bls(sig),
+        };
+        signed.verify(replicas)?;
+        Ok(signed)
+    }
+
+    /// Verifies that the notarization was signed by the given Replicas.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        match bincode::serialize(&self.notarization) {
+            Err(_) => Err(Error::NetworkOther(
+                "Could not serialise notarization".into(),
+            )),
+            Ok(data) => PublicKey::Bls(replicas.public_key()).verify(&self.group_sig, &data),
+        }
+    }
+}
+
+/// The receipt of a single transfer, with the proof that it was part
+/// of a notarized batch, for anchoring in external systems.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct NotarizedReceipt {
+    /// The notarization of the batch.
+    pub signed_notarization: SignedNotarization,
+    /// The digest of the transfer.
+    pub receipt: Digest,
+    /// The proof of the receipt, against the root of the notarization.
+    pub proof: MerkleProof,
+}
+
+impl NotarizedReceipt {
+    /// Verifies that the receipt was notarized by the given Replicas.
+    /// Only the receipt is revealed, not the transfer; holders of
+    /// the proof of the transfer can check it with `receipt_digest`.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        self.signed_notarization.verify(replicas)?;
+        if self
+            .proof
+            .verify(self.receipt, &self.signed_notarization.notarization.root)
+        {
+            Ok(())
+        } else {
+            Err(Error::from("Receipt is not part of the notarization"))
+        }
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::pending::DebitRelease;
+use safe_nd::{DebitAgreementProof, SignedTransfer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// An artifact produced by an Actor, to be sent to its Replicas.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum OutboxItem {
+    /// A transfer to be sent for validation.
+    Transfer(SignedTransfer),
+    /// A proof of agreement to be sent for registration.
+    Registration(DebitAgreementProof),
+    /// A release of an aborted debit, to be sent for the Replicas
+    /// that validated it to free its counter (see Replica::release_debit).
+    Release(DebitRelease),
+}
+
+/// An artifact awaiting acknowledgement, with its sequence number in the outbox.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct OutboxEntry {
+    /// The sequence number, by which the artifact is acknowledged.
+    pub sequence: u64,
+    /// The artifact.
+    pub item: OutboxItem,
+}
+
+/// The artifacts of an Actor that have not yet been acknowledged as delivered.
+/// The outbox is to be persisted along with the Actor, so that after a crash,
+/// the pending artifacts are sent again, and acknowledged ones are not.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct Outbox {
+    next_sequence: u64,
+    pending: BTreeMap<u64, OutboxItem>,
+}
+
+impl Outbox {
+    /// The artifacts awaiting acknowledgement, oldest first.
+    pub fn pending(&self) -> Vec<OutboxEntry> {
+        self.pending
+            .iter()
+            .map(|(sequence, item)| OutboxEntry {
+                sequence: *sequence,
+                item: item.clone(),
+            })
+            .collect()
+    }
+
+    /// Whether the artifact with the sequence number awaits acknowledgement.
+    pub fn is_pending(&self, sequence: u64) -> bool {
+        self.pending.contains_key(&sequence)
+    }
+
+    /// Whether there is nothing awaiting acknowledgement.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Adds an artifact, unless the same artifact is already pending.
+    pub(crate) fn push(&mut self, item: OutboxItem) {
+        if self.pending.values().any(|pending| pending == &item) {
+            return;
+        }
+        let _ = self.pending.insert(self.next_sequence, item);
+        self.next_sequence += 1;
+    }
+
+    /// Removes an artifact that is no longer to be sent, if pending.
+    pub(crate) fn withdraw(&mut self, item: &OutboxItem) {
+        self.pending.retain(|_, pending| pending != item);
+    }
+
+    /// Removes an acknowledged artifact.
+    pub(crate) fn acknowledge(&mut self, sequence: u64) {
+        let _ = self.pending.remove(&sequence);
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{clock::Timestamp, signer::TransferSigner};
+use safe_nd::{Error, Result, Signature, SignedTransfer, TransferId};
+use serde::{Deserialize, Serialize};
+
+/// How long a validated debit is left pending before it may be expired, in seconds,
+/// unless set otherwise (see Replica::with_pending_debit_timeout).
+pub const DEFAULT_PENDING_DEBIT_TIMEOUT: u64 = 60 * 60;
+
+/// A debit of a wallet that we have validated, but that has not been registered,
+/// such as when the client crashed before aggregating the proof of it.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PendingDebit {
+    /// The counter of the debit.
+    pub counter: u64,
+    /// When we validated it, if recorded, which is not the case
+    /// for the debits that a Replica was constructed with.
+    pub since: Option<Timestamp>,
+    /// The debit, if we still hold our validation of it,
+    /// which is not the case after a restart.
+    pub signed_transfer: Option<SignedTransfer>,
+}
+
+/// The release of a pending debit by its Actor, giving up on registering it. Replicas
+/// have issued shares of the proof of a debit they validated, so they only free its
+/// counter with the release of it (see Replica::expire_pending_debit).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct DebitRelease {
+    /// The debit released.
+    pub transfer_id: TransferId,
+    /// The signature of the Actor over the id of the debit.
+    pub actor_signature: Signature,
+}
+
+impl DebitRelease {
+    /// The release of the debit by the signer.
+    pub fn new<S: TransferSigner + ?Sized>(signer: &S, transfer_id: TransferId) -> Result<Self> {
+        Ok(Self {
+            transfer_id,
+            actor_signature: signer.sign(&release_payload(&transfer_id)?)?,
+        })
+    }
+
+    /// Verifies that the release was signed by the Actor of the debit.
+    pub fn verify(&self) -> Result<()> {
+        self.transfer_id
+            .actor
+            .verify(&self.actor_signature, &release_payload(&self.transfer_id)?)
+    }
+}
+
+/// The bytes of a release signed by the Actor.
+fn release_payload(transfer_id: &TransferId) -> Result<Vec<u8>> {
+    match bincode::serialize(&(b"debit_release", transfer_id)) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise release".into())),
+        Ok(data) => Ok(data),
+    }
+}
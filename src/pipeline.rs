@@ -0,0 +1,217 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use safe_nd::{AccountId, Money, Transfer, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The number of registered or failed transfers that the queue keeps the status of.
+pub const MAX_FINISHED_INTENTS: usize = 256;
+
+/// A transfer queued by the Actor, to be initiated once those queued before it
+/// are registered (see Actor::queue_transfer).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct TransferIntent {
+    /// The sequence number of the transfer in the queue.
+    pub sequence: u64,
+    /// The recipient.
+    pub to: AccountId,
+    /// The amount.
+    pub amount: Money,
+}
+
+/// How far a queued transfer has come.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum TransferStatus {
+    /// Waiting for its turn, or for a quorum of validations.
+    Pending,
+    /// Agreed by a quorum of our Replicas, to be registered.
+    Validated,
+    /// Sent for registration.
+    Registered,
+    /// Not initiated, or refused, for the reason.
+    Failed(String),
+}
+
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+enum IntentState {
+    Queued,
+    Initiated(TransferId),
+    Registered(TransferId),
+    Failed(String),
+}
+
+/// The transfers queued by an Actor, initiated one at a time in the order they were
+/// queued, each with the counter following the debit registered before it, so that
+/// transfers can be queued without waiting for those before them to complete.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct TransferQueue {
+    next_sequence: u64,
+    intents: BTreeMap<u64, (TransferIntent, IntentState)>,
+}
+
+impl TransferQueue {
+    /// The sequence number of the next transfer queued.
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// The transfers waiting for their turn, oldest first.
+    pub fn queued(&self) -> Vec<&TransferIntent> {
+        self.intents
+            .values()
+            .filter(|(_, state)| *state == IntentState::Queued)
+            .map(|(intent, _)| intent)
+            .collect()
+    }
+
+    /// The transfer initiated and not yet registered, if any, with the id of its debit.
+    pub fn in_flight(&self) -> Option<(&TransferIntent, TransferId)> {
+        self.intents
+            .values()
+            .find_map(|(intent, state)| match state {
+                IntentState::Initiated(id) => Some((intent, *id)),
+                _ => None,
+            })
+    }
+
+    /// Whether nothing is queued or in flight.
+    pub fn is_empty(&self) -> bool {
+        self.queued().is_empty() && self.in_flight().is_none()
+    }
+
+    /// The transfer with the sequence number, and the id of its debit once initiated.
+    pub(crate) fn get(&self, sequence: u64) -> Option<(&TransferIntent, Option<TransferId>)> {
+        let (intent, state) = self.intents.get(&sequence)?;
+        let id = match state {
+            IntentState::Initiated(id) | IntentState::Registered(id) => Some(*id),
+            IntentState::Queued | IntentState::Failed(_) => None,
+        };
+        Some((intent, id))
+    }
+
+    /// The reason the transfer with the sequence number failed, if it did.
+    pub(crate) fn failure(&self, sequence: u64) -> Option<&str> {
+        match self.intents.get(&sequence) {
+            Some((_, IntentState::Failed(reason))) => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// Adds a transfer at the end of the queue.
+    pub(crate) fn push(&mut self, intent: TransferIntent) {
+        self.next_sequence = intent.sequence + 1;
+        let _ = self
+            .intents
+            .insert(intent.sequence, (intent, IntentState::Queued));
+    }
+
+    /// Marks the first queued transfer as in flight, if the debit is of it
+    /// and none is in flight already.
+    pub(crate) fn initiated(&mut self, transfer: &Transfer) {
+        if self.in_flight().is_some() {
+            return;
+        }
+        let next = self
+            .intents
+            .values_mut()
+            .find(|(_, state)| *state == IntentState::Queued);
+        if let Some((intent, state)) = next {
+            if intent.to == transfer.to && intent.amount == transfer.amount {
+                *state = IntentState::Initiated(transfer.id);
+            }
+        }
+    }
+
+    /// Marks the transfer in flight as registered, if the debit is of it.
+    pub(crate) fn registered(&mut self, id: &TransferId) {
+        for (_, state) in self.intents.values_mut() {
+            if *state == IntentState::Initiated(*id) {
+                *state = IntentState::Registered(*id);
+            }
+        }
+        self.prune();
+    }
+
+    /// Marks the transfer as failed, for the reason.
+    pub(crate) fn failed(&mut self, sequence: u64, reason: String) {
+        if let Some((_, state)) = self.intents.get_mut(&sequence) {
+            *state = IntentState::Failed(reason);
+        }
+        self.prune();
+    }
+
+    /// Forgets the oldest finished transfers beyond MAX_FINISHED_INTENTS.
+    fn prune(&mut self) {
+        let finished: Vec<_> = self
+            .intents
+            .iter()
+            .filter(|(_, (_, state))| match state {
+                IntentState::Registered(_) | IntentState::Failed(_) => true,
+                IntentState::Queued | IntentState::Initiated(_) => false,
+            })
+            .map(|(sequence, _)| *sequence)
+            .collect();
+        let excess = finished.len().saturating_sub(MAX_FINISHED_INTENTS);
+        for sequence in finished.into_iter().take(excess) {
+            let _ = self.intents.remove(&sequence);
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn initiates_in_the_order_queued() {
+        // Arrange
+        let mut queue = TransferQueue::default();
+        let sender = get_random_pk();
+        let (first, second) = (get_intent(0, 10), get_intent(1, 20));
+        queue.push(first.clone());
+        queue.push(second.clone());
+
+        // Act
+        queue.initiated(&get_transfer(sender, &second, 0));
+        let skipped = queue.in_flight().is_none();
+        queue.initiated(&get_transfer(sender, &first, 0));
+        queue.initiated(&get_transfer(sender, &second, 1));
+        let in_flight = queue.in_flight().map(|(intent, _)| intent.sequence);
+        queue.registered(&Dot::new(sender, 0));
+
+        // Assert
+        assert!(skipped);
+        assert!(in_flight == Some(0));
+        assert!(queue.queued() == vec![&second]);
+        assert!(queue.get(0).and_then(|(_, id)| id) == Some(Dot::new(sender, 0)));
+        assert!(queue.next_sequence() == 2);
+    }
+
+    fn get_transfer(sender: PublicKey, intent: &TransferIntent, counter: u64) -> Transfer {
+        Transfer {
+            id: Dot::new(sender, counter),
+            to: intent.to,
+            amount: intent.amount,
+        }
+    }
+
+    fn get_intent(sequence: u64, amount: u64) -> TransferIntent {
+        TransferIntent {
+            sequence,
+            to: get_random_pk(),
+            amount: Money::from_nano(amount),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
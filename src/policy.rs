@@ -0,0 +1,275 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    money::covers,
+    signable::{Signable, SignableTransfer},
+};
+use safe_nd::{AccountId, Error, Money, PublicKey, Result, Signature, Transfer};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{cell::Cell, collections::BTreeSet};
+
+/// The maximum nesting of conditions.
+pub const MAX_CONDITION_DEPTH: usize = 8;
+/// The maximum number of conditions, nested ones included.
+pub const MAX_CONDITION_COUNT: usize = 64;
+
+/// A condition on the debits of an account, attached to it as its owner.
+/// Replicas only validate the debits that satisfy it.
+/// There are no loops, and the size of a condition is bounded,
+/// so evaluation always terminates, in time linear to its size.
+/// Conditions nested deeper than MAX_CONDITION_DEPTH are refused while
+/// being deserialized, before the levels below them are read.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum Condition {
+    /// The amount is at most this.
+    MaxAmount(Money),
+    /// The recipient is one of these.
+    RecipientIn(Vec<AccountId>),
+    /// The height of the Replicas is at least this.
+    HeightAtLeast(u64),
+    /// The height of the Replicas is below this.
+    HeightBelow(u64),
+    /// At least `threshold` of the keys have signed the transfer.
+    SignedBy {
+        /// The keys that may sign.
+        keys: Vec<PublicKey>,
+        /// How many of them must sign.
+        threshold: usize,
+    },
+    /// All of the conditions hold.
+    All(#[serde(deserialize_with = "nested")] Vec<Condition>),
+    /// At least one of the conditions holds.
+    Any(#[serde(deserialize_with = "nested")] Vec<Condition>),
+}
+
+thread_local! {
+    /// The levels of All and Any conditions being deserialized on this thread.
+    static DECODING_DEPTH: Cell<usize> = Cell::new(0);
+    /// Whether a condition was refused for nesting too deep, since last taken.
+    static NESTED_TOO_DEEP: Cell<bool> = Cell::new(false);
+}
+
+/// Leaves a level of the conditions being deserialized, however it ends.
+struct DepthGuard;
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DECODING_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// Deserializes the conditions of an All or Any condition, refusing them before any
+/// is read if they would be nested deeper than MAX_CONDITION_DEPTH, so that crafted
+/// payloads can not nest conditions as deep as the stack of the decoder takes.
+fn nested<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<Condition>, D::Error> {
+    let levels = DECODING_DEPTH.with(|depth| {
+        depth.set(depth.get() + 1);
+        depth.get()
+    });
+    let _guard = DepthGuard;
+    if levels >= MAX_CONDITION_DEPTH {
+        NESTED_TOO_DEEP.with(|refused| refused.set(true));
+        return Err(de::Error::custom(format!(
+            "Conditions nest deeper than {} levels",
+            MAX_CONDITION_DEPTH
+        )));
+    }
+    Vec::deserialize(deserializer)
+}
+
+/// Whether a condition was refused for nesting too deep on this thread
+/// since this was last called (see wire::decode).
+pub(crate) fn take_nested_too_deep() -> bool {
+    NESTED_TOO_DEEP.with(|refused| refused.replace(false))
+}
+
+/// A signature over a transfer, by a key other than the one of the sender,
+/// such as required by a SignedBy condition.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Witness {
+    /// The signing key.
+    pub key: PublicKey,
+    /// The signature over the transfer (see SignableTransfer).
+    pub signature: Signature,
+}
+
+impl Witness {
+    /// Verifies the signature over the transfer.
+    pub fn verify(&self, transfer: &Transfer) -> Result<()> {
+        SignableTransfer::new(transfer).verify(&self.key, &self.signature)
+    }
+}
+
+impl Condition {
+    /// Checks that the condition is within the bounds of depth and count,
+    /// and that every SignedBy condition can be satisfied.
+    pub fn check_bounds(&self) -> Result<()> {
+        let mut count = 0;
+        self.check(1, &mut count)
+    }
+
+    fn check(&self, depth: usize, count: &mut usize) -> Result<()> {
+        *count += 1;
+        if depth > MAX_CONDITION_DEPTH || *count > MAX_CONDITION_COUNT {
+            return Err(Error::ExceededSize);
+        }
+        match self {
+            Condition::SignedBy { keys, threshold } => {
+                if *threshold == 0 || *threshold > keys.len() {
+                    return Err(Error::InvalidOwners);
+                }
+            }
+            // as conditions of it would be nested too deep to be deserialized
+            Condition::All(_) | Condition::Any(_) if depth == MAX_CONDITION_DEPTH => {
+                return Err(Error::ExceededSize);
+            }
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                for condition in conditions {
+                    condition.check(depth + 1, count)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// The levels of conditions nested in the condition, counting itself.
+    pub fn depth(&self) -> usize {
+        match self {
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                1 + conditions.iter().map(|c| c.depth()).max().unwrap_or(0)
+            }
+            _ => 1,
+        }
+    }
+
+    /// Evaluates the condition for a transfer, at a height, with the
+    /// given witnesses. Only witnesses with valid signatures count.
+    pub fn evaluate(&self, transfer: &Transfer, height: u64, witnesses: &[Witness]) -> bool {
+        let signers: BTreeSet<_> = witnesses
+            .iter()
+            .filter(|w| w.verify(transfer).is_ok())
+            .map(|w| w.key)
+            .collect();
+        self.holds(transfer, height, &signers)
+    }
+
+    fn holds(&self, transfer: &Transfer, height: u64, signers: &BTreeSet<PublicKey>) -> bool {
+        match self {
+            Condition::MaxAmount(max) => covers(*max, transfer.amount),
+            Condition::RecipientIn(recipients) => recipients.contains(&transfer.to),
+            Condition::HeightAtLeast(min) => height >= *min,
+            Condition::HeightBelow(max) => height < *max,
+            Condition::SignedBy { keys, threshold } => {
+                let unique: BTreeSet<_> = keys.iter().collect();
+                unique.iter().filter(|k| signers.contains(k)).count() >= *threshold
+            }
+            Condition::All(conditions) => conditions
+                .iter()
+                .all(|c| c.holds(transfer, height, signers)),
+            Condition::Any(conditions) => conditions
+                .iter()
+                .any(|c| c.holds(transfer, height, signers)),
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn vesting_with_escape_hatch() {
+        // Arrange
+        let beneficiary = get_random_pk();
+        let trustee = SecretKey::random();
+        let transfer = get_transfer(beneficiary, 50);
+        let data = SignableTransfer::new(&transfer).to_bytes();
+        let witness = Witness {
+            key: PublicKey::from(trustee.public_key()),
+            signature: Signature::Bls(trustee.sign(&data)),
+        };
+        let condition = Condition::Any(vec![
+            Condition::All(vec![
+                Condition::HeightAtLeast(100),
+                Condition::RecipientIn(vec![beneficiary]),
+                Condition::MaxAmount(Money::from_nano(50)),
+            ]),
+            Condition::SignedBy {
+                keys: vec![witness.key],
+                threshold: 1,
+            },
+        ]);
+
+        // Act, Assert
+        assert!(condition.check_bounds().is_ok());
+        assert!(!condition.evaluate(&transfer, 99, &[]));
+        assert!(condition.evaluate(&transfer, 100, &[]));
+        assert!(condition.evaluate(&transfer, 99, &[witness]));
+        assert!(!condition.evaluate(&get_transfer(beneficiary, 51), 100, &[]));
+    }
+
+    #[test]
+    fn rejects_unbounded_or_unsatisfiable_conditions() {
+        let mut deep = Condition::MaxAmount(Money::zero());
+        for _ in 0..MAX_CONDITION_DEPTH {
+            deep = Condition::All(vec![deep]);
+        }
+        assert!(deep.check_bounds().is_err());
+        let wide = Condition::Any(vec![Condition::HeightBelow(1); MAX_CONDITION_COUNT]);
+        assert!(wide.check_bounds().is_err());
+        let unsatisfiable = Condition::SignedBy {
+            keys: vec![get_random_pk()],
+            threshold: 2,
+        };
+        assert!(unsatisfiable.check_bounds().is_err());
+    }
+
+    #[test]
+    fn refuses_to_deserialize_conditions_nested_too_deep() {
+        // Arrange
+        let mut deepest = Condition::HeightBelow(1);
+        for _ in 1..MAX_CONDITION_DEPTH {
+            deepest = Condition::Any(vec![deepest]);
+        }
+        let deepest = bincode::serialize(&deepest).unwrap();
+        // far deeper than the stack could take, were the levels read before refused
+        let mut too_deep = vec![];
+        for _ in 0..1_000_000 {
+            too_deep.extend(&6u32.to_le_bytes());
+            too_deep.extend(&1u64.to_le_bytes());
+        }
+        too_deep.extend(&deepest);
+
+        // Act
+        let decoded = bincode::deserialize::<Condition>(&deepest);
+        let refused = bincode::deserialize::<Condition>(&too_deep);
+
+        // Assert
+        assert!(decoded.is_ok());
+        assert!(refused.is_err());
+        assert!(take_nested_too_deep());
+        assert!(!take_nested_too_deep());
+    }
+
+    fn get_transfer(to: AccountId, amount: u64) -> Transfer {
+        Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to,
+            amount: Money::from_nano(amount),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
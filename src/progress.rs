@@ -0,0 +1,108 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use std::time::Duration;
+
+/// The number of events applied between reports of the progress of a replay.
+pub const REPLAY_REPORT_INTERVAL: usize = 1000;
+
+/// How far a replay of events has come.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub struct ReplayProgress {
+    /// The number of events applied so far.
+    pub applied: usize,
+    /// The number of events to apply in all, if known.
+    pub total: Option<usize>,
+    /// The time spent replaying so far.
+    pub elapsed: Duration,
+}
+
+impl ReplayProgress {
+    /// The share of the events applied, from 0 to 100, if the total is known.
+    pub fn percent(&self) -> Option<u8> {
+        match self.total {
+            Some(0) => Some(100),
+            Some(total) => Some((self.applied.min(total) * 100 / total) as u8),
+            None => None,
+        }
+    }
+
+    /// The time left to apply the remaining events at the pace so far,
+    /// if the total is known and any event has been applied.
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        if self.applied == 0 {
+            return None;
+        }
+        let remaining = total.saturating_sub(self.applied) as u128;
+        let nanos = self.elapsed.as_nanos() * remaining / self.applied as u128;
+        Some(Duration::from_nanos(nanos.min(u64::MAX as u128) as u64))
+    }
+}
+
+/// Whether a replay goes on, as answered by its observer.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub enum ReplayControl {
+    /// Go on replaying.
+    Continue,
+    /// Stop the replay, which then fails as cancelled.
+    Cancel,
+}
+
+/// Observes the progress of a replay, such as to show it in the UI of a starting node,
+/// or to bound the time a Replica takes to boot. It is called every
+/// REPLAY_REPORT_INTERVAL events, and once all are applied, and cancellation
+/// is thus cooperative: the replay stops at the next report after it is asked to.
+pub trait ReplayObserver {
+    /// Reports the progress, returning whether the replay goes on.
+    fn progress(&mut self, progress: &ReplayProgress) -> ReplayControl;
+}
+
+impl<F: FnMut(&ReplayProgress) -> ReplayControl> ReplayObserver for F {
+    fn progress(&mut self, progress: &ReplayProgress) -> ReplayControl {
+        self(progress)
+    }
+}
+
+/// Observes nothing, and never cancels.
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct Unobserved;
+
+impl ReplayObserver for Unobserved {
+    fn progress(&mut self, _: &ReplayProgress) -> ReplayControl {
+        ReplayControl::Continue
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn estimates_the_time_left() {
+        // Arrange
+        let progress = ReplayProgress {
+            applied: 250,
+            total: Some(1000),
+            elapsed: Duration::from_secs(10),
+        };
+
+        // Act
+        let percent = progress.percent();
+        let eta = progress.eta();
+        let unknown = ReplayProgress {
+            total: None,
+            ..progress
+        };
+
+        // Assert
+        assert!(percent == Some(25));
+        assert!(eta == Some(Duration::from_secs(30)));
+        assert!(unknown.percent().is_none());
+        assert!(unknown.eta().is_none());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    money::{saturating_add, saturating_sub},
+    ReplicaEvent,
+};
+use safe_nd::{AccountId, Money};
+use std::collections::BTreeMap;
+
+/// A read model derived from the events of a Replica.
+/// It is updated incrementally, one event at a time, as they are applied.
+pub trait Projection: Default {
+    /// Updates the read model with the event.
+    fn apply(&mut self, event: &ReplicaEvent);
+}
+
+/// Builds the projection from the events, in order.
+/// New events are then applied to it with Projection::apply.
+pub fn rebuild_projection<'a, P, I>(events: I) -> P
+where
+    P: Projection,
+    I: IntoIterator<Item = &'a ReplicaEvent>,
+{
+    let mut projection = P::default();
+    for event in events {
+        projection.apply(event);
+    }
+    projection
+}
+
+/// The balance of every account.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BalancesProjection(pub BTreeMap<AccountId, Money>);
+
+impl Projection for BalancesProjection {
+    fn apply(&mut self, event: &ReplicaEvent) {
+        match event {
+            ReplicaEvent::TransferRegistered(e) => {
+                let balance = self.0.entry(e.from()).or_insert_with(Money::zero);
+                *balance = saturating_sub(*balance, e.debit_proof.amount());
+            }
+            ReplicaEvent::TransferPropagated(e) => {
+                let balance = self.0.entry(e.to()).or_insert_with(Money::zero);
+                *balance = saturating_add(*balance, e.debit_proof.amount());
+            }
+            ReplicaEvent::DisbursementRegistered(e) => {
+                self.apply(&ReplicaEvent::TransferRegistered(e.registered()));
+            }
+            ReplicaEvent::PayoutPropagated(e) => {
+                let transfer = &e.payout.payout_proof.transfer;
+                let balance = self.0.entry(transfer.to).or_insert_with(Money::zero);
+                *balance = saturating_add(*balance, transfer.amount);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The number of registered debits of every account,
+/// which is the counter of its next debit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CountersProjection(pub BTreeMap<AccountId, u64>);
+
+impl Projection for CountersProjection {
+    fn apply(&mut self, event: &ReplicaEvent) {
+        match event {
+            ReplicaEvent::TransferRegistered(e) => {
+                let _ = self.0.insert(e.from(), e.id().counter + 1);
+            }
+            ReplicaEvent::DisbursementRegistered(e) => {
+                let _ = self
+                    .0
+                    .insert(e.debit_proof.from(), e.debit_proof.id().counter + 1);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The volume of registered debits per epoch.
+/// Transfers carry no time, so instead of per day, volumes are
+/// per epoch, closed by the recording of the checkpoint of the epoch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VolumesProjection {
+    /// The volume of each closed epoch.
+    pub epochs: BTreeMap<u64, Money>,
+    /// The volume since the last recorded checkpoint.
+    pub current: Money,
+}
+
+impl Projection for VolumesProjection {
+    fn apply(&mut self, event: &ReplicaEvent) {
+        match event {
+            ReplicaEvent::TransferRegistered(e) => {
+                self.current = saturating_add(self.current, e.debit_proof.amount());
+            }
+            ReplicaEvent::DisbursementRegistered(e) => {
+                self.current = saturating_add(self.current, e.debit_proof.amount());
+            }
+            ReplicaEvent::CheckpointRecorded(e) => {
+                let epoch = e.signed_checkpoint.checkpoint.epoch;
+                let _ = self
+                    .epochs
+                    .insert(epoch, std::mem::replace(&mut self.current, Money::zero()));
+            }
+            _ => (),
+        }
+    }
+}
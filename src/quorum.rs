@@ -0,0 +1,109 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use safe_nd::{Error, Result, Signature, SignatureShare};
+use std::collections::BTreeMap;
+use threshold_crypto::PublicKeySet;
+
+/// The number of shares needed to combine a signature of the key set.
+pub fn shares_needed(keys: &PublicKeySet) -> usize {
+    keys.threshold() + 1
+}
+
+/// Whether the number of distinct shares is enough to combine a signature of the key set.
+pub fn is_quorum(keys: &PublicKeySet, shares: usize) -> bool {
+    shares >= shares_needed(keys)
+}
+
+/// How many members of a group must agree, for the threshold of its key set.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub enum QuorumRule {
+    /// More than half of the group.
+    Majority,
+    /// More than two thirds of the group.
+    Supermajority,
+}
+
+impl QuorumRule {
+    /// The number of members of a group of the size that must agree.
+    pub fn required(self, group_size: usize) -> usize {
+        match self {
+            QuorumRule::Majority => group_size / 2 + 1,
+            QuorumRule::Supermajority => group_size * 2 / 3 + 1,
+        }
+    }
+
+    /// The threshold of the key set of a group of the size,
+    /// one less than the members that must agree.
+    pub fn threshold(self, group_size: usize) -> usize {
+        self.required(group_size) - 1
+    }
+
+    /// Checks that the key set is that of a group of the size under the rule,
+    /// so that a misconfigured group is caught when starting, rather than by
+    /// signatures that later fail to combine.
+    pub fn check(self, keys: &PublicKeySet, group_size: usize) -> Result<()> {
+        if group_size == 0 {
+            return Err(Error::from("Group is empty"));
+        }
+        if keys.threshold() != self.threshold(group_size) {
+            return Err(Error::from(format!(
+                "Key set threshold is {}, but a group of {} under {:?} needs {}",
+                keys.threshold(),
+                group_size,
+                self,
+                self.threshold(group_size)
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Combines the shares of a quorum of the Replicas of the key set into their signature.
+/// Duplicated shares count once, and too few of them fail with how many are needed.
+pub(crate) fn combine(keys: &PublicKeySet, shares: &[SignatureShare]) -> Result<Signature> {
+    let sig_shares: BTreeMap<_, _> = shares.iter().map(|s| (s.index, s.share.clone())).collect();
+    if !is_quorum(keys, sig_shares.len()) {
+        return Err(Error::from(format!(
+            "Not enough signature shares: {} of {} needed",
+            sig_shares.len(),
+            shares_needed(keys)
+        )));
+    }
+    match keys.combine_signatures(&sig_shares) {
+        Ok(sig) => Ok(Signature::Bls(sig)),
+        Err(_) => Err(Error::from("Signature shares do not combine")),
+    }
+}
+
+mod test {
+    use super::*;
+    use threshold_crypto::SecretKeySet;
+
+    #[test]
+    fn detects_misconfigured_thresholds() {
+        // Arrange
+        let keys = get_keys(4);
+
+        // Act
+        let supermajority = QuorumRule::Supermajority.check(&keys, 7);
+        let majority = QuorumRule::Majority.check(&keys, 7);
+
+        // Assert
+        assert!(QuorumRule::Supermajority.required(7) == 5);
+        assert!(QuorumRule::Majority.required(7) == 4);
+        assert!(shares_needed(&keys) == 5);
+        assert!(supermajority.is_ok());
+        assert!(majority.is_err());
+        assert!(QuorumRule::Majority.check(&keys, 0).is_err());
+    }
+
+    fn get_keys(threshold: usize) -> PublicKeySet {
+        SecretKeySet::random(threshold, &mut rand::thread_rng()).public_keys()
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::clock::Timestamp;
+use safe_nd::AccountId;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+};
+
+/// The number of wallets that buckets are kept for by default.
+pub const DEFAULT_RATE_LIMITED_WALLETS: usize = 100_000;
+
+/// How many validations a wallet may request: up to `burst` at once,
+/// and then one more every `refill_secs` seconds.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct RateLimit {
+    /// The most validations at once.
+    pub burst: u64,
+    /// The seconds it takes to be allowed one more.
+    pub refill_secs: u64,
+}
+
+/// The bucket of a wallet, as of a time.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub struct BucketState {
+    /// The validations the wallet may still request at once.
+    pub tokens: u64,
+    /// When the bucket was last refilled.
+    pub refilled_at: Timestamp,
+}
+
+/// A token bucket per sender wallet, limiting how often its debits are validated,
+/// so that a single client can not take all of the signing of its Replicas.
+/// Clones share the same buckets. Wallets whose bucket is full are forgotten
+/// first, as a fresh bucket is the same, when the buckets of too many are kept.
+/// If a thread panicked while holding the buckets, the limiter fails closed:
+/// no validation is allowed, and every bucket reads as empty.
+#[derive(Clone)]
+pub struct RateLimiter(Arc<Mutex<Buckets>>);
+
+struct Buckets {
+    limit: RateLimit,
+    capacity: usize,
+    buckets: HashMap<AccountId, BucketState>,
+    /// The wallets by when their bucket is full again, soonest first.
+    full_at: BTreeSet<(Timestamp, AccountId)>,
+}
+
+impl RateLimiter {
+    /// A limiter for the rate, keeping buckets for at most `capacity` wallets.
+    pub fn new(limit: RateLimit, capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(Buckets {
+            limit,
+            capacity,
+            buckets: Default::default(),
+            full_at: Default::default(),
+        })))
+    }
+
+    /// The state of the bucket of the wallet at the time, if it is not full.
+    pub fn state(&self, wallet: &AccountId, now: Timestamp) -> Option<BucketState> {
+        let buckets = match self.0.lock() {
+            Ok(buckets) => buckets,
+            Err(_) => {
+                return Some(BucketState {
+                    tokens: 0,
+                    refilled_at: now,
+                })
+            }
+        };
+        let bucket = buckets.refilled(buckets.buckets.get(wallet)?, now);
+        if bucket.tokens < buckets.limit.burst {
+            Some(bucket)
+        } else {
+            None
+        }
+    }
+
+    /// Takes a token from the bucket of the wallet, or returns
+    /// the seconds until one is available if it is empty.
+    pub(crate) fn acquire(&self, wallet: AccountId, now: Timestamp) -> Result<(), u64> {
+        let mut buckets = match self.0.lock() {
+            Ok(buckets) => buckets,
+            Err(poisoned) => return Err(poisoned.into_inner().limit.refill_secs.max(1)),
+        };
+        let full = BucketState {
+            tokens: buckets.limit.burst,
+            refilled_at: now,
+        };
+        let bucket = match buckets.buckets.get(&wallet) {
+            Some(bucket) => buckets.refilled(bucket, now),
+            None => full,
+        };
+        if bucket.tokens == 0 {
+            let ready = bucket.refilled_at + buckets.limit.refill_secs;
+            return Err(ready.saturating_sub(now).max(1));
+        }
+        buckets.insert(
+            wallet,
+            BucketState {
+                tokens: bucket.tokens - 1,
+                ..bucket
+            },
+        );
+        buckets.evict(now);
+        Ok(())
+    }
+
+    /// Moves the bucket of the wallet to its new key, once the key is rotated.
+    pub(crate) fn rekey(&self, previous: &AccountId, current: AccountId) {
+        if let Ok(mut buckets) = self.0.lock() {
+            if let Some(bucket) = buckets.remove(previous) {
+                buckets.insert(current, bucket);
+            }
+        }
+    }
+}
+
+impl Buckets {
+    fn refilled(&self, bucket: &BucketState, now: Timestamp) -> BucketState {
+        if self.limit.refill_secs == 0 {
+            return BucketState {
+                tokens: self.limit.burst,
+                refilled_at: now,
+            };
+        }
+        let refills = now.saturating_sub(bucket.refilled_at) / self.limit.refill_secs;
+        let tokens = bucket.tokens.saturating_add(refills).min(self.limit.burst);
+        let refilled_at = if tokens == self.limit.burst {
+            now
+        } else {
+            bucket.refilled_at + refills * self.limit.refill_secs
+        };
+        BucketState {
+            tokens,
+            refilled_at,
+        }
+    }
+
+    /// When the bucket is full again, if no token is taken from it.
+    fn full_at(&self, bucket: &BucketState) -> Timestamp {
+        let missing = self.limit.burst.saturating_sub(bucket.tokens);
+        bucket
+            .refilled_at
+            .saturating_add(missing.saturating_mul(self.limit.refill_secs))
+    }
+
+    fn insert(&mut self, wallet: AccountId, bucket: BucketState) {
+        let _ = self.remove(&wallet);
+        let _ = self.full_at.insert((self.full_at(&bucket), wallet));
+        let _ = self.buckets.insert(wallet, bucket);
+    }
+
+    fn remove(&mut self, wallet: &AccountId) -> Option<BucketState> {
+        let bucket = self.buckets.remove(wallet)?;
+        let _ = self.full_at.remove(&(self.full_at(&bucket), *wallet));
+        Some(bucket)
+    }
+
+    /// Forgets the buckets that are full again, soonest first, while too many are kept.
+    fn evict(&mut self, now: Timestamp) {
+        while self.buckets.len() > self.capacity {
+            let (full_at, wallet) = match self.full_at.iter().next() {
+                Some(first) if first.0 <= now => *first,
+                _ => return,
+            };
+            let _ = self.full_at.remove(&(full_at, wallet));
+            let _ = self.buckets.remove(&wallet);
+        }
+    }
+}
+
+impl Debug for RateLimiter {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0.lock() {
+            Ok(buckets) => write!(f, "RateLimiter({:?})", buckets.limit),
+            Err(_) => write!(f, "RateLimiter"),
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn refills_one_token_at_a_time() {
+        // Arrange
+        let limiter = get_limiter();
+        let wallet = get_random_pk();
+
+        // Act
+        let burst = (0..3).all(|_| limiter.acquire(wallet, 100).is_ok());
+        let empty = limiter.acquire(wallet, 105);
+        let refilled = limiter.acquire(wallet, 110);
+
+        // Assert
+        assert!(burst);
+        assert!(empty == Err(5));
+        assert!(refilled.is_ok());
+        assert!(limiter.state(&wallet, 110).map(|b| b.tokens) == Some(0));
+        assert!(limiter.state(&wallet, 140).is_none());
+    }
+
+    #[test]
+    fn forgets_full_buckets_when_over_capacity() {
+        // Arrange
+        let limit = RateLimit {
+            burst: 2,
+            refill_secs: 10,
+        };
+        let limiter = RateLimiter::new(limit, 1);
+        let (first, second) = (get_random_pk(), get_random_pk());
+
+        // Act
+        let acquired = limiter.acquire(first, 100).is_ok() && limiter.acquire(second, 105).is_ok();
+        let third = get_random_pk();
+        let refilled = limiter.acquire(third, 112);
+
+        // Assert
+        assert!(acquired);
+        assert!(refilled.is_ok());
+        assert!(limiter.state(&first, 112).is_none());
+        assert!(limiter.state(&second, 112).map(|b| b.tokens) == Some(1));
+        assert!(limiter.state(&third, 112).map(|b| b.tokens) == Some(1));
+    }
+
+    #[test]
+    fn fails_closed_once_poisoned() {
+        // Arrange
+        let limiter = get_limiter();
+        let wallet = get_random_pk();
+        let poisoner = limiter.clone();
+
+        // Act
+        let _ = std::thread::spawn(move || {
+            let _buckets = poisoner.0.lock().unwrap();
+            panic!("poisoning the buckets");
+        })
+        .join();
+
+        // Assert
+        assert!(limiter.acquire(wallet, 100) == Err(10));
+        assert!(limiter.state(&wallet, 100).map(|b| b.tokens) == Some(0));
+    }
+
+    fn get_limiter() -> RateLimiter {
+        let limit = RateLimit {
+            burst: 3,
+            refill_secs: 10,
+        };
+        RateLimiter::new(limit, 16)
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
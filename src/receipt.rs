@@ -0,0 +1,102 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    codec::{verified_in, Codec, Payload},
+    signable::{Signable, SignableCredit},
+};
+use safe_nd::{
+    AccountId, DebitAgreementProof, Error, Money, PublicKey, Result, Signature, TransferId,
+    TransferPropagated,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use threshold_crypto::PublicKeySet;
+
+/// The proof of agreement of the Replicas of the recipient that a credit reached
+/// them, combined from the shares they sign when receiving the propagated debit.
+/// The payer can hand it to the recipient as a receipt of the payment.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct CreditAgreementProof {
+    /// The proof of the debit that was credited.
+    pub debit_proof: DebitAgreementProof,
+    /// The aggregated signature of the crediting Replicas over the debit proof.
+    pub crediting_replicas_sig: Signature,
+}
+
+impl CreditAgreementProof {
+    /// Combines the propagations by a quorum of the crediting Replicas into a proof.
+    /// All of them must be of the same debit.
+    pub fn combine(propagations: &[TransferPropagated], replicas: &PublicKeySet) -> Result<Self> {
+        let debit_proof = match propagations.first() {
+            Some(propagated) => &propagated.debit_proof,
+            None => return Err(Error::from("Not enough signature shares")),
+        };
+        if propagations.iter().any(|p| p.debit_proof != *debit_proof) {
+            return Err(Error::from("Replicas credited different debits"));
+        }
+        let sig_shares: BTreeMap<_, _> = propagations
+            .iter()
+            .map(|p| {
+                (
+                    p.crediting_replica_sig.index,
+                    p.crediting_replica_sig.share.clone(),
+                )
+            })
+            .collect();
+        let sig = match replicas.combine_signatures(&sig_shares) {
+            Ok(sig) => sig,
+            Err(_) => return Err(Error::from("Not enough signature shares")),
+        };
+        let proof = CreditAgreementProof {
+            debit_proof: debit_proof.clone(),
+            crediting_replicas_sig: Signature::Bls(sig),
+        };
+        proof.verify(&PublicKey::Bls(replicas.public_key()))?;
+        Ok(proof)
+    }
+
+    /// Verifies that the credit was agreed by the group with the given key.
+    pub fn verify(&self, crediting_replicas: &PublicKey) -> Result<()> {
+        SignableCredit::new(&self.debit_proof)
+            .verify(crediting_replicas, &self.crediting_replicas_sig)
+    }
+
+    /// The id of the credited transfer.
+    pub fn id(&self) -> TransferId {
+        self.debit_proof.id()
+    }
+
+    /// The recipient.
+    pub fn to(&self) -> AccountId {
+        self.debit_proof.to()
+    }
+
+    /// The amount credited.
+    pub fn amount(&self) -> Money {
+        self.debit_proof.amount()
+    }
+}
+
+/// Verifies that the propagation was signed by a Replica of the crediting group,
+/// before it is combined with others into a CreditAgreementProof, over any of the
+/// codecs, such as those accepted at the height (see accepted_codecs).
+pub fn verify_propagated(
+    propagated: &TransferPropagated,
+    replicas: &PublicKeySet,
+    codecs: &[Codec],
+) -> Result<()> {
+    let share = &propagated.crediting_replica_sig;
+    let key = replicas.public_key_share(share.index);
+    let payload = Payload::Credit(&propagated.debit_proof);
+    if verified_in(codecs, payload, |data| key.verify(&share.share, data)) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
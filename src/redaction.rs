@@ -0,0 +1,264 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::Account,
+    hashing::{hash, Digest},
+    money::saturating_add,
+    search::{Payment, PaymentDirection},
+};
+use safe_nd::{AccountId, Error, Money, Result, Transfer};
+use serde::{Deserialize, Serialize};
+
+/// What to redact of a history before sharing it with third parties.
+/// Memos are attached apart from the transfers (see MEMO_KEY) and are not exported,
+/// so only counterparties and amounts are redacted.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct RedactionProfile {
+    /// Number of leading bytes of the serialised counterparty key kept, all if None.
+    pub counterparty_prefix: Option<usize>,
+    /// Width of the buckets amounts are rounded down into, exact if None.
+    pub amount_bucket: Option<Money>,
+}
+
+impl RedactionProfile {
+    /// Redacts a payment according to the profile.
+    pub fn redact(&self, payment: &Payment) -> Result<RedactedPayment> {
+        let mut counterparty = match bincode::serialize(&payment.counterparty()) {
+            Err(_) => return Err(Error::NetworkOther("Could not serialise key".into())),
+            Ok(data) => data,
+        };
+        if let Some(prefix) = self.counterparty_prefix {
+            counterparty.truncate(prefix);
+        }
+        let amount = match self.amount_bucket {
+            None => RedactedAmount::Exact(payment.transfer.amount),
+            Some(width) if width.as_nano() == 0 => {
+                return Err(Error::from("Amount buckets must not be empty"))
+            }
+            Some(width) => {
+                let nanos = payment.transfer.amount.as_nano();
+                let min = Money::from_nano(nanos - nanos % width.as_nano());
+                RedactedAmount::Bucket {
+                    min,
+                    max: saturating_add(min, width),
+                }
+            }
+        };
+        Ok(RedactedPayment {
+            direction: payment.direction,
+            counterparty,
+            amount,
+        })
+    }
+
+    /// Whether the payment was redacted according to the profile.
+    fn is_applied_to(&self, payment: &RedactedPayment) -> bool {
+        let counterparty = self
+            .counterparty_prefix
+            .map_or(true, |prefix| payment.counterparty.len() <= prefix);
+        let amount = match (self.amount_bucket, payment.amount) {
+            (None, RedactedAmount::Exact(_)) => true,
+            (Some(width), RedactedAmount::Bucket { min, max }) => {
+                width.as_nano() > 0
+                    && min.as_nano() % width.as_nano() == 0
+                    && max == saturating_add(min, width)
+            }
+            _ => false,
+        };
+        counterparty && amount
+    }
+}
+
+/// An amount, either exact or as the bucket it falls in.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum RedactedAmount {
+    /// The exact amount.
+    Exact(Money),
+    /// An amount of at least min, and less than max.
+    Bucket {
+        /// The lower bound, inclusive.
+        min: Money,
+        /// The upper bound, exclusive.
+        max: Money,
+    },
+}
+
+/// A payment with the details hidden by a profile removed.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct RedactedPayment {
+    /// Whether it was sent or received.
+    pub direction: PaymentDirection,
+    /// The leading bytes of the serialised counterparty key.
+    pub counterparty: Vec<u8>,
+    /// The amount.
+    pub amount: RedactedAmount,
+}
+
+impl RedactedPayment {
+    /// Whether this could have been redacted from the payment,
+    /// for a holder of the full history to check what was shared.
+    pub fn matches(&self, payment: &Payment) -> bool {
+        let counterparty = bincode::serialize(&payment.counterparty())
+            .map_or(false, |data| data.starts_with(&self.counterparty));
+        let amount = match self.amount {
+            RedactedAmount::Exact(amount) => payment.transfer.amount == amount,
+            RedactedAmount::Bucket { min, max } => {
+                let nanos = payment.transfer.amount.as_nano();
+                min.as_nano() <= nanos && nanos < max.as_nano()
+            }
+        };
+        self.direction == payment.direction && counterparty && amount
+    }
+}
+
+/// What was redacted from an export, and a digest of what was shared,
+/// so that third parties can verify the export they received.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct RedactionStatement {
+    /// The account of the history.
+    pub account_id: AccountId,
+    /// The profile applied to every payment.
+    pub profile: RedactionProfile,
+    /// Number of payments exported, none of them left out.
+    pub payment_count: u64,
+    /// The digest of the redacted payments.
+    pub digest: Digest,
+}
+
+/// The history of an account, redacted for sharing with third parties.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct RedactedExport {
+    /// The account of the history.
+    pub account_id: AccountId,
+    /// The profile applied to every payment.
+    pub profile: RedactionProfile,
+    /// The redacted payments, credits first, each in the order of the history.
+    pub payments: Vec<RedactedPayment>,
+}
+
+impl RedactedExport {
+    /// Redacts the full history of the account, applying the profile to every payment.
+    pub fn of(account: &Account, profile: &RedactionProfile) -> Result<Self> {
+        let payment = |direction, transfer: &Transfer| Payment {
+            direction,
+            transfer: transfer.clone(),
+        };
+        let payments = account
+            .credit_entries()
+            .iter()
+            .map(|entry| payment(PaymentDirection::Received, entry.transfer()))
+            .chain(
+                account
+                    .debit_entries()
+                    .iter()
+                    .map(|entry| payment(PaymentDirection::Sent, entry.transfer())),
+            )
+            .map(|payment| profile.redact(&payment))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            account_id: account.id(),
+            profile: profile.clone(),
+            payments,
+        })
+    }
+
+    /// The statement of what was redacted, to accompany the export.
+    pub fn statement(&self) -> Result<RedactionStatement> {
+        Ok(RedactionStatement {
+            account_id: self.account_id,
+            profile: self.profile.clone(),
+            payment_count: self.payments.len() as u64,
+            digest: self.digest()?,
+        })
+    }
+
+    /// Verifies that the export is the one described by the statement,
+    /// and that the profile was applied consistently to every payment.
+    pub fn verify(&self, statement: &RedactionStatement) -> Result<()> {
+        if self.account_id != statement.account_id || self.profile != statement.profile {
+            return Err(Error::from("Export does not match the statement"));
+        }
+        if self.payments.len() as u64 != statement.payment_count
+            || self.digest()? != statement.digest
+        {
+            return Err(Error::from("Export does not match the statement"));
+        }
+        if !self.payments.iter().all(|p| self.profile.is_applied_to(p)) {
+            return Err(Error::from("Profile was not applied to every payment"));
+        }
+        Ok(())
+    }
+
+    fn digest(&self) -> Result<Digest> {
+        match bincode::serialize(&(&self.account_id, &self.profile, &self.payments)) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise export".into())),
+            Ok(data) => Ok(hash(&[&data])),
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn redacts_consistently() -> Result<()> {
+        // Arrange
+        let id = get_random_pk();
+        let shop = get_random_pk();
+        let mut account = Account::new(id);
+        account.append(get_transfer(get_random_pk(), 0, id, 1_234));
+        account.append(get_transfer(id, 0, shop, 567));
+        let profile = RedactionProfile {
+            counterparty_prefix: Some(4),
+            amount_bucket: Some(Money::from_nano(100)),
+        };
+
+        // Act
+        let export = RedactedExport::of(&account, &profile)?;
+        let statement = export.statement()?;
+
+        // Assert
+        assert!(export.verify(&statement).is_ok());
+        assert!(export.payments.len() == 2);
+        let sent = &export.payments[1];
+        assert!(sent.counterparty.len() == 4);
+        assert!(
+            sent.amount
+                == RedactedAmount::Bucket {
+                    min: Money::from_nano(500),
+                    max: Money::from_nano(600),
+                }
+        );
+        let payment = Payment {
+            direction: PaymentDirection::Sent,
+            transfer: get_transfer(id, 0, shop, 567),
+        };
+        assert!(sent.matches(&payment));
+        let mut tampered = export.clone();
+        tampered.payments[0].amount = RedactedAmount::Exact(Money::from_nano(1_234));
+        assert!(tampered.verify(&statement).is_err());
+        assert!(tampered.verify(&tampered.statement()?).is_err());
+        Ok(())
+    }
+
+    fn get_transfer(from: PublicKey, counter: u64, to: PublicKey, amount: u64) -> Transfer {
+        Transfer {
+            id: Dot::new(from, counter),
+            to,
+            amount: Money::from_nano(amount),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::signer::TransferSigner;
+use safe_nd::{Error, Result, Signature, SignedTransfer, TransferId};
+use serde::{Deserialize, Serialize};
+
+/// A debit paying back a credit of the wallet to its sender, such as a merchant refunding
+/// a payment. Signed by the wallet, it names the credit refunded, so that the payer can
+/// verify what the debit refunds, and the Replicas that no more is refunded than was paid
+/// (see Replica::validate_refund).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Refund {
+    /// The debit, signed by the refunding wallet.
+    pub signed_transfer: SignedTransfer,
+    /// The id of the credit refunded.
+    pub original: TransferId,
+    /// The signature of the refunding wallet over the ids of the debit and the credit.
+    pub signature: Signature,
+}
+
+impl Refund {
+    /// The refund by the signer of the credit, with the debit.
+    pub fn new<S: TransferSigner + ?Sized>(
+        signer: &S,
+        signed_transfer: SignedTransfer,
+        original: TransferId,
+    ) -> Result<Self> {
+        let payload = refund_payload(&signed_transfer.id(), &original)?;
+        Ok(Self {
+            signature: signer.sign(&payload)?,
+            signed_transfer,
+            original,
+        })
+    }
+
+    /// Verifies that the debit is to the sender of the credit,
+    /// and that the refund was signed by the refunding wallet.
+    pub fn verify(&self) -> Result<()> {
+        let transfer = &self.signed_transfer.transfer;
+        if transfer.to != self.original.actor {
+            return Err(Error::from("Refund is not to the sender of the credit"));
+        }
+        let payload = refund_payload(&transfer.id, &self.original)?;
+        transfer.id.actor.verify(&self.signature, &payload)
+    }
+}
+
+/// The bytes of a refund signed by the refunding wallet.
+fn refund_payload(refund_id: &TransferId, original: &TransferId) -> Result<Vec<u8>> {
+    match bincode::serialize(&(b"refund", refund_id, original)) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise refund".into())),
+        Ok(data) => Ok(data),
+    }
+}
+
+mod test {
+    use super::*;
+    use crate::signable::{Signable, SignableTransfer};
+    use crdts::Dot;
+    use safe_nd::{ClientFullId, Money, PublicKey, SafeKey, Transfer};
+
+    #[test]
+    fn refunds_the_sender_of_the_credit() -> Result<()> {
+        // Arrange
+        let merchant = get_safe_key();
+        let customer = get_safe_key().public_key();
+        let original = Dot::new(customer, 3);
+
+        // Act
+        let refund = Refund::new(&merchant, get_debit(&merchant, customer), original)?;
+        let stranger = get_safe_key().public_key();
+        let elsewhere = Refund::new(&merchant, get_debit(&merchant, stranger), original)?;
+
+        // Assert
+        assert!(refund.verify().is_ok());
+        assert!(elsewhere.verify().is_err());
+        Ok(())
+    }
+
+    fn get_debit(from: &SafeKey, to: PublicKey) -> SignedTransfer {
+        let transfer = Transfer {
+            id: Dot::new(from.public_key(), 0),
+            to,
+            amount: Money::from_nano(10),
+        };
+        SignedTransfer {
+            actor_signature: from.sign(&SignableTransfer::new(&transfer).to_bytes()),
+            transfer,
+        }
+    }
+
+    fn get_safe_key() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+}
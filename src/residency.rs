@@ -0,0 +1,492 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::{Account, AccountRecord},
+    shard::Shards,
+    wire::SizeLimits,
+};
+use safe_nd::{AccountId, Error, Result};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt::{self, Debug, Formatter},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+/// Persistence of the wallets that a Replica evicts from memory, serialised,
+/// for them to be loaded back on demand (see Replica::with_wallet_store).
+pub trait WalletBackend: Send {
+    /// Stores the wallet, replacing what was stored of it.
+    fn save(&mut self, wallet: &AccountId, bytes: Vec<u8>) -> Result<()>;
+
+    /// The wallet as stored, if it is.
+    fn load(&self, wallet: &AccountId) -> Result<Option<Vec<u8>>>;
+
+    /// Drops what is stored of the wallet.
+    fn remove(&mut self, wallet: &AccountId) -> Result<()>;
+}
+
+/// A backend keeping the wallets in memory, serialised, such as for tests.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryWalletBackend {
+    wallets: HashMap<AccountId, Vec<u8>>,
+}
+
+impl MemoryWalletBackend {
+    /// An empty backend.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl WalletBackend for MemoryWalletBackend {
+    fn save(&mut self, wallet: &AccountId, bytes: Vec<u8>) -> Result<()> {
+        let _ = self.wallets.insert(*wallet, bytes);
+        Ok(())
+    }
+
+    fn load(&self, wallet: &AccountId) -> Result<Option<Vec<u8>>> {
+        Ok(self.wallets.get(wallet).cloned())
+    }
+
+    fn remove(&mut self, wallet: &AccountId) -> Result<()> {
+        let _ = self.wallets.remove(wallet);
+        Ok(())
+    }
+}
+
+/// A cheaply cloneable handle to the backend of the wallets of a Replica.
+/// Clones share the same backend, which is not to be shared by Replicas,
+/// as each drops what it holds of the wallets it no longer holds.
+#[derive(Clone)]
+pub struct WalletStore(Arc<Mutex<dyn WalletBackend>>);
+
+impl WalletStore {
+    /// Wraps the given backend.
+    pub fn new<B: WalletBackend + 'static>(backend: B) -> Self {
+        Self(Arc::new(Mutex::new(backend)))
+    }
+
+    /// Stores the account, in its serialisable form.
+    pub(crate) fn save(&self, account: &Account) -> Result<()> {
+        match bincode::serialize(&account.to_record()) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise account".into())),
+            Ok(bytes) => self.lock()?.save(&account.id(), bytes),
+        }
+    }
+
+    /// The account as stored, decoded within the limit of a snapshot,
+    /// and verified as when restored from one.
+    pub(crate) fn load(&self, wallet: &AccountId) -> Result<Option<Account>> {
+        let bytes = match self.lock()?.load(wallet)? {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let record: AccountRecord = SizeLimits::default().snapshot(&bytes)?;
+        Account::from_record(record).map(Some)
+    }
+
+    /// Drops the account.
+    pub(crate) fn remove(&self, wallet: &AccountId) -> Result<()> {
+        self.lock()?.remove(wallet)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, dyn WalletBackend + 'static>> {
+        match self.0.lock() {
+            Ok(backend) => Ok(backend),
+            Err(_) => Err(Error::from("Wallet store is poisoned")),
+        }
+    }
+}
+
+impl Debug for WalletStore {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "WalletStore")
+    }
+}
+
+/// A wallet held in memory, with when it was last used.
+#[derive(Debug)]
+struct Resident {
+    account: Account,
+    last_used: AtomicU64,
+}
+
+impl Clone for Resident {
+    fn clone(&self) -> Self {
+        Self {
+            account: self.account.clone(),
+            last_used: AtomicU64::new(self.last_used.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl PartialEq for Resident {
+    fn eq(&self, other: &Self) -> bool {
+        self.account == other.account
+    }
+}
+
+impl Eq for Resident {}
+
+/// The wallets of a Replica. Without a store, all of them are held in memory. With a
+/// store, at most `capacity` of them are, and the least recently used are evicted to
+/// the store, to be loaded back on demand. Wallets loaded by queries are not kept in
+/// memory, as queries do not mutate the Replica, while those loaded to apply an event
+/// are. A wallet that the store fails to load stays evicted, and the error is returned.
+#[derive(Debug)]
+pub(crate) struct ResidentWallets {
+    resident: Shards<Resident>,
+    /// The wallets held in the store only.
+    evicted: HashSet<AccountId>,
+    store: Option<(WalletStore, usize)>,
+    /// Whether wallets loaded are to keep checksums (see Account::with_checksums).
+    checksums: bool,
+    /// Ticks at every use of a wallet, ordering their uses.
+    uses: AtomicU64,
+}
+
+impl ResidentWallets {
+    /// Evicts to the store, keeping at most `capacity` wallets in memory.
+    pub(crate) fn with_store(mut self, store: WalletStore, capacity: usize) -> Self {
+        self.store = Some((store, capacity.max(1)));
+        self.evict(0);
+        self
+    }
+
+    /// Keeps checksums of all wallets, including those loaded from now on.
+    pub(crate) fn with_checksums(mut self) -> Self {
+        self.checksums = true;
+        for resident in self.resident.values_mut() {
+            resident.account = resident.account.clone().with_checksums();
+        }
+        self
+    }
+
+    /// The number of wallets held, in memory or in the store.
+    pub(crate) fn len(&self) -> usize {
+        self.resident.len() + self.evicted.len()
+    }
+
+    /// The number of wallets held in memory.
+    pub(crate) fn resident_len(&self) -> usize {
+        self.resident.len()
+    }
+
+    pub(crate) fn contains_key(&self, wallet: &AccountId) -> bool {
+        self.resident.contains_key(wallet) || self.evicted.contains(wallet)
+    }
+
+    /// The wallet, borrowed if in memory, else loaded from the store.
+    pub(crate) fn get(&self, wallet: &AccountId) -> Result<Option<Cow<'_, Account>>> {
+        if let Some(resident) = self.resident.get(wallet) {
+            resident.last_used.store(self.tick(), Ordering::Relaxed);
+            return Ok(Some(Cow::Borrowed(&resident.account)));
+        }
+        Ok(self.load(wallet)?.map(Cow::Owned))
+    }
+
+    /// The wallet, loaded into memory if evicted.
+    pub(crate) fn get_mut(&mut self, wallet: &AccountId) -> Result<Option<&mut Account>> {
+        self.make_resident(wallet)?;
+        let tick = self.tick();
+        Ok(self.resident.get_mut(wallet).map(|resident| {
+            *resident.last_used.get_mut() = tick;
+            &mut resident.account
+        }))
+    }
+
+    /// The wallet, loaded into memory if evicted, or inserted if not held.
+    /// A wallet the store fails to load is not replaced with a new one.
+    pub(crate) fn get_or_insert_with<F: FnOnce() -> Account>(
+        &mut self,
+        wallet: AccountId,
+        new: F,
+    ) -> Result<&mut Account> {
+        self.make_resident(&wallet)?;
+        if !self.resident.contains_key(&wallet) {
+            self.evict(1);
+        }
+        let last_used = AtomicU64::new(self.tick());
+        let resident = match self.resident.entry(wallet) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Resident {
+                account: new(),
+                last_used,
+            }),
+        };
+        Ok(&mut resident.account)
+    }
+
+    /// Holds the account, in place of what was held of the wallet. Whatever the store holds
+    /// of it is dropped, loaded or not, as it is replaced.
+    pub(crate) fn insert(&mut self, wallet: AccountId, account: Account) -> Option<Account> {
+        let previous = self.remove(&wallet).ok().flatten();
+        self.forget(&wallet);
+        let resident = Resident {
+            account,
+            last_used: AtomicU64::new(self.tick()),
+        };
+        let _ = self.resident.insert(wallet, resident);
+        self.evict(0);
+        previous
+    }
+
+    /// Drops the wallet, from memory and from the store. A wallet
+    /// the store fails to load is kept, and the error returned.
+    pub(crate) fn remove(&mut self, wallet: &AccountId) -> Result<Option<Account>> {
+        if let Some(resident) = self.resident.remove(wallet) {
+            self.forget(wallet);
+            return Ok(Some(resident.account));
+        }
+        let account = self.load(wallet)?;
+        self.forget(wallet);
+        Ok(account)
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &AccountId> {
+        self.resident.keys().chain(self.evicted.iter())
+    }
+
+    /// All wallets, those in the store loaded one at a time,
+    /// with an error for each that the store fails to load.
+    pub(crate) fn values(&self) -> impl Iterator<Item = Result<Cow<'_, Account>>> {
+        self.iter().map(|entry| entry.map(|(_, account)| account))
+    }
+
+    /// All wallets by their id, as by values.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = Result<(&AccountId, Cow<'_, Account>)>> {
+        self.resident
+            .iter()
+            .map(|(wallet, resident)| Ok((wallet, Cow::Borrowed(&resident.account))))
+            .chain(
+                self.evicted
+                    .iter()
+                    .map(move |wallet| match self.load(wallet)? {
+                        Some(account) => Ok((wallet, Cow::Owned(account))),
+                        None => Err(Error::from("Evicted wallet is not in the store")),
+                    }),
+            )
+    }
+
+    fn tick(&self) -> u64 {
+        self.uses.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn load(&self, wallet: &AccountId) -> Result<Option<Account>> {
+        if !self.evicted.contains(wallet) {
+            return Ok(None);
+        }
+        let account = match &self.store {
+            Some((store, _)) => store.load(wallet)?,
+            None => None,
+        };
+        match account {
+            None => Err(Error::from("Evicted wallet is not in the store")),
+            Some(account) if self.checksums => Ok(Some(account.with_checksums())),
+            Some(account) => Ok(Some(account)),
+        }
+    }
+
+    /// Loads the wallet into memory, if evicted.
+    pub(crate) fn make_resident(&mut self, wallet: &AccountId) -> Result<()> {
+        if let Some(account) = self.load(wallet)? {
+            let _ = self.evicted.remove(wallet);
+            let resident = Resident {
+                account,
+                last_used: AtomicU64::new(self.tick()),
+            };
+            let _ = self.resident.insert(*wallet, resident);
+            self.evict(0);
+        }
+        Ok(())
+    }
+
+    /// Drops what the store holds of the wallet, which may be
+    /// a stale copy of a wallet loaded back into memory.
+    fn forget(&mut self, wallet: &AccountId) {
+        let _ = self.evicted.remove(wallet);
+        if let Some((store, _)) = &self.store {
+            let _ = store.remove(wallet);
+        }
+    }
+
+    /// Evicts the least recently used wallets beyond the capacity, leaving room for
+    /// as many more, and an eighth of it more at once, so that the wallets are not
+    /// ordered on every load. A wallet that the store fails to save is kept in memory.
+    fn evict(&mut self, room: usize) {
+        let (store, capacity) = match &self.store {
+            Some((store, capacity)) if self.resident.len() + room > *capacity => {
+                (store.clone(), *capacity)
+            }
+            _ => return,
+        };
+        let keep = (capacity - capacity / 8).saturating_sub(room);
+        let mut uses: Vec<_> = self
+            .resident
+            .iter()
+            .map(|(wallet, resident)| (resident.last_used.load(Ordering::Relaxed), *wallet))
+            .collect();
+        uses.sort();
+        let excess = uses.len().saturating_sub(keep);
+        for (_, wallet) in uses.into_iter().take(excess) {
+            let saved = self
+                .resident
+                .get(&wallet)
+                .map_or(false, |resident| store.save(&resident.account).is_ok());
+            if saved {
+                let _ = self.resident.remove(&wallet);
+                let _ = self.evicted.insert(wallet);
+            }
+        }
+    }
+}
+
+impl Default for ResidentWallets {
+    fn default() -> Self {
+        Self {
+            resident: Default::default(),
+            evicted: Default::default(),
+            store: None,
+            checksums: false,
+            uses: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Clone for ResidentWallets {
+    fn clone(&self) -> Self {
+        Self {
+            resident: self.resident.clone(),
+            evicted: self.evicted.clone(),
+            store: self.store.clone(),
+            checksums: self.checksums,
+            uses: AtomicU64::new(self.uses.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Equal if holding the same wallets in memory, and the same evicted, up to the same
+/// capacity. The store is a handle to what is plugged in, and not compared.
+impl PartialEq for ResidentWallets {
+    fn eq(&self, other: &Self) -> bool {
+        self.resident == other.resident
+            && self.evicted == other.evicted
+            && self.store.as_ref().map(|(_, capacity)| capacity)
+                == other.store.as_ref().map(|(_, capacity)| capacity)
+    }
+}
+
+impl Eq for ResidentWallets {}
+
+impl std::iter::FromIterator<(AccountId, Account)> for ResidentWallets {
+    fn from_iter<I: IntoIterator<Item = (AccountId, Account)>>(iter: I) -> Self {
+        let mut wallets = Self::default();
+        for (wallet, account) in iter {
+            let resident = Resident {
+                account,
+                last_used: AtomicU64::new(0),
+            };
+            let _ = wallets.resident.insert(wallet, resident);
+        }
+        wallets
+    }
+}
+
+mod test {
+    use super::*;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn evicts_the_least_recently_used_wallets() {
+        // Arrange
+        let wallets: Vec<_> = (0..3).map(|_| get_random_pk()).collect();
+        let resident: ResidentWallets = wallets
+            .iter()
+            .map(|wallet| (*wallet, Account::new(*wallet)))
+            .collect();
+        let _ = resident.get(&wallets[0]);
+        let _ = resident.get(&wallets[2]);
+
+        // Act
+        let store = WalletStore::new(MemoryWalletBackend::new());
+        let mut resident = resident.with_store(store, 2);
+        let evicted = !resident.resident.contains_key(&wallets[1]);
+        let loaded = resident
+            .get(&wallets[1])
+            .unwrap()
+            .map(|account| account.id());
+        let _ = resident.get_mut(&wallets[1]);
+
+        // Assert
+        assert!(evicted);
+        assert!(loaded == Some(wallets[1]));
+        assert!(resident.len() == 3 && resident.resident_len() == 2);
+        assert!(resident.resident.contains_key(&wallets[1]));
+        assert!(!resident.resident.contains_key(&wallets[0]));
+        assert!(resident.values().filter(Result::is_ok).count() == 3);
+        assert!(matches!(resident.remove(&wallets[0]), Ok(Some(_))));
+        assert!(!resident.contains_key(&wallets[0]));
+        assert!(matches!(resident.get(&wallets[0]), Ok(None)));
+    }
+
+    #[test]
+    fn returns_the_errors_of_wallets_failing_to_load() {
+        // Arrange
+        let wallets: Vec<_> = (0..3).map(|_| get_random_pk()).collect();
+        let resident: ResidentWallets = wallets
+            .iter()
+            .map(|wallet| (*wallet, Account::new(*wallet)))
+            .collect();
+        let _ = resident.get(&wallets[0]);
+        let _ = resident.get(&wallets[2]);
+        let mut resident = resident.with_store(WalletStore::new(Unreadable), 2);
+
+        // Act
+        let loaded = resident.get(&wallets[1]).is_err();
+        let loaded_mut = resident.get_mut(&wallets[1]).is_err();
+        let inserted = resident
+            .get_or_insert_with(wallets[1], || Account::new(wallets[1]))
+            .is_err();
+
+        // Assert
+        assert!(loaded);
+        assert!(loaded_mut);
+        assert!(inserted);
+        assert!(resident.evicted.contains(&wallets[1]));
+        assert!(!resident.resident.contains_key(&wallets[1]));
+        assert!(resident.values().filter(Result::is_err).count() == 1);
+        assert!(resident.iter().filter(Result::is_ok).count() == 2);
+    }
+
+    /// A backend storing the wallets, but failing to load any of them.
+    struct Unreadable;
+
+    impl WalletBackend for Unreadable {
+        fn save(&mut self, _: &AccountId, _: Vec<u8>) -> Result<()> {
+            Ok(())
+        }
+
+        fn load(&self, _: &AccountId) -> Result<Option<Vec<u8>>> {
+            Err(Error::from("Unreadable"))
+        }
+
+        fn remove(&mut self, _: &AccountId) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
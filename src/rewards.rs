@@ -0,0 +1,159 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    money::{checked_sum, credited},
+    replica::Replica,
+    simulation::PayoutMint,
+    ReplicaEvent,
+};
+use safe_nd::{AccountId, DebitAgreementProof, Error, Money, Result, SafeKey};
+use std::collections::BTreeSet;
+
+/// A reference model of the node reward flow of sn_node, end to end:
+/// the section wallet is funded by payouts, and debited to credit the
+/// wallets of many nodes. Every debit is validated, registered and
+/// propagated through the Replica API, and after every step the supply
+/// held by the section and the nodes is checked against what was paid in,
+/// so that the reward implementation of sn_node can be validated against it.
+#[derive(Debug)]
+pub struct RewardFlow {
+    mint: PayoutMint,
+    replica: Replica,
+    section: SafeKey,
+    next_counter: u64,
+    supply: Money,
+    nodes: BTreeSet<AccountId>,
+}
+
+impl RewardFlow {
+    /// A flow where the section wallet, owned by the given key,
+    /// is held by a Replica of the group of the mint.
+    pub fn new(mint: PayoutMint, section: SafeKey) -> Self {
+        let replica = mint.replica(0);
+        Self {
+            mint,
+            replica,
+            section,
+            next_counter: 0,
+            supply: Money::zero(),
+            nodes: Default::default(),
+        }
+    }
+
+    /// The section wallet.
+    pub fn section(&self) -> AccountId {
+        self.section.public_key()
+    }
+
+    /// The Replica holding the section and node wallets.
+    pub fn replica(&self) -> &Replica {
+        &self.replica
+    }
+
+    /// The total paid in to the section wallet.
+    pub fn supply(&self) -> Money {
+        self.supply
+    }
+
+    /// The node wallets credited so far.
+    pub fn nodes(&self) -> &BTreeSet<AccountId> {
+        &self.nodes
+    }
+
+    /// Pays the amount in to the section wallet.
+    pub fn fund(&mut self, amount: Money) -> Result<()> {
+        let supply = credited(self.supply, amount)?;
+        let credit = self.mint.credit(self.section(), amount)?;
+        self.replica.simulated_credit(&credit)?;
+        self.supply = supply;
+        self.check_conservation()
+    }
+
+    /// Debits the section wallet once per reward, crediting the node wallet.
+    /// Returns the proofs of the debits, in the order of the rewards.
+    /// Stops at the first reward that fails, such as one exceeding the
+    /// remaining balance of the section, leaving the earlier ones paid.
+    pub fn pay_rewards(
+        &mut self,
+        rewards: &[(AccountId, Money)],
+    ) -> Result<Vec<DebitAgreementProof>> {
+        let mut proofs = vec![];
+        for (node, amount) in rewards {
+            let proof = self
+                .mint
+                .debit(&self.section, self.next_counter, *node, *amount)?;
+            let validated = self.replica.validate(proof.signed_transfer.clone())?;
+            self.replica
+                .apply(ReplicaEvent::TransferValidated(validated));
+            self.replica.simulated_debit(&proof)?;
+            self.replica.simulated_credit(&proof)?;
+            self.next_counter += 1;
+            let _ = self.nodes.insert(*node);
+            self.check_conservation()?;
+            proofs.push(proof);
+        }
+        Ok(proofs)
+    }
+
+    /// Verifies that the balances of the section and the nodes
+    /// add up to exactly what was paid in to the section.
+    pub fn check_conservation(&self) -> Result<()> {
+        let balances = std::iter::once(self.section())
+            .chain(self.nodes.iter().cloned())
+            .map(|id| self.replica.balance(&id).unwrap_or_else(Money::zero));
+        match checked_sum(balances) {
+            Some(held) if held == self.supply => Ok(()),
+            _ => Err(Error::from("Supply is not conserved")),
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use safe_nd::{ClientFullId, PublicKey};
+    use threshold_crypto::{SecretKey, SecretKeySet};
+
+    #[test]
+    fn rewards_conserve_supply() -> Result<()> {
+        // Arrange
+        let mut flow = RewardFlow::new(get_mint(), get_safe_key());
+        flow.fund(Money::from_nano(1_000))?;
+        let rewards: Vec<_> = (1..=20)
+            .map(|i| (get_random_pk(), Money::from_nano(i)))
+            .collect();
+
+        // Act
+        let proofs = flow.pay_rewards(&rewards)?;
+        flow.fund(Money::from_nano(50))?;
+        let overdraft = flow.pay_rewards(&[(get_random_pk(), Money::from_nano(1_000))]);
+
+        // Assert
+        assert!(proofs.len() == 20 && flow.nodes().len() == 20);
+        for (node, amount) in &rewards {
+            assert!(flow.replica().balance(node) == Some(*amount));
+        }
+        assert!(flow.replica().balance(&flow.section()) == Some(Money::from_nano(840)));
+        assert!(flow.supply() == Money::from_nano(1_050));
+        assert!(overdraft.is_err());
+        assert!(flow.check_conservation().is_ok());
+        Ok(())
+    }
+
+    fn get_mint() -> PayoutMint {
+        PayoutMint::new(SecretKeySet::random(0, &mut rand::thread_rng()))
+    }
+
+    fn get_safe_key() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::signer::TransferSigner;
+use safe_nd::{AccountId, Error, PublicKey, Result, Signature};
+use serde::{Deserialize, Serialize};
+
+/// The request of the owner of a wallet to hand it over to a new key, such as when
+/// the current key may have been compromised. Signed by the current key, it names
+/// the new key, and the number of rotations of the wallet before it, so that it can
+/// not be replayed once the wallet has been rotated again (see Replica::rotate_wallet_key).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletKeyRotation {
+    /// The current key of the wallet.
+    pub wallet: AccountId,
+    /// The key that the wallet is handed over to.
+    pub new_key: PublicKey,
+    /// The number of rotations of the wallet before this one.
+    pub sequence: u64,
+    /// The signature of the current key over the above.
+    pub signature: Signature,
+}
+
+impl WalletKeyRotation {
+    /// The rotation of the wallet of the signer to the new key.
+    pub fn new<S: TransferSigner + ?Sized>(
+        signer: &S,
+        new_key: PublicKey,
+        sequence: u64,
+    ) -> Result<Self> {
+        let wallet = signer.public_key();
+        let signature = signer.sign(&rotation_payload(&wallet, &new_key, sequence)?)?;
+        Ok(Self {
+            wallet,
+            new_key,
+            sequence,
+            signature,
+        })
+    }
+
+    /// Verifies that the rotation was signed by the current key, to another key.
+    pub fn verify(&self) -> Result<()> {
+        if self.new_key == self.wallet {
+            return Err(Error::from("Wallet is rotated to its own key"));
+        }
+        let payload = rotation_payload(&self.wallet, &self.new_key, self.sequence)?;
+        self.wallet.verify(&self.signature, &payload)
+    }
+}
+
+/// The bytes of a rotation signed by the current key.
+fn rotation_payload(wallet: &AccountId, new_key: &PublicKey, sequence: u64) -> Result<Vec<u8>> {
+    match bincode::serialize(&(b"wallet_key_rotation", wallet, new_key, sequence)) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise rotation".into())),
+        Ok(data) => Ok(data),
+    }
+}
+
+mod test {
+    use super::*;
+    use safe_nd::{ClientFullId, SafeKey};
+
+    #[test]
+    fn verifies_rotations_to_another_key() -> Result<()> {
+        // Arrange
+        let current = get_safe_key();
+        let new_key = get_safe_key().public_key();
+
+        // Act
+        let rotation = WalletKeyRotation::new(&current, new_key, 0)?;
+        let to_itself = WalletKeyRotation::new(&current, current.public_key(), 0)?;
+
+        // Assert
+        assert!(rotation.wallet == current.public_key());
+        assert!(rotation.verify().is_ok());
+        assert!(to_itself.verify().is_err());
+        Ok(())
+    }
+
+    fn get_safe_key() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+}
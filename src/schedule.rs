@@ -0,0 +1,269 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{clock::Timestamp, signer::SigningRequest};
+use safe_nd::{AccountId, Money, Transfer, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The most payments returned as due at once (see Actor::due_transfers).
+pub const MAX_DUE_TRANSFERS: usize = 64;
+
+/// When a scheduled transfer stops recurring.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum ScheduleEnd {
+    /// Recurs until cancelled.
+    Never,
+    /// Recurs until the number of payments has been made.
+    After(u64),
+    /// Recurs until the time, after which no payment is due.
+    At(Timestamp),
+}
+
+/// A payment recurring at an interval, such as a subscription
+/// (see Actor::schedule_transfer).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ScheduledTransfer {
+    /// The id of the schedule among those of the Actor.
+    pub id: u64,
+    /// The recipient.
+    pub to: AccountId,
+    /// The amount of each payment.
+    pub amount: Money,
+    /// When the first payment is due.
+    pub first_due: Timestamp,
+    /// The time between payments, in seconds.
+    pub interval: u64,
+    /// When the payments stop.
+    pub end: ScheduleEnd,
+}
+
+impl ScheduledTransfer {
+    /// When the payment is due, if it is one of the schedule.
+    pub fn due_at(&self, payment: u64) -> Option<Timestamp> {
+        if let ScheduleEnd::After(payments) = self.end {
+            if payment >= payments {
+                return None;
+            }
+        }
+        let due = self
+            .interval
+            .checked_mul(payment)
+            .and_then(|offset| self.first_due.checked_add(offset))?;
+        match self.end {
+            ScheduleEnd::At(end) if due > end => None,
+            _ => Some(due),
+        }
+    }
+}
+
+/// A payment of a schedule that is due, with the transfer to sign for it.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct DueTransfer {
+    /// The id of the schedule.
+    pub schedule: u64,
+    /// The number of the payment in the schedule, from 0.
+    pub payment: u64,
+    /// When the payment was due.
+    pub due_at: Timestamp,
+    /// The transfer, at the counter it is to be initiated at, to be
+    /// signed and passed to Actor::complete_transfer in its turn.
+    pub request: SigningRequest,
+}
+
+/// The payments made of a schedule, and the debit of the last, until registered.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+struct SchedulePayments {
+    made: u64,
+    in_flight: Option<TransferId>,
+}
+
+/// The transfers scheduled by an Actor, with the payments made of each. A debit to
+/// the recipient of a schedule, of its amount, makes its next payment, whether or not
+/// initiated from Actor::due_transfers. Schedules with no payment left are kept until
+/// cancelled, for their payments to be queried.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct TransferSchedule {
+    next_id: u64,
+    schedules: BTreeMap<u64, (ScheduledTransfer, SchedulePayments)>,
+}
+
+impl TransferSchedule {
+    /// The id of the next transfer scheduled.
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// The transfers scheduled, oldest first.
+    pub fn scheduled(&self) -> Vec<&ScheduledTransfer> {
+        self.schedules
+            .values()
+            .map(|(scheduled, _)| scheduled)
+            .collect()
+    }
+
+    /// The schedule with the id, if not cancelled.
+    pub fn get(&self, id: u64) -> Option<&ScheduledTransfer> {
+        self.schedules.get(&id).map(|(scheduled, _)| scheduled)
+    }
+
+    /// The number of payments made of the schedule, if not cancelled.
+    pub fn payments_made(&self, id: u64) -> Option<u64> {
+        self.schedules.get(&id).map(|(_, payments)| payments.made)
+    }
+
+    /// The payments due by the time and not yet made, as (schedule, payment, due_at),
+    /// the earliest due first, and no more than MAX_DUE_TRANSFERS.
+    pub(crate) fn due(&self, now: Timestamp) -> Vec<(&ScheduledTransfer, u64, Timestamp)> {
+        let mut due = vec![];
+        for (scheduled, payments) in self.schedules.values() {
+            let due_payments = (payments.made..)
+                .map(|payment| (payment, scheduled.due_at(payment)))
+                .take_while(|(_, due_at)| due_at.map_or(false, |due_at| due_at <= now))
+                .take(MAX_DUE_TRANSFERS)
+                .filter_map(|(payment, due_at)| Some((scheduled, payment, due_at?)));
+            due.extend(due_payments);
+        }
+        due.sort_by_key(|(scheduled, payment, due_at)| (*due_at, scheduled.id, *payment));
+        due.truncate(MAX_DUE_TRANSFERS);
+        due
+    }
+
+    /// Adds a schedule.
+    pub(crate) fn push(&mut self, scheduled: ScheduledTransfer) {
+        self.next_id = scheduled.id + 1;
+        let payments = SchedulePayments {
+            made: 0,
+            in_flight: None,
+        };
+        let _ = self.schedules.insert(scheduled.id, (scheduled, payments));
+    }
+
+    /// Removes a schedule.
+    pub(crate) fn cancel(&mut self, id: u64) {
+        let _ = self.schedules.remove(&id);
+    }
+
+    /// Makes the next payment of the first schedule with payments left that the
+    /// debit is of, by its recipient and amount.
+    pub(crate) fn initiated(&mut self, transfer: &Transfer) {
+        let next = self.schedules.values_mut().find(|(scheduled, payments)| {
+            scheduled.to == transfer.to
+                && scheduled.amount == transfer.amount
+                && scheduled.due_at(payments.made).is_some()
+        });
+        if let Some((_, payments)) = next {
+            payments.made += 1;
+            payments.in_flight = Some(transfer.id);
+        }
+    }
+
+    /// Forgets the debits in flight of payments, once registered.
+    pub(crate) fn registered(&mut self, next_debit: u64) {
+        for (_, payments) in self.schedules.values_mut() {
+            if payments
+                .in_flight
+                .map_or(false, |id| id.counter < next_debit)
+            {
+                payments.in_flight = None;
+            }
+        }
+    }
+
+    /// Takes back the payment the debit made, as it was dropped before registration.
+    pub(crate) fn discarded(&mut self, id: &TransferId) {
+        for (_, payments) in self.schedules.values_mut() {
+            if payments.in_flight == Some(*id) {
+                payments.made -= 1;
+                payments.in_flight = None;
+            }
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn returns_payments_due_until_the_end() {
+        // Arrange
+        let mut schedule = TransferSchedule::default();
+        schedule.push(get_scheduled(0, ScheduleEnd::After(3)));
+        schedule.push(get_scheduled(1, ScheduleEnd::At(250)));
+
+        // Act
+        let due: Vec<_> = schedule
+            .due(1_000)
+            .into_iter()
+            .map(|(scheduled, payment, due_at)| (scheduled.id, payment, due_at))
+            .collect();
+
+        // Assert
+        assert!(
+            due == vec![
+                (0, 0, 100),
+                (1, 0, 100),
+                (0, 1, 200),
+                (1, 1, 200),
+                (0, 2, 300)
+            ]
+        );
+        assert!(schedule.due(99).is_empty());
+        assert!(schedule.next_id() == 2);
+    }
+
+    #[test]
+    fn takes_back_payments_of_discarded_debits() {
+        // Arrange
+        let mut schedule = TransferSchedule::default();
+        let scheduled = get_scheduled(0, ScheduleEnd::Never);
+        schedule.push(scheduled.clone());
+        let sender = get_random_pk();
+        let first = get_transfer(sender, 0, &scheduled);
+        let second = get_transfer(sender, 1, &scheduled);
+
+        // Act
+        schedule.initiated(&first);
+        schedule.registered(1);
+        schedule.initiated(&second);
+        schedule.discarded(&first.id);
+        let before_discard = schedule.payments_made(0);
+        schedule.discarded(&second.id);
+
+        // Assert
+        assert!(before_discard == Some(2));
+        assert!(schedule.payments_made(0) == Some(1));
+        assert!(schedule.due(200).len() == 1);
+    }
+
+    fn get_scheduled(id: u64, end: ScheduleEnd) -> ScheduledTransfer {
+        ScheduledTransfer {
+            id,
+            to: get_random_pk(),
+            amount: Money::from_nano(10),
+            first_due: 100,
+            interval: 100,
+            end,
+        }
+    }
+
+    fn get_transfer(sender: PublicKey, counter: u64, scheduled: &ScheduledTransfer) -> Transfer {
+        Transfer {
+            id: Dot::new(sender, counter),
+            to: scheduled.to,
+            amount: scheduled.amount,
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
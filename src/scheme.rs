@@ -0,0 +1,290 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::codec::{accepted_codecs, verified_in, CodecMigration, Payload};
+use safe_nd::{DebitAgreementProof, Error, PublicKey, Result, Signature, SignatureShare};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+use threshold_crypto::PublicKeySet;
+
+/// The cryptography that signatures of actors and groups are verified with.
+/// Replicas verify through this, so that a new scheme (such as blsttc, or a
+/// post-quantum hybrid) can be introduced behind a feature flag, and verified
+/// alongside the current one during a migration, instead of being a breaking rewrite.
+pub trait SignatureScheme: Send + Sync {
+    /// The name of the scheme, for diagnostics.
+    fn name(&self) -> &str;
+    /// Verifies the signature of the key over the data.
+    fn verify(&self, public_key: &PublicKey, signature: &Signature, data: &[u8]) -> bool;
+    /// Verifies the signature share of a member of the group over the data.
+    fn verify_share(&self, group: &PublicKeySet, share: &SignatureShare, data: &[u8]) -> bool;
+    /// Verifies the signatures of the key over the data of each item, true only if all
+    /// are valid. One by one by default, which SafeNdScheme keeps, as threshold_crypto has
+    /// no aggregate verification. A scheme that has one may override this.
+    fn verify_batch(&self, public_key: &PublicKey, items: &[(&Signature, &[u8])]) -> bool {
+        items
+            .iter()
+            .all(|(signature, data)| self.verify(public_key, signature, data))
+    }
+}
+
+/// The scheme of safe-nd keys: ed25519 for clients, and BLS,
+/// over threshold_crypto, for groups and their members.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SafeNdScheme;
+
+impl SignatureScheme for SafeNdScheme {
+    fn name(&self) -> &str {
+        "safe-nd"
+    }
+
+    fn verify(&self, public_key: &PublicKey, signature: &Signature, data: &[u8]) -> bool {
+        public_key.verify(signature, data).is_ok()
+    }
+
+    fn verify_share(&self, group: &PublicKeySet, share: &SignatureShare, data: &[u8]) -> bool {
+        group
+            .public_key_share(share.index)
+            .verify(&share.share, data)
+    }
+}
+
+/// A signature under both the previous and the current scheme,
+/// carried during a transition from one to the other.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct HybridSignature {
+    /// The signature under the previous scheme.
+    pub previous: Signature,
+    /// The signature under the current scheme.
+    pub current: Signature,
+}
+
+/// A debit proof signed by the debiting Replicas under both schemes during a
+/// transition: the signature of the proof is under the previous scheme, so that
+/// peers not yet upgraded can still verify it, and the other under the current one.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct HybridProof {
+    /// The proof, signed under the previous scheme.
+    pub debit_proof: DebitAgreementProof,
+    /// The signature of the Replicas over the same transfer, under the current scheme.
+    pub current_sig: Signature,
+}
+
+impl HybridProof {
+    /// Both signatures of the proof.
+    pub fn signature(&self) -> HybridSignature {
+        HybridSignature {
+            previous: self.debit_proof.debiting_replicas_sig.clone(),
+            current: self.current_sig.clone(),
+        }
+    }
+}
+
+/// The scheme signatures are verified with, and during a migration, the previous
+/// one, whose signatures are also accepted until the section reaches a given height.
+/// Clones share the same schemes.
+#[derive(Clone)]
+pub struct SchemeVerifier {
+    current: Arc<dyn SignatureScheme>,
+    previous: Option<(Arc<dyn SignatureScheme>, u64)>,
+    codec_migration: Option<CodecMigration>,
+}
+
+impl SchemeVerifier {
+    /// Verifies with the scheme only.
+    pub fn new<S: SignatureScheme + 'static>(scheme: S) -> Self {
+        Self {
+            current: Arc::new(scheme),
+            previous: None,
+            codec_migration: None,
+        }
+    }
+
+    /// Also accepts signatures of the previous scheme, below the given section height.
+    pub fn with_previous<S: SignatureScheme + 'static>(
+        mut self,
+        previous: S,
+        until_height: u64,
+    ) -> Self {
+        self.previous = Some((Arc::new(previous), until_height));
+        self
+    }
+
+    /// Verifies proofs over the codecs accepted at the section height by the move
+    /// between them (see CodecMigration), instead of over the canonical encoding only.
+    pub fn with_codec_migration(mut self, migration: CodecMigration) -> Self {
+        self.codec_migration = Some(migration);
+        self
+    }
+
+    /// Verifies the signature of the key over the data, at the section height.
+    pub fn verify(
+        &self,
+        public_key: &PublicKey,
+        signature: &Signature,
+        data: &[u8],
+        height: u64,
+    ) -> bool {
+        self.current.verify(public_key, signature, data)
+            || self.previous_at(height).map_or(false, |previous| {
+                previous.verify(public_key, signature, data)
+            })
+    }
+
+    /// Verifies the signatures of the key over the data of each item, with the current
+    /// scheme (see SignatureScheme::verify_batch), which for SafeNdScheme is one by one,
+    /// and so no faster than verifying each. False if any is invalid, or is only valid
+    /// under the previous scheme, in which case they are to be verified one by one.
+    pub fn verify_batch(&self, public_key: &PublicKey, items: &[(&Signature, &[u8])]) -> bool {
+        self.current.verify_batch(public_key, items)
+    }
+
+    /// Verifies the signature share of a member of the group over the data, at the section height.
+    pub fn verify_share(
+        &self,
+        group: &PublicKeySet,
+        share: &SignatureShare,
+        data: &[u8],
+        height: u64,
+    ) -> bool {
+        self.current.verify_share(group, share, data)
+            || self
+                .previous_at(height)
+                .map_or(false, |previous| previous.verify_share(group, share, data))
+    }
+
+    /// Verifies a signature under both schemes, with the key of the signer under each:
+    /// it is valid if its current signature is, or, while the previous scheme is still
+    /// accepted at the section height, if its previous signature is.
+    pub fn verify_hybrid(
+        &self,
+        previous_key: &PublicKey,
+        current_key: &PublicKey,
+        signature: &HybridSignature,
+        data: &[u8],
+        height: u64,
+    ) -> bool {
+        self.current.verify(current_key, &signature.current, data)
+            || self.previous_at(height).map_or(false, |previous| {
+                previous.verify(previous_key, &signature.previous, data)
+            })
+    }
+
+    /// Verifies that the hybrid proof was agreed by the Replicas with the keys,
+    /// over the codecs accepted at the section height.
+    pub fn verify_hybrid_proof(
+        &self,
+        proof: &HybridProof,
+        previous_key: &PublicKey,
+        current_key: &PublicKey,
+        height: u64,
+    ) -> Result<()> {
+        let signature = proof.signature();
+        let codecs = accepted_codecs(self.codec_migration.as_ref(), height);
+        let payload = Payload::Proof(&proof.debit_proof.signed_transfer);
+        let verified = verified_in(&codecs, payload, |data| {
+            self.verify_hybrid(previous_key, current_key, &signature, data, height)
+        });
+        if verified {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+
+    fn previous_at(&self, height: u64) -> Option<&Arc<dyn SignatureScheme>> {
+        match &self.previous {
+            Some((previous, until_height)) if height < *until_height => Some(previous),
+            _ => None,
+        }
+    }
+}
+
+impl Default for SchemeVerifier {
+    fn default() -> Self {
+        Self::new(SafeNdScheme)
+    }
+}
+
+impl Debug for SchemeVerifier {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.previous {
+            None => write!(f, "SchemeVerifier({})", self.current.name()),
+            Some((previous, until_height)) => write!(
+                f,
+                "SchemeVerifier({}, {} until {})",
+                self.current.name(),
+                previous.name(),
+                until_height
+            ),
+        }
+    }
+}
+
+mod test {
+    use super::*;
+
+    struct Unknown;
+
+    impl SignatureScheme for Unknown {
+        fn name(&self) -> &str {
+            "unknown"
+        }
+
+        fn verify(&self, _: &PublicKey, _: &Signature, _: &[u8]) -> bool {
+            false
+        }
+
+        fn verify_share(&self, _: &PublicKeySet, _: &SignatureShare, _: &[u8]) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn accepts_previous_scheme_during_migration() {
+        // Arrange
+        let secret_key = threshold_crypto::SecretKey::random();
+        let public_key = PublicKey::Bls(secret_key.public_key());
+        let signature = Signature::Bls(secret_key.sign(b"data"));
+        let verifier = SchemeVerifier::new(Unknown).with_previous(SafeNdScheme, 10);
+
+        // Act
+        let during = verifier.verify(&public_key, &signature, b"data", 9);
+        let after = verifier.verify(&public_key, &signature, b"data", 10);
+
+        // Assert
+        assert!(during);
+        assert!(!after);
+        assert!(SchemeVerifier::default().verify(&public_key, &signature, b"data", 10));
+    }
+
+    #[test]
+    fn accepts_either_signature_of_hybrids() {
+        // Arrange
+        let secret_key = threshold_crypto::SecretKey::random();
+        let public_key = PublicKey::Bls(secret_key.public_key());
+        let signature = HybridSignature {
+            previous: Signature::Bls(secret_key.sign(b"data")),
+            current: Signature::Bls(secret_key.sign(b"data")),
+        };
+        let migrating = SchemeVerifier::new(Unknown).with_previous(SafeNdScheme, 10);
+        let migrated = SchemeVerifier::default().with_previous(Unknown, 10);
+
+        // Act
+        let during = migrating.verify_hybrid(&public_key, &public_key, &signature, b"data", 9);
+        let after = migrating.verify_hybrid(&public_key, &public_key, &signature, b"data", 10);
+
+        // Assert
+        assert!(during);
+        assert!(!after);
+        assert!(migrated.verify_hybrid(&public_key, &public_key, &signature, b"data", 10));
+    }
+}
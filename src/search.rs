@@ -0,0 +1,407 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::account::Account;
+use safe_nd::{AccountId, Money, Transfer, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Whether a payment was sent or received by the account.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum PaymentDirection {
+    /// A debit of the account.
+    Sent,
+    /// A credit to the account.
+    Received,
+}
+
+/// A transfer in the history of an account, as found by a search.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Payment {
+    /// Whether it was sent or received.
+    pub direction: PaymentDirection,
+    /// The transfer.
+    pub transfer: Transfer,
+}
+
+impl Payment {
+    /// The other account of the transfer.
+    pub fn counterparty(&self) -> AccountId {
+        match self.direction {
+            PaymentDirection::Sent => self.transfer.to,
+            PaymentDirection::Received => self.transfer.id.actor,
+        }
+    }
+}
+
+/// The criteria of a search of the history of an account.
+/// Transfers carry no time, so results are paged by their position
+/// in the history, the most recently applied first.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct PaymentQuery {
+    /// Only payments of exactly this amount.
+    pub amount: Option<Money>,
+    /// Only payments to or from this account.
+    pub counterparty: Option<AccountId>,
+    /// Only payments in this direction.
+    pub direction: Option<PaymentDirection>,
+    /// Only payments tagged with this by their sender (see Actor::attach_tags).
+    /// As tags are attached to credits, these are payments received.
+    pub tag: Option<String>,
+    /// Number of matching payments to skip, the most recent first.
+    pub skip: usize,
+    /// Maximum number of payments returned, all if None.
+    pub limit: Option<usize>,
+}
+
+/// The payments of the accounts held by a Replica, indexed by counterparty and by tag,
+/// and the account each credit was received by.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PaymentIndex {
+    payments: HashMap<AccountId, Vec<Payment>>,
+    by_counterparty: HashMap<(AccountId, AccountId), Vec<usize>>,
+    by_tag: HashMap<(AccountId, String), Vec<usize>>,
+    credited: HashMap<TransferId, AccountId>,
+}
+
+impl PaymentIndex {
+    /// Indexes the complete histories of the accounts, credits first.
+    pub(crate) fn of<'a, I: IntoIterator<Item = &'a Account>>(accounts: I) -> Self {
+        let mut index = Self::default();
+        for account in accounts {
+            for entry in account.credit_entries() {
+                index.insert(account.id(), PaymentDirection::Received, entry.transfer());
+            }
+            for entry in account.debit_entries() {
+                index.insert(account.id(), PaymentDirection::Sent, entry.transfer());
+            }
+        }
+        index
+    }
+
+    /// Indexes a transfer appended to the history of the account.
+    pub(crate) fn insert(
+        &mut self,
+        account_id: AccountId,
+        direction: PaymentDirection,
+        transfer: &Transfer,
+    ) {
+        let payment = Payment {
+            direction,
+            transfer: transfer.clone(),
+        };
+        if direction == PaymentDirection::Received {
+            let _ = self.credited.insert(transfer.id, account_id);
+        }
+        let payments = self.payments.entry(account_id).or_default();
+        self.by_counterparty
+            .entry((account_id, payment.counterparty()))
+            .or_default()
+            .push(payments.len());
+        payments.push(payment);
+    }
+
+    /// Indexes the tags attached to a credit of the account, if indexed.
+    pub(crate) fn tag(&mut self, account_id: AccountId, transfer_id: &TransferId, tags: &[&str]) {
+        let position = match self.payments.get(&account_id).and_then(|payments| {
+            // attachments are stored once the credit is propagated, so it is among the latest
+            payments.iter().rposition(|p| {
+                p.direction == PaymentDirection::Received && p.transfer.id == *transfer_id
+            })
+        }) {
+            Some(position) => position,
+            None => return,
+        };
+        for tag in tags {
+            let positions = self
+                .by_tag
+                .entry((account_id, tag.to_string()))
+                .or_default();
+            // tags are attached in any order, and positions are kept in the order of the history
+            if let Err(at) = positions.binary_search(&position) {
+                positions.insert(at, position);
+            }
+        }
+    }
+
+    /// Moves the payments of the account to its new key, once the key is rotated.
+    pub(crate) fn rekey(&mut self, previous: &AccountId, current: AccountId) {
+        if let Some(payments) = self.payments.remove(previous) {
+            let _ = self.payments.insert(current, payments);
+        }
+        let by_counterparty: Vec<_> = self
+            .by_counterparty
+            .keys()
+            .filter(|(account_id, _)| account_id == previous)
+            .copied()
+            .collect();
+        for key in by_counterparty {
+            if let Some(positions) = self.by_counterparty.remove(&key) {
+                let _ = self.by_counterparty.insert((current, key.1), positions);
+            }
+        }
+        let by_tag: Vec<_> = self
+            .by_tag
+            .keys()
+            .filter(|(account_id, _)| account_id == previous)
+            .cloned()
+            .collect();
+        for key in by_tag {
+            if let Some(positions) = self.by_tag.remove(&key) {
+                let _ = self.by_tag.insert((current, key.1), positions);
+            }
+        }
+        for account_id in self.credited.values_mut() {
+            if account_id == previous {
+                *account_id = current;
+            }
+        }
+    }
+
+    /// Drops the payments of the account that are no longer in its history, such as once
+    /// compacted away, or all of them once the account is exported to another group,
+    /// along with their positions and the accounts their credits were received by.
+    pub(crate) fn retain<F: Fn(&TransferId) -> bool>(&mut self, account_id: &AccountId, keep: F) {
+        let payments = match self.payments.remove(account_id) {
+            Some(payments) => payments,
+            None => return,
+        };
+        // the position of each payment kept, by its position before
+        let mut positions = Vec::with_capacity(payments.len());
+        let mut kept = vec![];
+        for payment in payments {
+            if keep(&payment.transfer.id) {
+                positions.push(Some(kept.len()));
+                kept.push(payment);
+            } else {
+                positions.push(None);
+                if self.credited.get(&payment.transfer.id) == Some(account_id) {
+                    let _ = self.credited.remove(&payment.transfer.id);
+                }
+            }
+        }
+        let remap = |indexed: &mut Vec<usize>| {
+            *indexed = indexed
+                .iter()
+                .filter_map(|position| positions[*position])
+                .collect();
+            !indexed.is_empty()
+        };
+        self.by_counterparty
+            .retain(|(id, _), indexed| id != account_id || remap(indexed));
+        self.by_tag
+            .retain(|(id, _), indexed| id != account_id || remap(indexed));
+        if !kept.is_empty() {
+            let _ = self.payments.insert(*account_id, kept);
+        }
+    }
+
+    /// The account the credit was received by, if indexed. The account
+    /// may since have been rotated, moved, or have dropped the credit.
+    pub(crate) fn credited_to(&self, transfer_id: &TransferId) -> Option<AccountId> {
+        self.credited.get(transfer_id).copied()
+    }
+
+    /// The payments of the account, in the order they were indexed.
+    pub(crate) fn payments(&self, account_id: &AccountId) -> &[Payment] {
+        self.payments
+            .get(account_id)
+            .map_or(&[], |payments| payments)
+    }
+
+    /// The payments of the account matching the query, the most recent first.
+    pub(crate) fn find(&self, account_id: &AccountId, query: &PaymentQuery) -> Vec<Payment> {
+        let payments = match self.payments.get(account_id) {
+            Some(payments) => payments,
+            None => return vec![],
+        };
+        let by_counterparty = query.counterparty.map(|counterparty| {
+            self.by_counterparty
+                .get(&(*account_id, counterparty))
+                .map_or(&[][..], Vec::as_slice)
+        });
+        let by_tag = query.tag.as_ref().map(|tag| {
+            self.by_tag
+                .get(&(*account_id, tag.clone()))
+                .map_or(&[][..], Vec::as_slice)
+        });
+        let positions: Vec<usize> = match (by_counterparty, by_tag) {
+            (Some(by_counterparty), Some(by_tag)) => by_counterparty
+                .iter()
+                .filter(|position| by_tag.binary_search(position).is_ok())
+                .copied()
+                .collect(),
+            (Some(indexed), None) | (None, Some(indexed)) => indexed.to_vec(),
+            (None, None) => (0..payments.len()).collect(),
+        };
+        positions
+            .into_iter()
+            .rev()
+            .map(|position| &payments[position])
+            .filter(|p| {
+                query
+                    .amount
+                    .map_or(true, |amount| p.transfer.amount == amount)
+            })
+            .filter(|p| {
+                query
+                    .direction
+                    .map_or(true, |direction| p.direction == direction)
+            })
+            .skip(query.skip)
+            .take(query.limit.unwrap_or(usize::max_value()))
+            .cloned()
+            .collect()
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn finds_payments_by_counterparty_and_amount() {
+        // Arrange
+        let id = get_random_pk();
+        let shop = get_random_pk();
+        let mut account = Account::new(id);
+        account.append(get_transfer(get_random_pk(), 0, id, 100));
+        let mut index = PaymentIndex::of(vec![&account]);
+        for (counter, (to, amount)) in vec![(shop, 5), (get_random_pk(), 5), (shop, 7), (shop, 5)]
+            .into_iter()
+            .enumerate()
+        {
+            let transfer = get_transfer(id, counter as u64, to, amount);
+            index.insert(id, PaymentDirection::Sent, &transfer);
+        }
+        let query = PaymentQuery {
+            amount: Some(Money::from_nano(5)),
+            counterparty: Some(shop),
+            ..Default::default()
+        };
+
+        // Act
+        let found = index.find(&id, &query);
+        let latest = index.find(
+            &id,
+            &PaymentQuery {
+                limit: Some(1),
+                ..query
+            },
+        );
+
+        // Assert
+        assert!(found.len() == 2);
+        assert!(found.iter().all(|p| p.counterparty() == shop));
+        assert!(latest.len() == 1 && latest[0].transfer.id.counter == 3);
+        let received = PaymentQuery {
+            direction: Some(PaymentDirection::Received),
+            ..Default::default()
+        };
+        assert!(index.find(&id, &received).len() == 1);
+    }
+
+    #[test]
+    fn finds_payments_by_tag() {
+        // Arrange
+        let id = get_random_pk();
+        let shop = get_random_pk();
+        let mut index = PaymentIndex::default();
+        let credits: Vec<_> = vec![shop, get_random_pk(), shop]
+            .into_iter()
+            .enumerate()
+            .map(|(counter, from)| get_transfer(from, counter as u64, id, 5))
+            .collect();
+        for credit in &credits {
+            index.insert(id, PaymentDirection::Received, credit);
+        }
+        let tagged = |tag: &str, counterparty| PaymentQuery {
+            tag: Some(tag.to_string()),
+            counterparty,
+            ..Default::default()
+        };
+
+        // Act
+        index.tag(id, &credits[2].id, &["rent"]);
+        index.tag(id, &credits[0].id, &["rent", "2020"]);
+        index.tag(id, &credits[1].id, &["rent"]);
+        index.tag(id, &get_transfer(shop, 9, id, 5).id, &["rent"]);
+
+        // Assert
+        let rent = index.find(&id, &tagged("rent", None));
+        assert!(rent.iter().map(|p| &p.transfer).eq(credits.iter().rev()));
+        assert!(index.find(&id, &tagged("rent", Some(shop))).len() == 2);
+        assert!(index.find(&id, &tagged("2020", Some(shop))).len() == 1);
+        assert!(index.find(&id, &tagged("2021", None)).is_empty());
+    }
+
+    #[test]
+    fn drops_payments_no_longer_held() {
+        // Arrange
+        let id = get_random_pk();
+        let shop = get_random_pk();
+        let mut index = PaymentIndex::default();
+        let credits: Vec<_> = vec![shop, get_random_pk(), shop]
+            .into_iter()
+            .enumerate()
+            .map(|(counter, from)| get_transfer(from, counter as u64, id, 5))
+            .collect();
+        for credit in &credits {
+            index.insert(id, PaymentDirection::Received, credit);
+        }
+        index.tag(id, &credits[0].id, &["rent"]);
+        index.tag(id, &credits[2].id, &["rent"]);
+        let other = get_random_pk();
+        index.insert(
+            other,
+            PaymentDirection::Sent,
+            &get_transfer(other, 0, shop, 5),
+        );
+
+        // Act
+        index.retain(&id, |transfer_id| *transfer_id != credits[0].id);
+        let compacted = index.clone();
+        index.retain(&other, |_| false);
+
+        // Assert
+        let rent = PaymentQuery {
+            tag: Some("rent".to_string()),
+            ..Default::default()
+        };
+        let from_shop = PaymentQuery {
+            counterparty: Some(shop),
+            ..Default::default()
+        };
+        assert!(compacted.payments(&id).len() == 2);
+        assert!(compacted.credited_to(&credits[0].id).is_none());
+        assert!(compacted.credited_to(&credits[2].id) == Some(id));
+        assert!(compacted
+            .find(&id, &rent)
+            .iter()
+            .map(|p| &p.transfer)
+            .eq(vec![&credits[2]]));
+        assert!(compacted.find(&id, &from_shop).len() == 1);
+        assert!(compacted.payments(&other).len() == 1);
+        assert!(index.payments(&other).is_empty());
+        assert!(index.find(&other, &Default::default()).is_empty());
+    }
+
+    fn get_transfer(from: PublicKey, counter: u64, to: PublicKey, amount: u64) -> Transfer {
+        Transfer {
+            id: Dot::new(from, counter),
+            to,
+            amount: Money::from_nano(amount),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    actor::Actor, error::TransferResult, money::covers, ReplicaValidator, TransferInitiated,
+};
+use safe_nd::{AccountId, Error, Money, Result, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A step of a settlement, from the point of view of the settling Actor.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum SettlementStep {
+    /// Completes when a credit of at least the amount has been received from the account.
+    Receive {
+        /// The paying account.
+        from: AccountId,
+        /// The least amount.
+        amount: Money,
+    },
+    /// Completes when a transfer of the amount to the account has been registered.
+    Send {
+        /// The recipient.
+        to: AccountId,
+        /// The amount.
+        amount: Money,
+    },
+}
+
+/// A small DAG of transfers, where steps only start once the steps they depend
+/// on have completed, such as receiving from one account, then forwarding to another.
+/// Steps can only depend on steps added before them, so there are no cycles.
+/// The settlement is to be persisted along with the Actor, and resumes after a
+/// restart from what the history of the Actor shows to have completed.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug, Default)]
+pub struct Settlement {
+    steps: Vec<(SettlementStep, Vec<usize>)>,
+    /// The completed steps, with the transfer that completed each.
+    completed: BTreeMap<usize, TransferId>,
+    /// The send step that has been initiated, and not yet registered.
+    in_flight: Option<(usize, TransferInitiated)>,
+}
+
+impl Settlement {
+    /// A settlement without steps.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a step, depending on the completion of the given earlier steps.
+    /// Returns the index of the step.
+    pub fn add(&mut self, step: SettlementStep, depends_on: &[usize]) -> Result<usize> {
+        let index = self.steps.len();
+        if depends_on.iter().any(|dependency| *dependency >= index) {
+            return Err(Error::from("Steps can only depend on earlier steps"));
+        }
+        self.steps.push((step, depends_on.to_vec()));
+        Ok(index)
+    }
+
+    /// The transfer that completed the step, if it has completed.
+    pub fn completed_by(&self, index: usize) -> Option<TransferId> {
+        self.completed.get(&index).cloned()
+    }
+
+    /// Whether all steps have completed.
+    pub fn is_complete(&self) -> bool {
+        self.completed.len() == self.steps.len()
+    }
+
+    /// Records the steps that the history of the Actor shows to have completed,
+    /// and initiates the first send step that is ready, if no other is in flight.
+    /// The initiated transfer is to be applied to the Actor and driven through
+    /// validation and registration as any other, before advancing again.
+    pub fn advance<V: ReplicaValidator>(
+        &mut self,
+        actor: &Actor<V>,
+    ) -> TransferResult<Option<TransferInitiated>> {
+        self.record_completed(actor);
+        if self.in_flight.is_some() {
+            return Ok(None);
+        }
+        let ready = self.steps.iter().enumerate().find(|(index, (step, deps))| {
+            let is_send = match step {
+                SettlementStep::Send { .. } => true,
+                SettlementStep::Receive { .. } => false,
+            };
+            is_send
+                && !self.completed.contains_key(index)
+                && deps.iter().all(|dep| self.completed.contains_key(dep))
+        });
+        let (index, to, amount) = match ready {
+            Some((index, (SettlementStep::Send { to, amount }, _))) => (index, *to, *amount),
+            _ => return Ok(None),
+        };
+        let initiated = actor.transfer(amount, to)?;
+        self.in_flight = Some((index, initiated.clone()));
+        Ok(Some(initiated))
+    }
+
+    /// Forgets the send step in flight, for it to be initiated again on
+    /// the next advance, such as when the Actor lost it in a restart.
+    pub fn retry_in_flight(&mut self) {
+        self.in_flight = None;
+    }
+
+    fn record_completed<V: ReplicaValidator>(&mut self, actor: &Actor<V>) {
+        if let Some((index, initiated)) = &self.in_flight {
+            let registered = actor
+                .debit_entries()
+                .iter()
+                .any(|entry| entry.transfer() == &initiated.signed_transfer.transfer);
+            if registered {
+                let _ = self.completed.insert(*index, initiated.id());
+                self.in_flight = None;
+            }
+        }
+        for index in 0..self.steps.len() {
+            let (step, deps) = &self.steps[index];
+            if self.completed.contains_key(&index)
+                || !deps.iter().all(|dep| self.completed.contains_key(dep))
+            {
+                continue;
+            }
+            let (from, amount) = match step {
+                SettlementStep::Receive { from, amount } => (*from, *amount),
+                SettlementStep::Send { .. } => continue,
+            };
+            // each credit only completes one step
+            let credit = actor
+                .credit_entries()
+                .iter()
+                .map(|e| e.transfer())
+                .find(|t| {
+                    t.id.actor == from
+                        && covers(t.amount, amount)
+                        && !self.completed.values().any(|id| *id == t.id)
+                });
+            if let Some(credit) = credit {
+                let _ = self.completed.insert(index, credit.id);
+            }
+        }
+    }
+}
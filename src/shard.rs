@@ -0,0 +1,199 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use futures::lock::{Mutex, MutexGuard};
+use safe_nd::AccountId;
+use std::{
+    collections::{
+        hash_map::{DefaultHasher, Entry},
+        HashMap,
+    },
+    hash::{Hash, Hasher},
+    iter::FromIterator,
+    ops::Index,
+};
+
+/// The number of shards that the state of wallets is split in.
+pub const SHARD_COUNT: usize = 64;
+
+/// The shard that the state of the wallet is in. Cmds of wallets in different
+/// shards touch disjoint state, so can be processed in parallel (see ReplicaHandle).
+pub(crate) fn shard_of(wallet: &AccountId) -> usize {
+    let mut hasher = DefaultHasher::new();
+    wallet.hash(&mut hasher);
+    hasher.finish() as usize % SHARD_COUNT
+}
+
+/// State of each wallet, split in a fixed vector of shards by the hash of the wallet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Shards<V> {
+    shards: Vec<HashMap<AccountId, V>>,
+}
+
+impl<V> Shards<V> {
+    pub(crate) fn len(&self) -> usize {
+        self.shards.iter().map(HashMap::len).sum()
+    }
+
+    pub(crate) fn contains_key(&self, wallet: &AccountId) -> bool {
+        self.shards[shard_of(wallet)].contains_key(wallet)
+    }
+
+    pub(crate) fn get(&self, wallet: &AccountId) -> Option<&V> {
+        self.shards[shard_of(wallet)].get(wallet)
+    }
+
+    pub(crate) fn get_mut(&mut self, wallet: &AccountId) -> Option<&mut V> {
+        self.shards[shard_of(wallet)].get_mut(wallet)
+    }
+
+    pub(crate) fn entry(&mut self, wallet: AccountId) -> Entry<'_, AccountId, V> {
+        self.shards[shard_of(&wallet)].entry(wallet)
+    }
+
+    pub(crate) fn insert(&mut self, wallet: AccountId, value: V) -> Option<V> {
+        self.shards[shard_of(&wallet)].insert(wallet, value)
+    }
+
+    pub(crate) fn remove(&mut self, wallet: &AccountId) -> Option<V> {
+        self.shards[shard_of(wallet)].remove(wallet)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&AccountId, &V)> {
+        self.shards.iter().flat_map(HashMap::iter)
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &AccountId> {
+        self.shards.iter().flat_map(HashMap::keys)
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.shards.iter_mut().flat_map(HashMap::values_mut)
+    }
+}
+
+impl<V> Default for Shards<V> {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARD_COUNT).map(|_| HashMap::new()).collect(),
+        }
+    }
+}
+
+impl<V> FromIterator<(AccountId, V)> for Shards<V> {
+    fn from_iter<I: IntoIterator<Item = (AccountId, V)>>(iter: I) -> Self {
+        let mut shards = Self::default();
+        for (wallet, value) in iter {
+            let _ = shards.insert(wallet, value);
+        }
+        shards
+    }
+}
+
+impl<V> Index<&AccountId> for Shards<V> {
+    type Output = V;
+
+    fn index(&self, wallet: &AccountId) -> &V {
+        &self.shards[shard_of(wallet)][wallet]
+    }
+}
+
+/// A lock of its own for each shard, held by a cmd of a wallet from the cmd to the
+/// application of its event, so that cmds of wallets in the same shard are serialised,
+/// while those of wallets in other shards are not held up by it. The locks are async,
+/// so that a cmd waiting for its shard yields to the executor.
+#[derive(Debug)]
+pub(crate) struct ShardLocks {
+    locks: Vec<Mutex<()>>,
+}
+
+impl ShardLocks {
+    /// Locks the shard of the wallet, until the guard is dropped.
+    pub(crate) async fn lock(&self, wallet: &AccountId) -> MutexGuard<'_, ()> {
+        self.locks[shard_of(wallet)].lock().await
+    }
+
+    /// Locks every shard, in order, until the guards are dropped, for cmds that apply
+    /// to any number of wallets. Cmds of a wallet hold a single lock, so can not deadlock.
+    pub(crate) async fn lock_all(&self) -> Vec<MutexGuard<'_, ()>> {
+        let mut guards = Vec::with_capacity(self.locks.len());
+        for lock in &self.locks {
+            guards.push(lock.lock().await);
+        }
+        guards
+    }
+}
+
+impl Default for ShardLocks {
+    fn default() -> Self {
+        Self {
+            locks: (0..SHARD_COUNT).map(|_| Mutex::new(())).collect(),
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn keeps_wallets_in_their_shard() {
+        // Arrange
+        let wallets: Vec<_> = (0..100).map(|_| get_random_pk()).collect();
+
+        // Act
+        let shards: Shards<usize> = wallets.iter().cloned().zip(0..).collect();
+
+        // Assert
+        assert!(shards.len() == wallets.len());
+        for (index, wallet) in wallets.iter().enumerate() {
+            assert!(shards[wallet] == index);
+            assert!(shards.shards[shard_of(wallet)].contains_key(wallet));
+        }
+    }
+
+    #[test]
+    fn locks_each_shard_on_its_own() {
+        // Arrange
+        let locks = ShardLocks::default();
+        let wallet = get_random_pk();
+        let other = loop {
+            let other = get_random_pk();
+            if shard_of(&other) != shard_of(&wallet) {
+                break other;
+            }
+        };
+
+        // Act
+        let _held = futures::executor::block_on(locks.lock(&wallet));
+
+        // Assert
+        assert!(locks.locks[shard_of(&wallet)].try_lock().is_none());
+        assert!(locks.locks[shard_of(&other)].try_lock().is_some());
+    }
+
+    #[test]
+    fn locks_every_shard_at_once() {
+        // Arrange
+        let locks = ShardLocks::default();
+
+        // Act
+        let held = futures::executor::block_on(locks.lock_all());
+
+        // Assert
+        assert!(held.len() == SHARD_COUNT);
+        assert!(locks.locks.iter().all(|lock| lock.try_lock().is_none()));
+        drop(held);
+        assert!(locks.locks.iter().all(|lock| lock.try_lock().is_some()));
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[cfg(any(feature = "test-utils", feature = "bench-utils"))]
+use safe_nd::{AccountId, Money, SafeKey};
+use safe_nd::{DebitAgreementProof, Error, PublicKey, Result, Signature, SignedTransfer, Transfer};
+
+/// The version of the canonical encodings.
+pub const SIGNABLE_VERSION: u8 = 1;
+
+const TRANSFER_TAG: &[u8] = b"safe-transfers/transfer";
+const PROOF_TAG: &[u8] = b"safe-transfers/proof";
+const CREDIT_TAG: &[u8] = b"safe-transfers/credit";
+
+/// Something signed over its canonical encoding. The encoding starts with a tag
+/// naming what is signed, and the version of the encoding, followed by the fields
+/// in a fixed order: integers as little endian u64, keys and signatures as a byte
+/// of their kind and their raw bytes. Unlike the bincode serialisation signed over
+/// before, it does not change with the serialisation library, or the layout of the
+/// structs. Signatures over that legacy serialisation are only accepted during a
+/// move between the codecs, by the height (see CodecMigration and verified_in).
+pub trait Signable {
+    /// The canonical encoding, which is what is signed.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Whether the check passes for the canonical encoding.
+    fn verified_by<F: FnMut(&[u8]) -> bool>(&self, mut check: F) -> bool
+    where
+        Self: Sized,
+    {
+        check(&self.to_bytes())
+    }
+
+    /// Verifies the signature of the key over the canonical encoding.
+    fn verify(&self, public_key: &PublicKey, signature: &Signature) -> Result<()>
+    where
+        Self: Sized,
+    {
+        if self.verified_by(|data| public_key.verify(signature, data).is_ok()) {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+}
+
+/// A transfer, as signed by its sender.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignableTransfer<'a>(&'a Transfer);
+
+impl<'a> SignableTransfer<'a> {
+    /// The transfer to sign.
+    pub fn new(transfer: &'a Transfer) -> Self {
+        Self(transfer)
+    }
+}
+
+impl<'a> Signable for SignableTransfer<'a> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(TRANSFER_TAG);
+        put_transfer(&mut bytes, self.0);
+        bytes
+    }
+}
+
+/// The transfer of the amount from the key, at the counter, signed by the key,
+/// for the generators and fixtures.
+#[cfg(any(feature = "test-utils", feature = "bench-utils"))]
+pub(crate) fn sign_transfer(
+    key: &SafeKey,
+    counter: u64,
+    to: AccountId,
+    amount: Money,
+) -> SignedTransfer {
+    let transfer = Transfer {
+        id: crdts::Dot::new(key.public_key(), counter),
+        to,
+        amount,
+    };
+    let actor_signature = key.sign(&SignableTransfer::new(&transfer).to_bytes());
+    SignedTransfer {
+        transfer,
+        actor_signature,
+    }
+}
+
+/// A transfer signed by its sender, as signed by the Replicas of the sender,
+/// whose agreement makes it a DebitAgreementProof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignableProof<'a>(&'a SignedTransfer);
+
+impl<'a> SignableProof<'a> {
+    /// The signed transfer to agree on.
+    pub fn new(signed_transfer: &'a SignedTransfer) -> Self {
+        Self(signed_transfer)
+    }
+}
+
+impl<'a> Signable for SignableProof<'a> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(PROOF_TAG);
+        put_signed_transfer(&mut bytes, self.0);
+        bytes
+    }
+}
+
+/// A DebitAgreementProof, as signed by the Replicas of the recipient when crediting it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignableCredit<'a>(&'a DebitAgreementProof);
+
+impl<'a> SignableCredit<'a> {
+    /// The proof to sign.
+    pub fn new(debit_proof: &'a DebitAgreementProof) -> Self {
+        Self(debit_proof)
+    }
+}
+
+impl<'a> Signable for SignableCredit<'a> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(CREDIT_TAG);
+        put_signed_transfer(&mut bytes, &self.0.signed_transfer);
+        put_signature(&mut bytes, &self.0.debiting_replicas_sig);
+        bytes
+    }
+}
+
+fn header(tag: &[u8]) -> Vec<u8> {
+    let mut bytes = tag.to_vec();
+    bytes.push(SIGNABLE_VERSION);
+    bytes
+}
+
+fn put_transfer(bytes: &mut Vec<u8>, transfer: &Transfer) {
+    put_key(bytes, &transfer.id.actor);
+    bytes.extend(&transfer.id.counter.to_le_bytes());
+    put_key(bytes, &transfer.to);
+    bytes.extend(&transfer.amount.as_nano().to_le_bytes());
+}
+
+fn put_signed_transfer(bytes: &mut Vec<u8>, signed_transfer: &SignedTransfer) {
+    put_transfer(bytes, &signed_transfer.transfer);
+    put_signature(bytes, &signed_transfer.actor_signature);
+}
+
+pub(crate) fn put_key(bytes: &mut Vec<u8>, key: &PublicKey) {
+    match key {
+        PublicKey::Ed25519(key) => {
+            bytes.push(0);
+            bytes.extend(&key.to_bytes()[..]);
+        }
+        PublicKey::Bls(key) => {
+            bytes.push(1);
+            bytes.extend(&key.to_bytes()[..]);
+        }
+        PublicKey::BlsShare(key) => {
+            bytes.push(2);
+            bytes.extend(&key.to_bytes()[..]);
+        }
+    }
+}
+
+fn put_signature(bytes: &mut Vec<u8>, signature: &Signature) {
+    match signature {
+        Signature::Ed25519(signature) => {
+            bytes.push(0);
+            bytes.extend(&signature.to_bytes()[..]);
+        }
+        Signature::Bls(signature) => {
+            bytes.push(1);
+            bytes.extend(&signature.to_bytes()[..]);
+        }
+        Signature::BlsShare(share) => {
+            bytes.push(2);
+            bytes.extend(&(share.index as u64).to_le_bytes());
+            bytes.extend(&share.share.to_bytes()[..]);
+        }
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::Money;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn accepts_only_canonical_signatures() {
+        // Arrange
+        let secret_key = SecretKey::random();
+        let public_key = PublicKey::Bls(secret_key.public_key());
+        let transfer = get_transfer(public_key);
+        let mut later = transfer.clone();
+        later.id.counter += 1;
+        let signable = SignableTransfer::new(&transfer);
+        let canonical = Signature::Bls(secret_key.sign(signable.to_bytes()));
+        let legacy = Signature::Bls(secret_key.sign(bincode::serialize(&transfer).unwrap()));
+
+        // Act
+        let canonical_result = signable.verify(&public_key, &canonical);
+        let legacy_result = signable.verify(&public_key, &legacy);
+
+        // Assert
+        assert!(canonical_result.is_ok());
+        assert!(legacy_result.is_err());
+        assert!(signable.to_bytes().starts_with(TRANSFER_TAG));
+        assert!(signable.to_bytes() != SignableTransfer::new(&later).to_bytes());
+    }
+
+    fn get_transfer(from: PublicKey) -> Transfer {
+        Transfer {
+            id: Dot::new(from, 0),
+            to: PublicKey::from(SecretKey::random().public_key()),
+            amount: Money::from_nano(10),
+        }
+    }
+}
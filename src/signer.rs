@@ -0,0 +1,117 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::signable::{Signable, SignableTransfer};
+use safe_nd::{ClientFullId, PublicKey, Result, SafeKey, Signature, Transfer};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+/// Signs on behalf of an Actor, so that the Actor does not need to hold
+/// the secret key, which can then be kept in a hardware wallet, an HSM or
+/// a remote signing service. Signatures returned are verified by the Actor.
+/// Signers that can only sign asynchronously are instead handed the payload
+/// of a SigningRequest, and their signature is passed back to the Actor
+/// (see Actor::signing_request and Actor::complete_transfer).
+pub trait TransferSigner: Send + Sync {
+    /// The key signed with, which is the id of the Actor.
+    fn public_key(&self) -> PublicKey;
+    /// Signs the payload.
+    fn sign(&self, payload: &[u8]) -> Result<Signature>;
+}
+
+impl TransferSigner for SafeKey {
+    fn public_key(&self) -> PublicKey {
+        SafeKey::public_key(self)
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<Signature> {
+        Ok(SafeKey::sign(self, payload))
+    }
+}
+
+/// The identity of a client, which signs with its own key of either scheme,
+/// so that a client owning an Ed25519 wallet needs no BLS key material.
+impl TransferSigner for ClientFullId {
+    fn public_key(&self) -> PublicKey {
+        *self.public_id().public_key()
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<Signature> {
+        Ok(ClientFullId::sign(self, payload))
+    }
+}
+
+impl<T: TransferSigner + ?Sized> TransferSigner for Arc<T> {
+    fn public_key(&self) -> PublicKey {
+        (**self).public_key()
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<Signature> {
+        (**self).sign(payload)
+    }
+}
+
+/// A transfer to be signed, with the canonical payload to sign.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SigningRequest {
+    /// The transfer to be signed.
+    pub transfer: Transfer,
+    /// The canonical payload of the transfer, which the signer signs.
+    pub payload: Vec<u8>,
+}
+
+impl SigningRequest {
+    /// The request to sign the transfer.
+    pub(crate) fn new(transfer: Transfer) -> Result<Self> {
+        let payload = SignableTransfer::new(&transfer).to_bytes();
+        Ok(Self { transfer, payload })
+    }
+}
+
+/// A cheaply cloneable handle to the TransferSigner used by an Actor.
+/// Two signers are equal if they are the same signer.
+#[derive(Clone)]
+pub(crate) struct Signer(Arc<dyn TransferSigner>);
+
+impl Signer {
+    /// Wraps the given signer.
+    pub(crate) fn new<S: TransferSigner + 'static>(signer: S) -> Self {
+        Self(Arc::new(signer))
+    }
+
+    /// The key signed with.
+    pub(crate) fn public_key(&self) -> PublicKey {
+        self.0.public_key()
+    }
+
+    /// Signs the payload, verifying the returned signature.
+    pub(crate) fn sign(&self, payload: &[u8]) -> Result<Signature> {
+        let signature = self.0.sign(payload)?;
+        self.public_key().verify(&signature, payload)?;
+        Ok(signature)
+    }
+}
+
+impl TransferSigner for Signer {
+    fn public_key(&self) -> PublicKey {
+        Signer::public_key(self)
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<Signature> {
+        Signer::sign(self, payload)
+    }
+}
+
+impl Debug for Signer {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Signer({:?})", self.public_key())
+    }
+}
@@ -0,0 +1,679 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[cfg(not(feature = "system-clock"))]
+use super::clock::Clock;
+use super::{
+    account::{Account, HistoryEntry},
+    actor::Actor,
+    clock::ManualTimeSource,
+    messages::TransferCmd,
+    quorum::{shares_needed, QuorumRule},
+    replica::Replica,
+    ActorEvent, ReplicaEvent, ReplicaValidator,
+};
+use crdts::Dot;
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use safe_nd::{
+    AccountId, ClientFullId, DebitAgreementProof, Error, Money, PublicKey, Result, SafeKey,
+    Transfer, TransferId, TransferRegistered,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
+use threshold_crypto::{PublicKeySet, SecretKeySet};
+
+/// How a simulated network is set up, and how unreliable it is.
+/// The same config always runs the same way, whatever the platform.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SimulationConfig {
+    /// The seed of all randomness of the run, keys included.
+    pub seed: u64,
+    /// The number of groups of Replicas.
+    pub groups: usize,
+    /// The number of Replicas in each group.
+    pub replicas: usize,
+    /// The number of Actors, spread over the groups.
+    pub actors: usize,
+    /// The balance of each Actor at genesis, in nanos.
+    pub initial_balance: u64,
+    /// The number of ticks to run for.
+    pub ticks: u64,
+    /// The chance of an idle Actor initiating a transfer on a tick.
+    pub transfer_rate: f64,
+    /// The chance of an Actor also signing a conflicting debit at the same counter,
+    /// sent to some of its Replicas only.
+    pub double_spend_rate: f64,
+    /// The chance of a message being dropped.
+    pub drop_rate: f64,
+    /// The most ticks a message is delayed by. Messages due on the same tick
+    /// are delivered in random order.
+    pub max_delay: u64,
+    /// The ticks an Actor waits for its Replicas before sending again.
+    pub resend_after: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            groups: 2,
+            replicas: 4,
+            actors: 4,
+            initial_balance: 1_000,
+            ticks: 200,
+            transfer_rate: 0.2,
+            double_spend_rate: 0.05,
+            drop_rate: 0.05,
+            max_delay: 5,
+            resend_after: 20,
+        }
+    }
+}
+
+/// What happened in a run, once its safety invariants were checked.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
+pub struct SimulationReport {
+    /// The seed of the run.
+    pub seed: u64,
+    /// The transfers initiated by the Actors.
+    pub initiated: usize,
+    /// The debits signed at the counter of another debit, to double spend.
+    pub conflicting: usize,
+    /// The distinct debits registered by any Replica.
+    pub registered: usize,
+    /// The distinct debits credited by any Replica.
+    pub credited: usize,
+    /// The messages delivered.
+    pub delivered: usize,
+    /// The messages dropped.
+    pub dropped: usize,
+    /// The sum of the balances of all wallets.
+    pub supply: Money,
+    /// The sum of the debits registered and not yet credited.
+    pub in_flight: Money,
+}
+
+/// An in-memory network of groups of Replicas and of Actors, with a seeded scheduler
+/// that drops, delays and reorders the messages between them. Each run ends by
+/// checking the safety invariants of AT2: no debit is spent twice, no credit is
+/// made out of nothing, no wallet is overdrawn, and the supply is conserved.
+/// Liveness is not checked, as dropped messages may stall transfers for good.
+pub struct Simulation {
+    config: SimulationConfig,
+    rng: StdRng,
+    clock: Arc<ManualTimeSource>,
+    now: u64,
+    genesis_total: u64,
+    groups: Vec<SimGroup>,
+    actors: Vec<SimActor>,
+    wallets: HashMap<AccountId, usize>,
+    queue: BTreeMap<(u64, u64, u64), Message>,
+    sent: u64,
+    initiated: usize,
+    conflicting: usize,
+    delivered: usize,
+    dropped: usize,
+}
+
+struct SimGroup {
+    keys: PublicKeySet,
+    replicas: Vec<Replica>,
+}
+
+struct SimActor {
+    actor: Actor<SimValidator>,
+    group: usize,
+    in_flight: Option<InFlight>,
+}
+
+/// The debit of an Actor not yet registered by a quorum of its Replicas.
+struct InFlight {
+    transfer_id: TransferId,
+    cmd: TransferCmd,
+    acks: BTreeSet<usize>,
+    sent_at: u64,
+}
+
+enum Message {
+    Cmd {
+        group: usize,
+        replica: usize,
+        cmd: TransferCmd,
+    },
+    Event {
+        actor: usize,
+        replica: usize,
+        event: ReplicaEvent,
+    },
+}
+
+/// Trusts the groups of the simulation, and only those.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct SimValidator {
+    groups: Vec<PublicKey>,
+}
+
+impl ReplicaValidator for SimValidator {
+    fn is_valid(&self, replica_group: PublicKey) -> bool {
+        self.groups.contains(&replica_group)
+    }
+}
+
+impl Simulation {
+    /// Sets up the network of the config, each Actor holding its initial balance.
+    pub fn new(config: SimulationConfig) -> Result<Self> {
+        check_config(&config)?;
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let clock = Arc::new(ManualTimeSource::new(0, 0));
+        let threshold = QuorumRule::Supermajority.threshold(config.replicas);
+        let key_sets: Vec<_> = (0..config.groups)
+            .map(|_| SecretKeySet::random(threshold, &mut rng))
+            .collect();
+        let group_keys: HashSet<_> = key_sets.iter().map(|keys| keys.public_keys()).collect();
+        let validator = SimValidator {
+            groups: key_sets
+                .iter()
+                .map(|keys| PublicKey::Bls(keys.public_keys().public_key()))
+                .collect(),
+        };
+        let genesis = PublicKey::Bls(SecretKeySet::random(0, &mut rng).public_keys().public_key());
+
+        let mut accounts = vec![HashMap::new(); config.groups];
+        let mut actors = vec![];
+        let mut wallets = HashMap::new();
+        for index in 0..config.actors {
+            let safe_key = SafeKey::client(ClientFullId::new_ed25519(&mut rng));
+            let id = safe_key.public_key();
+            let group = index % config.groups;
+            let mut account = Account::new(id);
+            account.append(Transfer {
+                id: Dot::new(genesis, index as u64),
+                to: id,
+                amount: Money::from_nano(config.initial_balance),
+            });
+            let _ = accounts[group].insert(id, account.clone());
+            let _ = wallets.insert(id, index);
+            let actor = Actor::from_snapshot(
+                account,
+                safe_key,
+                key_sets[group].public_keys(),
+                validator.clone(),
+                #[cfg(not(feature = "system-clock"))]
+                Clock::new(clock.clone()),
+            )
+            .with_time_source(clock.clone());
+            actors.push(SimActor {
+                actor,
+                group,
+                in_flight: None,
+            });
+        }
+
+        let groups = key_sets
+            .iter()
+            .zip(accounts)
+            .map(|(keys, accounts)| SimGroup {
+                keys: keys.public_keys(),
+                replicas: (0..config.replicas)
+                    .map(|index| {
+                        Replica::from_snapshot(
+                            keys.secret_key_share(index),
+                            index,
+                            keys.public_keys(),
+                            group_keys.clone(),
+                            accounts.clone(),
+                            Default::default(),
+                            #[cfg(not(feature = "system-clock"))]
+                            Clock::new(clock.clone()),
+                        )
+                        .with_time_source(clock.clone())
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Self {
+            config,
+            rng,
+            clock,
+            now: 0,
+            genesis_total: config.initial_balance * config.actors as u64,
+            groups,
+            actors,
+            wallets,
+            queue: Default::default(),
+            sent: 0,
+            initiated: 0,
+            conflicting: 0,
+            delivered: 0,
+            dropped: 0,
+        })
+    }
+
+    /// Runs all ticks of the config, and checks the safety invariants.
+    pub fn run(mut self) -> Result<SimulationReport> {
+        while self.now < self.config.ticks {
+            self.tick();
+        }
+        self.check_invariants()
+    }
+
+    /// Moves the network one tick forward: idle Actors may initiate transfers,
+    /// Actors waiting on their Replicas send again, and the messages due are delivered.
+    pub fn tick(&mut self) {
+        self.now += 1;
+        self.clock.set_now(self.now);
+        for index in 0..self.actors.len() {
+            if self.actors[index].in_flight.is_some() {
+                self.resend(index);
+            } else if self.rng.gen_bool(self.config.transfer_rate) {
+                self.initiate(index);
+            }
+        }
+        while let Some(key) = self.queue.keys().next().cloned() {
+            if key.0 > self.now {
+                break;
+            }
+            if let Some(message) = self.queue.remove(&key) {
+                self.delivered += 1;
+                self.deliver(message);
+            }
+        }
+    }
+
+    /// Checks the safety invariants against the state of all Replicas.
+    pub fn check_invariants(&self) -> Result<SimulationReport> {
+        let mut debits: HashMap<TransferId, Transfer> = HashMap::new();
+        let mut credits: HashMap<TransferId, Transfer> = HashMap::new();
+        for group in &self.groups {
+            for replica in &group.replicas {
+                let audit = replica.audit_supply(Money::zero())?;
+                if !audit.anomalies.is_empty() {
+                    return Err(Error::from(format!(
+                        "Replica holds anomalous wallets: {:?}",
+                        audit.anomalies
+                    )));
+                }
+                for wallet in self.wallets.keys() {
+                    for entry in replica.debit_entries(wallet).unwrap_or_default() {
+                        record(&mut debits, entry.transfer(), "Double spend")?;
+                    }
+                    for entry in replica.credit_entries(wallet).unwrap_or_default() {
+                        if let HistoryEntry::PropagatedCredit(_) = entry {
+                            record(&mut credits, entry.transfer(), "Conflicting credits")?;
+                        }
+                    }
+                }
+            }
+        }
+        for (id, credit) in &credits {
+            if debits.get(id) != Some(credit) {
+                return Err(Error::from(format!("Credit {:?} was never debited", id)));
+            }
+        }
+
+        let mut supply = 0;
+        for wallet in self.wallets.keys() {
+            let received: u64 = credits
+                .values()
+                .filter(|transfer| transfer.to == *wallet)
+                .map(|transfer| transfer.amount.as_nano())
+                .sum();
+            let sent: u64 = debits
+                .values()
+                .filter(|transfer| transfer.id.actor == *wallet)
+                .map(|transfer| transfer.amount.as_nano())
+                .sum();
+            let held = self.config.initial_balance + received;
+            if sent > held {
+                return Err(Error::from(format!(
+                    "Wallet {:?} is overdrawn by {} nanos",
+                    wallet,
+                    sent - held
+                )));
+            }
+            supply += held - sent;
+        }
+        let in_flight: u64 = debits
+            .iter()
+            .filter(|(id, _)| !credits.contains_key(id))
+            .map(|(_, transfer)| transfer.amount.as_nano())
+            .sum();
+        if supply + in_flight != self.genesis_total {
+            return Err(Error::from(format!(
+                "Supply is not conserved: {} held and {} in flight, of {}",
+                supply, in_flight, self.genesis_total
+            )));
+        }
+
+        Ok(SimulationReport {
+            seed: self.config.seed,
+            initiated: self.initiated,
+            conflicting: self.conflicting,
+            registered: debits.len(),
+            credited: credits.len(),
+            delivered: self.delivered,
+            dropped: self.dropped,
+            supply: Money::from_nano(supply),
+            in_flight: Money::from_nano(in_flight),
+        })
+    }
+
+    /// Initiates a transfer of a random amount to a random Actor, possibly
+    /// along with a conflicting one, sent to some of the Replicas only.
+    fn initiate(&mut self, index: usize) {
+        let balance = self.actors[index].actor.balance().as_nano();
+        if balance == 0 || self.actors.len() < 2 {
+            return;
+        }
+        let amount = Money::from_nano(self.rng.gen_range(1, balance + 1));
+        let to = self.recipient(index, None);
+        let initiated = match self.actors[index].actor.transfer(amount, to) {
+            Ok(initiated) => initiated,
+            Err(_) => return,
+        };
+        let conflicting =
+            if self.actors.len() > 2 && self.rng.gen_bool(self.config.double_spend_rate) {
+                let other = self.recipient(index, Some(to));
+                self.actors[index].actor.transfer(amount, other).ok()
+            } else {
+                None
+            };
+
+        let cmd = TransferCmd::ValidateTransfer(initiated.signed_transfer.clone());
+        let sim = &mut self.actors[index];
+        sim.actor
+            .apply(ActorEvent::TransferInitiated(initiated.clone()));
+        sim.in_flight = Some(InFlight {
+            transfer_id: initiated.id(),
+            cmd: cmd.clone(),
+            acks: Default::default(),
+            sent_at: self.now,
+        });
+        let group = sim.group;
+        self.initiated += 1;
+        self.broadcast(group, &cmd, &BTreeSet::new());
+
+        if let Some(conflicting) = conflicting {
+            self.conflicting += 1;
+            let cmd = TransferCmd::ValidateTransfer(conflicting.signed_transfer);
+            for replica in 0..self.config.replicas {
+                if self.rng.gen_bool(0.5) {
+                    self.send(Message::Cmd {
+                        group,
+                        replica,
+                        cmd: cmd.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Sends the validation or registration of the debit in flight again, to the
+    /// Replicas that have not registered it, if they have not answered for long enough.
+    fn resend(&mut self, index: usize) {
+        let now = self.now;
+        let resend_after = self.config.resend_after;
+        let sim = &mut self.actors[index];
+        let group = sim.group;
+        let (cmd, acks) = match &mut sim.in_flight {
+            Some(in_flight) if now - in_flight.sent_at >= resend_after => {
+                in_flight.sent_at = now;
+                (in_flight.cmd.clone(), in_flight.acks.clone())
+            }
+            _ => return,
+        };
+        self.broadcast(group, &cmd, &acks);
+    }
+
+    fn deliver(&mut self, message: Message) {
+        match message {
+            Message::Cmd {
+                group,
+                replica,
+                cmd,
+            } => self.deliver_cmd(group, replica, cmd),
+            Message::Event {
+                actor,
+                replica,
+                event,
+            } => self.deliver_event(actor, replica, event),
+        }
+    }
+
+    /// Handles the cmd at the Replica, and sends on what follows from its event.
+    fn deliver_cmd(&mut self, group: usize, index: usize, cmd: TransferCmd) {
+        let replica = &mut self.groups[group].replicas[index];
+        let event = match replica.handle_cmd(cmd.clone()) {
+            Ok(Some(event)) => event,
+            Ok(None) => return,
+            Err(_) => match cmd {
+                // a registration sent again is acknowledged again
+                TransferCmd::RegisterTransfer(debit_proof)
+                    if holds_debit(replica, &debit_proof) =>
+                {
+                    self.acknowledge(index, debit_proof);
+                    return;
+                }
+                _ => return,
+            },
+        };
+        replica.apply(event.clone());
+        match event {
+            ReplicaEvent::TransferValidated(ref validated) => {
+                if let Some(actor) = self.wallets.get(&validated.signed_transfer.from()).copied() {
+                    self.send(Message::Event {
+                        actor,
+                        replica: index,
+                        event,
+                    });
+                }
+            }
+            ReplicaEvent::TransferRegistered(registered) => {
+                let debit_proof = registered.debit_proof;
+                let recipient = debit_proof.signed_transfer.transfer.to;
+                if let Some(actor) = self.wallets.get(&recipient).copied() {
+                    let cmd = TransferCmd::PropagateTransfer(debit_proof.clone());
+                    let group = self.actors[actor].group;
+                    self.broadcast(group, &cmd, &BTreeSet::new());
+                }
+                self.acknowledge(index, debit_proof);
+            }
+            ReplicaEvent::TransferPropagated(ref propagated) => {
+                let recipient = propagated.debit_proof.signed_transfer.transfer.to;
+                if let Some(actor) = self.wallets.get(&recipient).copied() {
+                    self.send(Message::Event {
+                        actor,
+                        replica: index,
+                        event,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Handles the event of a Replica at the Actor.
+    fn deliver_event(&mut self, index: usize, replica: usize, event: ReplicaEvent) {
+        let sim = &mut self.actors[index];
+        match event {
+            ReplicaEvent::TransferValidated(validated) => {
+                let received = match sim.actor.receive(validated) {
+                    Ok(received) => received,
+                    Err(_) => return,
+                };
+                let proof = received.proof.clone();
+                sim.actor
+                    .apply(ActorEvent::TransferValidationReceived(received));
+                let sent = match proof.map(|proof| sim.actor.register(proof)) {
+                    Some(Ok(sent)) => sent,
+                    _ => return,
+                };
+                let cmd = TransferCmd::RegisterTransfer(sent.debit_proof.clone());
+                sim.in_flight = Some(InFlight {
+                    transfer_id: sent.debit_proof.id(),
+                    cmd: cmd.clone(),
+                    acks: Default::default(),
+                    sent_at: self.now,
+                });
+                sim.actor.apply(ActorEvent::TransferRegistrationSent(sent));
+                let group = sim.group;
+                self.broadcast(group, &cmd, &BTreeSet::new());
+            }
+            ReplicaEvent::TransferRegistered(registered) => {
+                let needed = shares_needed(&self.groups[sim.group].keys);
+                let registered = match &mut sim.in_flight {
+                    Some(in_flight) if in_flight.transfer_id == registered.debit_proof.id() => {
+                        let _ = in_flight.acks.insert(replica);
+                        in_flight.acks.len() >= needed
+                    }
+                    _ => false,
+                };
+                if registered {
+                    sim.in_flight = None;
+                }
+            }
+            event @ ReplicaEvent::TransferPropagated(_) => {
+                // the same credit from more Replicas has nothing to synch
+                if let Ok(synched) = sim.actor.synch(vec![event]) {
+                    sim.actor.apply(ActorEvent::TransfersSynched(synched));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Tells the sender of the debit that the Replica has registered it.
+    fn acknowledge(&mut self, replica: usize, debit_proof: DebitAgreementProof) {
+        if let Some(actor) = self.wallets.get(&debit_proof.from()).copied() {
+            self.send(Message::Event {
+                actor,
+                replica,
+                event: ReplicaEvent::TransferRegistered(TransferRegistered { debit_proof }),
+            });
+        }
+    }
+
+    /// Sends the cmd to the Replicas of the group, but those skipped.
+    fn broadcast(&mut self, group: usize, cmd: &TransferCmd, skipped: &BTreeSet<usize>) {
+        for replica in 0..self.config.replicas {
+            if !skipped.contains(&replica) {
+                self.send(Message::Cmd {
+                    group,
+                    replica,
+                    cmd: cmd.clone(),
+                });
+            }
+        }
+    }
+
+    /// Schedules the message after a random delay, unless it is dropped.
+    fn send(&mut self, message: Message) {
+        if self.rng.gen_bool(self.config.drop_rate) {
+            self.dropped += 1;
+            return;
+        }
+        let due = self.now + self.rng.gen_range(0, self.config.max_delay + 1);
+        // the random key orders the messages due on the same tick
+        let order = self.rng.next_u64();
+        self.sent += 1;
+        let _ = self.queue.insert((due, order, self.sent), message);
+    }
+
+    /// A random Actor other than the sender, and the one excluded, if any.
+    fn recipient(&mut self, sender: usize, excluded: Option<AccountId>) -> AccountId {
+        let candidates: Vec<_> = self
+            .actors
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != sender)
+            .map(|(_, sim)| sim.actor.id())
+            .filter(|id| Some(*id) != excluded)
+            .collect();
+        candidates[self.rng.gen_range(0, candidates.len())]
+    }
+}
+
+fn check_config(config: &SimulationConfig) -> Result<()> {
+    if config.groups == 0 || config.replicas == 0 {
+        return Err(Error::from("The network needs Replicas"));
+    }
+    let rates = [
+        config.transfer_rate,
+        config.double_spend_rate,
+        config.drop_rate,
+    ];
+    if rates.iter().any(|rate| *rate < 0.0 || *rate > 1.0) {
+        return Err(Error::from("Rates must be between 0 and 1"));
+    }
+    if config
+        .initial_balance
+        .checked_mul(config.actors as u64)
+        .is_none()
+    {
+        return Err(Error::from("The genesis supply overflows"));
+    }
+    Ok(())
+}
+
+/// Whether the Replica has registered the debit.
+fn holds_debit(replica: &Replica, debit_proof: &DebitAgreementProof) -> bool {
+    replica
+        .debit_entries(&debit_proof.from())
+        .unwrap_or_default()
+        .iter()
+        .any(|entry| entry.debit_proof() == Some(debit_proof))
+}
+
+/// Records the transfer by its id, failing if another transfer has the same id.
+fn record(
+    transfers: &mut HashMap<TransferId, Transfer>,
+    transfer: &Transfer,
+    violation: &str,
+) -> Result<()> {
+    match transfers.insert(transfer.id, transfer.clone()) {
+        Some(previous) if previous != *transfer => Err(Error::from(format!(
+            "{}: {:?} and {:?}",
+            violation, previous, transfer
+        ))),
+        _ => Ok(()),
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn keeps_safety_under_unreliable_delivery() -> Result<()> {
+        for seed in 0..3 {
+            // Arrange
+            let config = get_config(seed);
+
+            // Act
+            let report = Simulation::new(config)?.run()?;
+            let rerun = Simulation::new(config)?.run()?;
+
+            // Assert
+            assert!(report == rerun);
+            assert!(report.registered > 0);
+            assert!(report.dropped > 0);
+        }
+        Ok(())
+    }
+
+    fn get_config(seed: u64) -> SimulationConfig {
+        SimulationConfig {
+            seed,
+            double_spend_rate: 0.2,
+            drop_rate: 0.1,
+            ..Default::default()
+        }
+    }
+}
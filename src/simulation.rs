@@ -0,0 +1,171 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[cfg(not(feature = "system-clock"))]
+use super::clock::{Clock, ManualTimeSource};
+use super::{
+    replica::Replica,
+    signable::{Signable, SignableProof, SignableTransfer},
+};
+use crdts::Dot;
+use safe_nd::{
+    AccountId, DebitAgreementProof, Money, PublicKey, Result, SafeKey, Signature, SignedTransfer,
+    Transfer,
+};
+use std::collections::HashSet;
+use threshold_crypto::{PublicKeySet, SecretKey, SecretKeySet};
+
+/// Mints complete agreement proofs for simulated payouts, signed by a
+/// test group of Replicas, so that simulated flows go through the same
+/// validation as any other transfer, instead of bypassing it.
+/// Credits are paid from an account of the mint, and are accepted by
+/// Replicas that know the group of the mint (see Replica::add_known_group).
+/// Debits are of accounts held by Replicas of the mint's group.
+#[derive(Clone, Debug)]
+pub struct PayoutMint {
+    replicas: SecretKeySet,
+    payer: SecretKey,
+    next_counter: u64,
+}
+
+impl PayoutMint {
+    /// A mint signing with the given test group of Replicas.
+    pub fn new(replicas: SecretKeySet) -> Self {
+        Self {
+            replicas,
+            payer: SecretKey::random(),
+            next_counter: 0,
+        }
+    }
+
+    /// The PK set of the group of the mint.
+    pub fn replicas(&self) -> PublicKeySet {
+        self.replicas.public_keys()
+    }
+
+    /// A Replica of the group of the mint, without accounts,
+    /// which accepts the credits of the mint. Without the system clock,
+    /// its time stands still, until set with with_time_source.
+    pub fn replica(&self, index: usize) -> Replica {
+        let mut known_groups = HashSet::new();
+        let _ = known_groups.insert(self.replicas());
+        Replica::from_snapshot(
+            self.replicas.secret_key_share(index),
+            index,
+            self.replicas(),
+            known_groups,
+            Default::default(),
+            Default::default(),
+            #[cfg(not(feature = "system-clock"))]
+            Clock::new(ManualTimeSource::default()),
+        )
+    }
+
+    /// The account that simulated credits are paid from.
+    pub fn payer(&self) -> AccountId {
+        PublicKey::Bls(self.payer.public_key())
+    }
+
+    /// The proof of a payout of the amount to the account,
+    /// to be propagated to the Replicas of the account.
+    pub fn credit(&mut self, to: AccountId, amount: Money) -> Result<DebitAgreementProof> {
+        let transfer = Transfer {
+            id: Dot::new(self.payer(), self.next_counter),
+            to,
+            amount,
+        };
+        let actor_signature =
+            Signature::Bls(self.payer.sign(SignableTransfer::new(&transfer).to_bytes()));
+        let proof = self.agree(SignedTransfer {
+            transfer,
+            actor_signature,
+        })?;
+        self.next_counter += 1;
+        Ok(proof)
+    }
+
+    /// The proof of a debit of the account, signed by its owner,
+    /// to be registered at the Replicas of the account.
+    pub fn debit(
+        &self,
+        from: &SafeKey,
+        counter: u64,
+        to: AccountId,
+        amount: Money,
+    ) -> Result<DebitAgreementProof> {
+        let transfer = Transfer {
+            id: Dot::new(from.public_key(), counter),
+            to,
+            amount,
+        };
+        let actor_signature = from.sign(&SignableTransfer::new(&transfer).to_bytes());
+        self.agree(SignedTransfer {
+            transfer,
+            actor_signature,
+        })
+    }
+
+    fn agree(&self, signed_transfer: SignedTransfer) -> Result<DebitAgreementProof> {
+        let data = SignableProof::new(&signed_transfer).to_bytes();
+        Ok(DebitAgreementProof {
+            signed_transfer,
+            debiting_replicas_sig: Signature::Bls(self.replicas.secret_key().sign(data)),
+        })
+    }
+}
+
+// the tests build instances with the default clock, that of the system
+#[cfg(feature = "system-clock")]
+mod test {
+    use super::*;
+    use crate::{replica::Replica, Account};
+    use safe_nd::ClientFullId;
+    use std::collections::HashMap;
+
+    #[test]
+    fn simulated_payouts_are_verified() -> Result<()> {
+        // Arrange
+        let mut rng = rand::thread_rng();
+        let mut mint = PayoutMint::new(SecretKeySet::random(0, &mut rng));
+        let client_safe_key = get_safe_key();
+        let id = client_safe_key.public_key();
+        let mut known_groups = HashSet::new();
+        let _ = known_groups.insert(mint.replicas());
+        let mut replica = get_replica(&mint, known_groups);
+        let mut stranger = get_replica(&mint, Default::default());
+
+        // Act
+        let credit = mint.credit(id, Money::from_nano(10))?;
+        replica.simulated_credit(&credit)?;
+        let debit = mint.debit(&client_safe_key, 0, mint.payer(), Money::from_nano(4))?;
+        replica.simulated_debit(&debit)?;
+
+        // Assert
+        assert!(replica.balance(&id) == Some(Money::from_nano(6)));
+        assert!(stranger.simulated_credit(&credit).is_err());
+        assert!(replica.simulated_credit(&credit).is_err());
+        let out_of_order = mint.debit(&client_safe_key, 2, mint.payer(), Money::from_nano(1))?;
+        assert!(replica.simulated_debit(&out_of_order).is_err());
+        Ok(())
+    }
+
+    fn get_replica(mint: &PayoutMint, known_groups: HashSet<PublicKeySet>) -> Replica {
+        Replica::from_snapshot(
+            mint.replicas.secret_key_share(0),
+            0,
+            mint.replicas(),
+            known_groups,
+            HashMap::<AccountId, Account>::new(),
+            Default::default(),
+        )
+    }
+
+    fn get_safe_key() -> SafeKey {
+        SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()))
+    }
+}
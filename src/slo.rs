@@ -0,0 +1,189 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    time::Duration,
+};
+
+/// The operations of the Replica API that are tracked.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum ReplicaOperation {
+    /// Validation of a transfer.
+    Validate,
+    /// Registration of an agreed debit.
+    Register,
+    /// Reception of a propagated credit.
+    Propagate,
+    /// Any query.
+    Query,
+}
+
+/// The latency target of an operation, and how sustained
+/// a breach of it must be before it is reported.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SloTarget {
+    /// The latency that calls should not exceed.
+    pub max_latency: Duration,
+    /// Number of the most recent calls considered.
+    pub window: usize,
+    /// Number of calls of the window that may exceed the latency.
+    pub tolerated: usize,
+}
+
+/// Advisory events of a tracker, for peers to learn of a degraded Replica.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum SloEvent {
+    /// More calls than tolerated exceeded the latency target.
+    Breached {
+        /// The operation.
+        operation: ReplicaOperation,
+        /// Number of calls of the window that exceeded the target.
+        slow_calls: usize,
+        /// The target.
+        target: SloTarget,
+    },
+    /// The operation is within its target again.
+    Recovered {
+        /// The operation.
+        operation: ReplicaOperation,
+    },
+}
+
+/// What a tracker has observed of an operation.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SloMetrics {
+    /// Number of calls since the tracker was created.
+    pub total_calls: u64,
+    /// Number of calls in the window.
+    pub window_calls: usize,
+    /// Number of calls of the window that exceeded the target.
+    pub slow_calls: usize,
+    /// The highest latency in the window.
+    pub max_latency: Duration,
+    /// Whether the target is currently breached.
+    pub breached: bool,
+}
+
+/// Tracks the latency of calls to a Replica against SLO targets.
+/// The Replica itself does no IO, so calls are timed by upper layers,
+/// which record them here and relay the events to the peers of the Replica.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SloTracker {
+    targets: BTreeMap<ReplicaOperation, SloTarget>,
+    latencies: BTreeMap<ReplicaOperation, VecDeque<Duration>>,
+    totals: BTreeMap<ReplicaOperation, u64>,
+    breached: BTreeSet<ReplicaOperation>,
+}
+
+impl SloTracker {
+    /// A tracker without targets.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the target of the operation.
+    pub fn with_target(mut self, operation: ReplicaOperation, target: SloTarget) -> Self {
+        let _ = self.targets.insert(operation, target);
+        self
+    }
+
+    /// Records the latency of a call, returning an event when the
+    /// operation starts or stops breaching its target.
+    /// Calls of operations without target are only counted.
+    pub fn record(&mut self, operation: ReplicaOperation, latency: Duration) -> Option<SloEvent> {
+        *self.totals.entry(operation).or_insert(0) += 1;
+        let target = *self.targets.get(&operation)?;
+        let latencies = self.latencies.entry(operation).or_default();
+        latencies.push_back(latency);
+        while latencies.len() > target.window {
+            let _ = latencies.pop_front();
+        }
+        let slow_calls = latencies
+            .iter()
+            .filter(|l| **l > target.max_latency)
+            .count();
+        let was_breached = self.breached.contains(&operation);
+        if slow_calls > target.tolerated && !was_breached {
+            let _ = self.breached.insert(operation);
+            Some(SloEvent::Breached {
+                operation,
+                slow_calls,
+                target,
+            })
+        } else if slow_calls <= target.tolerated && was_breached {
+            let _ = self.breached.remove(&operation);
+            Some(SloEvent::Recovered { operation })
+        } else {
+            None
+        }
+    }
+
+    /// What has been observed of the operation, None if it has no calls.
+    pub fn metrics(&self, operation: ReplicaOperation) -> Option<SloMetrics> {
+        let total_calls = *self.totals.get(&operation)?;
+        let latencies = self.latencies.get(&operation);
+        let max_latency = self.targets.get(&operation).map(|t| t.max_latency);
+        Some(SloMetrics {
+            total_calls,
+            window_calls: latencies.map_or(0, |l| l.len()),
+            slow_calls: latencies
+                .map_or(0, |l| l.iter().filter(|l| Some(**l) > max_latency).count()),
+            max_latency: latencies
+                .and_then(|l| l.iter().max().copied())
+                .unwrap_or_default(),
+            breached: self.breached.contains(&operation),
+        })
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn reports_sustained_breach_once() {
+        // Arrange
+        let target = SloTarget {
+            max_latency: Duration::from_millis(100),
+            window: 4,
+            tolerated: 1,
+        };
+        let mut tracker = SloTracker::new().with_target(ReplicaOperation::Validate, target);
+        let slow = Duration::from_millis(150);
+        let fast = Duration::from_millis(10);
+
+        // Act
+        let events: Vec<_> = vec![slow, fast, slow, slow, fast, fast, fast]
+            .into_iter()
+            .map(|latency| tracker.record(ReplicaOperation::Validate, latency))
+            .collect();
+
+        // Assert
+        assert!(events[0].is_none());
+        assert!(
+            events[2]
+                == Some(SloEvent::Breached {
+                    operation: ReplicaOperation::Validate,
+                    slow_calls: 2,
+                    target,
+                })
+        );
+        assert!(events[3..6].iter().all(|e| e.is_none()));
+        assert!(
+            events[6]
+                == Some(SloEvent::Recovered {
+                    operation: ReplicaOperation::Validate
+                })
+        );
+        let metrics = tracker.metrics(ReplicaOperation::Validate).unwrap();
+        assert!(metrics.total_calls == 7 && metrics.window_calls == 4);
+        assert!(!metrics.breached);
+        assert!(tracker.record(ReplicaOperation::Query, slow).is_none());
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::AccountRecord,
+    archive::{KeySuccession, TrustAnchors},
+    attachment::SignedAttachment,
+    clock::{AppliedAt, Timestamp},
+    derivation::SignedAppWallet,
+    enclave::KeyShareAttestation,
+    hashing::{hash, Digest},
+    policy::Condition,
+    wire::{FieldReader, SizeLimits},
+    DoubleSpendAttempted, EscrowOpened, MultisigPolicySet, ReceivedCredit, SpendingLimitsSet,
+    WalletFeature,
+};
+use safe_nd::{AccountId, Error, Money, PublicKey, Result, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+#[cfg(feature = "zstd")]
+use std::io::Read;
+use threshold_crypto::{PublicKey as GroupKey, PublicKeySet};
+
+/// The version of the snapshot format written by this version of the crate.
+pub const SNAPSHOT_VERSION: u16 = 22;
+
+/// How the state is compressed in a snapshot (see Replica::with_snapshot_compression).
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum SnapshotCompression {
+    /// Not compressed.
+    None,
+    /// Compressed with zstd, at its default level. Compiled in with the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Default for SnapshotCompression {
+    fn default() -> Self {
+        SnapshotCompression::None
+    }
+}
+
+impl SnapshotCompression {
+    /// The tag of the compression in the envelope of a snapshot, which does not
+    /// depend on the features compiled in, so that any build can tell it apart.
+    fn tag(self) -> u8 {
+        match self {
+            SnapshotCompression::None => 0,
+            #[cfg(feature = "zstd")]
+            SnapshotCompression::Zstd => 1,
+        }
+    }
+
+    fn compress(self, body: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            SnapshotCompression::None => Ok(body),
+            #[cfg(feature = "zstd")]
+            SnapshotCompression::Zstd => zstd::encode_all(&body[..], 0)
+                .map_err(|_| Error::NetworkOther("Could not compress snapshot".into())),
+        }
+    }
+}
+
+/// The state of a snapshot, as written since version 18: compressed or not, with the
+/// key of the group it is of, the number of past keys of the group, and the length and
+/// hash of the state uncompressed, so that truncated or corrupted snapshots, and those
+/// of another group, are refused instead of restoring a Replica that rejects every proof.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+struct SnapshotEnvelope {
+    group_key: GroupKey,
+    key_epoch: u64,
+    compression: u8,
+    length: u64,
+    content_hash: Digest,
+    body: Vec<u8>,
+}
+
+impl SnapshotEnvelope {
+    /// The state uncompressed, if of the group, and intact. A state longer than the
+    /// limit is refused before decompressing it, and no more than its declared
+    /// length is decompressed, so that a small snapshot can not expand without bound.
+    fn open(self, group: &PublicKeySet, limit: u64) -> Result<Vec<u8>> {
+        if self.group_key != group.public_key() {
+            return Err(Error::from("Snapshot is of another group"));
+        }
+        if self.length > limit {
+            return Err(Error::ExceededSize);
+        }
+        let body = match self.compression {
+            0 => self.body,
+            #[cfg(feature = "zstd")]
+            1 => {
+                let mut body = vec![];
+                let _ = zstd::stream::read::Decoder::new(&self.body[..])
+                    .and_then(|decoder| decoder.take(self.length + 1).read_to_end(&mut body))
+                    .map_err(|_| {
+                        Error::FailedToParse("Snapshot could not be decompressed".into())
+                    })?;
+                body
+            }
+            tag => {
+                return Err(Error::FailedToParse(format!(
+                    "Unsupported snapshot compression {}",
+                    tag
+                )))
+            }
+        };
+        if body.len() as u64 != self.length || hash(&[&body]) != self.content_hash {
+            return Err(Error::FailedToParse("Snapshot is corrupted".into()));
+        }
+        Ok(body)
+    }
+}
+
+/// The state of a Replica, as written to a snapshot.
+/// Keys are not part of it, and are provided when restoring.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub(crate) struct ReplicaSnapshot {
+    pub(crate) accounts: Vec<AccountRecord>,
+    pub(crate) pending_debits: Vec<(AccountId, u64)>,
+    pub(crate) other_groups: Vec<PublicKeySet>,
+    pub(crate) owner_conditions: Vec<(AccountId, Condition)>,
+    pub(crate) quarantined: Vec<(AccountId, Digest)>,
+    pub(crate) features: Vec<(AccountId, BTreeSet<WalletFeature>)>,
+    pub(crate) key_history: Vec<(PublicKeySet, KeySuccession)>,
+    pub(crate) moved: Vec<(AccountId, PublicKey)>,
+    pub(crate) attachments: Vec<SignedAttachment>,
+    pub(crate) double_spends: Vec<DoubleSpendAttempted>,
+    pub(crate) alternate_proofs: Vec<ReceivedCredit>,
+    pub(crate) attestations: Vec<KeyShareAttestation>,
+    pub(crate) app_wallets: Vec<SignedAppWallet>,
+    pub(crate) multisig_policies: Vec<MultisigPolicySet>,
+    pub(crate) escrows: Vec<EscrowOpened>,
+    pub(crate) refunds: Vec<(TransferId, Money)>,
+    pub(crate) spending_limits: Vec<SpendingLimitsSet>,
+    pub(crate) pending_since: Vec<(AccountId, Timestamp)>,
+    pub(crate) expired_debits: Vec<(AccountId, u64)>,
+    pub(crate) applied_at: Vec<(AccountId, TransferId, AppliedAt)>,
+    pub(crate) trust_anchors: TrustAnchors,
+    pub(crate) disbursement_roots: Vec<(TransferId, Digest)>,
+    pub(crate) pending_refunds: Vec<(TransferId, (TransferId, Money))>,
+    pub(crate) import_nonces: Vec<(AccountId, Digest)>,
+}
+
+/// The layouts of the state written by past versions, from version 1 on: the number of
+/// fields of the state, and of each of its accounts. Fields have only ever been appended,
+/// so that a past layout is read as the current one, with the fields appended since empty.
+/// Every change of the layout bumps the version, and adds the layout it replaces here.
+const LAYOUTS: [(usize, usize); SNAPSHOT_VERSION as usize - 1] = [
+    (6, 5),   // 1
+    (7, 5),   // 2: key history
+    (8, 5),   // 3: moved wallets
+    (8, 7),   // 4: consolidated credits of the accounts
+    (9, 7),   // 5: attachments
+    (10, 7),  // 6: double spends
+    (11, 7),  // 7: alternate proofs
+    (11, 9),  // 8: fees of the accounts
+    (12, 9),  // 9: attestations
+    (13, 9),  // 10: app wallets
+    (13, 10), // 11: previous ids of the accounts
+    (14, 10), // 12: multisig policies
+    (15, 10), // 13: escrows
+    (16, 10), // 14: refunds
+    (17, 10), // 15: spending limits
+    (19, 10), // 16: pending since and expired debits
+    (20, 10), // 17: applied at
+    (20, 10), // 18: sealed in an envelope
+    (21, 10), // 19: trust anchors
+    (22, 10), // 20: disbursement roots
+    (23, 10), // 21: pending refunds
+];
+
+/// The first version sealing the state in an envelope.
+const ENVELOPE_VERSION: u16 = 18;
+
+impl ReplicaSnapshot {
+    /// The snapshot of the state of the group, in its envelope,
+    /// with the version of the format as header.
+    pub(crate) fn to_bytes(
+        &self,
+        group: &PublicKeySet,
+        compression: SnapshotCompression,
+    ) -> Result<Vec<u8>> {
+        let body = serialise(self)?;
+        let envelope = SnapshotEnvelope {
+            group_key: group.public_key(),
+            key_epoch: self.key_history.len() as u64,
+            compression: compression.tag(),
+            length: body.len() as u64,
+            content_hash: hash(&[&body]),
+            body: compression.compress(body)?,
+        };
+        let mut bytes = SNAPSHOT_VERSION.to_le_bytes().to_vec();
+        bytes.extend(serialise(&envelope)?);
+        Ok(bytes)
+    }
+
+    /// Reads a snapshot of the group, of any supported version,
+    /// migrating it to the current format. Snapshots before version 18
+    /// have no envelope, and are read without checking them. All versions
+    /// are decoded within the limits, as a snapshot may be received from a peer.
+    pub(crate) fn from_bytes(
+        bytes: &[u8],
+        group: &PublicKeySet,
+        limits: &SizeLimits,
+    ) -> Result<Self> {
+        if bytes.len() < 2 {
+            return Err(Error::FailedToParse("Snapshot has no version".into()));
+        }
+        let (header, body) = bytes.split_at(2);
+        let version = u16::from_le_bytes([header[0], header[1]]);
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(Error::FailedToParse(format!(
+                "Unsupported snapshot version {}",
+                version
+            )));
+        }
+        let (state, key_epoch) = if version >= ENVELOPE_VERSION {
+            let envelope: SnapshotEnvelope = limits.snapshot(body)?;
+            let key_epoch = envelope.key_epoch;
+            (envelope.open(group, limits.snapshot)?, Some(key_epoch))
+        } else {
+            (body.to_vec(), None)
+        };
+        let snapshot: Self = match LAYOUTS.get(version as usize - 1) {
+            None => limits.snapshot(&state)?,
+            Some((fields, account_fields)) => {
+                if state.len() as u64 > limits.snapshot {
+                    return Err(Error::ExceededSize);
+                }
+                Self::read(&state, *fields, *account_fields)?
+            }
+        };
+        if let Some(key_epoch) = key_epoch {
+            if snapshot.key_history.len() as u64 != key_epoch {
+                return Err(Error::FailedToParse(
+                    "Snapshot is not of its key epoch".into(),
+                ));
+            }
+        }
+        for (_, condition) in &snapshot.owner_conditions {
+            limits.check_depth(condition.depth())?;
+        }
+        Ok(snapshot)
+    }
+
+    /// Reads a state written in a past layout (see LAYOUTS).
+    fn read(mut bytes: &[u8], fields: usize, account_fields: usize) -> Result<Self> {
+        let mut reader = FieldReader::new(&mut bytes, fields);
+        let snapshot = Self {
+            accounts: reader.seq(|bytes| AccountRecord::read(bytes, account_fields))?,
+            pending_debits: reader.field()?,
+            other_groups: reader.field()?,
+            owner_conditions: reader.field()?,
+            quarantined: reader.field()?,
+            features: reader.field()?,
+            key_history: reader.field()?,
+            moved: reader.field()?,
+            attachments: reader.field()?,
+            double_spends: reader.field()?,
+            alternate_proofs: reader.field()?,
+            attestations: reader.field()?,
+            app_wallets: reader.field()?,
+            multisig_policies: reader.field()?,
+            escrows: reader.field()?,
+            refunds: reader.field()?,
+            spending_limits: reader.field()?,
+            pending_since: reader.field()?,
+            expired_debits: reader.field()?,
+            applied_at: reader.field()?,
+            trust_anchors: reader.field()?,
+            disbursement_roots: reader.field()?,
+            pending_refunds: reader.field()?,
+            import_nonces: reader.field()?,
+        };
+        if !bytes.is_empty() {
+            return Err(Error::FailedToParse("Snapshot has trailing bytes".into()));
+        }
+        Ok(snapshot)
+    }
+}
+
+fn serialise<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    bincode::serialize(value)
+        .map_err(|_| Error::NetworkOther("Could not serialise snapshot".into()))
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_snapshots_of_past_layouts() {
+        // Arrange
+        let group =
+            threshold_crypto::SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+        let wallet = PublicKey::Bls(threshold_crypto::SecretKey::random().public_key());
+        let entries: Vec<crate::HistoryEntry> = vec![];
+        let account = (
+            wallet,
+            None::<crate::AccountState>,
+            entries.clone(),
+            entries,
+            false,
+        );
+        let state = (
+            vec![account],
+            vec![(wallet, 0u64)],
+            Vec::<PublicKeySet>::new(),
+            Vec::<(AccountId, Condition)>::new(),
+            Vec::<(AccountId, Digest)>::new(),
+            Vec::<(AccountId, BTreeSet<WalletFeature>)>::new(),
+        );
+        let with_version = |version: u16, trailing: &[u8]| {
+            let mut bytes = version.to_le_bytes().to_vec();
+            bytes.extend(serialise(&state).unwrap());
+            bytes.extend(trailing);
+            bytes
+        };
+        let read =
+            |bytes: &[u8]| ReplicaSnapshot::from_bytes(bytes, &group, &SizeLimits::default());
+
+        // Act
+        let v1 = read(&with_version(1, &[]));
+        let v2 = read(&with_version(2, &[]));
+        let v2_extended = read(&with_version(2, &0u64.to_le_bytes()));
+
+        // Assert
+        assert!(
+            v1.as_ref().map(|s| s.accounts.clone())
+                == Ok(vec![crate::Account::new(wallet).to_record()])
+        );
+        assert!(v1.map(|s| s.pending_debits) == Ok(vec![(wallet, 0)]));
+        // version 2 has a key history more than version 1
+        assert!(v2.is_err());
+        assert!(v2_extended.map(|s| s.key_history.is_empty()) == Ok(true));
+        assert!(read(&with_version(ENVELOPE_VERSION, &[])).is_err());
+        assert!(read(&with_version(SNAPSHOT_VERSION + 1, &[])).is_err());
+    }
+}
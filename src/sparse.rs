@@ -0,0 +1,158 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::{Account, HistoryEntry},
+    checkpoint::{history_hash, history_prefix, AccountState, SignedCheckpoint},
+    hashing::{Digest, MerkleProof},
+};
+use safe_nd::{AccountId, Error, Result, Transfer};
+use serde::{Deserialize, Serialize};
+use threshold_crypto::PublicKeySet;
+
+/// The maximum number of wallets covered by a sparse proof.
+pub const MAX_SPARSE_PROOF_WALLETS: usize = 256;
+
+/// The state of a wallet at a checkpoint, with the entries of its history,
+/// from which the committed state is recomputed.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletSlice {
+    /// The state of the wallet at the checkpoint.
+    pub state: AccountState,
+    /// The proof of the state, against the state hash of the checkpoint.
+    pub proof: MerkleProof,
+    /// The digests the history hash is chained from, for the entries replaced
+    /// by a checkpoint or a consolidation (see AccountState::history_hash).
+    pub prefix: Vec<Digest>,
+    /// The credits of the wallet at the checkpoint, with their proofs.
+    pub credits: Vec<HistoryEntry>,
+    /// The debits of the wallet at the checkpoint. Only the transfers are
+    /// part of the slice, as they are needed to recompute the history hash.
+    pub debits: Vec<Transfer>,
+}
+
+impl WalletSlice {
+    /// The slice of the account at the state, proven by the proof.
+    pub(crate) fn of(account: &Account, state: AccountState, proof: MerkleProof) -> Result<Self> {
+        let (credits, debits) = match account.entries_at(&state) {
+            Some(entries) => entries,
+            None => return Err(Error::from("History since checkpoint is compacted")),
+        };
+        let slice = Self {
+            state,
+            proof,
+            prefix: history_prefix(account),
+            credits: credits.to_vec(),
+            debits: debits.iter().map(|e| e.transfer().clone()).collect(),
+        };
+        // the prefix may have changed since, by a later consolidation
+        slice.verify_history()?;
+        Ok(slice)
+    }
+
+    /// Verifies that the entries are those of the history committed to by the state.
+    pub fn verify_history(&self) -> Result<()> {
+        let transfers = self
+            .credits
+            .iter()
+            .map(HistoryEntry::transfer)
+            .chain(&self.debits);
+        if history_hash(&self.prefix, transfers)? == self.state.history_hash {
+            Ok(())
+        } else {
+            Err(Error::from("Entries are not the history of the state"))
+        }
+    }
+}
+
+/// The states of a few wallets at the last checkpoint of their Replicas, with their
+/// credits, such as for a custodian to verify the deposits to its wallets without
+/// the state of all other wallets (see Replica::sparse_proof). The states are bound
+/// to the state hash of the checkpoint, which only the Merkle paths are needed of.
+/// The proofs of the credits are to be verified against the groups that signed them.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SparseProof {
+    /// The last checkpoint of the Replicas.
+    pub signed_checkpoint: SignedCheckpoint,
+    /// The slices of the wallets, in the order requested.
+    pub wallets: Vec<WalletSlice>,
+}
+
+impl SparseProof {
+    /// Verifies that the checkpoint was signed by the given Replicas, and that
+    /// the state and the history of every wallet are part of it.
+    pub fn verify(&self, replicas: &PublicKeySet) -> Result<()> {
+        self.signed_checkpoint.verify(replicas)?;
+        let state_hash = &self.signed_checkpoint.checkpoint.state_hash;
+        for slice in &self.wallets {
+            if !slice.proof.verify(slice.state.digest()?, state_hash) {
+                return Err(Error::from("Wallet state is not part of the checkpoint"));
+            }
+            slice.verify_history()?;
+        }
+        Ok(())
+    }
+
+    /// The slice of the wallet, if covered.
+    pub fn wallet(&self, wallet: &AccountId) -> Option<&WalletSlice> {
+        self.wallets.iter().find(|slice| slice.state.id == *wallet)
+    }
+}
+
+mod test {
+    use super::*;
+    use crate::checkpoint::CheckpointStates;
+    use crdts::Dot;
+    use safe_nd::{Money, PublicKey};
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn slices_the_history_at_the_checkpoint() -> Result<()> {
+        // Arrange
+        let mut account = get_account();
+        let captured = capture(vec![&account, &get_account()])?;
+        let (state, proof) = captured.prove(&account.id()).unwrap();
+
+        // Act
+        account.append(get_transfer(account.id()));
+        let slice = WalletSlice::of(&account, state, proof)?;
+        let mut forged = slice.clone();
+        forged.debits.push(get_transfer(get_random_pk()));
+
+        // Assert
+        assert!(slice.credits.len() == 1);
+        assert!(slice.verify_history().is_ok());
+        assert!(slice
+            .proof
+            .verify(slice.state.digest()?, &captured.checkpoint.state_hash));
+        assert!(forged.verify_history().is_err());
+        Ok(())
+    }
+
+    fn capture(accounts: Vec<&Account>) -> Result<CheckpointStates> {
+        CheckpointStates::capture(1, accounts)
+    }
+
+    fn get_account() -> Account {
+        let mut account = Account::new(get_random_pk());
+        account.append(get_transfer(account.id()));
+        account
+    }
+
+    fn get_transfer(to: AccountId) -> Transfer {
+        Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to,
+            amount: Money::from_nano(10),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::AccountRecord,
+    clock::Timestamp,
+    hashing::{hash, Digest},
+};
+use safe_nd::{AccountId, Error, Result};
+use serde::{Deserialize, Serialize};
+use threshold_crypto::PublicKeySet;
+
+/// A debit of an exported wallet that was validated, and not registered when exported.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct ExportedPendingDebit {
+    /// The wallet.
+    pub wallet: AccountId,
+    /// The counter of the last debit validated.
+    pub counter: u64,
+    /// When it was validated, if recorded.
+    pub since: Option<Timestamp>,
+}
+
+/// The wallets split off by a group, such as when its section splits, for the sibling
+/// group to take over (see Replica::split and Replica::absorb). The histories carry
+/// their proofs, so that the sibling verifies them instead of trusting the sender.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletExportPacket {
+    /// The PK set of the group that exported the wallets.
+    pub group: PublicKeySet,
+    /// The wallets, with their histories.
+    pub(crate) accounts: Vec<AccountRecord>,
+    /// The debits of the wallets pending when exported.
+    pub pending_debits: Vec<ExportedPendingDebit>,
+}
+
+impl WalletExportPacket {
+    /// The exported wallets, in the order exported.
+    pub fn wallets(&self) -> Vec<AccountId> {
+        self.accounts.iter().map(AccountRecord::id).collect()
+    }
+
+    /// The hash of the packet, which the group that exported it records
+    /// (see WalletsExported), linking the export to the takeover.
+    pub fn hash(&self) -> Result<Digest> {
+        match bincode::serialize(self) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise packet".into())),
+            Ok(bytes) => Ok(hash(&[&bytes])),
+        }
+    }
+}
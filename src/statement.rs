@@ -0,0 +1,224 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    account::Account,
+    attachment::SignedAttachment,
+    money::{format_money, saturating_add, saturating_sub},
+    search::Payment,
+};
+use safe_nd::{AccountId, Money, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The header of the CSV form of a statement (see Statement::to_csv).
+pub const STATEMENT_CSV_HEADER: &str = "kind,counter,counterparty,amount,balance,memo";
+
+/// What a line of a statement is.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum StatementLineKind {
+    /// A transfer to the wallet.
+    Credit,
+    /// A transfer from the wallet.
+    Debit,
+    /// The fee charged for the debit before it.
+    Fee,
+}
+
+/// A line of a statement: a transfer to or from the wallet, or a fee it paid,
+/// with the balance of the wallet after it.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct StatementLine {
+    /// What the line is.
+    pub kind: StatementLineKind,
+    /// The id of the transfer, or of the debit that the fee was charged for.
+    pub transfer_id: TransferId,
+    /// The sender of a credit, or the recipient of a debit. None for fees.
+    pub counterparty: Option<AccountId>,
+    /// The amount credited, debited or charged.
+    pub amount: Money,
+    /// The balance of the wallet after the line.
+    pub balance: Money,
+    /// The memo attached to a credit by its sender, if any (see MEMO_KEY).
+    pub memo: Option<String>,
+}
+
+/// The transfers of a wallet in the order they were applied, with the balance after
+/// each, for accountants to reconcile the wallet without the history format.
+/// The history before a checkpoint or consolidation of the wallet is in the
+/// opening balance, as are the fees received by the wallet, which are not ordered.
+/// Serialisable with serde, such as to JSON, and to CSV (see to_csv).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Statement {
+    /// The wallet.
+    pub wallet: AccountId,
+    /// The balance before the first line.
+    pub opening_balance: Money,
+    /// The lines, oldest first.
+    pub lines: Vec<StatementLine>,
+    /// The balance of the wallet, as held.
+    pub closing_balance: Money,
+}
+
+impl Statement {
+    /// The statement of the account, its lines in the order of the payments. Entries of
+    /// the account missing from the payments follow them, credits first, and payments
+    /// that are not entries of the account are left out. Credits carry the memo of
+    /// their attachment, if any.
+    pub(crate) fn of<'a, I: IntoIterator<Item = &'a Payment>>(
+        account: &Account,
+        payments: I,
+        attachments: Option<&HashMap<TransferId, SignedAttachment>>,
+    ) -> Self {
+        let mut seen = HashSet::new();
+        let held = account
+            .credit_entries()
+            .iter()
+            .chain(account.debit_entries())
+            .map(|entry| entry.transfer());
+        let ordered: Vec<_> = payments
+            .into_iter()
+            .map(|payment| &payment.transfer)
+            .chain(held)
+            .filter(|transfer| account.get(&transfer.id).is_some())
+            .filter(|transfer| seen.insert(transfer.id))
+            .collect();
+        let mut balance = account.opening_balance();
+        let opening_balance = balance;
+        let mut lines = vec![];
+        for transfer in ordered {
+            let debit = account.is_own(&transfer.id.actor);
+            let (kind, counterparty) = if debit {
+                balance = saturating_sub(balance, transfer.amount);
+                (StatementLineKind::Debit, transfer.to)
+            } else {
+                balance = saturating_add(balance, transfer.amount);
+                (StatementLineKind::Credit, transfer.id.actor)
+            };
+            let memo = attachments
+                .and_then(|attachments| attachments.get(&transfer.id))
+                .and_then(|signed| signed.attachment.get_memo())
+                .filter(|_| !debit)
+                .map(str::to_string);
+            lines.push(StatementLine {
+                kind,
+                transfer_id: transfer.id,
+                counterparty: Some(counterparty),
+                amount: transfer.amount,
+                balance,
+                memo,
+            });
+            if let Some(fee) = account.fee_paid(&transfer.id).filter(|_| debit) {
+                balance = saturating_sub(balance, fee);
+                lines.push(StatementLine {
+                    kind: StatementLineKind::Fee,
+                    transfer_id: transfer.id,
+                    counterparty: None,
+                    amount: fee,
+                    balance,
+                    memo: None,
+                });
+            }
+        }
+        Self {
+            wallet: account.id(),
+            opening_balance,
+            lines,
+            closing_balance: account.balance(),
+        }
+    }
+
+    /// The lines in CSV, under STATEMENT_CSV_HEADER, one per row, with amounts
+    /// as whole tokens (see format_money). The format does not depend on locale.
+    /// Memos are quoted when they hold commas, quotes or line breaks.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", STATEMENT_CSV_HEADER);
+        for line in &self.lines {
+            let counterparty = line
+                .counterparty
+                .map_or_else(String::new, |id| id.to_string());
+            csv.push_str(&format!(
+                "{:?},{},{},{},{},{}\n",
+                line.kind,
+                line.transfer_id.counter,
+                counterparty,
+                format_money(line.amount),
+                format_money(line.balance),
+                line.memo.as_deref().map_or_else(String::new, csv_field)
+            ));
+        }
+        csv
+    }
+}
+
+/// The field quoted, if it holds a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::{PublicKey, Transfer};
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn runs_the_balance_in_the_order_applied() {
+        // Arrange
+        let id = get_random_pk();
+        let (payer, shop) = (get_random_pk(), get_random_pk());
+        let credit = get_transfer(payer, 0, id, 100);
+        let debit = get_transfer(id, 0, shop, 30);
+        let mut account = Account::new(id);
+        account.append(credit.clone());
+        account.append(debit.clone());
+        account.pay_fee(debit.id, Money::from_nano(1));
+
+        // Act
+        let statement = Statement::of(&account, &[], None);
+
+        // Assert
+        let balances: Vec<_> = statement
+            .lines
+            .iter()
+            .map(|l| l.balance.as_nano())
+            .collect();
+        assert!(statement.opening_balance == Money::zero());
+        assert!(balances == vec![100, 70, 69]);
+        assert!(statement.lines[1].counterparty == Some(shop));
+        assert!(statement.lines[2].kind == StatementLineKind::Fee);
+        assert!(statement.closing_balance == Money::from_nano(69));
+        let csv = statement.to_csv();
+        assert!(csv.lines().count() == 4);
+        let debit_row = format!("Debit,0,{},0.000000030,0.000000070,", shop);
+        assert!(csv.lines().nth(2) == Some(debit_row.as_str()));
+    }
+
+    #[test]
+    fn quotes_memos_in_csv() {
+        assert!(csv_field("INV-42") == "INV-42");
+        assert!(csv_field("a,b") == "\"a,b\"");
+        assert!(csv_field("say \"hi\"") == "\"say \"\"hi\"\"\"");
+    }
+
+    fn get_transfer(from: PublicKey, counter: u64, to: PublicKey, amount: u64) -> Transfer {
+        Transfer {
+            id: Dot::new(from, counter),
+            to,
+            amount: Money::from_nano(amount),
+        }
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    hashing::{hash, Digest},
+    ReplicaEvent,
+};
+use safe_nd::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// The link that the first event of a store is linked to.
+pub const GENESIS_LINK: Digest = [0; 32];
+
+/// An event of a store, with its index in the store as its sequence number, and its
+/// link to the event before it (see link_event), for operators to export a log of the
+/// events that is tamper evident, resuming from the last sequence number exported.
+#[derive(Clone, Hash, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct SequencedEvent {
+    /// The index of the event in the store.
+    pub sequence: u64,
+    /// The link of the event, committing to all events before it.
+    pub hash: Digest,
+    /// The event.
+    pub event: ReplicaEvent,
+}
+
+impl SequencedEvent {
+    /// Verifies that the event is linked to the link of the one before it,
+    /// GENESIS_LINK for the first event of the store.
+    pub fn verify(&self, previous: &Digest) -> Result<()> {
+        if link_event(previous, &self.event)? == self.hash {
+            Ok(())
+        } else {
+            Err(Error::NetworkOther(format!(
+                "Event chain is broken at {}",
+                self.sequence
+            )))
+        }
+    }
+}
+
+/// Persistence of the events of a Replica, and of snapshots of its state,
+/// for the Replica to be restored from the latest snapshot and the events after it.
+/// Every event is hash-linked to the one before it (see link_event), so that
+/// events altered, or missing before the latest snapshot, are detected.
+pub trait ReplicaEventStore: Send {
+    /// Appends the event, returning its index.
+    fn append(&mut self, event: &ReplicaEvent) -> Result<u64>;
+
+    /// The events from the index on, in the order they were appended.
+    fn read_from(&self, index: u64) -> Result<Vec<ReplicaEvent>>;
+
+    /// Same as read_from, with the index and link of each event. By default, the
+    /// links are those of the events read from the start of the store.
+    fn read_sequenced_from(&self, index: u64) -> Result<Vec<SequencedEvent>> {
+        let mut previous = GENESIS_LINK;
+        let mut events = vec![];
+        for (sequence, event) in self.read_from(0)?.into_iter().enumerate() {
+            previous = link_event(&previous, &event)?;
+            if sequence as u64 >= index {
+                events.push(SequencedEvent {
+                    sequence: sequence as u64,
+                    hash: previous,
+                    event,
+                });
+            }
+        }
+        Ok(events)
+    }
+
+    /// Stores a snapshot of the state (see Replica::to_snapshot), as of the
+    /// events before the index, replacing any previous snapshot.
+    fn snapshot(&mut self, index: u64, snapshot: &[u8]) -> Result<()>;
+
+    /// The latest snapshot, with the index of the first event after it.
+    fn latest_snapshot(&self) -> Result<Option<(u64, Vec<u8>)>>;
+
+    /// The index of the next event to be appended.
+    fn next_index(&self) -> u64;
+
+    /// The link of the last event appended, GENESIS_LINK if none.
+    fn tail_hash(&self) -> Digest;
+
+    /// Verifies that every event is linked to the one before it,
+    /// and that the chain reaches the link recorded with the latest snapshot.
+    fn verify_chain(&self) -> Result<()>;
+}
+
+/// A cheaply cloneable handle to the store of events of a Replica.
+/// Clones share the same store.
+#[derive(Clone)]
+pub struct EventStore(Arc<Mutex<dyn ReplicaEventStore>>);
+
+impl EventStore {
+    /// Wraps the given store.
+    pub fn new<S: ReplicaEventStore + 'static>(store: S) -> Self {
+        Self(Arc::new(Mutex::new(store)))
+    }
+
+    /// Appends the event, returning its index.
+    pub fn append(&self, event: &ReplicaEvent) -> Result<u64> {
+        self.lock()?.append(event)
+    }
+
+    /// The events from the index on.
+    pub fn read_from(&self, index: u64) -> Result<Vec<ReplicaEvent>> {
+        self.lock()?.read_from(index)
+    }
+
+    /// The events from the index on, with the index and link of each.
+    pub fn read_sequenced_from(&self, index: u64) -> Result<Vec<SequencedEvent>> {
+        self.lock()?.read_sequenced_from(index)
+    }
+
+    /// Stores a snapshot of the state as of all events appended so far.
+    pub fn snapshot(&self, snapshot: &[u8]) -> Result<()> {
+        let mut store = self.lock()?;
+        let index = store.next_index();
+        store.snapshot(index, snapshot)
+    }
+
+    /// The latest snapshot, with the index of the first event after it.
+    pub fn latest_snapshot(&self) -> Result<Option<(u64, Vec<u8>)>> {
+        self.lock()?.latest_snapshot()
+    }
+
+    /// The index of the next event, with the link of the last one,
+    /// for peers to compare the tails of their stores by.
+    pub fn tail(&self) -> Result<(u64, Digest)> {
+        let store = self.lock()?;
+        Ok((store.next_index(), store.tail_hash()))
+    }
+
+    /// Verifies the links of all events in the store.
+    pub fn verify_chain(&self) -> Result<()> {
+        self.lock()?.verify_chain()
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, dyn ReplicaEventStore + 'static>> {
+        match self.0.lock() {
+            Ok(store) => Ok(store),
+            Err(_) => Err(Error::from("Event store is poisoned")),
+        }
+    }
+}
+
+impl Debug for EventStore {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "EventStore")
+    }
+}
+
+/// A store keeping the events in memory, such as for tests.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryEventStore {
+    events: Vec<ReplicaEvent>,
+    /// The link of each event.
+    links: Vec<Digest>,
+    snapshot: Option<(u64, Vec<u8>)>,
+    /// The link of the last event before the snapshot.
+    snapshot_link: Digest,
+}
+
+impl MemoryEventStore {
+    /// An empty store.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl ReplicaEventStore for MemoryEventStore {
+    fn append(&mut self, event: &ReplicaEvent) -> Result<u64> {
+        let link = link_event(&self.tail_hash(), event)?;
+        self.events.push(event.clone());
+        self.links.push(link);
+        Ok(self.events.len() as u64 - 1)
+    }
+
+    fn read_from(&self, index: u64) -> Result<Vec<ReplicaEvent>> {
+        Ok(self.events.iter().skip(index as usize).cloned().collect())
+    }
+
+    fn read_sequenced_from(&self, index: u64) -> Result<Vec<SequencedEvent>> {
+        Ok(self
+            .events
+            .iter()
+            .zip(self.links.iter())
+            .enumerate()
+            .skip(index as usize)
+            .map(|(sequence, (event, hash))| SequencedEvent {
+                sequence: sequence as u64,
+                hash: *hash,
+                event: event.clone(),
+            })
+            .collect())
+    }
+
+    fn snapshot(&mut self, index: u64, snapshot: &[u8]) -> Result<()> {
+        self.snapshot_link = link_at(&self.links, index)?;
+        self.snapshot = Some((index, snapshot.to_vec()));
+        Ok(())
+    }
+
+    fn latest_snapshot(&self) -> Result<Option<(u64, Vec<u8>)>> {
+        Ok(self.snapshot.clone())
+    }
+
+    fn next_index(&self) -> u64 {
+        self.events.len() as u64
+    }
+
+    fn tail_hash(&self) -> Digest {
+        self.links.last().copied().unwrap_or(GENESIS_LINK)
+    }
+
+    fn verify_chain(&self) -> Result<()> {
+        let mut records = Vec::with_capacity(self.events.len());
+        for event in &self.events {
+            records.push(serialise(event)?);
+        }
+        let snapshot = self
+            .snapshot
+            .as_ref()
+            .map(|(index, _)| (*index, self.snapshot_link));
+        verify_links(
+            self.links.iter().zip(records.iter().map(|r| r.as_slice())),
+            snapshot,
+        )
+    }
+}
+
+/// The link of the event to the one before it: the hash of the link of that one,
+/// and the serialisation of this one. The link of the last event thus commits to
+/// all events before it, in their order.
+pub fn link_event(previous: &Digest, event: &ReplicaEvent) -> Result<Digest> {
+    Ok(link(previous, &serialise(event)?))
+}
+
+pub(crate) fn link(previous: &Digest, record: &[u8]) -> Digest {
+    hash(&[previous, record])
+}
+
+/// The link of the last of the first `index` events.
+pub(crate) fn link_at(links: &[Digest], index: u64) -> Result<Digest> {
+    match index {
+        0 => Ok(GENESIS_LINK),
+        index => match links.get(index as usize - 1) {
+            Some(link) => Ok(*link),
+            None => Err(Error::from("No such event")),
+        },
+    }
+}
+
+/// Verifies the links of the records in order,
+/// and that the chain reaches the link recorded at the snapshot, if any.
+pub(crate) fn verify_links<'a, I: IntoIterator<Item = (&'a Digest, &'a [u8])>>(
+    records: I,
+    snapshot: Option<(u64, Digest)>,
+) -> Result<()> {
+    let mut links = vec![];
+    let mut previous = GENESIS_LINK;
+    for (index, (stored, record)) in records.into_iter().enumerate() {
+        let expected = link(&previous, record);
+        if *stored != expected {
+            return Err(Error::NetworkOther(format!(
+                "Event chain is broken at {}",
+                index
+            )));
+        }
+        links.push(expected);
+        previous = expected;
+    }
+    match snapshot {
+        Some((index, link)) if link_at(&links, index).ok() != Some(link) => {
+            Err(Error::from("Events before the snapshot are missing"))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn serialise(event: &ReplicaEvent) -> Result<Vec<u8>> {
+    match bincode::serialize(event) {
+        Err(_) => Err(Error::NetworkOther("Could not serialise event".into())),
+        Ok(record) => Ok(record),
+    }
+}
+
+mod test {
+    use super::*;
+    use safe_nd::KnownGroupAdded;
+    use threshold_crypto::SecretKeySet;
+
+    #[test]
+    fn exports_events_linked_in_sequence() -> Result<()> {
+        // Arrange
+        let mut store = MemoryEventStore::new();
+        let events = vec![get_event(), get_event(), get_event()];
+        for event in &events {
+            let _ = store.append(event)?;
+        }
+
+        // Act
+        let all = store.read_sequenced_from(0)?;
+        let resumed = store.read_sequenced_from(2)?;
+        let mut tampered = resumed[0].clone();
+        tampered.event = get_event();
+
+        // Assert
+        assert!(all.iter().map(|e| e.sequence).eq(0..3));
+        assert!(all.iter().map(|e| e.event.clone()).eq(events));
+        assert!(all[0].verify(&GENESIS_LINK).is_ok());
+        assert!(all[1].verify(&all[0].hash).is_ok());
+        assert!(resumed == all[2..].to_vec());
+        assert!(resumed[0].verify(&all[1].hash).is_ok());
+        assert!(resumed[0].hash == store.tail_hash());
+        assert!(tampered.verify(&all[1].hash).is_err());
+        Ok(())
+    }
+
+    fn get_event() -> ReplicaEvent {
+        let group = SecretKeySet::random(0, &mut rand::thread_rng()).public_keys();
+        ReplicaEvent::KnownGroupAdded(KnownGroupAdded { group })
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::search::PaymentDirection;
+use safe_nd::{AccountId, Money, Transfer};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    sync::{mpsc::Sender, Mutex, PoisonError},
+};
+
+/// A debit registered, or a credit propagated, for a watched wallet
+/// (see Replica::subscribe).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletNotification {
+    /// The wallet.
+    pub wallet: AccountId,
+    /// Whether the transfer was a debit or a credit of the wallet.
+    pub direction: PaymentDirection,
+    /// The transfer.
+    pub transfer: Transfer,
+    /// The balance of the wallet after the transfer.
+    pub balance: Money,
+}
+
+/// The senders that notifications of each watched wallet are pushed to.
+/// Senders whose receiver has hung up are dropped on the next notification.
+/// They are behind a mutex only for the Replica to be Sync, as senders are not.
+#[derive(Default)]
+pub(crate) struct Subscriptions {
+    watchers: Mutex<Watchers>,
+}
+
+type Watchers = HashMap<AccountId, Vec<Sender<WalletNotification>>>;
+
+impl Subscriptions {
+    pub(crate) fn subscribe(&mut self, wallet: AccountId, sender: Sender<WalletNotification>) {
+        self.watchers_mut().entry(wallet).or_default().push(sender);
+    }
+
+    pub(crate) fn unsubscribe(&mut self, wallet: &AccountId) {
+        let _ = self.watchers_mut().remove(wallet);
+    }
+
+    pub(crate) fn is_watched(&self, wallet: &AccountId) -> bool {
+        self.watchers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(wallet)
+    }
+
+    pub(crate) fn notify(&mut self, notification: WalletNotification) {
+        let wallet = notification.wallet;
+        let watchers = self.watchers_mut();
+        if let Some(senders) = watchers.get_mut(&wallet) {
+            senders.retain(|sender| sender.send(notification.clone()).is_ok());
+            if senders.is_empty() {
+                let _ = watchers.remove(&wallet);
+            }
+        }
+    }
+
+    fn watchers_mut(&mut self) -> &mut Watchers {
+        self.watchers
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clone for Subscriptions {
+    fn clone(&self) -> Self {
+        let watchers = self
+            .watchers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        Self {
+            watchers: Mutex::new(watchers),
+        }
+    }
+}
+
+impl Debug for Subscriptions {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Subscriptions")
+    }
+}
+
+mod test {
+    use super::*;
+    use crdts::Dot;
+    use safe_nd::PublicKey;
+    use threshold_crypto::SecretKey;
+
+    #[test]
+    fn drops_hung_up_subscribers() {
+        // Arrange
+        let wallet = get_random_pk();
+        let mut subscriptions = Subscriptions::default();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (hung_up, _) = std::sync::mpsc::channel();
+        subscriptions.subscribe(wallet, sender);
+        subscriptions.subscribe(wallet, hung_up);
+        let notification = WalletNotification {
+            wallet,
+            direction: PaymentDirection::Received,
+            transfer: Transfer {
+                id: Dot::new(get_random_pk(), 0),
+                to: wallet,
+                amount: Money::from_nano(10),
+            },
+            balance: Money::from_nano(10),
+        };
+
+        // Act
+        subscriptions.notify(notification.clone());
+        let received = receiver.try_recv();
+        drop(receiver);
+        subscriptions.notify(notification.clone());
+
+        // Assert
+        assert!(received == Ok(notification));
+        assert!(!subscriptions.is_watched(&wallet));
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
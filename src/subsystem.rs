@@ -0,0 +1,107 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use safe_nd::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// A part of a Replica that is not needed for consensus, and can be disabled.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum Subsystem {
+    /// Queries of wallets by clients, such as balance_or_redirect and export_history.
+    Queries,
+    /// Statistics over all wallets. Compiled in with the `stats` feature.
+    Stats,
+    /// Subscriptions to the transfers of wallets (see Replica::subscribe).
+    Subscriptions,
+    /// Simulated credits and debits. Compiled in with the `simulated-payouts` feature.
+    SimulatedPayouts,
+}
+
+impl Subsystem {
+    /// Whether the subsystem is compiled in.
+    pub fn is_compiled(self) -> bool {
+        match self {
+            Subsystem::Stats => cfg!(feature = "stats"),
+            Subsystem::SimulatedPayouts => cfg!(feature = "simulated-payouts"),
+            Subsystem::Queries | Subsystem::Subscriptions => true,
+        }
+    }
+}
+
+/// The subsystems disabled on a Replica, for Elders that should only do the
+/// work critical to consensus (see Replica::with_subsystems).
+/// All subsystems compiled in are enabled by default.
+#[derive(Clone, Default, Hash, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Subsystems {
+    disabled: BTreeSet<Subsystem>,
+}
+
+impl Subsystems {
+    /// All subsystems compiled in enabled.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// All subsystems disabled, leaving only the work critical to consensus.
+    pub fn consensus_only() -> Self {
+        Self::all()
+            .disable(Subsystem::Queries)
+            .disable(Subsystem::Stats)
+            .disable(Subsystem::Subscriptions)
+            .disable(Subsystem::SimulatedPayouts)
+    }
+
+    /// Disables the subsystem.
+    pub fn disable(mut self, subsystem: Subsystem) -> Self {
+        let _ = self.disabled.insert(subsystem);
+        self
+    }
+
+    /// Enables the subsystem, if compiled in.
+    pub fn enable(mut self, subsystem: Subsystem) -> Self {
+        let _ = self.disabled.remove(&subsystem);
+        self
+    }
+
+    /// Whether the subsystem is compiled in, and not disabled.
+    pub fn is_enabled(&self, subsystem: Subsystem) -> bool {
+        subsystem.is_compiled() && !self.disabled.contains(&subsystem)
+    }
+
+    /// Errors if the subsystem is not enabled.
+    pub fn ensure(&self, subsystem: Subsystem) -> Result<()> {
+        if self.is_enabled(subsystem) {
+            Ok(())
+        } else {
+            Err(Error::from(format!("{:?} are disabled", subsystem)))
+        }
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn disables_subsystems() {
+        // Arrange
+        let subsystems = Subsystems::all().disable(Subsystem::Queries);
+
+        // Act
+        let consensus_only = Subsystems::consensus_only().enable(Subsystem::Subscriptions);
+
+        // Assert
+        assert!(!subsystems.is_enabled(Subsystem::Queries));
+        assert!(subsystems.ensure(Subsystem::Queries).is_err());
+        assert!(subsystems.is_enabled(Subsystem::Subscriptions));
+        assert!(subsystems.is_enabled(Subsystem::Stats) == Subsystem::Stats.is_compiled());
+        assert!(consensus_only.is_enabled(Subsystem::Subscriptions));
+        assert!(!consensus_only.is_enabled(Subsystem::Stats));
+        assert!(!consensus_only.is_enabled(Subsystem::SimulatedPayouts));
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use safe_nd::{AccountId, Money};
+use serde::{Deserialize, Serialize};
+
+/// The audit of the money held by a Replica against the total it is expected to hold,
+/// for operators to detect money being created or lost by a bug.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SupplyReport {
+    /// The total the Replica was expected to account for.
+    pub expected_total: Money,
+    /// The sum of the balances of all wallets.
+    pub total_balance: Money,
+    /// The sum of the debits registered here, to wallets held by other Replicas.
+    pub sent_elsewhere: Money,
+    /// The sum of the fees removed from circulation.
+    pub burned: Money,
+    /// How the accounted total differs from the expected, if it does.
+    pub discrepancy: Option<Discrepancy>,
+    /// The wallets whose balance does not follow from their history.
+    pub anomalies: Vec<WalletAnomaly>,
+}
+
+impl SupplyReport {
+    /// Whether the total is as expected, and no wallet is anomalous.
+    pub fn is_sound(&self) -> bool {
+        self.discrepancy.is_none() && self.anomalies.is_empty()
+    }
+}
+
+/// The difference of the accounted total from the expected one.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum Discrepancy {
+    /// More money is accounted for than expected, i.e. money was created.
+    Surplus(Money),
+    /// Less money is accounted for than expected, i.e. money was lost.
+    Deficit(Money),
+}
+
+/// A wallet whose balance does not follow from its history.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct WalletAnomaly {
+    /// The wallet.
+    pub wallet: AccountId,
+    /// What is wrong with it.
+    pub kind: AnomalyKind,
+}
+
+/// What is wrong with the balance of a wallet.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum AnomalyKind {
+    /// The history debits more than it credits, by the amount.
+    NegativeBalance(Money),
+    /// The balance held differs from the one the history adds up to.
+    BalanceMismatch {
+        /// The balance held.
+        held: Money,
+        /// The balance of the history.
+        computed: Money,
+    },
+}
+
+/// The difference of the accounted total from the expected one, if any.
+pub(crate) fn discrepancy(expected: Money, accounted: u128) -> Option<Discrepancy> {
+    let expected = u128::from(expected.as_nano());
+    if accounted > expected {
+        Some(Discrepancy::Surplus(to_money(accounted - expected)))
+    } else if accounted < expected {
+        Some(Discrepancy::Deficit(to_money(expected - accounted)))
+    } else {
+        None
+    }
+}
+
+/// The amount, capped at the largest one there can be.
+pub(crate) fn to_money(nanos: u128) -> Money {
+    Money::from_nano(nanos.min(u128::from(u64::MAX)) as u64)
+}
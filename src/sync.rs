@@ -0,0 +1,50 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{account::Account, disbursement::PayoutProof};
+use safe_nd::{TransferPropagated, TransferRegistered};
+use serde::{Deserialize, Serialize};
+
+/// How much of the history of a wallet a Replica holds,
+/// for a peer to send it the rest (see Replica::sync_delta).
+#[derive(Clone, Copy, Default, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SyncIndex {
+    /// The number of credits held, i.e. the index of the next credit.
+    pub credit_index: u64,
+    /// The number of debits held, i.e. the index of the next debit.
+    pub debit_index: u64,
+}
+
+impl SyncIndex {
+    /// The indices of the latest entries of the account.
+    pub(crate) fn of(account: &Account) -> Self {
+        Self {
+            credit_index: account.credit_count() as u64,
+            debit_index: account.next_debit(),
+        }
+    }
+}
+
+/// The events a Replica missed for some wallets, as held by a peer, for it to catch up
+/// on after being offline, or when newly promoted (see Replica::apply_sync).
+#[derive(Clone, Default, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct SyncPacket {
+    /// The debits registered, in the order of each wallet.
+    pub registered: Vec<TransferRegistered>,
+    /// The credits propagated.
+    pub propagated: Vec<TransferPropagated>,
+    /// The payouts of disbursements propagated.
+    pub payouts: Vec<PayoutProof>,
+}
+
+impl SyncPacket {
+    /// Whether there is nothing to catch up on.
+    pub fn is_empty(&self) -> bool {
+        self.registered.is_empty() && self.propagated.is_empty() && self.payouts.is_empty()
+    }
+}
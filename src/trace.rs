@@ -0,0 +1,173 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::receipt::CreditAgreementProof;
+use safe_nd::{Error, PublicKey, Result, SignatureShare, TransferId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use threshold_crypto::PublicKeySet;
+
+/// A step of a payment on its way from the sender to the recipient, in order.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum Hop {
+    /// The debit was validated by a Replica of the section of the sender.
+    Validated,
+    /// The debit was registered by a Replica of the section of the sender.
+    Registered,
+    /// The debit was propagated to, and credited by, a Replica of the section of the recipient.
+    Propagated,
+    /// The credit was agreed by the section of the recipient (see CreditAgreementProof).
+    Credited,
+}
+
+impl Hop {
+    /// The hop that follows this one, if any.
+    pub fn next(self) -> Option<Hop> {
+        match self {
+            Hop::Validated => Some(Hop::Registered),
+            Hop::Registered => Some(Hop::Propagated),
+            Hop::Propagated => Some(Hop::Credited),
+            Hop::Credited => None,
+        }
+    }
+}
+
+/// The attestation of a Replica that a payment reached a hop at its section
+/// (see Replica::sign_hop).
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct HopRecord {
+    /// The payment.
+    pub transfer_id: TransferId,
+    /// The hop reached.
+    pub hop: Hop,
+    /// The key of the section of the Replica.
+    pub section: PublicKey,
+    /// The signature of the Replica over the above.
+    pub replica_signature: SignatureShare,
+}
+
+impl HopRecord {
+    /// What a Replica signs to attest the hop.
+    pub(crate) fn payload(
+        transfer_id: &TransferId,
+        hop: Hop,
+        section: &PublicKey,
+    ) -> Result<Vec<u8>> {
+        match bincode::serialize(&(transfer_id, hop, section)) {
+            Err(_) => Err(Error::NetworkOther("Could not serialise hop".into())),
+            Ok(data) => Ok(data),
+        }
+    }
+
+    /// Verifies that the record was signed by a Replica of the section with the PK set.
+    pub fn verify(&self, section: &PublicKeySet) -> Result<()> {
+        if self.section != PublicKey::Bls(section.public_key()) {
+            return Err(Error::from("Hop is not of the section"));
+        }
+        let data = Self::payload(&self.transfer_id, self.hop, &self.section)?;
+        let key = section.public_key_share(self.replica_signature.index);
+        if key.verify(&self.replica_signature.share, data) {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+}
+
+/// The verified hops of a payment, across sections.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct PaymentTrace {
+    /// The payment.
+    pub transfer_id: TransferId,
+    /// The hop records, in the order received.
+    pub records: Vec<HopRecord>,
+    /// The proof that the section of the recipient agreed on the credit, once known.
+    pub credit: Option<CreditAgreementProof>,
+}
+
+impl PaymentTrace {
+    /// The furthest hop the payment is proven to have reached.
+    pub fn last_hop(&self) -> Option<Hop> {
+        if self.credit.is_some() {
+            return Some(Hop::Credited);
+        }
+        self.records.iter().map(|record| record.hop).max()
+    }
+
+    /// The hop the payment is waiting for, i.e. where it is stuck if it does not
+    /// reach it. None once credited.
+    pub fn pending_hop(&self) -> Option<Hop> {
+        match self.last_hop() {
+            None => Some(Hop::Validated),
+            Some(hop) => hop.next(),
+        }
+    }
+
+    /// The records of the hop.
+    pub fn records_of(&self, hop: Hop) -> impl Iterator<Item = &HopRecord> {
+        self.records.iter().filter(move |record| record.hop == hop)
+    }
+}
+
+/// Accumulates the hop records of payments, verified against the keys of the
+/// sections signing them, for support to tell where a payment is stuck.
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct PaymentTracer {
+    traces: HashMap<TransferId, PaymentTrace>,
+}
+
+impl PaymentTracer {
+    /// A tracer without any records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The trace of the payment, if any of its hops is recorded.
+    pub fn trace(&self, transfer_id: &TransferId) -> Option<&PaymentTrace> {
+        self.traces.get(transfer_id)
+    }
+
+    /// Records the hop, once verified to be signed by a Replica of the section.
+    /// A record of a Replica already recorded for the hop is ignored.
+    pub fn record_hop(&mut self, record: HopRecord, section: &PublicKeySet) -> Result<()> {
+        record.verify(section)?;
+        let trace = self.trace_mut(record.transfer_id);
+        let known = trace.records.iter().any(|known| {
+            known.hop == record.hop
+                && known.section == record.section
+                && known.replica_signature.index == record.replica_signature.index
+        });
+        if !known {
+            trace.records.push(record);
+        }
+        Ok(())
+    }
+
+    /// Records the agreement of the section of the recipient on the credit,
+    /// once verified to be signed by the section with the key.
+    pub fn record_credit(
+        &mut self,
+        proof: CreditAgreementProof,
+        section: &PublicKey,
+    ) -> Result<()> {
+        proof.verify(section)?;
+        let transfer_id = proof.id();
+        self.trace_mut(transfer_id).credit = Some(proof);
+        Ok(())
+    }
+
+    fn trace_mut(&mut self, transfer_id: TransferId) -> &mut PaymentTrace {
+        self.traces
+            .entry(transfer_id)
+            .or_insert_with(|| PaymentTrace {
+                transfer_id,
+                records: vec![],
+                credit: None,
+            })
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[cfg(not(feature = "system-clock"))]
+use super::clock::{Clock, ManualTimeSource};
+use super::{
+    checkpoint::{CheckpointStates, SignedCheckpoint},
+    clock::Timestamp,
+    hashing::Digest,
+    replica::{ReplayError, Replica},
+    wire::{FieldReader, SizeLimits},
+    ReplicaEvent,
+};
+use safe_nd::{AccountId, DebitAgreementProof, Error, Result, TransferId, TransferValidated};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use threshold_crypto::SecretKeySet;
+
+/// The version of the format of the state that a running Replica hands over
+/// to a new version of the crate (see Replica::export_upgrade_state).
+pub const UPGRADE_STATE_VERSION: u16 = 3;
+
+/// The outcome of replaying a recorded event log with the code of
+/// the running version of the crate, and with that of a new version.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Debug)]
+pub struct UpgradeReport {
+    /// Number of events replayed.
+    pub event_count: u64,
+    /// The state committed to after replaying with the running version.
+    pub old_state: Digest,
+    /// The state committed to after replaying with the new version.
+    pub new_state: Digest,
+    /// The accounts whose states differ between the versions, or that one of them lacks.
+    pub diverging_accounts: BTreeSet<AccountId>,
+}
+
+impl UpgradeReport {
+    /// Whether both versions arrive at the same state.
+    pub fn is_safe(&self) -> bool {
+        self.old_state == self.new_state && self.diverging_accounts.is_empty()
+    }
+}
+
+/// Replays the serialised events of a recorded log through the decoding (and any
+/// migration) of the running version, and through that of a new version, comparing
+/// the state commitments that both arrive at. Nothing of the log is modified, so that
+/// changes to the event format or to how events are applied can be tried on a copy
+/// of a real log before rolling them out.
+/// An event that the running version can not read means that the log is not
+/// a valid one, while an event the new version can not read is a breaking change.
+pub fn dry_run_upgrade<O, N>(log: &[Vec<u8>], old: O, new: N) -> Result<UpgradeReport>
+where
+    O: Fn(&[u8]) -> Result<ReplicaEvent>,
+    N: Fn(&[u8]) -> Result<ReplicaEvent>,
+{
+    let old =
+        replay(log, old).map_err(|e| Error::FailedToParse(format!("Running version: {}", e)))?;
+    let new = replay(log, new).map_err(|e| Error::FailedToParse(format!("New version: {}", e)))?;
+    let old_commitment = old.state_commitment()?;
+    let new_commitment = new.state_commitment()?;
+    let accounts: BTreeSet<_> = old_commitment
+        .account_digests
+        .keys()
+        .chain(new_commitment.account_digests.keys())
+        .cloned()
+        .collect();
+    let diverging_accounts = accounts
+        .into_iter()
+        .filter(|id| {
+            old_commitment.account_digests.get(id) != new_commitment.account_digests.get(id)
+        })
+        .collect();
+    Ok(UpgradeReport {
+        event_count: log.len() as u64,
+        old_state: old_commitment.checkpoint.state_hash,
+        new_state: new_commitment.checkpoint.state_hash,
+        diverging_accounts,
+    })
+}
+
+/// Replays the log into a Replica of a throwaway group, as events are not verified when applied.
+/// Without the system clock, both versions replay with time standing still, which is alike.
+fn replay<D: Fn(&[u8]) -> Result<ReplicaEvent>>(
+    log: &[Vec<u8>],
+    decode: D,
+) -> std::result::Result<Replica, ReplayError> {
+    let keys = SecretKeySet::random(0, &mut rand::thread_rng());
+    Replica::from_history_iter(
+        keys.secret_key_share(0),
+        0,
+        keys.public_keys(),
+        log.iter().map(|bytes| decode(bytes)),
+        #[cfg(not(feature = "system-clock"))]
+        Clock::new(ManualTimeSource::default()),
+    )
+}
+
+/// The state of a live Replica, as handed over to a new version of the crate.
+/// Besides a snapshot, it holds what a snapshot leaves to be recorded again,
+/// so that the new version takes over without replaying the history:
+/// the last validations, returned again on retries, the held credits
+/// with when they were held, and the checkpoints with the states they commit to.
+/// Keys are not part of it, and are provided when importing.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub(crate) struct UpgradeState {
+    /// The version of the crate that exported the state.
+    pub(crate) crate_version: String,
+    /// A snapshot, in its own versioned format.
+    pub(crate) snapshot: Vec<u8>,
+    pub(crate) last_validated: Vec<TransferValidated>,
+    pub(crate) held_credits: Vec<DebitAgreementProof>,
+    pub(crate) pending_checkpoint: Option<CheckpointStates>,
+    pub(crate) last_checkpoint: Option<(SignedCheckpoint, CheckpointStates)>,
+    pub(crate) held_since: Vec<(TransferId, Timestamp)>,
+}
+
+impl UpgradeState {
+    /// The state, with the version of the format as header.
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
+        match bincode::serialize(self) {
+            Err(_) => Err(Error::NetworkOther(
+                "Could not serialise upgrade state".into(),
+            )),
+            Ok(body) => {
+                let mut bytes = UPGRADE_STATE_VERSION.to_le_bytes().to_vec();
+                bytes.extend(body);
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Reads a state exported by this or an earlier version of the format,
+    /// migrating it to the current one. A state of a newer version is rejected,
+    /// as a downgrade can not know what the newer version added to it.
+    pub(crate) fn from_bytes(bytes: &[u8], limits: &SizeLimits) -> Result<Self> {
+        if bytes.len() < 2 {
+            return Err(Error::FailedToParse("Upgrade state has no version".into()));
+        }
+        let (header, body) = bytes.split_at(2);
+        let version = u16::from_le_bytes([header[0], header[1]]);
+        // When the format changes, older versions are read into their own
+        // types here, and migrated to the current one.
+        match version {
+            UPGRADE_STATE_VERSION => limits.snapshot(body),
+            1 => {
+                if body.len() as u64 > limits.snapshot {
+                    return Err(Error::ExceededSize);
+                }
+                Self::read_v1(body)
+            }
+            2 => {
+                if body.len() as u64 > limits.snapshot {
+                    return Err(Error::ExceededSize);
+                }
+                Self::read_v2(body)
+            }
+            version if version > UPGRADE_STATE_VERSION => Err(Error::FailedToParse(format!(
+                "Upgrade state version {} is newer than {}",
+                version, UPGRADE_STATE_VERSION
+            ))),
+            _ => Err(Error::FailedToParse(format!(
+                "Unsupported upgrade state version {}",
+                version
+            ))),
+        }
+    }
+
+    /// Reads a state of version 1, whose checkpoints do not commit to the history roots
+    /// of the accounts. They are the last fields, and are dropped: the states
+    /// are captured again when the next checkpoint is signed.
+    fn read_v1(mut bytes: &[u8]) -> Result<Self> {
+        let mut reader = FieldReader::new(&mut bytes, 4);
+        Ok(Self {
+            crate_version: reader.required()?,
+            snapshot: reader.required()?,
+            last_validated: reader.required()?,
+            held_credits: reader.required()?,
+            pending_checkpoint: None,
+            last_checkpoint: None,
+            held_since: Default::default(),
+        })
+    }
+
+    /// Reads a state of version 2, which does not record when the credits were held.
+    /// It is the last field, and is left empty: the credits are held since they are imported.
+    fn read_v2(mut bytes: &[u8]) -> Result<Self> {
+        let mut reader = FieldReader::new(&mut bytes, 6);
+        Ok(Self {
+            crate_version: reader.required()?,
+            snapshot: reader.required()?,
+            last_validated: reader.required()?,
+            held_credits: reader.required()?,
+            pending_checkpoint: reader.required()?,
+            last_checkpoint: reader.required()?,
+            held_since: reader.field()?,
+        })
+    }
+}
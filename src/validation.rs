@@ -0,0 +1,67 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{clock::Timestamp, error::RejectionReason};
+use safe_nd::{Money, Transfer};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+/// What a Replica knows of a debit when consulting its validation policies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidationContext {
+    /// The balance of the sender, before the debit.
+    pub balance: Money,
+    /// The current section height.
+    pub height: u64,
+    /// The current time.
+    pub now: Timestamp,
+}
+
+/// Rules of upper layers that debits must satisfy, such as minimum amounts,
+/// rate limits, or allow and deny lists. Consulted by the Replica after its
+/// own checks have passed, in the order the policies were added to it.
+pub trait ValidationPolicy: Send + Sync {
+    /// Checks the debit, rejecting it with the reason if it breaks the rules.
+    fn check(
+        &self,
+        transfer: &Transfer,
+        context: &ValidationContext,
+    ) -> Result<(), RejectionReason>;
+}
+
+/// The validation policies of a Replica, consulted in order.
+/// Without any, all debits passing the checks of the Replica are validated.
+/// Clones share the same policies.
+#[derive(Clone, Default)]
+pub struct ValidationPipeline(Vec<Arc<dyn ValidationPolicy>>);
+
+impl ValidationPipeline {
+    /// Adds the policy, consulted after those added before it.
+    pub(crate) fn push<P: ValidationPolicy + 'static>(&mut self, policy: P) {
+        self.0.push(Arc::new(policy));
+    }
+
+    /// Checks the debit against all policies, stopping at the first rejection.
+    pub(crate) fn check(
+        &self,
+        transfer: &Transfer,
+        context: &ValidationContext,
+    ) -> Result<(), RejectionReason> {
+        self.0
+            .iter()
+            .try_for_each(|policy| policy.check(transfer, context))
+    }
+}
+
+impl Debug for ValidationPipeline {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "ValidationPipeline({} policies)", self.0.len())
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::codec::{verified_in, Codec, Payload};
+use safe_nd::{
+    DebitAgreementProof, Error, PublicKey, Result, SignedTransfer, TransferId, TransferValidated,
+};
+use serde::{Deserialize, Serialize};
+use threshold_crypto::PublicKeySet;
+
+/// Verifies that the transfer was signed by its sender, over any of the codecs,
+/// such as those accepted at the height (see accepted_codecs).
+pub fn verify_signed_transfer(signed_transfer: &SignedTransfer, codecs: &[Codec]) -> Result<()> {
+    let from = signed_transfer.from();
+    let signature = &signed_transfer.actor_signature;
+    let payload = Payload::Transfer(&signed_transfer.transfer);
+    if verified_in(codecs, payload, |data| from.verify(signature, data).is_ok()) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
+/// Verifies that the validated transfer was signed by its sender,
+/// and validated by a member of the Replicas it names, over any of the codecs.
+/// Whether those Replicas are the ones of the sender is for the caller to check.
+pub fn verify_transfer_validated(validated: &TransferValidated, codecs: &[Codec]) -> Result<()> {
+    verify_signed_transfer(&validated.signed_transfer, codecs)?;
+    let share = &validated.replica_signature;
+    let key = validated.replicas.public_key_share(share.index);
+    let payload = Payload::Proof(&validated.signed_transfer);
+    if verified_in(codecs, payload, |data| key.verify(&share.share, data)) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
+/// Verifies that the transfer of the proof was signed by its sender,
+/// and agreed by the Replicas with the key set, over any of the codecs.
+pub fn verify_debit_agreement_proof(
+    proof: &DebitAgreementProof,
+    replicas: &PublicKeySet,
+    codecs: &[Codec],
+) -> Result<()> {
+    verify_signed_transfer(&proof.signed_transfer, codecs)?;
+    let key = PublicKey::Bls(replicas.public_key());
+    let signature = &proof.debiting_replicas_sig;
+    let payload = Payload::Proof(&proof.signed_transfer);
+    if verified_in(codecs, payload, |data| key.verify(signature, data).is_ok()) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
+/// A check made when verifying a transfer.
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum VerificationCheck {
+    /// The transfer is signed by the sender.
+    ActorSignature,
+    /// The agreement proof is signed by a group of Replicas we know of.
+    GroupSignature,
+    /// The transfer is the next debit of the sender.
+    Sequencing,
+    /// The balance of the sender covers the amount.
+    Balance,
+}
+
+/// The outcome of a check.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub enum CheckOutcome {
+    /// The check passed.
+    Passed,
+    /// The check failed, for the reason.
+    Failed(String),
+    /// The check could not be made, for the reason, such as an unknown sender.
+    Skipped(String),
+}
+
+/// Which checks a transfer passed, for diagnostics and for evidence in disputes.
+/// Every check is made, even after one has failed, unlike when validating,
+/// which stops at the first failure and is the path to use in hot loops.
+#[derive(Clone, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct VerificationReport {
+    /// The transfer verified.
+    pub transfer_id: TransferId,
+    /// The checks made, in order, with their outcomes.
+    pub checks: Vec<(VerificationCheck, CheckOutcome)>,
+}
+
+impl VerificationReport {
+    /// An empty report of the transfer.
+    pub(crate) fn new(transfer_id: TransferId) -> Self {
+        Self {
+            transfer_id,
+            checks: vec![],
+        }
+    }
+
+    /// Records the outcome of the check.
+    pub(crate) fn record(&mut self, check: VerificationCheck, outcome: CheckOutcome) {
+        self.checks.push((check, outcome));
+    }
+
+    /// Whether all checks passed.
+    pub fn is_valid(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|(_, outcome)| *outcome == CheckOutcome::Passed)
+    }
+
+    /// The outcome of the check, if it was made.
+    pub fn outcome(&self, check: VerificationCheck) -> Option<&CheckOutcome> {
+        self.checks
+            .iter()
+            .find(|(made, _)| *made == check)
+            .map(|(_, outcome)| outcome)
+    }
+
+    /// The checks that did not pass.
+    pub fn failed(&self) -> Vec<VerificationCheck> {
+        self.checks
+            .iter()
+            .filter(|(_, outcome)| *outcome != CheckOutcome::Passed)
+            .map(|(check, _)| *check)
+            .collect()
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    actor::Actor, error::TransferResult, money::checked_sum, ActorEvent, ReplicaEvent,
+    ReplicaValidator, TransferInitiated, TransferRegistrationSent, TransferValidationReceived,
+    TransfersSynched,
+};
+use safe_nd::{AccountId, DebitAgreementProof, Error, Money, Result, TransferValidated};
+use std::collections::BTreeMap;
+
+/// Several wallets of one application, each driven by its own Actor, with the
+/// Replicas of its own group, its own validator of remote groups, and its own
+/// counter and validations. Validations and proofs are routed to the Actor of
+/// the wallet they are of, so that the wallets do not need to be coordinated.
+/// Transfers can be made between the wallets, or from whichever of them can pay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Wallets<V: ReplicaValidator> {
+    actors: BTreeMap<AccountId, Actor<V>>,
+}
+
+impl<V: ReplicaValidator> Default for Wallets<V> {
+    fn default() -> Self {
+        Self {
+            actors: Default::default(),
+        }
+    }
+}
+
+impl<V: ReplicaValidator> Wallets<V> {
+    /// No wallets.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds the wallet of the Actor.
+    pub fn add(&mut self, actor: Actor<V>) -> Result<()> {
+        if self.actors.contains_key(&actor.id()) {
+            return Err(Error::DataExists);
+        }
+        let _ = self.actors.insert(actor.id(), actor);
+        Ok(())
+    }
+
+    /// Removes the wallet, returning its Actor.
+    pub fn remove(&mut self, wallet: &AccountId) -> Option<Actor<V>> {
+        self.actors.remove(wallet)
+    }
+
+    /// -----------------------------------------------------------------
+    /// ---------------------- Queries ----------------------------------
+    /// -----------------------------------------------------------------
+
+    /// Query for the ids of the wallets.
+    pub fn ids(&self) -> Vec<AccountId> {
+        self.actors.keys().cloned().collect()
+    }
+
+    /// Query for the Actor of the wallet.
+    pub fn get(&self, wallet: &AccountId) -> Option<&Actor<V>> {
+        self.actors.get(wallet)
+    }
+
+    /// Query for the balance of the wallet.
+    pub fn balance(&self, wallet: &AccountId) -> Option<Money> {
+        self.actors.get(wallet).map(Actor::balance)
+    }
+
+    /// Query for the sum of the balances of all wallets.
+    pub fn total_balance(&self) -> Option<Money> {
+        checked_sum(self.actors.values().map(Actor::balance))
+    }
+
+    /// Query for the balance of each wallet, by their ids.
+    pub fn balances(&self) -> BTreeMap<AccountId, Money> {
+        self.actors
+            .iter()
+            .map(|(wallet, actor)| (*wallet, actor.balance()))
+            .collect()
+    }
+
+    /// Query for the wallet to pay the amount to the recipient from: of those that can
+    /// (see Actor::preflight), the one with the least balance, keeping larger balances
+    /// for larger payments. None if no wallet can pay it on its own.
+    pub fn source_for(&self, amount: Money, to: &AccountId) -> Option<AccountId> {
+        self.actors
+            .values()
+            .filter(|actor| actor.preflight(amount, *to).is_ok())
+            .min_by_key(|actor| actor.balance())
+            .map(Actor::id)
+    }
+
+    /// -----------------------------------------------------------------
+    /// ---------------------- Cmds -------------------------------------
+    /// -----------------------------------------------------------------
+
+    /// Step 1. Initiates a transfer from the wallet (see Actor::transfer).
+    pub fn transfer(
+        &self,
+        from: &AccountId,
+        amount: Money,
+        to: AccountId,
+    ) -> TransferResult<TransferInitiated> {
+        self.actor(from)?.transfer(amount, to)
+    }
+
+    /// Step 1. Initiates a transfer from the wallet to another of the wallets.
+    pub fn transfer_between(
+        &self,
+        from: &AccountId,
+        to: &AccountId,
+        amount: Money,
+    ) -> TransferResult<TransferInitiated> {
+        let _ = self.actor(to)?;
+        self.transfer(from, amount, *to)
+    }
+
+    /// Step 1. Initiates a transfer from the wallet chosen by [source_for](Wallets::source_for),
+    /// failing with insufficient balance if no wallet can pay it on its own.
+    pub fn transfer_from_any(
+        &self,
+        amount: Money,
+        to: AccountId,
+    ) -> TransferResult<TransferInitiated> {
+        match self.source_for(amount, &to) {
+            Some(from) => self.transfer(&from, amount, to),
+            None => Err(Error::InsufficientBalance.into()),
+        }
+    }
+
+    /// Step 2. Receives a validation, for the wallet of its transfer (see Actor::receive).
+    pub fn receive(
+        &self,
+        validation: TransferValidated,
+    ) -> TransferResult<TransferValidationReceived> {
+        self.actor(&validation.from())?.receive(validation)
+    }
+
+    /// Step 3. Registers a debit proof, for the wallet it debits (see Actor::register).
+    pub fn register(
+        &self,
+        debit_proof: DebitAgreementProof,
+    ) -> TransferResult<TransferRegistrationSent> {
+        self.actor(&debit_proof.from())?.register(debit_proof)
+    }
+
+    /// Synchs the wallet with the events of its Replicas (see Actor::synch).
+    pub fn synch(&self, wallet: &AccountId, events: Vec<ReplicaEvent>) -> Result<TransfersSynched> {
+        self.actor(wallet)?.synch(events)
+    }
+
+    /// -----------------------------------------------------------------
+    /// ---------------------- Mutation ---------------------------------
+    /// -----------------------------------------------------------------
+
+    /// Applies the event to the Actor of the wallet it was raised for.
+    /// Fails if the wallet is not held, such as when it was removed
+    /// after the event was raised, leaving the other wallets as they were.
+    pub fn apply(&mut self, wallet: &AccountId, event: ActorEvent) -> Result<()> {
+        self.actors
+            .get_mut(wallet)
+            .ok_or_else(|| Error::from("No such wallet"))?
+            .apply(event);
+        Ok(())
+    }
+
+    fn actor(&self, wallet: &AccountId) -> Result<&Actor<V>> {
+        self.actors
+            .get(wallet)
+            .ok_or_else(|| Error::from("No such wallet"))
+    }
+}
+
+// the tests build instances with the default clock, that of the system
+#[cfg(feature = "system-clock")]
+mod test {
+    use super::*;
+    use crate::account::Account;
+    use crdts::Dot;
+    use safe_nd::{ClientFullId, PublicKey, SafeKey, Transfer};
+    use threshold_crypto::{SecretKey, SecretKeySet};
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Validator;
+
+    impl ReplicaValidator for Validator {
+        fn is_valid(&self, _: PublicKey) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn drives_wallets_of_different_groups() -> Result<()> {
+        // Arrange
+        let mut wallets = Wallets::new();
+        let first = get_actor(10);
+        let second = get_actor(20);
+        let (first_id, second_id) = (first.id(), second.id());
+        wallets.add(first)?;
+        wallets.add(second)?;
+
+        // Act
+        let initiated = wallets.transfer(&first_id, Money::from_nano(4), get_random_pk())?;
+        wallets.apply(&first_id, ActorEvent::TransferInitiated(initiated.clone()))?;
+        let other = wallets.transfer(&second_id, Money::from_nano(5), get_random_pk())?;
+        let unknown = wallets.apply(
+            &get_random_pk(),
+            ActorEvent::TransferInitiated(other.clone()),
+        );
+
+        // Assert
+        assert!(wallets.total_balance() == Some(Money::from_nano(30)));
+        assert!(initiated.id().counter == 0);
+        assert!(other.id().counter == 0);
+        assert!(unknown.is_err());
+        assert!(wallets
+            .transfer(&get_random_pk(), Money::from_nano(1), first_id)
+            .is_err());
+        assert!(wallets
+            .add(wallets.get(&first_id).unwrap().clone())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pays_from_the_smallest_wallet_that_can() -> Result<()> {
+        // Arrange
+        let mut wallets = Wallets::new();
+        let (small, large) = (get_actor(10), get_actor(50));
+        let (small_id, large_id) = (small.id(), large.id());
+        wallets.add(small)?;
+        wallets.add(large)?;
+
+        // Act
+        let from_small = wallets.transfer_from_any(Money::from_nano(8), get_random_pk())?;
+        let from_large = wallets.transfer_from_any(Money::from_nano(20), get_random_pk())?;
+        let too_much = wallets.transfer_from_any(Money::from_nano(60), get_random_pk());
+        let between = wallets.transfer_between(&large_id, &small_id, Money::from_nano(5))?;
+
+        // Assert
+        assert!(from_small.id().actor == small_id);
+        assert!(from_large.id().actor == large_id);
+        assert!(too_much.is_err());
+        assert!(between.signed_transfer.transfer.to == small_id);
+        assert!(wallets.balances().get(&large_id) == Some(&Money::from_nano(50)));
+        assert!(wallets
+            .transfer_between(&large_id, &get_random_pk(), Money::from_nano(5))
+            .is_err());
+        Ok(())
+    }
+
+    fn get_actor(balance: u64) -> Actor<Validator> {
+        let replicas = SecretKeySet::random(1, &mut rand::thread_rng()).public_keys();
+        let key = SafeKey::client(ClientFullId::new_ed25519(&mut rand::thread_rng()));
+        let mut account = Account::new(key.public_key());
+        account.append(Transfer {
+            id: Dot::new(get_random_pk(), 0),
+            to: key.public_key(),
+            amount: Money::from_nano(balance),
+        });
+        Actor::from_snapshot(account, key, replicas, Validator)
+    }
+
+    fn get_random_pk() -> PublicKey {
+        PublicKey::from(SecretKey::random().public_key())
+    }
+}
@@ -0,0 +1,330 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    policy::{take_nested_too_deep, MAX_CONDITION_DEPTH},
+    ReplicaEvent, TransferCmd,
+};
+use bincode::Options;
+use safe_nd::{DebitAgreementProof, Error, Result, SignedTransfer, TransferValidated};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// Maximum sizes, in bytes, of payloads received over the wire.
+/// They are enforced before deserialization, and during it, so that
+/// a crafted payload can not make us allocate more than the limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeLimits {
+    /// Limit for a transfer signed by an Actor.
+    pub signed_transfer: u64,
+    /// Limit for a validation by a Replica, which carries the key set of its group.
+    pub validation: u64,
+    /// Limit for an agreement proof.
+    pub debit_proof: u64,
+    /// Limit for a Replica event.
+    pub event: u64,
+    /// Limit for a cmd to a Replica, which may carry a disbursement
+    /// of up to MAX_DISBURSEMENT_PAYOUTS payouts.
+    pub cmd: u64,
+    /// Limit for a snapshot of a Replica, which holds all of its accounts.
+    pub snapshot: u64,
+    /// Limit for the levels of values nested in one another, such as of
+    /// conditions within conditions. Conditions are refused while decoded
+    /// once nested deeper than MAX_CONDITION_DEPTH, whatever this limit,
+    /// and a lower limit is checked once they are decoded.
+    pub depth: usize,
+}
+
+/// Why a payload received over the wire could not be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The payload, or a length within it, exceeds the limit in bytes.
+    TooLarge {
+        /// The limit.
+        limit: u64,
+    },
+    /// Values of the payload are nested deeper than the limit.
+    TooDeep {
+        /// The limit.
+        limit: usize,
+    },
+    /// The payload is not an encoding of the expected type.
+    Malformed(String),
+    /// The payload decodes, but is not the exact encoding of what it decodes to.
+    NonCanonical,
+}
+
+impl From<DecodeError> for Error {
+    fn from(error: DecodeError) -> Self {
+        match error {
+            DecodeError::TooLarge { .. } | DecodeError::TooDeep { .. } => Error::ExceededSize,
+            DecodeError::Malformed(reason) => Error::FailedToParse(reason),
+            DecodeError::NonCanonical => Error::FailedToParse("Non-canonical encoding".into()),
+        }
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            DecodeError::TooLarge { limit } => write!(f, "Payload exceeds {} bytes", limit),
+            DecodeError::TooDeep { limit } => {
+                write!(f, "Payload nests values deeper than {} levels", limit)
+            }
+            DecodeError::Malformed(reason) => write!(f, "Malformed payload: {}", reason),
+            DecodeError::NonCanonical => write!(f, "Non-canonical encoding"),
+        }
+    }
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        Self {
+            signed_transfer: 1024,
+            validation: 16 * 1024,
+            debit_proof: 2 * 1024,
+            event: 16 * 1024,
+            cmd: 1024 * 1024,
+            snapshot: 1024 * 1024 * 1024,
+            depth: MAX_CONDITION_DEPTH,
+        }
+    }
+}
+
+impl SizeLimits {
+    /// Deserializes a signed transfer, such as sent by an Actor for validation.
+    pub fn signed_transfer(&self, bytes: &[u8]) -> Result<SignedTransfer> {
+        deserialize_strict(bytes, self.signed_transfer)
+    }
+
+    /// Deserializes a validation, such as sent by a Replica to an Actor.
+    pub fn validation(&self, bytes: &[u8]) -> Result<TransferValidated> {
+        deserialize_strict(bytes, self.validation)
+    }
+
+    /// Deserializes an agreement proof, such as sent for registration or propagation.
+    pub fn debit_proof(&self, bytes: &[u8]) -> Result<DebitAgreementProof> {
+        deserialize_strict(bytes, self.debit_proof)
+    }
+
+    /// Deserializes a Replica event, such as sent when synching.
+    pub fn event(&self, bytes: &[u8]) -> Result<ReplicaEvent> {
+        let event = deserialize_strict(bytes, self.event)?;
+        if let ReplicaEvent::OwnerConditionAttached(attached) = &event {
+            self.check_depth(attached.condition.depth())?;
+        }
+        Ok(event)
+    }
+
+    /// Decodes a cmd to a Replica, such as received from a client or
+    /// another Replica (see Replica::handle_cmd_bytes).
+    pub fn cmd(&self, bytes: &[u8]) -> std::result::Result<TransferCmd, DecodeError> {
+        decode(bytes, self.cmd)
+    }
+
+    /// Decodes (part of) a snapshot of a Replica, or of its state for an upgrade.
+    pub(crate) fn snapshot<T: DeserializeOwned + Serialize>(&self, bytes: &[u8]) -> Result<T> {
+        deserialize_strict(bytes, self.snapshot)
+    }
+
+    /// Fails if values nested `depth` levels exceed the limit.
+    pub(crate) fn check_depth(&self, depth: usize) -> std::result::Result<(), DecodeError> {
+        if depth > self.depth {
+            return Err(DecodeError::TooDeep { limit: self.depth });
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes signed content, or content carrying signatures, of at most `limit` bytes,
+/// as [decode](decode) does, with its errors as those of the network.
+pub fn deserialize_strict<T: DeserializeOwned + Serialize>(bytes: &[u8], limit: u64) -> Result<T> {
+    decode(bytes, limit).map_err(Error::from)
+}
+
+/// Decodes a payload of at most `limit` bytes. No more than the limit is allocated
+/// while decoding, whatever the lengths the payload claims. Only the exact encoding
+/// of `bincode::serialize` is accepted: trailing bytes, unknown enum variants, and any
+/// other encoding that would not serialize back to the same bytes are rejected.
+/// What is verified is then what was received.
+pub fn decode<T: DeserializeOwned + Serialize>(
+    bytes: &[u8],
+    limit: u64,
+) -> std::result::Result<T, DecodeError> {
+    if bytes.len() as u64 > limit {
+        return Err(DecodeError::TooLarge { limit });
+    }
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .with_limit(limit);
+    let _ = take_nested_too_deep();
+    let value: T = match options.deserialize(bytes) {
+        Ok(value) => value,
+        Err(_) if take_nested_too_deep() => {
+            return Err(DecodeError::TooDeep {
+                limit: MAX_CONDITION_DEPTH,
+            })
+        }
+        Err(error) => {
+            return match *error {
+                bincode::ErrorKind::SizeLimit => Err(DecodeError::TooLarge { limit }),
+                _ => Err(DecodeError::Malformed(error.to_string())),
+            }
+        }
+    };
+    match bincode::serialize(&value) {
+        Ok(ref canonical) if canonical.as_slice() == bytes => Ok(value),
+        _ => Err(DecodeError::NonCanonical),
+    }
+}
+
+/// Reads the fields of a value one after the other, as bincode lays them out, for values
+/// written in the layout of an older version. Fields have only ever been appended to such
+/// values, so that those past the fields of the layout are left empty.
+pub(crate) struct FieldReader<'r, 'a> {
+    bytes: &'r mut &'a [u8],
+    fields: usize,
+    read: usize,
+}
+
+impl<'r, 'a> FieldReader<'r, 'a> {
+    /// Reads a value of `fields` fields from the bytes, advancing them past the value.
+    pub(crate) fn new(bytes: &'r mut &'a [u8], fields: usize) -> Self {
+        Self {
+            bytes,
+            fields,
+            read: 0,
+        }
+    }
+
+    /// The next field, or an empty one if the layout ends before it.
+    pub(crate) fn field<T: DeserializeOwned + Default>(&mut self) -> Result<T> {
+        if self.read >= self.fields {
+            return Ok(T::default());
+        }
+        self.required()
+    }
+
+    /// The next field, which every layout has.
+    pub(crate) fn required<T: DeserializeOwned>(&mut self) -> Result<T> {
+        self.read += 1;
+        // no more than the bytes left can be read, whatever the lengths they claim
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .with_limit(self.bytes.len() as u64)
+            .deserialize_from(&mut *self.bytes)
+            .map_err(|error| Error::FailedToParse(error.to_string()))
+    }
+
+    /// The next field, a sequence of values each read with `read`, or an empty
+    /// one if the layout ends before it.
+    pub(crate) fn seq<T>(
+        &mut self,
+        mut read: impl FnMut(&mut &'a [u8]) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        if self.read >= self.fields {
+            return Ok(vec![]);
+        }
+        let len: u64 = self.required()?;
+        // every value takes at least a byte
+        if len > self.bytes.len() as u64 {
+            return Err(Error::ExceededSize);
+        }
+        (0..len).map(|_| read(&mut *self.bytes)).collect()
+    }
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn rejects_oversized_payloads() {
+        // Arrange
+        let limits = SizeLimits {
+            signed_transfer: 8,
+            ..Default::default()
+        };
+        let payload = vec![0u8; 9];
+
+        // Act
+        let result = limits.signed_transfer(&payload);
+
+        // Assert
+        assert!(result == Err(Error::ExceededSize));
+    }
+
+    #[test]
+    fn rejects_oversized_lengths_within_payload() {
+        // a string claiming u64::MAX bytes, in a small payload
+        let payload = u64::max_value().to_le_bytes();
+        let result: Result<String> = deserialize_strict(&payload, 64);
+        assert!(result == Err(Error::ExceededSize));
+    }
+
+    #[test]
+    fn rejects_trailing_bytes_and_unknown_variants() {
+        let mut payload = bincode::serialize(&Some(7u8)).unwrap();
+        assert!(deserialize_strict::<Option<u8>>(&payload, 64) == Ok(Some(7)));
+
+        payload.push(0);
+        assert!(deserialize_strict::<Option<u8>>(&payload, 64).is_err());
+
+        // there is no variant 2 of an Option
+        let unknown_variant = vec![2u8, 7];
+        assert!(deserialize_strict::<Option<u8>>(&unknown_variant, 64).is_err());
+    }
+
+    #[test]
+    fn tells_why_payloads_are_not_decoded() {
+        let payload = bincode::serialize(&Some(7u8)).unwrap();
+        let mut trailing = payload.clone();
+        trailing.push(0);
+        let too_large = decode::<Option<u8>>(&u64::max_value().to_le_bytes(), 64);
+        let malformed = decode::<Option<u8>>(&trailing, 64);
+        assert!(too_large == Err(DecodeError::TooLarge { limit: 64 }));
+        assert!(matches!(malformed, Err(DecodeError::Malformed(_))));
+        assert!(decode::<Option<u8>>(&payload, 1) == Err(DecodeError::TooLarge { limit: 1 }));
+    }
+
+    #[test]
+    fn rejects_events_nesting_conditions_too_deep() {
+        // Arrange
+        let limits = SizeLimits::default();
+        let owner = threshold_crypto::SecretKey::random().public_key();
+        let mut condition = crate::Condition::HeightBelow(1);
+        for _ in 1..limits.depth {
+            condition = crate::Condition::Any(vec![condition]);
+        }
+        let event = |condition: &crate::Condition| {
+            let attached = crate::OwnerConditionAttached {
+                account_id: owner.into(),
+                condition: condition.clone(),
+            };
+            bincode::serialize(&ReplicaEvent::OwnerConditionAttached(attached)).unwrap()
+        };
+        let deepest = event(&condition);
+        let too_deep = event(&crate::Condition::Any(vec![condition]));
+
+        // Act, Assert
+        assert!(limits.event(&deepest).is_ok());
+        assert!(limits.event(&too_deep) == Err(Error::ExceededSize));
+        assert!(
+            decode::<ReplicaEvent>(&too_deep, limits.event)
+                == Err(DecodeError::TooDeep {
+                    limit: MAX_CONDITION_DEPTH
+                })
+        );
+        assert!(
+            limits.check_depth(limits.depth + 1)
+                == Err(DecodeError::TooDeep {
+                    limit: limits.depth
+                })
+        );
+    }
+}